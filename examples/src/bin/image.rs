@@ -60,10 +60,10 @@ fn main() {
         dimensions = caps.current_extent.unwrap_or([1024, 768]);
         let usage = caps.supported_usage_flags;
         let alpha = caps.supported_composite_alpha.iter().next().unwrap();
-        let format = caps.supported_formats[0].0;
+        let (format, color_space) = caps.supported_formats[0];
 
         vulkano::swapchain::Swapchain::new(device.clone(), surface.clone(), caps.min_image_count,
-                                           format, dimensions, 1,
+                                           format, color_space, dimensions, 1,
                                            usage, &queue, vulkano::swapchain::SurfaceTransform::Identity,
                                            alpha,
                                            vulkano::swapchain::PresentMode::Fifo, true, None).expect("failed to create swapchain")
@@ -202,6 +202,7 @@ fn main() {
                           depth_range: 0.0 .. 1.0,
                       }]),
                       scissors: None,
+                      ..vulkano::command_buffer::DynamicState::none()
                   },
                   vertex_buffer.clone(),
                   set.clone(), ()).unwrap()
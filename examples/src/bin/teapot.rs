@@ -62,10 +62,10 @@ fn main() {
         dimensions = caps.current_extent.unwrap_or([1024, 768]);
 
         let usage = caps.supported_usage_flags;
-        let format = caps.supported_formats[0].0;
+        let (format, color_space) = caps.supported_formats[0];
         let alpha = caps.supported_composite_alpha.iter().next().unwrap();
 
-        vulkano::swapchain::Swapchain::new(device.clone(), surface.clone(), caps.min_image_count, format, dimensions, 1,
+        vulkano::swapchain::Swapchain::new(device.clone(), surface.clone(), caps.min_image_count, format, color_space, dimensions, 1,
                                            usage, &queue, vulkano::swapchain::SurfaceTransform::Identity,
                                            alpha,
                                            vulkano::swapchain::PresentMode::Fifo, true, None).expect("failed to create swapchain")
@@ -135,11 +135,11 @@ fn main() {
 
     let mut recreate_swapchain = false;
 
-    let mut previous_frame = Box::new(vulkano::sync::now(device.clone())) as Box<GpuFuture>;
+    let mut frames = vulkano::sync::FramesInFlight::new(device.clone(), 2);
     let rotation_start = std::time::Instant::now();
 
     loop {
-        previous_frame.cleanup_finished();
+        frames.begin_frame(None).unwrap();
 
         if recreate_swapchain {
 
@@ -196,6 +196,7 @@ fn main() {
             .add_buffer(uniform_buffer_subbuffer).unwrap()
             .build().unwrap()
         );
+        frames.keep_alive(set.clone());
 
         let (image_num, acquire_future) = match vulkano::swapchain::acquire_next_image(swapchain.clone(),
                                                                                        None) {
@@ -224,28 +225,24 @@ fn main() {
                           depth_range: 0.0 .. 1.0,
                       }]),
                       scissors: None,
+                      ..vulkano::command_buffer::DynamicState::none()
                 },
                 (vertex_buffer.clone(), normals_buffer.clone()), 
                 index_buffer.clone(), set.clone(), ()).unwrap()
             .end_render_pass().unwrap()
             .build().unwrap();
         
-        let future = previous_frame.join(acquire_future)
+        let future = frames.previous_frame_end().join(acquire_future)
             .then_execute(queue.clone(), command_buffer).unwrap()
-            .then_swapchain_present(queue.clone(), swapchain.clone(), image_num)
-            .then_signal_fence_and_flush();
+            .then_swapchain_present(queue.clone(), swapchain.clone(), image_num);
 
-        match future {
-            Ok(future) => {
-                previous_frame = Box::new(future) as Box<_>;
-            }
+        match frames.end_frame(future) {
+            Ok(()) => (),
             Err(vulkano::sync::FlushError::OutOfDate) => {
                 recreate_swapchain = true;
-                previous_frame = Box::new(vulkano::sync::now(device.clone())) as Box<_>;
             }
             Err(e) => {
                 println!("{:?}", e);
-                previous_frame = Box::new(vulkano::sync::now(device.clone())) as Box<_>;
             }
         }
 
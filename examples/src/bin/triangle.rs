@@ -53,7 +53,7 @@ use vulkano::swapchain::SurfaceTransform;
 use vulkano::swapchain::Swapchain;
 use vulkano::swapchain::AcquireError;
 use vulkano::swapchain::SwapchainCreationError;
-use vulkano::sync::now;
+use vulkano::sync::FramesInFlight;
 use vulkano::sync::GpuFuture;
 
 use std::sync::Arc;
@@ -178,12 +178,12 @@ fn main() {
         // you can choose whether the window will be opaque or transparent.
         let alpha = caps.supported_composite_alpha.iter().next().unwrap();
 
-        // Choosing the internal format that the images will have.
-        let format = caps.supported_formats[0].0;
+        // Choosing the internal format and the color space that the images will have.
+        let (format, color_space) = caps.supported_formats[0];
 
         // Please take a look at the docs for the meaning of the parameters we didn't mention.
         Swapchain::new(device.clone(), surface.clone(), caps.min_image_count, format,
-                       dimensions, 1, caps.supported_usage_flags, &queue,
+                       color_space, dimensions, 1, caps.supported_usage_flags, &queue,
                        SurfaceTransform::Identity, alpha, PresentMode::Fifo, true,
                        None).expect("failed to create swapchain")
     };
@@ -323,15 +323,16 @@ void main() {
     // they are in use by the GPU.
     //
     // Destroying the `GpuFuture` blocks until the GPU is finished executing it. In order to avoid
-    // that, we store the submission of the previous frame here.
-    let mut previous_frame_end = Box::new(now(device.clone())) as Box<GpuFuture>;
+    // that, we store the submissions of the frames that are still being processed by the GPU in a
+    // `FramesInFlight`. Here we allow the GPU to process up to two frames simultaneously.
+    let mut frames = FramesInFlight::new(device.clone(), 2);
 
     loop {
-        // It is important to call this function from time to time, otherwise resources will keep
-        // accumulating and you will eventually reach an out of memory error.
-        // Calling this function polls various fences in order to determine what the GPU has
-        // already processed, and frees the resources that are no longer needed.
-        previous_frame_end.cleanup_finished();
+        // If two frames are already being processed, this function blocks until the oldest one
+        // is finished. It also frees the resources that are no longer needed by the GPU, which is
+        // important as otherwise they would keep accumulating and you would eventually reach an
+        // out of memory error.
+        frames.begin_frame(None).unwrap();
 
         // If the swapchain needs to be recreated, recreate it
         if recreate_swapchain {
@@ -421,6 +422,7 @@ void main() {
                           depth_range: 0.0 .. 1.0,
                       }]),
                       scissors: None,
+                      ..DynamicState::none()
                   },
                   vertex_buffer.clone(), (), ())
             .unwrap()
@@ -434,7 +436,7 @@ void main() {
             // Finish building the command buffer by calling `build`.
             .build().unwrap();
 
-        let future = previous_frame_end.join(acquire_future)
+        let future = frames.previous_frame_end().join(acquire_future)
             .then_execute(queue.clone(), command_buffer).unwrap()
 
             // The color output is now expected to contain our triangle. But in order to show it on
@@ -443,20 +445,17 @@ void main() {
             // This function does not actually present the image immediately. Instead it submits a
             // present command at the end of the queue. This means that it will only be presented once
             // the GPU has finished executing the command buffer that draws the triangle.
-            .then_swapchain_present(queue.clone(), swapchain.clone(), image_num)
-            .then_signal_fence_and_flush();
+            .then_swapchain_present(queue.clone(), swapchain.clone(), image_num);
 
-        match future {
-            Ok(future) => {
-                previous_frame_end = Box::new(future) as Box<_>;
-            }
+        // Submits the frame and stores it in `frames`. If the submission fails, the next frame
+        // simply starts from scratch.
+        match frames.end_frame(future) {
+            Ok(()) => (),
             Err(vulkano::sync::FlushError::OutOfDate) => {
                 recreate_swapchain = true;
-                previous_frame_end = Box::new(vulkano::sync::now(device.clone())) as Box<_>;
             }
             Err(e) => {
                 println!("{:?}", e);
-                previous_frame_end = Box::new(vulkano::sync::now(device.clone())) as Box<_>;
             }
         }
 
@@ -51,6 +51,8 @@ pub struct UnsafeBuffer {
     device: Arc<Device>,
     size: usize,
     usage: vk::BufferUsageFlags,
+    // True if the buffer was created with `Sharing::Exclusive`.
+    exclusive_sharing: bool,
 }
 
 impl UnsafeBuffer {
@@ -95,6 +97,11 @@ impl UnsafeBuffer {
             return Err(BufferCreationError::SparseResidencyAliasedFeatureNotEnabled);
         }
 
+        let exclusive_sharing = match sharing {
+            Sharing::Exclusive => true,
+            Sharing::Concurrent(_) => false,
+        };
+
         let buffer = {
             let (sh_mode, sh_indices) = match sharing {
                 Sharing::Exclusive => (vk::SHARING_MODE_EXCLUSIVE, SmallVec::<[u32; 8]>::new()),
@@ -197,6 +204,7 @@ impl UnsafeBuffer {
             device: device.clone(),
             size: size as usize,
             usage: usage_bits,
+            exclusive_sharing: exclusive_sharing,
         };
 
         Ok((obj, mem_reqs))
@@ -243,6 +251,13 @@ impl UnsafeBuffer {
         self.size
     }
 
+    /// Returns true if the buffer was created with `Sharing::Exclusive`, in which case it must be
+    /// transferred between queue families in order to be used by a queue of another family.
+    #[inline]
+    pub fn exclusive_sharing(&self) -> bool {
+        self.exclusive_sharing
+    }
+
     #[inline]
     pub fn usage_transfer_source(&self) -> bool {
         (self.usage & vk::BUFFER_USAGE_TRANSFER_SRC_BIT) != 0
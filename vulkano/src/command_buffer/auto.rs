@@ -8,6 +8,7 @@
 // according to those terms.
 
 use std::error;
+use std::ffi::CStr;
 use std::fmt;
 use std::iter;
 use std::mem;
@@ -17,12 +18,16 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 
 use OomError;
+use VulkanObject;
 use buffer::BufferAccess;
 use buffer::TypedBufferAccess;
+use command_buffer::ClearAttachment;
+use command_buffer::ClearRect;
 use command_buffer::CommandBuffer;
 use command_buffer::CommandBufferExecError;
 use command_buffer::DrawIndirectCommand;
 use command_buffer::DynamicState;
+use command_buffer::QueueFamilyTransfer;
 use command_buffer::StateCacher;
 use command_buffer::StateCacherOutcome;
 use command_buffer::pool::CommandPoolBuilderAlloc;
@@ -38,6 +43,7 @@ use command_buffer::sys::KindSecondaryRenderPass;
 use command_buffer::sys::UnsafeCommandBuffer;
 use command_buffer::sys::UnsafeCommandBufferBuilderBufferImageCopy;
 use command_buffer::sys::UnsafeCommandBufferBuilderColorImageClear;
+use command_buffer::sys::UnsafeCommandBufferBuilderDepthStencilImageClear;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageAspect;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageBlit;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageCopy;
@@ -57,23 +63,31 @@ use framebuffer::FramebufferAbstract;
 use framebuffer::LoadOp;
 use framebuffer::RenderPass;
 use framebuffer::RenderPassAbstract;
-use framebuffer::RenderPassCompatible;
 use framebuffer::RenderPassDescClearValues;
 use framebuffer::Subpass;
 use framebuffer::SubpassContents;
 use image::ImageAccess;
+use image::ImageAspects;
 use image::ImageLayout;
 use instance::QueueFamily;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
+use pipeline::depth_stencil::StencilFaceFlags;
 use pipeline::input_assembly::Index;
 use pipeline::vertex::VertexSource;
 use query::QueryPipelineStatisticFlags;
+use query::QueryPoolAccess;
+use query::QueryResultElement;
+use query::QueryResultFlags;
+use query::QueryState;
+use query::QueryType;
+use query::TimestampQueryPool;
 use sampler::Filter;
 use sync::AccessCheckError;
 use sync::AccessFlagBits;
 use sync::GpuFuture;
 use sync::PipelineStages;
+use vk;
 
 ///
 ///
@@ -86,15 +100,36 @@ pub struct AutoCommandBufferBuilder<P = StandardCommandPoolBuilder> {
     inner: SyncCommandBufferBuilder<P>,
     state_cacher: StateCacher,
 
+    // Identifier of the queue family of the command buffer.
+    queue_family: u32,
+
     // True if the queue family supports graphics operations.
     graphics_allowed: bool,
 
     // True if the queue family supports compute operations.
     compute_allowed: bool,
 
+    // True if the queue family supports timestamps.
+    timestamps_allowed: bool,
+
+    // Number of debug markers that have been begun and not ended yet.
+    debug_markers: u32,
+
+    // Queries that have been begun but not ended yet. Contains the query pool, the index of the
+    // query and the type of query.
+    active_queries: Vec<(vk::QueryPool, u32, QueryType)>,
+
+    // State of the queries that are used by this command buffer at the current point of the
+    // command buffer. Contains the query pool, the index of the query and its state. The states
+    // are only applied to the query pools when the command buffer is submitted.
+    query_states: Vec<(Arc<QueryPoolAccess>, u32, QueryState)>,
+
     // If we're inside a render pass, contains the render pass and the subpass index.
     render_pass: Option<(Box<RenderPassAbstract>, u32)>,
 
+    // Width, height and number of layers of the framebuffer of the current render pass, if known.
+    framebuffer_dimensions: Option<[u32; 3]>,
+
     // True if we are a secondary command buffer.
     secondary_cb: bool,
 
@@ -369,14 +404,15 @@ impl AutoCommandBufferBuilder<StandardCommandPoolBuilder> {
               F: FramebufferAbstract
     {
         unsafe {
-            let (secondary_cb, render_pass) = match kind {
-                Kind::Primary => (false, None),
+            let (secondary_cb, render_pass, framebuffer_dimensions) = match kind {
+                Kind::Primary => (false, None, None),
                 Kind::Secondary { render_pass: Some(ref sec), .. } => {
                     let render_pass = sec.subpass.render_pass().clone();
                     let index = sec.subpass.index();
-                    (true, Some((Box::new(render_pass) as Box<_>, index)))
+                    let dimensions = sec.framebuffer.as_ref().map(|f| f.dimensions());
+                    (true, Some((Box::new(render_pass) as Box<_>, index)), dimensions)
                 },
-                Kind::Secondary { render_pass: None, .. } => (true, None),
+                Kind::Secondary { render_pass: None, .. } => (true, None, None),
             };
 
             let pool = Device::standard_command_pool(&device, queue_family);
@@ -385,13 +421,20 @@ impl AutoCommandBufferBuilder<StandardCommandPoolBuilder> {
 
             let graphics_allowed = queue_family.supports_graphics();
             let compute_allowed = queue_family.supports_compute();
+            let timestamps_allowed = queue_family.timestamp_valid_bits() != 0;
 
             Ok(AutoCommandBufferBuilder {
                    inner: inner?,
                    state_cacher,
+                   queue_family: queue_family.id(),
                    graphics_allowed,
                    compute_allowed,
+                   timestamps_allowed,
+                   debug_markers: 0,
+                   active_queries: Vec::new(),
+                   query_states: Vec::new(),
                    render_pass,
+                   framebuffer_dimensions,
                    secondary_cb,
                    subpass_secondary: false,
                    flags,
@@ -401,6 +444,15 @@ impl AutoCommandBufferBuilder<StandardCommandPoolBuilder> {
 }
 
 impl<P> AutoCommandBufferBuilder<P> {
+    // Returns the queue family of the command buffer.
+    #[inline]
+    fn own_queue_family(&self) -> QueueFamily {
+        self.device()
+            .physical_device()
+            .queue_family_by_id(self.queue_family)
+            .unwrap()
+    }
+
     #[inline]
     fn ensure_outside_render_pass(&self) -> Result<(), AutoCommandBufferBuilderContextError> {
         if self.render_pass.is_none() {
@@ -425,10 +477,8 @@ impl<P> AutoCommandBufferBuilder<P> {
     }
 
     #[inline]
-    fn ensure_inside_render_pass_inline<Gp>(&self, pipeline: &Gp)
-                                            -> Result<(), AutoCommandBufferBuilderContextError>
-        where Gp: ?Sized + GraphicsPipelineAbstract
-    {
+    fn ensure_inside_render_pass_inline(&self)
+                                        -> Result<(), AutoCommandBufferBuilderContextError> {
         if self.render_pass.is_none() {
             return Err(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass);
         }
@@ -437,17 +487,46 @@ impl<P> AutoCommandBufferBuilder<P> {
             return Err(AutoCommandBufferBuilderContextError::WrongSubpassType);
         }
 
-        let local_render_pass = self.render_pass.as_ref().unwrap();
+        Ok(())
+    }
 
-        if pipeline.subpass_index() != local_render_pass.1 {
-            return Err(AutoCommandBufferBuilderContextError::WrongSubpassIndex);
-        }
+    // Returns the state of a query at the current point of the command buffer.
+    fn query_state<Q>(&self, pool: &Q, query: u32) -> QueryState
+        where Q: ?Sized + QueryPoolAccess
+    {
+        let handle = pool.inner().internal_object();
+        self.query_states
+            .iter()
+            .find(|&&(ref p, q, _)| p.inner().internal_object() == handle && q == query)
+            .map(|&(_, _, state)| state)
+            .unwrap_or_else(|| pool.query_state(query))
+    }
 
-        if !RenderPassCompatible::is_compatible_with(pipeline, &local_render_pass.0) {
-            return Err(AutoCommandBufferBuilderContextError::IncompatibleRenderPass);
+    // Sets the state of the queries `first_index .. first_index + count` at the current point of
+    // the command buffer. Must only be called after the range has been checked.
+    fn set_query_state(&mut self, pool: Arc<QueryPoolAccess>, first_index: u32, count: u32,
+                       state: QueryState) {
+        let handle = pool.inner().internal_object();
+        for query in first_index .. first_index + count {
+            let existing = self.query_states
+                .iter()
+                .position(|&(ref p, q, _)| p.inner().internal_object() == handle && q == query);
+            match existing {
+                Some(position) => self.query_states[position].2 = state,
+                None => self.query_states.push((pool.clone(), query, state)),
+            }
         }
+    }
 
-        Ok(())
+    // Checks whether `pipeline` can be used in the current subpass. Must only be called after
+    // `ensure_inside_render_pass_inline`.
+    #[inline]
+    fn check_pipeline_render_pass<Gp>(&self, pipeline: &Gp)
+                                      -> Result<(), CheckRenderPassCompatibilityError>
+        where Gp: ?Sized + GraphicsPipelineAbstract
+    {
+        let &(ref render_pass, subpass) = self.render_pass.as_ref().unwrap();
+        check_render_pass_compatibility(pipeline, &**render_pass, subpass)
     }
 
     /// Builds the command buffer.
@@ -459,6 +538,14 @@ impl<P> AutoCommandBufferBuilder<P> {
             return Err(AutoCommandBufferBuilderContextError::ForbiddenInsideRenderPass.into());
         }
 
+        if !self.active_queries.is_empty() {
+            return Err(AutoCommandBufferBuilderContextError::QueryIsActive.into());
+        }
+
+        if self.debug_markers != 0 {
+            return Err(AutoCommandBufferBuilderContextError::DebugMarkerIsActive.into());
+        }
+
         let submit_state = match self.flags {
             Flags::None => {
                 SubmitState::ExclusiveUse { in_use: AtomicBool::new(false) }
@@ -474,9 +561,98 @@ impl<P> AutoCommandBufferBuilder<P> {
         Ok(AutoCommandBuffer {
                inner: self.inner.build()?,
                submit_state,
+               query_states: self.query_states,
            })
     }
 
+    /// Adds a command that acquires the ownership of a buffer that was released by the queue
+    /// family `source` with `release_buffer_ownership`.
+    ///
+    /// This is only necessary for buffers created with the exclusive sharing mode, and only if
+    /// the release and the acquire aren't automatically performed when executing the command
+    /// buffer after a future of another queue family. The command buffer that acquires the
+    /// ownership must be executed after the one that released it, for example by waiting on a
+    /// semaphore.
+    #[inline]
+    pub fn acquire_buffer_ownership<B>(mut self, buffer: B, source: QueueFamily)
+                                       -> Result<Self, OwnershipTransferError>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            let destination = self.own_queue_family();
+            check_buffer_ownership_transfer(self.device(), &buffer, source, destination)?;
+            self.inner
+                .acquire_buffer_ownership(buffer, source.id(), destination.id())?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that acquires the ownership of an image that was released by the queue
+    /// family `source` with `release_image_ownership`.
+    ///
+    /// Same as `acquire_buffer_ownership`, but for images. The image must be in the layout
+    /// returned by `initial_layout_requirement()`.
+    #[inline]
+    pub fn acquire_image_ownership<I>(mut self, image: I, source: QueueFamily)
+                                      -> Result<Self, OwnershipTransferError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            let destination = self.own_queue_family();
+            check_image_ownership_transfer(self.device(), &image, source, destination)?;
+            self.inner
+                .acquire_image_ownership(image, source.id(), destination.id())?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that begins a query.
+    ///
+    /// The query must have been reset with `reset_query_pool`, either earlier in this command
+    /// buffer or in a command buffer that has been submitted. It must be ended with `end_query`
+    /// before the command buffer is built. Only one query of each type can be active at a time.
+    /// Timestamp queries can't be begun; use `write_timestamp` instead.
+    ///
+    /// If `precise` is true, an occlusion query counts the exact number of samples that pass the
+    /// depth and stencil tests instead of possibly only telling whether any sample passed. This
+    /// requires the `occlusion_query_precise` feature.
+    #[inline]
+    pub fn begin_query<Q>(mut self, pool: Arc<Q>, query: u32, precise: bool)
+                          -> Result<Self, BeginQueryError>
+        where Q: QueryPoolAccess + 'static
+    {
+        unsafe {
+            check_begin_query(self.device(),
+                              &*pool,
+                              query,
+                              precise,
+                              |index| self.query_state(&*pool, index))?;
+
+            let ty = pool.inner().ty();
+            let allowed = match ty {
+                QueryType::Occlusion => self.graphics_allowed,
+                _ => self.graphics_allowed || self.compute_allowed,
+            };
+            if !allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            if self.active_queries
+                .iter()
+                .any(|&(_, _, t)| mem::discriminant(&t) == mem::discriminant(&ty))
+            {
+                return Err(AutoCommandBufferBuilderContextError::QueryTypeAlreadyActive.into());
+            }
+
+            self.set_query_state(pool.clone(), query, 1, QueryState::Active);
+            self.active_queries.push((pool.inner().internal_object(), query, ty));
+            self.inner.begin_query(pool, query, precise);
+            Ok(self)
+        }
+    }
+
     /// Adds a command that enters a render pass.
     ///
     /// If `secondary` is true, then you will only be able to add secondary command buffers while
@@ -561,6 +737,7 @@ impl<P> AutoCommandBufferBuilder<P> {
             };
             self.inner
                 .begin_render_pass(framebuffer.clone(), contents, clear_values)?;
+            self.framebuffer_dimensions = Some(framebuffer.dimensions());
             self.render_pass = Some((Box::new(framebuffer) as Box<_>, 0));
             self.subpass_secondary = secondary;
             Ok(self)
@@ -585,11 +762,16 @@ impl<P> AutoCommandBufferBuilder<P> {
     /// If `layer_count` is superior to 1, the copy will happen between each individual layer as
     /// if they were separate images.
     ///
+    /// The copy is done in the `TransferSrcOptimal` and `TransferDstOptimal` layouts, and copies
+    /// either the color aspect or the depth and stencil aspects. Use `copy_image_with_layouts` to
+    /// choose them.
+    ///
     /// # Panic
     ///
     /// - Panics if the source or the destination was not created with `device`.
     ///
-    pub fn copy_image<S, D>(mut self, source: S, source_offset: [i32; 3],
+    #[inline]
+    pub fn copy_image<S, D>(self, source: S, source_offset: [i32; 3],
                             source_base_array_layer: u32, source_mip_level: u32,
                             destination: D, destination_offset: [i32; 3],
                             destination_base_array_layer: u32, destination_mip_level: u32,
@@ -597,31 +779,83 @@ impl<P> AutoCommandBufferBuilder<P> {
                             -> Result<Self, CopyImageError>
         where S: ImageAccess + Send + Sync + 'static,
               D: ImageAccess + Send + Sync + 'static
+    {
+        // If color is included, neither depth nor stencil may be.
+        let aspects = if source.has_color() {
+            ImageAspects::color()
+        } else {
+            ImageAspects {
+                color: false,
+                depth: source.has_depth() && destination.has_depth(),
+                stencil: source.has_stencil() && destination.has_stencil(),
+            }
+        };
+
+        self.copy_image_with_layouts(source,
+                                     ImageLayout::TransferSrcOptimal,
+                                     source_offset,
+                                     source_base_array_layer,
+                                     source_mip_level,
+                                     destination,
+                                     ImageLayout::TransferDstOptimal,
+                                     destination_offset,
+                                     destination_base_array_layer,
+                                     destination_mip_level,
+                                     extent,
+                                     layer_count,
+                                     aspects)
+    }
+
+    /// Same as `copy_image`, but lets you choose the layouts of the images and the aspects that
+    /// are copied.
+    ///
+    /// The source layout must be `TransferSrcOptimal` or `General`, and the destination layout
+    /// must be `TransferDstOptimal` or `General`. The images are transitioned to these layouts
+    /// before the copy if necessary. `General` is useful for images that are also used as
+    /// storage images.
+    ///
+    /// `aspects` must contain either only the color aspect, or the depth and/or stencil aspects.
+    /// All the aspects must be present in both the source and the destination.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn copy_image_with_layouts<S, D>(mut self, source: S, source_layout: ImageLayout,
+                                         source_offset: [i32; 3], source_base_array_layer: u32,
+                                         source_mip_level: u32, destination: D,
+                                         destination_layout: ImageLayout,
+                                         destination_offset: [i32; 3],
+                                         destination_base_array_layer: u32,
+                                         destination_mip_level: u32, extent: [u32; 3],
+                                         layer_count: u32, aspects: ImageAspects)
+                                         -> Result<Self, CopyImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static
     {
         unsafe {
             self.ensure_outside_render_pass()?;
 
             check_copy_image(self.device(),
                              &source,
+                             source_layout,
                              source_offset,
                              source_base_array_layer,
                              source_mip_level,
                              &destination,
+                             destination_layout,
                              destination_offset,
                              destination_base_array_layer,
                              destination_mip_level,
                              extent,
-                             layer_count)?;
+                             layer_count,
+                             aspects)?;
 
             let copy = UnsafeCommandBufferBuilderImageCopy {
-                // TODO: Allowing choosing a subset of the image aspects, but note that if color
-                // is included, neither depth nor stencil may.
                 aspect: UnsafeCommandBufferBuilderImageAspect {
-                    color: source.has_color(),
-                    depth: !source.has_color() &&
-                           source.has_depth() && destination.has_depth(),
-                    stencil: !source.has_color() &&
-                             source.has_stencil() && destination.has_stencil(),
+                    color: aspects.color,
+                    depth: aspects.depth,
+                    stencil: aspects.stencil,
                 },
                 source_mip_level,
                 destination_mip_level,
@@ -633,11 +867,9 @@ impl<P> AutoCommandBufferBuilder<P> {
                 extent,
             };
 
-            // TODO: Allow choosing layouts, but note that only Transfer*Optimal and General are
-            // valid.
             self.inner
-                .copy_image(source, ImageLayout::TransferSrcOptimal,
-                            destination, ImageLayout::TransferDstOptimal,
+                .copy_image(source, source_layout,
+                            destination, destination_layout,
                             iter::once(copy))?;
             Ok(self)
         }
@@ -670,11 +902,15 @@ impl<P> AutoCommandBufferBuilder<P> {
     /// If `layer_count` is superior to 1, the blit will happen between each individual layer as
     /// if they were separate images.
     ///
+    /// The blit is done in the `TransferSrcOptimal` and `TransferDstOptimal` layouts, and blits
+    /// all the aspects of the source. Use `blit_image_with_layouts` to choose them.
+    ///
     /// # Panic
     ///
     /// - Panics if the source or the destination was not created with `device`.
     ///
-    pub fn blit_image<S, D>(mut self, source: S, source_top_left: [i32; 3],
+    #[inline]
+    pub fn blit_image<S, D>(self, source: S, source_top_left: [i32; 3],
                             source_bottom_right: [i32; 3], source_base_array_layer: u32,
                             source_mip_level: u32, destination: D, destination_top_left: [i32; 3],
                             destination_bottom_right: [i32; 3], destination_base_array_layer: u32,
@@ -682,6 +918,53 @@ impl<P> AutoCommandBufferBuilder<P> {
                             -> Result<Self, BlitImageError>
         where S: ImageAccess + Send + Sync + 'static,
               D: ImageAccess + Send + Sync + 'static
+    {
+        let aspects = ImageAspects::from_image(&source);
+
+        self.blit_image_with_layouts(source,
+                                     ImageLayout::TransferSrcOptimal,
+                                     source_top_left,
+                                     source_bottom_right,
+                                     source_base_array_layer,
+                                     source_mip_level,
+                                     destination,
+                                     ImageLayout::TransferDstOptimal,
+                                     destination_top_left,
+                                     destination_bottom_right,
+                                     destination_base_array_layer,
+                                     destination_mip_level,
+                                     layer_count,
+                                     aspects,
+                                     filter)
+    }
+
+    /// Same as `blit_image`, but lets you choose the layouts of the images and the aspects that
+    /// are blitted.
+    ///
+    /// The source layout must be `TransferSrcOptimal` or `General`, and the destination layout
+    /// must be `TransferDstOptimal` or `General`. The images are transitioned to these layouts
+    /// before the blit if necessary.
+    ///
+    /// `aspects` must contain either only the color aspect, or the depth and/or stencil aspects.
+    /// All the aspects must be present in both the source and the destination.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the source or the destination was not created with `device`.
+    ///
+    pub fn blit_image_with_layouts<S, D>(mut self, source: S, source_layout: ImageLayout,
+                                         source_top_left: [i32; 3],
+                                         source_bottom_right: [i32; 3],
+                                         source_base_array_layer: u32, source_mip_level: u32,
+                                         destination: D, destination_layout: ImageLayout,
+                                         destination_top_left: [i32; 3],
+                                         destination_bottom_right: [i32; 3],
+                                         destination_base_array_layer: u32,
+                                         destination_mip_level: u32, layer_count: u32,
+                                         aspects: ImageAspects, filter: Filter)
+                                         -> Result<Self, BlitImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static
     {
         unsafe {
             if !self.graphics_allowed {
@@ -692,28 +975,26 @@ impl<P> AutoCommandBufferBuilder<P> {
 
             check_blit_image(self.device(),
                              &source,
+                             source_layout,
                              source_top_left,
                              source_bottom_right,
                              source_base_array_layer,
                              source_mip_level,
                              &destination,
+                             destination_layout,
                              destination_top_left,
                              destination_bottom_right,
                              destination_base_array_layer,
                              destination_mip_level,
                              layer_count,
+                             aspects,
                              filter)?;
 
             let blit = UnsafeCommandBufferBuilderImageBlit {
-                // TODO:
-                aspect: if source.has_color() {
-                    UnsafeCommandBufferBuilderImageAspect {
-                        color: true,
-                        depth: false,
-                        stencil: false,
-                    }
-                } else {
-                    unimplemented!()
+                aspect: UnsafeCommandBufferBuilderImageAspect {
+                    color: aspects.color,
+                    depth: aspects.depth,
+                    stencil: aspects.stencil,
                 },
                 source_mip_level,
                 destination_mip_level,
@@ -728,15 +1009,61 @@ impl<P> AutoCommandBufferBuilder<P> {
 
             self.inner
                 .blit_image(source,
-                            ImageLayout::TransferSrcOptimal,
-                            destination, // TODO: let choose layout
-                            ImageLayout::TransferDstOptimal,
+                            source_layout,
+                            destination,
+                            destination_layout,
                             iter::once(blit),
                             filter)?;
             Ok(self)
         }
     }
 
+    /// Adds a command that clears regions of attachments of the current subpass.
+    ///
+    /// Contrary to the clear values passed when beginning a render pass, this can be called at
+    /// any point during an inline subpass. Each attachment is cleared within each of the rects.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the clear value of a color attachment is not a color value.
+    ///
+    pub fn clear_attachments<A, R>(mut self, attachments: A, rects: R)
+                                   -> Result<Self, ClearAttachmentsError>
+        where A: IntoIterator<Item = ClearAttachment>,
+              R: IntoIterator<Item = ClearRect>
+    {
+        unsafe {
+            let (attachments, rects) = {
+                let &(ref render_pass, subpass) = match self.render_pass {
+                    Some(ref rp) => rp,
+                    None => {
+                        return Err(AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass
+                                       .into())
+                    },
+                };
+
+                if self.subpass_secondary {
+                    return Err(AutoCommandBufferBuilderContextError::WrongSubpassType.into());
+                }
+
+                let attachments = attachments.into_iter().collect::<Vec<_>>();
+                let rects = rects.into_iter().collect::<Vec<_>>();
+                check_clear_attachments(&**render_pass,
+                                        subpass,
+                                        self.framebuffer_dimensions,
+                                        &attachments,
+                                        &rects)?;
+                (attachments, rects)
+            };
+
+            debug_assert!(self.graphics_allowed);
+
+            self.inner
+                .clear_attachments(attachments.into_iter(), rects.into_iter());
+            Ok(self)
+        }
+    }
+
     /// Adds a command that clears all the layers and mipmap levels of a color image with a
     /// specific value.
     ///
@@ -760,10 +1087,33 @@ impl<P> AutoCommandBufferBuilder<P> {
     ///
     /// - Panics if `color` is not a color value.
     ///
-    pub fn clear_color_image_dimensions<I>(mut self, image: I, first_layer: u32, num_layers: u32,
+    #[inline]
+    pub fn clear_color_image_dimensions<I>(self, image: I, first_layer: u32, num_layers: u32,
                                            first_mipmap: u32, num_mipmaps: u32, color: ClearValue)
                                            -> Result<Self, ClearColorImageError>
         where I: ImageAccess + Send + Sync + 'static
+    {
+        self.clear_color_image_with_layout(image,
+                                           ImageLayout::TransferDstOptimal,
+                                           first_layer,
+                                           num_layers,
+                                           first_mipmap,
+                                           num_mipmaps,
+                                           color)
+    }
+
+    /// Same as `clear_color_image_dimensions`, but lets you choose the layout of the image
+    /// during the clear. The layout must be `TransferDstOptimal` or `General`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `color` is not a color value.
+    ///
+    pub fn clear_color_image_with_layout<I>(mut self, image: I, layout: ImageLayout,
+                                            first_layer: u32, num_layers: u32, first_mipmap: u32,
+                                            num_mipmaps: u32, color: ClearValue)
+                                            -> Result<Self, ClearColorImageError>
+        where I: ImageAccess + Send + Sync + 'static
     {
         unsafe {
             if !self.graphics_allowed && !self.compute_allowed {
@@ -773,6 +1123,7 @@ impl<P> AutoCommandBufferBuilder<P> {
             self.ensure_outside_render_pass()?;
             check_clear_color_image(self.device(),
                                     &image,
+                                    layout,
                                     first_layer,
                                     num_layers,
                                     first_mipmap,
@@ -792,16 +1143,85 @@ impl<P> AutoCommandBufferBuilder<P> {
                 layer_count: num_layers,
             };
 
-            // TODO: let choose layout
             self.inner
                 .clear_color_image(image,
-                                   ImageLayout::TransferDstOptimal,
+                                   layout,
                                    color,
                                    iter::once(region))?;
             Ok(self)
         }
     }
 
+    /// Adds a command that clears all the layers and mipmap levels of a depth, stencil or
+    /// depth-stencil image with a specific value.
+    ///
+    /// The value must be `ClearValue::Depth`, `ClearValue::Stencil` or
+    /// `ClearValue::DepthStencil`, and only the corresponding aspects are cleared.
+    #[inline]
+    pub fn clear_depth_stencil_image<I>(self, image: I, value: ClearValue)
+                                        -> Result<Self, ClearDepthStencilImageError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        let layers = image.dimensions().array_layers();
+        let levels = image.mipmap_levels();
+
+        self.clear_depth_stencil_image_dimensions(image, 0, layers, 0, levels, value)
+    }
+
+    /// Adds a command that clears a depth, stencil or depth-stencil image with a specific value.
+    #[inline]
+    pub fn clear_depth_stencil_image_dimensions<I>(self, image: I, first_layer: u32,
+                                                   num_layers: u32, first_mipmap: u32,
+                                                   num_mipmaps: u32, value: ClearValue)
+                                                   -> Result<Self, ClearDepthStencilImageError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        self.clear_depth_stencil_image_with_layout(image,
+                                                   ImageLayout::TransferDstOptimal,
+                                                   first_layer,
+                                                   num_layers,
+                                                   first_mipmap,
+                                                   num_mipmaps,
+                                                   value)
+    }
+
+    /// Same as `clear_depth_stencil_image_dimensions`, but lets you choose the layout of the
+    /// image during the clear. The layout must be `TransferDstOptimal` or `General`.
+    pub fn clear_depth_stencil_image_with_layout<I>(mut self, image: I, layout: ImageLayout,
+                                                    first_layer: u32, num_layers: u32,
+                                                    first_mipmap: u32, num_mipmaps: u32,
+                                                    value: ClearValue)
+                                                    -> Result<Self, ClearDepthStencilImageError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        unsafe {
+            if !self.graphics_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            self.ensure_outside_render_pass()?;
+            check_clear_depth_stencil_image(self.device(),
+                                            &image,
+                                            layout,
+                                            first_layer,
+                                            num_layers,
+                                            first_mipmap,
+                                            num_mipmaps,
+                                            value)?;
+
+            let region = UnsafeCommandBufferBuilderDepthStencilImageClear {
+                base_mip_level: first_mipmap,
+                level_count: num_mipmaps,
+                base_array_layer: first_layer,
+                layer_count: num_layers,
+            };
+
+            self.inner
+                .clear_depth_stencil_image(image, layout, value, iter::once(region))?;
+            Ok(self)
+        }
+    }
+
     /// Adds a command that copies from a buffer to another.
     ///
     /// This command will copy from the source to the destination. If their size is not equal, then
@@ -836,13 +1256,41 @@ impl<P> AutoCommandBufferBuilder<P> {
     }
 
     /// Adds a command that copies from a buffer to an image.
-    pub fn copy_buffer_to_image_dimensions<S, D, Px>(mut self, source: S, destination: D,
+    ///
+    /// If the image has a depth-stencil format, only its depth aspect is written.
+    #[inline]
+    pub fn copy_buffer_to_image_dimensions<S, D, Px>(self, source: S, destination: D,
                                                      offset: [u32; 3], size: [u32; 3],
                                                      first_layer: u32, num_layers: u32, mipmap: u32)
                                                      -> Result<Self, CopyBufferImageError>
         where S: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
               D: ImageAccess + Send + Sync + 'static,
               Format: AcceptsPixels<Px>
+    {
+        let aspect = default_copy_aspect(&destination);
+        self.copy_buffer_to_image_with_layout(source,
+                                              destination,
+                                              ImageLayout::TransferDstOptimal,
+                                              offset,
+                                              size,
+                                              first_layer,
+                                              num_layers,
+                                              mipmap,
+                                              aspect)
+    }
+
+    /// Same as `copy_buffer_to_image_dimensions`, but lets you choose the layout of the image
+    /// during the copy and the aspect to write. The layout must be `TransferDstOptimal` or
+    /// `General`, and exactly one aspect of the image must be selected.
+    pub fn copy_buffer_to_image_with_layout<S, D, Px>(mut self, source: S, destination: D,
+                                                      destination_layout: ImageLayout,
+                                                      offset: [u32; 3], size: [u32; 3],
+                                                      first_layer: u32, num_layers: u32,
+                                                      mipmap: u32, aspect: ImageAspects)
+                                                      -> Result<Self, CopyBufferImageError>
+        where S: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
+              D: ImageAccess + Send + Sync + 'static,
+              Format: AcceptsPixels<Px>
     {
         unsafe {
             self.ensure_outside_render_pass()?;
@@ -851,24 +1299,22 @@ impl<P> AutoCommandBufferBuilder<P> {
                                     &source,
                                     &destination,
                                     CheckCopyBufferImageTy::BufferToImage,
+                                    destination_layout,
                                     offset,
                                     size,
                                     first_layer,
                                     num_layers,
-                                    mipmap)?;
+                                    mipmap,
+                                    aspect)?;
 
             let copy = UnsafeCommandBufferBuilderBufferImageCopy {
                 buffer_offset: 0,
                 buffer_row_length: 0,
                 buffer_image_height: 0,
-                image_aspect: if destination.has_color() {
-                    UnsafeCommandBufferBuilderImageAspect {
-                        color: true,
-                        depth: false,
-                        stencil: false,
-                    }
-                } else {
-                    unimplemented!()
+                image_aspect: UnsafeCommandBufferBuilderImageAspect {
+                    color: aspect.color,
+                    depth: aspect.depth,
+                    stencil: aspect.stencil,
                 },
                 image_mip_level: mipmap,
                 image_base_array_layer: first_layer,
@@ -880,7 +1326,7 @@ impl<P> AutoCommandBufferBuilder<P> {
             self.inner
                 .copy_buffer_to_image(source,
                                       destination,
-                                      ImageLayout::TransferDstOptimal, // TODO: let choose layout
+                                      destination_layout,
                                       iter::once(copy))?;
             Ok(self)
         }
@@ -900,13 +1346,41 @@ impl<P> AutoCommandBufferBuilder<P> {
     }
 
     /// Adds a command that copies from an image to a buffer.
-    pub fn copy_image_to_buffer_dimensions<S, D, Px>(mut self, source: S, destination: D,
+    ///
+    /// If the image has a depth-stencil format, only its depth aspect is read.
+    #[inline]
+    pub fn copy_image_to_buffer_dimensions<S, D, Px>(self, source: S, destination: D,
                                                      offset: [u32; 3], size: [u32; 3],
                                                      first_layer: u32, num_layers: u32, mipmap: u32)
                                                      -> Result<Self, CopyBufferImageError>
         where S: ImageAccess + Send + Sync + 'static,
               D: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
               Format: AcceptsPixels<Px>
+    {
+        let aspect = default_copy_aspect(&source);
+        self.copy_image_to_buffer_with_layout(source,
+                                              ImageLayout::TransferSrcOptimal,
+                                              destination,
+                                              offset,
+                                              size,
+                                              first_layer,
+                                              num_layers,
+                                              mipmap,
+                                              aspect)
+    }
+
+    /// Same as `copy_image_to_buffer_dimensions`, but lets you choose the layout of the image
+    /// during the copy and the aspect to read. The layout must be `TransferSrcOptimal` or
+    /// `General`, and exactly one aspect of the image must be selected.
+    pub fn copy_image_to_buffer_with_layout<S, D, Px>(mut self, source: S,
+                                                      source_layout: ImageLayout, destination: D,
+                                                      offset: [u32; 3], size: [u32; 3],
+                                                      first_layer: u32, num_layers: u32,
+                                                      mipmap: u32, aspect: ImageAspects)
+                                                      -> Result<Self, CopyBufferImageError>
+        where S: ImageAccess + Send + Sync + 'static,
+              D: TypedBufferAccess<Content = [Px]> + Send + Sync + 'static,
+              Format: AcceptsPixels<Px>
     {
         unsafe {
             self.ensure_outside_render_pass()?;
@@ -915,20 +1389,22 @@ impl<P> AutoCommandBufferBuilder<P> {
                                     &destination,
                                     &source,
                                     CheckCopyBufferImageTy::ImageToBuffer,
+                                    source_layout,
                                     offset,
                                     size,
                                     first_layer,
                                     num_layers,
-                                    mipmap)?;
+                                    mipmap,
+                                    aspect)?;
 
             let copy = UnsafeCommandBufferBuilderBufferImageCopy {
                 buffer_offset: 0,
                 buffer_row_length: 0,
                 buffer_image_height: 0,
                 image_aspect: UnsafeCommandBufferBuilderImageAspect {
-                    color: source.has_color(),
-                    depth: source.has_depth(),
-                    stencil: source.has_stencil()
+                    color: aspect.color,
+                    depth: aspect.depth,
+                    stencil: aspect.stencil,
                 },
                 image_mip_level: mipmap,
                 image_base_array_layer: first_layer,
@@ -939,13 +1415,98 @@ impl<P> AutoCommandBufferBuilder<P> {
 
             self.inner
                 .copy_image_to_buffer(source,
-                                      ImageLayout::TransferSrcOptimal,
-                                      destination, // TODO: let choose layout
+                                      source_layout,
+                                      destination,
                                       iter::once(copy))?;
             Ok(self)
         }
     }
 
+    /// Adds a command that copies the results of a range of queries to a buffer.
+    ///
+    /// The results are written in the same layout as `get_results` on the query pool. `T`
+    /// determines whether they are written as 32 bits or 64 bits integers.
+    #[inline]
+    pub fn copy_query_pool_results<Q, D, T>(mut self, pool: Arc<Q>, first_index: u32, count: u32,
+                                            destination: D, flags: QueryResultFlags)
+                                            -> Result<Self, CopyQueryPoolResultsError>
+        where Q: QueryPoolAccess + 'static,
+              D: TypedBufferAccess<Content = [T]> + Send + Sync + 'static,
+              T: QueryResultElement
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_copy_query_pool_results(self.device(),
+                                          &*pool,
+                                          first_index,
+                                          count,
+                                          &destination,
+                                          flags,
+                                          |index| self.query_state(&*pool, index))?;
+
+            let values_per_query = pool.inner().num_values_per_query() as usize +
+                if flags.with_availability { 1 } else { 0 };
+            let stride = values_per_query * mem::size_of::<T>();
+
+            self.inner
+                .copy_query_pool_results(pool, first_index, count, destination, stride, flags)?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that opens a debug marker region named `name`. The region must be closed
+    /// with `debug_marker_end`.
+    ///
+    /// Debug marker regions are displayed by debugging and profiling tools, and can be nested.
+    /// The `VK_EXT_debug_marker` extension must be enabled on the device.
+    #[inline]
+    pub fn debug_marker_begin(mut self, name: &CStr, color: [f32; 4])
+                              -> Result<Self, DebugMarkerError> {
+        unsafe {
+            if !self.graphics_allowed && !self.compute_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            check_debug_marker(self.device(), self.debug_markers, false)?;
+            self.debug_markers += 1;
+            self.inner.debug_marker_begin(name.to_owned(), color);
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that closes the debug marker region opened by the last call to
+    /// `debug_marker_begin`.
+    #[inline]
+    pub fn debug_marker_end(mut self) -> Result<Self, DebugMarkerError> {
+        unsafe {
+            if !self.graphics_allowed && !self.compute_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            check_debug_marker(self.device(), self.debug_markers, true)?;
+            self.debug_markers -= 1;
+            self.inner.debug_marker_end();
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that inserts a single debug marker named `name`.
+    ///
+    /// The `VK_EXT_debug_marker` extension must be enabled on the device.
+    #[inline]
+    pub fn debug_marker_insert(mut self, name: &CStr, color: [f32; 4])
+                               -> Result<Self, DebugMarkerError> {
+        unsafe {
+            if !self.graphics_allowed && !self.compute_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            check_debug_marker(self.device(), self.debug_markers, false)?;
+            self.inner.debug_marker_insert(name.to_owned(), color);
+            Ok(self)
+        }
+    }
+
     #[inline]
     pub fn dispatch<Cp, S, Pc>(mut self, dimensions: [u32; 3], pipeline: Cp, sets: S, constants: Pc)
                                -> Result<Self, DispatchError>
@@ -988,9 +1549,8 @@ impl<P> AutoCommandBufferBuilder<P> {
               S: DescriptorSetsCollection
     {
         unsafe {
-            // TODO: must check that pipeline is compatible with render pass
-
-            self.ensure_inside_render_pass_inline(&pipeline)?;
+            self.ensure_inside_render_pass_inline()?;
+            self.check_pipeline_render_pass(&pipeline)?;
             check_dynamic_state_validity(&pipeline, &dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
             check_descriptor_sets_validity(&pipeline, &sets)?;
@@ -1035,9 +1595,8 @@ impl<P> AutoCommandBufferBuilder<P> {
               I: Index + 'static
     {
         unsafe {
-            // TODO: must check that pipeline is compatible with render pass
-
-            self.ensure_inside_render_pass_inline(&pipeline)?;
+            self.ensure_inside_render_pass_inline()?;
+            self.check_pipeline_render_pass(&pipeline)?;
             let ib_infos = check_index_buffer(self.device(), &index_buffer)?;
             check_dynamic_state_validity(&pipeline, &dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
@@ -1091,9 +1650,8 @@ impl<P> AutoCommandBufferBuilder<P> {
                       + 'static
     {
         unsafe {
-            // TODO: must check that pipeline is compatible with render pass
-
-            self.ensure_inside_render_pass_inline(&pipeline)?;
+            self.ensure_inside_render_pass_inline()?;
+            self.check_pipeline_render_pass(&pipeline)?;
             check_dynamic_state_validity(&pipeline, &dynamic)?;
             check_push_constants_validity(&pipeline, &constants)?;
             check_descriptor_sets_validity(&pipeline, &sets)?;
@@ -1130,6 +1688,28 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
     }
 
+    /// Adds a command that ends a query that was begun with `begin_query`.
+    #[inline]
+    pub fn end_query<Q>(mut self, pool: Arc<Q>, query: u32)
+                        -> Result<Self, AutoCommandBufferBuilderContextError>
+        where Q: QueryPoolAccess + 'static
+    {
+        unsafe {
+            let handle = pool.inner().internal_object();
+            let position = match self.active_queries
+                .iter()
+                .position(|&(p, q, _)| p == handle && q == query) {
+                Some(p) => p,
+                None => return Err(AutoCommandBufferBuilderContextError::QueryNotActive),
+            };
+
+            self.active_queries.remove(position);
+            self.set_query_state(pool.clone(), query, 1, QueryState::Ended);
+            self.inner.end_query(pool, query);
+            Ok(self)
+        }
+    }
+
     /// Adds a command that ends the current render pass.
     ///
     /// This must be called after you went through all the subpasses and before you can build
@@ -1158,6 +1738,7 @@ impl<P> AutoCommandBufferBuilder<P> {
 
             self.inner.end_render_pass();
             self.render_pass = None;
+            self.framebuffer_dimensions = None;
             Ok(self)
         }
     }
@@ -1171,6 +1752,12 @@ impl<P> AutoCommandBufferBuilder<P> {
                                       -> Result<Self, ExecuteCommandsError>
         where C: CommandBuffer + Send + Sync + 'static
     {
+        // The primary command buffer leaves the queries in the state in which the secondary
+        // command buffer leaves them.
+        for &(ref pool, query, state) in command_buffer.query_states() {
+            self.set_query_state(pool.clone(), query, 1, state);
+        }
+
         {
             let mut builder = self.inner.execute_commands();
             builder.add(command_buffer);
@@ -1243,6 +1830,74 @@ impl<P> AutoCommandBufferBuilder<P> {
         }
     }
 
+    /// Adds a command that releases the ownership of a buffer, in order to transfer it to the
+    /// queue family `destination`.
+    ///
+    /// The buffer must then be acquired by a command buffer of `destination` with
+    /// `acquire_buffer_ownership` before it is used there. See `acquire_buffer_ownership` for
+    /// more information.
+    #[inline]
+    pub fn release_buffer_ownership<B>(mut self, buffer: B, destination: QueueFamily)
+                                       -> Result<Self, OwnershipTransferError>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            let source = self.own_queue_family();
+            check_buffer_ownership_transfer(self.device(), &buffer, source, destination)?;
+            self.inner
+                .release_buffer_ownership(buffer, source.id(), destination.id())?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that releases the ownership of an image, in order to transfer it to the
+    /// queue family `destination`.
+    ///
+    /// Same as `release_buffer_ownership`, but for images. The image must be in the layout
+    /// returned by `final_layout_requirement()`.
+    #[inline]
+    pub fn release_image_ownership<I>(mut self, image: I, destination: QueueFamily)
+                                      -> Result<Self, OwnershipTransferError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            let source = self.own_queue_family();
+            check_image_ownership_transfer(self.device(), &image, source, destination)?;
+            self.inner
+                .release_image_ownership(image, source.id(), destination.id())?;
+            Ok(self)
+        }
+    }
+
+    /// Adds a command that resets the queries `first_index .. first_index + count` of a query
+    /// pool.
+    ///
+    /// Queries must be reset before they can be begun or written.
+    #[inline]
+    pub fn reset_query_pool<Q>(mut self, pool: Arc<Q>, first_index: u32, count: u32)
+                               -> Result<Self, ResetQueryPoolError>
+        where Q: QueryPoolAccess + 'static
+    {
+        unsafe {
+            self.ensure_outside_render_pass()?;
+            check_reset_query_pool(self.device(), &*pool, first_index, count)?;
+
+            let handle = pool.inner().internal_object();
+            if self.active_queries
+                .iter()
+                .any(|&(p, q, _)| p == handle && q >= first_index && q < first_index + count)
+            {
+                return Err(AutoCommandBufferBuilderContextError::QueryIsActive.into());
+            }
+
+            self.set_query_state(pool.clone(), first_index, count, QueryState::Reset);
+            self.inner.reset_query_pool(pool, first_index, count);
+            Ok(self)
+        }
+    }
+
     /// Adds a command that writes data to a buffer.
     ///
     /// If `data` is larger than the buffer, only the part of `data` that fits is written. If the
@@ -1268,6 +1923,33 @@ impl<P> AutoCommandBufferBuilder<P> {
             Ok(self)
         }
     }
+
+    /// Adds a command that writes the current time to a timestamp query once all the previous
+    /// commands have reached the given pipeline stage.
+    ///
+    /// Exactly one stage must be set in `stage`. The query must have been reset with
+    /// `reset_query_pool`, either earlier in this command buffer or in a command buffer that has
+    /// been submitted.
+    #[inline]
+    pub fn write_timestamp(mut self, pool: Arc<TimestampQueryPool>, query: u32,
+                           stage: PipelineStages)
+                           -> Result<Self, WriteTimestampError> {
+        unsafe {
+            if !self.timestamps_allowed {
+                return Err(AutoCommandBufferBuilderContextError::NotSupportedByQueueFamily.into());
+            }
+
+            check_write_timestamp(self.device(),
+                                  &*pool,
+                                  query,
+                                  stage,
+                                  |index| self.query_state(&*pool, index))?;
+
+            self.set_query_state(pool.clone(), query, 1, QueryState::Ended);
+            self.inner.write_timestamp(pool, query, stage);
+            Ok(self)
+        }
+    }
 }
 
 unsafe impl<P> DeviceOwned for AutoCommandBufferBuilder<P> {
@@ -1277,6 +1959,18 @@ unsafe impl<P> DeviceOwned for AutoCommandBufferBuilder<P> {
     }
 }
 
+// Aspect used by the buffer-image copies that don't let the user choose one. Depth-stencil
+// images have their depth aspect copied.
+fn default_copy_aspect<I>(image: &I) -> ImageAspects
+    where I: ?Sized + ImageAccess
+{
+    ImageAspects {
+        color: image.has_color(),
+        depth: !image.has_color() && image.has_depth(),
+        stencil: !image.has_color() && !image.has_depth() && image.has_stencil(),
+    }
+}
+
 // Shortcut function to set the push constants.
 unsafe fn push_constants<P, Pl, Pc>(destination: &mut SyncCommandBufferBuilder<P>, pipeline: Pl,
                                     push_constants: Pc)
@@ -1316,6 +2010,45 @@ unsafe fn set_state<P>(destination: &mut SyncCommandBufferBuilder<P>, dynamic: D
     if let Some(ref scissors) = dynamic.scissors {
         destination.set_scissor(0, scissors.iter().cloned().collect::<Vec<_>>().into_iter()); // TODO: don't collect
     }
+
+    if let Some(bias) = dynamic.depth_bias {
+        destination.set_depth_bias(bias.constant_factor, bias.clamp, bias.slope_factor);
+    }
+
+    if let Some(ref bounds) = dynamic.depth_bounds {
+        destination.set_depth_bounds(bounds.start, bounds.end);
+    }
+
+    if let Some(mask) = dynamic.compare_mask {
+        if mask.front == mask.back {
+            destination.set_stencil_compare_mask(StencilFaceFlags::FrontAndBack, mask.front);
+        } else {
+            destination.set_stencil_compare_mask(StencilFaceFlags::Front, mask.front);
+            destination.set_stencil_compare_mask(StencilFaceFlags::Back, mask.back);
+        }
+    }
+
+    if let Some(mask) = dynamic.write_mask {
+        if mask.front == mask.back {
+            destination.set_stencil_write_mask(StencilFaceFlags::FrontAndBack, mask.front);
+        } else {
+            destination.set_stencil_write_mask(StencilFaceFlags::Front, mask.front);
+            destination.set_stencil_write_mask(StencilFaceFlags::Back, mask.back);
+        }
+    }
+
+    if let Some(reference) = dynamic.reference {
+        if reference.front == reference.back {
+            destination.set_stencil_reference(StencilFaceFlags::FrontAndBack, reference.front);
+        } else {
+            destination.set_stencil_reference(StencilFaceFlags::Front, reference.front);
+            destination.set_stencil_reference(StencilFaceFlags::Back, reference.back);
+        }
+    }
+
+    if let Some(constants) = dynamic.blend_constants {
+        destination.set_blend_constants(constants);
+    }
 }
 
 // Shortcut function to bind vertex buffers.
@@ -1385,6 +2118,9 @@ pub struct AutoCommandBuffer<P = StandardCommandPoolAlloc> {
 
     // Tracks usage of the command buffer on the GPU.
     submit_state: SubmitState,
+
+    // State in which this command buffer leaves the queries it uses.
+    query_states: Vec<(Arc<QueryPoolAccess>, u32, QueryState)>,
 }
 
 // Whether the command buffer can be submitted.
@@ -1435,7 +2171,20 @@ unsafe impl<P> CommandBuffer for AutoCommandBuffer<P> {
         };
 
         let err = match self.inner.lock_submit(future, queue) {
-            Ok(()) => return Ok(()),
+            Ok(()) => {
+                // The states of the queries are only applied now, otherwise waiting for the
+                // results of a command buffer that is never submitted would block forever.
+                for &(ref pool, query, state) in &self.query_states {
+                    let state = match state {
+                        QueryState::Ended => QueryState::Available,
+                        state => state,
+                    };
+                    unsafe {
+                        pool.set_query_state(query, 1, state);
+                    }
+                }
+                return Ok(());
+            },
             Err(err) => err,
         };
 
@@ -1484,6 +2233,17 @@ unsafe impl<P> CommandBuffer for AutoCommandBuffer<P> {
         self.inner
             .check_image_access(image, layout, exclusive, queue)
     }
+
+    #[inline]
+    fn queue_family_transfer(&self, future: &GpuFuture, queue: &Queue)
+                             -> Option<QueueFamilyTransfer> {
+        self.inner.queue_family_transfer(future, queue)
+    }
+
+    #[inline]
+    fn query_states(&self) -> &[(Arc<QueryPoolAccess>, u32, QueryState)] {
+        &self.query_states
+    }
 }
 
 unsafe impl<P> DeviceOwned for AutoCommandBuffer<P> {
@@ -1547,6 +2307,11 @@ err_gen!(BuildError {
              OomError,
          });
 
+err_gen!(BeginQueryError {
+             AutoCommandBufferBuilderContextError,
+             CheckBeginQueryError,
+         });
+
 err_gen!(BeginRenderPassError {
              AutoCommandBufferBuilderContextError,
              SyncCommandBufferBuilderError,
@@ -1570,6 +2335,17 @@ err_gen!(ClearColorImageError {
              SyncCommandBufferBuilderError,
          });
 
+err_gen!(ClearDepthStencilImageError {
+             AutoCommandBufferBuilderContextError,
+             CheckClearDepthStencilImageError,
+             SyncCommandBufferBuilderError,
+         });
+
+err_gen!(ClearAttachmentsError {
+             AutoCommandBufferBuilderContextError,
+             CheckClearAttachmentsError,
+         });
+
 err_gen!(CopyBufferError {
              AutoCommandBufferBuilderContextError,
              CheckCopyBufferError,
@@ -1582,11 +2358,22 @@ err_gen!(CopyBufferImageError {
              SyncCommandBufferBuilderError,
          });
 
+err_gen!(CopyQueryPoolResultsError {
+             AutoCommandBufferBuilderContextError,
+             CheckCopyQueryPoolResultsError,
+             SyncCommandBufferBuilderError,
+         });
+
 err_gen!(FillBufferError {
              AutoCommandBufferBuilderContextError,
              CheckFillBufferError,
          });
 
+err_gen!(DebugMarkerError {
+             AutoCommandBufferBuilderContextError,
+             CheckDebugMarkerError,
+         });
+
 err_gen!(DispatchError {
              AutoCommandBufferBuilderContextError,
              CheckPushConstantsValidityError,
@@ -1597,6 +2384,7 @@ err_gen!(DispatchError {
 
 err_gen!(DrawError {
              AutoCommandBufferBuilderContextError,
+             CheckRenderPassCompatibilityError,
              CheckDynamicStateValidityError,
              CheckPushConstantsValidityError,
              CheckDescriptorSetsValidityError,
@@ -1606,6 +2394,7 @@ err_gen!(DrawError {
 
 err_gen!(DrawIndexedError {
              AutoCommandBufferBuilderContextError,
+             CheckRenderPassCompatibilityError,
              CheckDynamicStateValidityError,
              CheckPushConstantsValidityError,
              CheckDescriptorSetsValidityError,
@@ -1616,6 +2405,7 @@ err_gen!(DrawIndexedError {
 
 err_gen!(DrawIndirectError {
              AutoCommandBufferBuilderContextError,
+             CheckRenderPassCompatibilityError,
              CheckDynamicStateValidityError,
              CheckPushConstantsValidityError,
              CheckDescriptorSetsValidityError,
@@ -1628,11 +2418,27 @@ err_gen!(ExecuteCommandsError {
              SyncCommandBufferBuilderError,
          });
 
+err_gen!(OwnershipTransferError {
+             AutoCommandBufferBuilderContextError,
+             CheckOwnershipTransferError,
+             SyncCommandBufferBuilderError,
+         });
+
+err_gen!(ResetQueryPoolError {
+             AutoCommandBufferBuilderContextError,
+             CheckResetQueryPoolError,
+         });
+
 err_gen!(UpdateBufferError {
              AutoCommandBufferBuilderContextError,
              CheckUpdateBufferError,
          });
 
+err_gen!(WriteTimestampError {
+             AutoCommandBufferBuilderContextError,
+             CheckWriteTimestampError,
+         });
+
 #[derive(Debug, Copy, Clone)]
 pub enum AutoCommandBufferBuilderContextError {
     /// Operation forbidden in a secondary command buffer.
@@ -1656,10 +2462,23 @@ pub enum AutoCommandBufferBuilderContextError {
     WrongSubpassType,
     /// Tried to use a graphics pipeline whose subpass index didn't match the current subpass
     /// index.
+    ///
+    /// Draw commands now report this as `CheckRenderPassCompatibilityError::WrongSubpassIndex`.
     WrongSubpassIndex,
     /// Tried to use a graphics pipeline whose render pass is incompatible with the current render
     /// pass.
+    ///
+    /// Draw commands now report this as
+    /// `CheckRenderPassCompatibilityError::IncompatibleRenderPass`.
     IncompatibleRenderPass,
+    /// Tried to build the command buffer or to reset a query while a query is active.
+    QueryIsActive,
+    /// Tried to end a query that is not active.
+    QueryNotActive,
+    /// Tried to begin a query while a query of the same type is already active.
+    QueryTypeAlreadyActive,
+    /// Tried to build the command buffer while a debug marker region is open.
+    DebugMarkerIsActive,
 }
 
 impl error::Error for AutoCommandBufferBuilderContextError {
@@ -1695,6 +2514,18 @@ impl error::Error for AutoCommandBufferBuilderContextError {
                 "tried to use a graphics pipeline whose render pass is incompatible with the \
                  current render pass"
             },
+            AutoCommandBufferBuilderContextError::QueryIsActive => {
+                "tried to build the command buffer or to reset a query while a query is active"
+            },
+            AutoCommandBufferBuilderContextError::QueryNotActive => {
+                "tried to end a query that is not active"
+            },
+            AutoCommandBufferBuilderContextError::QueryTypeAlreadyActive => {
+                "tried to begin a query while a query of the same type is already active"
+            },
+            AutoCommandBufferBuilderContextError::DebugMarkerIsActive => {
+                "tried to build the command buffer while a debug marker region is open"
+            },
         }
     }
 }
@@ -1705,3 +2536,114 @@ impl fmt::Display for AutoCommandBufferBuilderContextError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::AutoCommandBufferBuilderContextError;
+    use command_buffer::BuildError;
+    use command_buffer::CopyBufferImageError;
+    use command_buffer::validity::CheckCopyBufferImageError;
+    use device::DeviceExtensions;
+    use format::D16Unorm;
+    use image::AttachmentImage;
+    use image::ImageAccess;
+    use image::ImageAspects;
+    use image::ImageLayout;
+    use image::ImageUsage;
+    use std::ffi::CString;
+    use testing::Call;
+    use vk;
+
+    use VulkanObject;
+
+    #[test]
+    fn copy_depth_aspect() {
+        let (loader, device, queue) = mock_dev_and_queue!();
+
+        let usage = ImageUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..ImageUsage::none()
+        };
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], D16Unorm, usage)
+            .unwrap();
+        let raw_image = image.inner().image.internal_object();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::all(),
+                                                    (0 .. 16 * 16).map(|_| 0u16))
+            .unwrap();
+
+        let result = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .copy_buffer_to_image_with_layout(buffer.clone(),
+                                              image.clone(),
+                                              ImageLayout::TransferDstOptimal,
+                                              [0, 0, 0],
+                                              [16, 16, 1],
+                                              0,
+                                              1,
+                                              0,
+                                              ImageAspects::stencil());
+        match result {
+            Err(CopyBufferImageError::CheckCopyBufferImageError(
+                CheckCopyBufferImageError::AspectsMismatch)) => (),
+            _ => panic!(),
+        }
+
+        loader.take_calls();
+        let _ = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .copy_buffer_to_image(buffer.clone(), image.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let aspects = loader
+            .take_calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                            Call::CmdCopyBufferToImage { destination, regions, .. } => {
+                                assert_eq!(destination, raw_image);
+                                Some(regions)
+                            },
+                            _ => None,
+                        })
+            .flat_map(|regions| regions.into_iter())
+            .map(|(_, subresource)| subresource.aspect_mask)
+            .collect::<Vec<_>>();
+        assert_eq!(aspects, vec![vk::IMAGE_ASPECT_DEPTH_BIT]);
+    }
+
+    #[test]
+    fn build_with_open_debug_marker() {
+        let extensions = DeviceExtensions {
+            ext_debug_marker: true,
+            ..DeviceExtensions::none()
+        };
+        let (_, device, mut queues) = mock_dev_and_queue!(extensions; 0);
+        let queue = queues.next().unwrap();
+        let name = CString::new("marker").unwrap();
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .debug_marker_begin(&name, [1.0; 4])
+            .unwrap();
+        match builder.build() {
+            Err(BuildError::AutoCommandBufferBuilderContextError(
+                    AutoCommandBufferBuilderContextError::DebugMarkerIsActive)) => (),
+            _ => panic!(),
+        }
+
+        AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .debug_marker_begin(&name, [1.0; 4])
+            .unwrap()
+            .debug_marker_end()
+            .unwrap()
+            .build()
+            .unwrap();
+    }
+}
@@ -76,29 +76,42 @@
 pub use self::auto::AutoCommandBuffer;
 pub use self::auto::AutoCommandBufferBuilder;
 pub use self::auto::AutoCommandBufferBuilderContextError;
+pub use self::auto::BeginQueryError;
 pub use self::auto::BeginRenderPassError;
 pub use self::auto::BlitImageError;
 pub use self::auto::BuildError;
+pub use self::auto::ClearAttachmentsError;
 pub use self::auto::ClearColorImageError;
+pub use self::auto::ClearDepthStencilImageError;
 pub use self::auto::CopyBufferError;
 pub use self::auto::CopyBufferImageError;
+pub use self::auto::CopyQueryPoolResultsError;
+pub use self::auto::DebugMarkerError;
 pub use self::auto::DispatchError;
 pub use self::auto::DrawError;
 pub use self::auto::DrawIndexedError;
 pub use self::auto::DrawIndirectError;
 pub use self::auto::ExecuteCommandsError;
 pub use self::auto::FillBufferError;
+pub use self::auto::OwnershipTransferError;
+pub use self::auto::ResetQueryPoolError;
 pub use self::auto::UpdateBufferError;
+pub use self::auto::WriteTimestampError;
 pub use self::state_cacher::StateCacher;
 pub use self::state_cacher::StateCacherOutcome;
 pub use self::traits::CommandBuffer;
 pub use self::traits::CommandBufferExecError;
 pub use self::traits::CommandBufferExecFuture;
+pub use self::traits::QueueFamilyTransfer;
 
+use format::ClearValue;
+use pipeline::raster::DepthBias;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
+use std::ops::Range;
 
 pub mod pool;
+pub mod profiler;
 pub mod submit;
 pub mod synced;
 pub mod sys;
@@ -136,13 +149,23 @@ pub struct DispatchIndirectCommand {
 }
 
 /// The dynamic state to use for a draw command.
+///
+/// Each field corresponds to a state that the graphics pipeline can declare as dynamic. A field
+/// must be `Some` if and only if the corresponding state of the pipeline is dynamic.
 // TODO: probably not the right location
 #[derive(Debug, Clone)]
 pub struct DynamicState {
     pub line_width: Option<f32>,
     pub viewports: Option<Vec<Viewport>>,
     pub scissors: Option<Vec<Scissor>>,
-    // TODO: missing fields
+    /// Requires the `depth_bias_clamp` feature to be enabled if the clamp is not 0.0.
+    pub depth_bias: Option<DepthBias>,
+    /// Minimum and maximum depth values, both between 0.0 and 1.0.
+    pub depth_bounds: Option<Range<f32>>,
+    pub compare_mask: Option<DynamicStencilValue>,
+    pub write_mask: Option<DynamicStencilValue>,
+    pub reference: Option<DynamicStencilValue>,
+    pub blend_constants: Option<[f32; 4]>,
 }
 
 impl DynamicState {
@@ -152,6 +175,12 @@ impl DynamicState {
             line_width: None,
             viewports: None,
             scissors: None,
+            depth_bias: None,
+            depth_bounds: None,
+            compare_mask: None,
+            write_mask: None,
+            reference: None,
+            blend_constants: None,
         }
     }
 }
@@ -162,3 +191,38 @@ impl Default for DynamicState {
         DynamicState::none()
     }
 }
+
+/// Value of a dynamic stencil state, for the front and the back faces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DynamicStencilValue {
+    pub front: u32,
+    pub back: u32,
+}
+
+/// An attachment of the current subpass to clear with `clear_attachments`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ClearAttachment {
+    /// Clears the color attachment whose index within the color attachments of the subpass is
+    /// the first value. The clear value must be `Float`, `Int` or `Uint` depending on the format
+    /// of the attachment.
+    Color(u32, ClearValue),
+    /// Clears the depth aspect of the depth-stencil attachment of the subpass.
+    Depth(f32),
+    /// Clears the stencil aspect of the depth-stencil attachment of the subpass.
+    Stencil(u32),
+    /// Clears both aspects of the depth-stencil attachment of the subpass.
+    DepthStencil((f32, u32)),
+}
+
+/// A region of the framebuffer to clear with `clear_attachments`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClearRect {
+    /// Coordinates of the top-left corner of the region, in pixels.
+    pub offset: [i32; 2],
+    /// Width and height of the region, in pixels.
+    pub extent: [u32; 2],
+    /// First layer of the framebuffer to clear.
+    pub base_array_layer: u32,
+    /// Number of layers to clear.
+    pub layer_count: u32,
+}
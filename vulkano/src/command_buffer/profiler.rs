@@ -0,0 +1,799 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Measuring the time the GPU spends on parts of a command buffer.
+//!
+//! The `GpuProfiler` writes a pair of timestamps around each scope of a command buffer, and
+//! turns them into a tree of timings once the GPU has finished executing the command buffer.
+//! If the `VK_EXT_debug_marker` extension is enabled on the device, each scope is also surrounded
+//! with a debug marker region so that it shows up in debugging tools.
+//!
+//! Each frame is profiled in four steps:
+//!
+//! - `GpuProfiler::begin_frame` starts profiling a command buffer that is being built.
+//! - `ProfilerFrame::begin_scope` and `ProfilerFrame::end_scope` surround the commands to
+//!   measure. Scopes can be nested.
+//! - `ProfilerFrame::finish` must be called before the command buffer is built, then
+//!   `GpuProfiler::end_frame` takes the future that signals a fence once the command buffer has
+//!   been executed.
+//! - `GpuProfiler::poll` returns the timings of the frames whose fence has been signaled.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use vulkano::device::Device;
+//! # use vulkano::device::Queue;
+//! # let device: Arc<Device> = return;
+//! # let queue: Arc<Queue> = return;
+//! use vulkano::command_buffer::AutoCommandBufferBuilder;
+//! use vulkano::command_buffer::CommandBuffer;
+//! use vulkano::command_buffer::profiler::GpuProfiler;
+//! use vulkano::command_buffer::profiler::chrome_trace;
+//! use vulkano::sync::GpuFuture;
+//!
+//! let profiler = GpuProfiler::new(device.clone(), queue.family(), 32).unwrap();
+//!
+//! let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+//! let (mut frame, builder) = profiler.begin_frame(builder).unwrap();
+//! let builder = frame.begin_scope(builder, "shadows").unwrap();
+//! // ... add commands ...
+//! let builder = frame.end_scope(builder).unwrap();
+//! let builder = frame.finish(builder).unwrap();
+//!
+//! let future = builder.build().unwrap()
+//!     .execute(queue.clone()).unwrap()
+//!     .then_signal_fence_and_flush().unwrap();
+//! let future = Arc::new(future);
+//! profiler.end_frame(frame, future.clone());
+//!
+//! // Later, for example at the next frame:
+//! let timings = profiler.poll().unwrap();
+//! for frame in &timings {
+//!     println!("frame {} took {} ns", frame.index, frame.root.duration_ns);
+//! }
+//! let json = chrome_trace(&timings);
+//! ```
+
+use std::error;
+use std::ffi::CString;
+use std::fmt;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use OomError;
+use command_buffer::AutoCommandBufferBuilder;
+use command_buffer::DebugMarkerError;
+use command_buffer::ResetQueryPoolError;
+use command_buffer::WriteTimestampError;
+use device::Device;
+use instance::QueueFamily;
+use query::GetResultsError;
+use query::QueryResultFlags;
+use query::TimestampQueryPool;
+use sync::FenceSignalFuture;
+use sync::GpuFuture;
+use sync::PipelineStages;
+
+/// Measures the time spent by the GPU on scopes of command buffers.
+///
+/// See the documentation of the module for an overview.
+pub struct GpuProfiler {
+    device: Arc<Device>,
+    // Number of timestamp queries of each pool. Two per scope, including the root scope.
+    queries_per_frame: u32,
+    // Number of nanoseconds per timestamp increment.
+    timestamp_period: f32,
+    // Mask of the meaningful bits of the timestamps.
+    timestamp_mask: u64,
+    // True if scopes are surrounded with debug markers.
+    debug_markers: bool,
+    // Index of the next frame.
+    next_frame: AtomicUsize,
+    // Query pools that aren't used by any frame. Shared with the frames, which put back their
+    // pool when they are destroyed if the GPU no longer writes to it.
+    free_pools: Arc<Mutex<Vec<Arc<TimestampQueryPool>>>>,
+    // Frames that have been submitted but not resolved yet.
+    pending: Mutex<Vec<(ProfilerFrame, Box<FenceStatus + Send + Sync>)>>,
+}
+
+impl GpuProfiler {
+    /// Builds a new profiler for command buffers that are executed on queues of `queue_family`.
+    ///
+    /// Each frame can contain up to `max_scopes_per_frame` scopes, not counting the scope of the
+    /// whole frame.
+    pub fn new(device: Arc<Device>, queue_family: QueueFamily, max_scopes_per_frame: u32)
+               -> Result<GpuProfiler, ProfilerError> {
+        let valid_bits = queue_family.timestamp_valid_bits();
+        if valid_bits == 0 {
+            return Err(ProfilerError::TimestampsNotSupported);
+        }
+
+        let timestamp_mask = if valid_bits >= 64 {
+            !0
+        } else {
+            (1u64 << valid_bits) - 1
+        };
+
+        let timestamp_period = device.physical_device().limits().timestamp_period();
+        let debug_markers = device.loaded_extensions().ext_debug_marker;
+
+        Ok(GpuProfiler {
+               device: device,
+               queries_per_frame: (max_scopes_per_frame + 1) * 2,
+               timestamp_period: timestamp_period,
+               timestamp_mask: timestamp_mask,
+               debug_markers: debug_markers,
+               next_frame: AtomicUsize::new(0),
+               free_pools: Arc::new(Mutex::new(Vec::new())),
+               pending: Mutex::new(Vec::new()),
+           })
+    }
+
+    /// Starts profiling a frame.
+    ///
+    /// Adds to `builder` a command that resets the queries of the frame, and opens the scope that
+    /// covers the whole frame. Must be called outside of a render pass.
+    pub fn begin_frame<P>(&self, builder: AutoCommandBufferBuilder<P>)
+                          -> Result<(ProfilerFrame, AutoCommandBufferBuilder<P>), ProfilerError> {
+        let pool = match self.free_pools.lock().unwrap().pop() {
+            Some(pool) => pool,
+            None => TimestampQueryPool::new(self.device.clone(), self.queries_per_frame)?,
+        };
+
+        // The frame is built first so that the pool is put back if an error happens.
+        let mut frame = ProfilerFrame {
+            index: self.next_frame.fetch_add(1, Ordering::Relaxed) as u64,
+            pool: pool,
+            free_pools: self.free_pools.clone(),
+            reuse_pool: true,
+            debug_markers: self.debug_markers,
+            scopes: Vec::new(),
+            stack: Vec::new(),
+            finished: false,
+        };
+
+        let builder = builder
+            .reset_query_pool(frame.pool.clone(), 0, self.queries_per_frame)?;
+        let builder = frame.begin_scope(builder, "frame")?;
+
+        // From now on, the pool may be written by a command buffer that the profiler doesn't know
+        // about until the frame is passed to `end_frame`.
+        frame.reuse_pool = false;
+        Ok((frame, builder))
+    }
+
+    /// Registers a frame whose command buffer has been submitted.
+    ///
+    /// `future` must be the future that signals a fence once the command buffer that was
+    /// profiled by `frame` has finished executing.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `finish` hasn't been called on the frame.
+    ///
+    pub fn end_frame<F>(&self, mut frame: ProfilerFrame, future: Arc<FenceSignalFuture<F>>)
+        where F: GpuFuture + Send + 'static
+    {
+        assert!(frame.finished);
+        // The frame is only destroyed once its fence has been signaled, or when the profiler is
+        // destroyed.
+        frame.reuse_pool = true;
+        self.pending.lock().unwrap().push((frame, Box::new(future)));
+    }
+
+    /// Returns the timings of the frames whose fence has been signaled since the last call, in
+    /// the order in which the frames were begun.
+    ///
+    /// Doesn't block. Frames whose fence hasn't been signaled yet are returned by a later call.
+    ///
+    /// If reading the timings of a frame fails, the frame is kept and the error is returned. The
+    /// timings that were already read by the same call are returned first, and the error is
+    /// returned by the next call.
+    pub fn poll(&self) -> Result<Vec<FrameTimings>, ProfilerError> {
+        let mut pending = self.pending.lock().unwrap();
+        let mut output = Vec::new();
+
+        let mut n = 0;
+        while n < pending.len() {
+            let result = match pending[n].1.is_signaled() {
+                Ok(false) => {
+                    n += 1;
+                    continue;
+                },
+                Ok(true) => self.resolve(&pending[n].0),
+                Err(err) => Err(err.into()),
+            };
+
+            match result {
+                Ok(timings) => {
+                    // The pool of the frame is put back in the list of free pools when it is
+                    // dropped.
+                    pending.remove(n);
+                    output.push(timings);
+                },
+                Err(_) if !output.is_empty() => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        output.sort_by_key(|f| f.index);
+        Ok(output)
+    }
+
+    // Reads the timestamps of a frame whose command buffer has finished executing.
+    fn resolve(&self, frame: &ProfilerFrame) -> Result<FrameTimings, ProfilerError> {
+        let num_queries = frame.scopes.len() as u32 * 2;
+        let mut timestamps = vec![0u64; num_queries as usize];
+        let flags = QueryResultFlags {
+            wait: true,
+            ..QueryResultFlags::none()
+        };
+        frame
+            .pool
+            .get_results(0, num_queries, &mut timestamps, flags)?;
+
+        let to_ns = |ticks: u64| (ticks as f64 * self.timestamp_period as f64) as u64;
+
+        let mut timings: Vec<Option<ScopeTimings>> = frame
+            .scopes
+            .iter()
+            .enumerate()
+            .map(|(num, scope)| {
+                let begin = timestamps[num * 2] & self.timestamp_mask;
+                let end = timestamps[num * 2 + 1] & self.timestamp_mask;
+                Some(ScopeTimings {
+                         name: scope.name.clone(),
+                         start_ns: to_ns(begin),
+                         duration_ns: to_ns(end.wrapping_sub(begin) & self.timestamp_mask),
+                         children: Vec::new(),
+                     })
+            })
+            .collect();
+
+        // Scopes are always begun after their parent, so moving each scope into its parent in
+        // reverse order builds the tree bottom-up while keeping the children in order.
+        for num in (1 .. frame.scopes.len()).rev() {
+            let parent = frame.scopes[num].parent.unwrap();
+            let child = timings[num].take().unwrap();
+            timings[parent].as_mut().unwrap().children.insert(0, child);
+        }
+
+        Ok(FrameTimings {
+               index: frame.index,
+               root: timings[0].take().unwrap(),
+           })
+    }
+}
+
+// Type-erased access to a `FenceSignalFuture`.
+trait FenceStatus {
+    fn is_signaled(&self) -> Result<bool, OomError>;
+}
+
+impl<F> FenceStatus for Arc<FenceSignalFuture<F>>
+    where F: GpuFuture
+{
+    #[inline]
+    fn is_signaled(&self) -> Result<bool, OomError> {
+        FenceSignalFuture::is_signaled(&**self)
+    }
+}
+
+/// Frame being profiled. Created with `GpuProfiler::begin_frame`.
+///
+/// If the frame is dropped without being passed to `GpuProfiler::end_frame`, its queries are not
+/// reused by later frames, since the command buffer that writes them may still be executing.
+pub struct ProfilerFrame {
+    index: u64,
+    pool: Arc<TimestampQueryPool>,
+    // Where to put back `pool` when the frame is destroyed.
+    free_pools: Arc<Mutex<Vec<Arc<TimestampQueryPool>>>>,
+    // True if `pool` can be put back when the frame is destroyed. False while the pool may be
+    // written by a command buffer that hasn't been passed to `end_frame`.
+    reuse_pool: bool,
+    debug_markers: bool,
+    // List of scopes in the order in which they were begun. Scope `n` uses the queries `2 * n`
+    // and `2 * n + 1`.
+    scopes: Vec<RecordedScope>,
+    // Indices within `scopes` of the scopes that are open.
+    stack: Vec<usize>,
+    finished: bool,
+}
+
+struct RecordedScope {
+    name: String,
+    parent: Option<usize>,
+}
+
+impl ProfilerFrame {
+    /// Returns the index of the frame. Frames are numbered in the order in which they are begun.
+    #[inline]
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Opens a scope named `name` by adding to `builder` a command that writes a timestamp.
+    ///
+    /// The scope must be closed with `end_scope`. Scopes can be nested, and can be opened and
+    /// closed inside or outside of render passes.
+    pub fn begin_scope<P>(&mut self, builder: AutoCommandBufferBuilder<P>, name: &str)
+                          -> Result<AutoCommandBufferBuilder<P>, ProfilerError> {
+        assert!(!self.finished);
+
+        let query = self.scopes.len() as u32 * 2;
+        if query + 2 > self.pool.num_slots() {
+            return Err(ProfilerError::TooManyScopes);
+        }
+
+        let c_name = match CString::new(name) {
+            Ok(n) => n,
+            Err(_) => return Err(ProfilerError::InvalidName),
+        };
+
+        let stage = PipelineStages {
+            top_of_pipe: true,
+            ..PipelineStages::none()
+        };
+        let mut builder = builder.write_timestamp(self.pool.clone(), query, stage)?;
+        if self.debug_markers {
+            builder = builder.debug_marker_begin(&c_name, [0.0; 4])?;
+        }
+
+        self.scopes.push(RecordedScope {
+                             name: name.to_owned(),
+                             parent: self.stack.last().cloned(),
+                         });
+        self.stack.push(self.scopes.len() - 1);
+        Ok(builder)
+    }
+
+    /// Closes the scope that was opened by the last call to `begin_scope`.
+    pub fn end_scope<P>(&mut self, builder: AutoCommandBufferBuilder<P>)
+                        -> Result<AutoCommandBufferBuilder<P>, ProfilerError> {
+        // The first scope of the stack is the whole frame, which is closed by `finish`.
+        if self.stack.len() <= 1 {
+            return Err(ProfilerError::NoActiveScope);
+        }
+
+        self.close_scope(builder)
+    }
+
+    /// Closes the scope of the whole frame. Must be called after all the scopes have been closed
+    /// and before the command buffer is built.
+    pub fn finish<P>(&mut self, builder: AutoCommandBufferBuilder<P>)
+                     -> Result<AutoCommandBufferBuilder<P>, ProfilerError> {
+        if self.stack.len() != 1 {
+            return Err(ProfilerError::ScopesStillOpen);
+        }
+
+        let builder = self.close_scope(builder)?;
+        self.finished = true;
+        Ok(builder)
+    }
+
+    fn close_scope<P>(&mut self, builder: AutoCommandBufferBuilder<P>)
+                      -> Result<AutoCommandBufferBuilder<P>, ProfilerError> {
+        let scope = *self.stack.last().unwrap();
+
+        let mut builder = builder;
+        if self.debug_markers {
+            builder = builder.debug_marker_end()?;
+        }
+
+        let stage = PipelineStages {
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+        let builder = builder
+            .write_timestamp(self.pool.clone(), scope as u32 * 2 + 1, stage)?;
+
+        self.stack.pop();
+        Ok(builder)
+    }
+}
+
+impl Drop for ProfilerFrame {
+    #[inline]
+    fn drop(&mut self) {
+        if !self.reuse_pool {
+            return;
+        }
+
+        // The queries are reset by the next frame that uses the pool, so it can be reused even if
+        // they have been written.
+        if let Ok(mut free_pools) = self.free_pools.lock() {
+            free_pools.push(self.pool.clone());
+        }
+    }
+}
+
+/// Timings of a profiled frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameTimings {
+    /// Index of the frame, in the order in which frames were begun.
+    pub index: u64,
+    /// Scope that covers the whole frame, named `"frame"`.
+    pub root: ScopeTimings,
+}
+
+impl FrameTimings {
+    /// Returns the timings of the frame in the Chrome trace event format.
+    ///
+    /// Equivalent to calling `chrome_trace` with only this frame.
+    #[inline]
+    pub fn to_chrome_trace(&self) -> String {
+        chrome_trace(Some(self))
+    }
+}
+
+/// Timings of a scope and of its nested scopes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeTimings {
+    /// Name passed to `begin_scope`.
+    pub name: String,
+    /// Time at which the scope started, in nanoseconds. The origin is unspecified, but is the
+    /// same for all the frames of a profiler until the GPU clock wraps around.
+    pub start_ns: u64,
+    /// Time spent between the start and the end of the scope, in nanoseconds.
+    pub duration_ns: u64,
+    /// Scopes that were opened inside of this one, in order.
+    pub children: Vec<ScopeTimings>,
+}
+
+/// Turns a list of frame timings into a JSON document in the Chrome trace event format.
+///
+/// The output can be loaded in `chrome://tracing` or in any tool that supports this format. Each
+/// scope is a complete event (`"ph": "X"`) whose `args` contain the index of its frame.
+pub fn chrome_trace<'a, I>(frames: I) -> String
+    where I: IntoIterator<Item = &'a FrameTimings>
+{
+    let mut output = String::from("{\"traceEvents\":[");
+    let mut first = true;
+
+    for frame in frames {
+        write_chrome_trace_events(&mut output, &mut first, frame.index, &frame.root);
+    }
+
+    output.push_str("]}");
+    output
+}
+
+// Appends the events of a scope and of its children.
+fn write_chrome_trace_events(output: &mut String, first: &mut bool, frame: u64,
+                             scope: &ScopeTimings) {
+    if !*first {
+        output.push(',');
+    }
+    *first = false;
+
+    output.push_str("{\"name\":\"");
+    for c in scope.name.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(output, "\\u{:04x}", c as u32).unwrap();
+            },
+            c => output.push(c),
+        }
+    }
+
+    // Timestamps of the Chrome trace format are in microseconds.
+    write!(output,
+           "\",\"cat\":\"gpu\",\"ph\":\"X\",\"ts\":{}.{:03},\"dur\":{}.{:03},\"pid\":0,\"tid\":0,\
+            \"args\":{{\"frame\":{}}}}}",
+           scope.start_ns / 1000,
+           scope.start_ns % 1000,
+           scope.duration_ns / 1000,
+           scope.duration_ns % 1000,
+           frame)
+        .unwrap();
+
+    for child in &scope.children {
+        write_chrome_trace_events(output, first, frame, child);
+    }
+}
+
+/// Error that can happen when using a `GpuProfiler`.
+#[derive(Debug, Clone)]
+pub enum ProfilerError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The queue family doesn't support timestamps.
+    TimestampsNotSupported,
+    /// The frame already contains the maximum number of scopes.
+    TooManyScopes,
+    /// The name of a scope contains a nul character.
+    InvalidName,
+    /// Tried to end a scope while no scope was open.
+    NoActiveScope,
+    /// Tried to finish a frame while some scopes were still open.
+    ScopesStillOpen,
+    /// Error while resetting the queries of a frame.
+    ResetQueryPoolError(ResetQueryPoolError),
+    /// Error while writing a timestamp.
+    WriteTimestampError(WriteTimestampError),
+    /// Error while adding a debug marker.
+    DebugMarkerError(DebugMarkerError),
+    /// Error while reading the timestamps of a frame.
+    GetResultsError(GetResultsError),
+}
+
+impl error::Error for ProfilerError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ProfilerError::OomError(_) => "not enough memory available",
+            ProfilerError::TimestampsNotSupported => {
+                "the queue family doesn't support timestamps"
+            },
+            ProfilerError::TooManyScopes => {
+                "the frame already contains the maximum number of scopes"
+            },
+            ProfilerError::InvalidName => "the name of a scope contains a nul character",
+            ProfilerError::NoActiveScope => "tried to end a scope while no scope was open",
+            ProfilerError::ScopesStillOpen => {
+                "tried to finish a frame while some scopes were still open"
+            },
+            ProfilerError::ResetQueryPoolError(_) => {
+                "error while resetting the queries of a frame"
+            },
+            ProfilerError::WriteTimestampError(_) => "error while writing a timestamp",
+            ProfilerError::DebugMarkerError(_) => "error while adding a debug marker",
+            ProfilerError::GetResultsError(_) => "error while reading the timestamps of a frame",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ProfilerError::OomError(ref err) => Some(err),
+            ProfilerError::ResetQueryPoolError(ref err) => Some(err),
+            ProfilerError::WriteTimestampError(ref err) => Some(err),
+            ProfilerError::DebugMarkerError(ref err) => Some(err),
+            ProfilerError::GetResultsError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProfilerError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ProfilerError {
+    #[inline]
+    fn from(err: OomError) -> ProfilerError {
+        ProfilerError::OomError(err)
+    }
+}
+
+impl From<ResetQueryPoolError> for ProfilerError {
+    #[inline]
+    fn from(err: ResetQueryPoolError) -> ProfilerError {
+        ProfilerError::ResetQueryPoolError(err)
+    }
+}
+
+impl From<WriteTimestampError> for ProfilerError {
+    #[inline]
+    fn from(err: WriteTimestampError) -> ProfilerError {
+        ProfilerError::WriteTimestampError(err)
+    }
+}
+
+impl From<DebugMarkerError> for ProfilerError {
+    #[inline]
+    fn from(err: DebugMarkerError) -> ProfilerError {
+        ProfilerError::DebugMarkerError(err)
+    }
+}
+
+impl From<GetResultsError> for ProfilerError {
+    #[inline]
+    fn from(err: GetResultsError) -> ProfilerError {
+        ProfilerError::GetResultsError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use command_buffer::profiler::FrameTimings;
+    use command_buffer::profiler::GpuProfiler;
+    use command_buffer::profiler::ProfilerError;
+    use command_buffer::profiler::ScopeTimings;
+    use command_buffer::profiler::chrome_trace;
+    use query::GetResultsError;
+    use sync::GpuFuture;
+
+    #[test]
+    fn nested_scopes() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let profiler = GpuProfiler::new(device.clone(), queue.family(), 8).unwrap();
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+        let (mut frame, builder) = profiler.begin_frame(builder).unwrap();
+        let builder = frame.begin_scope(builder, "a").unwrap();
+        let builder = frame.end_scope(builder).unwrap();
+        let builder = frame.begin_scope(builder, "b").unwrap();
+        let builder = frame.begin_scope(builder, "c").unwrap();
+        let builder = frame.end_scope(builder).unwrap();
+        let builder = frame.end_scope(builder).unwrap();
+        let builder = frame.finish(builder).unwrap();
+
+        let future = builder
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap();
+        let future = Arc::new(future);
+        profiler.end_frame(frame, future.clone());
+        future.wait(None).unwrap();
+
+        let timings = profiler.poll().unwrap();
+        assert_eq!(timings.len(), 1);
+        assert!(profiler.poll().unwrap().is_empty());
+
+        // The mock advances its clock by 1000 ticks of 1 ns at each timestamp.
+        let root = &timings[0].root;
+        assert_eq!(root.name, "frame");
+        assert_eq!((root.start_ns, root.duration_ns), (1000, 7000));
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].name, "a");
+        assert_eq!((root.children[0].start_ns, root.children[0].duration_ns), (2000, 1000));
+        assert_eq!(root.children[1].name, "b");
+        assert_eq!((root.children[1].start_ns, root.children[1].duration_ns), (4000, 3000));
+        assert_eq!(root.children[1].children.len(), 1);
+        assert_eq!(root.children[1].children[0].name, "c");
+        assert_eq!(root.children[1].children[0].start_ns, 5000);
+    }
+
+    #[test]
+    fn scope_errors() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let profiler = GpuProfiler::new(device.clone(), queue.family(), 1).unwrap();
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+        let (mut frame, builder) = profiler.begin_frame(builder).unwrap();
+
+        let builder = match frame.end_scope(builder) {
+            Err(ProfilerError::NoActiveScope) => {
+                AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap()
+            },
+            _ => panic!(),
+        };
+
+        let builder = frame.begin_scope(builder, "a").unwrap();
+        match frame.begin_scope(builder, "b") {
+            Err(ProfilerError::TooManyScopes) => (),
+            _ => panic!(),
+        }
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+        match frame.finish(builder) {
+            Err(ProfilerError::ScopesStillOpen) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn failed_resolve_keeps_frame() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let profiler = GpuProfiler::new(device.clone(), queue.family(), 1).unwrap();
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+        let (mut frame, builder) = profiler.begin_frame(builder).unwrap();
+        let _ = frame.finish(builder).unwrap();
+
+        // The fence belongs to another command buffer, so the queries are never written.
+        let future = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap();
+        let future = Arc::new(future);
+        profiler.end_frame(frame, future.clone());
+        future.wait(None).unwrap();
+
+        for _ in 0 .. 2 {
+            match profiler.poll() {
+                Err(ProfilerError::GetResultsError(GetResultsError::QueryNotEnded { .. })) => (),
+                _ => panic!(),
+            }
+        }
+        assert_eq!(profiler.pending.lock().unwrap().len(), 1);
+        assert!(profiler.free_pools.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn dropped_frame_keeps_pool() {
+        let (loader, device, queue) = mock_dev_and_queue!();
+        let profiler = GpuProfiler::new(device.clone(), queue.family(), 1).unwrap();
+
+        // The command buffer of a frame that isn't passed to `end_frame` may still be executing.
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+        let (frame, _) = profiler.begin_frame(builder).unwrap();
+        drop(frame);
+        assert!(profiler.free_pools.lock().unwrap().is_empty());
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+        let (mut frame, builder) = profiler.begin_frame(builder).unwrap();
+        let future = frame
+            .finish(builder)
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap();
+        let future = Arc::new(future);
+        profiler.end_frame(frame, future.clone());
+        assert!(profiler.free_pools.lock().unwrap().is_empty());
+
+        future.wait(None).unwrap();
+        assert_eq!(profiler.poll().unwrap().len(), 1);
+        assert_eq!(profiler.free_pools.lock().unwrap().len(), 1);
+
+        // The next frame reuses the pool instead of creating a new one.
+        loader.take_calls();
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+        let _ = profiler.begin_frame(builder).unwrap();
+        assert!(!loader
+                    .call_names()
+                    .iter()
+                    .any(|&name| name == "vkCreateQueryPool"));
+    }
+
+    #[test]
+    fn chrome_trace_format() {
+        let frame = FrameTimings {
+            index: 3,
+            root: ScopeTimings {
+                name: "frame".to_owned(),
+                start_ns: 1500,
+                duration_ns: 20000,
+                children: vec![
+                    ScopeTimings {
+                        name: "a \"quoted\" name".to_owned(),
+                        start_ns: 2000,
+                        duration_ns: 250,
+                        children: Vec::new(),
+                    },
+                ],
+            },
+        };
+
+        assert_eq!(chrome_trace(&[frame.clone()]), frame.to_chrome_trace());
+        assert_eq!(frame.to_chrome_trace(),
+                   "{\"traceEvents\":[\
+                    {\"name\":\"frame\",\"cat\":\"gpu\",\"ph\":\"X\",\"ts\":1.500,\
+                    \"dur\":20.000,\"pid\":0,\"tid\":0,\"args\":{\"frame\":3}},\
+                    {\"name\":\"a \\\"quoted\\\" name\",\"cat\":\"gpu\",\"ph\":\"X\",\
+                    \"ts\":2.000,\"dur\":0.250,\"pid\":0,\"tid\":0,\"args\":{\"frame\":3}}\
+                    ]}");
+    }
+}
@@ -105,6 +105,12 @@ impl StateCacher {
         cmp!(line_width);
         cmp!(viewports);
         cmp!(scissors);
+        cmp!(depth_bias);
+        cmp!(depth_bounds);
+        cmp!(compare_mask);
+        cmp!(write_mask);
+        cmp!(reference);
+        cmp!(blend_constants);
 
         incoming
     }
@@ -352,7 +358,46 @@ impl<'s> StateCacherVertexBuffers<'s> {
 mod tests {
     use buffer::BufferUsage;
     use buffer::CpuAccessibleBuffer;
+    use command_buffer::DynamicState;
+    use command_buffer::DynamicStencilValue;
     use command_buffer::state_cacher::StateCacher;
+    use pipeline::raster::DepthBias;
+
+    #[test]
+    fn dynamic_state_caching() {
+        let mut cacher = StateCacher::new();
+
+        let state = DynamicState {
+            depth_bias: Some(DepthBias {
+                                 constant_factor: 1.0,
+                                 clamp: 0.0,
+                                 slope_factor: 2.0,
+                             }),
+            reference: Some(DynamicStencilValue { front: 1, back: 2 }),
+            blend_constants: Some([0.0, 0.5, 0.5, 1.0]),
+            ..DynamicState::none()
+        };
+
+        let out = cacher.dynamic_state(state.clone());
+        assert!(out.depth_bias.is_some());
+        assert!(out.reference.is_some());
+        assert!(out.blend_constants.is_some());
+
+        let out = cacher.dynamic_state(DynamicState {
+                                           reference: Some(DynamicStencilValue {
+                                                               front: 1,
+                                                               back: 3,
+                                                           }),
+                                           ..state.clone()
+                                       });
+        assert!(out.depth_bias.is_none());
+        assert_eq!(out.reference, Some(DynamicStencilValue { front: 1, back: 3 }));
+        assert!(out.blend_constants.is_none());
+
+        cacher.invalidate();
+        let out = cacher.dynamic_state(state);
+        assert!(out.depth_bias.is_some());
+    }
 
     #[test]
     fn vb_caching_single() {
@@ -121,6 +121,44 @@ impl<'a> SubmitBindSparseBuilder<'a> {
         self.fence = fence.internal_object();
     }
 
+    /// Adds a semaphore to be waited upon before the first batch is executed.
+    ///
+    /// Since batches start execution in order, the wait applies to all the batches of the
+    /// builder. Adds an empty batch if the builder doesn't have any.
+    ///
+    /// # Safety
+    ///
+    /// Same as `SubmitBindSparseBatchBuilder::add_wait_semaphore`.
+    ///
+    #[inline]
+    pub unsafe fn add_wait_semaphore(&mut self, semaphore: &'a Semaphore) {
+        if self.infos.is_empty() {
+            self.infos.push(SubmitBindSparseBatchBuilder::new());
+        }
+
+        self.infos[0].add_wait_semaphore(semaphore);
+    }
+
+    /// Adds a semaphore that is going to be signaled at the end of the last batch.
+    ///
+    /// The signal operations of `vkQueueBindSparse` include all the sparse binding operations
+    /// that occur earlier in submission order, therefore the semaphore is only signaled once all
+    /// the batches have completed. Adds an empty batch if the builder doesn't have any.
+    ///
+    /// # Safety
+    ///
+    /// Same as `SubmitBindSparseBatchBuilder::add_signal_semaphore`.
+    ///
+    #[inline]
+    pub unsafe fn add_signal_semaphore(&mut self, semaphore: &'a Semaphore) {
+        if self.infos.is_empty() {
+            self.infos.push(SubmitBindSparseBatchBuilder::new());
+        }
+
+        let last = self.infos.len() - 1;
+        self.infos[last].add_signal_semaphore(semaphore);
+    }
+
     /// Attempts to merge this builder with another one.
     ///
     /// If both builders have a fence already set, then this function will return `other` as an
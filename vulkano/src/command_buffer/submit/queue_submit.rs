@@ -27,31 +27,72 @@ use check_errors;
 use vk;
 
 /// Prototype for a submission that executes command buffers.
+///
+/// The submission is made of one or more batches, each with its own semaphores to wait upon,
+/// command buffers and semaphores to signal. Batches start execution in order, but can finish in
+/// a different order. A new batch is automatically started when a semaphore wait is added after
+/// command buffers or semaphore signals, or when a command buffer is added after semaphore
+/// signals. Merging two builders concatenates their batches, except that semaphore waits that
+/// aren't followed by anything are moved to the next batch.
 // TODO: example here
 #[derive(Debug)]
 pub struct SubmitCommandBufferBuilder<'a> {
-    wait_semaphores: SmallVec<[vk::Semaphore; 16]>,
-    destination_stages: SmallVec<[vk::PipelineStageFlags; 8]>,
-    signal_semaphores: SmallVec<[vk::Semaphore; 16]>,
-    command_buffers: SmallVec<[vk::CommandBuffer; 4]>,
+    batches: SmallVec<[SubmitCommandBufferBatch; 1]>,
     fence: vk::Fence,
     marker: PhantomData<&'a ()>,
 }
 
+// A single batch of a submission. Corresponds to a `VkSubmitInfo`.
+#[derive(Debug)]
+struct SubmitCommandBufferBatch {
+    wait_semaphores: SmallVec<[vk::Semaphore; 8]>,
+    destination_stages: SmallVec<[vk::PipelineStageFlags; 8]>,
+    command_buffers: SmallVec<[vk::CommandBuffer; 4]>,
+    signal_semaphores: SmallVec<[vk::Semaphore; 8]>,
+}
+
+impl SubmitCommandBufferBatch {
+    #[inline]
+    fn new() -> SubmitCommandBufferBatch {
+        SubmitCommandBufferBatch {
+            wait_semaphores: SmallVec::new(),
+            destination_stages: SmallVec::new(),
+            command_buffers: SmallVec::new(),
+            signal_semaphores: SmallVec::new(),
+        }
+    }
+}
+
 impl<'a> SubmitCommandBufferBuilder<'a> {
     /// Builds a new empty `SubmitCommandBufferBuilder`.
     #[inline]
     pub fn new() -> SubmitCommandBufferBuilder<'a> {
         SubmitCommandBufferBuilder {
-            wait_semaphores: SmallVec::new(),
-            destination_stages: SmallVec::new(),
-            signal_semaphores: SmallVec::new(),
-            command_buffers: SmallVec::new(),
+            batches: SmallVec::new(),
             fence: 0,
             marker: PhantomData,
         }
     }
 
+    // Returns the last batch. Starts a new one if there is no batch yet, or if `start_new`
+    // returns true for the last batch.
+    #[inline]
+    fn last_batch<F>(&mut self, start_new: F) -> &mut SubmitCommandBufferBatch
+        where F: FnOnce(&SubmitCommandBufferBatch) -> bool
+    {
+        if self.batches.last().map(start_new).unwrap_or(true) {
+            self.batches.push(SubmitCommandBufferBatch::new());
+        }
+
+        self.batches.last_mut().unwrap()
+    }
+
+    /// Returns the number of batches of the submission.
+    #[inline]
+    pub fn num_batches(&self) -> usize {
+        self.batches.len()
+    }
+
     /// Returns true if this builder will signal a fence when submitted.
     ///
     /// # Example
@@ -122,6 +163,9 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     /// Only the given `stages` of the command buffers added afterwards will wait upon
     /// the semaphore. Other stages not included in `stages` can execute before waiting.
     ///
+    /// If command buffers or semaphore signals have already been added to the current batch, a
+    /// new batch is started so that they don't wait upon the semaphore.
+    ///
     /// # Safety
     ///
     /// - The stages must be supported by the device.
@@ -143,13 +187,19 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     pub unsafe fn add_wait_semaphore(&mut self, semaphore: &'a Semaphore, stages: PipelineStages) {
         debug_assert!(stages.into_vulkan_bits() != 0);
         // TODO: debug assert that the device supports the stages
-        self.wait_semaphores.push(semaphore.internal_object());
-        self.destination_stages.push(stages.into_vulkan_bits());
+        let batch = self.last_batch(|b| {
+                                        !b.command_buffers.is_empty() ||
+                                            !b.signal_semaphores.is_empty()
+                                    });
+        batch.wait_semaphores.push(semaphore.internal_object());
+        batch.destination_stages.push(stages.into_vulkan_bits());
     }
 
     /// Adds a command buffer that is executed as part of this command.
     ///
-    /// The command buffers are submitted in the order in which they are added.
+    /// The command buffers are submitted in the order in which they are added. If semaphore
+    /// signals have already been added to the current batch, a new batch is started so that the
+    /// semaphores don't wait for this command buffer.
     ///
     /// # Safety
     ///
@@ -167,7 +217,9 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     ///
     #[inline]
     pub unsafe fn add_command_buffer<P>(&mut self, command_buffer: &'a UnsafeCommandBuffer<P>) {
-        self.command_buffers.push(command_buffer.internal_object());
+        self.last_batch(|b| !b.signal_semaphores.is_empty())
+            .command_buffers
+            .push(command_buffer.internal_object());
     }
 
     /// Returns the number of semaphores to signal.
@@ -175,10 +227,14 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     /// In other words, this is the number of times `add_signal_semaphore` has been called.
     #[inline]
     pub fn num_signal_semaphores(&self) -> usize {
-        self.signal_semaphores.len()
+        self.batches
+            .iter()
+            .map(|b| b.signal_semaphores.len())
+            .sum()
     }
 
-    /// Adds a semaphore that is going to be signaled at the end of the submission.
+    /// Adds a semaphore that is going to be signaled once all the command buffers that have been
+    /// added so far have finished executing.
     ///
     /// # Safety
     ///
@@ -192,7 +248,9 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
     ///
     #[inline]
     pub unsafe fn add_signal_semaphore(&mut self, semaphore: &'a Semaphore) {
-        self.signal_semaphores.push(semaphore.internal_object());
+        self.last_batch(|_| false)
+            .signal_semaphores
+            .push(semaphore.internal_object());
     }
 
     /// Submits the command buffer to the given queue.
@@ -205,39 +263,67 @@ impl<'a> SubmitCommandBufferBuilder<'a> {
             let vk = queue.device().pointers();
             let queue = queue.internal_object_guard();
 
-            debug_assert_eq!(self.wait_semaphores.len(), self.destination_stages.len());
-
-            let batch = vk::SubmitInfo {
-                sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
-                pNext: ptr::null(),
-                waitSemaphoreCount: self.wait_semaphores.len() as u32,
-                pWaitSemaphores: self.wait_semaphores.as_ptr(),
-                pWaitDstStageMask: self.destination_stages.as_ptr(),
-                commandBufferCount: self.command_buffers.len() as u32,
-                pCommandBuffers: self.command_buffers.as_ptr(),
-                signalSemaphoreCount: self.signal_semaphores.len() as u32,
-                pSignalSemaphores: self.signal_semaphores.as_ptr(),
-            };
-
-            check_errors(vk.QueueSubmit(*queue, 1, &batch, self.fence))?;
+            let batches = self.batches
+                .iter()
+                .map(|batch| {
+                    debug_assert_eq!(batch.wait_semaphores.len(), batch.destination_stages.len());
+
+                    vk::SubmitInfo {
+                        sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
+                        pNext: ptr::null(),
+                        waitSemaphoreCount: batch.wait_semaphores.len() as u32,
+                        pWaitSemaphores: batch.wait_semaphores.as_ptr(),
+                        pWaitDstStageMask: batch.destination_stages.as_ptr(),
+                        commandBufferCount: batch.command_buffers.len() as u32,
+                        pCommandBuffers: batch.command_buffers.as_ptr(),
+                        signalSemaphoreCount: batch.signal_semaphores.len() as u32,
+                        pSignalSemaphores: batch.signal_semaphores.as_ptr(),
+                    }
+                })
+                .collect::<SmallVec<[_; 4]>>();
+
+            check_errors(vk.QueueSubmit(*queue,
+                                        batches.len() as u32,
+                                        batches.as_ptr(),
+                                        self.fence))?;
             Ok(())
         }
     }
 
     /// Merges this builder with another builder.
     ///
+    /// The batches of `other` are submitted after the batches of `self`. Since each batch keeps
+    /// its own semaphores, the command buffers of `self` don't wait upon the semaphores of
+    /// `other`.
+    ///
+    /// If the last batch of `self` only waits upon semaphores, these waits are added to the first
+    /// batch of `other` instead. Otherwise they wouldn't apply to anything, and the command
+    /// buffers of `other` could start before the semaphores are signaled.
+    ///
     /// # Panic
     ///
     /// Panics if both builders have a fence already set.
-    // TODO: create multiple batches instead
     pub fn merge(mut self, other: Self) -> Self {
         assert!(self.fence == 0 || other.fence == 0,
                 "Can't merge two queue submits that both have a fence");
 
-        self.wait_semaphores.extend(other.wait_semaphores);
-        self.destination_stages.extend(other.destination_stages); // TODO: meh? will be solved if we submit multiple batches
-        self.signal_semaphores.extend(other.signal_semaphores);
-        self.command_buffers.extend(other.command_buffers);
+        let mut other_batches = other.batches.into_iter();
+
+        let last_waits_only = self.batches
+            .last()
+            .map(|b| b.command_buffers.is_empty() && b.signal_semaphores.is_empty())
+            .unwrap_or(false);
+        if last_waits_only {
+            if let Some(first) = other_batches.next() {
+                let last = self.batches.last_mut().unwrap();
+                last.wait_semaphores.extend(first.wait_semaphores);
+                last.destination_stages.extend(first.destination_stages);
+                last.command_buffers.extend(first.command_buffers);
+                last.signal_semaphores.extend(first.signal_semaphores);
+            }
+        }
+
+        self.batches.extend(other_batches);
 
         if self.fence == 0 {
             self.fence = other.fence;
@@ -299,8 +385,13 @@ impl From<Error> for SubmitCommandBufferError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
     use std::time::Duration;
     use sync::Fence;
+    use testing::Call;
+    use testing::MockLoader;
+    use testing::SubmitBatch;
 
     #[test]
     fn empty_submit() {
@@ -358,4 +449,129 @@ mod tests {
             });
         }
     }
+
+    #[test]
+    fn batches() {
+        unsafe {
+            let (loader, device, queue) = mock_dev_and_queue!();
+
+            let sem1 = Semaphore::alloc(device.clone()).unwrap();
+            let sem2 = Semaphore::alloc(device.clone()).unwrap();
+            let stages = PipelineStages {
+                all_commands: true,
+                ..PipelineStages::none()
+            };
+
+            let mut builder = SubmitCommandBufferBuilder::new();
+            assert_eq!(builder.num_batches(), 0);
+            builder.add_wait_semaphore(&sem1, stages);
+            builder.add_wait_semaphore(&sem2, stages);
+            assert_eq!(builder.num_batches(), 1);
+            builder.add_signal_semaphore(&sem1);
+            builder.add_signal_semaphore(&sem2);
+            assert_eq!(builder.num_batches(), 1);
+            assert_eq!(builder.num_signal_semaphores(), 2);
+
+            // A wait after a signal must not apply to what's before the signal.
+            builder.add_wait_semaphore(&sem1, stages);
+            assert_eq!(builder.num_batches(), 2);
+            assert_eq!(builder.num_signal_semaphores(), 2);
+
+            let stages = stages.into_vulkan_bits();
+            let batches = submitted_batches(&loader, builder, &queue);
+            assert_eq!(batches.len(), 2);
+            assert_eq!(batches[0].wait_semaphores,
+                       vec![(sem1.internal_object(), stages), (sem2.internal_object(), stages)]);
+            assert_eq!(batches[0].signal_semaphores,
+                       vec![sem1.internal_object(), sem2.internal_object()]);
+            assert_eq!(batches[1].wait_semaphores, vec![(sem1.internal_object(), stages)]);
+            assert!(batches[1].signal_semaphores.is_empty());
+        }
+    }
+
+    // Submits `builder` and returns the batches passed to `vkQueueSubmit`.
+    fn submitted_batches(loader: &MockLoader, builder: SubmitCommandBufferBuilder, queue: &Queue)
+                         -> Vec<SubmitBatch> {
+        loader.take_calls();
+        builder.submit(queue).unwrap();
+        match loader.take_calls().pop() {
+            Some(Call::QueueSubmit { batches, .. }) => batches,
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn merge_keeps_batches() {
+        unsafe {
+            let (loader, device, queue) = mock_dev_and_queue!();
+
+            let sem1 = Semaphore::alloc(device.clone()).unwrap();
+            let sem2 = Semaphore::alloc(device.clone()).unwrap();
+            let sem3 = Semaphore::alloc(device.clone()).unwrap();
+            let vertex = PipelineStages {
+                vertex_shader: true,
+                ..PipelineStages::none()
+            };
+            let fragment = PipelineStages {
+                fragment_shader: true,
+                ..PipelineStages::none()
+            };
+
+            let mut builder1 = SubmitCommandBufferBuilder::new();
+            builder1.add_wait_semaphore(&sem1, vertex);
+            builder1.add_signal_semaphore(&sem2);
+            let mut builder2 = SubmitCommandBufferBuilder::new();
+            builder2.add_wait_semaphore(&sem2, fragment);
+            builder2.add_signal_semaphore(&sem3);
+
+            let builder = builder1.merge(builder2);
+            assert_eq!(builder.num_batches(), 2);
+
+            let batches = submitted_batches(&loader, builder, &queue);
+            assert_eq!(batches.len(), 2);
+            assert_eq!(batches[0].wait_semaphores,
+                       vec![(sem1.internal_object(), vertex.into_vulkan_bits())]);
+            assert_eq!(batches[0].signal_semaphores, vec![sem2.internal_object()]);
+            assert_eq!(batches[1].wait_semaphores,
+                       vec![(sem2.internal_object(), fragment.into_vulkan_bits())]);
+            assert_eq!(batches[1].signal_semaphores, vec![sem3.internal_object()]);
+        }
+    }
+
+    #[test]
+    fn merge_moves_lone_waits() {
+        unsafe {
+            let (loader, device, queue) = mock_dev_and_queue!();
+
+            let command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+                .unwrap()
+                .build()
+                .unwrap();
+
+            // Same as what an acquired swapchain image followed by a draw produces.
+            let acquire = Semaphore::alloc(device.clone()).unwrap();
+            let present = Semaphore::alloc(device.clone()).unwrap();
+            let output = PipelineStages {
+                color_attachment_output: true,
+                ..PipelineStages::none()
+            };
+
+            let mut builder1 = SubmitCommandBufferBuilder::new();
+            builder1.add_wait_semaphore(&acquire, output);
+            let mut builder2 = SubmitCommandBufferBuilder::new();
+            builder2.add_command_buffer(command_buffer.inner());
+            builder2.add_signal_semaphore(&present);
+
+            let builder = builder1.merge(builder2);
+            assert_eq!(builder.num_batches(), 1);
+
+            let batches = submitted_batches(&loader, builder, &queue);
+            assert_eq!(batches.len(), 1);
+            assert_eq!(batches[0].wait_semaphores,
+                       vec![(acquire.internal_object(), output.into_vulkan_bits())]);
+            assert_eq!(batches[0].command_buffers,
+                       vec![command_buffer.inner().internal_object()]);
+            assert_eq!(batches[0].signal_semaphores, vec![present.internal_object()]);
+        }
+    }
 }
@@ -9,6 +9,7 @@
 
 use smallvec::SmallVec;
 
+use command_buffer::submit::SubmitBindSparseBuilder;
 use command_buffer::submit::SubmitCommandBufferBuilder;
 use command_buffer::submit::SubmitPresentBuilder;
 use sync::PipelineStages;
@@ -75,3 +76,16 @@ impl<'a> Into<SubmitPresentBuilder<'a>> for SubmitSemaphoresWaitBuilder<'a> {
         }
     }
 }
+
+impl<'a> Into<SubmitBindSparseBuilder<'a>> for SubmitSemaphoresWaitBuilder<'a> {
+    #[inline]
+    fn into(mut self) -> SubmitBindSparseBuilder<'a> {
+        unsafe {
+            let mut builder = SubmitBindSparseBuilder::new();
+            for sem in self.semaphores.drain() {
+                builder.add_wait_semaphore(sem);
+            }
+            builder
+        }
+    }
+}
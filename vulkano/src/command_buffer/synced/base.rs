@@ -19,6 +19,7 @@ use std::sync::Mutex;
 use OomError;
 use buffer::BufferAccess;
 use command_buffer::CommandBufferExecError;
+use command_buffer::QueueFamilyTransfer;
 use command_buffer::pool::CommandPool;
 use command_buffer::pool::CommandPoolAlloc;
 use command_buffer::pool::CommandPoolBuilderAlloc;
@@ -1210,6 +1211,139 @@ impl<P> SyncCommandBuffer<P> {
         }
     }
 
+    /// Builds the pipeline barriers that transfer the ownership of the resources used by the
+    /// command buffer from the queue family of `future` to the queue family of `queue`.
+    ///
+    /// Returns `None` if `future` doesn't execute on a queue of another family, or if none of the
+    /// resources that `future` gives access to were created with the exclusive sharing mode.
+    ///
+    /// > **Note**: Suitable when implementing the `CommandBuffer` trait.
+    pub fn queue_family_transfer(&self, future: &GpuFuture, queue: &Queue)
+                                 -> Option<QueueFamilyTransfer> {
+        let source = match future.queue() {
+            Some(source) => source,
+            None => return None,
+        };
+
+        if source.family().id() == queue.family().id() {
+            return None;
+        }
+
+        let families = Some((source.family().id(), queue.family().id()));
+        let (rel_src_stages, rel_src_access, rel_dst_stages, rel_dst_access) =
+            ownership_transfer_scopes(true);
+        let (acq_src_stages, acq_src_access, acq_dst_stages, acq_dst_access) =
+            ownership_transfer_scopes(false);
+
+        let commands_lock = self.commands.lock().unwrap();
+        let mut release = UnsafeCommandBufferBuilderPipelineBarrier::new();
+        let mut acquire = UnsafeCommandBufferBuilderPipelineBarrier::new();
+
+        for (key, entry) in self.resources.iter() {
+            let (command_id, resource_ty, resource_index) = match *key {
+                CbKey::Command {
+                    command_id,
+                    resource_ty,
+                    resource_index,
+                    ..
+                } => {
+                    (command_id, resource_ty, resource_index)
+                },
+                _ => unreachable!(),
+            };
+
+            match resource_ty {
+                KeyTy::Buffer => {
+                    let buf = commands_lock[command_id].buffer(resource_index);
+
+                    if !buf.inner().buffer.exclusive_sharing() {
+                        continue;
+                    }
+
+                    // Only the resources that were last used by `future` need to be transferred.
+                    if future.check_buffer_access(buf, entry.exclusive, queue).is_err() {
+                        continue;
+                    }
+
+                    unsafe {
+                        release.add_buffer_memory_barrier(buf,
+                                                          rel_src_stages,
+                                                          rel_src_access,
+                                                          rel_dst_stages,
+                                                          rel_dst_access,
+                                                          false,
+                                                          families,
+                                                          0,
+                                                          buf.size());
+                        acquire.add_buffer_memory_barrier(buf,
+                                                          acq_src_stages,
+                                                          acq_src_access,
+                                                          acq_dst_stages,
+                                                          acq_dst_access,
+                                                          false,
+                                                          families,
+                                                          0,
+                                                          buf.size());
+                    }
+                },
+
+                KeyTy::Image => {
+                    let img = commands_lock[command_id].image(resource_index);
+
+                    // If the command buffer doesn't care about the previous content of the
+                    // image, there's nothing to transfer.
+                    if !img.inner().image.exclusive_sharing() ||
+                        entry.initial_layout == ImageLayout::Undefined
+                    {
+                        continue;
+                    }
+
+                    if future
+                        .check_image_access(img, entry.initial_layout, entry.exclusive, queue)
+                        .is_err()
+                    {
+                        continue;
+                    }
+
+                    unsafe {
+                        release.add_image_memory_barrier(img,
+                                                         0 .. img.mipmap_levels(),
+                                                         0 .. img.dimensions().array_layers(),
+                                                         rel_src_stages,
+                                                         rel_src_access,
+                                                         rel_dst_stages,
+                                                         rel_dst_access,
+                                                         false,
+                                                         families,
+                                                         entry.initial_layout,
+                                                         entry.initial_layout);
+                        acquire.add_image_memory_barrier(img,
+                                                         0 .. img.mipmap_levels(),
+                                                         0 .. img.dimensions().array_layers(),
+                                                         acq_src_stages,
+                                                         acq_src_access,
+                                                         acq_dst_stages,
+                                                         acq_dst_access,
+                                                         false,
+                                                         families,
+                                                         entry.initial_layout,
+                                                         entry.initial_layout);
+                    }
+                },
+            }
+        }
+
+        if release.is_empty() {
+            return None;
+        }
+
+        Some(QueueFamilyTransfer {
+                 source,
+                 release,
+                 acquire,
+             })
+    }
+
     /// Checks whether this command buffer has access to a buffer.
     ///
     /// > **Note**: Suitable when implementing the `CommandBuffer` trait.
@@ -1258,6 +1392,45 @@ impl<P> SyncCommandBuffer<P> {
     }
 }
 
+// Returns the source stages, source access, destination stages and destination access of the
+// barrier that releases (if `release` is true) or acquires the ownership of a resource.
+//
+// The release barrier makes the writes of all the previous commands available, and the acquire
+// barrier makes them visible to all the following commands. The other halves of the scopes are
+// ignored by the implementation.
+pub(super) fn ownership_transfer_scopes(release: bool)
+    -> (PipelineStages, AccessFlagBits, PipelineStages, AccessFlagBits) {
+    let all_commands = PipelineStages {
+        all_commands: true,
+        ..PipelineStages::none()
+    };
+
+    if release {
+        (all_commands,
+         AccessFlagBits {
+             memory_write: true,
+             ..AccessFlagBits::none()
+         },
+         PipelineStages {
+             bottom_of_pipe: true,
+             ..PipelineStages::none()
+         },
+         AccessFlagBits::none())
+    } else {
+        (PipelineStages {
+             top_of_pipe: true,
+             ..PipelineStages::none()
+         },
+         AccessFlagBits::none(),
+         all_commands,
+         AccessFlagBits {
+             memory_read: true,
+             memory_write: true,
+             ..AccessFlagBits::none()
+         })
+    }
+}
+
 unsafe impl<P> DeviceOwned for SyncCommandBuffer<P> {
     #[inline]
     fn device(&self) -> &Arc<Device> {
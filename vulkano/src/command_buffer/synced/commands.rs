@@ -10,24 +10,31 @@
 use smallvec::SmallVec;
 use std::any::Any;
 use std::borrow::Cow;
+use std::ffi::CString;
 use std::mem;
 use std::ptr;
 use std::sync::Arc;
 
 use buffer::BufferAccess;
+use buffer::TypedBufferAccess;
+use command_buffer::ClearAttachment;
+use command_buffer::ClearRect;
 use command_buffer::CommandBuffer;
 use command_buffer::synced::base::Command;
 use command_buffer::synced::base::FinalCommand;
 use command_buffer::synced::base::KeyTy;
 use command_buffer::synced::base::SyncCommandBufferBuilder;
 use command_buffer::synced::base::SyncCommandBufferBuilderError;
+use command_buffer::synced::base::ownership_transfer_scopes;
 use command_buffer::sys::UnsafeCommandBufferBuilder;
 use command_buffer::sys::UnsafeCommandBufferBuilderBindVertexBuffer;
 use command_buffer::sys::UnsafeCommandBufferBuilderBufferImageCopy;
 use command_buffer::sys::UnsafeCommandBufferBuilderColorImageClear;
+use command_buffer::sys::UnsafeCommandBufferBuilderDepthStencilImageClear;
 use command_buffer::sys::UnsafeCommandBufferBuilderExecuteCommands;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageCopy;
 use command_buffer::sys::UnsafeCommandBufferBuilderImageBlit;
+use command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
 use descriptor::descriptor::DescriptorDescTy;
 use descriptor::descriptor::ShaderStages;
 use descriptor::descriptor_set::DescriptorSet;
@@ -39,15 +46,241 @@ use image::ImageAccess;
 use image::ImageLayout;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
+use pipeline::depth_stencil::StencilFaceFlags;
 use pipeline::input_assembly::IndexType;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
+use query::QueryPoolAccess;
+use query::QueryResultElement;
+use query::QueryResultFlags;
 use sampler::Filter;
 use sync::AccessFlagBits;
 use sync::Event;
 use sync::PipelineStages;
 
 impl<P> SyncCommandBufferBuilder<P> {
+    /// Adds a pipeline barrier that acquires the ownership of a buffer, in order to transfer it
+    /// from the queue family `source_family` to the queue family `destination_family`.
+    #[inline]
+    pub unsafe fn acquire_buffer_ownership<B>(&mut self, buffer: B, source_family: u32,
+                                              destination_family: u32)
+                                              -> Result<(), SyncCommandBufferBuilderError>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        struct Cmd<B> {
+            buffer: B,
+            families: (u32, u32),
+        }
+
+        impl<P, B> Command<P> for Cmd<B>
+            where B: BufferAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdPipelineBarrier"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let (src_stages, src_access, dst_stages, dst_access) =
+                    ownership_transfer_scopes(false);
+                let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+                barrier.add_buffer_memory_barrier(&self.buffer,
+                                                  src_stages,
+                                                  src_access,
+                                                  dst_stages,
+                                                  dst_access,
+                                                  false,
+                                                  Some(self.families),
+                                                  0,
+                                                  self.buffer.size());
+                out.pipeline_barrier(&barrier);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<B>(B);
+                impl<B> FinalCommand for Fin<B>
+                    where B: BufferAccess + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdPipelineBarrier"
+                    }
+                    fn buffer(&self, num: usize) -> &BufferAccess {
+                        assert_eq!(num, 0);
+                        &self.0
+                    }
+                    fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+                        "buffer".into()
+                    }
+                }
+                Box::new(Fin(self.buffer))
+            }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                assert_eq!(num, 0);
+                &self.buffer
+            }
+
+            fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+                "buffer".into()
+            }
+        }
+
+        self.append_command(Cmd {
+                                buffer: buffer,
+                                families: (source_family, destination_family),
+                            });
+        self.prev_cmd_resource(KeyTy::Buffer,
+                               0,
+                               true,
+                               PipelineStages {
+                                   all_commands: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   memory_read: true,
+                                   memory_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               ImageLayout::Undefined,
+                               ImageLayout::Undefined)?;
+        Ok(())
+    }
+
+    /// Adds a pipeline barrier that acquires the ownership of an image, in order to transfer it
+    /// from the queue family `source_family` to the queue family `destination_family`.
+    ///
+    /// The image must be in the layout returned by `initial_layout_requirement()`.
+    #[inline]
+    pub unsafe fn acquire_image_ownership<I>(&mut self, image: I, source_family: u32,
+                                             destination_family: u32)
+                                             -> Result<(), SyncCommandBufferBuilderError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        struct Cmd<I> {
+            image: I,
+            families: (u32, u32),
+            layout: ImageLayout,
+        }
+
+        impl<P, I> Command<P> for Cmd<I>
+            where I: ImageAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdPipelineBarrier"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let (src_stages, src_access, dst_stages, dst_access) =
+                    ownership_transfer_scopes(false);
+                let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+                barrier.add_image_memory_barrier(&self.image,
+                                                 0 .. self.image.mipmap_levels(),
+                                                 0 .. self.image.dimensions().array_layers(),
+                                                 src_stages,
+                                                 src_access,
+                                                 dst_stages,
+                                                 dst_access,
+                                                 false,
+                                                 Some(self.families),
+                                                 self.layout,
+                                                 self.layout);
+                out.pipeline_barrier(&barrier);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<I>(I);
+                impl<I> FinalCommand for Fin<I>
+                    where I: ImageAccess + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdPipelineBarrier"
+                    }
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        assert_eq!(num, 0);
+                        &self.0
+                    }
+                    fn image_name(&self, _: usize) -> Cow<'static, str> {
+                        "image".into()
+                    }
+                }
+                Box::new(Fin(self.image))
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                assert_eq!(num, 0);
+                &self.image
+            }
+
+            fn image_name(&self, _: usize) -> Cow<'static, str> {
+                "image".into()
+            }
+        }
+
+        let layout = image.initial_layout_requirement();
+
+        self.append_command(Cmd {
+                                image: image,
+                                families: (source_family, destination_family),
+                                layout: layout,
+                            });
+        self.prev_cmd_resource(KeyTy::Image,
+                               0,
+                               true,
+                               PipelineStages {
+                                   all_commands: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   memory_read: true,
+                                   memory_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               layout,
+                               layout)?;
+        Ok(())
+    }
+
+    /// Calls `vkCmdBeginQuery` on the builder.
+    #[inline]
+    pub unsafe fn begin_query<Q>(&mut self, pool: Arc<Q>, query: u32, precise: bool)
+        where Q: QueryPoolAccess + 'static
+    {
+        struct Cmd<Q> {
+            pool: Arc<Q>,
+            query: u32,
+            precise: bool,
+        }
+
+        impl<P, Q> Command<P> for Cmd<Q>
+            where Q: QueryPoolAccess + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdBeginQuery"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.begin_query(self.pool.inner().query(self.query).unwrap(), self.precise);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<Q>(Arc<Q>);
+                impl<Q> FinalCommand for Fin<Q>
+                    where Q: QueryPoolAccess + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdBeginQuery"
+                    }
+                }
+                Box::new(Fin(self.pool))
+            }
+        }
+
+        self.append_command(Cmd {
+                                pool,
+                                query,
+                                precise,
+                            });
+    }
+
     /// Calls `vkBeginRenderPass` on the builder.
     // TODO: it shouldn't be possible to get an error if the framebuffer checked conflicts already
     // TODO: after begin_render_pass has been called, flushing should be forbidden and an error
@@ -562,6 +795,44 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
+    /// Calls `vkCmdClearAttachments` on the builder.
+    ///
+    /// Does nothing if the list of attachments or the list of rects is empty, as it would be a
+    /// no-op and isn't a valid usage of the command anyway.
+    #[inline]
+    pub unsafe fn clear_attachments<A, R>(&mut self, attachments: A, rects: R)
+        where A: Iterator<Item = ClearAttachment> + Send + Sync + 'static,
+              R: Iterator<Item = ClearRect> + Send + Sync + 'static
+    {
+        struct Cmd<A, R> {
+            attachments: Option<A>,
+            rects: Option<R>,
+        }
+
+        impl<P, A, R> Command<P> for Cmd<A, R>
+            where A: Iterator<Item = ClearAttachment>,
+                  R: Iterator<Item = ClearRect>
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdClearAttachments"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.clear_attachments(self.attachments.take().unwrap(),
+                                      self.rects.take().unwrap());
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdClearAttachments")
+            }
+        }
+
+        self.append_command(Cmd {
+                                attachments: Some(attachments),
+                                rects: Some(rects),
+                            });
+    }
+
     /// Calls `vkCmdClearColorImage` on the builder.
     ///
     /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
@@ -652,6 +923,96 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
+    /// Calls `vkCmdClearDepthStencilImage` on the builder.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    pub unsafe fn clear_depth_stencil_image<I, R>(&mut self, image: I, layout: ImageLayout,
+                                          value: ClearValue, regions: R)
+                                          -> Result<(), SyncCommandBufferBuilderError>
+        where I: ImageAccess + Send + Sync + 'static,
+              R: Iterator<Item = UnsafeCommandBufferBuilderDepthStencilImageClear> + Send + Sync + 'static
+    {
+        struct Cmd<I, R> {
+            image: Option<I>,
+            layout: ImageLayout,
+            value: ClearValue,
+            regions: Option<R>,
+        }
+
+        impl<P, I, R> Command<P> for Cmd<I, R>
+            where I: ImageAccess + Send + Sync + 'static,
+                  R: Iterator<Item = UnsafeCommandBufferBuilderDepthStencilImageClear>
+                         + Send
+                         + Sync
+                         + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdClearDepthStencilImage"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.clear_depth_stencil_image(self.image.as_ref().unwrap(),
+                                      self.layout,
+                                      self.value,
+                                      self.regions.take().unwrap());
+            }
+
+            fn into_final_command(mut self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<I>(I);
+                impl<I> FinalCommand for Fin<I>
+                    where I: ImageAccess + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdClearDepthStencilImage"
+                    }
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        assert_eq!(num, 0);
+                        &self.0
+                    }
+                    fn image_name(&self, num: usize) -> Cow<'static, str> {
+                        assert_eq!(num, 0);
+                        "target".into()
+                    }
+                }
+
+                // Note: borrow checker somehow doesn't accept `self.image` without using an Option.
+                Box::new(Fin(self.image.take().unwrap()))
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                assert_eq!(num, 0);
+                self.image.as_ref().unwrap()
+            }
+
+            fn image_name(&self, num: usize) -> Cow<'static, str> {
+                assert_eq!(num, 0);
+                "target".into()
+            }
+        }
+
+        self.append_command(Cmd {
+                                image: Some(image),
+                                layout,
+                                value,
+                                regions: Some(regions),
+                            });
+        self.prev_cmd_resource(KeyTy::Image,
+                               0,
+                               true,
+                               PipelineStages {
+                                   transfer: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   transfer_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               layout,
+                               layout)?;
+        Ok(())
+    }
+
     /// Calls `vkCmdCopyBuffer` on the builder.
     ///
     /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
@@ -1013,35 +1374,197 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
-    /// Calls `vkCmdDispatch` on the builder.
+    /// Calls `vkCmdCopyQueryPoolResults` on the builder.
     #[inline]
-    pub unsafe fn dispatch(&mut self, dimensions: [u32; 3]) {
-        struct Cmd {
-            dimensions: [u32; 3],
+    pub unsafe fn copy_query_pool_results<Q, D, T>(&mut self, pool: Arc<Q>, first_index: u32,
+                                                   count: u32, destination: D, stride: usize,
+                                                   flags: QueryResultFlags)
+                                                   -> Result<(), SyncCommandBufferBuilderError>
+        where Q: QueryPoolAccess + 'static,
+              D: TypedBufferAccess<Content = [T]> + Send + Sync + 'static,
+              T: QueryResultElement
+    {
+        struct Cmd<Q, D> {
+            pool: Arc<Q>,
+            first_index: u32,
+            count: u32,
+            destination: D,
+            stride: usize,
+            flags: QueryResultFlags,
         }
 
-        impl<P> Command<P> for Cmd {
+        impl<P, Q, D, T> Command<P> for Cmd<Q, D>
+            where Q: QueryPoolAccess + 'static,
+                  D: TypedBufferAccess<Content = [T]> + Send + Sync + 'static,
+                  T: QueryResultElement
+        {
             fn name(&self) -> &'static str {
-                "vkCmdDispatch"
+                "vkCmdCopyQueryPoolResults"
             }
 
             unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
-                out.dispatch(self.dimensions);
+                let queries = self.pool
+                    .inner()
+                    .queries_range(self.first_index, self.count)
+                    .unwrap();
+                out.copy_query_pool_results(queries, &self.destination, self.stride, self.flags);
             }
 
             fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
-                Box::new("vkCmdDispatch")
-            }
-        }
-
-        self.append_command(Cmd { dimensions });
-    }
-
-    /// Calls `vkCmdDispatchIndirect` on the builder.
-    #[inline]
-    pub unsafe fn dispatch_indirect<B>(&mut self, buffer: B)
-                                       -> Result<(), SyncCommandBufferBuilderError>
-        where B: BufferAccess + Send + Sync + 'static
+                struct Fin<Q, D>(Arc<Q>, D);
+                impl<Q, D> FinalCommand for Fin<Q, D>
+                    where Q: QueryPoolAccess + 'static,
+                          D: BufferAccess + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdCopyQueryPoolResults"
+                    }
+                    fn buffer(&self, num: usize) -> &BufferAccess {
+                        assert_eq!(num, 0);
+                        &self.1
+                    }
+                    fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+                        "destination".into()
+                    }
+                }
+                Box::new(Fin(self.pool, self.destination))
+            }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                assert_eq!(num, 0);
+                &self.destination
+            }
+
+            fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+                "destination".into()
+            }
+        }
+
+        self.append_command(Cmd {
+                                pool,
+                                first_index,
+                                count,
+                                destination,
+                                stride,
+                                flags,
+                            });
+        self.prev_cmd_resource(KeyTy::Buffer,
+                               0,
+                               true,
+                               PipelineStages {
+                                   transfer: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   transfer_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               ImageLayout::Undefined,
+                               ImageLayout::Undefined)?;
+        Ok(())
+    }
+
+    /// Calls `vkCmdDebugMarkerBeginEXT` on the builder.
+    #[inline]
+    pub unsafe fn debug_marker_begin(&mut self, name: CString, color: [f32; 4]) {
+        struct Cmd {
+            name: CString,
+            color: [f32; 4],
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDebugMarkerBeginEXT"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.debug_marker_begin(&self.name, self.color);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdDebugMarkerBeginEXT")
+            }
+        }
+
+        self.append_command(Cmd { name, color });
+    }
+
+    /// Calls `vkCmdDebugMarkerEndEXT` on the builder.
+    #[inline]
+    pub unsafe fn debug_marker_end(&mut self) {
+        struct Cmd;
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDebugMarkerEndEXT"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.debug_marker_end();
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdDebugMarkerEndEXT")
+            }
+        }
+
+        self.append_command(Cmd);
+    }
+
+    /// Calls `vkCmdDebugMarkerInsertEXT` on the builder.
+    #[inline]
+    pub unsafe fn debug_marker_insert(&mut self, name: CString, color: [f32; 4]) {
+        struct Cmd {
+            name: CString,
+            color: [f32; 4],
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDebugMarkerInsertEXT"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.debug_marker_insert(&self.name, self.color);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdDebugMarkerInsertEXT")
+            }
+        }
+
+        self.append_command(Cmd { name, color });
+    }
+
+    /// Calls `vkCmdDispatch` on the builder.
+    #[inline]
+    pub unsafe fn dispatch(&mut self, dimensions: [u32; 3]) {
+        struct Cmd {
+            dimensions: [u32; 3],
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdDispatch"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.dispatch(self.dimensions);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdDispatch")
+            }
+        }
+
+        self.append_command(Cmd { dimensions });
+    }
+
+    /// Calls `vkCmdDispatchIndirect` on the builder.
+    #[inline]
+    pub unsafe fn dispatch_indirect<B>(&mut self, buffer: B)
+                                       -> Result<(), SyncCommandBufferBuilderError>
+        where B: BufferAccess + Send + Sync + 'static
     {
         struct Cmd<B> {
             buffer: B,
@@ -1332,6 +1855,43 @@ impl<P> SyncCommandBufferBuilder<P> {
         Ok(())
     }
 
+    /// Calls `vkCmdEndQuery` on the builder.
+    #[inline]
+    pub unsafe fn end_query<Q>(&mut self, pool: Arc<Q>, query: u32)
+        where Q: QueryPoolAccess + 'static
+    {
+        struct Cmd<Q> {
+            pool: Arc<Q>,
+            query: u32,
+        }
+
+        impl<P, Q> Command<P> for Cmd<Q>
+            where Q: QueryPoolAccess + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdEndQuery"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.end_query(self.pool.inner().query(self.query).unwrap());
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<Q>(Arc<Q>);
+                impl<Q> FinalCommand for Fin<Q>
+                    where Q: QueryPoolAccess + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdEndQuery"
+                    }
+                }
+                Box::new(Fin(self.pool))
+            }
+        }
+
+        self.append_command(Cmd { pool, query });
+    }
+
     /// Calls `vkCmdEndRenderPass` on the builder.
     #[inline]
     pub unsafe fn end_render_pass(&mut self) {
@@ -1517,6 +2077,232 @@ impl<P> SyncCommandBufferBuilder<P> {
                             });
     }
 
+    /// Adds a pipeline barrier that releases the ownership of a buffer, in order to transfer it
+    /// from the queue family `source_family` to the queue family `destination_family`.
+    #[inline]
+    pub unsafe fn release_buffer_ownership<B>(&mut self, buffer: B, source_family: u32,
+                                              destination_family: u32)
+                                              -> Result<(), SyncCommandBufferBuilderError>
+        where B: BufferAccess + Send + Sync + 'static
+    {
+        struct Cmd<B> {
+            buffer: B,
+            families: (u32, u32),
+        }
+
+        impl<P, B> Command<P> for Cmd<B>
+            where B: BufferAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdPipelineBarrier"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let (src_stages, src_access, dst_stages, dst_access) =
+                    ownership_transfer_scopes(true);
+                let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+                barrier.add_buffer_memory_barrier(&self.buffer,
+                                                  src_stages,
+                                                  src_access,
+                                                  dst_stages,
+                                                  dst_access,
+                                                  false,
+                                                  Some(self.families),
+                                                  0,
+                                                  self.buffer.size());
+                out.pipeline_barrier(&barrier);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<B>(B);
+                impl<B> FinalCommand for Fin<B>
+                    where B: BufferAccess + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdPipelineBarrier"
+                    }
+                    fn buffer(&self, num: usize) -> &BufferAccess {
+                        assert_eq!(num, 0);
+                        &self.0
+                    }
+                    fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+                        "buffer".into()
+                    }
+                }
+                Box::new(Fin(self.buffer))
+            }
+
+            fn buffer(&self, num: usize) -> &BufferAccess {
+                assert_eq!(num, 0);
+                &self.buffer
+            }
+
+            fn buffer_name(&self, _: usize) -> Cow<'static, str> {
+                "buffer".into()
+            }
+        }
+
+        self.append_command(Cmd {
+                                buffer: buffer,
+                                families: (source_family, destination_family),
+                            });
+        self.prev_cmd_resource(KeyTy::Buffer,
+                               0,
+                               true,
+                               PipelineStages {
+                                   all_commands: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   memory_read: true,
+                                   memory_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               ImageLayout::Undefined,
+                               ImageLayout::Undefined)?;
+        Ok(())
+    }
+
+    /// Adds a pipeline barrier that releases the ownership of an image, in order to transfer it
+    /// from the queue family `source_family` to the queue family `destination_family`.
+    ///
+    /// The image must be in the layout returned by `final_layout_requirement()`.
+    #[inline]
+    pub unsafe fn release_image_ownership<I>(&mut self, image: I, source_family: u32,
+                                             destination_family: u32)
+                                             -> Result<(), SyncCommandBufferBuilderError>
+        where I: ImageAccess + Send + Sync + 'static
+    {
+        struct Cmd<I> {
+            image: I,
+            families: (u32, u32),
+            layout: ImageLayout,
+        }
+
+        impl<P, I> Command<P> for Cmd<I>
+            where I: ImageAccess + Send + Sync + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdPipelineBarrier"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let (src_stages, src_access, dst_stages, dst_access) =
+                    ownership_transfer_scopes(true);
+                let mut barrier = UnsafeCommandBufferBuilderPipelineBarrier::new();
+                barrier.add_image_memory_barrier(&self.image,
+                                                 0 .. self.image.mipmap_levels(),
+                                                 0 .. self.image.dimensions().array_layers(),
+                                                 src_stages,
+                                                 src_access,
+                                                 dst_stages,
+                                                 dst_access,
+                                                 false,
+                                                 Some(self.families),
+                                                 self.layout,
+                                                 self.layout);
+                out.pipeline_barrier(&barrier);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<I>(I);
+                impl<I> FinalCommand for Fin<I>
+                    where I: ImageAccess + Send + Sync + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdPipelineBarrier"
+                    }
+                    fn image(&self, num: usize) -> &ImageAccess {
+                        assert_eq!(num, 0);
+                        &self.0
+                    }
+                    fn image_name(&self, _: usize) -> Cow<'static, str> {
+                        "image".into()
+                    }
+                }
+                Box::new(Fin(self.image))
+            }
+
+            fn image(&self, num: usize) -> &ImageAccess {
+                assert_eq!(num, 0);
+                &self.image
+            }
+
+            fn image_name(&self, _: usize) -> Cow<'static, str> {
+                "image".into()
+            }
+        }
+
+        let layout = image.final_layout_requirement();
+
+        self.append_command(Cmd {
+                                image: image,
+                                families: (source_family, destination_family),
+                                layout: layout,
+                            });
+        self.prev_cmd_resource(KeyTy::Image,
+                               0,
+                               true,
+                               PipelineStages {
+                                   all_commands: true,
+                                   ..PipelineStages::none()
+                               },
+                               AccessFlagBits {
+                                   memory_read: true,
+                                   memory_write: true,
+                                   ..AccessFlagBits::none()
+                               },
+                               layout,
+                               layout)?;
+        Ok(())
+    }
+
+    /// Calls `vkCmdResetQueryPool` on the builder.
+    #[inline]
+    pub unsafe fn reset_query_pool<Q>(&mut self, pool: Arc<Q>, first_index: u32, count: u32)
+        where Q: QueryPoolAccess + 'static
+    {
+        struct Cmd<Q> {
+            pool: Arc<Q>,
+            first_index: u32,
+            count: u32,
+        }
+
+        impl<P, Q> Command<P> for Cmd<Q>
+            where Q: QueryPoolAccess + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdResetQueryPool"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                let queries = self.pool
+                    .inner()
+                    .queries_range(self.first_index, self.count)
+                    .unwrap();
+                out.reset_query_pool(queries);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<Q>(Arc<Q>);
+                impl<Q> FinalCommand for Fin<Q>
+                    where Q: QueryPoolAccess + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdResetQueryPool"
+                    }
+                }
+                Box::new(Fin(self.pool))
+            }
+        }
+
+        self.append_command(Cmd {
+                                pool,
+                                first_index,
+                                count,
+                            });
+    }
+
     /// Calls `vkCmdResetEvent` on the builder.
     #[inline]
     pub unsafe fn reset_event(&mut self, event: Arc<Event>, stages: PipelineStages) {
@@ -1682,7 +2468,81 @@ impl<P> SyncCommandBufferBuilder<P> {
         self.append_command(Cmd { line_width });
     }
 
-    // TODO: stencil states
+    /// Calls `vkCmdSetStencilCompareMask` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_compare_mask(&mut self, face_mask: StencilFaceFlags,
+                                           compare_mask: u32) {
+        struct Cmd {
+            face_mask: StencilFaceFlags,
+            compare_mask: u32,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetStencilCompareMask"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_stencil_compare_mask(self.face_mask, self.compare_mask);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdSetStencilCompareMask")
+            }
+        }
+
+        self.append_command(Cmd { face_mask, compare_mask });
+    }
+
+    /// Calls `vkCmdSetStencilReference` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_reference(&mut self, face_mask: StencilFaceFlags, reference: u32) {
+        struct Cmd {
+            face_mask: StencilFaceFlags,
+            reference: u32,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetStencilReference"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_stencil_reference(self.face_mask, self.reference);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdSetStencilReference")
+            }
+        }
+
+        self.append_command(Cmd { face_mask, reference });
+    }
+
+    /// Calls `vkCmdSetStencilWriteMask` on the builder.
+    #[inline]
+    pub unsafe fn set_stencil_write_mask(&mut self, face_mask: StencilFaceFlags, write_mask: u32) {
+        struct Cmd {
+            face_mask: StencilFaceFlags,
+            write_mask: u32,
+        }
+
+        impl<P> Command<P> for Cmd {
+            fn name(&self) -> &'static str {
+                "vkCmdSetStencilWriteMask"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.set_stencil_write_mask(self.face_mask, self.write_mask);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                Box::new("vkCmdSetStencilWriteMask")
+            }
+        }
+
+        self.append_command(Cmd { face_mask, write_mask });
+    }
 
     /// Calls `vkCmdSetScissor` on the builder.
     ///
@@ -1820,6 +2680,44 @@ impl<P> SyncCommandBufferBuilder<P> {
                                ImageLayout::Undefined)
             .unwrap();
     }
+
+    /// Calls `vkCmdWriteTimestamp` on the builder.
+    #[inline]
+    pub unsafe fn write_timestamp<Q>(&mut self, pool: Arc<Q>, query: u32, stage: PipelineStages)
+        where Q: QueryPoolAccess + 'static
+    {
+        struct Cmd<Q> {
+            pool: Arc<Q>,
+            query: u32,
+            stage: PipelineStages,
+        }
+
+        impl<P, Q> Command<P> for Cmd<Q>
+            where Q: QueryPoolAccess + 'static
+        {
+            fn name(&self) -> &'static str {
+                "vkCmdWriteTimestamp"
+            }
+
+            unsafe fn send(&mut self, out: &mut UnsafeCommandBufferBuilder<P>) {
+                out.write_timestamp(self.pool.inner().query(self.query).unwrap(), self.stage);
+            }
+
+            fn into_final_command(self: Box<Self>) -> Box<FinalCommand + Send + Sync> {
+                struct Fin<Q>(Arc<Q>);
+                impl<Q> FinalCommand for Fin<Q>
+                    where Q: QueryPoolAccess + 'static
+                {
+                    fn name(&self) -> &'static str {
+                        "vkCmdWriteTimestamp"
+                    }
+                }
+                Box::new(Fin(self.pool))
+            }
+        }
+
+        self.append_command(Cmd { pool, query, stage });
+    }
 }
 
 pub struct SyncCommandBufferBuilderBindDescriptorSets<'b, P: 'b> {
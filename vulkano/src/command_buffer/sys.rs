@@ -19,7 +19,10 @@ use OomError;
 use VulkanObject;
 use buffer::BufferAccess;
 use buffer::BufferInner;
+use buffer::TypedBufferAccess;
 use check_errors;
+use command_buffer::ClearAttachment;
+use command_buffer::ClearRect;
 use command_buffer::CommandBuffer;
 use command_buffer::pool::CommandPool;
 use command_buffer::pool::CommandPoolAlloc;
@@ -44,10 +47,13 @@ use image::ImageLayout;
 use instance::QueueFamily;
 use pipeline::ComputePipelineAbstract;
 use pipeline::GraphicsPipelineAbstract;
+use pipeline::depth_stencil::StencilFaceFlags;
 use pipeline::input_assembly::IndexType;
 use pipeline::viewport::Scissor;
 use pipeline::viewport::Viewport;
 use query::QueryPipelineStatisticFlags;
+use query::QueryResultElement;
+use query::QueryResultFlags;
 use query::UnsafeQueriesRange;
 use query::UnsafeQuery;
 use sampler::Filter;
@@ -602,7 +608,7 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                                   source.num_layers as u32);
                 debug_assert!(copy.destination_base_array_layer + copy.layer_count <=
                                   destination.num_layers as u32);
-                debug_assert!(copy.source_mip_level < destination.num_mipmap_levels as u32);
+                debug_assert!(copy.source_mip_level < source.num_mipmap_levels as u32);
                 debug_assert!(copy.destination_mip_level < destination.num_mipmap_levels as u32);
 
                 if copy.layer_count == 0 {
@@ -612,7 +618,7 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                 Some(vk::ImageCopy {
                     srcSubresource: vk::ImageSubresourceLayers {
                         aspectMask: copy.aspect.to_vk_bits(),
-                        mipLevel: copy.source_mip_level,
+                        mipLevel: copy.source_mip_level + source.first_mipmap_level as u32,
                         baseArrayLayer: copy.source_base_array_layer + source.first_layer as u32,
                         layerCount: copy.layer_count,
                     },
@@ -623,7 +629,8 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                     },
                     dstSubresource: vk::ImageSubresourceLayers {
                         aspectMask: copy.aspect.to_vk_bits(),
-                        mipLevel: copy.destination_mip_level,
+                        mipLevel: copy.destination_mip_level +
+                            destination.first_mipmap_level as u32,
                         baseArrayLayer: copy.destination_base_array_layer +
                             destination.first_layer as u32,
                         layerCount: copy.layer_count,
@@ -698,7 +705,7 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                                   source.num_layers as u32);
                 debug_assert!(blit.destination_base_array_layer + blit.layer_count <=
                                   destination.num_layers as u32);
-                debug_assert!(blit.source_mip_level < destination.num_mipmap_levels as u32);
+                debug_assert!(blit.source_mip_level < source.num_mipmap_levels as u32);
                 debug_assert!(blit.destination_mip_level < destination.num_mipmap_levels as u32);
 
                 if blit.layer_count == 0 {
@@ -708,7 +715,7 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                 Some(vk::ImageBlit {
                     srcSubresource: vk::ImageSubresourceLayers {
                         aspectMask: blit.aspect.to_vk_bits(),
-                        mipLevel: blit.source_mip_level,
+                        mipLevel: blit.source_mip_level + source.first_mipmap_level as u32,
                         baseArrayLayer: blit.source_base_array_layer + source.first_layer as u32,
                         layerCount: blit.layer_count,
                     },
@@ -726,7 +733,8 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                     ],
                     dstSubresource: vk::ImageSubresourceLayers {
                         aspectMask: blit.aspect.to_vk_bits(),
-                        mipLevel: blit.destination_mip_level,
+                        mipLevel: blit.destination_mip_level +
+                            destination.first_mipmap_level as u32,
                         baseArrayLayer: blit.destination_base_array_layer +
                             destination.first_layer as u32,
                         layerCount: blit.layer_count,
@@ -763,18 +771,93 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                         filter as u32);
     }
 
-    // TODO: missing structs
-    /*/// Calls `vkCmdClearAttachments` on the builder.
+    /// Calls `vkCmdClearAttachments` on the builder.
     ///
     /// Does nothing if the list of attachments or the list of rects is empty, as it would be a
     /// no-op and isn't a valid usage of the command anyway.
     #[inline]
     pub unsafe fn clear_attachments<A, R>(&mut self, attachments: A, rects: R)
-        where A: Iterator<Item = >,
-              R: Iterator<Item = >
+        where A: Iterator<Item = ClearAttachment>,
+              R: Iterator<Item = ClearRect>
     {
-        let attachments: SmallVec<[_; 16]> = attachments.map().collect();
-        let rects: SmallVec<[_; 4]> = rects.map().collect();
+        let attachments: SmallVec<[_; 16]> = attachments
+            .map(|attachment| match attachment {
+                     ClearAttachment::Color(index, value) => {
+                         let color = match value {
+                             ClearValue::Float(val) => vk::ClearColorValue { float32: val },
+                             ClearValue::Int(val) => vk::ClearColorValue { int32: val },
+                             ClearValue::Uint(val) => vk::ClearColorValue { uint32: val },
+                             _ => panic!("The clear value is not a color value"),
+                         };
+
+                         vk::ClearAttachment {
+                             aspectMask: vk::IMAGE_ASPECT_COLOR_BIT,
+                             colorAttachment: index,
+                             clearValue: vk::ClearValue { color: color },
+                         }
+                     },
+                     ClearAttachment::Depth(depth) => {
+                         vk::ClearAttachment {
+                             aspectMask: vk::IMAGE_ASPECT_DEPTH_BIT,
+                             colorAttachment: 0,
+                             clearValue: vk::ClearValue {
+                                 depthStencil: vk::ClearDepthStencilValue {
+                                     depth: depth,
+                                     stencil: 0,
+                                 },
+                             },
+                         }
+                     },
+                     ClearAttachment::Stencil(stencil) => {
+                         vk::ClearAttachment {
+                             aspectMask: vk::IMAGE_ASPECT_STENCIL_BIT,
+                             colorAttachment: 0,
+                             clearValue: vk::ClearValue {
+                                 depthStencil: vk::ClearDepthStencilValue {
+                                     depth: 0.0,
+                                     stencil: stencil,
+                                 },
+                             },
+                         }
+                     },
+                     ClearAttachment::DepthStencil((depth, stencil)) => {
+                         vk::ClearAttachment {
+                             aspectMask: vk::IMAGE_ASPECT_DEPTH_BIT |
+                                 vk::IMAGE_ASPECT_STENCIL_BIT,
+                             colorAttachment: 0,
+                             clearValue: vk::ClearValue {
+                                 depthStencil: vk::ClearDepthStencilValue {
+                                     depth: depth,
+                                     stencil: stencil,
+                                 },
+                             },
+                         }
+                     },
+                 })
+            .collect();
+
+        let rects: SmallVec<[_; 4]> = rects
+            .filter_map(|rect| {
+                if rect.extent[0] == 0 || rect.extent[1] == 0 || rect.layer_count == 0 {
+                    return None;
+                }
+
+                Some(vk::ClearRect {
+                         rect: vk::Rect2D {
+                             offset: vk::Offset2D {
+                                 x: rect.offset[0],
+                                 y: rect.offset[1],
+                             },
+                             extent: vk::Extent2D {
+                                 width: rect.extent[0],
+                                 height: rect.extent[1],
+                             },
+                         },
+                         baseArrayLayer: rect.base_array_layer,
+                         layerCount: rect.layer_count,
+                     })
+            })
+            .collect();
 
         if attachments.is_empty() || rects.is_empty() {
             return;
@@ -782,9 +865,12 @@ impl<P> UnsafeCommandBufferBuilder<P> {
 
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdClearAttachments(cmd, attachments.len() as u32, attachments.as_ptr(),
-                               rects.len() as u32, rects.as_ptr());
-    }*/
+        vk.CmdClearAttachments(cmd,
+                               attachments.len() as u32,
+                               attachments.as_ptr(),
+                               rects.len() as u32,
+                               rects.as_ptr());
+    }
 
     /// Calls `vkCmdClearColorImage` on the builder.
     ///
@@ -854,6 +940,84 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                               regions.as_ptr());
     }
 
+    /// Calls `vkCmdClearDepthStencilImage` on the builder.
+    ///
+    /// The aspects that are cleared depend on the variant of `value`, which must be `Depth`,
+    /// `Stencil` or `DepthStencil`.
+    ///
+    /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
+    /// usage of the command anyway.
+    pub unsafe fn clear_depth_stencil_image<I, R>(&mut self, image: &I, layout: ImageLayout,
+                                                  value: ClearValue, regions: R)
+        where I: ?Sized + ImageAccess,
+              R: Iterator<Item = UnsafeCommandBufferBuilderDepthStencilImageClear>
+    {
+        debug_assert!(image.format().ty().is_depth_and_or_stencil());
+
+        let image = image.inner();
+        debug_assert!(image.image.usage_transfer_destination());
+        debug_assert!(layout == ImageLayout::General || layout == ImageLayout::TransferDstOptimal);
+
+        let (value, aspect_mask) = match value {
+            ClearValue::Depth(depth) => {
+                (vk::ClearDepthStencilValue {
+                     depth: depth,
+                     stencil: 0,
+                 },
+                 vk::IMAGE_ASPECT_DEPTH_BIT)
+            },
+            ClearValue::Stencil(stencil) => {
+                (vk::ClearDepthStencilValue {
+                     depth: 0.0,
+                     stencil: stencil,
+                 },
+                 vk::IMAGE_ASPECT_STENCIL_BIT)
+            },
+            ClearValue::DepthStencil((depth, stencil)) => {
+                (vk::ClearDepthStencilValue {
+                     depth: depth,
+                     stencil: stencil,
+                 },
+                 vk::IMAGE_ASPECT_DEPTH_BIT | vk::IMAGE_ASPECT_STENCIL_BIT)
+            },
+            _ => panic!("The clear value is not a depth or stencil value"),
+        };
+
+        let regions: SmallVec<[_; 8]> = regions
+            .filter_map(|region| {
+                debug_assert!(region.layer_count + region.base_array_layer <=
+                                  image.num_layers as u32);
+                debug_assert!(region.level_count + region.base_mip_level <=
+                                  image.num_mipmap_levels as u32);
+
+                if region.layer_count == 0 || region.level_count == 0 {
+                    return None;
+                }
+
+                Some(vk::ImageSubresourceRange {
+                         aspectMask: aspect_mask,
+                         baseMipLevel: region.base_mip_level + image.first_mipmap_level as u32,
+                         levelCount: region.level_count,
+                         baseArrayLayer: region.base_array_layer + image.first_layer as u32,
+                         layerCount: region.layer_count,
+                     })
+            })
+            .collect();
+
+        if regions.is_empty() {
+            return;
+        }
+
+        let vk = self.device().pointers();
+        let cmd = self.internal_object();
+        vk.CmdClearDepthStencilImage(cmd,
+                                     image.image.internal_object(),
+                                     layout as u32,
+                                     &value,
+                                     regions.len() as u32,
+                                     regions.as_ptr());
+    }
+
     /// Calls `vkCmdCopyBuffer` on the builder.
     ///
     /// Does nothing if the list of regions is empty, as it would be a no-op and isn't a valid
@@ -1027,14 +1191,20 @@ impl<P> UnsafeCommandBufferBuilder<P> {
     }
 
     /// Calls `vkCmdCopyQueryPoolResults` on the builder.
+    ///
+    /// `T` determines whether the results are written as 32 bits or 64 bits integers.
     #[inline]
-    pub unsafe fn copy_query_pool_results(&mut self, queries: UnsafeQueriesRange,
-                                          destination: &BufferAccess, stride: usize) {
+    pub unsafe fn copy_query_pool_results<D, T>(&mut self, queries: UnsafeQueriesRange,
+                                                destination: &D, stride: usize,
+                                                flags: QueryResultFlags)
+        where D: ?Sized + TypedBufferAccess<Content = [T]>,
+              T: QueryResultElement
+    {
         let destination = destination.inner();
         debug_assert!(destination.offset < destination.buffer.size());
         debug_assert!(destination.buffer.usage_transfer_destination());
-
-        let flags = 0; // FIXME:
+        debug_assert_eq!(destination.offset % mem::size_of::<T>(), 0);
+        debug_assert_eq!(stride % mem::size_of::<T>(), 0);
 
         let vk = self.device().pointers();
         let cmd = self.internal_object();
@@ -1045,7 +1215,7 @@ impl<P> UnsafeCommandBufferBuilder<P> {
                                    destination.buffer.internal_object(),
                                    destination.offset as vk::DeviceSize,
                                    stride as vk::DeviceSize,
-                                   flags);
+                                   flags.into_vulkan_bits::<T>());
     }
 
     /// Calls `vkCmdDispatch` on the builder.
@@ -1299,7 +1469,7 @@ impl<P> UnsafeCommandBufferBuilder<P> {
     pub unsafe fn set_blend_constants(&mut self, constants: [f32; 4]) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetBlendConstants(cmd, constants); // TODO: correct to pass array?
+        vk.CmdSetBlendConstants(cmd, constants.as_ptr());
     }
 
     /// Calls `vkCmdSetDepthBias` on the builder.
@@ -1342,32 +1512,30 @@ impl<P> UnsafeCommandBufferBuilder<P> {
         vk.CmdSetLineWidth(cmd, line_width);
     }
 
-    // TODO: missing enum for face_mask
-    /*/// Calls `vkCmdSetStencilCompareMask` on the builder.
+    /// Calls `vkCmdSetStencilCompareMask` on the builder.
     #[inline]
-    pub unsafe fn set_stencil_compare_mask(&mut self, face_mask: , compare_mask: u32) {
+    pub unsafe fn set_stencil_compare_mask(&mut self, face_mask: StencilFaceFlags,
+                                           compare_mask: u32) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetStencilCompareMask(cmd, face_mask, compare_mask);
-    }*/
+        vk.CmdSetStencilCompareMask(cmd, face_mask as u32, compare_mask);
+    }
 
-    // TODO: missing enum for face_mask
-    /*/// Calls `vkCmdSetStencilReference` on the builder.
+    /// Calls `vkCmdSetStencilReference` on the builder.
     #[inline]
-    pub unsafe fn set_stencil_reference(&mut self, face_mask: , reference: u32) {
+    pub unsafe fn set_stencil_reference(&mut self, face_mask: StencilFaceFlags, reference: u32) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetStencilReference(cmd, face_mask, reference);
-    }*/
+        vk.CmdSetStencilReference(cmd, face_mask as u32, reference);
+    }
 
-    // TODO: missing enum for face_mask
-    /*/// Calls `vkCmdSetStencilWriteMask` on the builder.
+    /// Calls `vkCmdSetStencilWriteMask` on the builder.
     #[inline]
-    pub unsafe fn set_stencil_write_mask(&mut self, face_mask: , write_mask: u32) {
+    pub unsafe fn set_stencil_write_mask(&mut self, face_mask: StencilFaceFlags, write_mask: u32) {
         let vk = self.device().pointers();
         let cmd = self.internal_object();
-        vk.CmdSetStencilWriteMask(cmd, face_mask, write_mask);
-    }*/
+        vk.CmdSetStencilWriteMask(cmd, face_mask as u32, write_mask);
+    }
 
     /// Calls `vkCmdSetScissor` on the builder.
     ///
@@ -1643,6 +1811,15 @@ pub struct UnsafeCommandBufferBuilderColorImageClear {
     pub layer_count: u32,
 }
 
+// TODO: move somewhere else?
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnsafeCommandBufferBuilderDepthStencilImageClear {
+    pub base_mip_level: u32,
+    pub level_count: u32,
+    pub base_array_layer: u32,
+    pub layer_count: u32,
+}
+
 // TODO: move somewhere else?
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct UnsafeCommandBufferBuilderBufferImageCopy {
@@ -15,24 +15,34 @@ use std::sync::Mutex;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 
+use OomError;
 use SafeDeref;
 use VulkanObject;
 use buffer::BufferAccess;
+use command_buffer::pool::standard::StandardCommandPoolAlloc;
 use command_buffer::submit::SubmitAnyBuilder;
 use command_buffer::submit::SubmitCommandBufferBuilder;
+use command_buffer::sys::Flags;
+use command_buffer::sys::Kind;
 use command_buffer::sys::UnsafeCommandBuffer;
+use command_buffer::sys::UnsafeCommandBufferBuilder;
+use command_buffer::sys::UnsafeCommandBufferBuilderPipelineBarrier;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
 use image::ImageAccess;
 use image::ImageLayout;
+use query::QueryPoolAccess;
+use query::QueryState;
 use sync::AccessCheckError;
 use sync::AccessError;
 use sync::AccessFlagBits;
 use sync::FlushError;
 use sync::GpuFuture;
+use sync::LazySemaphore;
 use sync::NowFuture;
 use sync::PipelineStages;
+use sync::Semaphore;
 use sync::now;
 
 pub unsafe trait CommandBuffer: DeviceOwned {
@@ -125,12 +135,21 @@ pub unsafe trait CommandBuffer: DeviceOwned {
             assert!(future.queue().unwrap().is_same(&queue));
         }
 
+        let ownership_transfer = match self.queue_family_transfer(&future, &queue) {
+            Some(transfer) => Some(OwnershipTransfer::new(transfer, &queue)?),
+            None => None,
+        };
+
         self.lock_submit(&future, &queue)?;
 
+        let sparse_semaphore = LazySemaphore::new(self.device().clone());
+
         Ok(CommandBufferExecFuture {
                previous: future,
                command_buffer: self,
                queue: queue,
+               sparse_semaphore: sparse_semaphore,
+               ownership_transfer: ownership_transfer,
                submitted: Mutex::new(false),
                finished: AtomicBool::new(false),
            })
@@ -143,6 +162,34 @@ pub unsafe trait CommandBuffer: DeviceOwned {
                           queue: &Queue)
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError>;
 
+    /// Returns the pipeline barriers that transfer the ownership of the resources used by this
+    /// command buffer from the queue family of `future` to the queue family of `queue`, if any.
+    ///
+    /// This is called by `execute_after`, which then executes the release barrier on the queue of
+    /// `future` and the acquire barrier on `queue` before the command buffer.
+    ///
+    /// The default implementation returns `None`, in which case the resources that use the
+    /// exclusive sharing mode can't be shared between queue families unless the user transfers
+    /// their ownership manually.
+    #[inline]
+    fn queue_family_transfer(&self, future: &GpuFuture, queue: &Queue)
+                             -> Option<QueueFamilyTransfer> {
+        None
+    }
+
+    /// Returns the queries that are used by this command buffer, the pools they belong to, and
+    /// the state in which the command buffer leaves them.
+    ///
+    /// These states are applied to the query pools when the command buffer is submitted, except
+    /// that the queries left `Ended` become `Available`. When a secondary command buffer is
+    /// executed by a primary command buffer, the primary command buffer takes these states over.
+    ///
+    /// The default implementation returns an empty list.
+    #[inline]
+    fn query_states(&self) -> &[(Arc<QueryPoolAccess>, u32, QueryState)] {
+        &[]
+    }
+
     // FIXME: lots of other methods
 }
 
@@ -180,6 +227,68 @@ unsafe impl<T> CommandBuffer for T
                           -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
         (**self).check_image_access(image, layout, exclusive, queue)
     }
+
+    #[inline]
+    fn queue_family_transfer(&self, future: &GpuFuture, queue: &Queue)
+                             -> Option<QueueFamilyTransfer> {
+        (**self).queue_family_transfer(future, queue)
+    }
+
+    #[inline]
+    fn query_states(&self) -> &[(Arc<QueryPoolAccess>, u32, QueryState)] {
+        (**self).query_states()
+    }
+}
+
+/// Pipeline barriers that transfer the ownership of resources between two queue families.
+///
+/// See `CommandBuffer::queue_family_transfer`.
+pub struct QueueFamilyTransfer {
+    /// Queue that currently owns the resources.
+    pub source: Arc<Queue>,
+    /// Barrier that releases the ownership of the resources. Executed on `source`.
+    pub release: UnsafeCommandBufferBuilderPipelineBarrier,
+    /// Barrier that acquires the ownership of the resources. Executed on the destination queue.
+    pub acquire: UnsafeCommandBufferBuilderPipelineBarrier,
+}
+
+// Command buffers and semaphore that perform a queue family ownership transfer before a command
+// buffer is executed.
+struct OwnershipTransfer {
+    source: Arc<Queue>,
+    release: UnsafeCommandBuffer<StandardCommandPoolAlloc>,
+    acquire: UnsafeCommandBuffer<StandardCommandPoolAlloc>,
+    // Signaled by the release submission and waited upon by the acquire submission.
+    semaphore: Semaphore,
+    // True if the release has already been submitted.
+    release_submitted: Mutex<bool>,
+}
+
+impl OwnershipTransfer {
+    // Records the barriers of `transfer` in command buffers allocated from the standard pools of
+    // the source queue and of `destination`.
+    fn new(transfer: QueueFamilyTransfer, destination: &Queue)
+           -> Result<OwnershipTransfer, OomError> {
+        unsafe fn record(queue: &Queue, barrier: &UnsafeCommandBufferBuilderPipelineBarrier)
+                         -> Result<UnsafeCommandBuffer<StandardCommandPoolAlloc>, OomError> {
+            let pool = Device::standard_command_pool(queue.device(), queue.family());
+            let mut builder = UnsafeCommandBufferBuilder::new(&pool,
+                                                              Kind::primary(),
+                                                              Flags::OneTimeSubmit)?;
+            builder.pipeline_barrier(barrier);
+            builder.build()
+        }
+
+        unsafe {
+            Ok(OwnershipTransfer {
+                   release: record(&transfer.source, &transfer.release)?,
+                   acquire: record(destination, &transfer.acquire)?,
+                   semaphore: Semaphore::alloc(destination.device().clone())?,
+                   source: transfer.source,
+                   release_submitted: Mutex::new(false),
+               })
+        }
+    }
 }
 
 /// Represents a command buffer being executed by the GPU and the moment when the execution
@@ -192,6 +301,11 @@ pub struct CommandBufferExecFuture<F, Cb>
     previous: F,
     command_buffer: Cb,
     queue: Arc<Queue>,
+    // Used to wait for a sparse binding submission of `previous`, if necessary.
+    sparse_semaphore: LazySemaphore,
+    // Transfers the ownership of the resources used by the command buffer from the queue family
+    // of `previous`, if necessary.
+    ownership_transfer: Option<OwnershipTransfer>,
     // True if the command buffer has already been submitted.
     // If flush is called multiple times, we want to block so that only one flushing is executed.
     // Therefore we use a `Mutex<bool>` and not an `AtomicBool`.
@@ -212,26 +326,52 @@ unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
         Ok(match self.previous.build_submission()? {
                SubmitAnyBuilder::Empty => {
                    let mut builder = SubmitCommandBufferBuilder::new();
+                   self.add_ownership_transfer(&mut builder)?;
                    builder.add_command_buffer(self.command_buffer.inner());
                    SubmitAnyBuilder::CommandBuffer(builder)
                },
                SubmitAnyBuilder::SemaphoresWait(sem) => {
                    let mut builder: SubmitCommandBufferBuilder = sem.into();
+                   self.add_ownership_transfer(&mut builder)?;
                    builder.add_command_buffer(self.command_buffer.inner());
                    SubmitAnyBuilder::CommandBuffer(builder)
                },
                SubmitAnyBuilder::CommandBuffer(mut builder) => {
                    // FIXME: add pipeline barrier
+                   // The previous submission is on the same queue as ours, therefore the queue
+                   // family can't have changed.
+                   debug_assert!(self.ownership_transfer.is_none());
                    builder.add_command_buffer(self.command_buffer.inner());
                    SubmitAnyBuilder::CommandBuffer(builder)
                },
-               SubmitAnyBuilder::QueuePresent(_) |
-               SubmitAnyBuilder::BindSparse(_) => {
-                   unimplemented!() // TODO:
-                /*present.submit();     // TODO: wrong
-                let mut builder = SubmitCommandBufferBuilder::new();
-                builder.add_command_buffer(self.command_buffer.inner());
-                SubmitAnyBuilder::CommandBuffer(builder)*/
+               SubmitAnyBuilder::QueuePresent(_) => {
+                   // A present can't be merged with a command buffer submission and doesn't
+                   // signal anything. Submit it by flushing the previous future.
+                   self.previous.flush()?;
+                   let mut builder = SubmitCommandBufferBuilder::new();
+                   self.add_ownership_transfer(&mut builder)?;
+                   builder.add_command_buffer(self.command_buffer.inner());
+                   SubmitAnyBuilder::CommandBuffer(builder)
+               },
+               SubmitAnyBuilder::BindSparse(mut sparse) => {
+                   // Sparse bindings aren't ordered with the submissions that follow them,
+                   // therefore we chain the two with a semaphore. The sparse binding is only
+                   // submitted the first time the submission is built.
+                   let semaphore = self.sparse_semaphore.submit_signal(|semaphore| {
+                       sparse.add_signal_semaphore(semaphore);
+                       sparse
+                           .submit(&self.previous.queue().unwrap())
+                           .map_err(FlushError::from)
+                   })?;
+                   let mut builder = SubmitCommandBufferBuilder::new();
+                   builder.add_wait_semaphore(semaphore,
+                                              PipelineStages {
+                                                  all_commands: true,
+                                                  ..PipelineStages::none()
+                                              });
+                   self.add_ownership_transfer(&mut builder)?;
+                   builder.add_command_buffer(self.command_buffer.inner());
+                   SubmitAnyBuilder::CommandBuffer(builder)
                },
            })
     }
@@ -309,6 +449,43 @@ unsafe impl<F, Cb> GpuFuture for CommandBufferExecFuture<F, Cb>
     }
 }
 
+impl<F, Cb> CommandBufferExecFuture<F, Cb>
+    where F: GpuFuture,
+          Cb: CommandBuffer
+{
+    // If the ownership of some resources needs to be transferred, submits the release barrier on
+    // the source queue and adds the acquire barrier to `builder`.
+    unsafe fn add_ownership_transfer<'a>(&'a self, builder: &mut SubmitCommandBufferBuilder<'a>)
+                                         -> Result<(), FlushError> {
+        let transfer = match self.ownership_transfer {
+            Some(ref transfer) => transfer,
+            None => return Ok(()),
+        };
+
+        // `build_submission` can be called multiple times, for example if submitting what it
+        // returns fails. The release must only be submitted once, otherwise the semaphore would
+        // be signaled twice.
+        {
+            let mut release_submitted = transfer.release_submitted.lock().unwrap();
+            if !*release_submitted {
+                let mut release = SubmitCommandBufferBuilder::new();
+                release.add_command_buffer(&transfer.release);
+                release.add_signal_semaphore(&transfer.semaphore);
+                release.submit(&transfer.source)?;
+                *release_submitted = true;
+            }
+        }
+
+        builder.add_wait_semaphore(&transfer.semaphore,
+                                   PipelineStages {
+                                       all_commands: true,
+                                       ..PipelineStages::none()
+                                   });
+        builder.add_command_buffer(&transfer.acquire);
+        Ok(())
+    }
+}
+
 unsafe impl<F, Cb> DeviceOwned for CommandBufferExecFuture<F, Cb>
     where F: GpuFuture,
           Cb: CommandBuffer
@@ -356,6 +533,10 @@ pub enum CommandBufferExecError {
     /// the GPU and was not created with the "concurrent" flag.
     ExclusiveAlreadyInUse,
 
+    /// Not enough memory to allocate the command buffers that transfer the ownership of the
+    /// resources between queue families.
+    OomError(OomError),
+
     // TODO: missing entries (eg. wrong queue family, secondary command buffer)
 }
 
@@ -375,6 +556,9 @@ impl error::Error for CommandBufferExecError {
                 "the command buffer or one of the secondary command buffers it executes is \
                  already in use by the GPU and was not created with the \"concurrent\" flag"
             },
+            CommandBufferExecError::OomError(_) => {
+                "not enough memory"
+            },
         }
     }
 
@@ -382,14 +566,255 @@ impl error::Error for CommandBufferExecError {
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             CommandBufferExecError::AccessError { ref error, .. } => Some(error),
+            CommandBufferExecError::OomError(ref err) => Some(err),
             _ => None,
         }
     }
 }
 
+impl From<OomError> for CommandBufferExecError {
+    #[inline]
+    fn from(err: OomError) -> CommandBufferExecError {
+        CommandBufferExecError::OomError(err)
+    }
+}
+
 impl fmt::Display for CommandBufferExecError {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buffer::BufferAccess;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use command_buffer::OwnershipTransferError;
+    use command_buffer::validity::CheckOwnershipTransferError;
+    use device::Queue;
+    use sync::GpuFuture;
+    use testing::BufferBarrier;
+    use testing::Call;
+    use testing::MockLoader;
+    use vk;
+
+    use VulkanObject;
+
+    // Builds a mock device with a queue of the graphics family and a queue of the transfer-only
+    // family.
+    fn mock_device() -> (MockLoader, Arc<Queue>, Arc<Queue>) {
+        let (loader, _, mut queues) = mock_dev_and_queue!(0, 1);
+        let graphics = queues.next().unwrap();
+        let transfer = queues.next().unwrap();
+        (loader, graphics, transfer)
+    }
+
+    // Returns the buffer barriers of `calls` that transfer the ownership of a buffer.
+    fn ownership_barriers(calls: &[Call]) -> Vec<BufferBarrier> {
+        calls
+            .iter()
+            .filter_map(|call| match *call {
+                            Call::CmdPipelineBarrier { ref buffer_barriers, .. } => {
+                                Some(buffer_barriers.clone())
+                            },
+                            _ => None,
+                        })
+            .flat_map(|barriers| barriers.into_iter())
+            .filter(|b| b.source_queue_family != vk::QUEUE_FAMILY_IGNORED)
+            .collect()
+    }
+
+    #[test]
+    fn automatic_ownership_transfer() {
+        let (loader, graphics, transfer) = mock_device();
+        let device = graphics.device().clone();
+
+        let buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+            .unwrap();
+        let raw_buffer = buffer.inner().buffer.internal_object();
+
+        let upload = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(),
+                                                                       transfer.family())
+            .unwrap()
+            .fill_buffer(buffer.clone(), 1)
+            .unwrap()
+            .build()
+            .unwrap();
+        let usage = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(),
+                                                                      graphics.family())
+            .unwrap()
+            .fill_buffer(buffer.clone(), 2)
+            .unwrap()
+            .build()
+            .unwrap();
+        loader.take_calls();
+
+        upload
+            .execute(transfer.clone())
+            .unwrap()
+            .then_signal_semaphore()
+            .then_execute(graphics.clone(), usage)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let calls = loader.take_calls();
+
+        // One release and one acquire barrier, both from the transfer family to the graphics
+        // family.
+        let barriers = ownership_barriers(&calls);
+        assert_eq!(barriers.len(), 2);
+        for barrier in barriers {
+            assert_eq!(barrier.buffer, raw_buffer);
+            assert_eq!(barrier.source_queue_family, transfer.family().id());
+            assert_eq!(barrier.destination_queue_family, graphics.family().id());
+        }
+
+        // The release is submitted on its own and signals a semaphore that the final submission
+        // waits upon.
+        let submits = calls
+            .into_iter()
+            .filter_map(|call| match call {
+                            Call::QueueSubmit { batches, .. } => Some(batches),
+                            _ => None,
+                        })
+            .collect::<Vec<_>>();
+        assert_eq!(submits.len(), 3);
+        let release_signal = submits[1][0].signal_semaphores[0];
+        assert!(submits[2]
+                    .iter()
+                    .any(|b| b.wait_semaphores.iter().any(|&(s, _)| s == release_signal)));
+    }
+
+    #[test]
+    fn release_submitted_once() {
+        let (loader, graphics, transfer) = mock_device();
+        let device = graphics.device().clone();
+
+        let buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+            .unwrap();
+        let upload = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(),
+                                                                       transfer.family())
+            .unwrap()
+            .fill_buffer(buffer.clone(), 1)
+            .unwrap()
+            .build()
+            .unwrap();
+        let usage = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(),
+                                                                      graphics.family())
+            .unwrap()
+            .fill_buffer(buffer.clone(), 2)
+            .unwrap()
+            .build()
+            .unwrap();
+        loader.take_calls();
+
+        let future = upload
+            .execute(transfer.clone())
+            .unwrap()
+            .then_signal_semaphore()
+            .then_execute(graphics.clone(), usage)
+            .unwrap();
+
+        // Building the submission again, for example after a failed submit, must not submit the
+        // release again.
+        unsafe {
+            let _ = future.build_submission().unwrap();
+            let _ = future.build_submission().unwrap();
+        }
+        future.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+        let signaled = loader
+            .take_calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                            Call::QueueSubmit { batches, .. } => Some(batches),
+                            _ => None,
+                        })
+            .flat_map(|batches| batches.into_iter())
+            .flat_map(|batch| batch.signal_semaphores.into_iter())
+            .collect::<Vec<_>>();
+        let mut unique = signaled.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(signaled, unique);
+    }
+
+    #[test]
+    fn no_transfer_within_family() {
+        let (loader, graphics, _) = mock_device();
+        let device = graphics.device().clone();
+
+        let buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+            .unwrap();
+        let first = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(),
+                                                                      graphics.family())
+            .unwrap()
+            .fill_buffer(buffer.clone(), 1)
+            .unwrap()
+            .build()
+            .unwrap();
+        let second = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(),
+                                                                       graphics.family())
+            .unwrap()
+            .fill_buffer(buffer.clone(), 2)
+            .unwrap()
+            .build()
+            .unwrap();
+        loader.take_calls();
+
+        first
+            .execute(graphics.clone())
+            .unwrap()
+            .then_execute(graphics.clone(), second)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        assert!(ownership_barriers(&loader.take_calls()).is_empty());
+    }
+
+    #[test]
+    fn explicit_ownership_transfer() {
+        let (loader, graphics, transfer) = mock_device();
+        let device = graphics.device().clone();
+
+        let buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+            .unwrap();
+        loader.take_calls();
+
+        let _cb = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(),
+                                                                    transfer.family())
+            .unwrap()
+            .fill_buffer(buffer.clone(), 1)
+            .unwrap()
+            .release_buffer_ownership(buffer.clone(), graphics.family())
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let barriers = ownership_barriers(&loader.take_calls());
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].source_queue_family, transfer.family().id());
+        assert_eq!(barriers[0].destination_queue_family, graphics.family().id());
+
+        let err = AutoCommandBufferBuilder::primary(device.clone(), graphics.family())
+            .unwrap()
+            .acquire_buffer_ownership(buffer.clone(), graphics.family());
+        match err {
+            Err(OwnershipTransferError::CheckOwnershipTransferError(
+                    CheckOwnershipTransferError::SameQueueFamily)) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use device::Device;
+use query::QueryPoolAccess;
+use query::QueryState;
+use query::QueryType;
+
+/// Checks whether a begin query command is valid.
+///
+/// `query_state` must return the state of a query of `pool` at the point of the command buffer
+/// where the command is recorded.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+///
+pub fn check_begin_query<Q, F>(device: &Device, pool: &Q, query: u32, precise: bool,
+                               query_state: F)
+                               -> Result<(), CheckBeginQueryError>
+    where Q: ?Sized + QueryPoolAccess,
+          F: Fn(u32) -> QueryState
+{
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+
+    if query >= pool.inner().num_slots() {
+        return Err(CheckBeginQueryError::OutOfRange);
+    }
+
+    match pool.inner().ty() {
+        QueryType::Occlusion => {
+            if precise && !device.enabled_features().occlusion_query_precise {
+                return Err(CheckBeginQueryError::OcclusionQueryPreciseFeatureNotEnabled);
+            }
+        },
+        QueryType::PipelineStatistics(_) => {
+            if precise {
+                return Err(CheckBeginQueryError::PreciseNotAllowed);
+            }
+        },
+        QueryType::Timestamp => {
+            return Err(CheckBeginQueryError::WrongQueryType);
+        },
+    }
+
+    if query_state(query) != QueryState::Reset {
+        return Err(CheckBeginQueryError::QueryNotReset);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a `begin_query` command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckBeginQueryError {
+    /// The query index is out of the range of the query pool.
+    OutOfRange,
+    /// Timestamp queries can't be begun.
+    WrongQueryType,
+    /// The query must be reset before it can be begun.
+    QueryNotReset,
+    /// Only occlusion queries can be precise.
+    PreciseNotAllowed,
+    /// The `occlusion_query_precise` feature must be enabled for precise occlusion queries.
+    OcclusionQueryPreciseFeatureNotEnabled,
+}
+
+impl error::Error for CheckBeginQueryError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckBeginQueryError::OutOfRange => {
+                "the query index is out of the range of the query pool"
+            },
+            CheckBeginQueryError::WrongQueryType => {
+                "timestamp queries can't be begun"
+            },
+            CheckBeginQueryError::QueryNotReset => {
+                "the query must be reset before it can be begun"
+            },
+            CheckBeginQueryError::PreciseNotAllowed => {
+                "only occlusion queries can be precise"
+            },
+            CheckBeginQueryError::OcclusionQueryPreciseFeatureNotEnabled => {
+                "the `occlusion_query_precise` feature must be enabled for precise occlusion \
+                 queries"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckBeginQueryError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use query::OcclusionQueryPool;
+    use query::TimestampQueryPool;
+
+    #[test]
+    fn not_reset() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = OcclusionQueryPool::new(device.clone(), 4).unwrap();
+
+        match check_begin_query(&device, &*pool, 0, false, |i| pool.query_state(i)) {
+            Err(CheckBeginQueryError::QueryNotReset) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = OcclusionQueryPool::new(device.clone(), 4).unwrap();
+
+        match check_begin_query(&device, &*pool, 4, false, |i| pool.query_state(i)) {
+            Err(CheckBeginQueryError::OutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn timestamp() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device.clone(), 4).unwrap();
+
+        match check_begin_query(&device, &*pool, 0, false, |i| pool.query_state(i)) {
+            Err(CheckBeginQueryError::WrongQueryType) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -14,7 +14,9 @@ use VulkanObject;
 use device::Device;
 use format::FormatTy;
 use image::ImageAccess;
+use image::ImageAspects;
 use image::ImageDimensions;
+use image::ImageLayout;
 use sampler::Filter;
 
 /// Checks whether a blit image command is valid.
@@ -25,12 +27,13 @@ use sampler::Filter;
 ///
 /// - Panics if the source or the destination was not created with `device`.
 ///
-pub fn check_blit_image<S, D>(device: &Device, source: &S, source_top_left: [i32; 3],
-                              source_bottom_right: [i32; 3], source_base_array_layer: u32,
-                              source_mip_level: u32, destination: &D,
+pub fn check_blit_image<S, D>(device: &Device, source: &S, source_layout: ImageLayout,
+                              source_top_left: [i32; 3], source_bottom_right: [i32; 3],
+                              source_base_array_layer: u32, source_mip_level: u32,
+                              destination: &D, destination_layout: ImageLayout,
                               destination_top_left: [i32; 3], destination_bottom_right: [i32; 3],
                               destination_base_array_layer: u32, destination_mip_level: u32,
-                              layer_count: u32, filter: Filter)
+                              layer_count: u32, aspects: ImageAspects, filter: Filter)
                               -> Result<(), CheckBlitImageError>
     where S: ?Sized + ImageAccess,
           D: ?Sized + ImageAccess
@@ -51,6 +54,26 @@ pub fn check_blit_image<S, D>(device: &Device, source: &S, source_top_left: [i32
         return Err(CheckBlitImageError::MissingTransferDestinationUsage);
     }
 
+    if source_layout != ImageLayout::TransferSrcOptimal && source_layout != ImageLayout::General {
+        return Err(CheckBlitImageError::UnexpectedSourceLayout);
+    }
+
+    if destination_layout != ImageLayout::TransferDstOptimal &&
+        destination_layout != ImageLayout::General
+    {
+        return Err(CheckBlitImageError::UnexpectedDestinationLayout);
+    }
+
+    if aspects.is_empty() || (aspects.color && (aspects.depth || aspects.stencil)) {
+        return Err(CheckBlitImageError::InvalidAspects);
+    }
+
+    if !aspects.is_subset_of(&ImageAspects::from_image(source)) ||
+        !aspects.is_subset_of(&ImageAspects::from_image(destination))
+    {
+        return Err(CheckBlitImageError::AspectsMismatch);
+    }
+
     if !source_inner.image.supports_blit_source() {
         return Err(CheckBlitImageError::SourceFormatNotSupported);
     }
@@ -59,6 +82,10 @@ pub fn check_blit_image<S, D>(device: &Device, source: &S, source_top_left: [i32
         return Err(CheckBlitImageError::DestinationFormatNotSupported);
     }
 
+    if filter == Filter::Linear && !source_inner.image.supports_linear_filtering() {
+        return Err(CheckBlitImageError::LinearFilterNotSupported);
+    }
+
     if source.samples() != 1 || destination.samples() != 1 {
         return Err(CheckBlitImageError::UnexpectedMultisampled);
     }
@@ -210,10 +237,20 @@ pub enum CheckBlitImageError {
     MissingTransferSourceUsage,
     /// The destination is missing the transfer destination usage.
     MissingTransferDestinationUsage,
+    /// The source layout must be `TransferSrcOptimal` or `General`.
+    UnexpectedSourceLayout,
+    /// The destination layout must be `TransferDstOptimal` or `General`.
+    UnexpectedDestinationLayout,
+    /// No aspect was selected, or the color aspect was selected along with depth or stencil.
+    InvalidAspects,
+    /// The source or the destination doesn't have one of the selected aspects.
+    AspectsMismatch,
     /// The format of the source image doesn't support blit operations.
     SourceFormatNotSupported,
     /// The format of the destination image doesn't support blit operations.
     DestinationFormatNotSupported,
+    /// The format of the source image doesn't support linear filtering.
+    LinearFilterNotSupported,
     /// You must use the nearest filter when blitting depth/stencil images.
     DepthStencilNearestMandatory,
     /// The format of the source and destination must be equal when blitting depth/stencil images.
@@ -243,12 +280,28 @@ impl error::Error for CheckBlitImageError {
             CheckBlitImageError::MissingTransferDestinationUsage => {
                 "the destination is missing the transfer destination usage"
             },
+            CheckBlitImageError::UnexpectedSourceLayout => {
+                "the source layout must be `TransferSrcOptimal` or `General`"
+            },
+            CheckBlitImageError::UnexpectedDestinationLayout => {
+                "the destination layout must be `TransferDstOptimal` or `General`"
+            },
+            CheckBlitImageError::InvalidAspects => {
+                "no aspect was selected, or the color aspect was selected along with depth or \
+                 stencil"
+            },
+            CheckBlitImageError::AspectsMismatch => {
+                "the source or the destination doesn't have one of the selected aspects"
+            },
             CheckBlitImageError::SourceFormatNotSupported => {
                 "the format of the source image doesn't support blit operations"
             },
             CheckBlitImageError::DestinationFormatNotSupported => {
                 "the format of the destination image doesn't support blit operations"
             },
+            CheckBlitImageError::LinearFilterNotSupported => {
+                "the format of the source image doesn't support linear filtering"
+            },
             CheckBlitImageError::DepthStencilNearestMandatory => {
                 "you must use the nearest filter when blitting depth/stencil images"
             },
@@ -283,3 +336,36 @@ impl fmt::Display for CheckBlitImageError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::D32Sfloat;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+
+    #[test]
+    fn depth_blit() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let usage = ImageUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..ImageUsage::none()
+        };
+        let src = AttachmentImage::with_usage(device.clone(), [16, 16], D32Sfloat, usage)
+            .unwrap();
+        let dst = AttachmentImage::with_usage(device.clone(), [8, 8], D32Sfloat, usage).unwrap();
+
+        check_blit_image(&device, &src, ImageLayout::TransferSrcOptimal, [0, 0, 0], [16, 16, 1],
+                         0, 0, &dst, ImageLayout::General, [0, 0, 0], [8, 8, 1], 0, 0, 1,
+                         ImageAspects::depth(), Filter::Nearest)
+            .unwrap();
+
+        match check_blit_image(&device, &src, ImageLayout::TransferSrcOptimal, [0, 0, 0],
+                               [16, 16, 1], 0, 0, &dst, ImageLayout::General, [0, 0, 0],
+                               [8, 8, 1], 0, 0, 1, ImageAspects::depth(), Filter::Linear) {
+            Err(CheckBlitImageError::DepthStencilNearestMandatory) => (),
+            _ => panic!(),
+        }
+    }
+}
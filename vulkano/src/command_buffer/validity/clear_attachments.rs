@@ -0,0 +1,338 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use command_buffer::ClearAttachment;
+use command_buffer::ClearRect;
+use format::ClearValue;
+use format::FormatTy;
+use framebuffer::ATTACHMENT_UNUSED;
+use framebuffer::RenderPassDesc;
+
+/// Checks whether a clear attachments command is valid.
+///
+/// `framebuffer_dimensions` are the width, height and number of layers of the framebuffer. The
+/// bounds of the rects aren't checked if it is `None`, which happens in secondary command
+/// buffers whose framebuffer isn't known.
+///
+/// # Panic
+///
+/// - Panics if `subpass` is out of range of the render pass.
+///
+pub fn check_clear_attachments<R>(render_pass: &R, subpass: u32,
+                                  framebuffer_dimensions: Option<[u32; 3]>,
+                                  attachments: &[ClearAttachment], rects: &[ClearRect])
+                                  -> Result<(), CheckClearAttachmentsError>
+    where R: ?Sized + RenderPassDesc
+{
+    let subpass_desc = render_pass
+        .subpass_desc(subpass as usize)
+        .expect("Subpass index out of range");
+
+    for attachment in attachments {
+        let (needs_depth, needs_stencil) = match *attachment {
+            ClearAttachment::Color(index, value) => {
+                let atch_num = match subpass_desc.color_attachments.get(index as usize) {
+                    // Clearing an unused attachment has no effect.
+                    Some(&(ATTACHMENT_UNUSED, _)) => continue,
+                    Some(&(num, _)) => num,
+                    None => {
+                        return Err(CheckClearAttachmentsError::ColorAttachmentOutOfRange {
+                                       index: index,
+                                       num_color_attachments: subpass_desc
+                                           .color_attachments
+                                           .len() as u32,
+                                   });
+                    },
+                };
+
+                let format_ty = render_pass.attachment_desc(atch_num).unwrap().format.ty();
+                let matches = match value {
+                    ClearValue::Float(_) => format_ty == FormatTy::Float,
+                    ClearValue::Int(_) => format_ty == FormatTy::Sint,
+                    ClearValue::Uint(_) => format_ty == FormatTy::Uint,
+                    _ => false,
+                };
+
+                if !matches {
+                    return Err(CheckClearAttachmentsError::WrongClearValueType { index: index });
+                }
+
+                continue;
+            },
+            ClearAttachment::Depth(depth) => {
+                if !(depth >= 0.0 && depth <= 1.0) {
+                    return Err(CheckClearAttachmentsError::DepthValueOutOfRange);
+                }
+                (true, false)
+            },
+            ClearAttachment::Stencil(_) => (false, true),
+            ClearAttachment::DepthStencil((depth, _)) => {
+                if !(depth >= 0.0 && depth <= 1.0) {
+                    return Err(CheckClearAttachmentsError::DepthValueOutOfRange);
+                }
+                (true, true)
+            },
+        };
+
+        let (has_depth, has_stencil) = render_pass
+            .has_depth_stencil_attachment(subpass)
+            .unwrap();
+        if (needs_depth && !has_depth) || (needs_stencil && !has_stencil) {
+            return Err(CheckClearAttachmentsError::MissingDepthStencilAttachment);
+        }
+    }
+
+    for (index, rect) in rects.iter().enumerate() {
+        if rect.extent[0] == 0 || rect.extent[1] == 0 || rect.layer_count == 0 {
+            return Err(CheckClearAttachmentsError::EmptyRect { index: index });
+        }
+
+        let dimensions = match framebuffer_dimensions {
+            Some(d) => d,
+            None => continue,
+        };
+
+        let layers_end = match rect.base_array_layer.checked_add(rect.layer_count) {
+            Some(end) => end,
+            None => return Err(CheckClearAttachmentsError::RectOutOfRange { index: index }),
+        };
+
+        if rect.offset[0] < 0 || rect.offset[1] < 0 ||
+            rect.offset[0] as u64 + rect.extent[0] as u64 > dimensions[0] as u64 ||
+            rect.offset[1] as u64 + rect.extent[1] as u64 > dimensions[1] as u64 ||
+            layers_end > dimensions[2]
+        {
+            return Err(CheckClearAttachmentsError::RectOutOfRange { index: index });
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen from `check_clear_attachments`.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckClearAttachmentsError {
+    /// A color attachment index is out of range of the color attachments of the subpass.
+    ColorAttachmentOutOfRange {
+        /// The index that was passed.
+        index: u32,
+        /// Number of color attachments in the subpass.
+        num_color_attachments: u32,
+    },
+    /// The clear value of a color attachment doesn't match the format of the attachment.
+    WrongClearValueType {
+        /// Index of the color attachment within the subpass.
+        index: u32,
+    },
+    /// A depth or stencil aspect is cleared, but the subpass doesn't have a depth-stencil
+    /// attachment with this aspect.
+    MissingDepthStencilAttachment,
+    /// The depth value must be between 0.0 and 1.0.
+    DepthValueOutOfRange,
+    /// A rect has a width, height or number of layers of 0.
+    EmptyRect {
+        /// Index of the rect in the list.
+        index: usize,
+    },
+    /// A rect is out of range of the framebuffer.
+    RectOutOfRange {
+        /// Index of the rect in the list.
+        index: usize,
+    },
+}
+
+impl error::Error for CheckClearAttachmentsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckClearAttachmentsError::ColorAttachmentOutOfRange { .. } => {
+                "a color attachment index is out of range of the color attachments of the subpass"
+            },
+            CheckClearAttachmentsError::WrongClearValueType { .. } => {
+                "the clear value of a color attachment doesn't match the format of the attachment"
+            },
+            CheckClearAttachmentsError::MissingDepthStencilAttachment => {
+                "a depth or stencil aspect is cleared, but the subpass doesn't have a \
+                 depth-stencil attachment with this aspect"
+            },
+            CheckClearAttachmentsError::DepthValueOutOfRange => {
+                "the depth value must be between 0.0 and 1.0"
+            },
+            CheckClearAttachmentsError::EmptyRect { .. } => {
+                "a rect has a width, height or number of layers of 0"
+            },
+            CheckClearAttachmentsError::RectOutOfRange { .. } => {
+                "a rect is out of range of the framebuffer"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckClearAttachmentsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::Format;
+    use framebuffer::LayoutAttachmentDescription;
+    use framebuffer::LayoutPassDependencyDescription;
+    use framebuffer::LayoutPassDescription;
+    use framebuffer::LoadOp;
+    use framebuffer::RenderPassDescClearValues;
+    use framebuffer::StoreOp;
+    use image::ImageLayout;
+
+    // Single subpass with a color attachment, and optionally a depth attachment.
+    struct Desc {
+        depth: bool,
+    }
+
+    unsafe impl RenderPassDesc for Desc {
+        fn num_attachments(&self) -> usize {
+            if self.depth { 2 } else { 1 }
+        }
+
+        fn attachment_desc(&self, num: usize) -> Option<LayoutAttachmentDescription> {
+            let format = match num {
+                0 => Format::R8G8B8A8Unorm,
+                1 if self.depth => Format::D16Unorm,
+                _ => return None,
+            };
+
+            Some(LayoutAttachmentDescription {
+                     format: format,
+                     samples: 1,
+                     load: LoadOp::Clear,
+                     store: StoreOp::Store,
+                     stencil_load: LoadOp::Clear,
+                     stencil_store: StoreOp::Store,
+                     initial_layout: ImageLayout::Undefined,
+                     final_layout: ImageLayout::General,
+                 })
+        }
+
+        fn num_subpasses(&self) -> usize {
+            1
+        }
+
+        fn subpass_desc(&self, num: usize) -> Option<LayoutPassDescription> {
+            if num != 0 {
+                return None;
+            }
+
+            Some(LayoutPassDescription {
+                     color_attachments: vec![(0, ImageLayout::ColorAttachmentOptimal)],
+                     depth_stencil: if self.depth {
+                         Some((1, ImageLayout::DepthStencilAttachmentOptimal))
+                     } else {
+                         None
+                     },
+                     input_attachments: vec![],
+                     resolve_attachments: vec![],
+                     preserve_attachments: vec![],
+                 })
+        }
+
+        fn num_dependencies(&self) -> usize {
+            0
+        }
+
+        fn dependency_desc(&self, _: usize) -> Option<LayoutPassDependencyDescription> {
+            None
+        }
+    }
+
+    unsafe impl RenderPassDescClearValues<Vec<ClearValue>> for Desc {
+        fn convert_clear_values(&self, values: Vec<ClearValue>)
+                                -> Box<Iterator<Item = ClearValue>> {
+            Box::new(values.into_iter())
+        }
+    }
+
+    #[test]
+    fn color_and_depth() {
+        let rp = Desc { depth: true };
+        let rect = ClearRect {
+            offset: [0, 0],
+            extent: [32, 32],
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let attachments = [
+            ClearAttachment::Color(0, ClearValue::Float([0.0; 4])),
+            ClearAttachment::Depth(1.0),
+        ];
+        check_clear_attachments(&rp, 0, Some([32, 32, 1]), &attachments, &[rect]).unwrap();
+
+        match check_clear_attachments(&rp, 0, Some([32, 32, 1]),
+                                      &[ClearAttachment::Color(1, ClearValue::Float([0.0; 4]))],
+                                      &[rect]) {
+            Err(CheckClearAttachmentsError::ColorAttachmentOutOfRange { index: 1, .. }) => (),
+            _ => panic!(),
+        }
+
+        match check_clear_attachments(&rp, 0, Some([32, 32, 1]),
+                                      &[ClearAttachment::Color(0, ClearValue::Uint([0; 4]))],
+                                      &[rect]) {
+            Err(CheckClearAttachmentsError::WrongClearValueType { index: 0 }) => (),
+            _ => panic!(),
+        }
+
+        match check_clear_attachments(&rp, 0, Some([32, 32, 1]),
+                                      &[ClearAttachment::Stencil(0)], &[rect]) {
+            Err(CheckClearAttachmentsError::MissingDepthStencilAttachment) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn rect_bounds() {
+        let rp = Desc { depth: false };
+        let attachments = [ClearAttachment::Color(0, ClearValue::Float([0.0; 4]))];
+        let rect = ClearRect {
+            offset: [16, 0],
+            extent: [32, 32],
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+
+        match check_clear_attachments(&rp, 0, Some([32, 32, 1]), &attachments, &[rect]) {
+            Err(CheckClearAttachmentsError::RectOutOfRange { index: 0 }) => (),
+            _ => panic!(),
+        }
+
+        let overflow = ClearRect {
+            offset: [0, 0],
+            base_array_layer: 1,
+            layer_count: u32::max_value(),
+            ..rect
+        };
+        match check_clear_attachments(&rp, 0, Some([32, 32, 1]), &attachments, &[overflow]) {
+            Err(CheckClearAttachmentsError::RectOutOfRange { index: 0 }) => (),
+            _ => panic!(),
+        }
+
+        // Bounds can't be checked if the framebuffer is unknown.
+        check_clear_attachments(&rp, 0, None, &attachments, &[rect]).unwrap();
+
+        let empty = ClearRect { layer_count: 0, ..rect };
+        match check_clear_attachments(&rp, 0, None, &attachments, &[rect, empty]) {
+            Err(CheckClearAttachmentsError::EmptyRect { index: 1 }) => (),
+            _ => panic!(),
+        }
+    }
+}
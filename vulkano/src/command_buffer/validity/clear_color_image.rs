@@ -13,6 +13,7 @@ use std::fmt;
 use VulkanObject;
 use device::Device;
 use image::ImageAccess;
+use image::ImageLayout;
 
 /// Checks whether a clear color image command is valid.
 ///
@@ -20,8 +21,9 @@ use image::ImageAccess;
 ///
 /// - Panics if the destination was not created with `device`.
 ///
-pub fn check_clear_color_image<I>(device: &Device, image: &I, first_layer: u32, num_layers: u32,
-                                  first_mipmap: u32, num_mipmaps: u32)
+pub fn check_clear_color_image<I>(device: &Device, image: &I, layout: ImageLayout,
+                                  first_layer: u32, num_layers: u32, first_mipmap: u32,
+                                  num_mipmaps: u32)
                                   -> Result<(), CheckClearColorImageError>
     where I: ?Sized + ImageAccess
 {
@@ -32,6 +34,10 @@ pub fn check_clear_color_image<I>(device: &Device, image: &I, first_layer: u32,
         return Err(CheckClearColorImageError::MissingTransferUsage);
     }
 
+    if layout != ImageLayout::TransferDstOptimal && layout != ImageLayout::General {
+        return Err(CheckClearColorImageError::UnexpectedLayout);
+    }
+
     if first_layer + num_layers > image.dimensions().array_layers() {
         return Err(CheckClearColorImageError::OutOfRange);
     }
@@ -48,6 +54,8 @@ pub fn check_clear_color_image<I>(device: &Device, image: &I, first_layer: u32,
 pub enum CheckClearColorImageError {
     /// The image is missing the transfer destination usage.
     MissingTransferUsage,
+    /// The layout must be `TransferDstOptimal` or `General`.
+    UnexpectedLayout,
     /// The array layers and mipmap levels are out of range.
     OutOfRange,
 }
@@ -59,6 +67,9 @@ impl error::Error for CheckClearColorImageError {
             CheckClearColorImageError::MissingTransferUsage => {
                 "the image is missing the transfer destination usage"
             },
+            CheckClearColorImageError::UnexpectedLayout => {
+                "the layout must be `TransferDstOptimal` or `General`"
+            },
             CheckClearColorImageError::OutOfRange => {
                 "the array layers and mipmap levels are out of range"
             },
@@ -0,0 +1,208 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use device::Device;
+use format::ClearValue;
+use image::ImageAccess;
+use image::ImageLayout;
+
+/// Checks whether a clear depth-stencil image command is valid.
+///
+/// # Panic
+///
+/// - Panics if the destination was not created with `device`.
+///
+pub fn check_clear_depth_stencil_image<I>(device: &Device, image: &I, layout: ImageLayout,
+                                          first_layer: u32, num_layers: u32, first_mipmap: u32,
+                                          num_mipmaps: u32, value: ClearValue)
+                                          -> Result<(), CheckClearDepthStencilImageError>
+    where I: ?Sized + ImageAccess
+{
+    assert_eq!(image.inner().image.device().internal_object(),
+               device.internal_object());
+
+    if !image.inner().image.usage_transfer_destination() {
+        return Err(CheckClearDepthStencilImageError::MissingTransferUsage);
+    }
+
+    if layout != ImageLayout::TransferDstOptimal && layout != ImageLayout::General {
+        return Err(CheckClearDepthStencilImageError::UnexpectedLayout);
+    }
+
+    if !image.format().ty().is_depth_and_or_stencil() {
+        return Err(CheckClearDepthStencilImageError::NotDepthStencilFormat);
+    }
+
+    let (depth, aspects_ok) = match value {
+        ClearValue::Depth(depth) => (Some(depth), image.has_depth()),
+        ClearValue::Stencil(_) => (None, image.has_stencil()),
+        ClearValue::DepthStencil((depth, _)) => {
+            (Some(depth), image.has_depth() && image.has_stencil())
+        },
+        _ => return Err(CheckClearDepthStencilImageError::WrongClearValueType),
+    };
+
+    if !aspects_ok {
+        return Err(CheckClearDepthStencilImageError::WrongClearValueType);
+    }
+
+    if let Some(depth) = depth {
+        if !(depth >= 0.0 && depth <= 1.0) {
+            return Err(CheckClearDepthStencilImageError::DepthValueOutOfRange);
+        }
+    }
+
+    let layers_end = match first_layer.checked_add(num_layers) {
+        Some(end) => end,
+        None => return Err(CheckClearDepthStencilImageError::OutOfRange),
+    };
+
+    if layers_end > image.dimensions().array_layers() {
+        return Err(CheckClearDepthStencilImageError::OutOfRange);
+    }
+
+    let mipmaps_end = match first_mipmap.checked_add(num_mipmaps) {
+        Some(end) => end,
+        None => return Err(CheckClearDepthStencilImageError::OutOfRange),
+    };
+
+    if mipmaps_end > image.mipmap_levels() {
+        return Err(CheckClearDepthStencilImageError::OutOfRange);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen from `check_clear_depth_stencil_image`.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckClearDepthStencilImageError {
+    /// The image is missing the transfer destination usage.
+    MissingTransferUsage,
+    /// The layout must be `TransferDstOptimal` or `General`.
+    UnexpectedLayout,
+    /// The format of the image isn't a depth, stencil or depth-stencil format.
+    NotDepthStencilFormat,
+    /// The clear value isn't a depth or stencil value, or clears an aspect that the image
+    /// doesn't have.
+    WrongClearValueType,
+    /// The depth value must be between 0.0 and 1.0.
+    DepthValueOutOfRange,
+    /// The array layers and mipmap levels are out of range.
+    OutOfRange,
+}
+
+impl error::Error for CheckClearDepthStencilImageError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckClearDepthStencilImageError::MissingTransferUsage => {
+                "the image is missing the transfer destination usage"
+            },
+            CheckClearDepthStencilImageError::UnexpectedLayout => {
+                "the layout must be `TransferDstOptimal` or `General`"
+            },
+            CheckClearDepthStencilImageError::NotDepthStencilFormat => {
+                "the format of the image isn't a depth, stencil or depth-stencil format"
+            },
+            CheckClearDepthStencilImageError::WrongClearValueType => {
+                "the clear value isn't a depth or stencil value, or clears an aspect that the \
+                 image doesn't have"
+            },
+            CheckClearDepthStencilImageError::DepthValueOutOfRange => {
+                "the depth value must be between 0.0 and 1.0"
+            },
+            CheckClearDepthStencilImageError::OutOfRange => {
+                "the array layers and mipmap levels are out of range"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckClearDepthStencilImageError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::D16Unorm;
+    use format::R8G8B8A8Unorm;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+    use std::u32;
+
+    fn usage() -> ImageUsage {
+        ImageUsage {
+            transfer_destination: true,
+            ..ImageUsage::none()
+        }
+    }
+
+    #[test]
+    fn depth() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], D16Unorm, usage())
+            .unwrap();
+
+        check_clear_depth_stencil_image(&device, &image, ImageLayout::TransferDstOptimal, 0, 1,
+                                        0, 1, ClearValue::Depth(1.0))
+            .unwrap();
+
+        match check_clear_depth_stencil_image(&device, &image, ImageLayout::TransferDstOptimal,
+                                              0, 1, 0, 1, ClearValue::Stencil(0)) {
+            Err(CheckClearDepthStencilImageError::WrongClearValueType) => (),
+            _ => panic!(),
+        }
+
+        match check_clear_depth_stencil_image(&device, &image, ImageLayout::TransferDstOptimal,
+                                              0, 1, 0, 1, ClearValue::Depth(2.0)) {
+            Err(CheckClearDepthStencilImageError::DepthValueOutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn range_overflow() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], D16Unorm, usage())
+            .unwrap();
+
+        match check_clear_depth_stencil_image(&device, &image, ImageLayout::TransferDstOptimal,
+                                              1, u32::MAX, 0, 1, ClearValue::Depth(1.0)) {
+            Err(CheckClearDepthStencilImageError::OutOfRange) => (),
+            _ => panic!(),
+        }
+
+        match check_clear_depth_stencil_image(&device, &image, ImageLayout::TransferDstOptimal,
+                                              0, 1, 1, u32::MAX, ClearValue::Depth(1.0)) {
+            Err(CheckClearDepthStencilImageError::OutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn color_image() {
+        let (device, _) = gfx_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], R8G8B8A8Unorm, usage())
+            .unwrap();
+
+        match check_clear_depth_stencil_image(&device, &image, ImageLayout::TransferDstOptimal,
+                                              0, 1, 0, 1, ClearValue::Depth(1.0)) {
+            Err(CheckClearDepthStencilImageError::NotDepthStencilFormat) => (),
+            _ => panic!(),
+        }
+    }
+}
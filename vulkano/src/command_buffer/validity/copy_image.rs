@@ -15,7 +15,9 @@ use device::Device;
 use format::FormatTy;
 use format::PossibleCompressedFormatDesc;
 use image::ImageAccess;
+use image::ImageAspects;
 use image::ImageDimensions;
+use image::ImageLayout;
 
 /// Checks whether a copy image command is valid.
 ///
@@ -25,11 +27,12 @@ use image::ImageDimensions;
 ///
 /// - Panics if the source or the destination was not created with `device`.
 ///
-pub fn check_copy_image<S, D>(device: &Device, source: &S, source_offset: [i32; 3],
-                              source_base_array_layer: u32, source_mip_level: u32,
-                              destination: &D, destination_offset: [i32; 3],
+pub fn check_copy_image<S, D>(device: &Device, source: &S, source_layout: ImageLayout,
+                              source_offset: [i32; 3], source_base_array_layer: u32,
+                              source_mip_level: u32, destination: &D,
+                              destination_layout: ImageLayout, destination_offset: [i32; 3],
                               destination_base_array_layer: u32, destination_mip_level: u32,
-                              extent: [u32; 3], layer_count: u32)
+                              extent: [u32; 3], layer_count: u32, aspects: ImageAspects)
                               -> Result<(), CheckCopyImageError>
     where S: ?Sized + ImageAccess,
           D: ?Sized + ImageAccess
@@ -50,6 +53,26 @@ pub fn check_copy_image<S, D>(device: &Device, source: &S, source_offset: [i32;
         return Err(CheckCopyImageError::MissingTransferDestinationUsage);
     }
 
+    if source_layout != ImageLayout::TransferSrcOptimal && source_layout != ImageLayout::General {
+        return Err(CheckCopyImageError::UnexpectedSourceLayout);
+    }
+
+    if destination_layout != ImageLayout::TransferDstOptimal &&
+        destination_layout != ImageLayout::General
+    {
+        return Err(CheckCopyImageError::UnexpectedDestinationLayout);
+    }
+
+    if aspects.is_empty() || (aspects.color && (aspects.depth || aspects.stencil)) {
+        return Err(CheckCopyImageError::InvalidAspects);
+    }
+
+    if !aspects.is_subset_of(&ImageAspects::from_image(source)) ||
+        !aspects.is_subset_of(&ImageAspects::from_image(destination))
+    {
+        return Err(CheckCopyImageError::AspectsMismatch);
+    }
+
     if source.samples() != destination.samples() {
         return Err(CheckCopyImageError::SampleCountMismatch);
     }
@@ -170,6 +193,14 @@ pub enum CheckCopyImageError {
     MissingTransferSourceUsage,
     /// The destination is missing the transfer destination usage.
     MissingTransferDestinationUsage,
+    /// The source layout must be `TransferSrcOptimal` or `General`.
+    UnexpectedSourceLayout,
+    /// The destination layout must be `TransferDstOptimal` or `General`.
+    UnexpectedDestinationLayout,
+    /// No aspect was selected, or the color aspect was selected along with depth or stencil.
+    InvalidAspects,
+    /// The source or the destination doesn't have one of the selected aspects.
+    AspectsMismatch,
     /// The number of samples in the source and destination do not match.
     SampleCountMismatch,
     /// The format of the source and destination must be equal when copying depth/stencil images.
@@ -197,6 +228,19 @@ impl error::Error for CheckCopyImageError {
             CheckCopyImageError::MissingTransferDestinationUsage => {
                 "the destination is missing the transfer destination usage"
             },
+            CheckCopyImageError::UnexpectedSourceLayout => {
+                "the source layout must be `TransferSrcOptimal` or `General`"
+            },
+            CheckCopyImageError::UnexpectedDestinationLayout => {
+                "the destination layout must be `TransferDstOptimal` or `General`"
+            },
+            CheckCopyImageError::InvalidAspects => {
+                "no aspect was selected, or the color aspect was selected along with depth or \
+                 stencil"
+            },
+            CheckCopyImageError::AspectsMismatch => {
+                "the source or the destination doesn't have one of the selected aspects"
+            },
             CheckCopyImageError::SampleCountMismatch => {
                 "the number of samples in the source and destination do not match"
             }
@@ -228,3 +272,86 @@ impl fmt::Display for CheckCopyImageError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::D32Sfloat;
+    use format::R8G8B8A8Unorm;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+
+    fn transfer_usage() -> ImageUsage {
+        ImageUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..ImageUsage::none()
+        }
+    }
+
+    #[test]
+    fn depth_copy() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let src = AttachmentImage::with_usage(device.clone(), [16, 16], D32Sfloat,
+                                              transfer_usage())
+            .unwrap();
+        let dst = AttachmentImage::with_usage(device.clone(), [16, 16], D32Sfloat,
+                                              transfer_usage())
+            .unwrap();
+
+        check_copy_image(&device, &src, ImageLayout::General, [0, 0, 0], 0, 0,
+                         &dst, ImageLayout::TransferDstOptimal, [0, 0, 0], 0, 0,
+                         [16, 16, 1], 1, ImageAspects::depth())
+            .unwrap();
+    }
+
+    #[test]
+    fn wrong_layout() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let src = AttachmentImage::with_usage(device.clone(), [16, 16], R8G8B8A8Unorm,
+                                              transfer_usage())
+            .unwrap();
+        let dst = AttachmentImage::with_usage(device.clone(), [16, 16], R8G8B8A8Unorm,
+                                              transfer_usage())
+            .unwrap();
+
+        match check_copy_image(&device, &src, ImageLayout::TransferDstOptimal, [0, 0, 0], 0, 0,
+                               &dst, ImageLayout::TransferDstOptimal, [0, 0, 0], 0, 0,
+                               [16, 16, 1], 1, ImageAspects::color()) {
+            Err(CheckCopyImageError::UnexpectedSourceLayout) => (),
+            _ => panic!(),
+        }
+
+        match check_copy_image(&device, &src, ImageLayout::TransferSrcOptimal, [0, 0, 0], 0, 0,
+                               &dst, ImageLayout::ShaderReadOnlyOptimal, [0, 0, 0], 0, 0,
+                               [16, 16, 1], 1, ImageAspects::color()) {
+            Err(CheckCopyImageError::UnexpectedDestinationLayout) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn wrong_aspects() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let src = AttachmentImage::with_usage(device.clone(), [16, 16], R8G8B8A8Unorm,
+                                              transfer_usage())
+            .unwrap();
+        let dst = AttachmentImage::with_usage(device.clone(), [16, 16], R8G8B8A8Unorm,
+                                              transfer_usage())
+            .unwrap();
+
+        match check_copy_image(&device, &src, ImageLayout::TransferSrcOptimal, [0, 0, 0], 0, 0,
+                               &dst, ImageLayout::TransferDstOptimal, [0, 0, 0], 0, 0,
+                               [16, 16, 1], 1, ImageAspects::depth()) {
+            Err(CheckCopyImageError::AspectsMismatch) => (),
+            _ => panic!(),
+        }
+
+        match check_copy_image(&device, &src, ImageLayout::TransferSrcOptimal, [0, 0, 0], 0, 0,
+                               &dst, ImageLayout::TransferDstOptimal, [0, 0, 0], 0, 0,
+                               [16, 16, 1], 1, ImageAspects::none()) {
+            Err(CheckCopyImageError::InvalidAspects) => (),
+            _ => panic!(),
+        }
+    }
+}
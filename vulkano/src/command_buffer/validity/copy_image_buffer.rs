@@ -9,6 +9,7 @@
 
 use std::error;
 use std::fmt;
+use std::mem;
 
 use VulkanObject;
 use buffer::TypedBufferAccess;
@@ -18,6 +19,8 @@ use format::AcceptsPixels;
 use format::Format;
 use format::IncompatiblePixelsType;
 use image::ImageAccess;
+use image::ImageAspects;
+use image::ImageLayout;
 
 /// Type of operation to check.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -35,9 +38,10 @@ pub enum CheckCopyBufferImageTy {
 ///
 // TODO: handle compressed image formats
 pub fn check_copy_buffer_image<B, I, P>(device: &Device, buffer: &B, image: &I,
-                                        ty: CheckCopyBufferImageTy, image_offset: [u32; 3],
-                                        image_size: [u32; 3], image_first_layer: u32,
-                                        image_num_layers: u32, image_mipmap: u32)
+                                        ty: CheckCopyBufferImageTy, image_layout: ImageLayout,
+                                        image_offset: [u32; 3], image_size: [u32; 3],
+                                        image_first_layer: u32, image_num_layers: u32,
+                                        image_mipmap: u32, image_aspect: ImageAspects)
                                         -> Result<(), CheckCopyBufferImageError>
     where I: ?Sized + ImageAccess,
           B: ?Sized + TypedBufferAccess<Content = [P]>,
@@ -59,6 +63,11 @@ pub fn check_copy_buffer_image<B, I, P>(device: &Device, buffer: &B, image: &I,
             if !image_inner.image.usage_transfer_destination() {
                 return Err(CheckCopyBufferImageError::DestinationMissingTransferUsage);
             }
+            if image_layout != ImageLayout::TransferDstOptimal &&
+                image_layout != ImageLayout::General
+            {
+                return Err(CheckCopyBufferImageError::UnexpectedImageLayout);
+            }
         },
         CheckCopyBufferImageTy::ImageToBuffer => {
             if !image_inner.image.usage_transfer_source() {
@@ -67,9 +76,27 @@ pub fn check_copy_buffer_image<B, I, P>(device: &Device, buffer: &B, image: &I,
             if !buffer_inner.buffer.usage_transfer_destination() {
                 return Err(CheckCopyBufferImageError::DestinationMissingTransferUsage);
             }
+            if image_layout != ImageLayout::TransferSrcOptimal &&
+                image_layout != ImageLayout::General
+            {
+                return Err(CheckCopyBufferImageError::UnexpectedImageLayout);
+            }
         },
     }
 
+    // Exactly one aspect must be selected.
+    if [image_aspect.color, image_aspect.depth, image_aspect.stencil]
+        .iter()
+        .filter(|&&a| a)
+        .count() != 1
+    {
+        return Err(CheckCopyBufferImageError::InvalidAspects);
+    }
+
+    if !image_aspect.is_subset_of(&ImageAspects::from_image(image)) {
+        return Err(CheckCopyBufferImageError::AspectsMismatch);
+    }
+
     if image.samples() != 1 {
         return Err(CheckCopyBufferImageError::UnexpectedMultisampled);
     }
@@ -95,11 +122,21 @@ pub fn check_copy_buffer_image<B, I, P>(device: &Device, buffer: &B, image: &I,
         return Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange);
     }
 
-    image.format().ensure_accepts()?;
-
     {
         let num_texels = image_size[0] * image_size[1] * image_size[2] * image_num_layers;
-        let required_len = num_texels as usize * image.format().rate() as usize;
+        let required_len = if image_aspect.color {
+            image.format().ensure_accepts()?;
+            num_texels as usize * image.format().rate() as usize
+        } else {
+            // The depth or stencil values are tightly packed in the buffer, even if the image
+            // has both aspects.
+            let aspect_size = depth_stencil_aspect_size(image.format(), image_aspect);
+            if aspect_size % mem::size_of::<P>() != 0 {
+                return Err(CheckCopyBufferImageError::WrongPixelType(IncompatiblePixelsType));
+            }
+            num_texels as usize * aspect_size / mem::size_of::<P>()
+        };
+
         if required_len > buffer.len() {
             return Err(CheckCopyBufferImageError::BufferTooSmall {
                            required_len: required_len,
@@ -113,6 +150,21 @@ pub fn check_copy_buffer_image<B, I, P>(device: &Device, buffer: &B, image: &I,
     Ok(())
 }
 
+// Returns the size in bytes of one texel of the depth or stencil aspect of `format` when it is
+// copied to or from a buffer.
+fn depth_stencil_aspect_size(format: Format, aspect: ImageAspects) -> usize {
+    if aspect.stencil {
+        return 1;
+    }
+
+    match format {
+        Format::D16Unorm | Format::D16Unorm_S8Uint => 2,
+        Format::X8_D24UnormPack32 | Format::D24Unorm_S8Uint | Format::D32Sfloat |
+        Format::D32Sfloat_S8Uint => 4,
+        _ => unreachable!(),
+    }
+}
+
 /// Error that can happen from `check_copy_buffer_image`.
 #[derive(Debug, Copy, Clone)]
 pub enum CheckCopyBufferImageError {
@@ -120,8 +172,15 @@ pub enum CheckCopyBufferImageError {
     SourceMissingTransferUsage,
     /// The destination buffer or image is missing the transfer destination usage.
     DestinationMissingTransferUsage,
+    /// The image layout must be `General`, or `TransferSrcOptimal` or `TransferDstOptimal`
+    /// depending on the direction of the copy.
+    UnexpectedImageLayout,
     /// The source and destination are overlapping.
     OverlappingRanges,
+    /// Not exactly one of the color, depth and stencil aspects was selected.
+    InvalidAspects,
+    /// The image doesn't have the selected aspect.
+    AspectsMismatch,
     /// The image must not be multisampled.
     UnexpectedMultisampled,
     /// The image coordinates are out of range.
@@ -147,9 +206,18 @@ impl error::Error for CheckCopyBufferImageError {
             CheckCopyBufferImageError::DestinationMissingTransferUsage => {
                 "the destination buffer is missing the transfer destination usage"
             },
+            CheckCopyBufferImageError::UnexpectedImageLayout => {
+                "the image layout is not valid for the direction of the copy"
+            },
             CheckCopyBufferImageError::OverlappingRanges => {
                 "the source and destination are overlapping"
             },
+            CheckCopyBufferImageError::InvalidAspects => {
+                "not exactly one of the color, depth and stencil aspects was selected"
+            },
+            CheckCopyBufferImageError::AspectsMismatch => {
+                "the image doesn't have the selected aspect"
+            },
             CheckCopyBufferImageError::UnexpectedMultisampled => {
                 "the image must not be multisampled"
             },
@@ -188,3 +256,100 @@ impl From<IncompatiblePixelsType> for CheckCopyBufferImageError {
         CheckCopyBufferImageError::WrongPixelType(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use format::D16Unorm;
+    use format::D32Sfloat;
+    use image::AttachmentImage;
+    use image::ImageUsage;
+
+    fn transfer_usage() -> ImageUsage {
+        ImageUsage {
+            transfer_source: true,
+            transfer_destination: true,
+            ..ImageUsage::none()
+        }
+    }
+
+    #[test]
+    fn depth_copy() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], D16Unorm,
+                                                transfer_usage())
+            .unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    (0 .. 16 * 16).map(|_| 0u16))
+            .unwrap();
+
+        check_copy_buffer_image(&device, &buffer, &image, CheckCopyBufferImageTy::BufferToImage,
+                                ImageLayout::TransferDstOptimal, [0, 0, 0], [16, 16, 1], 0, 1, 0,
+                                ImageAspects::depth())
+            .unwrap();
+
+        match check_copy_buffer_image(&device, &buffer, &image,
+                                      CheckCopyBufferImageTy::BufferToImage,
+                                      ImageLayout::TransferDstOptimal, [0, 0, 0], [16, 16, 2],
+                                      0, 1, 0, ImageAspects::depth()) {
+            Err(CheckCopyBufferImageError::ImageCoordinatesOutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_buffer_too_small() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], D32Sfloat,
+                                                transfer_usage())
+            .unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    (0 .. 16 * 16).map(|_| 0u16))
+            .unwrap();
+
+        match check_copy_buffer_image(&device, &buffer, &image,
+                                      CheckCopyBufferImageTy::BufferToImage,
+                                      ImageLayout::TransferDstOptimal, [0, 0, 0], [16, 16, 1],
+                                      0, 1, 0, ImageAspects::depth()) {
+            Err(CheckCopyBufferImageError::BufferTooSmall { required_len: 512, .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn wrong_aspect() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let image = AttachmentImage::with_usage(device.clone(), [16, 16], D32Sfloat,
+                                                transfer_usage())
+            .unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
+                                                    (0 .. 16 * 16).map(|_| 0.0f32))
+            .unwrap();
+
+        match check_copy_buffer_image(&device, &buffer, &image,
+                                      CheckCopyBufferImageTy::ImageToBuffer,
+                                      ImageLayout::TransferSrcOptimal, [0, 0, 0], [16, 16, 1],
+                                      0, 1, 0, ImageAspects::stencil()) {
+            Err(CheckCopyBufferImageError::AspectsMismatch) => (),
+            _ => panic!(),
+        }
+
+        match check_copy_buffer_image(&device, &buffer, &image,
+                                      CheckCopyBufferImageTy::ImageToBuffer,
+                                      ImageLayout::TransferSrcOptimal, [0, 0, 0], [16, 16, 1],
+                                      0, 1, 0, ImageAspects::color()) {
+            Err(CheckCopyBufferImageError::AspectsMismatch) => (),
+            _ => panic!(),
+        }
+
+        match check_copy_buffer_image(&device, &buffer, &image,
+                                      CheckCopyBufferImageTy::ImageToBuffer,
+                                      ImageLayout::TransferSrcOptimal, [0, 0, 0], [16, 16, 1],
+                                      0, 1, 0, ImageAspects::depth_stencil()) {
+            Err(CheckCopyBufferImageError::InvalidAspects) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+use std::mem;
+
+use VulkanObject;
+use buffer::TypedBufferAccess;
+use device::Device;
+use device::DeviceOwned;
+use query::QueryPoolAccess;
+use query::QueryResultElement;
+use query::QueryResultFlags;
+use query::QueryState;
+use query::QueryType;
+
+/// Checks whether a copy query pool results command is valid.
+///
+/// `query_state` must return the state of a query of `pool` at the point of the command buffer
+/// where the command is recorded.
+///
+/// # Panic
+///
+/// - Panics if the query pool or the buffer were not created with `device`.
+///
+pub fn check_copy_query_pool_results<Q, D, T, F>(device: &Device, pool: &Q, first_index: u32,
+                                                 count: u32, destination: &D,
+                                                 flags: QueryResultFlags, query_state: F)
+                                                 -> Result<(), CheckCopyQueryPoolResultsError>
+    where Q: ?Sized + QueryPoolAccess,
+          D: ?Sized + TypedBufferAccess<Content = [T]>,
+          T: QueryResultElement,
+          F: Fn(u32) -> QueryState
+{
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+    assert_eq!(destination.inner().buffer.device().internal_object(),
+               device.internal_object());
+
+    if count == 0 || pool.inner().queries_range(first_index, count).is_none() {
+        return Err(CheckCopyQueryPoolResultsError::OutOfRange);
+    }
+
+    if flags.partial {
+        if let QueryType::Timestamp = pool.inner().ty() {
+            return Err(CheckCopyQueryPoolResultsError::InvalidFlags);
+        }
+    }
+
+    if !destination.inner().buffer.usage_transfer_destination() {
+        return Err(CheckCopyQueryPoolResultsError::BufferMissingUsage);
+    }
+
+    let values_per_query = pool.inner().num_values_per_query() as usize +
+        if flags.with_availability { 1 } else { 0 };
+    let required_len = values_per_query * count as usize;
+    let actual_len = destination.size() / mem::size_of::<T>();
+    if actual_len < required_len {
+        return Err(CheckCopyQueryPoolResultsError::BufferTooSmall {
+                       required_len: required_len,
+                       actual_len: actual_len,
+                   });
+    }
+
+    if flags.wait {
+        for index in first_index .. first_index + count {
+            match query_state(index) {
+                QueryState::Ended | QueryState::Available => (),
+                _ => return Err(CheckCopyQueryPoolResultsError::QueryNotEnded { index: index }),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a `copy_query_pool_results` command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckCopyQueryPoolResultsError {
+    /// The range of queries is empty or out of the range of the query pool.
+    OutOfRange,
+    /// The flags are not valid for this type of query.
+    InvalidFlags,
+    /// The "transfer destination" usage must be enabled on the buffer.
+    BufferMissingUsage,
+    /// The destination buffer is too small to hold the results.
+    BufferTooSmall {
+        /// Minimum number of elements of the buffer.
+        required_len: usize,
+        /// Actual number of elements of the buffer.
+        actual_len: usize,
+    },
+    /// The results are waited for, but a query has not been ended or written since it was last
+    /// reset.
+    QueryNotEnded {
+        /// Index of the query.
+        index: u32,
+    },
+}
+
+impl error::Error for CheckCopyQueryPoolResultsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckCopyQueryPoolResultsError::OutOfRange => {
+                "the range of queries is empty or out of the range of the query pool"
+            },
+            CheckCopyQueryPoolResultsError::InvalidFlags => {
+                "the flags are not valid for this type of query"
+            },
+            CheckCopyQueryPoolResultsError::BufferMissingUsage => {
+                "the transfer destination usage must be enabled on the buffer"
+            },
+            CheckCopyQueryPoolResultsError::BufferTooSmall { .. } => {
+                "the destination buffer is too small to hold the results"
+            },
+            CheckCopyQueryPoolResultsError::QueryNotEnded { .. } => {
+                "the results are waited for, but a query has not been ended or written since it \
+                 was last reset"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckCopyQueryPoolResultsError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use query::OcclusionQueryPool;
+    use std::iter;
+
+    #[test]
+    fn buffer_too_small() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = OcclusionQueryPool::new(device.clone(), 4).unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::all(),
+                                                    iter::repeat(0u64).take(4))
+            .unwrap();
+
+        let flags = QueryResultFlags {
+            with_availability: true,
+            ..QueryResultFlags::none()
+        };
+        match check_copy_query_pool_results(&device, &*pool, 0, 4, &buffer, flags,
+                                            |i| pool.query_state(i)) {
+            Err(CheckCopyQueryPoolResultsError::BufferTooSmall {
+                    required_len: 8,
+                    actual_len: 4,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn missing_usage() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = OcclusionQueryPool::new(device.clone(), 4).unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::vertex_buffer(),
+                                                    iter::repeat(0u64).take(4))
+            .unwrap();
+
+        match check_copy_query_pool_results(&device, &*pool, 0, 4, &buffer,
+                                            QueryResultFlags::none(), |i| pool.query_state(i)) {
+            Err(CheckCopyQueryPoolResultsError::BufferMissingUsage) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn wait_on_unreset_query() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = OcclusionQueryPool::new(device.clone(), 4).unwrap();
+        let buffer = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::all(),
+                                                    iter::repeat(0u64).take(4))
+            .unwrap();
+
+        let flags = QueryResultFlags {
+            wait: true,
+            ..QueryResultFlags::none()
+        };
+        match check_copy_query_pool_results(&device, &*pool, 0, 4, &buffer, flags,
+                                            |i| pool.query_state(i)) {
+            Err(CheckCopyQueryPoolResultsError::QueryNotEnded { index: 0 }) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use device::Device;
+
+/// Checks whether a debug marker command is valid.
+///
+/// `active_markers` is the number of markers that have been begun and not ended yet in the
+/// command buffer. Pass `end` as true for `debug_marker_end` commands.
+pub fn check_debug_marker(device: &Device, active_markers: u32, end: bool)
+                          -> Result<(), CheckDebugMarkerError> {
+    if !device.loaded_extensions().ext_debug_marker {
+        return Err(CheckDebugMarkerError::ExtensionNotEnabled);
+    }
+
+    if end && active_markers == 0 {
+        return Err(CheckDebugMarkerError::NoActiveMarker);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a debug marker command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckDebugMarkerError {
+    /// The `VK_EXT_debug_marker` extension must be enabled on the device.
+    ExtensionNotEnabled,
+    /// Tried to end a debug marker while no marker was begun in the command buffer.
+    NoActiveMarker,
+}
+
+impl error::Error for CheckDebugMarkerError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckDebugMarkerError::ExtensionNotEnabled => {
+                "the `VK_EXT_debug_marker` extension must be enabled on the device"
+            },
+            CheckDebugMarkerError::NoActiveMarker => {
+                "tried to end a debug marker while no marker was begun in the command buffer"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckDebugMarkerError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_not_enabled() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        match check_debug_marker(&device, 0, false) {
+            Err(CheckDebugMarkerError::ExtensionNotEnabled) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -22,7 +22,7 @@ pub fn check_dynamic_state_validity<Pl>(pipeline: &Pl, state: &DynamicState)
 
     if pipeline.has_dynamic_line_width() {
         if let Some(value) = state.line_width {
-            if value != 1.0 && !pipeline.device().enabled_features().wide_lines {
+            if value != 1.0 && !device.enabled_features().wide_lines {
                 return Err(CheckDynamicStateValidityError::LineWidthMissingExtension);
             }
         } else {
@@ -71,7 +71,77 @@ pub fn check_dynamic_state_validity<Pl>(pipeline: &Pl, state: &DynamicState)
         }
     }
 
-    // TODO: don't forget to implement the rest
+    if pipeline.has_dynamic_depth_bias() {
+        if let Some(bias) = state.depth_bias {
+            if bias.clamp != 0.0 && !device.enabled_features().depth_bias_clamp {
+                return Err(CheckDynamicStateValidityError::DepthBiasClampMissingFeature);
+            }
+        } else {
+            return Err(CheckDynamicStateValidityError::DepthBiasMissing);
+        }
+
+    } else {
+        if state.depth_bias.is_some() {
+            return Err(CheckDynamicStateValidityError::DepthBiasNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_depth_bounds() {
+        if let Some(ref bounds) = state.depth_bounds {
+            if !(bounds.start >= 0.0 && bounds.start <= 1.0) ||
+                !(bounds.end >= 0.0 && bounds.end <= 1.0)
+            {
+                return Err(CheckDynamicStateValidityError::DepthBoundsOutOfRange);
+            }
+        } else {
+            return Err(CheckDynamicStateValidityError::DepthBoundsMissing);
+        }
+
+    } else {
+        if state.depth_bounds.is_some() {
+            return Err(CheckDynamicStateValidityError::DepthBoundsNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_stencil_compare_mask() {
+        if state.compare_mask.is_none() {
+            return Err(CheckDynamicStateValidityError::StencilCompareMaskMissing);
+        }
+    } else {
+        if state.compare_mask.is_some() {
+            return Err(CheckDynamicStateValidityError::StencilCompareMaskNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_stencil_write_mask() {
+        if state.write_mask.is_none() {
+            return Err(CheckDynamicStateValidityError::StencilWriteMaskMissing);
+        }
+    } else {
+        if state.write_mask.is_some() {
+            return Err(CheckDynamicStateValidityError::StencilWriteMaskNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_stencil_reference() {
+        if state.reference.is_none() {
+            return Err(CheckDynamicStateValidityError::StencilReferenceMissing);
+        }
+    } else {
+        if state.reference.is_some() {
+            return Err(CheckDynamicStateValidityError::StencilReferenceNotDynamic);
+        }
+    }
+
+    if pipeline.has_dynamic_blend_constants() {
+        if state.blend_constants.is_none() {
+            return Err(CheckDynamicStateValidityError::BlendConstantsMissing);
+        }
+    } else {
+        if state.blend_constants.is_some() {
+            return Err(CheckDynamicStateValidityError::BlendConstantsNotDynamic);
+        }
+    }
 
     Ok(())
 }
@@ -108,6 +178,39 @@ pub enum CheckDynamicStateValidityError {
         /// Number of scissors that were passed.
         obtained: usize,
     },
+    /// Passed a dynamic depth bias, while the pipeline doesn't have depth bias set as dynamic.
+    DepthBiasNotDynamic,
+    /// The pipeline has a dynamic depth bias, but no depth bias was passed.
+    DepthBiasMissing,
+    /// The `depth_bias_clamp` feature must be enabled in order to use a depth bias clamp
+    /// different from 0.0.
+    DepthBiasClampMissingFeature,
+    /// Passed dynamic depth bounds, while the pipeline doesn't have depth bounds set as dynamic.
+    DepthBoundsNotDynamic,
+    /// The pipeline has dynamic depth bounds, but no depth bounds were passed.
+    DepthBoundsMissing,
+    /// The depth bounds must be between 0.0 and 1.0.
+    DepthBoundsOutOfRange,
+    /// Passed dynamic stencil compare masks, while the pipeline doesn't have stencil compare
+    /// masks set as dynamic.
+    StencilCompareMaskNotDynamic,
+    /// The pipeline has dynamic stencil compare masks, but no compare masks were passed.
+    StencilCompareMaskMissing,
+    /// Passed dynamic stencil write masks, while the pipeline doesn't have stencil write masks
+    /// set as dynamic.
+    StencilWriteMaskNotDynamic,
+    /// The pipeline has dynamic stencil write masks, but no write masks were passed.
+    StencilWriteMaskMissing,
+    /// Passed dynamic stencil references, while the pipeline doesn't have stencil references set
+    /// as dynamic.
+    StencilReferenceNotDynamic,
+    /// The pipeline has dynamic stencil references, but no references were passed.
+    StencilReferenceMissing,
+    /// Passed dynamic blend constants, while the pipeline doesn't have blend constants set as
+    /// dynamic.
+    BlendConstantsNotDynamic,
+    /// The pipeline has dynamic blend constants, but no blend constants were passed.
+    BlendConstantsMissing,
 }
 
 impl error::Error for CheckDynamicStateValidityError {
@@ -144,6 +247,55 @@ impl error::Error for CheckDynamicStateValidityError {
             CheckDynamicStateValidityError::ScissorsCountMismatch { .. } => {
                 "the number of dynamic scissors doesn't match the expected number of scissors"
             },
+            CheckDynamicStateValidityError::DepthBiasNotDynamic => {
+                "passed a dynamic depth bias, while the pipeline doesn't have depth bias set as \
+                 dynamic"
+            },
+            CheckDynamicStateValidityError::DepthBiasMissing => {
+                "the pipeline has a dynamic depth bias, but no depth bias was passed"
+            },
+            CheckDynamicStateValidityError::DepthBiasClampMissingFeature => {
+                "the `depth_bias_clamp` feature must be enabled in order to use a depth bias \
+                 clamp different from 0.0"
+            },
+            CheckDynamicStateValidityError::DepthBoundsNotDynamic => {
+                "passed dynamic depth bounds, while the pipeline doesn't have depth bounds set as \
+                 dynamic"
+            },
+            CheckDynamicStateValidityError::DepthBoundsMissing => {
+                "the pipeline has dynamic depth bounds, but no depth bounds were passed"
+            },
+            CheckDynamicStateValidityError::DepthBoundsOutOfRange => {
+                "the depth bounds must be between 0.0 and 1.0"
+            },
+            CheckDynamicStateValidityError::StencilCompareMaskNotDynamic => {
+                "passed dynamic stencil compare masks, while the pipeline doesn't have stencil \
+                 compare masks set as dynamic"
+            },
+            CheckDynamicStateValidityError::StencilCompareMaskMissing => {
+                "the pipeline has dynamic stencil compare masks, but no compare masks were passed"
+            },
+            CheckDynamicStateValidityError::StencilWriteMaskNotDynamic => {
+                "passed dynamic stencil write masks, while the pipeline doesn't have stencil \
+                 write masks set as dynamic"
+            },
+            CheckDynamicStateValidityError::StencilWriteMaskMissing => {
+                "the pipeline has dynamic stencil write masks, but no write masks were passed"
+            },
+            CheckDynamicStateValidityError::StencilReferenceNotDynamic => {
+                "passed dynamic stencil references, while the pipeline doesn't have stencil \
+                 references set as dynamic"
+            },
+            CheckDynamicStateValidityError::StencilReferenceMissing => {
+                "the pipeline has dynamic stencil references, but no references were passed"
+            },
+            CheckDynamicStateValidityError::BlendConstantsNotDynamic => {
+                "passed dynamic blend constants, while the pipeline doesn't have blend constants \
+                 set as dynamic"
+            },
+            CheckDynamicStateValidityError::BlendConstantsMissing => {
+                "the pipeline has dynamic blend constants, but no blend constants were passed"
+            },
         }
     }
 }
@@ -155,4 +307,315 @@ impl fmt::Display for CheckDynamicStateValidityError {
     }
 }
 
-// TODO: tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::sync::Arc;
+
+    use command_buffer::DynamicStencilValue;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use device::Device;
+    use device::DeviceExtensions;
+    use features::Features;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::Subpass;
+    use instance::Instance;
+    use instance::InstanceExtensions;
+    use instance::PhysicalDevice;
+    use pipeline::GraphicsPipeline;
+    use pipeline::raster::DepthBias;
+    use pipeline::shader::EmptyShaderInterfaceDef;
+    use pipeline::shader::GraphicsShaderType;
+    use pipeline::shader::ShaderModule;
+    use pipeline::viewport::Viewport;
+    use testing::MockLoader;
+
+    // Builds a pipeline for the single subpass of an empty render pass, with one dynamic viewport,
+    // then calls each of the `$method`s on the builder. The mock driver doesn't look at the
+    // content of shader modules.
+    macro_rules! pipeline {
+        ($device:expr $(, $method:ident)*) => ({
+            let device = $device;
+            let module = unsafe { ShaderModule::new(device.clone(), &[0; 8]).unwrap() };
+            let rp = Arc::new(EmptySinglePassRenderPassDesc.build_render_pass(device.clone())
+                                  .unwrap());
+
+            let name = CStr::from_bytes_with_nul(b"main\0").unwrap();
+            let vs = unsafe {
+                module.graphics_entry_point::<(), _, _, _>(name,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyPipelineDesc,
+                                                           GraphicsShaderType::Vertex)
+            };
+            let fs = unsafe {
+                module.graphics_entry_point::<(), _, _, _>(name,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyShaderInterfaceDef,
+                                                           EmptyPipelineDesc,
+                                                           GraphicsShaderType::Fragment)
+            };
+
+            let pipeline: Arc<GraphicsPipelineAbstract + Send + Sync> =
+                Arc::new(GraphicsPipeline::start()
+                             .vertex_input_single_buffer::<()>()
+                             .vertex_shader(vs, ())
+                             .triangle_list()
+                             .viewports_dynamic_scissors_irrelevant(1)
+                             .fragment_shader(fs, ())
+                             .render_pass(Subpass::from(rp, 0).unwrap())
+                             $(.$method())*
+                             .build(device)
+                             .unwrap());
+            pipeline
+        });
+    }
+
+    // Returns a mock device with the `depth_bounds` feature enabled.
+    fn depth_bounds_device() -> Arc<Device> {
+        let loader = MockLoader::new();
+        let instance = Instance::with_loader(loader.function_pointers(),
+                                             None,
+                                             &InstanceExtensions::none(),
+                                             None)
+            .unwrap();
+        let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+        let family = physical.queue_family_by_id(0).unwrap();
+        let features = Features {
+            depth_bounds: true,
+            ..Features::none()
+        };
+        let (device, _) = Device::new(physical,
+                                      &features,
+                                      &DeviceExtensions::none(),
+                                      Some((family, 1.0)))
+            .unwrap();
+        device
+    }
+
+    // Returns a state that only contains the viewport that the pipelines expect.
+    fn viewport_state() -> DynamicState {
+        DynamicState {
+            viewports: Some(vec![
+                Viewport {
+                    origin: [0.0, 0.0],
+                    dimensions: [16.0, 16.0],
+                    depth_range: 0.0 .. 1.0,
+                },
+            ]),
+            ..DynamicState::none()
+        }
+    }
+
+    fn stencil_value() -> Option<DynamicStencilValue> {
+        Some(DynamicStencilValue {
+                 front: 0xff,
+                 back: 0xff,
+             })
+    }
+
+    #[test]
+    fn valid() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device);
+
+        match check_dynamic_state_validity(&pipeline, &viewport_state()) {
+            Ok(()) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_bias_not_dynamic() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device);
+        let state = DynamicState {
+            depth_bias: Some(DepthBias {
+                                 constant_factor: 1.0,
+                                 clamp: 0.0,
+                                 slope_factor: 1.0,
+                             }),
+            ..viewport_state()
+        };
+
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthBiasNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_bias_missing() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device, depth_bias_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &viewport_state()) {
+            Err(CheckDynamicStateValidityError::DepthBiasMissing) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_bias_clamp_missing_feature() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device, depth_bias_dynamic);
+        let state = DynamicState {
+            depth_bias: Some(DepthBias {
+                                 constant_factor: 1.0,
+                                 clamp: 0.5,
+                                 slope_factor: 1.0,
+                             }),
+            ..viewport_state()
+        };
+
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthBiasClampMissingFeature) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_bounds_not_dynamic() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device);
+        let state = DynamicState {
+            depth_bounds: Some(0.0 .. 1.0),
+            ..viewport_state()
+        };
+
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthBoundsNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_bounds_missing() {
+        let pipeline = pipeline!(depth_bounds_device(), depth_bounds_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &viewport_state()) {
+            Err(CheckDynamicStateValidityError::DepthBoundsMissing) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn depth_bounds_out_of_range() {
+        let pipeline = pipeline!(depth_bounds_device(), depth_bounds_dynamic);
+        let state = DynamicState {
+            depth_bounds: Some(0.0 .. 1.5),
+            ..viewport_state()
+        };
+
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::DepthBoundsOutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn stencil_compare_mask_not_dynamic() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device);
+        let state = DynamicState {
+            compare_mask: stencil_value(),
+            ..viewport_state()
+        };
+
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::StencilCompareMaskNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn stencil_compare_mask_missing() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device, stencil_compare_mask_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &viewport_state()) {
+            Err(CheckDynamicStateValidityError::StencilCompareMaskMissing) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn stencil_write_mask_not_dynamic() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device);
+        let state = DynamicState {
+            write_mask: stencil_value(),
+            ..viewport_state()
+        };
+
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::StencilWriteMaskNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn stencil_write_mask_missing() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device, stencil_write_mask_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &viewport_state()) {
+            Err(CheckDynamicStateValidityError::StencilWriteMaskMissing) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn stencil_reference_not_dynamic() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device);
+        let state = DynamicState {
+            reference: stencil_value(),
+            ..viewport_state()
+        };
+
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::StencilReferenceNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn stencil_reference_missing() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device, stencil_reference_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &viewport_state()) {
+            Err(CheckDynamicStateValidityError::StencilReferenceMissing) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn blend_constants_not_dynamic() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device);
+        let state = DynamicState {
+            blend_constants: Some([0.0; 4]),
+            ..viewport_state()
+        };
+
+        match check_dynamic_state_validity(&pipeline, &state) {
+            Err(CheckDynamicStateValidityError::BlendConstantsNotDynamic) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn blend_constants_missing() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = pipeline!(device, blend_constants_dynamic);
+
+        match check_dynamic_state_validity(&pipeline, &viewport_state()) {
+            Err(CheckDynamicStateValidityError::BlendConstantsMissing) => (),
+            _ => panic!(),
+        }
+    }
+}
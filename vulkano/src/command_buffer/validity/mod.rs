@@ -9,31 +9,52 @@
 
 //! Functions that check the validity of commands.
 
+pub use self::begin_query::{CheckBeginQueryError, check_begin_query};
 pub use self::blit_image::{CheckBlitImageError, check_blit_image};
+pub use self::clear_attachments::{CheckClearAttachmentsError, check_clear_attachments};
 pub use self::clear_color_image::{CheckClearColorImageError, check_clear_color_image};
+pub use self::clear_depth_stencil_image::{CheckClearDepthStencilImageError,
+                                          check_clear_depth_stencil_image};
 pub use self::copy_buffer::{CheckCopyBuffer, CheckCopyBufferError, check_copy_buffer};
 pub use self::copy_image::{CheckCopyImageError, check_copy_image};
 pub use self::copy_image_buffer::{CheckCopyBufferImageError, CheckCopyBufferImageTy,
                                   check_copy_buffer_image};
+pub use self::copy_query_pool_results::{CheckCopyQueryPoolResultsError,
+                                        check_copy_query_pool_results};
+pub use self::debug_marker::{CheckDebugMarkerError, check_debug_marker};
 pub use self::descriptor_sets::{CheckDescriptorSetsValidityError, check_descriptor_sets_validity};
 pub use self::dispatch::{CheckDispatchError, check_dispatch};
 pub use self::dynamic_state::{CheckDynamicStateValidityError, check_dynamic_state_validity};
 pub use self::fill_buffer::{CheckFillBufferError, check_fill_buffer};
 pub use self::index_buffer::{CheckIndexBuffer, CheckIndexBufferError, check_index_buffer};
+pub use self::ownership_transfer::{CheckOwnershipTransferError, check_buffer_ownership_transfer,
+                                   check_image_ownership_transfer};
 pub use self::push_constants::{CheckPushConstantsValidityError, check_push_constants_validity};
+pub use self::render_pass::{CheckRenderPassCompatibilityError, check_render_pass_compatibility};
+pub use self::reset_query_pool::{CheckResetQueryPoolError, check_reset_query_pool};
 pub use self::update_buffer::{CheckUpdateBufferError, check_update_buffer};
 pub use self::vertex_buffers::{CheckVertexBuffer, CheckVertexBufferError, check_vertex_buffers};
+pub use self::write_timestamp::{CheckWriteTimestampError, check_write_timestamp};
 
+mod begin_query;
 mod blit_image;
+mod clear_attachments;
 mod clear_color_image;
+mod clear_depth_stencil_image;
 mod copy_buffer;
 mod copy_image;
 mod copy_image_buffer;
+mod copy_query_pool_results;
+mod debug_marker;
 mod descriptor_sets;
 mod dispatch;
 mod dynamic_state;
 mod fill_buffer;
 mod index_buffer;
+mod ownership_transfer;
 mod push_constants;
+mod render_pass;
+mod reset_query_pool;
 mod update_buffer;
 mod vertex_buffers;
+mod write_timestamp;
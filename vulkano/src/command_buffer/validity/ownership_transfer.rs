@@ -0,0 +1,153 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use buffer::BufferAccess;
+use device::Device;
+use device::DeviceOwned;
+use image::ImageAccess;
+use instance::QueueFamily;
+
+/// Checks whether a command that releases or acquires the ownership of a buffer is valid.
+///
+/// # Panic
+///
+/// - Panics if the buffer not created with `device`.
+///
+pub fn check_buffer_ownership_transfer<B>(device: &Device, buffer: &B, source: QueueFamily,
+                                          destination: QueueFamily)
+                                          -> Result<(), CheckOwnershipTransferError>
+    where B: ?Sized + BufferAccess
+{
+    assert_eq!(buffer.inner().buffer.device().internal_object(),
+               device.internal_object());
+
+    if !buffer.inner().buffer.exclusive_sharing() {
+        return Err(CheckOwnershipTransferError::ConcurrentSharing);
+    }
+
+    check_families(source, destination)
+}
+
+/// Checks whether a command that releases or acquires the ownership of an image is valid.
+///
+/// # Panic
+///
+/// - Panics if the image not created with `device`.
+///
+pub fn check_image_ownership_transfer<I>(device: &Device, image: &I, source: QueueFamily,
+                                         destination: QueueFamily)
+                                         -> Result<(), CheckOwnershipTransferError>
+    where I: ?Sized + ImageAccess
+{
+    assert_eq!(image.inner().image.device().internal_object(),
+               device.internal_object());
+
+    if !image.inner().image.exclusive_sharing() {
+        return Err(CheckOwnershipTransferError::ConcurrentSharing);
+    }
+
+    check_families(source, destination)
+}
+
+fn check_families(source: QueueFamily, destination: QueueFamily)
+                  -> Result<(), CheckOwnershipTransferError> {
+    if source.id() == destination.id() {
+        return Err(CheckOwnershipTransferError::SameQueueFamily);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a command that releases or acquires the
+/// ownership of a resource.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckOwnershipTransferError {
+    /// The resource was created with the concurrent sharing mode, and doesn't need any ownership
+    /// transfer.
+    ConcurrentSharing,
+    /// The source and destination queue families are the same.
+    SameQueueFamily,
+}
+
+impl error::Error for CheckOwnershipTransferError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckOwnershipTransferError::ConcurrentSharing => {
+                "the resource was created with the concurrent sharing mode"
+            },
+            CheckOwnershipTransferError::SameQueueFamily => {
+                "the source and destination queue families are the same"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckOwnershipTransferError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+
+    #[test]
+    fn concurrent_sharing() {
+        let (_, device, _) = mock_dev_and_queue!(0, 1);
+        let families = device.physical_device().queue_families().collect::<Vec<_>>();
+
+        let buffer = unsafe {
+            CpuAccessibleBuffer::<u32>::raw(device.clone(),
+                                            4,
+                                            BufferUsage::all(),
+                                            families.iter().cloned())
+                .unwrap()
+        };
+
+        match check_buffer_ownership_transfer(&device, &buffer, families[0], families[1]) {
+            Err(CheckOwnershipTransferError::ConcurrentSharing) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn same_queue_family() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+            .unwrap();
+
+        match check_buffer_ownership_transfer(&device, &buffer, queue.family(), queue.family()) {
+            Err(CheckOwnershipTransferError::SameQueueFamily) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn wrong_device() {
+        let (_, dev1, queue) = mock_dev_and_queue!();
+        let (_, dev2, _) = mock_dev_and_queue!();
+        let buffer = CpuAccessibleBuffer::from_data(dev1, BufferUsage::all(), 0u32).unwrap();
+
+        assert_should_panic!({
+                                 let _ = check_buffer_ownership_transfer(&dev2,
+                                                                         &buffer,
+                                                                         queue.family(),
+                                                                         queue.family());
+                             });
+    }
+}
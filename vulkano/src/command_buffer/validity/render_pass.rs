@@ -0,0 +1,168 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use framebuffer::RenderPassCompatible;
+use framebuffer::RenderPassDesc;
+use pipeline::GraphicsPipelineAbstract;
+
+/// Checks whether a graphics pipeline can be used in the subpass `subpass` of `render_pass`.
+///
+/// The pipeline must have been created for the same subpass index, and for a render pass that is
+/// compatible with `render_pass`.
+pub fn check_render_pass_compatibility<Gp, Rp>(pipeline: &Gp, render_pass: &Rp, subpass: u32)
+                                               -> Result<(), CheckRenderPassCompatibilityError>
+    where Gp: ?Sized + GraphicsPipelineAbstract,
+          Rp: ?Sized + RenderPassDesc
+{
+    if pipeline.subpass_index() != subpass {
+        return Err(CheckRenderPassCompatibilityError::WrongSubpassIndex {
+                       expected: subpass,
+                       obtained: pipeline.subpass_index(),
+                   });
+    }
+
+    if !RenderPassCompatible::is_compatible_with(pipeline, render_pass) {
+        return Err(CheckRenderPassCompatibilityError::IncompatibleRenderPass);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when checking whether a pipeline can be used in the current subpass.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckRenderPassCompatibilityError {
+    /// The pipeline was created for another subpass index than the current one.
+    WrongSubpassIndex {
+        /// Index of the current subpass.
+        expected: u32,
+        /// Index of the subpass the pipeline was created for.
+        obtained: u32,
+    },
+
+    /// The render pass of the pipeline isn't compatible with the current render pass.
+    IncompatibleRenderPass,
+}
+
+impl error::Error for CheckRenderPassCompatibilityError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckRenderPassCompatibilityError::WrongSubpassIndex { .. } => {
+                "the pipeline was created for another subpass index than the current one"
+            },
+            CheckRenderPassCompatibilityError::IncompatibleRenderPass => {
+                "the render pass of the pipeline isn't compatible with the current render pass"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckRenderPassCompatibilityError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::sync::Arc;
+
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use device::Device;
+    use format::Format;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::Subpass;
+    use pipeline::GraphicsPipeline;
+    use pipeline::shader::EmptyShaderInterfaceDef;
+    use pipeline::shader::GraphicsShaderType;
+    use pipeline::shader::ShaderModule;
+
+    // Builds a pipeline for the single subpass of an empty render pass. The mock driver doesn't
+    // look at the content of shader modules.
+    fn empty_pipeline(device: Arc<Device>) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+        let module = unsafe { ShaderModule::new(device.clone(), &[0; 8]).unwrap() };
+        let rp = Arc::new(EmptySinglePassRenderPassDesc.build_render_pass(device.clone()).unwrap());
+
+        let name = CStr::from_bytes_with_nul(b"main\0").unwrap();
+        let vs = unsafe {
+            module.graphics_entry_point::<(), _, _, _>(name,
+                                                       EmptyShaderInterfaceDef,
+                                                       EmptyShaderInterfaceDef,
+                                                       EmptyPipelineDesc,
+                                                       GraphicsShaderType::Vertex)
+        };
+        let fs = unsafe {
+            module.graphics_entry_point::<(), _, _, _>(name,
+                                                       EmptyShaderInterfaceDef,
+                                                       EmptyShaderInterfaceDef,
+                                                       EmptyPipelineDesc,
+                                                       GraphicsShaderType::Fragment)
+        };
+
+        Arc::new(GraphicsPipeline::start()
+                     .vertex_input_single_buffer::<()>()
+                     .vertex_shader(vs, ())
+                     .triangle_list()
+                     .viewports_dynamic_scissors_irrelevant(1)
+                     .fragment_shader(fs, ())
+                     .render_pass(Subpass::from(rp, 0).unwrap())
+                     .build(device)
+                     .unwrap())
+    }
+
+    #[test]
+    fn compatible() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = empty_pipeline(device.clone());
+        let rp = EmptySinglePassRenderPassDesc.build_render_pass(device).unwrap();
+
+        match check_render_pass_compatibility(&*pipeline, &rp, 0) {
+            Ok(()) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn wrong_subpass_index() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = empty_pipeline(device.clone());
+        let rp = EmptySinglePassRenderPassDesc.build_render_pass(device).unwrap();
+
+        match check_render_pass_compatibility(&*pipeline, &rp, 1) {
+            Err(CheckRenderPassCompatibilityError::WrongSubpassIndex {
+                    expected: 1,
+                    obtained: 0,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn incompatible_render_pass() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pipeline = empty_pipeline(device.clone());
+        let rp = single_pass_renderpass!(device,
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap();
+
+        match check_render_pass_compatibility(&*pipeline, &rp, 0) {
+            Err(CheckRenderPassCompatibilityError::IncompatibleRenderPass) => (),
+            _ => panic!(),
+        }
+    }
+}
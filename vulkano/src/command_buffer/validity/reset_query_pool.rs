@@ -0,0 +1,97 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use device::Device;
+use query::QueryPoolAccess;
+
+/// Checks whether a reset query pool command is valid.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+///
+pub fn check_reset_query_pool<Q>(device: &Device, pool: &Q, first_index: u32, count: u32)
+                                 -> Result<(), CheckResetQueryPoolError>
+    where Q: ?Sized + QueryPoolAccess
+{
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+
+    if count == 0 {
+        return Err(CheckResetQueryPoolError::EmptyRange);
+    }
+
+    if pool.inner().queries_range(first_index, count).is_none() {
+        return Err(CheckResetQueryPoolError::OutOfRange);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a `reset_query_pool` command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckResetQueryPoolError {
+    /// The number of queries to reset is 0.
+    EmptyRange,
+    /// The range of queries is out of the range of the query pool.
+    OutOfRange,
+}
+
+impl error::Error for CheckResetQueryPoolError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckResetQueryPoolError::EmptyRange => {
+                "the number of queries to reset is 0"
+            },
+            CheckResetQueryPoolError::OutOfRange => {
+                "the range of queries is out of the range of the query pool"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckResetQueryPoolError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use query::TimestampQueryPool;
+
+    #[test]
+    fn out_of_range() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device.clone(), 4).unwrap();
+
+        assert!(check_reset_query_pool(&device, &*pool, 0, 4).is_ok());
+        match check_reset_query_pool(&device, &*pool, 2, 3) {
+            Err(CheckResetQueryPoolError::OutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn empty_range() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device.clone(), 4).unwrap();
+
+        match check_reset_query_pool(&device, &*pool, 0, 0) {
+            Err(CheckResetQueryPoolError::EmptyRange) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use VulkanObject;
+use device::Device;
+use query::QueryPoolAccess;
+use query::QueryState;
+use query::QueryType;
+use sync::PipelineStages;
+
+/// Checks whether a write timestamp command is valid.
+///
+/// `query_state` must return the state of a query of `pool` at the point of the command buffer
+/// where the command is recorded.
+///
+/// # Panic
+///
+/// - Panics if the query pool was not created with `device`.
+///
+pub fn check_write_timestamp<Q, F>(device: &Device, pool: &Q, query: u32, stage: PipelineStages,
+                                   query_state: F)
+                                   -> Result<(), CheckWriteTimestampError>
+    where Q: ?Sized + QueryPoolAccess,
+          F: Fn(u32) -> QueryState
+{
+    assert_eq!(pool.device().internal_object(), device.internal_object());
+
+    if query >= pool.inner().num_slots() {
+        return Err(CheckWriteTimestampError::OutOfRange);
+    }
+
+    match pool.inner().ty() {
+        QueryType::Timestamp => (),
+        _ => return Err(CheckWriteTimestampError::WrongQueryType),
+    }
+
+    if stage.into_vulkan_bits().count_ones() != 1 {
+        return Err(CheckWriteTimestampError::InvalidStage);
+    }
+
+    if query_state(query) != QueryState::Reset {
+        return Err(CheckWriteTimestampError::QueryNotReset);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when attempting to add a `write_timestamp` command.
+#[derive(Debug, Copy, Clone)]
+pub enum CheckWriteTimestampError {
+    /// The query index is out of the range of the query pool.
+    OutOfRange,
+    /// The query pool is not a timestamp query pool.
+    WrongQueryType,
+    /// Exactly one pipeline stage must be specified.
+    InvalidStage,
+    /// The query must be reset before the timestamp can be written.
+    QueryNotReset,
+}
+
+impl error::Error for CheckWriteTimestampError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            CheckWriteTimestampError::OutOfRange => {
+                "the query index is out of the range of the query pool"
+            },
+            CheckWriteTimestampError::WrongQueryType => {
+                "the query pool is not a timestamp query pool"
+            },
+            CheckWriteTimestampError::InvalidStage => {
+                "exactly one pipeline stage must be specified"
+            },
+            CheckWriteTimestampError::QueryNotReset => {
+                "the query must be reset before the timestamp can be written"
+            },
+        }
+    }
+}
+
+impl fmt::Display for CheckWriteTimestampError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use query::TimestampQueryPool;
+
+    #[test]
+    fn invalid_stage() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device.clone(), 4).unwrap();
+
+        let stages = PipelineStages {
+            top_of_pipe: true,
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+        match check_write_timestamp(&device, &*pool, 0, stages, |i| pool.query_state(i)) {
+            Err(CheckWriteTimestampError::InvalidStage) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn not_reset() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device.clone(), 4).unwrap();
+
+        let stages = PipelineStages {
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+        match check_write_timestamp(&device, &*pool, 0, stages, |i| pool.query_state(i)) {
+            Err(CheckWriteTimestampError::QueryNotReset) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -47,15 +47,20 @@ pub use self::fixed_size_pool::FixedSizeDescriptorSetBuilderArray;
 pub use self::fixed_size_pool::FixedSizeDescriptorSetsPool;
 pub use self::persistent::PersistentDescriptorSet;
 pub use self::persistent::PersistentDescriptorSetBuf;
+pub use self::persistent::PersistentDescriptorSetBufs;
 pub use self::persistent::PersistentDescriptorSetBufView;
 pub use self::persistent::PersistentDescriptorSetBuildError;
 pub use self::persistent::PersistentDescriptorSetBuilder;
 pub use self::persistent::PersistentDescriptorSetBuilderArray;
 pub use self::persistent::PersistentDescriptorSetError;
 pub use self::persistent::PersistentDescriptorSetImg;
+pub use self::persistent::PersistentDescriptorSetImgs;
 pub use self::persistent::PersistentDescriptorSetSampler;
+pub use self::persistent::PersistentDescriptorSetSamplers;
 pub use self::std_pool::StdDescriptorPool;
 pub use self::std_pool::StdDescriptorPoolAlloc;
+pub use self::std_pool::StdDescriptorPoolStatistics;
+pub use self::sys::DescriptorCopy;
 pub use self::sys::DescriptorPool;
 pub use self::sys::DescriptorPoolAlloc;
 pub use self::sys::DescriptorPoolAllocError;
@@ -462,6 +462,98 @@ impl<L, R> PersistentDescriptorSetBuilderArray<L, R>
            })
     }
 
+    /// Binds a list of buffers as the next elements in the array.
+    ///
+    /// All the buffers are written to the descriptor set at once. An error is returned if one of
+    /// the buffers isn't compatible with the descriptor, or if there are more buffers than
+    /// remaining elements in the array.
+    ///
+    /// # Panic
+    ///
+    /// Panics if one of the buffers doesn't have the same device as the pipeline layout.
+    ///
+    pub fn add_buffers<I, T>(mut self, buffers: I)
+        -> Result<PersistentDescriptorSetBuilderArray<L, (R, PersistentDescriptorSetBufs<T>)>,
+                  PersistentDescriptorSetError>
+        where I: IntoIterator<Item = T>,
+              T: BufferAccess
+    {
+        let buffers = buffers.into_iter().collect::<Vec<_>>();
+        let num_buffers = buffers.len();
+
+        for buffer in buffers.iter() {
+            assert_eq!(self.builder.layout.device().internal_object(),
+                       buffer.inner().buffer.device().internal_object());
+        }
+
+        if self.array_element + num_buffers > self.desc.array_count as usize {
+            return Err(PersistentDescriptorSetError::ArrayOutOfBounds);
+        }
+
+        let binding = self.builder.binding_id as u32;
+        let first_array_element = self.array_element as u32;
+
+        let write = match self.desc.ty {
+            DescriptorDescTy::Buffer(ref buffer_desc) => {
+                // See the comments in `add_buffer`.
+                assert!(self.builder
+                            .layout
+                            .device()
+                            .enabled_features()
+                            .robust_buffer_access);
+
+                if buffer_desc.storage {
+                    if buffers.iter().any(|b| !b.inner().buffer.usage_storage_buffer()) {
+                        return Err(PersistentDescriptorSetError::MissingUsage);
+                    }
+                } else {
+                    if buffers.iter().any(|b| !b.inner().buffer.usage_uniform_buffer()) {
+                        return Err(PersistentDescriptorSetError::MissingUsage);
+                    }
+                }
+
+                if buffers.is_empty() {
+                    None
+                } else if buffer_desc.storage {
+                    unsafe {
+                        Some(DescriptorWrite::storage_buffers(binding,
+                                                              first_array_element,
+                                                              &buffers))
+                    }
+                } else {
+                    unsafe {
+                        Some(DescriptorWrite::uniform_buffers(binding,
+                                                              first_array_element,
+                                                              &buffers))
+                    }
+                }
+            },
+            ref d => {
+                return Err(PersistentDescriptorSetError::WrongDescriptorTy {
+                               expected: d.ty().unwrap(),
+                           });
+            },
+        };
+
+        self.builder.writes.extend(write);
+
+        Ok(PersistentDescriptorSetBuilderArray {
+               builder: PersistentDescriptorSetBuilder {
+                   layout: self.builder.layout,
+                   set_id: self.builder.set_id,
+                   binding_id: self.builder.binding_id,
+                   writes: self.builder.writes,
+                   resources: (self.builder.resources,
+                               PersistentDescriptorSetBufs {
+                                   buffers: buffers,
+                                   descriptor_num: binding,
+                               }),
+               },
+               desc: self.desc,
+               array_element: self.array_element + num_buffers,
+           })
+    }
+
     /// Binds a buffer view as the next element in the array.
     ///
     /// An error is returned if the buffer isn't compatible with the descriptor.
@@ -571,39 +663,7 @@ impl<L, R> PersistentDescriptorSetBuilderArray<L, R>
                 multisampled,
                 array_layers,
             } => {
-                if !image_view.parent().inner().image.usage_input_attachment() {
-                    return Err(PersistentDescriptorSetError::MissingUsage);
-                }
-
-                if multisampled && image_view.samples() == 1 {
-                    return Err(PersistentDescriptorSetError::ExpectedMultisampled);
-                } else if !multisampled && image_view.samples() != 1 {
-                    return Err(PersistentDescriptorSetError::UnexpectedMultisampled);
-                }
-
-                let image_layers = image_view.dimensions().array_layers();
-
-                match array_layers {
-                    DescriptorImageDescArray::NonArrayed => {
-                        if image_layers != 1 {
-                            return Err(PersistentDescriptorSetError::ArrayLayersMismatch {
-                                           expected: 1,
-                                           obtained: image_layers,
-                                       });
-                        }
-                    },
-                    DescriptorImageDescArray::Arrayed { max_layers: Some(max_layers) } => {
-                        if image_layers > max_layers {
-                            // TODO: is this correct? "max" layers? or is it in fact min layers?
-                            return Err(PersistentDescriptorSetError::ArrayLayersMismatch {
-                                           expected: max_layers,
-                                           obtained: image_layers,
-                                       });
-                        }
-                    },
-                    DescriptorImageDescArray::Arrayed { max_layers: None } => {},
-                };
-
+                input_attachment_match_desc(&image_view, multisampled, array_layers)?;
                 DescriptorWrite::input_attachment(self.builder.binding_id as u32,
                                                   self.array_element as u32,
                                                   &image_view)
@@ -632,6 +692,97 @@ impl<L, R> PersistentDescriptorSetBuilderArray<L, R>
            })
     }
 
+    /// Binds a list of image views as the next elements in the array.
+    ///
+    /// All the image views are written to the descriptor set at once. An error is returned if
+    /// one of the image views isn't compatible with the descriptor, or if there are more image
+    /// views than remaining elements in the array.
+    ///
+    /// # Panic
+    ///
+    /// Panics if one of the image views doesn't have the same device as the pipeline layout.
+    ///
+    pub fn add_images<I, T>(mut self, image_views: I)
+        -> Result<PersistentDescriptorSetBuilderArray<L, (R, PersistentDescriptorSetImgs<T>)>,
+                  PersistentDescriptorSetError>
+        where I: IntoIterator<Item = T>,
+              T: ImageViewAccess
+    {
+        let image_views = image_views.into_iter().collect::<Vec<_>>();
+        let num_images = image_views.len();
+
+        for image_view in image_views.iter() {
+            assert_eq!(self.builder.layout.device().internal_object(),
+                       image_view.parent().inner().image.device().internal_object());
+        }
+
+        if self.array_element + num_images > self.desc.array_count as usize {
+            return Err(PersistentDescriptorSetError::ArrayOutOfBounds);
+        }
+
+        let binding = self.builder.binding_id as u32;
+        let first_array_element = self.array_element as u32;
+
+        let write = match self.desc.ty {
+            DescriptorDescTy::Image(ref desc) => {
+                for image_view in image_views.iter() {
+                    image_match_desc(image_view, desc)?;
+                }
+
+                if image_views.is_empty() {
+                    None
+                } else if desc.sampled {
+                    Some(DescriptorWrite::sampled_images(binding,
+                                                         first_array_element,
+                                                         &image_views))
+                } else {
+                    Some(DescriptorWrite::storage_images(binding,
+                                                         first_array_element,
+                                                         &image_views))
+                }
+            },
+            DescriptorDescTy::InputAttachment {
+                multisampled,
+                array_layers,
+            } => {
+                for image_view in image_views.iter() {
+                    input_attachment_match_desc(image_view, multisampled, array_layers)?;
+                }
+
+                if image_views.is_empty() {
+                    None
+                } else {
+                    Some(DescriptorWrite::input_attachments(binding,
+                                                            first_array_element,
+                                                            &image_views))
+                }
+            },
+            ref ty => {
+                return Err(PersistentDescriptorSetError::WrongDescriptorTy {
+                               expected: ty.ty().unwrap(),
+                           });
+            },
+        };
+
+        self.builder.writes.extend(write);
+
+        Ok(PersistentDescriptorSetBuilderArray {
+               builder: PersistentDescriptorSetBuilder {
+                   layout: self.builder.layout,
+                   set_id: self.builder.set_id,
+                   binding_id: self.builder.binding_id,
+                   writes: self.builder.writes,
+                   resources: (self.builder.resources,
+                               PersistentDescriptorSetImgs {
+                                   images: image_views,
+                                   descriptor_num: binding,
+                               }),
+               },
+               desc: self.desc,
+               array_element: self.array_element + num_images,
+           })
+    }
+
     /// Binds an image view with a sampler as the next element in the array.
     ///
     /// An error is returned if the image view isn't compatible with the descriptor.
@@ -697,6 +848,87 @@ impl<L, R> PersistentDescriptorSetBuilderArray<L, R>
            })
     }
 
+    /// Binds multiple image views with their samplers as the next elements in the array.
+    ///
+    /// An error is returned if there isn't enough room left in the array or if one of the image
+    /// views isn't compatible with the descriptor or with its sampler.
+    ///
+    /// # Panic
+    ///
+    /// Panics if one of the image views or one of the samplers doesn't have the same device as the
+    /// pipeline layout.
+    ///
+    pub fn add_sampled_images<I, T>(mut self, images: I)
+        -> Result<PersistentDescriptorSetBuilderArray<L,
+                                                      ((R, PersistentDescriptorSetImgs<T>),
+                                                       PersistentDescriptorSetSamplers)>,
+                  PersistentDescriptorSetError>
+        where I: IntoIterator<Item = (T, Arc<Sampler>)>,
+              T: ImageViewAccess
+    {
+        let (image_views, samplers): (Vec<_>, Vec<_>) = images.into_iter().unzip();
+        let num_images = image_views.len();
+
+        for (image_view, sampler) in image_views.iter().zip(samplers.iter()) {
+            assert_eq!(self.builder.layout.device().internal_object(),
+                       image_view.parent().inner().image.device().internal_object());
+            assert_eq!(self.builder.layout.device().internal_object(),
+                       sampler.device().internal_object());
+        }
+
+        if self.array_element + num_images > self.desc.array_count as usize {
+            return Err(PersistentDescriptorSetError::ArrayOutOfBounds);
+        }
+
+        let binding = self.builder.binding_id as u32;
+        let first_array_element = self.array_element as u32;
+
+        let write = match self.desc.ty {
+            DescriptorDescTy::CombinedImageSampler(ref desc) => {
+                for (image_view, sampler) in image_views.iter().zip(samplers.iter()) {
+                    if !image_view.can_be_sampled(sampler) {
+                        return Err(PersistentDescriptorSetError::IncompatibleImageViewSampler);
+                    }
+
+                    image_match_desc(image_view, desc)?;
+                }
+
+                if image_views.is_empty() {
+                    None
+                } else {
+                    Some(DescriptorWrite::combined_image_samplers(binding,
+                                                                  first_array_element,
+                                                                  &samplers,
+                                                                  &image_views))
+                }
+            },
+            ref ty => {
+                return Err(PersistentDescriptorSetError::WrongDescriptorTy {
+                               expected: ty.ty().unwrap(),
+                           });
+            },
+        };
+
+        self.builder.writes.extend(write);
+
+        Ok(PersistentDescriptorSetBuilderArray {
+               builder: PersistentDescriptorSetBuilder {
+                   layout: self.builder.layout,
+                   set_id: self.builder.set_id,
+                   binding_id: self.builder.binding_id,
+                   writes: self.builder.writes,
+                   resources: ((self.builder.resources,
+                                PersistentDescriptorSetImgs {
+                                    images: image_views,
+                                    descriptor_num: binding,
+                                }),
+                               PersistentDescriptorSetSamplers { samplers: samplers }),
+               },
+               desc: self.desc,
+               array_element: self.array_element + num_images,
+           })
+    }
+
     /// Binds a sampler as the next element in the array.
     ///
     /// An error is returned if the sampler isn't compatible with the descriptor.
@@ -812,6 +1044,47 @@ fn image_match_desc<I>(image_view: &I, desc: &DescriptorImageDesc)
     Ok(())
 }
 
+// Checks whether an image view matches an input attachment descriptor.
+fn input_attachment_match_desc<I>(image_view: &I, multisampled: bool,
+                                  array_layers: DescriptorImageDescArray)
+                                  -> Result<(), PersistentDescriptorSetError>
+    where I: ?Sized + ImageViewAccess
+{
+    if !image_view.parent().inner().image.usage_input_attachment() {
+        return Err(PersistentDescriptorSetError::MissingUsage);
+    }
+
+    if multisampled && image_view.samples() == 1 {
+        return Err(PersistentDescriptorSetError::ExpectedMultisampled);
+    } else if !multisampled && image_view.samples() != 1 {
+        return Err(PersistentDescriptorSetError::UnexpectedMultisampled);
+    }
+
+    let image_layers = image_view.dimensions().array_layers();
+
+    match array_layers {
+        DescriptorImageDescArray::NonArrayed => {
+            if image_layers != 1 {
+                return Err(PersistentDescriptorSetError::ArrayLayersMismatch {
+                               expected: 1,
+                               obtained: image_layers,
+                           });
+            }
+        },
+        DescriptorImageDescArray::Arrayed { max_layers: Some(max_layers) } => {
+            if image_layers > max_layers {
+                return Err(PersistentDescriptorSetError::ArrayLayersMismatch {
+                               expected: max_layers,
+                               obtained: image_layers,
+                           });
+            }
+        },
+        DescriptorImageDescArray::Arrayed { max_layers: None } => {},
+    };
+
+    Ok(())
+}
+
 pub unsafe trait PersistentDescriptorSetResources {
     fn num_buffers(&self) -> usize;
     fn buffer(&self, index: usize) -> Option<(&BufferAccess, u32)>;
@@ -878,6 +1151,44 @@ unsafe impl<R, B> PersistentDescriptorSetResources for (R, PersistentDescriptorS
     }
 }
 
+/// Internal object related to the `PersistentDescriptorSet` system.
+pub struct PersistentDescriptorSetBufs<B> {
+    buffers: Vec<B>,
+    descriptor_num: u32,
+}
+
+unsafe impl<R, B> PersistentDescriptorSetResources for (R, PersistentDescriptorSetBufs<B>)
+    where R: PersistentDescriptorSetResources,
+          B: BufferAccess
+{
+    #[inline]
+    fn num_buffers(&self) -> usize {
+        self.0.num_buffers() + self.1.buffers.len()
+    }
+
+    #[inline]
+    fn buffer(&self, index: usize) -> Option<(&BufferAccess, u32)> {
+        if let Some(buf) = self.0.buffer(index) {
+            Some(buf)
+        } else {
+            self.1
+                .buffers
+                .get(index - self.0.num_buffers())
+                .map(|buf| (buf as &BufferAccess, self.1.descriptor_num))
+        }
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        self.0.num_images()
+    }
+
+    #[inline]
+    fn image(&self, index: usize) -> Option<(&ImageViewAccess, u32)> {
+        self.0.image(index)
+    }
+}
+
 /// Internal object related to the `PersistentDescriptorSet` system.
 pub struct PersistentDescriptorSetBufView<V>
     where V: BufferViewRef
@@ -954,6 +1265,44 @@ unsafe impl<R, I> PersistentDescriptorSetResources for (R, PersistentDescriptorS
     }
 }
 
+/// Internal object related to the `PersistentDescriptorSet` system.
+pub struct PersistentDescriptorSetImgs<I> {
+    images: Vec<I>,
+    descriptor_num: u32,
+}
+
+unsafe impl<R, I> PersistentDescriptorSetResources for (R, PersistentDescriptorSetImgs<I>)
+    where R: PersistentDescriptorSetResources,
+          I: ImageViewAccess
+{
+    #[inline]
+    fn num_buffers(&self) -> usize {
+        self.0.num_buffers()
+    }
+
+    #[inline]
+    fn buffer(&self, index: usize) -> Option<(&BufferAccess, u32)> {
+        self.0.buffer(index)
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        self.0.num_images() + self.1.images.len()
+    }
+
+    #[inline]
+    fn image(&self, index: usize) -> Option<(&ImageViewAccess, u32)> {
+        if let Some(img) = self.0.image(index) {
+            Some(img)
+        } else {
+            self.1
+                .images
+                .get(index - self.0.num_images())
+                .map(|img| (img as &ImageViewAccess, self.1.descriptor_num))
+        }
+    }
+}
+
 /// Internal object related to the `PersistentDescriptorSet` system.
 pub struct PersistentDescriptorSetSampler {
     sampler: Arc<Sampler>,
@@ -983,6 +1332,35 @@ unsafe impl<R> PersistentDescriptorSetResources for (R, PersistentDescriptorSetS
     }
 }
 
+/// Internal object related to the `PersistentDescriptorSet` system.
+pub struct PersistentDescriptorSetSamplers {
+    samplers: Vec<Arc<Sampler>>,
+}
+
+unsafe impl<R> PersistentDescriptorSetResources for (R, PersistentDescriptorSetSamplers)
+    where R: PersistentDescriptorSetResources
+{
+    #[inline]
+    fn num_buffers(&self) -> usize {
+        self.0.num_buffers()
+    }
+
+    #[inline]
+    fn buffer(&self, index: usize) -> Option<(&BufferAccess, u32)> {
+        self.0.buffer(index)
+    }
+
+    #[inline]
+    fn num_images(&self) -> usize {
+        self.0.num_images()
+    }
+
+    #[inline]
+    fn image(&self, index: usize) -> Option<(&ImageViewAccess, u32)> {
+        self.0.image(index)
+    }
+}
+
 /// Error related to the persistent descriptor set.
 #[derive(Debug, Clone)]
 pub enum PersistentDescriptorSetError {
@@ -1133,3 +1511,203 @@ impl fmt::Display for PersistentDescriptorSetBuildError {
         write!(fmt, "{}", error::Error::description(self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::DescriptorImageDesc;
+    use descriptor::descriptor::DescriptorImageDescArray;
+    use descriptor::descriptor::DescriptorImageDescDimensions;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::DescriptorSet;
+    use descriptor::descriptor_set::PersistentDescriptorSet;
+    use descriptor::descriptor_set::PersistentDescriptorSetError;
+    use descriptor::pipeline_layout::PipelineLayout;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use descriptor::pipeline_layout::RuntimePipelineDesc;
+    use device::Device;
+    use format::Format;
+    use image::AttachmentImage;
+    use sampler::Sampler;
+    use std::iter;
+    use std::sync::Arc;
+
+    // Builds a pipeline layout whose only set contains an array of 4 uniform buffers.
+    fn uniform_buffers_layout(device: Arc<Device>) -> Arc<PipelineLayout<RuntimePipelineDesc>> {
+        let desc = DescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                             dynamic: Some(false),
+                                             storage: false,
+                                         }),
+            array_count: 4,
+            stages: ShaderStages::all(),
+            readonly: true,
+        };
+
+        let desc = RuntimePipelineDesc::new(iter::once(iter::once(Some(desc))), iter::empty())
+            .unwrap();
+        Arc::new(desc.build(device).unwrap())
+    }
+
+    fn buffers(device: &Arc<Device>, num: usize) -> Vec<Arc<CpuAccessibleBuffer<u32>>> {
+        (0 .. num)
+            .map(|_| {
+                     CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+                         .unwrap()
+                 })
+            .collect()
+    }
+
+    // Builds a pipeline layout whose only set contains an array of 4 combined image samplers.
+    fn sampled_images_layout(device: Arc<Device>) -> Arc<PipelineLayout<RuntimePipelineDesc>> {
+        let image_desc = DescriptorImageDesc {
+            sampled: true,
+            dimensions: DescriptorImageDescDimensions::TwoDimensional,
+            format: None,
+            multisampled: false,
+            array_layers: DescriptorImageDescArray::NonArrayed,
+        };
+
+        let desc = DescriptorDesc {
+            ty: DescriptorDescTy::CombinedImageSampler(image_desc),
+            array_count: 4,
+            stages: ShaderStages::all(),
+            readonly: true,
+        };
+
+        let desc = RuntimePipelineDesc::new(iter::once(iter::once(Some(desc))), iter::empty())
+            .unwrap();
+        Arc::new(desc.build(device).unwrap())
+    }
+
+    fn sampled_images(device: &Arc<Device>, num: usize)
+                      -> Vec<(Arc<AttachmentImage<Format>>, Arc<Sampler>)> {
+        (0 .. num)
+            .map(|_| {
+                     let image = AttachmentImage::sampled(device.clone(),
+                                                          [16, 16],
+                                                          Format::R8G8B8A8Unorm)
+                         .unwrap();
+                     (image, Sampler::simple_repeat_linear_no_mipmap(device.clone()))
+                 })
+            .collect()
+    }
+
+    #[test]
+    fn add_buffers() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = uniform_buffers_layout(device.clone());
+
+        let set = PersistentDescriptorSet::start(layout, 0)
+            .enter_array()
+            .unwrap()
+            .add_buffers(buffers(&device, 3))
+            .unwrap()
+            .add_buffer(buffers(&device, 1).remove(0))
+            .unwrap()
+            .leave_array()
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(set.num_buffers(), 4);
+        assert!(set.buffer(3).is_some());
+        assert!(set.buffer(4).is_none());
+    }
+
+    #[test]
+    fn add_buffers_out_of_bounds() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = uniform_buffers_layout(device.clone());
+
+        let result = PersistentDescriptorSet::start(layout, 0)
+            .enter_array()
+            .unwrap()
+            .add_buffers(buffers(&device, 5));
+
+        match result {
+            Err(PersistentDescriptorSetError::ArrayOutOfBounds) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn add_buffers_missing_elements() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = uniform_buffers_layout(device.clone());
+
+        let result = PersistentDescriptorSet::start(layout, 0)
+            .enter_array()
+            .unwrap()
+            .add_buffers(buffers(&device, 3))
+            .unwrap()
+            .leave_array();
+
+        match result {
+            Err(PersistentDescriptorSetError::MissingArrayElements {
+                    expected: 4,
+                    obtained: 3,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn add_sampled_images() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = sampled_images_layout(device.clone());
+        let (image, sampler) = sampled_images(&device, 1).remove(0);
+
+        let set = PersistentDescriptorSet::start(layout, 0)
+            .enter_array()
+            .unwrap()
+            .add_sampled_images(sampled_images(&device, 3))
+            .unwrap()
+            .add_sampled_image(image, sampler)
+            .unwrap()
+            .leave_array()
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(set.num_images(), 4);
+        assert!(set.image(3).is_some());
+        assert!(set.image(4).is_none());
+    }
+
+    #[test]
+    fn add_sampled_images_out_of_bounds() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = sampled_images_layout(device.clone());
+
+        let result = PersistentDescriptorSet::start(layout, 0)
+            .enter_array()
+            .unwrap()
+            .add_sampled_images(sampled_images(&device, 5));
+
+        match result {
+            Err(PersistentDescriptorSetError::ArrayOutOfBounds) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn add_sampled_images_wrong_ty() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = uniform_buffers_layout(device.clone());
+
+        let result = PersistentDescriptorSet::start(layout, 0)
+            .enter_array()
+            .unwrap()
+            .add_sampled_images(sampled_images(&device, 2));
+
+        match result {
+            Err(PersistentDescriptorSetError::WrongDescriptorTy { .. }) => (),
+            _ => panic!(),
+        }
+    }
+}
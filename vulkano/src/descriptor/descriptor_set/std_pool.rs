@@ -7,37 +7,110 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crossbeam::sync::SegQueue;
+use fnv::FnvHashMap;
+use std::cell::RefCell;
+use std::cmp;
+use std::iter;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::Weak;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 
 use OomError;
 use descriptor::descriptor_set::DescriptorPool;
 use descriptor::descriptor_set::DescriptorPoolAlloc;
 use descriptor::descriptor_set::DescriptorPoolAllocError;
-use descriptor::descriptor_set::DescriptorsCount;
 use descriptor::descriptor_set::UnsafeDescriptorPool;
 use descriptor::descriptor_set::UnsafeDescriptorSet;
 use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
 use device::Device;
 use device::DeviceOwned;
 
+// Number of sets of the first Vulkan pool created for a layout.
+const FIRST_POOL_CAPACITY: u32 = 8;
+// Maximum number of sets of a single Vulkan pool.
+const MAX_POOL_CAPACITY: u32 = 1024;
+
 /// Standard implementation of a descriptor pool.
 ///
 /// It is guaranteed that the `Arc<StdDescriptorPool>` is kept alive by its allocations. This is
 /// desirable so that we can store a `Weak<StdDescriptorPool>`.
 ///
-/// Whenever a set is allocated, this implementation will try to find a pool that has some space
-/// for it. If there is one, allocate from it. If there is none, create a new pool whose capacity
-/// is 40 sets and 40 times the requested descriptors. This number is arbitrary.
+/// Each thread allocates from its own Vulkan pools, and each layout has its own Vulkan pools.
+/// Whenever a thread runs out of sets for a layout, a new Vulkan pool is created whose capacity
+/// is twice the capacity of the previous one, up to 1024 sets. All the sets of a new pool are
+/// allocated at once.
+///
+/// Freeing a set never calls `vkFreeDescriptorSets`. Instead the set is put back in a lock-free
+/// queue and will be returned by a later allocation of the same thread and layout.
+///
+/// The Vulkan pools of a thread are destroyed when the thread exits, or when the thread creates
+/// pools for another layout after the `StdDescriptorPool` or the layout has been destroyed. In all
+/// cases they are kept alive until all the sets allocated from them have been freed.
+///
+/// The sets of a layout are identified by the layout object, and not by its content. Sets are
+/// therefore never shared between two layouts, even if they are identical.
 pub struct StdDescriptorPool {
     device: Arc<Device>,
-    pools: Mutex<Vec<Arc<Mutex<Pool>>>>,
+    // Number of Vulkan pools that have been created.
+    num_pools: AtomicUsize,
+    // Total number of sets that have been allocated from the Vulkan pools.
+    capacity: AtomicUsize,
+    // Number of sets that are currently allocated.
+    in_use: AtomicUsize,
+    // Number of allocations that returned a set that had been freed before.
+    recycled: AtomicUsize,
+}
+
+// Key of `LOCAL_POOLS`. The address of the `StdDescriptorPool` and the identifier of the layout.
+// The address can't be reused by another `StdDescriptorPool` as long as the entry holds a weak
+// pointer to it.
+type LocalPoolsKey = (usize, usize);
+
+// An entry of `LOCAL_POOLS`.
+struct LocalPools {
+    // The `StdDescriptorPool` the pools belong to.
+    parent: Weak<StdDescriptorPool>,
+    // Weak handle to the layout of the sets.
+    layout: Weak<()>,
+    pools: Arc<LayoutPools>,
+}
+
+thread_local! {
+    // The pools of the current thread, for each `StdDescriptorPool` and each layout. Dropped, and
+    // thus pruned, when the thread exits.
+    static LOCAL_POOLS: RefCell<FnvHashMap<LocalPoolsKey, LocalPools>> =
+        RefCell::new(FnvHashMap::default());
 }
 
-struct Pool {
-    pool: UnsafeDescriptorPool,
-    remaining_capacity: DescriptorsCount,
-    remaining_sets_count: u32,
+// The Vulkan pools of a thread for a layout.
+struct LayoutPools {
+    // The Vulkan pools. Only ever locked by the thread that owns them, when creating a new pool.
+    pools: Mutex<Vec<UnsafeDescriptorPool>>,
+    // Capacity of the next Vulkan pool to create.
+    next_capacity: AtomicUsize,
+    // Sets that are available for allocation. The boolean is true if the set has already been
+    // used by a previous allocation.
+    reserve: SegQueue<(UnsafeDescriptorSet, bool)>,
+}
+
+/// Usage statistics of a `StdDescriptorPool`.
+///
+/// Returned by `StdDescriptorPool::statistics()`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct StdDescriptorPoolStatistics {
+    /// Number of Vulkan descriptor pools that have been created.
+    pub num_pools: usize,
+    /// Total number of descriptor sets that have been allocated from the Vulkan pools. This
+    /// includes the sets that are currently in use and the ones that are available for reuse.
+    pub capacity: usize,
+    /// Number of descriptor sets that are currently in use.
+    pub in_use: usize,
+    /// Number of allocations that reused a descriptor set that had been freed, instead of
+    /// allocating a new one.
+    pub recycled: usize,
 }
 
 impl StdDescriptorPool {
@@ -45,18 +118,73 @@ impl StdDescriptorPool {
     pub fn new(device: Arc<Device>) -> StdDescriptorPool {
         StdDescriptorPool {
             device: device,
-            pools: Mutex::new(Vec::new()),
+            num_pools: AtomicUsize::new(0),
+            capacity: AtomicUsize::new(0),
+            in_use: AtomicUsize::new(0),
+            recycled: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the usage statistics of the pool.
+    ///
+    /// Since other threads can allocate or free sets at the same time, the values are not
+    /// necessarily consistent with each other.
+    pub fn statistics(&self) -> StdDescriptorPoolStatistics {
+        StdDescriptorPoolStatistics {
+            num_pools: self.num_pools.load(Ordering::Relaxed),
+            capacity: self.capacity.load(Ordering::Relaxed),
+            in_use: self.in_use.load(Ordering::Relaxed),
+            recycled: self.recycled.load(Ordering::Relaxed),
+        }
+    }
+
+    // Creates a new Vulkan pool for `layout`, allocates all its sets and puts them in the reserve
+    // of `layout_pools`.
+    fn grow(&self, layout_pools: &LayoutPools, layout: &UnsafeDescriptorSetLayout)
+            -> Result<(), OomError> {
+        let mut pools = layout_pools.pools.lock().unwrap();
+
+        let capacity = layout_pools.next_capacity.load(Ordering::Relaxed) as u32;
+        layout_pools
+            .next_capacity
+            .store(cmp::min(capacity * 2, MAX_POOL_CAPACITY) as usize, Ordering::Relaxed);
+
+        let count = layout.descriptors_count().clone() * capacity;
+        let mut new_pool = UnsafeDescriptorPool::new(self.device.clone(), &count, capacity, false)?;
+
+        let sets = unsafe {
+            match new_pool.alloc(iter::repeat(layout).take(capacity as usize)) {
+                Ok(sets) => sets,
+                Err(DescriptorPoolAllocError::OutOfHostMemory) => {
+                    return Err(OomError::OutOfHostMemory);
+                },
+                Err(DescriptorPoolAllocError::OutOfDeviceMemory) => {
+                    return Err(OomError::OutOfDeviceMemory);
+                },
+                // A fragmented pool error can't happen at the first ever allocation.
+                Err(DescriptorPoolAllocError::FragmentedPool) => unreachable!(),
+                // The pool was created with enough space for all the sets.
+                Err(DescriptorPoolAllocError::OutOfPoolMemory) => unreachable!(),
+            }
+        };
+
+        for set in sets {
+            layout_pools.reserve.push((set, false));
         }
+
+        pools.push(new_pool);
+        self.num_pools.fetch_add(1, Ordering::Relaxed);
+        self.capacity.fetch_add(capacity as usize, Ordering::Relaxed);
+        Ok(())
     }
 }
 
 /// A descriptor set allocated from a `StdDescriptorPool`.
 pub struct StdDescriptorPoolAlloc {
-    pool: Arc<Mutex<Pool>>,
     // The set. Inside an option so that we can extract it in the destructor.
     set: Option<UnsafeDescriptorSet>,
-    // We need to keep track of this count in order to add it back to the capacity when freeing.
-    descriptors: DescriptorsCount,
+    // The pools the set was allocated from, where the set goes back when it is freed.
+    pools: Arc<LayoutPools>,
     // We keep the parent of the pool alive, otherwise it would be destroyed.
     pool_parent: Arc<StdDescriptorPool>,
 }
@@ -64,83 +192,56 @@ pub struct StdDescriptorPoolAlloc {
 unsafe impl DescriptorPool for Arc<StdDescriptorPool> {
     type Alloc = StdDescriptorPoolAlloc;
 
-    // TODO: eventually use a lock-free algorithm?
     fn alloc(&mut self, layout: &UnsafeDescriptorSetLayout)
              -> Result<StdDescriptorPoolAlloc, OomError> {
-        let mut pools = self.pools.lock().unwrap();
-
-        // Try find an existing pool with some free space.
-        for pool_arc in pools.iter_mut() {
-            let mut pool = pool_arc.lock().unwrap();
+        let key = (&**self as *const StdDescriptorPool as usize, layout.id());
 
-            if pool.remaining_sets_count == 0 {
-                continue;
-            }
+        let layout_pools = LOCAL_POOLS.with(|local| {
+            let mut local = local.borrow_mut();
 
-            if !(pool.remaining_capacity >= *layout.descriptors_count()) {
-                continue;
+            if let Some(entry) = local.get(&key) {
+                return entry.pools.clone();
             }
 
-            // Note that we decrease these values *before* trying to allocate from the pool.
-            // If allocating from the pool results in an error, we just ignore it. In order to
-            // avoid trying the same failing pool every time, we "pollute" it by reducing the
-            // available space.
-            pool.remaining_sets_count -= 1;
-            pool.remaining_capacity -= *layout.descriptors_count();
-
-            let alloc = unsafe {
-                match pool.pool.alloc(Some(layout)) {
-                    Ok(mut sets) => sets.next().unwrap(),
-                    // An error can happen if we're out of memory, or if the pool is fragmented.
-                    // We handle these errors by just ignoring this pool and trying the next ones.
-                    Err(_) => continue,
-                }
-            };
-
-            return Ok(StdDescriptorPoolAlloc {
-                          pool: pool_arc.clone(),
-                          set: Some(alloc),
-                          descriptors: *layout.descriptors_count(),
-                          pool_parent: self.clone(),
-                      });
-        }
+            // Before adding an entry, remove the ones whose layout or `StdDescriptorPool` has been
+            // destroyed.
+            local.retain(|_, entry| {
+                entry.parent.upgrade().is_some() && entry.layout.upgrade().is_some()
+            });
 
-        // No existing pool can be used. Create a new one.
-        // We use an arbitrary number of 40 sets and 40 times the requested descriptors.
-        let count = layout.descriptors_count().clone() * 40;
-        // Failure to allocate a new pool results in an error for the whole function because
-        // there's no way we can recover from that.
-        let mut new_pool = UnsafeDescriptorPool::new(self.device.clone(), &count, 40, true)?;
+            let pools = Arc::new(LayoutPools {
+                                     pools: Mutex::new(Vec::new()),
+                                     next_capacity: AtomicUsize::new(FIRST_POOL_CAPACITY as
+                                                                         usize),
+                                     reserve: SegQueue::new(),
+                                 });
+            local.insert(key,
+                         LocalPools {
+                             parent: Arc::downgrade(self),
+                             layout: layout.weak_handle(),
+                             pools: pools.clone(),
+                         });
+            pools
+        });
 
-        let alloc = unsafe {
-            match new_pool.alloc(Some(layout)) {
-                Ok(mut sets) => sets.next().unwrap(),
-                Err(DescriptorPoolAllocError::OutOfHostMemory) => {
-                    return Err(OomError::OutOfHostMemory);
-                },
-                Err(DescriptorPoolAllocError::OutOfDeviceMemory) => {
-                    return Err(OomError::OutOfDeviceMemory);
-                },
-                // A fragmented pool error can't happen at the first ever allocation.
-                Err(DescriptorPoolAllocError::FragmentedPool) => unreachable!(),
-                // Out of pool memory cannot happen at the first ever allocation.
-                Err(DescriptorPoolAllocError::OutOfPoolMemory) => unreachable!(),
-            }
+        let (set, recycled) = match layout_pools.reserve.try_pop() {
+            Some(elem) => elem,
+            None => {
+                self.grow(&layout_pools, layout)?;
+                // Other threads can only push to the reserve, so it can't be empty.
+                layout_pools.reserve.try_pop().unwrap()
+            },
         };
 
-        let pool_obj = Arc::new(Mutex::new(Pool {
-                                               pool: new_pool,
-                                               remaining_capacity: count -
-                                                   *layout.descriptors_count(),
-                                               remaining_sets_count: 40 - 1,
-                                           }));
+        if recycled {
+            self.recycled.fetch_add(1, Ordering::Relaxed);
+        }
 
-        pools.push(pool_obj.clone());
+        self.in_use.fetch_add(1, Ordering::Relaxed);
 
         Ok(StdDescriptorPoolAlloc {
-               pool: pool_obj,
-               set: Some(alloc),
-               descriptors: *layout.descriptors_count(),
+               set: Some(set),
+               pools: layout_pools,
                pool_parent: self.clone(),
            })
     }
@@ -168,40 +269,42 @@ impl DescriptorPoolAlloc for StdDescriptorPoolAlloc {
 impl Drop for StdDescriptorPoolAlloc {
     // This is the destructor of a single allocation (not of the whole pool).
     fn drop(&mut self) {
-        unsafe {
-            let mut pool = self.pool.lock().unwrap();
-            pool.pool.free(self.set.take()).unwrap();
-            // Add back the capacity only after freeing, in case of a panic during the free.
-            pool.remaining_sets_count += 1;
-            pool.remaining_capacity += self.descriptors;
-        }
+        let set = self.set.take().unwrap();
+        self.pools.reserve.push((set, true));
+        self.pool_parent.in_use.fetch_sub(1, Ordering::Relaxed);
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use VulkanObject;
     use descriptor::descriptor::DescriptorDesc;
     use descriptor::descriptor::DescriptorDescTy;
     use descriptor::descriptor::ShaderStages;
     use descriptor::descriptor_set::DescriptorPool;
     use descriptor::descriptor_set::StdDescriptorPool;
     use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+    use descriptor::descriptor_set::DescriptorPoolAlloc;
+    use device::Device;
     use std::iter;
     use std::sync::Arc;
+    use std::thread;
 
-    #[test]
-    fn desc_pool_kept_alive() {
-        // Test that the `StdDescriptorPool` is kept alive by its allocations.
-        let (device, _) = gfx_dev_and_queue!();
-
+    fn sampler_layout(device: Arc<Device>) -> UnsafeDescriptorSetLayout {
         let desc = DescriptorDesc {
             ty: DescriptorDescTy::Sampler,
             array_count: 1,
             stages: ShaderStages::all(),
             readonly: false,
         };
-        let layout = UnsafeDescriptorSetLayout::new(device.clone(), iter::once(Some(desc)))
-            .unwrap();
+        UnsafeDescriptorSetLayout::new(device, iter::once(Some(desc))).unwrap()
+    }
+
+    #[test]
+    fn desc_pool_kept_alive() {
+        // Test that the `StdDescriptorPool` is kept alive by its allocations.
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = sampler_layout(device.clone());
 
         let mut pool = Arc::new(StdDescriptorPool::new(device));
         let pool_weak = Arc::downgrade(&pool);
@@ -209,4 +312,101 @@ mod tests {
         drop(pool);
         assert!(pool_weak.upgrade().is_some());
     }
+
+    #[test]
+    fn recycles_freed_sets() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = sampler_layout(device.clone());
+        let mut pool = Arc::new(StdDescriptorPool::new(device));
+
+        let first = pool.alloc(&layout).unwrap();
+        let raw = first.inner().internal_object();
+        drop(first);
+
+        // The reserve is a queue, so the freed set comes after the other sets of the pool.
+        let allocs = (0 .. 8)
+            .map(|_| pool.alloc(&layout).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(allocs.last().unwrap().inner().internal_object(), raw);
+
+        let stats = pool.statistics();
+        assert_eq!(stats.num_pools, 1);
+        assert_eq!(stats.in_use, 8);
+        assert_eq!(stats.recycled, 1);
+    }
+
+    #[test]
+    fn grows_geometrically() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout = sampler_layout(device.clone());
+        let mut pool = Arc::new(StdDescriptorPool::new(device));
+
+        let allocs = (0 .. 30)
+            .map(|_| pool.alloc(&layout).unwrap())
+            .collect::<Vec<_>>();
+
+        let stats = pool.statistics();
+        assert_eq!(stats.num_pools, 3);
+        assert_eq!(stats.capacity, 8 + 16 + 32);
+        assert_eq!(stats.in_use, 30);
+        assert_eq!(stats.recycled, 0);
+
+        drop(allocs);
+        assert_eq!(pool.statistics().in_use, 0);
+    }
+
+    #[test]
+    fn separate_pools_per_layout_and_thread() {
+        let (device, _) = gfx_dev_and_queue!();
+        let layout1 = sampler_layout(device.clone());
+        let layout2 = sampler_layout(device.clone());
+        let mut pool = Arc::new(StdDescriptorPool::new(device.clone()));
+
+        let _a = pool.alloc(&layout1).unwrap();
+        let _b = pool.alloc(&layout2).unwrap();
+        assert_eq!(pool.statistics().num_pools, 2);
+
+        let mut pool2 = pool.clone();
+        thread::spawn(move || {
+                          let layout = sampler_layout(device);
+                          pool2.alloc(&layout).unwrap();
+                      })
+            .join()
+            .unwrap();
+        assert_eq!(pool.statistics().num_pools, 3);
+    }
+
+    #[test]
+    fn prunes_pools_of_destroyed_layouts() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let mut pool = Arc::new(StdDescriptorPool::new(device.clone()));
+
+        let layout1 = sampler_layout(device.clone());
+        drop(pool.alloc(&layout1).unwrap());
+        drop(layout1);
+        loader.take_calls();
+
+        let layout2 = sampler_layout(device.clone());
+        let _alloc = pool.alloc(&layout2).unwrap();
+        let names = loader.call_names();
+        assert_eq!(names.iter().filter(|n| **n == "vkDestroyDescriptorPool").count(), 1);
+    }
+
+    #[test]
+    fn destroys_pools_of_exited_threads() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let layout = Arc::new(sampler_layout(device.clone()));
+        let pool = Arc::new(StdDescriptorPool::new(device));
+
+        let mut pool2 = pool.clone();
+        let layout2 = layout.clone();
+        loader.take_calls();
+        thread::spawn(move || { pool2.alloc(&layout2).unwrap(); })
+            .join()
+            .unwrap();
+
+        let names = loader.call_names();
+        assert_eq!(names.iter().filter(|n| **n == "vkDestroyDescriptorPool").count(), 1);
+        assert_eq!(pool.statistics().in_use, 0);
+    }
 }
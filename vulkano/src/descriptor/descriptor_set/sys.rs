@@ -544,13 +544,35 @@ pub struct UnsafeDescriptorSet {
 }
 
 impl UnsafeDescriptorSet {
-    // TODO: add copying from other descriptor sets
-    //       add a `copy` method that just takes a copy, and an `update` method that takes both
-    //       writes and copies and that actually performs the operation
+    /// Modifies a descriptor set by writing descriptors to it. Doesn't check that the writes are
+    /// correct, and doesn't check whether the descriptor set is in use.
+    ///
+    /// Has the same safety requirements as `update`.
+    #[inline]
+    pub unsafe fn write<I>(&mut self, device: &Device, writes: I)
+        where I: Iterator<Item = DescriptorWrite>
+    {
+        self.update(device, writes, None.into_iter())
+    }
+
+    /// Modifies a descriptor set by copying descriptors from other descriptor sets, or from other
+    /// bindings of the same descriptor set. Doesn't check that the copies are correct, and
+    /// doesn't check whether the descriptor set is in use.
+    ///
+    /// Has the same safety requirements as `update`. In addition to this, the descriptor sets
+    /// that are copied from must not be updated at the same time.
+    #[inline]
+    pub unsafe fn copy<J>(&mut self, device: &Device, copies: J)
+        where J: Iterator<Item = DescriptorCopy>
+    {
+        self.update(device, None.into_iter(), copies)
+    }
 
     /// Modifies a descriptor set. Doesn't check that the writes or copies are correct, and
     /// doesn't check whether the descriptor set is in use.
     ///
+    /// The writes are performed before the copies.
+    ///
     /// **Important**: You must ensure that the `UnsafeDescriptorSetLayout` object is alive before
     /// updating a descriptor set.
     ///
@@ -564,8 +586,9 @@ impl UnsafeDescriptorSet {
     ///   command buffer contains a pointer/reference to a descriptor set, it is illegal to write
     ///   to it.
     ///
-    pub unsafe fn write<I>(&mut self, device: &Device, writes: I)
-        where I: Iterator<Item = DescriptorWrite>
+    pub unsafe fn update<I, J>(&mut self, device: &Device, writes: I, copies: J)
+        where I: Iterator<Item = DescriptorWrite>,
+              J: Iterator<Item = DescriptorCopy>
     {
         let vk = device.pointers();
 
@@ -717,14 +740,30 @@ impl UnsafeDescriptorSet {
             };
         }
 
-        // It is forbidden to call `vkUpdateDescriptorSets` with 0 writes, so we need to perform
-        // this emptiness check.
-        if !raw_writes.is_empty() {
+        let raw_copies: SmallVec<[_; 16]> = copies
+            .map(|copy| {
+                vk::CopyDescriptorSet {
+                    sType: vk::STRUCTURE_TYPE_COPY_DESCRIPTOR_SET,
+                    pNext: ptr::null(),
+                    srcSet: copy.source,
+                    srcBinding: copy.source_binding,
+                    srcArrayElement: copy.source_first_array_element,
+                    dstSet: self.set,
+                    dstBinding: copy.destination_binding,
+                    dstArrayElement: copy.destination_first_array_element,
+                    descriptorCount: copy.count,
+                }
+            })
+            .collect();
+
+        // It is pointless to call `vkUpdateDescriptorSets` with 0 writes and 0 copies, so we
+        // perform this emptiness check.
+        if !raw_writes.is_empty() || !raw_copies.is_empty() {
             vk.UpdateDescriptorSets(device.internal_object(),
                                     raw_writes.len() as u32,
                                     raw_writes.as_ptr(),
-                                    0,
-                                    ptr::null());
+                                    raw_copies.len() as u32,
+                                    raw_copies.as_ptr());
         }
     }
 }
@@ -746,11 +785,57 @@ impl fmt::Debug for UnsafeDescriptorSet {
     }
 }
 
+/// Represents a single copy entry from a descriptor set to another.
+///
+/// While it is safe to build a `DescriptorCopy`, it is unsafe to actually use it to copy
+/// descriptors.
+#[derive(Debug, Clone)]
+pub struct DescriptorCopy {
+    source: vk::DescriptorSet,
+    source_binding: u32,
+    source_first_array_element: u32,
+    destination_binding: u32,
+    destination_first_array_element: u32,
+    count: u32,
+}
+
+impl DescriptorCopy {
+    /// Builds a copy of `count` consecutive descriptors of the binding `source_binding` of
+    /// `source`, starting at the array element `source_array_element`, to the binding
+    /// `destination_binding` of the descriptor set that is updated, starting at the array element
+    /// `destination_array_element`.
+    ///
+    /// If `count` is larger than the number of remaining array elements of a binding, the copy
+    /// continues with the next bindings.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `count` is 0.
+    ///
+    #[inline]
+    pub fn new(source: &UnsafeDescriptorSet, source_binding: u32, source_array_element: u32,
+               destination_binding: u32, destination_array_element: u32, count: u32)
+               -> DescriptorCopy {
+        assert_ne!(count, 0, "Can't copy 0 descriptors");
+
+        DescriptorCopy {
+            source: source.set,
+            source_binding: source_binding,
+            source_first_array_element: source_array_element,
+            destination_binding: destination_binding,
+            destination_first_array_element: destination_array_element,
+            count: count,
+        }
+    }
+}
+
 /// Represents a single write entry to a descriptor set.
 ///
 /// Use the various constructors to build a `DescriptorWrite`. While it is safe to build a
 /// `DescriptorWrite`, it is unsafe to actually use it to write to a descriptor set.
-// TODO: allow binding whole arrays at once
+///
+/// The constructors whose name is in the plural write consecutive elements of an array binding
+/// at once, starting at `first_array_element`. They panic if the list of resources is empty.
 pub struct DescriptorWrite {
     binding: u32,
     first_array_element: u32,
@@ -792,6 +877,27 @@ impl DescriptorWrite {
         }
     }
 
+    #[inline]
+    pub fn storage_images<I>(binding: u32, first_array_element: u32, images: &[I])
+                             -> DescriptorWrite
+        where I: ImageViewAccess
+    {
+        assert!(!images.is_empty(), "Can't write an empty list of descriptors");
+
+        DescriptorWrite {
+            binding: binding,
+            first_array_element: first_array_element,
+            inner: images
+                .iter()
+                .map(|image| {
+                         let layout = image.descriptor_set_storage_image_layout() as u32;
+                         DescriptorWriteInner::StorageImage(image.inner().internal_object(),
+                                                            layout)
+                     })
+                .collect(),
+        }
+    }
+
     #[inline]
     pub fn sampler(binding: u32, array_element: u32, sampler: &Arc<Sampler>) -> DescriptorWrite {
         DescriptorWrite {
@@ -816,6 +922,27 @@ impl DescriptorWrite {
         }
     }
 
+    #[inline]
+    pub fn sampled_images<I>(binding: u32, first_array_element: u32, images: &[I])
+                             -> DescriptorWrite
+        where I: ImageViewAccess
+    {
+        assert!(!images.is_empty(), "Can't write an empty list of descriptors");
+
+        DescriptorWrite {
+            binding: binding,
+            first_array_element: first_array_element,
+            inner: images
+                .iter()
+                .map(|image| {
+                         let layout = image.descriptor_set_sampled_image_layout() as u32;
+                         DescriptorWriteInner::SampledImage(image.inner().internal_object(),
+                                                            layout)
+                     })
+                .collect(),
+        }
+    }
+
     #[inline]
     pub fn combined_image_sampler<I>(binding: u32, array_element: u32, sampler: &Arc<Sampler>,
                                      image: &I)
@@ -838,6 +965,31 @@ impl DescriptorWrite {
         }
     }
 
+    #[inline]
+    pub fn combined_image_samplers<I>(binding: u32, first_array_element: u32,
+                                      samplers: &[Arc<Sampler>], images: &[I])
+                                      -> DescriptorWrite
+        where I: ImageViewAccess
+    {
+        assert!(!images.is_empty(), "Can't write an empty list of descriptors");
+        assert_eq!(samplers.len(), images.len());
+
+        DescriptorWrite {
+            binding: binding,
+            first_array_element: first_array_element,
+            inner: samplers
+                .iter()
+                .zip(images.iter())
+                .map(|(sampler, image)| {
+                         let layout = image.descriptor_set_combined_image_sampler_layout() as u32;
+                         DescriptorWriteInner::CombinedImageSampler(sampler.internal_object(),
+                                                                    image.inner().internal_object(),
+                                                                    layout)
+                     })
+                .collect(),
+        }
+    }
+
     #[inline]
     pub fn uniform_texel_buffer<'a, F, B>(binding: u32, array_element: u32, view: &BufferView<F, B>)
                                           -> DescriptorWrite
@@ -870,32 +1022,24 @@ impl DescriptorWrite {
     pub unsafe fn uniform_buffer<B>(binding: u32, array_element: u32, buffer: &B) -> DescriptorWrite
         where B: BufferAccess
     {
-        let size = buffer.size();
-        let BufferInner { buffer, offset } = buffer.inner();
+        DescriptorWrite {
+            binding: binding,
+            first_array_element: array_element,
+            inner: smallvec!(uniform_buffer_inner(buffer)),
+        }
+    }
 
-        debug_assert_eq!(offset %
-                             buffer
-                                 .device()
-                                 .physical_device()
-                                 .limits()
-                                 .min_uniform_buffer_offset_alignment() as
-                                 usize,
-                         0);
-        debug_assert!(size <=
-                          buffer
-                              .device()
-                              .physical_device()
-                              .limits()
-                              .max_uniform_buffer_range() as usize);
+    #[inline]
+    pub unsafe fn uniform_buffers<B>(binding: u32, first_array_element: u32, buffers: &[B])
+                                     -> DescriptorWrite
+        where B: BufferAccess
+    {
+        assert!(!buffers.is_empty(), "Can't write an empty list of descriptors");
 
         DescriptorWrite {
             binding: binding,
-            first_array_element: array_element,
-            inner: smallvec!({
-                                 DescriptorWriteInner::UniformBuffer(buffer.internal_object(),
-                                                                     offset,
-                                                                     size)
-                             }),
+            first_array_element: first_array_element,
+            inner: buffers.iter().map(|b| uniform_buffer_inner(b)).collect(),
         }
     }
 
@@ -903,32 +1047,24 @@ impl DescriptorWrite {
     pub unsafe fn storage_buffer<B>(binding: u32, array_element: u32, buffer: &B) -> DescriptorWrite
         where B: BufferAccess
     {
-        let size = buffer.size();
-        let BufferInner { buffer, offset } = buffer.inner();
+        DescriptorWrite {
+            binding: binding,
+            first_array_element: array_element,
+            inner: smallvec!(storage_buffer_inner(buffer)),
+        }
+    }
 
-        debug_assert_eq!(offset %
-                             buffer
-                                 .device()
-                                 .physical_device()
-                                 .limits()
-                                 .min_storage_buffer_offset_alignment() as
-                                 usize,
-                         0);
-        debug_assert!(size <=
-                          buffer
-                              .device()
-                              .physical_device()
-                              .limits()
-                              .max_storage_buffer_range() as usize);
+    #[inline]
+    pub unsafe fn storage_buffers<B>(binding: u32, first_array_element: u32, buffers: &[B])
+                                     -> DescriptorWrite
+        where B: BufferAccess
+    {
+        assert!(!buffers.is_empty(), "Can't write an empty list of descriptors");
 
         DescriptorWrite {
             binding: binding,
-            first_array_element: array_element,
-            inner: smallvec!({
-                                 DescriptorWriteInner::StorageBuffer(buffer.internal_object(),
-                                                                     offset,
-                                                                     size)
-                             }),
+            first_array_element: first_array_element,
+            inner: buffers.iter().map(|b| storage_buffer_inner(b)).collect(),
         }
     }
 
@@ -1013,6 +1149,27 @@ impl DescriptorWrite {
         }
     }
 
+    #[inline]
+    pub fn input_attachments<I>(binding: u32, first_array_element: u32, images: &[I])
+                                -> DescriptorWrite
+        where I: ImageViewAccess
+    {
+        assert!(!images.is_empty(), "Can't write an empty list of descriptors");
+
+        DescriptorWrite {
+            binding: binding,
+            first_array_element: first_array_element,
+            inner: images
+                .iter()
+                .map(|image| {
+                         let layout = image.descriptor_set_input_attachment_layout() as u32;
+                         DescriptorWriteInner::InputAttachment(image.inner().internal_object(),
+                                                               layout)
+                     })
+                .collect(),
+        }
+    }
+
     /// Returns the type corresponding to this write.
     #[inline]
     pub fn ty(&self) -> DescriptorType {
@@ -1035,16 +1192,113 @@ impl DescriptorWrite {
     }
 }
 
+// Builds the write of a single uniform buffer descriptor.
+#[inline]
+fn uniform_buffer_inner<B>(buffer: &B) -> DescriptorWriteInner
+    where B: BufferAccess
+{
+    let size = buffer.size();
+    let BufferInner { buffer, offset } = buffer.inner();
+
+    debug_assert_eq!(offset %
+                         buffer
+                             .device()
+                             .physical_device()
+                             .limits()
+                             .min_uniform_buffer_offset_alignment() as usize,
+                     0);
+    debug_assert!(size <=
+                      buffer
+                          .device()
+                          .physical_device()
+                          .limits()
+                          .max_uniform_buffer_range() as usize);
+
+    DescriptorWriteInner::UniformBuffer(buffer.internal_object(), offset, size)
+}
+
+// Builds the write of a single storage buffer descriptor.
+#[inline]
+fn storage_buffer_inner<B>(buffer: &B) -> DescriptorWriteInner
+    where B: BufferAccess
+{
+    let size = buffer.size();
+    let BufferInner { buffer, offset } = buffer.inner();
+
+    debug_assert_eq!(offset %
+                         buffer
+                             .device()
+                             .physical_device()
+                             .limits()
+                             .min_storage_buffer_offset_alignment() as usize,
+                     0);
+    debug_assert!(size <=
+                      buffer
+                          .device()
+                          .physical_device()
+                          .limits()
+                          .max_storage_buffer_range() as usize);
+
+    DescriptorWriteInner::StorageBuffer(buffer.internal_object(), offset, size)
+}
+
 #[cfg(test)]
 mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
     use descriptor::descriptor::DescriptorBufferDesc;
     use descriptor::descriptor::DescriptorDesc;
     use descriptor::descriptor::DescriptorDescTy;
     use descriptor::descriptor::ShaderStages;
+    use descriptor::descriptor_set::DescriptorCopy;
+    use descriptor::descriptor_set::DescriptorWrite;
     use descriptor::descriptor_set::DescriptorsCount;
     use descriptor::descriptor_set::UnsafeDescriptorPool;
     use descriptor::descriptor_set::UnsafeDescriptorSetLayout;
+    use device::Device;
     use std::iter;
+    use std::sync::Arc;
+    use testing::Call;
+
+    use VulkanObject;
+
+    // Builds a layout with a single binding that is an array of 4 uniform buffers, and a pool
+    // that can hold 2 such sets.
+    fn uniform_buffers_layout(device: Arc<Device>)
+                              -> (UnsafeDescriptorSetLayout, UnsafeDescriptorPool) {
+        let desc = DescriptorDesc {
+            ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                             dynamic: Some(false),
+                                             storage: false,
+                                         }),
+            array_count: 4,
+            stages: ShaderStages::all_graphics(),
+            readonly: true,
+        };
+
+        let layout = UnsafeDescriptorSetLayout::new(device.clone(), iter::once(Some(desc)))
+            .unwrap();
+        let count = DescriptorsCount {
+            uniform_buffer: 8,
+            ..DescriptorsCount::zero()
+        };
+        let pool = UnsafeDescriptorPool::new(device, &count, 2, false).unwrap();
+        (layout, pool)
+    }
+
+    // Returns the writes and copies of all the `vkUpdateDescriptorSets` calls.
+    fn updates(calls: Vec<Call>)
+               -> Vec<(Vec<(u64, u32, u32, u32)>, Vec<(u64, u32, u32, u64, u32, u32, u32)>)> {
+        calls
+            .into_iter()
+            .filter_map(|call| match call {
+                            Call::UpdateDescriptorSets { writes, copies, .. } => {
+                                Some((writes, copies))
+                            },
+                            _ => None,
+                        })
+            .collect()
+    }
 
     #[test]
     fn pool_create() {
@@ -1157,4 +1411,69 @@ mod tests {
             assert_eq!(sets.count(), 0);
         }
     }
+
+    #[test]
+    fn array_write() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let (layout, mut pool) = uniform_buffers_layout(device.clone());
+        let buffers = (0 .. 3)
+            .map(|_| {
+                     CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0u32)
+                         .unwrap()
+                 })
+            .collect::<Vec<_>>();
+
+        let mut set = unsafe { pool.alloc(iter::once(&layout)).unwrap().next().unwrap() };
+        loader.take_calls();
+        unsafe {
+            set.write(&device,
+                      iter::once(DescriptorWrite::uniform_buffers(0, 1, &buffers)));
+        }
+
+        let raw = set.internal_object();
+        assert_eq!(updates(loader.take_calls()),
+                   vec![(vec![(raw, 0, 1, 3)], vec![])]);
+    }
+
+    #[test]
+    fn empty_array_write() {
+        let buffers: Vec<Arc<CpuAccessibleBuffer<u32>>> = Vec::new();
+
+        assert_should_panic!("Can't write an empty list of descriptors", {
+            unsafe {
+                let _ = DescriptorWrite::uniform_buffers(0, 0, &buffers);
+            }
+        });
+    }
+
+    #[test]
+    fn copy() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let (layout, mut pool) = uniform_buffers_layout(device.clone());
+
+        let mut sets = unsafe { pool.alloc(vec![&layout, &layout]).unwrap() };
+        let source = sets.next().unwrap();
+        let mut destination = sets.next().unwrap();
+
+        loader.take_calls();
+        unsafe {
+            destination.copy(&device, iter::once(DescriptorCopy::new(&source, 0, 1, 0, 2, 2)));
+        }
+
+        let src = source.internal_object();
+        let dst = destination.internal_object();
+        assert_eq!(updates(loader.take_calls()),
+                   vec![(vec![], vec![(src, 0, 1, dst, 0, 2, 2)])]);
+    }
+
+    #[test]
+    fn copy_zero() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let (layout, mut pool) = uniform_buffers_layout(device);
+        let set = unsafe { pool.alloc(iter::once(&layout)).unwrap().next().unwrap() };
+
+        assert_should_panic!("Can't copy 0 descriptors", {
+            let _ = DescriptorCopy::new(&set, 0, 0, 0, 0, 0);
+        });
+    }
 }
@@ -12,6 +12,10 @@ use std::fmt;
 use std::mem;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::Weak;
+use std::sync::atomic::ATOMIC_USIZE_INIT;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 
 use OomError;
 use VulkanObject;
@@ -23,6 +27,9 @@ use descriptor::descriptor_set::DescriptorsCount;
 use device::Device;
 use device::DeviceOwned;
 
+// Source of the identifiers of the layouts.
+static NEXT_LAYOUT_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
 /// Describes to the Vulkan implementation the layout of all descriptors within a descriptor set.
 ///
 /// Despite its name, this type is technically not unsafe. However it serves the same purpose
@@ -35,6 +42,11 @@ pub struct UnsafeDescriptorSetLayout {
     device: Arc<Device>,
     // Number of descriptors.
     descriptors_count: DescriptorsCount,
+    // Identifier that is unique within the process. Contrary to the Vulkan handle, it is never
+    // reused after the layout has been destroyed.
+    id: usize,
+    // Only referenced by this layout. Weak handles to it tell whether the layout is still alive.
+    alive: Arc<()>,
 }
 
 impl UnsafeDescriptorSetLayout {
@@ -98,6 +110,8 @@ impl UnsafeDescriptorSetLayout {
                layout: layout,
                device: device,
                descriptors_count: descriptors_count,
+               id: NEXT_LAYOUT_ID.fetch_add(1, Ordering::Relaxed),
+               alive: Arc::new(()),
            })
     }
 
@@ -106,6 +120,18 @@ impl UnsafeDescriptorSetLayout {
     pub fn descriptors_count(&self) -> &DescriptorsCount {
         &self.descriptors_count
     }
+
+    /// Returns an identifier of this layout that is unique within the process.
+    #[inline]
+    pub(crate) fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Returns a handle that can no longer be upgraded once this layout has been destroyed.
+    #[inline]
+    pub(crate) fn weak_handle(&self) -> Weak<()> {
+        Arc::downgrade(&self.alive)
+    }
 }
 
 unsafe impl DeviceOwned for UnsafeDescriptorSetLayout {
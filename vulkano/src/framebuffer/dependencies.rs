@@ -0,0 +1,353 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::collections::BTreeMap;
+
+use framebuffer::ATTACHMENT_UNUSED;
+use framebuffer::LayoutPassDependencyDescription;
+use framebuffer::LayoutPassDescription;
+use image::ImageLayout;
+use sync::AccessFlagBits;
+use sync::PipelineStages;
+
+/// Infers the minimal list of dependencies between subpasses from the way each subpass uses
+/// the attachments.
+///
+/// A dependency is generated from a subpass to a later subpass if the later subpass reads an
+/// attachment that the earlier one wrote (for example a color attachment that is then used as an
+/// input attachment), if it writes an attachment that the earlier one read or wrote, or if it
+/// uses the attachment with a different layout. Only the latest subpasses that accessed the
+/// attachment are depended upon, as the dependencies with the older ones are implied.
+///
+/// The stages and access masks of each dependency only contain the ones that correspond to the
+/// attachment usages that caused it. Since all the accesses to attachments within a render pass
+/// are local to a pixel, `by_region` is set whenever all the stages involved are framebuffer-space
+/// stages, which is always the case for the dependencies returned by this function.
+///
+/// Subpasses that don't share any attachment don't get any dependency between them. If a subpass
+/// reads a resource that isn't an attachment (for example a storage buffer) written by an earlier
+/// subpass, you have to add the corresponding dependency yourself.
+///
+/// This function can be called with the output of `RenderPassDesc::subpass_descs()`.
+pub fn infer_subpass_dependencies<I>(subpasses: I) -> Vec<LayoutPassDependencyDescription>
+    where I: IntoIterator<Item = LayoutPassDescription>
+{
+    // For each attachment, the latest subpass that wrote it, the subpasses that read it since
+    // then, and the layout it was last used with.
+    struct AttachmentState {
+        last_write: Option<(usize, Usage)>,
+        reads: Vec<(usize, Usage)>,
+        layout: ImageLayout,
+    }
+
+    let mut attachments: BTreeMap<usize, AttachmentState> = BTreeMap::new();
+    // Dependencies indexed by `(source_subpass, destination_subpass)`.
+    let mut dependencies: BTreeMap<(usize, usize), LayoutPassDependencyDescription> =
+        BTreeMap::new();
+
+    for (dst, subpass) in subpasses.into_iter().enumerate() {
+        for (attachment, usage) in subpass_usages(&subpass) {
+            let state = attachments.entry(attachment).or_insert(AttachmentState {
+                                                                    last_write: None,
+                                                                    reads: Vec::new(),
+                                                                    layout: usage.layout,
+                                                                });
+
+            // A layout transition reads and writes the whole attachment, and must therefore be
+            // ordered as if it was a write.
+            let writes = usage.write || state.layout != usage.layout;
+
+            if let Some((src, ref src_usage)) = state.last_write {
+                add_dependency(&mut dependencies, src, src_usage, dst, &usage);
+            }
+
+            if writes {
+                for &(src, ref src_usage) in state.reads.iter() {
+                    add_dependency(&mut dependencies, src, src_usage, dst, &usage);
+                }
+
+                state.last_write = Some((dst, usage));
+                state.reads.clear();
+            } else {
+                state.reads.push((dst, usage));
+            }
+
+            state.layout = usage.layout;
+        }
+    }
+
+    dependencies.into_iter().map(|(_, dependency)| dependency).collect()
+}
+
+// How a subpass uses one of the attachments.
+#[derive(Debug, Copy, Clone)]
+struct Usage {
+    stages: PipelineStages,
+    access: AccessFlagBits,
+    write: bool,
+    layout: ImageLayout,
+}
+
+// Returns the list of attachments used by a subpass, and how they are used. An attachment that is
+// referenced multiple times appears only once.
+fn subpass_usages(subpass: &LayoutPassDescription) -> Vec<(usize, Usage)> {
+    let mut usages: BTreeMap<usize, Usage> = BTreeMap::new();
+
+    {
+        let mut add = |(attachment, layout): (usize, ImageLayout), stages, access, write| {
+            if attachment == ATTACHMENT_UNUSED {
+                return;
+            }
+
+            let usage = usages.entry(attachment).or_insert(Usage {
+                                                               stages: PipelineStages::none(),
+                                                               access: AccessFlagBits::none(),
+                                                               write: false,
+                                                               layout: layout,
+                                                           });
+            usage.stages |= stages;
+            usage.access |= access;
+            usage.write |= write;
+        };
+
+        let color_stages = PipelineStages {
+            color_attachment_output: true,
+            ..PipelineStages::none()
+        };
+
+        let depth_stages = PipelineStages {
+            early_fragment_tests: true,
+            late_fragment_tests: true,
+            ..PipelineStages::none()
+        };
+
+        let input_stages = PipelineStages {
+            fragment_shader: true,
+            ..PipelineStages::none()
+        };
+
+        for &reference in subpass.color_attachments.iter() {
+            // Blending reads the existing content of the attachment.
+            let access = AccessFlagBits {
+                color_attachment_read: true,
+                color_attachment_write: true,
+                ..AccessFlagBits::none()
+            };
+            add(reference, color_stages, access, true);
+        }
+
+        for &reference in subpass.resolve_attachments.iter() {
+            let access = AccessFlagBits {
+                color_attachment_write: true,
+                ..AccessFlagBits::none()
+            };
+            add(reference, color_stages, access, true);
+        }
+
+        if let Some(reference) = subpass.depth_stencil {
+            let write = reference.1 != ImageLayout::DepthStencilReadOnlyOptimal;
+            let access = AccessFlagBits {
+                depth_stencil_attachment_read: true,
+                depth_stencil_attachment_write: write,
+                ..AccessFlagBits::none()
+            };
+            add(reference, depth_stages, access, write);
+        }
+
+        for &reference in subpass.input_attachments.iter() {
+            let access = AccessFlagBits {
+                input_attachment_read: true,
+                ..AccessFlagBits::none()
+            };
+            add(reference, input_stages, access, false);
+        }
+    }
+
+    usages.into_iter().collect()
+}
+
+// Adds to `dependencies` what is needed for `dst_usage` in subpass `dst` to happen after
+// `src_usage` in subpass `src`.
+fn add_dependency(dependencies: &mut BTreeMap<(usize, usize), LayoutPassDependencyDescription>,
+                  src: usize, src_usage: &Usage, dst: usize, dst_usage: &Usage) {
+    debug_assert!(src < dst);
+
+    // Only writes need to be made available. Read accesses only need an execution dependency.
+    let source_access = AccessFlagBits {
+        color_attachment_write: src_usage.access.color_attachment_write,
+        depth_stencil_attachment_write: src_usage.access.depth_stencil_attachment_write,
+        ..AccessFlagBits::none()
+    };
+
+    let dependency = dependencies.entry((src, dst)).or_insert(LayoutPassDependencyDescription {
+        source_subpass: src,
+        destination_subpass: dst,
+        source_stages: PipelineStages::none(),
+        destination_stages: PipelineStages::none(),
+        source_access: AccessFlagBits::none(),
+        destination_access: AccessFlagBits::none(),
+        by_region: true,
+    });
+
+    dependency.source_stages |= src_usage.stages;
+    dependency.destination_stages |= dst_usage.stages;
+    dependency.source_access |= source_access;
+    dependency.destination_access |= dst_usage.access;
+    dependency.by_region = is_framebuffer_space(&dependency.source_stages) &&
+        is_framebuffer_space(&dependency.destination_stages);
+}
+
+// Returns true if all the stages are framebuffer-space stages, in which case a dependency
+// between them can be by region.
+#[inline]
+pub(crate) fn is_framebuffer_space(stages: &PipelineStages) -> bool {
+    let framebuffer_stages = PipelineStages {
+        fragment_shader: stages.fragment_shader,
+        early_fragment_tests: stages.early_fragment_tests,
+        late_fragment_tests: stages.late_fragment_tests,
+        color_attachment_output: stages.color_attachment_output,
+        ..PipelineStages::none()
+    };
+
+    *stages == framebuffer_stages
+}
+
+#[cfg(test)]
+mod tests {
+    use framebuffer::LayoutPassDescription;
+    use framebuffer::infer_subpass_dependencies;
+    use image::ImageLayout;
+    use sync::PipelineStages;
+
+    fn subpass(color: Vec<usize>, depth: Option<(usize, ImageLayout)>, input: Vec<usize>)
+               -> LayoutPassDescription {
+        LayoutPassDescription {
+            color_attachments: color
+                .into_iter()
+                .map(|a| (a, ImageLayout::ColorAttachmentOptimal))
+                .collect(),
+            depth_stencil: depth,
+            input_attachments: input
+                .into_iter()
+                .map(|a| (a, ImageLayout::ShaderReadOnlyOptimal))
+                .collect(),
+            resolve_attachments: vec![],
+            preserve_attachments: vec![],
+        }
+    }
+
+    #[test]
+    fn color_to_input() {
+        let deps = infer_subpass_dependencies(vec![subpass(vec![0], None, vec![]),
+                                                   subpass(vec![1], None, vec![0])]);
+
+        assert_eq!(deps.len(), 1);
+        let dep = &deps[0];
+        assert_eq!(dep.source_subpass, 0);
+        assert_eq!(dep.destination_subpass, 1);
+        assert_eq!(dep.source_stages,
+                   PipelineStages {
+                       color_attachment_output: true,
+                       ..PipelineStages::none()
+                   });
+        assert_eq!(dep.destination_stages,
+                   PipelineStages {
+                       fragment_shader: true,
+                       ..PipelineStages::none()
+                   });
+        assert!(dep.source_access.color_attachment_write);
+        assert!(!dep.source_access.color_attachment_read);
+        assert!(dep.destination_access.input_attachment_read);
+        assert!(!dep.destination_access.color_attachment_write);
+        assert!(dep.by_region);
+    }
+
+    #[test]
+    fn depth_to_input() {
+        let depth = Some((0, ImageLayout::DepthStencilAttachmentOptimal));
+        let deps = infer_subpass_dependencies(vec![subpass(vec![], depth, vec![]),
+                                                   subpass(vec![1], None, vec![0])]);
+
+        assert_eq!(deps.len(), 1);
+        let dep = &deps[0];
+        assert_eq!(dep.source_stages,
+                   PipelineStages {
+                       early_fragment_tests: true,
+                       late_fragment_tests: true,
+                       ..PipelineStages::none()
+                   });
+        assert!(dep.source_access.depth_stencil_attachment_write);
+        assert!(dep.destination_access.input_attachment_read);
+        assert!(dep.by_region);
+    }
+
+    #[test]
+    fn independent_subpasses() {
+        let deps = infer_subpass_dependencies(vec![subpass(vec![0], None, vec![]),
+                                                   subpass(vec![1], None, vec![])]);
+        assert!(deps.is_empty());
+    }
+
+    #[test]
+    fn only_latest_writer() {
+        // Subpass 2 only needs to depend on subpass 1, which itself depends on subpass 0.
+        let deps = infer_subpass_dependencies(vec![subpass(vec![0], None, vec![]),
+                                                   subpass(vec![0], None, vec![]),
+                                                   subpass(vec![1], None, vec![0])]);
+
+        assert_eq!(deps.len(), 2);
+        assert_eq!((deps[0].source_subpass, deps[0].destination_subpass), (0, 1));
+        assert_eq!((deps[1].source_subpass, deps[1].destination_subpass), (1, 2));
+    }
+
+    #[test]
+    fn write_after_read() {
+        // Subpass 1 transitions the attachment to the layout of input attachments, so subpass 2
+        // is ordered after it. Both readers must be finished before subpass 3 overwrites the
+        // attachment.
+        let deps = infer_subpass_dependencies(vec![subpass(vec![0], None, vec![]),
+                                                   subpass(vec![1], None, vec![0]),
+                                                   subpass(vec![2], None, vec![0]),
+                                                   subpass(vec![0], None, vec![])]);
+
+        let pairs = deps.iter()
+            .map(|d| (d.source_subpass, d.destination_subpass))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, vec![(0, 1), (1, 2), (1, 3), (2, 3)]);
+
+        let war = deps.iter().find(|d| d.source_subpass == 2).unwrap();
+        assert!(!war.source_access.color_attachment_write);
+        assert!(!war.source_access.input_attachment_read);
+        assert!(war.source_stages.fragment_shader);
+        assert!(war.destination_access.color_attachment_write);
+    }
+
+    #[test]
+    fn read_only_depth() {
+        // Reading the depth buffer in two subpasses with the same layout doesn't need ordering.
+        let depth = Some((0, ImageLayout::DepthStencilReadOnlyOptimal));
+        let deps = infer_subpass_dependencies(vec![subpass(vec![1], depth, vec![]),
+                                                   subpass(vec![2], depth, vec![])]);
+        assert!(deps.is_empty());
+    }
+
+    #[test]
+    fn merged_attachments() {
+        let depth = Some((1, ImageLayout::DepthStencilAttachmentOptimal));
+        let deps = infer_subpass_dependencies(vec![subpass(vec![0], depth, vec![]),
+                                                   subpass(vec![2], None, vec![0, 1])]);
+
+        assert_eq!(deps.len(), 1);
+        let dep = &deps[0];
+        assert!(dep.source_stages.color_attachment_output);
+        assert!(dep.source_stages.late_fragment_tests);
+        assert!(dep.source_access.color_attachment_write);
+        assert!(dep.source_access.depth_stencil_attachment_write);
+    }
+}
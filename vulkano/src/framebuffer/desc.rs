@@ -459,12 +459,20 @@ impl LayoutAttachmentDescription {
     }
 }
 
+/// Value that can be used in place of an attachment index in the color, input and resolve
+/// attachments of a `LayoutPassDescription`, in order to indicate that the slot is not used.
+///
+/// For example a subpass whose `color_attachments` are attachment 0, `ATTACHMENT_UNUSED` and
+/// attachment 2 has fragment shader outputs at locations 0 and 2, and the output at location 1 is
+/// discarded.
+pub const ATTACHMENT_UNUSED: usize = !0;
+
 /// Describes one of the passes of a render pass.
 ///
 /// # Restrictions
 ///
-/// All these restrictions are checked when the `RenderPass` object is created.
-/// TODO: that's not the case ^
+/// All these restrictions are checked by `RenderPassDescBuilder::build`. The `RenderPass` object
+/// only checks some of them when it is created.
 ///
 /// - The number of color attachments must be less than the limit of the physical device.
 /// - All the attachments in `color_attachments` and `depth_stencil` must have the same
@@ -480,8 +488,8 @@ impl LayoutAttachmentDescription {
 ///   attachment is not also used as a color or depth/stencil attachment in the same subpass,
 ///   then the loading operation must not be `Clear`.
 ///
-// TODO: add tests for all these restrictions
-// TODO: allow unused attachments (for example attachment 0 and 2 are used, 1 is unused)
+/// The color, input and resolve attachments can contain `ATTACHMENT_UNUSED`. A resolve attachment
+/// must be unused if the corresponding color attachment is unused.
 #[derive(Debug, Clone)]
 pub struct LayoutPassDescription {
     /// Indices and layouts of attachments to use as color attachments.
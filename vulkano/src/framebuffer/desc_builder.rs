@@ -0,0 +1,1078 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::error;
+use std::fmt;
+
+use format::ClearValue;
+use format::FormatTy;
+use framebuffer::ATTACHMENT_UNUSED;
+use framebuffer::LayoutAttachmentDescription;
+use framebuffer::LayoutPassDependencyDescription;
+use framebuffer::LayoutPassDescription;
+use framebuffer::LoadOp;
+use framebuffer::RenderPassDesc;
+use framebuffer::RenderPassDescClearValues;
+use framebuffer::dependencies::is_framebuffer_space;
+use framebuffer::infer_subpass_dependencies;
+use image::ImageLayout;
+use sync::PipelineStages;
+
+/// Builds the description of a render pass at runtime.
+///
+/// Contrary to the `single_pass_renderpass!` and `ordered_passes_renderpass!` macros, the
+/// attachments, subpasses and dependencies don't need to be known at compile time. The
+/// description is checked against the restrictions of the Vulkan specifications when `build` is
+/// called, so that `RenderPass::new` never receives an invalid description.
+///
+/// # Example
+///
+/// ```
+/// use vulkano::format::Format;
+/// use vulkano::framebuffer::LayoutAttachmentDescription;
+/// use vulkano::framebuffer::LayoutPassDescription;
+/// use vulkano::framebuffer::LoadOp;
+/// use vulkano::framebuffer::RenderPassDesc;
+/// use vulkano::framebuffer::RenderPassDescBuilder;
+/// use vulkano::framebuffer::StoreOp;
+/// use vulkano::image::ImageLayout;
+///
+/// # let device: std::sync::Arc<vulkano::device::Device> = return;
+/// let desc = RenderPassDescBuilder::new()
+///     .attachment(LayoutAttachmentDescription {
+///         format: Format::R8G8B8A8Unorm,
+///         samples: 1,
+///         load: LoadOp::Clear,
+///         store: StoreOp::Store,
+///         stencil_load: LoadOp::DontCare,
+///         stencil_store: StoreOp::DontCare,
+///         initial_layout: ImageLayout::Undefined,
+///         final_layout: ImageLayout::ColorAttachmentOptimal,
+///     })
+///     .subpass(LayoutPassDescription {
+///         color_attachments: vec![(0, ImageLayout::ColorAttachmentOptimal)],
+///         depth_stencil: None,
+///         input_attachments: vec![],
+///         resolve_attachments: vec![],
+///         preserve_attachments: vec![],
+///     })
+///     .build()
+///     .unwrap();
+///
+/// let render_pass = desc.build_render_pass(device.clone()).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RenderPassDescBuilder {
+    attachments: Vec<LayoutAttachmentDescription>,
+    subpasses: Vec<LayoutPassDescription>,
+    dependencies: Vec<LayoutPassDependencyDescription>,
+}
+
+impl RenderPassDescBuilder {
+    /// Builds a new empty builder.
+    #[inline]
+    pub fn new() -> RenderPassDescBuilder {
+        RenderPassDescBuilder {
+            attachments: Vec::new(),
+            subpasses: Vec::new(),
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// Adds an attachment to the render pass. Attachments are numbered in the order in which they
+    /// are added, starting from 0.
+    #[inline]
+    pub fn attachment(mut self, attachment: LayoutAttachmentDescription) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Adds a subpass to the render pass. Subpasses are numbered in the order in which they are
+    /// added, starting from 0.
+    #[inline]
+    pub fn subpass(mut self, subpass: LayoutPassDescription) -> Self {
+        self.subpasses.push(subpass);
+        self
+    }
+
+    /// Adds a dependency between two subpasses.
+    #[inline]
+    pub fn dependency(mut self, dependency: LayoutPassDependencyDescription) -> Self {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    /// Adds the dependencies that are required by the way the subpasses added so far use the
+    /// attachments.
+    ///
+    /// See `infer_subpass_dependencies` for more information. Dependencies that don't involve
+    /// attachments must still be added with `dependency`.
+    #[inline]
+    pub fn infer_dependencies(mut self) -> Self {
+        let dependencies = infer_subpass_dependencies(self.subpasses.iter().cloned());
+        self.dependencies.extend(dependencies);
+        self
+    }
+
+    /// Checks the description and turns it into an object that implements `RenderPassDesc`.
+    ///
+    /// The limits of the device, such as the maximum number of color attachments, are checked
+    /// later by `RenderPass::new`.
+    pub fn build(self) -> Result<RuntimeRenderPassDesc, RenderPassDescError> {
+        for (num, attachment) in self.attachments.iter().enumerate() {
+            if !attachment.samples.is_power_of_two() || attachment.samples > 64 {
+                return Err(RenderPassDescError::InvalidSamplesCount { attachment: num });
+            }
+
+            if attachment.format.ty() == FormatTy::Compressed {
+                return Err(RenderPassDescError::CompressedFormat { attachment: num });
+            }
+
+            match attachment.final_layout {
+                ImageLayout::Undefined |
+                ImageLayout::Preinitialized => {
+                    return Err(RenderPassDescError::InvalidFinalLayout { attachment: num });
+                },
+                _ => (),
+            }
+        }
+
+        if self.subpasses.is_empty() {
+            return Err(RenderPassDescError::NoSubpass);
+        }
+
+        // For each attachment, true if it has been used by one of the subpasses that were
+        // checked so far.
+        let mut used = vec![false; self.attachments.len()];
+        for (num, subpass) in self.subpasses.iter().enumerate() {
+            self.check_subpass(num, subpass, &mut used)?;
+        }
+
+        for (num, dependency) in self.dependencies.iter().enumerate() {
+            if dependency.source_subpass >= self.subpasses.len() ||
+                dependency.destination_subpass >= self.subpasses.len()
+            {
+                return Err(RenderPassDescError::DependencySubpassOutOfRange { dependency: num });
+            }
+
+            if dependency.source_subpass > dependency.destination_subpass {
+                return Err(RenderPassDescError::DependencyWrongOrder { dependency: num });
+            }
+
+            if dependency.source_stages == PipelineStages::none() ||
+                dependency.destination_stages == PipelineStages::none()
+            {
+                return Err(RenderPassDescError::DependencyEmptyStages { dependency: num });
+            }
+
+            if !dependency
+                .source_access
+                .is_compatible_with(&dependency.source_stages) ||
+                !dependency
+                    .destination_access
+                    .is_compatible_with(&dependency.destination_stages)
+            {
+                return Err(RenderPassDescError::DependencyAccessNotSupportedByStages {
+                               dependency: num,
+                           });
+            }
+
+            // A dependency of a subpass on itself can only synchronize framebuffer-space stages,
+            // and must then be by region.
+            if dependency.source_subpass == dependency.destination_subpass {
+                if !is_framebuffer_space(&dependency.source_stages) ||
+                    !is_framebuffer_space(&dependency.destination_stages)
+                {
+                    return Err(RenderPassDescError::SelfDependencyNotFramebufferSpace {
+                                   dependency: num,
+                               });
+                }
+
+                if !dependency.by_region {
+                    return Err(RenderPassDescError::SelfDependencyNotByRegion {
+                                   dependency: num,
+                               });
+                }
+            }
+        }
+
+        Ok(RuntimeRenderPassDesc {
+               attachments: self.attachments,
+               subpasses: self.subpasses,
+               dependencies: self.dependencies,
+           })
+    }
+
+    // Checks the subpass `num`, and marks the attachments it uses in `used`.
+    fn check_subpass(&self, num: usize, subpass: &LayoutPassDescription, used: &mut [bool])
+                     -> Result<(), RenderPassDescError> {
+        let mut samples = None;
+        let mut check_samples = |attachment: &LayoutAttachmentDescription| match samples {
+            Some(s) if s != attachment.samples => Err(RenderPassDescError::SamplesMismatch {
+                                                          subpass: num,
+                                                      }),
+            _ => {
+                samples = Some(attachment.samples);
+                Ok(())
+            },
+        };
+
+        for &(atch_num, layout) in subpass.color_attachments.iter() {
+            if let Some(attachment) = self.check_reference(num, atch_num, layout, true)? {
+                match attachment.format.ty() {
+                    FormatTy::Float | FormatTy::Uint | FormatTy::Sint => (),
+                    _ => {
+                        return Err(RenderPassDescError::ColorAttachmentWrongFormat {
+                                       subpass: num,
+                                       attachment: atch_num,
+                                   });
+                    },
+                }
+
+                check_samples(attachment)?;
+            }
+        }
+
+        if let Some((atch_num, layout)) = subpass.depth_stencil {
+            let attachment = self.check_reference(num, atch_num, layout, false)?.unwrap();
+            if !attachment.format.ty().is_depth_and_or_stencil() {
+                return Err(RenderPassDescError::DepthStencilAttachmentWrongFormat {
+                               subpass: num,
+                               attachment: atch_num,
+                           });
+            }
+
+            check_samples(attachment)?;
+        }
+
+        for &(atch_num, layout) in subpass.input_attachments.iter() {
+            let attachment = match self.check_reference(num, atch_num, layout, true)? {
+                Some(a) => a,
+                None => continue,
+            };
+
+            let other_use = subpass
+                .color_attachments
+                .iter()
+                .chain(subpass.depth_stencil.iter())
+                .find(|&&(a, _)| a == atch_num);
+
+            match other_use {
+                Some(&(_, other_layout)) => {
+                    if other_layout != layout {
+                        return Err(RenderPassDescError::AttachmentLayoutMismatch {
+                                       subpass: num,
+                                       attachment: atch_num,
+                                   });
+                    }
+                },
+                None => {
+                    if !used[atch_num] && attachment.load == LoadOp::Clear {
+                        return Err(RenderPassDescError::InputAttachmentCleared {
+                                       subpass: num,
+                                       attachment: atch_num,
+                                   });
+                    }
+                },
+            }
+        }
+
+        if !subpass.resolve_attachments.is_empty() {
+            if subpass.resolve_attachments.len() != subpass.color_attachments.len() {
+                return Err(RenderPassDescError::ResolveAttachmentsCountMismatch { subpass: num });
+            }
+
+            for (&(atch_num, layout), &(color_num, _)) in
+                subpass
+                    .resolve_attachments
+                    .iter()
+                    .zip(subpass.color_attachments.iter())
+            {
+                let attachment = match self.check_reference(num, atch_num, layout, true)? {
+                    Some(a) => a,
+                    None => continue,
+                };
+
+                if attachment.samples != 1 {
+                    return Err(RenderPassDescError::ResolveAttachmentMultisampled {
+                                   subpass: num,
+                                   attachment: atch_num,
+                               });
+                }
+
+                let color = if color_num == ATTACHMENT_UNUSED {
+                    None
+                } else {
+                    Some(&self.attachments[color_num])
+                };
+
+                match color {
+                    Some(ref c) if c.samples > 1 => {
+                        if c.format != attachment.format {
+                            return Err(RenderPassDescError::ResolveFormatMismatch {
+                                           subpass: num,
+                                           attachment: atch_num,
+                                       });
+                        }
+                    },
+                    _ => {
+                        return Err(RenderPassDescError::ResolveSourceNotMultisampled {
+                                       subpass: num,
+                                       attachment: atch_num,
+                                   });
+                    },
+                }
+            }
+        }
+
+        let uses = || {
+            subpass
+                .color_attachments
+                .iter()
+                .chain(subpass.depth_stencil.iter())
+                .chain(subpass.input_attachments.iter())
+                .chain(subpass.resolve_attachments.iter())
+                .map(|&(a, _)| a)
+                .filter(|&a| a != ATTACHMENT_UNUSED)
+        };
+
+        for &atch_num in subpass.preserve_attachments.iter() {
+            if atch_num >= self.attachments.len() {
+                return Err(RenderPassDescError::AttachmentOutOfRange {
+                               subpass: num,
+                               attachment: atch_num,
+                           });
+            }
+
+            if uses().any(|a| a == atch_num) {
+                return Err(RenderPassDescError::PreserveAttachmentAlreadyUsed {
+                               subpass: num,
+                               attachment: atch_num,
+                           });
+            }
+        }
+
+        for atch_num in uses() {
+            used[atch_num] = true;
+        }
+
+        Ok(())
+    }
+
+    // Checks an attachment reference of the subpass `subpass`. Returns `None` if the reference is
+    // `ATTACHMENT_UNUSED` and `unused_allowed` is true.
+    fn check_reference(&self, subpass: usize, attachment: usize, layout: ImageLayout,
+                       unused_allowed: bool)
+                       -> Result<Option<&LayoutAttachmentDescription>, RenderPassDescError> {
+        if attachment == ATTACHMENT_UNUSED && unused_allowed {
+            return Ok(None);
+        }
+
+        if attachment >= self.attachments.len() {
+            return Err(RenderPassDescError::AttachmentOutOfRange {
+                           subpass: subpass,
+                           attachment: attachment,
+                       });
+        }
+
+        match layout {
+            ImageLayout::Undefined |
+            ImageLayout::Preinitialized => {
+                return Err(RenderPassDescError::InvalidReferenceLayout {
+                               subpass: subpass,
+                               attachment: attachment,
+                           });
+            },
+            _ => (),
+        }
+
+        Ok(Some(&self.attachments[attachment]))
+    }
+}
+
+/// Description of a render pass built with a `RenderPassDescBuilder`.
+#[derive(Debug, Clone)]
+pub struct RuntimeRenderPassDesc {
+    attachments: Vec<LayoutAttachmentDescription>,
+    subpasses: Vec<LayoutPassDescription>,
+    dependencies: Vec<LayoutPassDependencyDescription>,
+}
+
+unsafe impl RenderPassDesc for RuntimeRenderPassDesc {
+    #[inline]
+    fn num_attachments(&self) -> usize {
+        self.attachments.len()
+    }
+
+    #[inline]
+    fn attachment_desc(&self, num: usize) -> Option<LayoutAttachmentDescription> {
+        self.attachments.get(num).cloned()
+    }
+
+    #[inline]
+    fn num_subpasses(&self) -> usize {
+        self.subpasses.len()
+    }
+
+    #[inline]
+    fn subpass_desc(&self, num: usize) -> Option<LayoutPassDescription> {
+        self.subpasses.get(num).cloned()
+    }
+
+    #[inline]
+    fn num_dependencies(&self) -> usize {
+        self.dependencies.len()
+    }
+
+    #[inline]
+    fn dependency_desc(&self, num: usize) -> Option<LayoutPassDependencyDescription> {
+        self.dependencies.get(num).cloned()
+    }
+}
+
+unsafe impl RenderPassDescClearValues<Vec<ClearValue>> for RuntimeRenderPassDesc {
+    /// # Panic
+    ///
+    /// - Panics if the number of clear values is not the number of attachments.
+    /// - Panics if a clear value doesn't match the format of its attachment, or isn't
+    ///   `ClearValue::None` for an attachment whose `load` and `stencil_load` aren't
+    ///   `LoadOp::Clear`.
+    ///
+    fn convert_clear_values(&self, values: Vec<ClearValue>) -> Box<Iterator<Item = ClearValue>> {
+        assert_eq!(values.len(),
+                   self.attachments.len(),
+                   "Wrong number of clear values");
+
+        for (attachment, value) in self.attachments.iter().zip(values.iter()) {
+            let cleared = attachment.load == LoadOp::Clear ||
+                attachment.stencil_load == LoadOp::Clear;
+            let matches = match (cleared, *value) {
+                (false, ClearValue::None) => true,
+                (true, ClearValue::Float(_)) => attachment.format.ty() == FormatTy::Float,
+                (true, ClearValue::Int(_)) => attachment.format.ty() == FormatTy::Sint,
+                (true, ClearValue::Uint(_)) => attachment.format.ty() == FormatTy::Uint,
+                (true, ClearValue::Depth(_)) => attachment.format.ty() == FormatTy::Depth,
+                (true, ClearValue::Stencil(_)) => attachment.format.ty() == FormatTy::Stencil,
+                (true, ClearValue::DepthStencil(_)) => {
+                    attachment.format.ty() == FormatTy::DepthStencil
+                },
+                _ => false,
+            };
+
+            assert!(matches, "Clear value doesn't match its attachment");
+        }
+
+        Box::new(values.into_iter())
+    }
+}
+
+/// Error that can happen when building a `RuntimeRenderPassDesc`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RenderPassDescError {
+    /// The number of samples of an attachment is not a power of two between 1 and 64.
+    InvalidSamplesCount {
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// An attachment has a compressed format.
+    CompressedFormat {
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// The final layout of an attachment is `Undefined` or `Preinitialized`.
+    InvalidFinalLayout {
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// The render pass doesn't have any subpass.
+    NoSubpass,
+    /// A subpass references an attachment that doesn't exist, or uses `ATTACHMENT_UNUSED` for
+    /// a depth-stencil or preserve attachment.
+    AttachmentOutOfRange {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// A subpass references an attachment with the `Undefined` or `Preinitialized` layout.
+    InvalidReferenceLayout {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// A color attachment of a subpass doesn't have a color format.
+    ColorAttachmentWrongFormat {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// The depth-stencil attachment of a subpass doesn't have a depth and/or stencil format.
+    DepthStencilAttachmentWrongFormat {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// The color and depth-stencil attachments of a subpass don't all have the same number of
+    /// samples.
+    SamplesMismatch {
+        /// Index of the subpass.
+        subpass: usize,
+    },
+    /// An attachment is used both as an input attachment and as a color or depth-stencil
+    /// attachment of a subpass, with different layouts.
+    AttachmentLayoutMismatch {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// The first use of an attachment is as an input attachment, but the attachment is loaded
+    /// with `LoadOp::Clear`.
+    InputAttachmentCleared {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// A subpass has resolve attachments, but not as many as color attachments.
+    ResolveAttachmentsCountMismatch {
+        /// Index of the subpass.
+        subpass: usize,
+    },
+    /// A resolve attachment has more than one sample.
+    ResolveAttachmentMultisampled {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the resolve attachment.
+        attachment: usize,
+    },
+    /// The color attachment corresponding to a resolve attachment is unused or has only one
+    /// sample.
+    ResolveSourceNotMultisampled {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the resolve attachment.
+        attachment: usize,
+    },
+    /// A resolve attachment doesn't have the same format as its color attachment.
+    ResolveFormatMismatch {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the resolve attachment.
+        attachment: usize,
+    },
+    /// A preserve attachment of a subpass is also used by another member of the subpass.
+    PreserveAttachmentAlreadyUsed {
+        /// Index of the subpass.
+        subpass: usize,
+        /// Index of the attachment.
+        attachment: usize,
+    },
+    /// A dependency references a subpass that doesn't exist.
+    DependencySubpassOutOfRange {
+        /// Index of the dependency.
+        dependency: usize,
+    },
+    /// The source subpass of a dependency is after its destination subpass.
+    DependencyWrongOrder {
+        /// Index of the dependency.
+        dependency: usize,
+    },
+    /// The source or destination stages of a dependency are empty.
+    DependencyEmptyStages {
+        /// Index of the dependency.
+        dependency: usize,
+    },
+    /// The access flags of a dependency aren't supported by its pipeline stages.
+    DependencyAccessNotSupportedByStages {
+        /// Index of the dependency.
+        dependency: usize,
+    },
+    /// The source and destination subpasses of a dependency are the same, but its stages aren't
+    /// all framebuffer-space stages.
+    SelfDependencyNotFramebufferSpace {
+        /// Index of the dependency.
+        dependency: usize,
+    },
+    /// The source and destination subpasses of a dependency are the same, but the dependency
+    /// isn't by region.
+    SelfDependencyNotByRegion {
+        /// Index of the dependency.
+        dependency: usize,
+    },
+}
+
+impl error::Error for RenderPassDescError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            RenderPassDescError::InvalidSamplesCount { .. } => {
+                "the number of samples of an attachment is not a power of two between 1 and 64"
+            },
+            RenderPassDescError::CompressedFormat { .. } => {
+                "an attachment has a compressed format"
+            },
+            RenderPassDescError::InvalidFinalLayout { .. } => {
+                "the final layout of an attachment is `Undefined` or `Preinitialized`"
+            },
+            RenderPassDescError::NoSubpass => {
+                "the render pass doesn't have any subpass"
+            },
+            RenderPassDescError::AttachmentOutOfRange { .. } => {
+                "a subpass references an attachment that doesn't exist"
+            },
+            RenderPassDescError::InvalidReferenceLayout { .. } => {
+                "a subpass references an attachment with the `Undefined` or `Preinitialized` \
+                 layout"
+            },
+            RenderPassDescError::ColorAttachmentWrongFormat { .. } => {
+                "a color attachment of a subpass doesn't have a color format"
+            },
+            RenderPassDescError::DepthStencilAttachmentWrongFormat { .. } => {
+                "the depth-stencil attachment of a subpass doesn't have a depth and/or stencil \
+                 format"
+            },
+            RenderPassDescError::SamplesMismatch { .. } => {
+                "the color and depth-stencil attachments of a subpass don't all have the same \
+                 number of samples"
+            },
+            RenderPassDescError::AttachmentLayoutMismatch { .. } => {
+                "an attachment is used both as an input attachment and as a color or \
+                 depth-stencil attachment of a subpass, with different layouts"
+            },
+            RenderPassDescError::InputAttachmentCleared { .. } => {
+                "the first use of an attachment is as an input attachment, but the attachment is \
+                 loaded with `LoadOp::Clear`"
+            },
+            RenderPassDescError::ResolveAttachmentsCountMismatch { .. } => {
+                "a subpass has resolve attachments, but not as many as color attachments"
+            },
+            RenderPassDescError::ResolveAttachmentMultisampled { .. } => {
+                "a resolve attachment has more than one sample"
+            },
+            RenderPassDescError::ResolveSourceNotMultisampled { .. } => {
+                "the color attachment corresponding to a resolve attachment is unused or has only \
+                 one sample"
+            },
+            RenderPassDescError::ResolveFormatMismatch { .. } => {
+                "a resolve attachment doesn't have the same format as its color attachment"
+            },
+            RenderPassDescError::PreserveAttachmentAlreadyUsed { .. } => {
+                "a preserve attachment of a subpass is also used by another member of the subpass"
+            },
+            RenderPassDescError::DependencySubpassOutOfRange { .. } => {
+                "a dependency references a subpass that doesn't exist"
+            },
+            RenderPassDescError::DependencyWrongOrder { .. } => {
+                "the source subpass of a dependency is after its destination subpass"
+            },
+            RenderPassDescError::DependencyEmptyStages { .. } => {
+                "the source or destination stages of a dependency are empty"
+            },
+            RenderPassDescError::DependencyAccessNotSupportedByStages { .. } => {
+                "the access flags of a dependency aren't supported by its pipeline stages"
+            },
+            RenderPassDescError::SelfDependencyNotFramebufferSpace { .. } => {
+                "a dependency of a subpass on itself has stages that aren't framebuffer-space \
+                 stages"
+            },
+            RenderPassDescError::SelfDependencyNotByRegion { .. } => {
+                "a dependency of a subpass on itself isn't by region"
+            },
+        }
+    }
+}
+
+impl fmt::Display for RenderPassDescError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use format::ClearValue;
+    use format::Format;
+    use framebuffer::ATTACHMENT_UNUSED;
+    use framebuffer::LayoutAttachmentDescription;
+    use framebuffer::LayoutPassDependencyDescription;
+    use framebuffer::LayoutPassDescription;
+    use framebuffer::LoadOp;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::RenderPassDescBuilder;
+    use framebuffer::RenderPassDescClearValues;
+    use framebuffer::RenderPassDescError;
+    use framebuffer::StoreOp;
+    use image::ImageLayout;
+    use sync::AccessFlagBits;
+    use sync::PipelineStages;
+
+    fn attachment(format: Format, samples: u32) -> LayoutAttachmentDescription {
+        LayoutAttachmentDescription {
+            format: format,
+            samples: samples,
+            load: LoadOp::Clear,
+            store: StoreOp::Store,
+            stencil_load: LoadOp::DontCare,
+            stencil_store: StoreOp::DontCare,
+            initial_layout: ImageLayout::Undefined,
+            final_layout: ImageLayout::General,
+        }
+    }
+
+    fn subpass(color: Vec<usize>) -> LayoutPassDescription {
+        LayoutPassDescription {
+            color_attachments: color
+                .into_iter()
+                .map(|a| (a, ImageLayout::ColorAttachmentOptimal))
+                .collect(),
+            depth_stencil: None,
+            input_attachments: vec![],
+            resolve_attachments: vec![],
+            preserve_attachments: vec![],
+        }
+    }
+
+    #[test]
+    fn basic() {
+        let (_, device, _) = mock_dev_and_queue!();
+
+        let desc = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .attachment(attachment(Format::D16Unorm, 1))
+            .subpass(LayoutPassDescription {
+                         depth_stencil: Some((1, ImageLayout::DepthStencilAttachmentOptimal)),
+                         ..subpass(vec![0])
+                     })
+            .build()
+            .unwrap();
+
+        assert_eq!(desc.num_attachments(), 2);
+        assert_eq!(desc.num_subpasses(), 1);
+        assert_eq!(desc.num_samples(0), Some(1));
+        let _ = desc.build_render_pass(device).unwrap();
+    }
+
+    #[test]
+    fn unused_color_attachment() {
+        let (_, device, _) = mock_dev_and_queue!();
+
+        let desc = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(subpass(vec![0, ATTACHMENT_UNUSED, 1]))
+            .build()
+            .unwrap();
+
+        assert_eq!(desc.num_color_attachments(0), Some(3));
+        let _ = desc.build_render_pass(device).unwrap();
+    }
+
+    #[test]
+    fn no_subpass() {
+        match RenderPassDescBuilder::new().build() {
+            Err(RenderPassDescError::NoSubpass) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn attachment_out_of_range() {
+        let result = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(subpass(vec![1]))
+            .build();
+
+        match result {
+            Err(RenderPassDescError::AttachmentOutOfRange {
+                    subpass: 0,
+                    attachment: 1,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn color_attachment_wrong_format() {
+        let result = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::D16Unorm, 1))
+            .subpass(subpass(vec![0]))
+            .build();
+
+        match result {
+            Err(RenderPassDescError::ColorAttachmentWrongFormat { .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn samples_mismatch() {
+        let result = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 4))
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(subpass(vec![0, 1]))
+            .build();
+
+        match result {
+            Err(RenderPassDescError::SamplesMismatch { subpass: 0 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let ok = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 4))
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(LayoutPassDescription {
+                         resolve_attachments: vec![(1, ImageLayout::ColorAttachmentOptimal)],
+                         ..subpass(vec![0])
+                     })
+            .build();
+        assert!(ok.is_ok());
+
+        let not_multisampled = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(LayoutPassDescription {
+                         resolve_attachments: vec![(1, ImageLayout::ColorAttachmentOptimal)],
+                         ..subpass(vec![0])
+                     })
+            .build();
+        match not_multisampled {
+            Err(RenderPassDescError::ResolveSourceNotMultisampled { .. }) => (),
+            _ => panic!(),
+        }
+
+        let format_mismatch = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 4))
+            .attachment(attachment(Format::R8Unorm, 1))
+            .subpass(LayoutPassDescription {
+                         resolve_attachments: vec![(1, ImageLayout::ColorAttachmentOptimal)],
+                         ..subpass(vec![0])
+                     })
+            .build();
+        match format_mismatch {
+            Err(RenderPassDescError::ResolveFormatMismatch { .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn input_attachment_cleared() {
+        let result = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(LayoutPassDescription {
+                         input_attachments: vec![(0, ImageLayout::ShaderReadOnlyOptimal)],
+                         ..subpass(vec![])
+                     })
+            .build();
+
+        match result {
+            Err(RenderPassDescError::InputAttachmentCleared {
+                    subpass: 0,
+                    attachment: 0,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn input_after_color() {
+        let result = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(subpass(vec![0]))
+            .subpass(LayoutPassDescription {
+                         input_attachments: vec![(0, ImageLayout::ShaderReadOnlyOptimal)],
+                         preserve_attachments: vec![],
+                         ..subpass(vec![1])
+                     })
+            .build();
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn infer_dependencies() {
+        let (_, device, _) = mock_dev_and_queue!();
+
+        let desc = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(subpass(vec![0]))
+            .subpass(LayoutPassDescription {
+                         input_attachments: vec![(0, ImageLayout::ShaderReadOnlyOptimal)],
+                         ..subpass(vec![1])
+                     })
+            .infer_dependencies()
+            .build()
+            .unwrap();
+
+        assert_eq!(desc.num_dependencies(), 1);
+        let dependency = desc.dependency_desc(0).unwrap();
+        assert_eq!(dependency.source_subpass, 0);
+        assert_eq!(dependency.destination_subpass, 1);
+        assert!(dependency.source_access.color_attachment_write);
+        assert!(dependency.destination_access.input_attachment_read);
+        let _ = desc.build_render_pass(device).unwrap();
+    }
+
+    #[test]
+    fn preserve_already_used() {
+        let result = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(LayoutPassDescription {
+                         preserve_attachments: vec![0],
+                         ..subpass(vec![0])
+                     })
+            .build();
+
+        match result {
+            Err(RenderPassDescError::PreserveAttachmentAlreadyUsed { .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn dependency_wrong_order() {
+        let result = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(subpass(vec![0]))
+            .subpass(subpass(vec![0]))
+            .dependency(LayoutPassDependencyDescription {
+                            source_subpass: 1,
+                            destination_subpass: 0,
+                            source_stages: PipelineStages {
+                                all_graphics: true,
+                                ..PipelineStages::none()
+                            },
+                            destination_stages: PipelineStages {
+                                all_graphics: true,
+                                ..PipelineStages::none()
+                            },
+                            source_access: AccessFlagBits::all(),
+                            destination_access: AccessFlagBits::all(),
+                            by_region: true,
+                        })
+            .build();
+
+        match result {
+            Err(RenderPassDescError::DependencyWrongOrder { dependency: 0 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn clear_values() {
+        let desc = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(subpass(vec![0]))
+            .build()
+            .unwrap();
+
+        let values = desc.convert_clear_values(vec![ClearValue::Float([0.0; 4])]);
+        assert_eq!(values.count(), 1);
+
+        assert_should_panic!("Clear value doesn't match its attachment", {
+            desc.convert_clear_values(vec![ClearValue::Uint([0; 4])]);
+        });
+    }
+
+    #[test]
+    fn stencil_clear_values() {
+        let (_, device, _) = mock_dev_and_queue!();
+
+        // Only the stencil aspect is cleared.
+        let desc = RenderPassDescBuilder::new()
+            .attachment(LayoutAttachmentDescription {
+                            load: LoadOp::DontCare,
+                            stencil_load: LoadOp::Clear,
+                            ..attachment(Format::S8Uint, 1)
+                        })
+            .subpass(LayoutPassDescription {
+                         depth_stencil: Some((0, ImageLayout::DepthStencilAttachmentOptimal)),
+                         ..subpass(vec![])
+                     })
+            .build()
+            .unwrap();
+
+        assert_should_panic!("Clear value doesn't match its attachment", {
+            desc.convert_clear_values(vec![ClearValue::None]);
+        });
+
+        let render_pass = desc.build_render_pass(device).unwrap();
+        let values = render_pass.convert_clear_values(vec![ClearValue::Stencil(0)]);
+        assert_eq!(values.count(), 1);
+    }
+
+    #[test]
+    fn self_dependency() {
+        let builder = RenderPassDescBuilder::new()
+            .attachment(attachment(Format::R8G8B8A8Unorm, 1))
+            .subpass(subpass(vec![0]));
+        let dependency = LayoutPassDependencyDescription {
+            source_subpass: 0,
+            destination_subpass: 0,
+            source_stages: PipelineStages {
+                color_attachment_output: true,
+                ..PipelineStages::none()
+            },
+            destination_stages: PipelineStages {
+                fragment_shader: true,
+                ..PipelineStages::none()
+            },
+            source_access: AccessFlagBits {
+                color_attachment_write: true,
+                ..AccessFlagBits::none()
+            },
+            destination_access: AccessFlagBits {
+                input_attachment_read: true,
+                ..AccessFlagBits::none()
+            },
+            by_region: true,
+        };
+
+        assert!(builder.clone().dependency(dependency.clone()).build().is_ok());
+
+        let result = builder
+            .clone()
+            .dependency(LayoutPassDependencyDescription {
+                            destination_stages: PipelineStages {
+                                vertex_shader: true,
+                                ..PipelineStages::none()
+                            },
+                            destination_access: AccessFlagBits {
+                                shader_read: true,
+                                ..AccessFlagBits::none()
+                            },
+                            ..dependency.clone()
+                        })
+            .build();
+        match result {
+            Err(RenderPassDescError::SelfDependencyNotFramebufferSpace { dependency: 0 }) => (),
+            _ => panic!(),
+        }
+
+        let result = builder
+            .dependency(LayoutPassDependencyDescription {
+                            by_region: false,
+                            ..dependency
+                        })
+            .build();
+        match result {
+            Err(RenderPassDescError::SelfDependencyNotByRegion { dependency: 0 }) => (),
+            _ => panic!(),
+        }
+    }
+}
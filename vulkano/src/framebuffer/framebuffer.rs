@@ -628,8 +628,6 @@ mod tests {
         }
     }
 
-    // TODO: check samples mismatch
-
     #[test]
     fn attachment_dims_larger_than_specified_valid() {
         let (device, _) = gfx_dev_and_queue!();
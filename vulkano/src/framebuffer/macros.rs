@@ -75,6 +75,7 @@ macro_rules! ordered_passes_renderpass {
             use $crate::framebuffer::LayoutAttachmentDescription;
             use $crate::framebuffer::LayoutPassDescription;
             use $crate::framebuffer::LayoutPassDependencyDescription;
+            use $crate::framebuffer::infer_subpass_dependencies;
             use $crate::image::ImageLayout;
             use $crate::sync::AccessFlagBits;
             use $crate::sync::PipelineStages;
@@ -83,6 +84,8 @@ macro_rules! ordered_passes_renderpass {
                 $(
                     pub $atch_name: (Format, u32),
                 )*
+                // Computed once by `dependencies()` when the description is created.
+                pub dependencies: Vec<LayoutPassDependencyDescription>,
             }
 
             #[allow(unsafe_code)]
@@ -109,12 +112,12 @@ macro_rules! ordered_passes_renderpass {
 
                 #[inline]
                 fn num_dependencies(&self) -> usize {
-                    num_dependencies()
+                    self.dependencies.len()
                 }
 
                 #[inline]
                 fn dependency_desc(&self, id: usize) -> Option<LayoutPassDependencyDescription> {
-                    dependency(id)
+                    self.dependencies.get(id).cloned()
                 }
             }
 
@@ -232,28 +235,61 @@ macro_rules! ordered_passes_renderpass {
                 None
             }
 
-            #[inline]
-            fn num_dependencies() -> usize {
-                num_subpasses().saturating_sub(1)
-            }
-
-            #[inline]
-            fn dependency(id: usize) -> Option<LayoutPassDependencyDescription> {
-                let num_passes = num_subpasses();
+            /// Returns the dependencies between the subpasses.
+            ///
+            /// The dependencies are inferred from the way the subpasses use the attachments. In
+            /// addition, each subpass depends on the previous one if no dependency was inferred
+            /// between them, so that the subpasses still execute in order if they synchronize
+            /// through resources that aren't attachments.
+            pub fn dependencies() -> Vec<LayoutPassDependencyDescription> {
+                let subpasses = (0 .. num_subpasses()).map(|id| subpass(id).unwrap());
+                let mut dependencies = infer_subpass_dependencies(subpasses);
+
+                for dst in 1 .. num_subpasses() {
+                    let src = dst - 1;
+                    if dependencies.iter().any(|d| d.source_subpass == src &&
+                                                    d.destination_subpass == dst)
+                    {
+                        continue;
+                    }
 
-                if id + 1 >= num_passes {
-                    return None;
+                    dependencies.push(LayoutPassDependencyDescription {
+                        source_subpass: src,
+                        destination_subpass: dst,
+                        source_stages: PipelineStages {
+                            all_graphics: true,
+                            .. PipelineStages::none()
+                        },
+                        destination_stages: PipelineStages {
+                            all_graphics: true,
+                            .. PipelineStages::none()
+                        },
+                        source_access: AccessFlagBits {
+                            shader_write: true,
+                            color_attachment_write: true,
+                            depth_stencil_attachment_write: true,
+                            .. AccessFlagBits::none()
+                        },
+                        destination_access: AccessFlagBits {
+                            indirect_command_read: true,
+                            index_read: true,
+                            vertex_attribute_read: true,
+                            uniform_read: true,
+                            input_attachment_read: true,
+                            shader_read: true,
+                            shader_write: true,
+                            color_attachment_read: true,
+                            color_attachment_write: true,
+                            depth_stencil_attachment_read: true,
+                            depth_stencil_attachment_write: true,
+                            .. AccessFlagBits::none()
+                        },
+                        by_region: false,
+                    });
                 }
 
-                Some(LayoutPassDependencyDescription {
-                    source_subpass: id,
-                    destination_subpass: id + 1,
-                    source_stages: PipelineStages { all_graphics: true, .. PipelineStages::none() },         // TODO: correct values
-                    destination_stages: PipelineStages { all_graphics: true, .. PipelineStages::none() },         // TODO: correct values
-                    source_access: AccessFlagBits::all(),         // TODO: correct values
-                    destination_access: AccessFlagBits::all(),         // TODO: correct values
-                    by_region: true,            // TODO: correct values
-                })
+                dependencies.sort_by_key(|d| (d.source_subpass, d.destination_subpass));
+                dependencies
             }
 
             /// Returns the initial and final layout of an attachment, given its num.
@@ -342,6 +378,7 @@ macro_rules! ordered_passes_renderpass {
             $(
                 $atch_name: ($format, $samples),
             )*
+            dependencies: scope::dependencies(),
         }.build_render_pass($device)
     });
 }
@@ -349,6 +386,7 @@ macro_rules! ordered_passes_renderpass {
 #[cfg(test)]
 mod tests {
     use format::Format;
+    use framebuffer::RenderPassDesc;
 
     #[test]
     fn single_pass_resolve() {
@@ -375,4 +413,70 @@ mod tests {
             }
         ).unwrap();
     }
+
+    #[test]
+    fn ordered_passes_dependencies() {
+        let (device, _) = gfx_dev_and_queue!();
+        let render_pass = ordered_passes_renderpass!(device.clone(),
+            attachments: {
+                a: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                },
+                b: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                },
+                c: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 1,
+                }
+            },
+            passes: [
+                { color: [a], depth_stencil: {}, input: [] },
+                { color: [c], depth_stencil: {}, input: [] },
+                { color: [b], depth_stencil: {}, input: [a] }
+            ]
+        ).unwrap();
+
+        // The subpasses that don't depend on each other through an attachment are still
+        // chained.
+        let pairs = (0 .. render_pass.num_dependencies())
+            .map(|n| {
+                     let dependency = render_pass.dependency_desc(n).unwrap();
+                     (dependency.source_subpass, dependency.destination_subpass)
+                 })
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, vec![(0, 1), (0, 2), (1, 2)]);
+
+        let dependency = render_pass.dependency_desc(1).unwrap();
+        assert!(dependency.source_stages.color_attachment_output);
+        assert!(dependency.destination_stages.fragment_shader);
+        assert!(dependency.destination_access.input_attachment_read);
+        assert!(dependency.by_region);
+
+        let dependency = render_pass.dependency_desc(0).unwrap();
+        assert!(dependency.source_stages.all_graphics);
+        assert!(dependency.destination_stages.all_graphics);
+        assert!(!dependency.by_region);
+    }
+
+    #[test]
+    fn single_pass_no_dependency() {
+        let (device, _) = gfx_dev_and_queue!();
+        let render_pass = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap();
+
+        assert_eq!(render_pass.num_dependencies(), 0);
+    }
 }
@@ -93,6 +93,8 @@
 pub use self::attachments_list::AttachmentsList;
 pub use self::compat_atch::IncompatibleRenderPassAttachmentError;
 pub use self::compat_atch::ensure_image_view_compatible;
+pub use self::dependencies::infer_subpass_dependencies;
+pub use self::desc::ATTACHMENT_UNUSED;
 pub use self::desc::LayoutAttachmentDescription;
 pub use self::desc::LayoutPassDependencyDescription;
 pub use self::desc::LayoutPassDescription;
@@ -102,6 +104,9 @@ pub use self::desc::RenderPassDescAttachments;
 pub use self::desc::RenderPassDescDependencies;
 pub use self::desc::RenderPassDescSubpasses;
 pub use self::desc::StoreOp;
+pub use self::desc_builder::RenderPassDescBuilder;
+pub use self::desc_builder::RenderPassDescError;
+pub use self::desc_builder::RuntimeRenderPassDesc;
 pub use self::empty::EmptySinglePassRenderPassDesc;
 pub use self::framebuffer::Framebuffer;
 pub use self::framebuffer::FramebufferBuilder;
@@ -123,7 +128,9 @@ use vk;
 mod macros;
 mod attachments_list;
 mod compat_atch;
+mod dependencies;
 mod desc;
+mod desc_builder;
 mod empty;
 mod framebuffer;
 mod sys;
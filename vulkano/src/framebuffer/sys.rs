@@ -19,6 +19,7 @@ use std::sync::Mutex;
 use device::Device;
 use device::DeviceOwned;
 use format::ClearValue;
+use framebuffer::ATTACHMENT_UNUSED;
 use framebuffer::EmptySinglePassRenderPassDesc;
 use framebuffer::LayoutAttachmentDescription;
 use framebuffer::LayoutPassDependencyDescription;
@@ -27,6 +28,7 @@ use framebuffer::LoadOp;
 use framebuffer::RenderPassAbstract;
 use framebuffer::RenderPassDesc;
 use framebuffer::RenderPassDescClearValues;
+use image::ImageLayout;
 
 use Error;
 use OomError;
@@ -134,18 +136,18 @@ impl<D> RenderPass<D>
                                   pass.resolve_attachments.len() == pass.color_attachments.len());
                 debug_assert!(pass.resolve_attachments
                                   .iter()
+                                  .filter(|a| a.0 != ATTACHMENT_UNUSED)
                                   .all(|a| attachments[a.0].samples == 1));
-                debug_assert!(pass.resolve_attachments.is_empty() ||
-                                  pass.color_attachments
-                                      .iter()
-                                      .all(|a| attachments[a.0].samples > 1));
-                debug_assert!(pass.resolve_attachments.is_empty() ||
-                                  pass.resolve_attachments
-                                      .iter()
-                                      .zip(pass.color_attachments.iter())
-                                      .all(|(r, c)| {
-                                               attachments[r.0].format == attachments[c.0].format
-                                           }));
+                debug_assert!(pass.resolve_attachments
+                                  .iter()
+                                  .zip(pass.color_attachments.iter())
+                                  .filter(|&(r, _)| r.0 != ATTACHMENT_UNUSED)
+                                  .all(|(r, c)| {
+                                           c.0 != ATTACHMENT_UNUSED &&
+                                               attachments[c.0].samples > 1 &&
+                                               attachments[r.0].format ==
+                                                   attachments[c.0].format
+                                       }));
                 debug_assert!(pass.color_attachments
                                   .iter()
                                   .cloned()
@@ -163,6 +165,7 @@ impl<D> RenderPass<D>
                         .iter()
                         .cloned()
                         .chain(pass.depth_stencil.clone().into_iter())
+                        .filter(|&(atch, _)| atch != ATTACHMENT_UNUSED)
                         .all(|(atch, layout)| if let Some(r) =
                             pass.input_attachments.iter().find(|r| r.0 == atch)
                         {
@@ -172,31 +175,15 @@ impl<D> RenderPass<D>
                         })
                 );
 
-                let resolve = pass.resolve_attachments
-                    .into_iter()
-                    .map(|(offset, img_la)| {
-                             debug_assert!(offset < attachments.len());
-                             vk::AttachmentReference {
-                                 attachment: offset as u32,
-                                 layout: img_la as u32,
-                             }
-                         });
-
-                let color = pass.color_attachments.into_iter().map(|(offset, img_la)| {
-                    debug_assert!(offset < attachments.len());
-                    vk::AttachmentReference {
-                        attachment: offset as u32,
-                        layout: img_la as u32,
-                    }
-                });
+                debug_assert!(pass.color_attachments
+                                  .iter()
+                                  .chain(pass.input_attachments.iter())
+                                  .chain(pass.resolve_attachments.iter())
+                                  .all(|a| a.0 == ATTACHMENT_UNUSED || a.0 < attachments.len()));
 
-                let input = pass.input_attachments.into_iter().map(|(offset, img_la)| {
-                    debug_assert!(offset < attachments.len());
-                    vk::AttachmentReference {
-                        attachment: offset as u32,
-                        layout: img_la as u32,
-                    }
-                });
+                let resolve = pass.resolve_attachments.into_iter().map(attachment_reference);
+                let color = pass.color_attachments.into_iter().map(attachment_reference);
+                let input = pass.input_attachments.into_iter().map(attachment_reference);
 
                 let depthstencil = if let Some((offset, img_la)) = pass.depth_stencil {
                     Some(vk::AttachmentReference {
@@ -239,6 +226,21 @@ impl<D> RenderPass<D>
                     return Err(RenderPassCreationError::ColorAttachmentsLimitExceeded);
                 }
 
+                // All the color and depth-stencil attachments of a subpass must have the same
+                // number of samples.
+                {
+                    let mut samples = pass.color_attachments
+                        .iter()
+                        .chain(pass.depth_stencil.iter())
+                        .filter(|&&(a, _)| a != ATTACHMENT_UNUSED)
+                        .map(|&(a, _)| attachments[a].samples);
+                    if let Some(first) = samples.next() {
+                        if samples.any(|s| s != first) {
+                            return Err(RenderPassCreationError::SamplesMismatch);
+                        }
+                    }
+                }
+
                 let color_attachments = attachment_references.as_ptr().offset(ref_index as isize);
                 ref_index += pass.color_attachments.len();
                 let input_attachments = attachment_references.as_ptr().offset(ref_index as isize);
@@ -360,6 +362,19 @@ impl<D> RenderPass<D>
     }
 }
 
+// Turns an attachment index and layout of a `LayoutPassDescription` into a Vulkan reference.
+#[inline]
+fn attachment_reference((offset, layout): (usize, ImageLayout)) -> vk::AttachmentReference {
+    vk::AttachmentReference {
+        attachment: if offset == ATTACHMENT_UNUSED {
+            vk::ATTACHMENT_UNUSED
+        } else {
+            offset as u32
+        },
+        layout: layout as u32,
+    }
+}
+
 impl RenderPass<EmptySinglePassRenderPassDesc> {
     /// Builds a render pass with one subpass and no attachment.
     ///
@@ -511,6 +526,9 @@ pub enum RenderPassCreationError {
     OomError(OomError),
     /// The maximum number of color attachments has been exceeded.
     ColorAttachmentsLimitExceeded,
+    /// The color and depth-stencil attachments of a subpass don't all have the same number of
+    /// samples.
+    SamplesMismatch,
 }
 
 impl error::Error for RenderPassCreationError {
@@ -521,6 +539,10 @@ impl error::Error for RenderPassCreationError {
             RenderPassCreationError::ColorAttachmentsLimitExceeded => {
                 "the maximum number of color attachments has been exceeded"
             },
+            RenderPassCreationError::SamplesMismatch => {
+                "the color and depth-stencil attachments of a subpass don't all have the same \
+                 number of samples"
+            },
         }
     }
 
@@ -628,4 +650,35 @@ mod tests {
         assert_ne!(granularity[0], 0);
         assert_ne!(granularity[1], 0);
     }
+
+    #[test]
+    fn attachment_samples_mismatch() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let render_pass = single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::R8G8B8A8Unorm,
+                    samples: 4,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: Format::D16Unorm,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {depth}
+            }
+        );
+
+        match render_pass {
+            Err(RenderPassCreationError::SamplesMismatch) => (),
+            _ => panic!(),
+        }
+    }
 }
@@ -9,9 +9,11 @@
 
 use device::DeviceOwned;
 use format::ClearValue;
+use framebuffer::ATTACHMENT_UNUSED;
 use framebuffer::FramebufferSys;
 use framebuffer::RenderPassDesc;
 use framebuffer::RenderPassSys;
+use image::ImageLayout;
 use image::ImageViewAccess;
 use pipeline::shader::ShaderInterfaceDef;
 
@@ -180,6 +182,8 @@ unsafe impl<A, B: ?Sized> RenderPassSubpassInterface<B> for A
         for element in other.elements() {
             for location in element.location.clone() {
                 let attachment_id = match pass_descr.color_attachments.get(location as usize) {
+                    // The outputs written to an unused attachment are discarded.
+                    Some(&(ATTACHMENT_UNUSED, _)) => continue,
                     Some(a) => a.0,
                     None => return false,
                 };
@@ -237,9 +241,80 @@ unsafe impl<A: ?Sized, B: ?Sized> RenderPassCompatible<B> for A
             }
         }
 
-        return true;
+        if self.num_subpasses() != other.num_subpasses() {
+            return false;
+        }
+
+        // Two attachment references are compatible if they refer to compatible attachments. The
+        // layouts of the references don't matter.
+        let refs_compatible = |mine: &[(usize, ImageLayout)], others: &[(usize, ImageLayout)]| {
+            mine.len() == others.len() &&
+                mine.iter().zip(others.iter()).all(|(&(my_id, _), &(other_id, _))| {
+                    if my_id == ATTACHMENT_UNUSED || other_id == ATTACHMENT_UNUSED {
+                        return my_id == other_id;
+                    }
+
+                    let my_atch = self.attachment_desc(my_id).unwrap();
+                    let other_atch = other.attachment_desc(other_id).unwrap();
+                    my_atch.is_compatible_with(&other_atch)
+                })
+        };
+
+        // As a special case, the resolve attachments aren't compared if both render passes have a
+        // single subpass.
+        let compare_resolve = self.num_subpasses() != 1;
+
+        for subpass_num in 0 .. self.num_subpasses() {
+            let my_subpass = self.subpass_desc(subpass_num).unwrap();
+            let other_subpass = other.subpass_desc(subpass_num).unwrap();
+
+            if !refs_compatible(&my_subpass.color_attachments, &other_subpass.color_attachments) ||
+                !refs_compatible(&my_subpass.input_attachments, &other_subpass.input_attachments)
+            {
+                return false;
+            }
+
+            if compare_resolve &&
+                !refs_compatible(&my_subpass.resolve_attachments,
+                                 &other_subpass.resolve_attachments)
+            {
+                return false;
+            }
+
+            match (my_subpass.depth_stencil, other_subpass.depth_stencil) {
+                (None, None) => (),
+                (Some(mine), Some(others)) => {
+                    if !refs_compatible(&[mine], &[others]) {
+                        return false;
+                    }
+                },
+                _ => return false,
+            }
+        }
+
+        // The dependencies must be identical and declared in the same order.
+        if self.num_dependencies() != other.num_dependencies() {
+            return false;
+        }
+
+        for dep_num in 0 .. self.num_dependencies() {
+            let mine = self.dependency_desc(dep_num).unwrap();
+            let others = other.dependency_desc(dep_num).unwrap();
+
+            if mine.source_subpass != others.source_subpass ||
+                mine.destination_subpass != others.destination_subpass ||
+                mine.source_stages != others.source_stages ||
+                mine.destination_stages != others.destination_stages ||
+                mine.source_access.into_vulkan_bits() != others.source_access.into_vulkan_bits() ||
+                mine.destination_access.into_vulkan_bits() !=
+                    others.destination_access.into_vulkan_bits() ||
+                mine.by_region != others.by_region
+            {
+                return false;
+            }
+        }
 
-        // FIXME: finish
+        true
     }
 }
 
@@ -347,3 +422,106 @@ impl<L> Into<(L, u32)> for Subpass<L> {
         (self.render_pass, self.subpass_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use format::Format;
+    use framebuffer::RenderPassCompatible;
+
+    #[test]
+    fn compatible_ignores_load_store() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp1 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap();
+
+        let rp2 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: DontCare, store: DontCare, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap();
+
+        assert!(RenderPassCompatible::is_compatible_with(&rp1, &rp2));
+    }
+
+    #[test]
+    fn incompatible_subpass_attachments() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp1 = ordered_passes_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, },
+                b: { load: Clear, store: Store, format: Format::D16Unorm, samples: 1, }
+            },
+            passes: [
+                { color: [a], depth_stencil: {b}, input: [] }
+            ]
+        ).unwrap();
+
+        let rp2 = ordered_passes_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Load, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, },
+                b: { load: Clear, store: Store, format: Format::D16Unorm, samples: 1, }
+            },
+            passes: [
+                { color: [], depth_stencil: {b}, input: [a] }
+            ]
+        ).unwrap();
+
+        assert!(!RenderPassCompatible::is_compatible_with(&rp1, &rp2));
+        assert!(!RenderPassCompatible::is_compatible_with(&rp2, &rp1));
+    }
+
+    #[test]
+    fn incompatible_num_subpasses() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp1 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap();
+
+        let rp2 = ordered_passes_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            passes: [
+                { color: [a], depth_stencil: {}, input: [] },
+                { color: [a], depth_stencil: {}, input: [] }
+            ]
+        ).unwrap();
+
+        assert!(!RenderPassCompatible::is_compatible_with(&rp1, &rp2));
+    }
+
+    #[test]
+    fn single_subpass_ignores_resolve() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let rp1 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: DontCare, format: Format::R8G8B8A8Unorm, samples: 4, },
+                b: { load: DontCare, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {}, resolve: [b], }
+        ).unwrap();
+
+        let rp2 = single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: DontCare, format: Format::R8G8B8A8Unorm, samples: 4, },
+                b: { load: DontCare, store: Store, format: Format::R8G8B8A8Unorm, samples: 1, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap();
+
+        assert!(RenderPassCompatible::is_compatible_with(&rp1, &rp2));
+        assert!(RenderPassCompatible::is_compatible_with(&rp2, &rp1));
+    }
+}
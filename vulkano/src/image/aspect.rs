@@ -0,0 +1,113 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::ops::BitOr;
+
+use image::ImageAccess;
+
+/// Describes which aspects of an image a command operates on.
+///
+/// Color images only have the color aspect. Depth-stencil images have both the depth and the
+/// stencil aspects, and commands such as copies can operate on one of them or on both at once.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ImageAspects {
+    /// The color components of the image.
+    pub color: bool,
+    /// The depth component of the image.
+    pub depth: bool,
+    /// The stencil component of the image.
+    pub stencil: bool,
+}
+
+impl ImageAspects {
+    /// Builds an `ImageAspects` with all values set to false.
+    #[inline]
+    pub fn none() -> ImageAspects {
+        ImageAspects {
+            color: false,
+            depth: false,
+            stencil: false,
+        }
+    }
+
+    /// Builds an `ImageAspects` with only `color` set to true.
+    #[inline]
+    pub fn color() -> ImageAspects {
+        ImageAspects {
+            color: true,
+            ..ImageAspects::none()
+        }
+    }
+
+    /// Builds an `ImageAspects` with only `depth` set to true.
+    #[inline]
+    pub fn depth() -> ImageAspects {
+        ImageAspects {
+            depth: true,
+            ..ImageAspects::none()
+        }
+    }
+
+    /// Builds an `ImageAspects` with only `stencil` set to true.
+    #[inline]
+    pub fn stencil() -> ImageAspects {
+        ImageAspects {
+            stencil: true,
+            ..ImageAspects::none()
+        }
+    }
+
+    /// Builds an `ImageAspects` with `depth` and `stencil` set to true.
+    #[inline]
+    pub fn depth_stencil() -> ImageAspects {
+        ImageAspects {
+            depth: true,
+            stencil: true,
+            ..ImageAspects::none()
+        }
+    }
+
+    /// Returns all the aspects that are present in the format of an image.
+    #[inline]
+    pub fn from_image<I>(image: &I) -> ImageAspects
+        where I: ?Sized + ImageAccess
+    {
+        ImageAspects {
+            color: image.has_color(),
+            depth: image.has_depth(),
+            stencil: image.has_stencil(),
+        }
+    }
+
+    /// Returns true if all the aspects of `self` are also in `other`.
+    #[inline]
+    pub fn is_subset_of(&self, other: &ImageAspects) -> bool {
+        (!self.color || other.color) && (!self.depth || other.depth) &&
+            (!self.stencil || other.stencil)
+    }
+
+    /// Returns true if no aspect is set.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        !self.color && !self.depth && !self.stencil
+    }
+}
+
+impl BitOr for ImageAspects {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        ImageAspects {
+            color: self.color || rhs.color,
+            depth: self.depth || rhs.depth,
+            stencil: self.stencil || rhs.stencil,
+        }
+    }
+}
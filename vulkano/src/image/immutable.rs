@@ -8,6 +8,8 @@
 // according to those terms.
 
 use smallvec::SmallVec;
+use std::cmp;
+use std::ops::Range;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
@@ -26,6 +28,7 @@ use format::AcceptsPixels;
 use format::Format;
 use format::FormatDesc;
 use image::Dimensions;
+use image::ImageDimensions;
 use image::ImageInner;
 use image::ImageLayout;
 use image::ImageUsage;
@@ -45,6 +48,7 @@ use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
 use memory::pool::PotentialDedicatedAllocation;
 use memory::pool::StdMemoryPoolAlloc;
+use sampler::Filter;
 use sync::AccessError;
 use sync::NowFuture;
 use sync::Sharing;
@@ -67,6 +71,8 @@ pub struct ImmutableImage<F, A = PotentialDedicatedAllocation<StdMemoryPoolAlloc
 pub struct ImmutableImageInitialization<F, A = PotentialDedicatedAllocation<StdMemoryPoolAlloc>> {
     image: Arc<ImmutableImage<F, A>>,
     used: AtomicBool,
+    // Mipmap levels that this object gives access to.
+    mip_levels_access: Range<u32>,
 }
 
 impl<F> ImmutableImage<F> {
@@ -181,6 +187,7 @@ impl<F> ImmutableImage<F> {
         let init = ImmutableImageInitialization {
             image: image.clone(),
             used: AtomicBool::new(false),
+            mip_levels_access: 0 .. image.mipmap_levels(),
         };
 
         Ok((image, init))
@@ -188,7 +195,8 @@ impl<F> ImmutableImage<F> {
 
     /// Construct an ImmutableImage from the contents of `iter`.
     ///
-    /// TODO: Support mipmaps
+    /// Only the first mipmap level is allocated. See `from_iter_with_mipmaps` to also generate
+    /// the other levels.
     #[inline]
     pub fn from_iter<P, I>(iter: I, dimensions: Dimensions, format: F, queue: Arc<Queue>)
                            -> Result<(Arc<Self>,
@@ -198,16 +206,42 @@ impl<F> ImmutableImage<F> {
               F: FormatDesc + AcceptsPixels<P> + 'static + Send + Sync,
               I: ExactSizeIterator<Item = P>,
               Format: AcceptsPixels<P>
+    {
+        ImmutableImage::from_iter_with_mipmaps(iter,
+                                               dimensions,
+                                               MipmapsCount::One,
+                                               format,
+                                               queue)
+    }
+
+    /// Construct an ImmutableImage from the contents of `iter`, and generates the other mipmap
+    /// levels from the first one.
+    ///
+    /// See `from_buffer_with_mipmaps` for more information.
+    #[inline]
+    pub fn from_iter_with_mipmaps<P, I, M>(iter: I, dimensions: Dimensions, mipmaps: M,
+                                           format: F, queue: Arc<Queue>)
+                                           -> Result<(Arc<Self>,
+                                                      CommandBufferExecFuture<NowFuture,
+                                                                              AutoCommandBuffer>),
+                                                     ImageCreationError>
+        where P: Send + Sync + Clone + 'static,
+              F: FormatDesc + AcceptsPixels<P> + 'static + Send + Sync,
+              I: ExactSizeIterator<Item = P>,
+              M: Into<MipmapsCount>,
+              Format: AcceptsPixels<P>
     {
         let source = CpuAccessibleBuffer::from_iter(queue.device().clone(),
                                                     BufferUsage::transfer_source(),
                                                     iter)?;
-        ImmutableImage::from_buffer(source, dimensions, format, queue)
+        ImmutableImage::from_buffer_with_mipmaps(source, dimensions, mipmaps, format, queue)
     }
 
     /// Construct an ImmutableImage containing a copy of the data in `source`.
     ///
-    /// TODO: Support mipmaps
+    /// Only the first mipmap level is allocated. See `from_buffer_with_mipmaps` to also generate
+    /// the other levels.
+    #[inline]
     pub fn from_buffer<B, P>(source: B, dimensions: Dimensions, format: F, queue: Arc<Queue>)
                              -> Result<(Arc<Self>,
                                         CommandBufferExecFuture<NowFuture, AutoCommandBuffer>),
@@ -217,43 +251,126 @@ impl<F> ImmutableImage<F> {
               F: FormatDesc + AcceptsPixels<P> + 'static + Send + Sync,
               Format: AcceptsPixels<P>
     {
+        ImmutableImage::from_buffer_with_mipmaps(source,
+                                                 dimensions,
+                                                 MipmapsCount::One,
+                                                 format,
+                                                 queue)
+    }
+
+    /// Construct an ImmutableImage containing a copy of the data in `source`, and generates the
+    /// other mipmap levels from the first one.
+    ///
+    /// `source` contains the data of the first mipmap level. Each of the next levels is then
+    /// filled by blitting the previous level with a linear filter. All of this is done on the
+    /// GPU by the returned future.
+    ///
+    /// Returns `ImageCreationError::MipmapsGenerationNotSupported` if more than one mipmap level
+    /// is requested, and the format doesn't support blits with linear filtering.
+    pub fn from_buffer_with_mipmaps<B, P, M>(source: B, dimensions: Dimensions, mipmaps: M,
+                                             format: F, queue: Arc<Queue>)
+                                             -> Result<(Arc<Self>,
+                                                        CommandBufferExecFuture<NowFuture,
+                                                                                AutoCommandBuffer>),
+                                                       ImageCreationError>
+        where B: BufferAccess + TypedBufferAccess<Content = [P]> + 'static + Clone + Send + Sync,
+              P: Send + Sync + Clone + 'static,
+              F: FormatDesc + AcceptsPixels<P> + 'static + Send + Sync,
+              M: Into<MipmapsCount>,
+              Format: AcceptsPixels<P>
+    {
+        let mipmaps = mipmaps.into();
         let usage = ImageUsage {
+            transfer_source: match mipmaps {
+                MipmapsCount::One => false,
+                _ => true,
+            },
             transfer_destination: true,
             sampled: true,
             ..ImageUsage::none()
         };
         let layout = ImageLayout::ShaderReadOnlyOptimal;
 
-        let (buffer, init) =
-            ImmutableImage::uninitialized(source.device().clone(),
-                                          dimensions,
-                                          format,
-                                          MipmapsCount::One,
-                                          usage,
-                                          layout,
-                                          source.device().active_queue_families())?;
+        let (image, _) = ImmutableImage::uninitialized(source.device().clone(),
+                                                       dimensions,
+                                                       format,
+                                                       mipmaps,
+                                                       usage,
+                                                       layout,
+                                                       source.device().active_queue_families())?;
+
+        let num_levels = image.mipmap_levels();
+        if num_levels >= 2 {
+            let inner = &image.image;
+            if !inner.supports_blit_source() || !inner.supports_blit_destination() ||
+                !inner.supports_linear_filtering()
+            {
+                return Err(ImageCreationError::MipmapsGenerationNotSupported);
+            }
+        }
 
-        let cb = AutoCommandBufferBuilder::new(source.device().clone(), queue.family())?
+        // Each mipmap level is written by a different command, and the levels are in different
+        // layouts at the same time. We therefore give access to each level separately.
+        let levels = (0 .. num_levels)
+            .map(|level| {
+                     Arc::new(ImmutableImageInitialization {
+                                  image: image.clone(),
+                                  used: AtomicBool::new(false),
+                                  mip_levels_access: level .. level + 1,
+                              })
+                 })
+            .collect::<Vec<_>>();
+
+        let mut cb = AutoCommandBufferBuilder::new(source.device().clone(), queue.family())?
             .copy_buffer_to_image_dimensions(source,
-                                             init,
+                                             levels[0].clone(),
                                              [0, 0, 0],
                                              dimensions.width_height_depth(),
                                              0,
                                              dimensions.array_layers_with_cube(),
                                              0)
-            .unwrap()
-            .build()
             .unwrap();
 
+        let image_dimensions = image.image.dimensions();
+        for level in 1 .. num_levels {
+            let source_extent = mipmap_extent(image_dimensions, level - 1);
+            let destination_extent = mipmap_extent(image_dimensions, level);
+
+            cb = cb.blit_image(levels[level as usize - 1].clone(),
+                               [0, 0, 0],
+                               source_extent,
+                               0,
+                               0,
+                               levels[level as usize].clone(),
+                               [0, 0, 0],
+                               destination_extent,
+                               0,
+                               0,
+                               image_dimensions.array_layers(),
+                               Filter::Linear)
+                .unwrap();
+        }
+
+        let cb = cb.build().unwrap();
+
         let future = match cb.execute(queue) {
             Ok(f) => f,
             Err(_) => unreachable!(),
         };
 
-        Ok((buffer, future))
+        Ok((image, future))
     }
 }
 
+// Returns the bottom-right corner of the given mipmap level, for use in a blit.
+fn mipmap_extent(dimensions: ImageDimensions, level: u32) -> [i32; 3] {
+    [
+        cmp::max(1, dimensions.width() >> level) as i32,
+        cmp::max(1, dimensions.height() >> level) as i32,
+        cmp::max(1, dimensions.depth() >> level) as i32,
+    ]
+}
+
 impl<F, A> ImmutableImage<F, A> {
     /// Returns the dimensions of the image.
     #[inline]
@@ -396,7 +513,28 @@ unsafe impl<F, A> ImageAccess for ImmutableImageInitialization<F, A>
 {
     #[inline]
     fn inner(&self) -> ImageInner {
-        ImageAccess::inner(&self.image)
+        ImageInner {
+            image: &self.image.image,
+            first_layer: 0,
+            num_layers: self.image.image.dimensions().array_layers() as usize,
+            first_mipmap_level: self.mip_levels_access.start as usize,
+            num_mipmap_levels: (self.mip_levels_access.end - self.mip_levels_access.start) as
+                usize,
+        }
+    }
+
+    #[inline]
+    fn mipmap_levels(&self) -> u32 {
+        self.mip_levels_access.end - self.mip_levels_access.start
+    }
+
+    #[inline]
+    fn dimensions(&self) -> ImageDimensions {
+        self.image
+            .image
+            .dimensions()
+            .mipmap_dimensions(self.mip_levels_access.start)
+            .unwrap()
     }
 
     #[inline]
@@ -416,7 +554,15 @@ unsafe impl<F, A> ImageAccess for ImmutableImageInitialization<F, A>
 
     #[inline]
     fn conflicts_image(&self, other: &ImageAccess) -> bool {
-        self.conflict_key() == other.conflict_key() // TODO:
+        if self.conflict_key() != other.conflict_key() {
+            return false;
+        }
+
+        // Different mipmap levels of the same image don't conflict.
+        let other = other.inner();
+        let other_start = other.first_mipmap_level as u32;
+        let other_end = other_start + other.num_mipmap_levels as u32;
+        self.mip_levels_access.start < other_end && other_start < self.mip_levels_access.end
     }
 
     #[inline]
@@ -437,7 +583,8 @@ unsafe impl<F, A> ImageAccess for ImmutableImageInitialization<F, A>
             return Err(AccessError::AlreadyInUse);
         }
 
-        // FIXME: Mipmapped textures require multiple writes to initialize
+        // All the mipmap levels are written by the same command buffer, so a single lock is
+        // enough to initialize the image.
         if !self.used.compare_and_swap(false, true, Ordering::Relaxed) {
             Ok(())
         } else {
@@ -456,3 +603,116 @@ unsafe impl<F, A> ImageAccess for ImmutableImageInitialization<F, A>
         self.image.initialized.store(true, Ordering::Relaxed);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use format::R8G8B8A8Unorm;
+    use image::Dimensions;
+    use image::ImageAccess;
+    use image::ImmutableImage;
+    use image::MipmapsCount;
+    use sync::GpuFuture;
+    use testing::Call;
+    use vk;
+
+    #[test]
+    fn from_iter_with_mipmaps() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let dimensions = Dimensions::Dim2d {
+            width: 64,
+            height: 32,
+        };
+        let data = (0 .. 64 * 32).map(|_| [0u8, 0, 0, 255]);
+        let (image, future) = ImmutableImage::from_iter_with_mipmaps(data,
+                                                                     dimensions,
+                                                                     MipmapsCount::Log2,
+                                                                     R8G8B8A8Unorm,
+                                                                     queue)
+            .unwrap();
+        assert_eq!(image.mipmap_levels(), 7);
+
+        future
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+    }
+
+    #[test]
+    fn mipmaps_blits_and_layouts() {
+        let (loader, _, queue) = mock_dev_and_queue!();
+
+        let dimensions = Dimensions::Dim2d {
+            width: 8,
+            height: 2,
+        };
+        let data = (0 .. 8 * 2).map(|_| [0u8, 0, 0, 255]);
+        loader.take_calls();
+        let (image, _) = ImmutableImage::from_iter_with_mipmaps(data,
+                                                                dimensions,
+                                                                MipmapsCount::Log2,
+                                                                R8G8B8A8Unorm,
+                                                                queue)
+            .unwrap();
+        assert_eq!(image.mipmap_levels(), 4);
+
+        // Replays the barriers to check the layout of each level when it is copied or blitted.
+        let mut layouts = vec![vk::IMAGE_LAYOUT_UNDEFINED; 4];
+        let mut blits = Vec::new();
+        for call in loader.calls() {
+            match call {
+                Call::CmdPipelineBarrier { image_barriers, .. } => {
+                    for barrier in image_barriers {
+                        let level = barrier.subresource.first_mipmap_level as usize;
+                        assert_eq!(barrier.subresource.num_mipmap_levels, 1);
+                        assert_eq!(barrier.old_layout, layouts[level]);
+                        layouts[level] = barrier.new_layout;
+                    }
+                },
+                Call::CmdCopyBufferToImage { destination_layout, regions, .. } => {
+                    assert_eq!(regions.len(), 1);
+                    assert_eq!(regions[0].1.first_mipmap_level, 0);
+                    assert_eq!(destination_layout, vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL);
+                    assert_eq!(layouts[0], vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL);
+                },
+                Call::CmdBlitImage { source_layout, destination_layout, regions, filter, .. } => {
+                    assert_eq!(source_layout, vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL);
+                    assert_eq!(destination_layout, vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL);
+                    assert_eq!(filter, vk::FILTER_LINEAR);
+                    assert_eq!(regions.len(), 1);
+                    let region = &regions[0];
+                    let source = region.source.first_mipmap_level as usize;
+                    let destination = region.destination.first_mipmap_level as usize;
+                    assert_eq!(layouts[source], vk::IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL);
+                    assert_eq!(layouts[destination], vk::IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL);
+                    blits.push((source,
+                                region.source_offsets,
+                                destination,
+                                region.destination_offsets));
+                },
+                _ => (),
+            }
+        }
+
+        assert_eq!(blits,
+                   vec![(0, [[0, 0, 0], [8, 2, 1]], 1, [[0, 0, 0], [4, 1, 1]]),
+                        (1, [[0, 0, 0], [4, 1, 1]], 2, [[0, 0, 0], [2, 1, 1]]),
+                        (2, [[0, 0, 0], [2, 1, 1]], 3, [[0, 0, 0], [1, 1, 1]])]);
+        assert_eq!(layouts, vec![vk::IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL; 4]);
+    }
+
+    #[test]
+    fn from_iter_single_level() {
+        let (_, queue) = gfx_dev_and_queue!();
+
+        let dimensions = Dimensions::Dim2d {
+            width: 16,
+            height: 16,
+        };
+        let data = (0 .. 16 * 16).map(|_| [0u8, 0, 0, 255]);
+        let (image, _) = ImmutableImage::from_iter(data, dimensions, R8G8B8A8Unorm, queue)
+            .unwrap();
+        assert_eq!(image.mipmap_levels(), 1);
+    }
+}
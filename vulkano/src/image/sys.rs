@@ -65,6 +65,9 @@ pub struct UnsafeImage {
     // Features that are supported for this particular format.
     format_features: vk::FormatFeatureFlagBits,
 
+    // True if the image was created with `Sharing::Exclusive`.
+    exclusive_sharing: bool,
+
     // `vkDestroyImage` is called only if `needs_destruction` is true.
     needs_destruction: bool,
 }
@@ -550,6 +553,7 @@ impl UnsafeImage {
             samples: num_samples,
             mipmaps: mipmaps,
             format_features: format_features,
+            exclusive_sharing: sh_mode == vk::SHARING_MODE_EXCLUSIVE,
             needs_destruction: true,
         };
 
@@ -558,6 +562,9 @@ impl UnsafeImage {
 
     /// Creates an image from a raw handle. The image won't be destroyed.
     ///
+    /// The image is assumed to have been created with `Sharing::Exclusive`. Use
+    /// `set_exclusive_sharing` if that's not the case.
+    ///
     /// This function is for example used at the swapchain's initialization.
     pub unsafe fn from_raw(device: Arc<Device>, handle: u64, usage: u32, format: Format,
                            dimensions: ImageDimensions, samples: u32, mipmaps: u32)
@@ -579,10 +586,23 @@ impl UnsafeImage {
             samples: samples,
             mipmaps: mipmaps,
             format_features: output.optimalTilingFeatures,
+            exclusive_sharing: true,
             needs_destruction: false, // TODO: pass as parameter
         }
     }
 
+    /// Sets whether the image uses exclusive sharing. Only meaningful for images created with
+    /// `from_raw`.
+    ///
+    /// # Safety
+    ///
+    /// - `exclusive` must match the sharing mode the image was created with.
+    ///
+    #[inline]
+    pub unsafe fn set_exclusive_sharing(&mut self, exclusive: bool) {
+        self.exclusive_sharing = exclusive;
+    }
+
     pub unsafe fn bind_memory(&self, memory: &DeviceMemory, offset: usize) -> Result<(), OomError> {
         let vk = self.device.pointers();
 
@@ -739,6 +759,13 @@ impl UnsafeImage {
         (self.format_features & vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT) != 0
     }
 
+    /// Returns true if the image was created with `Sharing::Exclusive`, in which case it must be
+    /// transferred between queue families in order to be used by a queue of another family.
+    #[inline]
+    pub fn exclusive_sharing(&self) -> bool {
+        self.exclusive_sharing
+    }
+
     #[inline]
     pub fn usage_transfer_source(&self) -> bool {
         (self.usage & vk::IMAGE_USAGE_TRANSFER_SRC_BIT) != 0
@@ -832,6 +859,9 @@ pub enum ImageCreationError {
     UnsupportedUsage,
     /// The `shader_storage_image_multisample` feature must be enabled to create such an image.
     ShaderStorageImageMultisampleFeatureNotEnabled,
+    /// Mipmaps were requested to be generated, but the format doesn't support blits with a
+    /// linear filter.
+    MipmapsGenerationNotSupported,
 }
 
 impl error::Error for ImageCreationError {
@@ -854,6 +884,10 @@ impl error::Error for ImageCreationError {
                 "the `shader_storage_image_multisample` feature must be enabled to create such \
                  an image"
             },
+            ImageCreationError::MipmapsGenerationNotSupported => {
+                "mipmaps were requested to be generated, but the format doesn't support blits \
+                 with a linear filter"
+            },
         }
     }
 
@@ -237,6 +237,16 @@ unsafe impl<T> ImageAccess for T
         (**self).inner()
     }
 
+    #[inline]
+    fn mipmap_levels(&self) -> u32 {
+        (**self).mipmap_levels()
+    }
+
+    #[inline]
+    fn dimensions(&self) -> ImageDimensions {
+        (**self).dimensions()
+    }
+
     #[inline]
     fn initial_layout_requirement(&self) -> ImageLayout {
         (**self).initial_layout_requirement()
@@ -295,6 +305,16 @@ unsafe impl<I> ImageAccess for ImageAccessFromUndefinedLayout<I>
         self.image.inner()
     }
 
+    #[inline]
+    fn mipmap_levels(&self) -> u32 {
+        self.image.mipmap_levels()
+    }
+
+    #[inline]
+    fn dimensions(&self) -> ImageDimensions {
+        self.image.dimensions()
+    }
+
     #[inline]
     fn initial_layout_requirement(&self) -> ImageLayout {
         if self.preinitialized {
@@ -400,6 +400,7 @@ device_extensions! {
     khr_dedicated_allocation => b"VK_KHR_dedicated_allocation",
     khr_incremental_present => b"VK_KHR_incremental_present",
     ext_debug_marker => b"VK_EXT_debug_marker",
+    ext_hdr_metadata => b"VK_EXT_hdr_metadata",
 }
 
 /// Error that can happen when loading the list of layers.
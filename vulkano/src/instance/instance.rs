@@ -1062,6 +1062,13 @@ impl<'a> QueueFamily<'a> {
         (self.flags() & vk::QUEUE_SPARSE_BINDING_BIT) != 0
     }
 
+    /// Returns the number of meaningful bits in the timestamps written by queues of this family,
+    /// or `0` if these queues don't support timestamps.
+    #[inline]
+    pub fn timestamp_valid_bits(&self) -> u32 {
+        self.physical_device.infos().queue_families[self.id as usize].timestampValidBits
+    }
+
     /// Internal utility function that returns the flags of this queue family.
     #[inline]
     fn flags(&self) -> u32 {
@@ -91,6 +91,8 @@ pub mod query;
 pub mod sampler;
 pub mod swapchain;
 pub mod sync;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 use std::error;
 use std::fmt;
@@ -0,0 +1,376 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Sub-allocator that manages the ranges of a single block of device memory.
+//!
+//! The allocator doesn't interact with Vulkan at all. It only keeps track of which parts of a
+//! block of `size` bytes are in use.
+//!
+//! The block is split in consecutive segments, each of them either free or occupied. Free
+//! segments are also indexed by size, which makes it possible to find the smallest free segment
+//! that fits a request without walking over all the allocations. Adjacent free segments are
+//! always merged together.
+//!
+//! Since linear and optimal resources can share the same block, the allocator also makes sure
+//! that a linear resource and an optimal resource never share a page of
+//! `buffer_image_granularity` bytes.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use memory::pool::AllocLayout;
+use memory::pool::MemoryPoolStats;
+
+/// Manages the ranges of a single block of memory.
+#[derive(Debug)]
+pub struct BlockAllocator {
+    size: usize,
+    granularity: usize,
+    // All the segments of the block, indexed by their offset. Always covers the whole block.
+    segments: BTreeMap<usize, Segment>,
+    // List of `(size, offset)` of all the free segments.
+    free: BTreeSet<(usize, usize)>,
+    // Total number of bytes occupied by allocations.
+    used: usize,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Segment {
+    size: usize,
+    // `None` if the segment is free.
+    layout: Option<AllocLayout>,
+}
+
+impl BlockAllocator {
+    /// Builds an allocator for a block of `size` bytes, entirely free.
+    ///
+    /// `granularity` is the value of the `buffer_image_granularity` limit.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `granularity` is 0.
+    ///
+    pub fn new(size: usize, granularity: usize) -> BlockAllocator {
+        assert!(size != 0);
+        assert!(granularity != 0);
+
+        let mut segments = BTreeMap::new();
+        segments.insert(0,
+                        Segment {
+                            size: size,
+                            layout: None,
+                        });
+        let mut free = BTreeSet::new();
+        free.insert((size, 0));
+
+        BlockAllocator {
+            size: size,
+            granularity: granularity,
+            segments: segments,
+            free: free,
+            used: 0,
+        }
+    }
+
+    /// Returns the size of the block managed by this allocator.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of bytes currently occupied by allocations.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Returns statistics about the block.
+    pub fn stats(&self) -> MemoryPoolStats {
+        MemoryPoolStats {
+            blocks: 1,
+            allocations: self.segments.len() - self.free.len(),
+            used: self.used,
+            free: self.size - self.used,
+            largest_free_range: self.free.iter().next_back().map(|&(s, _)| s).unwrap_or(0),
+        }
+    }
+
+    /// Returns true if nothing is allocated.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.used == 0
+    }
+
+    /// Allocates a range of `size` bytes whose offset is a multiple of `alignment`. Returns the
+    /// offset of the range, or `None` if there isn't enough contiguous free space.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `size` is 0.
+    /// - Panics if `alignment` is 0.
+    ///
+    pub fn alloc(&mut self, size: usize, alignment: usize, layout: AllocLayout) -> Option<usize> {
+        assert!(size != 0);
+        assert!(alignment != 0);
+
+        // Best fit: we try the free segments from the smallest to the largest, starting with the
+        // first one that is large enough. Most of the time the first candidate is the right one,
+        // but alignment and granularity requirements can make it too small.
+        let found = self.free
+            .range((size, 0) ..)
+            .filter_map(|&(_, offset)| self.fit(offset, size, alignment, layout))
+            .next();
+
+        let (free_offset, start) = match found {
+            Some(f) => f,
+            None => return None,
+        };
+
+        let free_size = self.segments[&free_offset].size;
+        self.remove_free(free_offset);
+
+        // Space left before and after the allocation goes back to the free segments.
+        if start > free_offset {
+            self.insert_free(free_offset, start - free_offset);
+        }
+        let end = start + size;
+        if end < free_offset + free_size {
+            self.insert_free(end, free_offset + free_size - end);
+        }
+
+        self.segments.insert(start,
+                             Segment {
+                                 size: size,
+                                 layout: Some(layout),
+                             });
+        self.used += size;
+        Some(start)
+    }
+
+    /// Frees the allocation that starts at `offset`, and merges the freed range with its free
+    /// neighbours.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if there is no allocation at `offset`.
+    ///
+    pub fn free(&mut self, offset: usize) {
+        let mut start = offset;
+        let mut size = match self.segments.get(&offset) {
+            Some(&Segment {
+                     size,
+                     layout: Some(_),
+                 }) => size,
+            _ => panic!("No allocation at offset {}", offset),
+        };
+        self.used -= size;
+        self.segments.remove(&offset);
+
+        // Merge with the next segment.
+        let next = offset + size;
+        if let Some(&Segment { size: next_size, layout: None }) = self.segments.get(&next) {
+            self.remove_free(next);
+            size += next_size;
+        }
+
+        // Merge with the previous segment.
+        let prev = self.segments
+            .range(.. offset)
+            .next_back()
+            .map(|(&o, s)| (o, *s));
+        if let Some((prev_offset, Segment { size: prev_size, layout: None })) = prev {
+            self.remove_free(prev_offset);
+            start = prev_offset;
+            size += prev_size;
+        }
+
+        self.insert_free(start, size);
+    }
+
+    // Checks whether an allocation fits in the free segment at `free_offset`. If it does, returns
+    // `free_offset` and the offset where the allocation would start.
+    fn fit(&self, free_offset: usize, size: usize, alignment: usize, layout: AllocLayout)
+           -> Option<(usize, usize)> {
+        let free_end = free_offset + self.segments[&free_offset].size;
+        let mut start = align(free_offset, alignment);
+
+        // If the previous allocation has a different layout and ends in the same page, we must
+        // move to the next page. Since free segments are always merged, the segment before a
+        // free segment is always occupied.
+        if let Some((&prev_offset, prev)) = self.segments.range(.. free_offset).next_back() {
+            if prev.layout != Some(layout) &&
+                same_page(prev_offset + prev.size - 1, start, self.granularity)
+            {
+                start = align(align(start, self.granularity), alignment);
+            }
+        }
+
+        let end = match start.checked_add(size) {
+            Some(e) => e,
+            None => return None,
+        };
+        if end > free_end {
+            return None;
+        }
+
+        // Same thing for the next allocation, except that we can't move it.
+        if let Some(next) = self.segments.get(&free_end) {
+            if next.layout != Some(layout) && same_page(end - 1, free_end, self.granularity) {
+                return None;
+            }
+        }
+
+        Some((free_offset, start))
+    }
+
+    #[inline]
+    fn insert_free(&mut self, offset: usize, size: usize) {
+        self.segments.insert(offset,
+                             Segment {
+                                 size: size,
+                                 layout: None,
+                             });
+        self.free.insert((size, offset));
+    }
+
+    #[inline]
+    fn remove_free(&mut self, offset: usize) {
+        let segment = self.segments.remove(&offset).unwrap();
+        debug_assert!(segment.layout.is_none());
+        let was_present = self.free.remove(&(segment.size, offset));
+        debug_assert!(was_present);
+    }
+}
+
+#[inline]
+fn align(val: usize, al: usize) -> usize {
+    al * ((val + al - 1) / al)
+}
+
+#[inline]
+fn same_page(a: usize, b: usize, granularity: usize) -> bool {
+    a / granularity == b / granularity
+}
+
+#[cfg(test)]
+mod tests {
+    use memory::pool::AllocLayout;
+    use memory::pool::allocator::BlockAllocator;
+
+    #[test]
+    fn basic_alloc() {
+        let mut allocator = BlockAllocator::new(1024, 1);
+        assert_eq!(allocator.alloc(100, 1, AllocLayout::Linear), Some(0));
+        assert_eq!(allocator.alloc(100, 1, AllocLayout::Linear), Some(100));
+        assert_eq!(allocator.used(), 200);
+        assert_eq!(allocator.alloc(1000, 1, AllocLayout::Linear), None);
+    }
+
+    #[test]
+    fn alignment() {
+        let mut allocator = BlockAllocator::new(1024, 1);
+        assert_eq!(allocator.alloc(3, 1, AllocLayout::Linear), Some(0));
+        assert_eq!(allocator.alloc(16, 64, AllocLayout::Linear), Some(64));
+        // The padding left before the aligned allocation can still be used.
+        assert_eq!(allocator.alloc(8, 4, AllocLayout::Linear), Some(4));
+    }
+
+    #[test]
+    fn full_block() {
+        let mut allocator = BlockAllocator::new(256, 1);
+        assert_eq!(allocator.alloc(256, 256, AllocLayout::Optimal), Some(0));
+        assert_eq!(allocator.alloc(1, 1, AllocLayout::Optimal), None);
+        allocator.free(0);
+        assert!(allocator.is_empty());
+        assert_eq!(allocator.alloc(256, 1, AllocLayout::Optimal), Some(0));
+    }
+
+    #[test]
+    fn coalesce() {
+        let mut allocator = BlockAllocator::new(300, 1);
+        let a = allocator.alloc(100, 1, AllocLayout::Linear).unwrap();
+        let b = allocator.alloc(100, 1, AllocLayout::Linear).unwrap();
+        let c = allocator.alloc(100, 1, AllocLayout::Linear).unwrap();
+
+        allocator.free(a);
+        allocator.free(c);
+        assert_eq!(allocator.alloc(200, 1, AllocLayout::Linear), None);
+
+        // Freeing `b` must merge the three ranges.
+        allocator.free(b);
+        assert!(allocator.is_empty());
+        assert_eq!(allocator.alloc(300, 1, AllocLayout::Linear), Some(0));
+    }
+
+    #[test]
+    fn best_fit() {
+        let mut allocator = BlockAllocator::new(1000, 1);
+        let a = allocator.alloc(300, 1, AllocLayout::Linear).unwrap();
+        allocator.alloc(10, 1, AllocLayout::Linear).unwrap();
+        let b = allocator.alloc(50, 1, AllocLayout::Linear).unwrap();
+        allocator.alloc(10, 1, AllocLayout::Linear).unwrap();
+        allocator.free(a);
+        allocator.free(b);
+
+        // The hole of 50 bytes is smaller than the hole of 300 bytes.
+        assert_eq!(allocator.alloc(40, 1, AllocLayout::Linear), Some(b));
+    }
+
+    #[test]
+    fn granularity() {
+        let mut allocator = BlockAllocator::new(4096, 1024);
+        assert_eq!(allocator.alloc(100, 1, AllocLayout::Linear), Some(0));
+        // Same layout can share the page.
+        assert_eq!(allocator.alloc(100, 1, AllocLayout::Linear), Some(100));
+        // Different layout must start on the next page.
+        assert_eq!(allocator.alloc(100, 1, AllocLayout::Optimal), Some(1024));
+        // The end of the first page can be used again by a linear resource.
+        assert_eq!(allocator.alloc(100, 1, AllocLayout::Linear), Some(200));
+    }
+
+    #[test]
+    fn granularity_next_neighbour() {
+        let mut allocator = BlockAllocator::new(2048, 1024);
+        let a = allocator.alloc(1000, 1, AllocLayout::Linear).unwrap();
+        assert_eq!(allocator.alloc(10, 1, AllocLayout::Linear), Some(1000));
+        allocator.free(a);
+
+        // The free range at the start of the block shares a page with a linear resource.
+        assert_eq!(allocator.alloc(10, 1, AllocLayout::Optimal), Some(1024));
+        // The gap between the two resources can only be used by a linear resource.
+        assert_eq!(allocator.alloc(14, 1, AllocLayout::Optimal), Some(1034));
+        assert_eq!(allocator.alloc(14, 1, AllocLayout::Linear), Some(1010));
+    }
+
+    #[test]
+    fn stats() {
+        let mut allocator = BlockAllocator::new(1000, 1);
+        let a = allocator.alloc(100, 1, AllocLayout::Linear).unwrap();
+        allocator.alloc(200, 1, AllocLayout::Linear).unwrap();
+        allocator.alloc(50, 1, AllocLayout::Linear).unwrap();
+        allocator.free(a);
+
+        let stats = allocator.stats();
+        assert_eq!(stats.blocks, 1);
+        assert_eq!(stats.allocations, 2);
+        assert_eq!(stats.used, 250);
+        assert_eq!(stats.free, 750);
+        assert_eq!(stats.largest_free_range, 650);
+    }
+
+    #[test]
+    #[should_panic]
+    fn double_free() {
+        let mut allocator = BlockAllocator::new(1024, 1);
+        let a = allocator.alloc(100, 1, AllocLayout::Linear).unwrap();
+        allocator.free(a);
+        allocator.free(a);
+    }
+}
@@ -7,7 +7,10 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::cmp;
+
 use device::DeviceOwned;
+use instance::MemoryHeap;
 use instance::MemoryType;
 use memory::DedicatedAlloc;
 use memory::DeviceMemory;
@@ -22,6 +25,7 @@ pub use self::non_host_visible::StdNonHostVisibleMemoryTypePoolAlloc;
 pub use self::pool::StdMemoryPool;
 pub use self::pool::StdMemoryPoolAlloc;
 
+mod allocator;
 mod host_visible;
 mod non_host_visible;
 mod pool;
@@ -56,6 +60,29 @@ pub unsafe trait MemoryPool: DeviceOwned {
                      map: MappingRequirement)
                      -> Result<Self::Alloc, DeviceMemoryAllocError>;
 
+    /// Returns statistics about the memory that the pool has allocated from the given memory
+    /// type.
+    ///
+    /// Dedicated allocations are not included. The default implementation returns empty
+    /// statistics.
+    #[inline]
+    fn memory_type_stats(&self, _ty: MemoryType) -> MemoryPoolStats {
+        MemoryPoolStats::default()
+    }
+
+    /// Returns statistics about the memory that the pool has allocated from the given heap.
+    ///
+    /// The default implementation merges the value of `memory_type_stats` for all the memory
+    /// types of the heap.
+    fn heap_stats(&self, heap: MemoryHeap) -> MemoryPoolStats {
+        self.device()
+            .physical_device()
+            .memory_types()
+            .filter(|ty| ty.heap().id() == heap.id())
+            .fold(MemoryPoolStats::default(),
+                  |stats, ty| stats.merge(&self.memory_type_stats(ty)))
+    }
+
     /// Chooses a memory type and allocates memory from it.
     ///
     /// Contrary to `alloc_generic`, this function may allocate a whole new block of memory
@@ -177,6 +204,42 @@ pub unsafe trait MemoryPoolAlloc {
     fn offset(&self) -> usize;
 }
 
+/// Statistics about the memory allocated by a pool.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MemoryPoolStats {
+    /// Number of blocks of device memory that the pool has allocated.
+    pub blocks: usize,
+    /// Number of allocations from these blocks that are currently alive.
+    pub allocations: usize,
+    /// Number of bytes of the blocks that are used by allocations.
+    pub used: usize,
+    /// Number of bytes of the blocks that are not used by any allocation.
+    pub free: usize,
+    /// Size in bytes of the largest contiguous free range within a block. A higher `free` value
+    /// with a low `largest_free_range` indicates fragmentation.
+    pub largest_free_range: usize,
+}
+
+impl MemoryPoolStats {
+    /// Returns the total size of the blocks, in other words `used + free`.
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.used + self.free
+    }
+
+    /// Combines the statistics of two groups of blocks.
+    #[inline]
+    pub fn merge(&self, other: &MemoryPoolStats) -> MemoryPoolStats {
+        MemoryPoolStats {
+            blocks: self.blocks + other.blocks,
+            allocations: self.allocations + other.allocations,
+            used: self.used + other.used,
+            free: self.free + other.free,
+            largest_free_range: cmp::max(self.largest_free_range, other.largest_free_range),
+        }
+    }
+}
+
 /// Whether an allocation should map the memory or not.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MappingRequirement {
@@ -8,23 +8,28 @@
 // according to those terms.
 
 use std::cmp;
-use std::ops::Range;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+use OomError;
 use device::Device;
 use instance::Instance;
 use instance::MemoryType;
 use memory::DeviceMemory;
 use memory::DeviceMemoryAllocError;
+use memory::pool::AllocLayout;
+use memory::pool::MemoryPoolStats;
+use memory::pool::allocator::BlockAllocator;
 
 /// Memory pool that operates on a given memory type.
 #[derive(Debug)]
 pub struct StdNonHostVisibleMemoryTypePool {
     device: Arc<Device>,
     memory_type: u32,
-    // TODO: obviously very inefficient
-    occupied: Mutex<Vec<(Arc<DeviceMemory>, Vec<Range<usize>>)>>,
+    // Value of the `buffer_image_granularity` limit.
+    granularity: usize,
+    // Blocks of memory, and the allocator that keeps track of the ranges used in each of them.
+    occupied: Mutex<Vec<(Arc<DeviceMemory>, BlockAllocator)>>,
 }
 
 impl StdNonHostVisibleMemoryTypePool {
@@ -45,56 +50,47 @@ impl StdNonHostVisibleMemoryTypePool {
         Arc::new(StdNonHostVisibleMemoryTypePool {
                      device: device.clone(),
                      memory_type: memory_type.id(),
+                     granularity: device.physical_device().limits().buffer_image_granularity() as
+                         usize,
                      occupied: Mutex::new(Vec::new()),
                  })
     }
 
     /// Allocates memory from the pool.
     ///
+    /// `layout` is used to make sure that linear and optimal resources never share a page of
+    /// `buffer_image_granularity` bytes.
+    ///
     /// # Panic
     ///
     /// - Panics if `size` is 0.
     /// - Panics if `alignment` is 0.
     ///
-    pub fn alloc(me: &Arc<Self>, size: usize, alignment: usize)
+    #[inline]
+    pub fn alloc(me: &Arc<Self>, size: usize, alignment: usize, layout: AllocLayout)
                  -> Result<StdNonHostVisibleMemoryTypePoolAlloc, DeviceMemoryAllocError> {
+        StdNonHostVisibleMemoryTypePool::alloc_within_budget(me, size, alignment, layout, None)
+    }
+
+    /// Same as `alloc`, but allocates at most `budget` bytes of new device memory if the
+    /// allocation doesn't fit in the existing blocks. Returns `OomError` if `size` is larger
+    /// than `budget`.
+    pub(crate) fn alloc_within_budget(me: &Arc<Self>, size: usize, alignment: usize,
+                                      layout: AllocLayout, budget: Option<usize>)
+                                      -> Result<StdNonHostVisibleMemoryTypePoolAlloc,
+                                                DeviceMemoryAllocError> {
         assert!(size != 0);
         assert!(alignment != 0);
 
-        #[inline]
-        fn align(val: usize, al: usize) -> usize {
-            al * (1 + (val - 1) / al)
-        }
-
-        // Find a location.
         let mut occupied = me.occupied.lock().unwrap();
 
         // Try finding an entry in already-allocated chunks.
-        for &mut (ref dev_mem, ref mut entries) in occupied.iter_mut() {
-            // Try find some free space in-between two entries.
-            for i in 0 .. entries.len().saturating_sub(1) {
-                let entry1 = entries[i].clone();
-                let entry1_end = align(entry1.end, alignment);
-                let entry2 = entries[i + 1].clone();
-                if entry1_end + size <= entry2.start {
-                    entries.insert(i + 1, entry1_end .. entry1_end + size);
-                    return Ok(StdNonHostVisibleMemoryTypePoolAlloc {
-                                  pool: me.clone(),
-                                  memory: dev_mem.clone(),
-                                  offset: entry1_end,
-                                  size: size,
-                              });
-                }
-            }
-
-            // Try append at the end.
-            let last_end = entries.last().map(|e| align(e.end, alignment)).unwrap_or(0);
-            if last_end + size <= dev_mem.size() {
-                entries.push(last_end .. last_end + size);
+        for &mut (ref dev_mem, ref mut allocator) in occupied.iter_mut() {
+            if let Some(offset) = allocator.alloc(size, alignment, layout) {
                 return Ok(StdNonHostVisibleMemoryTypePoolAlloc {
                               pool: me.clone(),
                               memory: dev_mem.clone(),
-                              offset: last_end,
+                              offset: offset,
                               size: size,
                           });
             }
@@ -103,20 +99,39 @@ impl StdNonHostVisibleMemoryTypePool {
         // We need to allocate a new block.
         let new_block = {
             const MIN_BLOCK_SIZE: usize = 8 * 1024 * 1024; // 8 MB
-            let to_alloc = cmp::max(MIN_BLOCK_SIZE, size.next_power_of_two());
+            let mut to_alloc = cmp::max(MIN_BLOCK_SIZE, size.next_power_of_two());
+            if let Some(budget) = budget {
+                if size > budget {
+                    let err = OomError::OutOfDeviceMemory;
+                    return Err(DeviceMemoryAllocError::OomError(err));
+                }
+                to_alloc = cmp::min(to_alloc, budget);
+            }
             let new_block = DeviceMemory::alloc(me.device.clone(), me.memory_type(), to_alloc)?;
             Arc::new(new_block)
         };
 
-        occupied.push((new_block.clone(), vec![0 .. size]));
+        let mut allocator = BlockAllocator::new(new_block.size(), me.granularity);
+        let offset = allocator.alloc(size, alignment, layout).unwrap();
+        debug_assert_eq!(offset, 0);
+        occupied.push((new_block.clone(), allocator));
         Ok(StdNonHostVisibleMemoryTypePoolAlloc {
                pool: me.clone(),
                memory: new_block,
-               offset: 0,
+               offset: offset,
                size: size,
            })
     }
 
+    /// Returns statistics about the blocks allocated by this pool.
+    pub fn stats(&self) -> MemoryPoolStats {
+        let occupied = self.occupied.lock().unwrap();
+        occupied
+            .iter()
+            .fold(MemoryPoolStats::default(),
+                  |stats, &(_, ref allocator)| stats.merge(&allocator.stats()))
+    }
+
     /// Returns the device this pool operates on.
     #[inline]
     pub fn device(&self) -> &Arc<Device> {
@@ -167,6 +182,6 @@ impl Drop for StdNonHostVisibleMemoryTypePoolAlloc {
             .find(|e| &*e.0 as *const DeviceMemory == &*self.memory)
             .unwrap();
 
-        entries.1.retain(|e| e.start != self.offset);
+        entries.1.free(self.offset);
     }
 }
@@ -16,6 +16,7 @@ use std::sync::Mutex;
 
 use device::Device;
 use device::DeviceOwned;
+use instance::MemoryHeap;
 use instance::MemoryType;
 use memory::DeviceMemory;
 use memory::DeviceMemoryAllocError;
@@ -24,6 +25,7 @@ use memory::pool::AllocLayout;
 use memory::pool::MappingRequirement;
 use memory::pool::MemoryPool;
 use memory::pool::MemoryPoolAlloc;
+use memory::pool::MemoryPoolStats;
 use memory::pool::StdHostVisibleMemoryTypePool;
 use memory::pool::StdHostVisibleMemoryTypePoolAlloc;
 use memory::pool::StdNonHostVisibleMemoryTypePool;
@@ -33,9 +35,12 @@ use memory::pool::StdNonHostVisibleMemoryTypePoolAlloc;
 pub struct StdMemoryPool {
     device: Arc<Device>,
 
-    // For each memory type index, stores the associated pool.
-    pools:
-        Mutex<HashMap<(u32, AllocLayout, MappingRequirement), Pool, BuildHasherDefault<FnvHasher>>>,
+    // For each memory type index, stores the associated pool. Linear and optimal resources share
+    // the same pool.
+    pools: Mutex<HashMap<(u32, MappingRequirement), Pool, BuildHasherDefault<FnvHasher>>>,
+
+    // For each heap index, the maximum number of bytes that the pool is allowed to allocate.
+    budgets: Mutex<HashMap<u32, usize, BuildHasherDefault<FnvHasher>>>,
 }
 
 impl StdMemoryPool {
@@ -48,8 +53,65 @@ impl StdMemoryPool {
         Arc::new(StdMemoryPool {
                      device: device.clone(),
                      pools: Mutex::new(HashMap::with_capacity_and_hasher(cap, hasher)),
+                     budgets: Mutex::new(HashMap::default()),
                  })
     }
+
+    /// Sets the maximum number of bytes of device memory that the pool can allocate from a heap.
+    ///
+    /// Once the blocks allocated from the heap reach this size, allocations that don't fit in the
+    /// existing blocks return `DeviceMemoryAllocError::OomError` instead of allocating more
+    /// memory. Passing `None` removes the budget.
+    ///
+    /// This is a soft limit: it doesn't free memory that was already allocated, and dedicated
+    /// allocations are not counted.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the heap doesn't belong to the physical device of the pool.
+    ///
+    pub fn set_heap_budget(&self, heap: MemoryHeap, budget: Option<usize>) {
+        assert_eq!(heap.physical_device().index(),
+                   self.device.physical_device().index());
+
+        let mut budgets = self.budgets.lock().unwrap();
+        match budget {
+            Some(budget) => {
+                budgets.insert(heap.id(), budget);
+            },
+            None => {
+                budgets.remove(&heap.id());
+            },
+        }
+    }
+
+    /// Returns the budget of a heap set with `set_heap_budget`, if any.
+    #[inline]
+    pub fn heap_budget(&self, heap: MemoryHeap) -> Option<usize> {
+        self.budgets.lock().unwrap().get(&heap.id()).cloned()
+    }
+
+    // Returns the number of bytes that can still be allocated from the heap of `memory_type`, or
+    // `None` if there is no budget.
+    fn remaining_budget(&self, pools: &HashMap<(u32, MappingRequirement), Pool,
+                                               BuildHasherDefault<FnvHasher>>,
+                        memory_type: MemoryType)
+                        -> Option<usize> {
+        let heap = memory_type.heap().id();
+        let budget = match self.budgets.lock().unwrap().get(&heap) {
+            Some(&b) => b,
+            None => return None,
+        };
+
+        let physical_device = self.device.physical_device();
+        let allocated: usize = pools
+            .iter()
+            .filter(|&(&(ty, _), _)| physical_device.memory_type_by_id(ty).unwrap().heap().id() ==
+                        heap)
+            .map(|(_, pool)| pool.stats().total())
+            .sum();
+        Some(budget.saturating_sub(allocated))
+    }
 }
 
 unsafe impl MemoryPool for Arc<StdMemoryPool> {
@@ -63,11 +125,16 @@ unsafe impl MemoryPool for Arc<StdMemoryPool> {
         let memory_type_host_visible = memory_type.is_host_visible();
         assert!(memory_type_host_visible || map == MappingRequirement::DoNotMap);
 
-        match pools.entry((memory_type.id(), layout, map)) {
+        let budget = self.remaining_budget(&pools, memory_type);
+
+        match pools.entry((memory_type.id(), map)) {
             Entry::Occupied(entry) => {
                 match entry.get() {
                     &Pool::HostVisible(ref pool) => {
-                        let alloc = StdHostVisibleMemoryTypePool::alloc(&pool, size, alignment)?;
+                        let alloc =
+                            StdHostVisibleMemoryTypePool::alloc_within_budget(&pool, size,
+                                                                              alignment, layout,
+                                                                              budget)?;
                         let inner = StdMemoryPoolAllocInner::HostVisible(alloc);
                         Ok(StdMemoryPoolAlloc {
                                inner: inner,
@@ -75,7 +142,10 @@ unsafe impl MemoryPool for Arc<StdMemoryPool> {
                            })
                     },
                     &Pool::NonHostVisible(ref pool) => {
-                        let alloc = StdNonHostVisibleMemoryTypePool::alloc(&pool, size, alignment)?;
+                        let alloc =
+                            StdNonHostVisibleMemoryTypePool::alloc_within_budget(&pool, size,
+                                                                                 alignment, layout,
+                                                                                 budget)?;
                         let inner = StdMemoryPoolAllocInner::NonHostVisible(alloc);
                         Ok(StdMemoryPoolAlloc {
                                inner: inner,
@@ -89,7 +159,10 @@ unsafe impl MemoryPool for Arc<StdMemoryPool> {
                 if memory_type_host_visible {
                     let pool = StdHostVisibleMemoryTypePool::new(self.device.clone(), memory_type);
                     entry.insert(Pool::HostVisible(pool.clone()));
-                    let alloc = StdHostVisibleMemoryTypePool::alloc(&pool, size, alignment)?;
+                    let alloc =
+                        StdHostVisibleMemoryTypePool::alloc_within_budget(&pool, size,
+                                                                          alignment, layout,
+                                                                          budget)?;
                     let inner = StdMemoryPoolAllocInner::HostVisible(alloc);
                     Ok(StdMemoryPoolAlloc {
                            inner: inner,
@@ -99,7 +172,10 @@ unsafe impl MemoryPool for Arc<StdMemoryPool> {
                     let pool = StdNonHostVisibleMemoryTypePool::new(self.device.clone(),
                                                                     memory_type);
                     entry.insert(Pool::NonHostVisible(pool.clone()));
-                    let alloc = StdNonHostVisibleMemoryTypePool::alloc(&pool, size, alignment)?;
+                    let alloc =
+                        StdNonHostVisibleMemoryTypePool::alloc_within_budget(&pool, size,
+                                                                             alignment, layout,
+                                                                             budget)?;
                     let inner = StdMemoryPoolAllocInner::NonHostVisible(alloc);
                     Ok(StdMemoryPoolAlloc {
                            inner: inner,
@@ -109,6 +185,15 @@ unsafe impl MemoryPool for Arc<StdMemoryPool> {
             },
         }
     }
+
+    fn memory_type_stats(&self, memory_type: MemoryType) -> MemoryPoolStats {
+        let pools = self.pools.lock().unwrap();
+        pools
+            .iter()
+            .filter(|&(&(ty, _), _)| ty == memory_type.id())
+            .fold(MemoryPoolStats::default(),
+                  |stats, (_, pool)| stats.merge(&pool.stats()))
+    }
 }
 
 unsafe impl DeviceOwned for StdMemoryPool {
@@ -124,6 +209,16 @@ enum Pool {
     NonHostVisible(Arc<StdNonHostVisibleMemoryTypePool>),
 }
 
+impl Pool {
+    #[inline]
+    fn stats(&self) -> MemoryPoolStats {
+        match *self {
+            Pool::HostVisible(ref pool) => pool.stats(),
+            Pool::NonHostVisible(ref pool) => pool.stats(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct StdMemoryPoolAlloc {
     inner: StdMemoryPoolAllocInner,
@@ -171,3 +266,68 @@ enum StdMemoryPoolAllocInner {
     NonHostVisible(StdNonHostVisibleMemoryTypePoolAlloc),
     HostVisible(StdHostVisibleMemoryTypePoolAlloc),
 }
+
+#[cfg(test)]
+mod tests {
+    use OomError;
+    use memory::DeviceMemoryAllocError;
+    use memory::pool::AllocLayout;
+    use memory::pool::MappingRequirement;
+    use memory::pool::MemoryPool;
+    use memory::pool::StdMemoryPool;
+
+    #[test]
+    fn stats() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = StdMemoryPool::new(device.clone());
+        let ty = device.physical_device().memory_types().next().unwrap();
+
+        let a = pool.alloc_generic(ty, 256, 16, AllocLayout::Linear,
+                                   MappingRequirement::DoNotMap)
+            .unwrap();
+        let _b = pool.alloc_generic(ty, 512, 16, AllocLayout::Optimal,
+                                    MappingRequirement::DoNotMap)
+            .unwrap();
+
+        let stats = pool.memory_type_stats(ty);
+        assert_eq!(stats.blocks, 1);
+        assert_eq!(stats.allocations, 2);
+        assert_eq!(stats.used, 768);
+        assert_eq!(pool.heap_stats(ty.heap()), stats);
+
+        drop(a);
+        let stats = pool.memory_type_stats(ty);
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.used, 512);
+    }
+
+    #[test]
+    fn heap_budget() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = StdMemoryPool::new(device.clone());
+        let ty = device.physical_device().memory_types().next().unwrap();
+
+        pool.set_heap_budget(ty.heap(), Some(4096));
+        assert_eq!(pool.heap_budget(ty.heap()), Some(4096));
+
+        let _a = pool.alloc_generic(ty, 1024, 16, AllocLayout::Linear,
+                                    MappingRequirement::DoNotMap)
+            .unwrap();
+        assert_eq!(pool.memory_type_stats(ty).total(), 4096);
+
+        match pool.alloc_generic(ty, 4096, 16, AllocLayout::Linear, MappingRequirement::DoNotMap) {
+            Err(DeviceMemoryAllocError::OomError(OomError::OutOfDeviceMemory)) => (),
+            _ => panic!(),
+        }
+
+        // Allocations that fit in the existing block are still possible.
+        assert!(pool.alloc_generic(ty, 1024, 16, AllocLayout::Linear,
+                                   MappingRequirement::DoNotMap)
+                    .is_ok());
+
+        pool.set_heap_budget(ty.heap(), None);
+        assert!(pool.alloc_generic(ty, 4096, 16, AllocLayout::Linear,
+                                   MappingRequirement::DoNotMap)
+                    .is_ok());
+    }
+}
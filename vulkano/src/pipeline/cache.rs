@@ -13,21 +13,37 @@
 //! pipelines on the disk.
 //!
 //! You can create either an empty cache or a cache from some initial data. Whenever you create a
-//! graphics or compute pipeline, you have the possibility to pass a reference to that cache.
-//! TODO: ^ that's not the case yet
+//! graphics or compute pipeline, you have the possibility to pass a reference to that cache
+//! (see `GraphicsPipelineBuilder::build_with_cache` and `ComputePipeline::with_cache`).
 //! The Vulkan implementation will then look in the cache for an existing entry, or add one if it
 //! doesn't exist.
 //!
-//! Once that is done, you can extract the data from the cache and store it. See the documentation
-//! of [`get_data`](struct.PipelineCache.html#method.get_data) for example of how to store the data
-//! on the disk, and [`with_data`](struct.PipelineCache.html#method.with_data) for how to reload it.
+//! Once that is done, you can extract the data from the cache and store it. The simplest way to
+//! do so is to use [`save`](struct.PipelineCache.html#method.save) and
+//! [`load_or_empty`](struct.PipelineCache.html#method.load_or_empty). If you want to store the
+//! data somewhere else, see [`get_data`](struct.PipelineCache.html#method.get_data) and
+//! [`with_data`](struct.PipelineCache.html#method.with_data).
+//!
+//! The data of a pipeline cache starts with a header that identifies the device and driver that
+//! produced it. Vulkano checks this header when the data is reloaded, and rejects data that was
+//! produced by another physical device or by another version of the driver.
 //!
 
+use std::error;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Write;
 use std::mem;
+use std::path::Path;
+use std::path::PathBuf;
 use std::ptr;
 use std::sync::Arc;
 
 use device::Device;
+use device::DeviceOwned;
 
 use OomError;
 use VulkanObject;
@@ -46,15 +62,14 @@ impl PipelineCache {
     /// Builds a new pipeline cache from existing data. The data must have been previously obtained
     /// with [`get_data`](#method.get_data).
     ///
-    /// The data passed to this function will most likely be blindly trusted by the Vulkan
-    /// implementation. Therefore you can easily crash your application or the system by passing
-    /// wrong data. Hence why this function is unsafe.
+    /// The header at the start of the data is checked against the physical device of `device`.
+    /// If the data was produced by another device or by another version of the driver, an error
+    /// is returned and you should create an [`empty`](#method.empty) cache instead.
     ///
     /// # Example
     ///
     /// This example loads a cache from a file, if it exists.
     /// See [`get_data`](#method.get_data) for how to store the data in a file.
-    /// TODO: there's a header in the cached data that must be checked ; talk about this
     ///
     /// ```
     /// # use std::sync::Arc;
@@ -74,19 +89,70 @@ impl PipelineCache {
     ///     } else { None }
     /// };
     ///
-    /// let cache = if let Some(data) = data {
-    ///     // This is unsafe because there is no way to be sure that the file contains valid data.
-    ///     unsafe { PipelineCache::with_data(device.clone(), &data).unwrap() }
-    /// } else {
-    ///     PipelineCache::empty(device.clone()).unwrap()
-    /// };
+    /// let cache = data
+    ///     .and_then(|data| PipelineCache::with_data(device.clone(), &data).ok())
+    ///     .unwrap_or_else(|| PipelineCache::empty(device.clone()).unwrap());
     /// ```
     #[inline]
-    pub unsafe fn with_data(device: Arc<Device>, initial_data: &[u8])
-                            -> Result<Arc<PipelineCache>, OomError> {
+    pub fn with_data(device: Arc<Device>, initial_data: &[u8])
+                     -> Result<Arc<PipelineCache>, PipelineCacheCreationError> {
+        {
+            let physical_device = device.physical_device();
+            check_header(initial_data,
+                         physical_device.pci_vendor_id(),
+                         physical_device.pci_device_id(),
+                         physical_device.uuid())?;
+        }
+        unsafe { Ok(PipelineCache::new_impl(device, Some(initial_data))?) }
+    }
+
+    /// Builds a new pipeline cache from existing data, without checking its header.
+    ///
+    /// # Safety
+    ///
+    /// The data passed to this function will most likely be blindly trusted by the Vulkan
+    /// implementation. Therefore you can easily crash your application or the system by passing
+    /// wrong data.
+    #[inline]
+    pub unsafe fn with_data_unchecked(device: Arc<Device>, initial_data: &[u8])
+                                      -> Result<Arc<PipelineCache>, OomError> {
         PipelineCache::new_impl(device, Some(initial_data))
     }
 
+    /// Loads a pipeline cache from a file previously written with [`save`](#method.save).
+    ///
+    /// If the file doesn't exist, can't be read, or contains data that was produced by another
+    /// device or driver, an empty cache is returned instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use vulkano::device::Device;
+    /// use vulkano::pipeline::cache::PipelineCache;
+    /// # let device: Arc<Device> = return;
+    /// let cache = PipelineCache::load_or_empty(device.clone(), "pipeline_cache.bin").unwrap();
+    /// ```
+    pub fn load_or_empty<P>(device: Arc<Device>, path: P) -> Result<Arc<PipelineCache>, OomError>
+        where P: AsRef<Path>
+    {
+        let data = File::open(path).and_then(|mut file| {
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+            Ok(data)
+        });
+
+        if let Ok(data) = data {
+            match PipelineCache::with_data(device.clone(), &data) {
+                Ok(cache) => return Ok(cache),
+                Err(PipelineCacheCreationError::OomError(err)) => return Err(err),
+                Err(_) => (),
+            }
+        }
+
+        PipelineCache::empty(device)
+    }
+
     /// Builds a new empty pipeline cache.
     ///
     /// # Example
@@ -214,6 +280,55 @@ impl PipelineCache {
             Ok(data)
         }
     }
+
+    /// Writes the data of the cache to a file, so that it can be reloaded later with
+    /// [`load_or_empty`](#method.load_or_empty).
+    ///
+    /// The data is first written to a temporary file next to `path`, which is then renamed. This
+    /// means that `path` never contains partially-written data, even if the program is
+    /// interrupted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use vulkano::pipeline::cache::PipelineCache;
+    /// # let cache: Arc<PipelineCache> = return;
+    /// // If an error happens (eg. no permission for the file) we simply skip storing the cache.
+    /// let _ = cache.save("pipeline_cache.bin");
+    /// ```
+    pub fn save<P>(&self, path: P) -> Result<(), PipelineCacheSaveError>
+        where P: AsRef<Path>
+    {
+        let data = self.get_data()?;
+
+        let path = path.as_ref();
+        let tmp_path = {
+            let mut tmp = path.as_os_str().to_owned();
+            tmp.push(".tmp");
+            PathBuf::from(tmp)
+        };
+
+        let result = File::create(&tmp_path).and_then(|mut file| {
+            file.write_all(&data)?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, path)
+        });
+
+        if let Err(err) = result {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+}
+
+unsafe impl DeviceOwned for PipelineCache {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
 }
 
 unsafe impl VulkanObject for PipelineCache {
@@ -237,16 +352,333 @@ impl Drop for PipelineCache {
     }
 }
 
+// Size in bytes of a `VkPipelineCacheHeaderVersionOne`.
+const HEADER_VERSION_ONE_SIZE: usize = 32;
+
+// Checks that the header at the start of `data` was produced by the physical device with the
+// given vendor ID, device ID and pipeline cache UUID.
+fn check_header(data: &[u8], vendor_id: u32, device_id: u32, uuid: &[u8; 16])
+                -> Result<(), PipelineCacheCreationError> {
+    // All the fields of the header are stored with the least significant byte first, regardless
+    // of the endianness of the host.
+    let read_u32 = |offset: usize| {
+        (data[offset] as u32) | (data[offset + 1] as u32) << 8 |
+            (data[offset + 2] as u32) << 16 | (data[offset + 3] as u32) << 24
+    };
+
+    if data.len() < HEADER_VERSION_ONE_SIZE {
+        return Err(PipelineCacheCreationError::HeaderTooShort);
+    }
+
+    let header_size = read_u32(0) as usize;
+    if header_size < HEADER_VERSION_ONE_SIZE || header_size > data.len() {
+        return Err(PipelineCacheCreationError::HeaderTooShort);
+    }
+
+    let version = read_u32(4);
+    if version != vk::PIPELINE_CACHE_HEADER_VERSION_ONE {
+        return Err(PipelineCacheCreationError::UnsupportedHeaderVersion { version: version });
+    }
+
+    if read_u32(8) != vendor_id {
+        return Err(PipelineCacheCreationError::VendorMismatch);
+    }
+
+    if read_u32(12) != device_id {
+        return Err(PipelineCacheCreationError::DeviceMismatch);
+    }
+
+    if &data[16 .. 32] != &uuid[..] {
+        return Err(PipelineCacheCreationError::UuidMismatch);
+    }
+
+    Ok(())
+}
+
+/// Error that can happen when creating a pipeline cache from existing data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PipelineCacheCreationError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The data is too short to contain a pipeline cache header.
+    HeaderTooShort,
+    /// The version of the header is not supported by vulkano.
+    UnsupportedHeaderVersion {
+        /// Version found in the header.
+        version: u32,
+    },
+    /// The data was produced by a device from another vendor.
+    VendorMismatch,
+    /// The data was produced by another device.
+    DeviceMismatch,
+    /// The data was produced by another driver or another version of the driver.
+    UuidMismatch,
+}
+
+impl error::Error for PipelineCacheCreationError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            PipelineCacheCreationError::OomError(_) => "not enough memory available",
+            PipelineCacheCreationError::HeaderTooShort => {
+                "the data is too short to contain a pipeline cache header"
+            },
+            PipelineCacheCreationError::UnsupportedHeaderVersion { .. } => {
+                "the version of the header is not supported"
+            },
+            PipelineCacheCreationError::VendorMismatch => {
+                "the data was produced by a device from another vendor"
+            },
+            PipelineCacheCreationError::DeviceMismatch => {
+                "the data was produced by another device"
+            },
+            PipelineCacheCreationError::UuidMismatch => {
+                "the data was produced by another driver or another version of the driver"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            PipelineCacheCreationError::OomError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PipelineCacheCreationError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for PipelineCacheCreationError {
+    #[inline]
+    fn from(err: OomError) -> PipelineCacheCreationError {
+        PipelineCacheCreationError::OomError(err)
+    }
+}
+
+/// Error that can happen when saving a pipeline cache to a file.
+#[derive(Debug)]
+pub enum PipelineCacheSaveError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// Error while writing the file.
+    IoError(io::Error),
+}
+
+impl error::Error for PipelineCacheSaveError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            PipelineCacheSaveError::OomError(_) => "not enough memory available",
+            PipelineCacheSaveError::IoError(_) => "error while writing the file",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            PipelineCacheSaveError::OomError(ref err) => Some(err),
+            PipelineCacheSaveError::IoError(ref err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for PipelineCacheSaveError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for PipelineCacheSaveError {
+    #[inline]
+    fn from(err: OomError) -> PipelineCacheSaveError {
+        PipelineCacheSaveError::OomError(err)
+    }
+}
+
+impl From<io::Error> for PipelineCacheSaveError {
+    #[inline]
+    fn from(err: io::Error) -> PipelineCacheSaveError {
+        PipelineCacheSaveError::IoError(err)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::env;
+    use std::fs;
+    use std::fs::File;
+    use std::io::Read;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::time::SystemTime;
+    use std::time::UNIX_EPOCH;
+
     use pipeline::cache::PipelineCache;
+    use pipeline::cache::PipelineCacheCreationError;
+    use pipeline::cache::check_header;
+    use vk;
+
+    // Returns a path in the temporary directory that isn't used by any other test, including the
+    // tests of other processes that run at the same time.
+    fn unique_temp_path(name: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        env::temp_dir().join(format!("vulkano-{}-{}-{}-{}.bin",
+                                     name,
+                                     time.as_secs(),
+                                     time.subsec_nanos(),
+                                     COUNTER.fetch_add(1, Ordering::Relaxed)))
+    }
+
+    const VENDOR_ID: u32 = 0x1002;
+    const DEVICE_ID: u32 = 0x67df;
+    const UUID: [u8; 16] = [0xab; 16];
+
+    // Builds a pipeline cache header, followed by `extra` bytes of data.
+    fn header(size: u32, version: u32, vendor_id: u32, device_id: u32, uuid: &[u8; 16],
+              extra: usize)
+              -> Vec<u8> {
+        let mut data = Vec::new();
+        for &value in &[size, version, vendor_id, device_id] {
+            for shift in 0 .. 4 {
+                data.push((value >> (shift * 8)) as u8);
+            }
+        }
+        data.extend_from_slice(uuid);
+        data.extend((0 .. extra).map(|_| 0u8));
+        data
+    }
+
+    fn valid_header() -> Vec<u8> {
+        header(32, vk::PIPELINE_CACHE_HEADER_VERSION_ONE, VENDOR_ID, DEVICE_ID, &UUID, 16)
+    }
+
+    #[test]
+    fn check_header_valid() {
+        assert!(check_header(&valid_header(), VENDOR_ID, DEVICE_ID, &UUID).is_ok());
+
+        // The header can be longer than the version one header.
+        let data = header(40, vk::PIPELINE_CACHE_HEADER_VERSION_ONE, VENDOR_ID, DEVICE_ID, &UUID,
+                          8);
+        assert!(check_header(&data, VENDOR_ID, DEVICE_ID, &UUID).is_ok());
+    }
+
+    #[test]
+    fn check_header_short_data() {
+        let data = valid_header();
+        match check_header(&data[.. 16], VENDOR_ID, DEVICE_ID, &UUID) {
+            Err(PipelineCacheCreationError::HeaderTooShort) => (),
+            _ => panic!(),
+        }
+
+        match check_header(&[], VENDOR_ID, DEVICE_ID, &UUID) {
+            Err(PipelineCacheCreationError::HeaderTooShort) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn check_header_wrong_length() {
+        let data = header(16, vk::PIPELINE_CACHE_HEADER_VERSION_ONE, VENDOR_ID, DEVICE_ID, &UUID,
+                          16);
+        match check_header(&data, VENDOR_ID, DEVICE_ID, &UUID) {
+            Err(PipelineCacheCreationError::HeaderTooShort) => (),
+            _ => panic!(),
+        }
+
+        // The header is longer than the data.
+        let data = header(0xff, vk::PIPELINE_CACHE_HEADER_VERSION_ONE, VENDOR_ID, DEVICE_ID,
+                          &UUID, 16);
+        match check_header(&data, VENDOR_ID, DEVICE_ID, &UUID) {
+            Err(PipelineCacheCreationError::HeaderTooShort) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn check_header_wrong_version() {
+        let data = header(32, 2, VENDOR_ID, DEVICE_ID, &UUID, 0);
+        match check_header(&data, VENDOR_ID, DEVICE_ID, &UUID) {
+            Err(PipelineCacheCreationError::UnsupportedHeaderVersion { version: 2 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn check_header_wrong_physical_device() {
+        let data = valid_header();
+
+        match check_header(&data, VENDOR_ID + 1, DEVICE_ID, &UUID) {
+            Err(PipelineCacheCreationError::VendorMismatch) => (),
+            _ => panic!(),
+        }
+
+        match check_header(&data, VENDOR_ID, DEVICE_ID + 1, &UUID) {
+            Err(PipelineCacheCreationError::DeviceMismatch) => (),
+            _ => panic!(),
+        }
+
+        let mut uuid = UUID;
+        uuid[15] ^= 1;
+        match check_header(&data, VENDOR_ID, DEVICE_ID, &uuid) {
+            Err(PipelineCacheCreationError::UuidMismatch) => (),
+            _ => panic!(),
+        }
+    }
 
     #[test]
     fn merge_self_forbidden() {
-        let (device, queue) = gfx_dev_and_queue!();
+        let (_, device, _) = mock_dev_and_queue!();
         let pipeline = PipelineCache::empty(device).unwrap();
         assert_should_panic!({
                                  pipeline.merge(&[&pipeline]).unwrap();
                              });
     }
+
+    #[test]
+    fn data_round_trip() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        let data = cache.get_data().unwrap();
+        assert!(PipelineCache::with_data(device.clone(), &data).is_ok());
+
+        // Data from another device is rejected.
+        let mut data = data;
+        data[12] ^= 1;
+        match PipelineCache::with_data(device, &data) {
+            Err(PipelineCacheCreationError::DeviceMismatch) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let path = unique_temp_path("pipeline-cache-save-and-load");
+
+        // A missing file gives an empty cache.
+        PipelineCache::load_or_empty(device.clone(), &path).unwrap();
+
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        cache.save(&path).unwrap();
+        let mut data = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, cache.get_data().unwrap());
+        PipelineCache::load_or_empty(device.clone(), &path).unwrap();
+
+        // Invalid data gives an empty cache as well.
+        File::create(&path).unwrap().write_all(b"garbage").unwrap();
+        PipelineCache::load_or_empty(device, &path).unwrap();
+
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -24,6 +24,7 @@ use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
 use descriptor::pipeline_layout::PipelineLayoutNotSupersetError;
 use descriptor::pipeline_layout::PipelineLayoutSuperset;
 use descriptor::pipeline_layout::PipelineLayoutSys;
+use pipeline::cache::PipelineCache;
 use pipeline::shader::EntryPointAbstract;
 use pipeline::shader::SpecializationConstants;
 
@@ -69,6 +70,29 @@ impl ComputePipeline<()> {
                                                             pipeline_layout)
         }
     }
+
+    /// Same as `new`, but uses a pipeline cache to speed up the creation of the pipeline.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the cache was not created with the same device as `device`.
+    ///
+    pub fn with_cache<Cs>(
+        device: Arc<Device>, shader: &Cs, specialization: &Cs::SpecializationConstants,
+        cache: &PipelineCache)
+        -> Result<ComputePipeline<PipelineLayout<Cs::PipelineLayout>>, ComputePipelineCreationError>
+        where Cs::PipelineLayout: Clone,
+              Cs: EntryPointAbstract
+    {
+        unsafe {
+            let pipeline_layout = shader.layout().clone().build(device.clone())?;
+            ComputePipeline::new_inner(device,
+                                       shader,
+                                       specialization,
+                                       pipeline_layout,
+                                       Some(cache))
+        }
+    }
 }
 
 impl<Pl> ComputePipeline<Pl> {
@@ -93,6 +117,32 @@ impl<Pl> ComputePipeline<Pl> {
         }
     }
 
+    /// Same as `with_pipeline_layout`, but uses a pipeline cache to speed up the creation of the
+    /// pipeline.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the cache was not created with the same device as `device`.
+    ///
+    pub fn with_pipeline_layout_and_cache<Cs>(device: Arc<Device>, shader: &Cs,
+                                              specialization: &Cs::SpecializationConstants,
+                                              pipeline_layout: Pl, cache: &PipelineCache)
+                                              -> Result<ComputePipeline<Pl>,
+                                                        ComputePipelineCreationError>
+        where Cs::PipelineLayout: Clone,
+              Cs: EntryPointAbstract,
+              Pl: PipelineLayoutAbstract
+    {
+        unsafe {
+            PipelineLayoutSuperset::ensure_superset_of(&pipeline_layout, shader.layout())?;
+            ComputePipeline::new_inner(device,
+                                       shader,
+                                       specialization,
+                                       pipeline_layout,
+                                       Some(cache))
+        }
+    }
+
     /// Same as `with_pipeline_layout`, but doesn't check whether the pipeline layout is a
     /// superset of what the shader expects.
     pub unsafe fn with_unchecked_pipeline_layout<Cs>(
@@ -103,6 +153,22 @@ impl<Pl> ComputePipeline<Pl> {
               Cs: EntryPointAbstract,
               Pl: PipelineLayoutAbstract
     {
+        ComputePipeline::new_inner(device, shader, specialization, pipeline_layout, None)
+    }
+
+    // Actual implementation of the constructors.
+    unsafe fn new_inner<Cs>(
+        device: Arc<Device>, shader: &Cs, specialization: &Cs::SpecializationConstants,
+        pipeline_layout: Pl, cache: Option<&PipelineCache>)
+        -> Result<ComputePipeline<Pl>, ComputePipelineCreationError>
+        where Cs::PipelineLayout: Clone,
+              Cs: EntryPointAbstract,
+              Pl: PipelineLayoutAbstract
+    {
+        if let Some(cache) = cache {
+            assert_eq!(device.internal_object(), cache.device().internal_object());
+        }
+
         let vk = device.pointers();
 
         let pipeline = {
@@ -140,7 +206,7 @@ impl<Pl> ComputePipeline<Pl> {
 
             let mut output = mem::uninitialized();
             check_errors(vk.CreateComputePipelines(device.internal_object(),
-                                                   0,
+                                                   cache.map(|c| c.internal_object()).unwrap_or(0),
                                                    1,
                                                    &infos,
                                                    ptr::null(),
@@ -378,9 +444,11 @@ mod tests {
     use descriptor::descriptor::DescriptorDescTy;
     use descriptor::descriptor::ShaderStages;
     use descriptor::descriptor_set::PersistentDescriptorSet;
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
     use descriptor::pipeline_layout::PipelineLayoutDesc;
     use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
     use pipeline::ComputePipeline;
+    use pipeline::cache::PipelineCache;
     use pipeline::shader::ShaderModule;
     use pipeline::shader::SpecializationConstants;
     use pipeline::shader::SpecializationMapEntry;
@@ -388,10 +456,41 @@ mod tests {
     use std::sync::Arc;
     use sync::GpuFuture;
     use sync::now;
+    use testing::Call;
+
+    use VulkanObject;
 
     // TODO: test for basic creation
     // TODO: test for pipeline layout error
 
+    #[test]
+    fn with_cache() {
+        let (loader, device, _) = mock_dev_and_queue!();
+
+        // The mock driver doesn't look at the content of shader modules.
+        let module = unsafe { ShaderModule::new(device.clone(), &[0; 8]).unwrap() };
+        let shader = unsafe {
+            static NAME: [u8; 5] = [109, 97, 105, 110, 0]; // "main"
+            module.compute_entry_point::<(), _>(CStr::from_ptr(NAME.as_ptr() as *const _),
+                                                EmptyPipelineDesc)
+        };
+
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        loader.take_calls();
+        let _pipeline = ComputePipeline::with_cache(device.clone(), &shader, &(), &cache).unwrap();
+
+        let used_cache = loader
+            .calls()
+            .iter()
+            .filter_map(|call| match *call {
+                            Call::CreateComputePipelines { cache, .. } => Some(cache),
+                            _ => None,
+                        })
+            .next()
+            .unwrap();
+        assert_eq!(used_cache, cache.internal_object());
+    }
+
     #[test]
     fn spec_constants() {
         // This test checks whether specialization constants work.
@@ -172,6 +172,18 @@ pub enum StencilOp {
     DecrementAndWrap = vk::STENCIL_OP_DECREMENT_AND_WRAP,
 }
 
+/// Specifies which faces a dynamic stencil state applies to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u32)]
+pub enum StencilFaceFlags {
+    /// The state of points, lines and triangles whose front is facing the user.
+    Front = vk::STENCIL_FACE_FRONT_BIT,
+    /// The state of triangles whose back is facing the user.
+    Back = vk::STENCIL_FACE_BACK_BIT,
+    /// Both of the above.
+    FrontAndBack = vk::STENCIL_FRONT_AND_BACK,
+}
+
 /// Allows you to ask the GPU to exclude fragments that are outside of a certain range.
 #[derive(Debug, Clone, PartialEq)]
 pub enum DepthBounds {
@@ -13,26 +13,32 @@
 
 use smallvec::SmallVec;
 use std::mem;
+use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
 use std::u32;
 
 use descriptor::pipeline_layout::PipelineLayoutAbstract;
 use device::Device;
+use device::DeviceOwned;
+use framebuffer::ATTACHMENT_UNUSED;
 use framebuffer::RenderPassAbstract;
 use framebuffer::Subpass;
 use pipeline::blend::AttachmentBlend;
 use pipeline::blend::AttachmentsBlend;
 use pipeline::blend::Blend;
 use pipeline::blend::LogicOp;
+use pipeline::cache::PipelineCache;
 use pipeline::depth_stencil::Compare;
 use pipeline::depth_stencil::DepthBounds;
 use pipeline::depth_stencil::DepthStencil;
 use pipeline::graphics_pipeline::GraphicsPipeline;
+use pipeline::graphics_pipeline::GraphicsPipelineAbstract;
 use pipeline::graphics_pipeline::GraphicsPipelineCreationError;
 use pipeline::graphics_pipeline::Inner as GraphicsPipelineInner;
 use pipeline::input_assembly::PrimitiveTopology;
 use pipeline::raster::CullMode;
+use pipeline::raster::DepthBias;
 use pipeline::raster::DepthBiasControl;
 use pipeline::raster::FrontFace;
 use pipeline::raster::PolygonMode;
@@ -70,11 +76,17 @@ pub struct GraphicsPipelineBuilder<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss,
     geometry_shader: Option<(Gs, Gss)>,
     viewport: Option<ViewportsState>,
     raster: Rasterization,
+    // Note: a `rasterizationSamples` of 0 means that the sample count is the number of samples of
+    // the subpass.
     multisample: vk::PipelineMultisampleStateCreateInfo,
+    // Storage for the `pSampleMask` pointer of `multisample`.
+    sample_mask: Option<[u32; 2]>,
     fragment_shader: Option<(Fs, Fss)>,
     depth_stencil: DepthStencil,
     blend: Blend,
     render_pass: Option<Subpass<Rp>>,
+    allow_derivatives: bool,
+    base_pipeline: Option<Arc<GraphicsPipelineAbstract + Send + Sync>>,
 }
 
 // Additional parameters if tessellation is used.
@@ -117,10 +129,13 @@ impl
                     sType: vk::STRUCTURE_TYPE_PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
                     ..mem::zeroed()
                 },
+                sample_mask: None,
                 fragment_shader: None,
                 depth_stencil: DepthStencil::disabled(),
                 blend: Blend::pass_through(),
                 render_pass: None,
+                allow_derivatives: false,
+                base_pipeline: None,
             }
         }
     }
@@ -158,7 +173,22 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
     pub fn build(self, device: Arc<Device>)
                  -> Result<GraphicsPipeline<Vdef, Box<PipelineLayoutAbstract + Send + Sync>, Rp>,
                            GraphicsPipelineCreationError> {
-        self.with_auto_layout(device, &[])
+        self.with_auto_layout_impl(device, &[], None)
+    }
+
+    /// Builds the graphics pipeline, using an inferred pipeline layout and a pipeline cache.
+    ///
+    /// The Vulkan implementation will look in the cache for an existing entry, or add one if it
+    /// doesn't exist. This can speed up the creation of pipelines considerably.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the cache was not created with the same device as `device`.
+    ///
+    pub fn build_with_cache(self, device: Arc<Device>, cache: &PipelineCache)
+                            -> Result<GraphicsPipeline<Vdef, Box<PipelineLayoutAbstract + Send + Sync>, Rp>,
+                                      GraphicsPipelineCreationError> {
+        self.with_auto_layout_impl(device, &[], Some(cache))
     }
 
     /// Builds the graphics pipeline, using an inferred pipeline layout with some dynamic buffers.
@@ -168,6 +198,15 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
     pub fn with_auto_layout(self, device: Arc<Device>, dynamic_buffers: &[(usize, usize)])
                             -> Result<GraphicsPipeline<Vdef, Box<PipelineLayoutAbstract + Send + Sync>, Rp>,
                                       GraphicsPipelineCreationError>
+    {
+        self.with_auto_layout_impl(device, dynamic_buffers, None)
+    }
+
+    // Implementation of the various constructors that infer the pipeline layout.
+    fn with_auto_layout_impl(self, device: Arc<Device>, dynamic_buffers: &[(usize, usize)],
+                             cache: Option<&PipelineCache>)
+                             -> Result<GraphicsPipeline<Vdef, Box<PipelineLayoutAbstract + Send + Sync>, Rp>,
+                                       GraphicsPipelineCreationError>
     {
         let pipeline_layout;
 
@@ -297,7 +336,7 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             }
         }
 
-        self.with_pipeline_layout(device, pipeline_layout)
+        self.with_pipeline_layout_impl(device, pipeline_layout, cache)
     }
 
     /// Builds the graphics pipeline.
@@ -305,13 +344,57 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
     /// Does the same as `build`, except that `build` automatically builds the pipeline layout
     /// object corresponding to the union of your shaders while this function allows you to specify
     /// the pipeline layout.
-    pub fn with_pipeline_layout<Pl>(mut self, device: Arc<Device>, pipeline_layout: Pl)
+    pub fn with_pipeline_layout<Pl>(self, device: Arc<Device>, pipeline_layout: Pl)
                                     -> Result<GraphicsPipeline<Vdef, Pl, Rp>,
                                               GraphicsPipelineCreationError>
         where Pl: PipelineLayoutAbstract
+    {
+        self.with_pipeline_layout_impl(device, pipeline_layout, None)
+    }
+
+    /// Builds the graphics pipeline with a specific pipeline layout and a pipeline cache.
+    ///
+    /// Does the same as `with_pipeline_layout`, except that the pipeline cache is used to speed
+    /// up the creation of the pipeline. See `build_with_cache`.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the cache was not created with the same device as `device`.
+    ///
+    pub fn with_pipeline_layout_and_cache<Pl>(self, device: Arc<Device>, pipeline_layout: Pl,
+                                              cache: &PipelineCache)
+                                              -> Result<GraphicsPipeline<Vdef, Pl, Rp>,
+                                                        GraphicsPipelineCreationError>
+        where Pl: PipelineLayoutAbstract
+    {
+        self.with_pipeline_layout_impl(device, pipeline_layout, Some(cache))
+    }
+
+    // Implementation of the various constructors.
+    fn with_pipeline_layout_impl<Pl>(mut self, device: Arc<Device>, pipeline_layout: Pl,
+                                     cache: Option<&PipelineCache>)
+                                     -> Result<GraphicsPipeline<Vdef, Pl, Rp>,
+                                               GraphicsPipelineCreationError>
+        where Pl: PipelineLayoutAbstract
     {
         // TODO: return errors instead of panicking if missing param
 
+        if let Some(cache) = cache {
+            assert_eq!(device.internal_object(), cache.device().internal_object());
+        }
+
+        let mut flags = 0;
+        if self.allow_derivatives {
+            flags |= vk::PIPELINE_CREATE_ALLOW_DERIVATIVES_BIT;
+        }
+        if let Some(ref base) = self.base_pipeline {
+            assert_eq!(device.internal_object(), base.device().internal_object());
+            if !base.allows_derivatives() {
+                return Err(GraphicsPipelineCreationError::BasePipelineDoesntAllowDerivatives);
+            }
+            flags |= vk::PIPELINE_CREATE_DERIVATIVE_BIT;
+        }
+
         let vk = device.pointers();
 
         // Checking that the pipeline layout matches the shader stages.
@@ -830,11 +913,58 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             lineWidth: self.raster.line_width.unwrap_or(1.0),
         };
 
-        self.multisample.rasterizationSamples = self.render_pass
-            .as_ref()
-            .unwrap()
-            .num_samples()
-            .unwrap_or(1);
+        {
+            let subpass = self.render_pass.as_ref().unwrap();
+            let subpass_samples = subpass.num_samples();
+
+            if self.multisample.rasterizationSamples == 0 {
+                self.multisample.rasterizationSamples = subpass_samples.unwrap_or(1);
+            } else if let Some(expected) = subpass_samples {
+                if self.multisample.rasterizationSamples != expected {
+                    return Err(GraphicsPipelineCreationError::SampleCountMismatch {
+                                   expected: expected,
+                                   obtained: self.multisample.rasterizationSamples,
+                               });
+                }
+            }
+
+            // Color slots that are `ATTACHMENT_UNUSED` don't have a number of samples.
+            let has_color = subpass
+                .render_pass()
+                .subpass_desc(subpass.index() as usize)
+                .unwrap()
+                .color_attachments
+                .iter()
+                .any(|&(atch, _)| atch != ATTACHMENT_UNUSED);
+            let (has_depth, has_stencil) = (subpass.has_depth(), subpass.has_stencil());
+
+            // The sample counts flags of the limits use the same values as the sample counts.
+            let limits = device.physical_device().limits();
+            let supported = if has_color || has_depth || has_stencil {
+                let mut supported = 0xffffffff;
+                if has_color {
+                    supported &= limits.framebuffer_color_sample_counts();
+                }
+                if has_depth {
+                    supported &= limits.framebuffer_depth_sample_counts();
+                }
+                if has_stencil {
+                    supported &= limits.framebuffer_stencil_sample_counts();
+                }
+                supported
+            } else {
+                limits.framebuffer_no_attachments_sample_counts()
+            };
+
+            if supported & self.multisample.rasterizationSamples == 0 {
+                return Err(GraphicsPipelineCreationError::SampleCountNotSupported);
+            }
+        }
+
+        self.multisample.pSampleMask = match self.sample_mask {
+            Some(ref mask) => mask.as_ptr(),
+            None => ptr::null(),
+        };
         if self.multisample.sampleShadingEnable != vk::FALSE {
             debug_assert!(self.multisample.minSampleShading >= 0.0 &&
                               self.multisample.minSampleShading <= 1.0);
@@ -1042,7 +1172,7 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             let infos = vk::GraphicsPipelineCreateInfo {
                 sType: vk::STRUCTURE_TYPE_GRAPHICS_PIPELINE_CREATE_INFO,
                 pNext: ptr::null(),
-                flags: 0, // TODO: some flags are available but none are critical
+                flags: flags,
                 stageCount: stages.len() as u32,
                 pStages: stages.as_ptr(),
                 pVertexInputState: &vertex_input_state,
@@ -1068,13 +1198,16 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
                     .inner()
                     .internal_object(),
                 subpass: self.render_pass.as_ref().unwrap().index(),
-                basePipelineHandle: 0, // TODO:
-                basePipelineIndex: -1, // TODO:
+                basePipelineHandle: self.base_pipeline
+                    .as_ref()
+                    .map(|base| GraphicsPipelineAbstract::inner(&**base).internal_object())
+                    .unwrap_or(0),
+                basePipelineIndex: -1,
             };
 
             let mut output = mem::uninitialized();
             check_errors(vk.CreateGraphicsPipelines(device.internal_object(),
-                                                    0,
+                                                    cache.map(|c| c.internal_object()).unwrap_or(0),
                                                     1,
                                                     &infos,
                                                     ptr::null(),
@@ -1107,15 +1240,39 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
                dynamic_blend_constants: self.blend.blend_constants.is_none(),
 
                num_viewports: self.viewport.as_ref().unwrap().num_viewports(),
+
+               allows_derivatives: self.allow_derivatives,
            })
     }
-
-    // TODO: add build_with_cache method
 }
 
 impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
     GraphicsPipelineBuilder<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp> {
-    // TODO: add pipeline derivate system
+    /// Allows other pipelines to be created as derivatives of the pipeline being built. See
+    /// `derive_from`.
+    ///
+    /// Creating derivatives of a pipeline can be faster than creating them from scratch, but the
+    /// pipeline itself may be slightly slower to create.
+    #[inline]
+    pub fn allow_derivatives(mut self) -> Self {
+        self.allow_derivatives = true;
+        self
+    }
+
+    /// Creates the pipeline as a derivative of `base`.
+    ///
+    /// The Vulkan implementation can reuse parts of `base` in order to speed up the creation of
+    /// the new pipeline. Pipelines that share most of their states are good candidates.
+    ///
+    /// Building the pipeline will return an error if `base` was not built with
+    /// `allow_derivatives`, and will panic if `base` doesn't belong to the same device.
+    #[inline]
+    pub fn derive_from<P>(mut self, base: Arc<P>) -> Self
+        where P: GraphicsPipelineAbstract + Send + Sync + 'static
+    {
+        self.base_pipeline = Some(base as Arc<_>);
+        self
+    }
 
     /// Sets the vertex input.
     #[inline]
@@ -1132,10 +1289,13 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             viewport: self.viewport,
             raster: self.raster,
             multisample: self.multisample,
+            sample_mask: self.sample_mask,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
         }
     }
 
@@ -1179,10 +1339,13 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             viewport: self.viewport,
             raster: self.raster,
             multisample: self.multisample,
+            sample_mask: self.sample_mask,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
         }
     }
 
@@ -1336,10 +1499,13 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             viewport: self.viewport,
             raster: self.raster,
             multisample: self.multisample,
+            sample_mask: self.sample_mask,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
         }
     }
 
@@ -1369,10 +1535,13 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             viewport: self.viewport,
             raster: self.raster,
             multisample: self.multisample,
+            sample_mask: self.sample_mask,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
         }
     }
 
@@ -1547,7 +1716,38 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
         self
     }
 
-    // TODO: missing DepthBiasControl
+    /// Enables the depth bias. The depth value of each fragment will be offset by
+    /// `constant_factor` plus `slope_factor` multiplied by the slope of the polygon. If `clamp`
+    /// is not 0.0, the bias is then clamped to `clamp`.
+    ///
+    /// Using a value of `clamp` different from 0.0 requires the `depth_bias_clamp` feature to be
+    /// enabled on the device.
+    ///
+    /// The depth bias is disabled by default.
+    #[inline]
+    pub fn depth_bias(mut self, constant_factor: f32, clamp: f32, slope_factor: f32) -> Self {
+        self.raster.depth_bias = DepthBiasControl::Static(DepthBias {
+                                                              constant_factor: constant_factor,
+                                                              clamp: clamp,
+                                                              slope_factor: slope_factor,
+                                                          });
+        self
+    }
+
+    /// Enables the depth bias and sets it as dynamic, which means that you will need to set its
+    /// value when drawing.
+    #[inline]
+    pub fn depth_bias_dynamic(mut self) -> Self {
+        self.raster.depth_bias = DepthBiasControl::Dynamic;
+        self
+    }
+
+    /// Disables the depth bias. This is the default.
+    #[inline]
+    pub fn depth_bias_disabled(mut self) -> Self {
+        self.raster.depth_bias = DepthBiasControl::Disabled;
+        self
+    }
 
     /// Disables sample shading. The fragment shader will only be run once per fragment (ie. per
     /// pixel) and not once by sample. The output will then be copied in all of the covered
@@ -1617,7 +1817,40 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
         self
     }
 
-    // TODO: rasterizationSamples and pSampleMask
+    /// Sets the number of samples per pixel used when rasterizing.
+    ///
+    /// If the subpass has color or depth-stencil attachments, this value must be equal to their
+    /// number of samples. If the subpass has no attachment, this value must be supported by the
+    /// `framebuffer_no_attachments_sample_counts` limit of the device. By default, the number of
+    /// samples of the subpass is used, or 1 if it has no attachment.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `count` is not a power of two between 1 and 64.
+    ///
+    #[inline]
+    pub fn sample_count(mut self, count: u32) -> Self {
+        assert!(count.is_power_of_two() && count <= 64);
+        self.multisample.rasterizationSamples = count;
+        self
+    }
+
+    /// Sets the sample mask. Bit `n` of the mask corresponds to sample `n` of each pixel. Samples
+    /// whose bit is 0 are not written, as if they weren't covered by the primitive.
+    ///
+    /// By default all the samples are enabled.
+    #[inline]
+    pub fn sample_mask(mut self, mask: u64) -> Self {
+        self.sample_mask = Some([mask as u32, (mask >> 32) as u32]);
+        self
+    }
+
+    /// Enables all the samples. This is the default.
+    #[inline]
+    pub fn sample_mask_disabled(mut self) -> Self {
+        self.sample_mask = None;
+        self
+    }
 
     /// Sets the fragment shader to use.
     ///
@@ -1640,10 +1873,13 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             viewport: self.viewport,
             raster: self.raster,
             multisample: self.multisample,
+            sample_mask: self.sample_mask,
             fragment_shader: Some((shader, specialization_constants)),
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: self.render_pass,
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
         }
     }
 
@@ -1681,7 +1917,62 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
         self
     }
 
-    // TODO: missing tons of depth-stencil stuff
+    /// Enables the depth bounds test. Fragments whose depth in the depth buffer is outside of
+    /// `range` will be discarded.
+    ///
+    /// The depth bounds test requires the `depth_bounds` feature to be enabled on the device.
+    /// It is disabled by default.
+    #[inline]
+    pub fn depth_bounds(mut self, range: Range<f32>) -> Self {
+        self.depth_stencil.depth_bounds_test = DepthBounds::Fixed(range);
+        self
+    }
+
+    /// Enables the depth bounds test and sets its range as dynamic, which means that you will
+    /// need to set it when drawing.
+    ///
+    /// The depth bounds test requires the `depth_bounds` feature to be enabled on the device.
+    #[inline]
+    pub fn depth_bounds_dynamic(mut self) -> Self {
+        self.depth_stencil.depth_bounds_test = DepthBounds::Dynamic;
+        self
+    }
+
+    /// Disables the depth bounds test. This is the default.
+    #[inline]
+    pub fn depth_bounds_disabled(mut self) -> Self {
+        self.depth_stencil.depth_bounds_test = DepthBounds::Disabled;
+        self
+    }
+
+    /// Sets the stencil compare masks of both faces as dynamic, which means that you will need to
+    /// set them when drawing.
+    #[inline]
+    pub fn stencil_compare_mask_dynamic(mut self) -> Self {
+        self.depth_stencil.stencil_front.compare_mask = None;
+        self.depth_stencil.stencil_back.compare_mask = None;
+        self
+    }
+
+    /// Sets the stencil write masks of both faces as dynamic, which means that you will need to
+    /// set them when drawing.
+    #[inline]
+    pub fn stencil_write_mask_dynamic(mut self) -> Self {
+        self.depth_stencil.stencil_front.write_mask = None;
+        self.depth_stencil.stencil_back.write_mask = None;
+        self
+    }
+
+    /// Sets the stencil references of both faces as dynamic, which means that you will need to
+    /// set them when drawing.
+    #[inline]
+    pub fn stencil_reference_dynamic(mut self) -> Self {
+        self.depth_stencil.stencil_front.reference = None;
+        self.depth_stencil.stencil_back.reference = None;
+        self
+    }
+
+    // TODO: missing stencil operations
 
 
     #[inline]
@@ -1756,10 +2047,13 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
             viewport: self.viewport,
             raster: self.raster,
             multisample: self.multisample,
+            sample_mask: self.sample_mask,
             fragment_shader: self.fragment_shader,
             depth_stencil: self.depth_stencil,
             blend: self.blend,
             render_pass: Some(subpass),
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline,
         }
     }
 }
@@ -1800,10 +2094,228 @@ impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp> Clone
                 alphaToCoverageEnable: self.multisample.alphaToCoverageEnable,
                 alphaToOneEnable: self.multisample.alphaToOneEnable,
             },
+            sample_mask: self.sample_mask,
             fragment_shader: self.fragment_shader.clone(),
             depth_stencil: self.depth_stencil.clone(),
             blend: self.blend.clone(),
             render_pass: self.render_pass.clone(),
+            allow_derivatives: self.allow_derivatives,
+            base_pipeline: self.base_pipeline.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+    use std::sync::Arc;
+
+    use descriptor::pipeline_layout::EmptyPipelineDesc;
+    use format::Format;
+    use framebuffer::EmptySinglePassRenderPassDesc;
+    use framebuffer::RenderPassDesc;
+    use framebuffer::Subpass;
+    use pipeline::GraphicsPipeline;
+    use pipeline::GraphicsPipelineAbstract;
+    use pipeline::GraphicsPipelineCreationError;
+    use pipeline::cache::PipelineCache;
+    use pipeline::shader::EmptyShaderInterfaceDef;
+    use pipeline::shader::GraphicsShaderType;
+    use pipeline::shader::ShaderModule;
+    use testing::Call;
+    use testing::MockLoader;
+    use vk;
+
+    use VulkanObject;
+
+    // Returns the `flags`, `cache` and `base_pipelines` of the `vkCreateGraphicsPipelines`
+    // calls that have been recorded since the last call to this function.
+    fn created_pipelines(loader: &MockLoader) -> Vec<(u32, u64, (u64, i32))> {
+        loader
+            .take_calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                            Call::CreateGraphicsPipelines {
+                                cache,
+                                flags,
+                                base_pipelines,
+                                ..
+                            } => Some((flags[0], cache, base_pipelines[0])),
+                            _ => None,
+                        })
+            .collect()
+    }
+
+    // Returns the number of samples and the sample mask of the `vkCreateGraphicsPipelines` calls
+    // that have been recorded since the last call to this function.
+    fn created_multisample(loader: &MockLoader) -> Vec<(u32, Option<u64>)> {
+        loader
+            .take_calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                            Call::CreateGraphicsPipelines { multisample, .. } => {
+                                Some(multisample[0])
+                            },
+                            _ => None,
+                        })
+            .collect()
+    }
+
+    // Starts building a pipeline whose states are all set, with the shaders of `$module` and the
+    // single subpass of `$rp`. The mock driver doesn't look at the content of shader modules.
+    macro_rules! builder {
+        ($module:expr, $rp:expr) => ({
+            let name = CStr::from_bytes_with_nul(b"main\0").unwrap();
+            let vs = unsafe {
+                $module.graphics_entry_point::<(), _, _, _>(name,
+                                                            EmptyShaderInterfaceDef,
+                                                            EmptyShaderInterfaceDef,
+                                                            EmptyPipelineDesc,
+                                                            GraphicsShaderType::Vertex)
+            };
+            let fs = unsafe {
+                $module.graphics_entry_point::<(), _, _, _>(name,
+                                                            EmptyShaderInterfaceDef,
+                                                            EmptyShaderInterfaceDef,
+                                                            EmptyPipelineDesc,
+                                                            GraphicsShaderType::Fragment)
+            };
+
+            GraphicsPipeline::start()
+                .vertex_input_single_buffer::<()>()
+                .vertex_shader(vs, ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fs, ())
+                .render_pass(Subpass::from($rp.clone(), 0).unwrap())
+        });
+    }
+
+    #[test]
+    fn with_cache() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &[0; 8]).unwrap() };
+        let rp = Arc::new(EmptySinglePassRenderPassDesc.build_render_pass(device.clone()).unwrap());
+
+        let cache = PipelineCache::empty(device.clone()).unwrap();
+        loader.take_calls();
+        let _pipeline = builder!(module, rp)
+            .build_with_cache(device.clone(), &cache)
+            .unwrap();
+
+        let created = created_pipelines(&loader);
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].1, cache.internal_object());
+
+        let _pipeline = builder!(module, rp).build(device.clone()).unwrap();
+        assert_eq!(created_pipelines(&loader)[0].1, 0);
+    }
+
+    #[test]
+    fn derivatives() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &[0; 8]).unwrap() };
+        let rp = Arc::new(EmptySinglePassRenderPassDesc.build_render_pass(device.clone()).unwrap());
+
+        loader.take_calls();
+        let base = Arc::new(builder!(module, rp)
+                                .allow_derivatives()
+                                .build(device.clone())
+                                .unwrap());
+        assert!(base.allows_derivatives());
+        assert_eq!(created_pipelines(&loader),
+                   vec![(vk::PIPELINE_CREATE_ALLOW_DERIVATIVES_BIT, 0, (0, -1))]);
+
+        let _derived = builder!(module, rp)
+            .derive_from(base.clone())
+            .build(device.clone())
+            .unwrap();
+        let base_handle = GraphicsPipelineAbstract::inner(&*base).internal_object();
+        assert_eq!(created_pipelines(&loader),
+                   vec![(vk::PIPELINE_CREATE_DERIVATIVE_BIT, 0, (base_handle, -1))]);
+    }
+
+    #[test]
+    fn base_doesnt_allow_derivatives() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &[0; 8]).unwrap() };
+        let rp = Arc::new(EmptySinglePassRenderPassDesc.build_render_pass(device.clone()).unwrap());
+
+        let base = Arc::new(builder!(module, rp).build(device.clone()).unwrap());
+        assert!(!base.allows_derivatives());
+
+        loader.take_calls();
+        match builder!(module, rp).derive_from(base).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::BasePipelineDoesntAllowDerivatives) => (),
+            _ => panic!(),
+        }
+        assert!(created_pipelines(&loader).is_empty());
+    }
+
+    #[test]
+    fn sample_count_and_mask() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &[0; 8]).unwrap() };
+        let rp = Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 4, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap());
+
+        loader.take_calls();
+        let _pipeline = builder!(module, rp).build(device.clone()).unwrap();
+        assert_eq!(created_multisample(&loader), vec![(4, None)]);
+
+        let _pipeline = builder!(module, rp)
+            .sample_count(4)
+            .sample_mask(0b0101)
+            .build(device.clone())
+            .unwrap();
+        assert_eq!(created_multisample(&loader), vec![(4, Some(0b0101))]);
+
+        let _pipeline = builder!(module, rp)
+            .sample_mask(0b0101)
+            .sample_mask_disabled()
+            .build(device.clone())
+            .unwrap();
+        assert_eq!(created_multisample(&loader), vec![(4, None)]);
+    }
+
+    #[test]
+    fn sample_count_mismatch() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &[0; 8]).unwrap() };
+        let rp = Arc::new(single_pass_renderpass!(device.clone(),
+            attachments: {
+                a: { load: Clear, store: Store, format: Format::R8G8B8A8Unorm, samples: 4, }
+            },
+            pass: { color: [a], depth_stencil: {} }
+        ).unwrap());
+
+        loader.take_calls();
+        match builder!(module, rp).sample_count(2).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::SampleCountMismatch {
+                    expected: 4,
+                    obtained: 2,
+                }) => (),
+            _ => panic!(),
+        }
+        assert!(created_pipelines(&loader).is_empty());
+    }
+
+    #[test]
+    fn sample_count_not_supported() {
+        let (loader, device, _) = mock_dev_and_queue!();
+        let module = unsafe { ShaderModule::new(device.clone(), &[0; 8]).unwrap() };
+        let rp = Arc::new(EmptySinglePassRenderPassDesc.build_render_pass(device.clone()).unwrap());
+
+        // The mock device supports up to 8 samples when the subpass has no attachment.
+        loader.take_calls();
+        match builder!(module, rp).sample_count(16).build(device.clone()) {
+            Err(GraphicsPipelineCreationError::SampleCountNotSupported) => (),
+            _ => panic!(),
         }
+        assert!(created_pipelines(&loader).is_empty());
     }
 }
@@ -182,6 +182,22 @@ pub enum GraphicsPipelineCreationError {
 
     /// The `alpha_to_one` feature must be enabled in order to use alpha-to-one.
     AlphaToOneFeatureNotEnabled,
+
+    /// The sample count of the pipeline doesn't match the number of samples of the attachments
+    /// of the subpass.
+    SampleCountMismatch {
+        /// Number of samples of the attachments of the subpass.
+        expected: u32,
+        /// Sample count of the pipeline.
+        obtained: u32,
+    },
+
+    /// The sample count of the pipeline isn't supported by the device for the attachments of the
+    /// subpass.
+    SampleCountNotSupported,
+
+    /// The base pipeline passed to `derive_from` was not built with `allow_derivatives`.
+    BasePipelineDoesntAllowDerivatives,
 }
 
 impl error::Error for GraphicsPipelineCreationError {
@@ -319,6 +335,17 @@ impl error::Error for GraphicsPipelineCreationError {
             GraphicsPipelineCreationError::AlphaToOneFeatureNotEnabled => {
                 "the `alpha_to_one` feature must be enabled in order to use alpha-to-one"
             },
+            GraphicsPipelineCreationError::SampleCountMismatch { .. } => {
+                "the sample count of the pipeline doesn't match the number of samples of the \
+                 attachments of the subpass"
+            },
+            GraphicsPipelineCreationError::SampleCountNotSupported => {
+                "the sample count of the pipeline isn't supported by the device for the \
+                 attachments of the subpass"
+            },
+            GraphicsPipelineCreationError::BasePipelineDoesntAllowDerivatives => {
+                "the base pipeline was not built with `allow_derivatives`"
+            },
         }
     }
 
@@ -13,11 +13,16 @@
 //! code and can contain one or more entry points. Note that for the moment the official
 //! GLSL-to-SPIR-V compiler does not support multiple entry points.
 //!
-//! The vulkano library does not provide any functionnality that checks and introspects the SPIR-V
-//! code, therefore the whole shader-related API is unsafe. You are encouraged to use the
-//! `vulkano-shaders` crate that will generate Rust code that wraps around vulkano's shaders API.
+//! Vulkano doesn't validate SPIR-V code, therefore most of the shader-related API is unsafe. You
+//! are encouraged to use the `vulkano-shaders` crate that will generate Rust code that wraps
+//! around vulkano's shaders API.
+//!
+//! If the SPIR-V code is only known at runtime, you can instead use `ShaderModule::reflect`,
+//! which extracts the interface, descriptors and push constants of each entry point from the
+//! SPIR-V code and returns entry points that can be used without any further `unsafe` code.
 
 use std::borrow::Cow;
+use std::cmp;
 use std::error;
 use std::ffi::CStr;
 use std::fmt;
@@ -28,6 +33,7 @@ use std::mem;
 use std::ops::Range;
 use std::ptr;
 use std::sync::Arc;
+use std::vec::IntoIter as VecIntoIter;
 
 use descriptor::pipeline_layout::EmptyPipelineDesc;
 use descriptor::pipeline_layout::PipelineLayoutDesc;
@@ -40,6 +46,14 @@ use check_errors;
 use device::Device;
 use vk;
 
+pub use self::reflect::EntryPointType;
+pub use self::reflect::ReflectedEntryPoint;
+pub use self::reflect::ReflectedShader;
+pub use self::reflect::ShaderReflectionError;
+
+mod reflect;
+mod spirv;
+
 /// Contains SPIR-V code with one or more entry points.
 ///
 /// Note that it is advised to wrap around a `ShaderModule` with a struct that is different for
@@ -79,6 +93,25 @@ impl ShaderModule {
         Self::from_ptr(device, spirv.as_ptr(), spirv.len() * mem::size_of::<u32>())
     }
 
+    /// Builds a new shader module from SPIR-V bytes, and extracts the description of its entry
+    /// points from the SPIR-V code.
+    ///
+    /// The bytes can be in either endianness. The entry points of the returned `ReflectedShader`
+    /// can then be used to build pipelines without having to describe their interface manually.
+    ///
+    /// # Safety
+    ///
+    /// - The SPIR-V code is only parsed in order to extract the interface of the entry points.
+    ///   It is not otherwise validated.
+    /// - The SPIR-V code may require some features that are not enabled. This isn't checked by
+    ///   this function either.
+    ///
+    pub unsafe fn reflect(device: Arc<Device>, spirv: &[u8])
+                          -> Result<ReflectedShader, ShaderReflectionError> {
+        let doc = spirv::parse_spirv(spirv)?;
+        reflect::reflect(device, &doc)
+    }
+
     /// Builds a new shader module from SPIR-V.
     ///
     /// # Safety
@@ -444,6 +477,98 @@ unsafe impl ShaderInterfaceDef for EmptyShaderInterfaceDef {
     }
 }
 
+/// Description of a shader interface that is built at runtime.
+#[derive(Debug, Clone)]
+pub struct RuntimeShaderInterfaceDef {
+    entries: Vec<ShaderInterfaceDefEntry>,
+}
+
+impl RuntimeShaderInterfaceDef {
+    /// Builds a new `RuntimeShaderInterfaceDef` from a list of entries.
+    ///
+    /// Returns an error if two entries share a location or if the format of an entry is larger
+    /// than 128 bits.
+    pub fn new<I>(entries: I) -> Result<RuntimeShaderInterfaceDef, RuntimeShaderInterfaceDefError>
+        where I: IntoIterator<Item = ShaderInterfaceDefEntry>
+    {
+        let entries: Vec<_> = entries.into_iter().collect();
+
+        for (a_id, a) in entries.iter().enumerate() {
+            match a.format.size() {
+                Some(size) if size <= 16 => (),
+                _ => return Err(RuntimeShaderInterfaceDefError::FormatTooLarge {
+                                    location: a.location.start,
+                                    format: a.format,
+                                }),
+            }
+
+            for b in entries.iter().skip(a_id + 1) {
+                if a.location.start < b.location.end && b.location.start < a.location.end {
+                    return Err(RuntimeShaderInterfaceDefError::OverlappingLocations {
+                                   location: cmp::max(a.location.start, b.location.start),
+                               });
+                }
+            }
+        }
+
+        Ok(RuntimeShaderInterfaceDef { entries: entries })
+    }
+
+    /// Builds an empty `RuntimeShaderInterfaceDef`.
+    #[inline]
+    pub fn empty() -> RuntimeShaderInterfaceDef {
+        RuntimeShaderInterfaceDef { entries: Vec::new() }
+    }
+}
+
+unsafe impl ShaderInterfaceDef for RuntimeShaderInterfaceDef {
+    type Iter = VecIntoIter<ShaderInterfaceDefEntry>;
+
+    #[inline]
+    fn elements(&self) -> Self::Iter {
+        self.entries.clone().into_iter()
+    }
+}
+
+/// Error that can happen when building a `RuntimeShaderInterfaceDef`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuntimeShaderInterfaceDefError {
+    /// Two entries share a location.
+    OverlappingLocations {
+        /// First location that is shared.
+        location: u32,
+    },
+
+    /// The format of an entry is larger than 128 bits.
+    FormatTooLarge {
+        /// First location of the entry.
+        location: u32,
+        /// Format of the entry.
+        format: Format,
+    },
+}
+
+impl error::Error for RuntimeShaderInterfaceDefError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            RuntimeShaderInterfaceDefError::OverlappingLocations { .. } => {
+                "two entries share a location"
+            },
+            RuntimeShaderInterfaceDefError::FormatTooLarge { .. } => {
+                "the format of an entry is larger than 128 bits"
+            },
+        }
+    }
+}
+
+impl fmt::Display for RuntimeShaderInterfaceDefError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
 /// Extension trait for `ShaderInterfaceDef` that specifies that the interface is potentially
 /// compatible with another one.
 pub unsafe trait ShaderInterfaceDefMatch<I>: ShaderInterfaceDef
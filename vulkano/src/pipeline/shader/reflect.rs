@@ -0,0 +1,1348 @@
+// Copyright (c) 2018 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::cmp;
+use std::error;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::fmt;
+use std::sync::Arc;
+use std::u32;
+use std::usize;
+
+use descriptor::descriptor::DescriptorBufferDesc;
+use descriptor::descriptor::DescriptorDesc;
+use descriptor::descriptor::DescriptorDescTy;
+use descriptor::descriptor::DescriptorImageDesc;
+use descriptor::descriptor::DescriptorImageDescArray;
+use descriptor::descriptor::DescriptorImageDescDimensions;
+use descriptor::descriptor::ShaderStages;
+use descriptor::pipeline_layout::PipelineLayoutDescPcRange;
+use descriptor::pipeline_layout::RuntimePipelineDesc;
+use device::Device;
+use format::Format;
+use pipeline::shader::ComputeEntryPoint;
+use pipeline::shader::GeometryShaderExecutionMode;
+use pipeline::shader::GraphicsEntryPoint;
+use pipeline::shader::GraphicsShaderType;
+use pipeline::shader::RuntimeShaderInterfaceDef;
+use pipeline::shader::RuntimeShaderInterfaceDefError;
+use pipeline::shader::ShaderInterfaceDefEntry;
+use pipeline::shader::ShaderModule;
+use pipeline::shader::spirv;
+use pipeline::shader::spirv::Instruction;
+use pipeline::shader::spirv::Spirv;
+
+use OomError;
+
+/// A shader module, alongside with the description of its entry points that was extracted from
+/// its SPIR-V code.
+///
+/// Can be obtained by calling `ShaderModule::reflect`.
+#[derive(Debug)]
+pub struct ReflectedShader {
+    module: Arc<ShaderModule>,
+    entry_points: Vec<ReflectedEntryPoint>,
+}
+
+impl ReflectedShader {
+    /// Returns the shader module.
+    #[inline]
+    pub fn module(&self) -> &Arc<ShaderModule> {
+        &self.module
+    }
+
+    /// Returns the list of entry points of the shader module.
+    #[inline]
+    pub fn entry_points(&self) -> &[ReflectedEntryPoint] {
+        &self.entry_points
+    }
+
+    /// Returns the description of the entry point with the given name.
+    #[inline]
+    pub fn entry_point(&self, name: &str) -> Option<&ReflectedEntryPoint> {
+        self.entry_points
+            .iter()
+            .find(|e| e.name.as_bytes() == name.as_bytes())
+    }
+
+    /// Returns the graphics entry point with the given name, ready to be passed when building a
+    /// graphics pipeline.
+    ///
+    /// Returns `None` if there is no entry point with this name, or if it's a compute entry point.
+    pub fn graphics_entry_point(&self, name: &str)
+                                -> Option<GraphicsEntryPoint<(),
+                                                             RuntimeShaderInterfaceDef,
+                                                             RuntimeShaderInterfaceDef,
+                                                             RuntimePipelineDesc>> {
+        let entry_point = self.entry_point(name)?;
+        let ty = match entry_point.ty {
+            EntryPointType::Graphics(ty) => ty,
+            EntryPointType::Compute => return None,
+        };
+
+        // Safe because the input, output and layout were extracted from the module itself.
+        unsafe {
+            Some(self.module.graphics_entry_point(&entry_point.name,
+                                                  entry_point.input.clone(),
+                                                  entry_point.output.clone(),
+                                                  entry_point.layout.clone(),
+                                                  ty))
+        }
+    }
+
+    /// Returns the compute entry point with the given name, ready to be passed when building a
+    /// compute pipeline.
+    ///
+    /// Returns `None` if there is no entry point with this name, or if it's a graphics entry
+    /// point.
+    pub fn compute_entry_point(&self, name: &str)
+                               -> Option<ComputeEntryPoint<(), RuntimePipelineDesc>> {
+        let entry_point = self.entry_point(name)?;
+        if entry_point.ty != EntryPointType::Compute {
+            return None;
+        }
+
+        // Safe because the layout was extracted from the module itself.
+        unsafe {
+            Some(self.module
+                     .compute_entry_point(&entry_point.name, entry_point.layout.clone()))
+        }
+    }
+}
+
+/// Description of an entry point of a shader module, extracted from its SPIR-V code.
+#[derive(Debug, Clone)]
+pub struct ReflectedEntryPoint {
+    name: CString,
+    ty: EntryPointType,
+    input: RuntimeShaderInterfaceDef,
+    output: RuntimeShaderInterfaceDef,
+    layout: RuntimePipelineDesc,
+}
+
+impl ReflectedEntryPoint {
+    /// Returns the name of the entry point.
+    #[inline]
+    pub fn name(&self) -> &CStr {
+        &self.name
+    }
+
+    /// Returns the type of the entry point.
+    #[inline]
+    pub fn ty(&self) -> EntryPointType {
+        self.ty
+    }
+
+    /// Returns the input attributes of the entry point. Always empty for compute entry points.
+    #[inline]
+    pub fn input(&self) -> &RuntimeShaderInterfaceDef {
+        &self.input
+    }
+
+    /// Returns the output attributes of the entry point. Always empty for compute entry points.
+    #[inline]
+    pub fn output(&self) -> &RuntimeShaderInterfaceDef {
+        &self.output
+    }
+
+    /// Returns the descriptors and push constants used by the entry point.
+    #[inline]
+    pub fn layout(&self) -> &RuntimePipelineDesc {
+        &self.layout
+    }
+}
+
+/// Type of an entry point.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EntryPointType {
+    /// The entry point is a stage of a graphics pipeline.
+    Graphics(GraphicsShaderType),
+    /// The entry point is a compute shader.
+    Compute,
+}
+
+// Implementation of `ShaderModule::reflect`.
+//
+// The entry points are reflected before the module is created, so that invalid SPIR-V code
+// doesn't reach the driver.
+pub(crate) unsafe fn reflect(device: Arc<Device>, doc: &Spirv)
+                             -> Result<ReflectedShader, ShaderReflectionError> {
+    let entry_points = {
+        let limits = device.physical_device().limits();
+        reflect_entry_points(doc,
+                             limits.max_bound_descriptor_sets(),
+                             limits.max_per_stage_resources())?
+    };
+    let module = ShaderModule::from_words(device, &doc.words)?;
+
+    Ok(ReflectedShader {
+           module: module,
+           entry_points: entry_points,
+       })
+}
+
+// Extracts the description of all the entry points of a module.
+//
+// The descriptors must use sets below `max_sets` and bindings below `max_bindings`.
+fn reflect_entry_points(doc: &Spirv, max_sets: u32, max_bindings: u32)
+                        -> Result<Vec<ReflectedEntryPoint>, ShaderReflectionError> {
+    let mut entry_points = Vec::new();
+
+    for instruction in doc.instructions.iter() {
+        if let Instruction::EntryPoint {
+            execution,
+            id,
+            ref name,
+            ref interface,
+        } = *instruction
+        {
+            entry_points.push(reflect_entry_point(doc,
+                                                  execution,
+                                                  id,
+                                                  name,
+                                                  interface,
+                                                  max_sets,
+                                                  max_bindings)?);
+        }
+    }
+
+    Ok(entry_points)
+}
+
+fn reflect_entry_point(doc: &Spirv, execution: u32, id: u32, name: &str, interface: &[u32],
+                       max_sets: u32, max_bindings: u32)
+                       -> Result<ReflectedEntryPoint, ShaderReflectionError> {
+    let none = ShaderStages::none();
+    let (ty, stages) = match execution {
+        spirv::EXECUTION_MODEL_VERTEX => {
+            (EntryPointType::Graphics(GraphicsShaderType::Vertex),
+             ShaderStages { vertex: true, ..none })
+        },
+        spirv::EXECUTION_MODEL_TESSELLATION_CONTROL => {
+            (EntryPointType::Graphics(GraphicsShaderType::TessellationControl),
+             ShaderStages { tessellation_control: true, ..none })
+        },
+        spirv::EXECUTION_MODEL_TESSELLATION_EVALUATION => {
+            (EntryPointType::Graphics(GraphicsShaderType::TessellationEvaluation),
+             ShaderStages { tessellation_evaluation: true, ..none })
+        },
+        spirv::EXECUTION_MODEL_GEOMETRY => {
+            let mode = geometry_execution_mode(doc, id)
+                .ok_or(ShaderReflectionError::MissingGeometryInputMode)?;
+            (EntryPointType::Graphics(GraphicsShaderType::Geometry(mode)),
+             ShaderStages { geometry: true, ..none })
+        },
+        spirv::EXECUTION_MODEL_FRAGMENT => {
+            (EntryPointType::Graphics(GraphicsShaderType::Fragment),
+             ShaderStages { fragment: true, ..none })
+        },
+        spirv::EXECUTION_MODEL_GL_COMPUTE => {
+            (EntryPointType::Compute, ShaderStages { compute: true, ..none })
+        },
+        _ => return Err(ShaderReflectionError::UnsupportedExecutionModel),
+    };
+
+    // The inputs of tessellation and geometry shaders, and the outputs of tessellation control
+    // shaders, are arrays with one element per vertex.
+    let ignore_first_array_in = match execution {
+        spirv::EXECUTION_MODEL_TESSELLATION_CONTROL |
+        spirv::EXECUTION_MODEL_TESSELLATION_EVALUATION |
+        spirv::EXECUTION_MODEL_GEOMETRY => true,
+        _ => false,
+    };
+    let ignore_first_array_out = execution == spirv::EXECUTION_MODEL_TESSELLATION_CONTROL;
+
+    let (input, output) = if ty == EntryPointType::Compute {
+        (RuntimeShaderInterfaceDef::empty(), RuntimeShaderInterfaceDef::empty())
+    } else {
+        (interface_def(doc, interface, spirv::STORAGE_CLASS_INPUT, ignore_first_array_in)?,
+         interface_def(doc, interface, spirv::STORAGE_CLASS_OUTPUT, ignore_first_array_out)?)
+    };
+
+    Ok(ReflectedEntryPoint {
+           name: CString::new(name).map_err(|_| ShaderReflectionError::InvalidString)?,
+           ty: ty,
+           input: input,
+           output: output,
+           layout: pipeline_desc(doc, stages, max_sets, max_bindings)?,
+       })
+}
+
+// Returns the type of primitives expected by a geometry shader.
+fn geometry_execution_mode(doc: &Spirv, entry_point_id: u32)
+                           -> Option<GeometryShaderExecutionMode> {
+    doc.instructions
+        .iter()
+        .filter_map(|i| match *i {
+                        Instruction::ExecutionMode { target_id, mode }
+                            if target_id == entry_point_id => {
+                            match mode {
+                                spirv::EXECUTION_MODE_INPUT_POINTS => {
+                                    Some(GeometryShaderExecutionMode::Points)
+                                },
+                                spirv::EXECUTION_MODE_INPUT_LINES => {
+                                    Some(GeometryShaderExecutionMode::Lines)
+                                },
+                                spirv::EXECUTION_MODE_INPUT_LINES_ADJACENCY => {
+                                    Some(GeometryShaderExecutionMode::LinesWithAdjacency)
+                                },
+                                spirv::EXECUTION_MODE_TRIANGLES => {
+                                    Some(GeometryShaderExecutionMode::Triangles)
+                                },
+                                spirv::EXECUTION_MODE_INPUT_TRIANGLES_ADJACENCY => {
+                                    Some(GeometryShaderExecutionMode::TrianglesWithAdjacency)
+                                },
+                                _ => None,
+                            }
+                        },
+                        _ => None,
+                    })
+        .next()
+}
+
+// Builds the description of the inputs or outputs of an entry point.
+fn interface_def(doc: &Spirv, interface: &[u32], storage_class: u32, ignore_first_array: bool)
+                 -> Result<RuntimeShaderInterfaceDef, ShaderReflectionError> {
+    let mut entries = Vec::new();
+
+    for &variable_id in interface {
+        let type_id = match variable(doc, variable_id) {
+            Some((type_id, class)) if class == storage_class => type_id,
+            _ => continue,
+        };
+
+        if is_builtin(doc, variable_id) {
+            continue;
+        }
+
+        let location = decoration(doc, variable_id, spirv::DECORATION_LOCATION)
+            .ok_or(ShaderReflectionError::MissingLocation { id: variable_id })?;
+        let (format, num_locations) = interface_format(doc, type_id, ignore_first_array)?;
+
+        let end = location
+            .checked_add(num_locations)
+            .ok_or(ShaderReflectionError::LocationOutOfRange { id: variable_id })?;
+
+        entries.push(ShaderInterfaceDefEntry {
+                         location: location .. end,
+                         format: format,
+                         name: name(doc, variable_id).map(|n| n.into()),
+                     });
+    }
+
+    Ok(RuntimeShaderInterfaceDef::new(entries)?)
+}
+
+// Returns the format and the number of locations of a type used in a shader interface.
+fn interface_format(doc: &Spirv, searched: u32, ignore_first_array: bool)
+                    -> Result<(Format, u32), ShaderReflectionError> {
+    let unsupported = ShaderReflectionError::UnsupportedType { id: searched };
+
+    for instruction in doc.instructions.iter() {
+        match *instruction {
+            Instruction::TypeInt { result_id, .. } |
+            Instruction::TypeFloat { result_id, .. } |
+            Instruction::TypeVector { result_id, .. } if result_id == searched => {
+                if ignore_first_array {
+                    return Err(unsupported);
+                }
+                let format = vector_format(doc, searched).ok_or(unsupported)?;
+                return Ok((format, 1));
+            },
+            Instruction::TypeMatrix {
+                result_id,
+                column_type_id,
+                column_count,
+            } if result_id == searched => {
+                if ignore_first_array {
+                    return Err(unsupported);
+                }
+                let format = vector_format(doc, column_type_id).ok_or(unsupported)?;
+                return Ok((format, column_count));
+            },
+            Instruction::TypeArray {
+                result_id,
+                type_id,
+                length_id,
+            } if result_id == searched => {
+                let (format, num_locations) = interface_format(doc, type_id, false)?;
+                if ignore_first_array {
+                    return Ok((format, num_locations));
+                }
+                let len = constant_u64(doc, length_id).ok_or(unsupported)?;
+                if len > u32::MAX as u64 {
+                    return Err(unsupported);
+                }
+                let total = num_locations.checked_mul(len as u32).ok_or(unsupported)?;
+                return Ok((format, total));
+            },
+            Instruction::TypePointer { result_id, type_id, .. } if result_id == searched => {
+                return interface_format(doc, type_id, ignore_first_array);
+            },
+            _ => (),
+        }
+    }
+
+    Err(unsupported)
+}
+
+// Returns the format that corresponds to a scalar or a vector type.
+//
+// Only 32 bits and 64 bits components are supported, and the resulting format is never larger
+// than 128 bits.
+fn vector_format(doc: &Spirv, searched: u32) -> Option<Format> {
+    // Returns the kind of component (0 = float, 1 = signed, 2 = unsigned) and its width.
+    let scalar = |id: u32| {
+        doc.instructions
+            .iter()
+            .filter_map(|i| match *i {
+                            Instruction::TypeFloat { result_id, width } if result_id == id => {
+                                Some((0, width))
+                            },
+                            Instruction::TypeInt {
+                                result_id,
+                                width,
+                                signedness,
+                            } if result_id == id => {
+                                Some((if signedness { 1 } else { 2 }, width))
+                            },
+                            _ => None,
+                        })
+            .next()
+    };
+
+    let (kind, width, count) = match scalar(searched) {
+        Some((kind, width)) => (kind, width, 1),
+        None => {
+            doc.instructions
+                .iter()
+                .filter_map(|i| match *i {
+                                Instruction::TypeVector {
+                                    result_id,
+                                    component_id,
+                                    count,
+                                } if result_id == searched => {
+                                    scalar(component_id).map(|(k, w)| (k, w, count))
+                                },
+                                _ => None,
+                            })
+                .next()?
+        },
+    };
+
+    Some(match (kind, width, count) {
+             (0, 32, 1) => Format::R32Sfloat,
+             (0, 32, 2) => Format::R32G32Sfloat,
+             (0, 32, 3) => Format::R32G32B32Sfloat,
+             (0, 32, 4) => Format::R32G32B32A32Sfloat,
+             (1, 32, 1) => Format::R32Sint,
+             (1, 32, 2) => Format::R32G32Sint,
+             (1, 32, 3) => Format::R32G32B32Sint,
+             (1, 32, 4) => Format::R32G32B32A32Sint,
+             (2, 32, 1) => Format::R32Uint,
+             (2, 32, 2) => Format::R32G32Uint,
+             (2, 32, 3) => Format::R32G32B32Uint,
+             (2, 32, 4) => Format::R32G32B32A32Uint,
+             (0, 64, 1) => Format::R64Sfloat,
+             (0, 64, 2) => Format::R64G64Sfloat,
+             (1, 64, 1) => Format::R64Sint,
+             (1, 64, 2) => Format::R64G64Sint,
+             (2, 64, 1) => Format::R64Uint,
+             (2, 64, 2) => Format::R64G64Uint,
+             _ => return None,
+         })
+}
+
+// Builds the description of the descriptors and push constants of the module.
+//
+// Since SPIR-V doesn't tell which entry point uses which descriptor, all the descriptors of the
+// module are assigned to `stages`.
+//
+// The set and binding numbers are checked against `max_sets` and `max_bindings` before the
+// layout is allocated, so that a huge number in the SPIR-V code can't exhaust the memory.
+fn pipeline_desc(doc: &Spirv, stages: ShaderStages, max_sets: u32, max_bindings: u32)
+                 -> Result<RuntimePipelineDesc, ShaderReflectionError> {
+    let mut sets: Vec<Vec<Option<DescriptorDesc>>> = Vec::new();
+    let mut push_constants_size = 0;
+
+    for instruction in doc.instructions.iter() {
+        match *instruction {
+            Instruction::Decorate {
+                target_id,
+                decoration: spirv::DECORATION_DESCRIPTOR_SET,
+                ref params,
+            } => {
+                let set = *params
+                    .get(0)
+                    .ok_or(ShaderReflectionError::IncompleteInstruction)?;
+                let binding = decoration(doc, target_id, spirv::DECORATION_BINDING)
+                    .ok_or(ShaderReflectionError::MissingBinding { id: target_id })?;
+                if set >= max_sets || binding >= max_bindings {
+                    return Err(ShaderReflectionError::DescriptorOutOfRange { id: target_id });
+                }
+                let (set, binding) = (set as usize, binding as usize);
+                let pointed_ty = variable(doc, target_id)
+                    .and_then(|(ty, _)| pointed_type(doc, ty))
+                    .ok_or(ShaderReflectionError::UnsupportedType { id: target_id })?;
+                let (ty, readonly, array_count) =
+                    descriptor_infos(doc, target_id, pointed_ty, false)?;
+
+                if sets.len() <= set {
+                    sets.resize(set + 1, Vec::new());
+                }
+                if sets[set].len() <= binding {
+                    sets[set].resize(binding + 1, None);
+                }
+                sets[set][binding] = Some(DescriptorDesc {
+                                              ty: ty,
+                                              array_count: array_count,
+                                              stages: stages.clone(),
+                                              readonly: readonly,
+                                          });
+            },
+            Instruction::TypePointer {
+                type_id,
+                storage_class: spirv::STORAGE_CLASS_PUSH_CONSTANT,
+                ..
+            } => {
+                let size = type_size(doc, type_id, None)
+                    .ok_or(ShaderReflectionError::UnsupportedType { id: type_id })?;
+                push_constants_size = cmp::max(push_constants_size, size);
+            },
+            _ => (),
+        }
+    }
+
+    let push_constants = if push_constants_size != 0 {
+        Some(PipelineLayoutDescPcRange {
+                 offset: 0,
+                 size: push_constants_size,
+                 stages: stages,
+             })
+    } else {
+        None
+    };
+
+    // A single range of push constants can't conflict with anything.
+    Ok(RuntimePipelineDesc::new(sets, push_constants).unwrap())
+}
+
+// Returns the type of descriptor, whether it is read-only, and the number of array elements.
+//
+// See also section 14.5.2 of the Vulkan specs: Descriptor Set Interface.
+fn descriptor_infos(doc: &Spirv, variable_id: u32, pointed_ty: u32,
+                    force_combined_image_sampled: bool)
+                    -> Result<(DescriptorDescTy, bool, u32), ShaderReflectionError> {
+    let unsupported = ShaderReflectionError::UnsupportedType { id: pointed_ty };
+
+    for instruction in doc.instructions.iter() {
+        match *instruction {
+            Instruction::TypeStruct {
+                result_id,
+                ref member_types,
+            } if result_id == pointed_ty => {
+                let storage_class = variable(doc, variable_id).map(|(_, class)| class);
+                let storage = if storage_class == Some(spirv::STORAGE_CLASS_STORAGE_BUFFER) {
+                    true
+                } else if has_decoration(doc, pointed_ty, spirv::DECORATION_BUFFER_BLOCK) {
+                    true
+                } else if has_decoration(doc, pointed_ty, spirv::DECORATION_BLOCK) {
+                    false
+                } else {
+                    return Err(unsupported);
+                };
+
+                // A storage buffer is read-only if all of its members are non-writable.
+                let readonly = !storage ||
+                    has_decoration(doc, variable_id, spirv::DECORATION_NON_WRITABLE) ||
+                    (0 .. member_types.len() as u32).all(|member| {
+                        member_decoration(doc, pointed_ty, member, spirv::DECORATION_NON_WRITABLE)
+                            .is_some()
+                    });
+
+                let desc = DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                                        dynamic: Some(false),
+                                                        storage: storage,
+                                                    });
+                return Ok((desc, readonly, 1));
+            },
+
+            Instruction::TypeImage {
+                result_id,
+                dim,
+                arrayed,
+                ms,
+                sampled,
+            } if result_id == pointed_ty => {
+                // Vulkan requires the `Sampled` operand to be 1 or 2.
+                let sampled = match sampled {
+                    1 => true,
+                    2 => false,
+                    _ => return Err(unsupported),
+                };
+
+                let array_layers = if arrayed {
+                    DescriptorImageDescArray::Arrayed { max_layers: None }
+                } else {
+                    DescriptorImageDescArray::NonArrayed
+                };
+
+                let readonly = sampled ||
+                    has_decoration(doc, variable_id, spirv::DECORATION_NON_WRITABLE);
+
+                let desc = match dim {
+                    spirv::DIM_SUBPASS_DATA => {
+                        if force_combined_image_sampled || sampled {
+                            return Err(unsupported);
+                        }
+                        DescriptorDescTy::InputAttachment {
+                            multisampled: ms,
+                            array_layers: array_layers,
+                        }
+                    },
+                    spirv::DIM_BUFFER => {
+                        DescriptorDescTy::TexelBuffer {
+                            storage: !sampled,
+                            format: None, // TODO: specify format if known
+                        }
+                    },
+                    _ => {
+                        let dimensions = match dim {
+                            spirv::DIM_1D => DescriptorImageDescDimensions::OneDimensional,
+                            spirv::DIM_2D => DescriptorImageDescDimensions::TwoDimensional,
+                            spirv::DIM_3D => DescriptorImageDescDimensions::ThreeDimensional,
+                            spirv::DIM_CUBE => DescriptorImageDescDimensions::Cube,
+                            _ => return Err(unsupported),
+                        };
+
+                        let desc = DescriptorImageDesc {
+                            sampled: sampled,
+                            dimensions: dimensions,
+                            format: None, // TODO: specify format if known
+                            multisampled: ms,
+                            array_layers: array_layers,
+                        };
+
+                        if force_combined_image_sampled {
+                            DescriptorDescTy::CombinedImageSampler(desc)
+                        } else {
+                            DescriptorDescTy::Image(desc)
+                        }
+                    },
+                };
+
+                return Ok((desc, readonly, 1));
+            },
+
+            Instruction::TypeSampledImage {
+                result_id,
+                image_type_id,
+            } if result_id == pointed_ty => {
+                return descriptor_infos(doc, variable_id, image_type_id, true);
+            },
+
+            Instruction::TypeSampler { result_id } if result_id == pointed_ty => {
+                return Ok((DescriptorDescTy::Sampler, true, 1));
+            },
+
+            Instruction::TypeArray {
+                result_id,
+                type_id,
+                length_id,
+            } if result_id == pointed_ty => {
+                let (desc, readonly, array_count) =
+                    descriptor_infos(doc, variable_id, type_id, false)?;
+                if array_count != 1 {
+                    return Err(unsupported);
+                }
+                let len = constant_u64(doc, length_id).ok_or(unsupported)?;
+                return Ok((desc, readonly, len as u32));
+            },
+
+            _ => (),
+        }
+    }
+
+    Err(unsupported)
+}
+
+// Returns the size in bytes of a type, or `None` if it's not known.
+//
+// `matrix_stride` is the value of the `MatrixStride` decoration of the struct member that has
+// this type, if any.
+fn type_size(doc: &Spirv, searched: u32, matrix_stride: Option<u32>) -> Option<usize> {
+    for instruction in doc.instructions.iter() {
+        match *instruction {
+            Instruction::TypeInt { result_id, width, .. } |
+            Instruction::TypeFloat { result_id, width } if result_id == searched => {
+                return Some(width as usize / 8);
+            },
+            Instruction::TypeVector {
+                result_id,
+                component_id,
+                count,
+            } if result_id == searched => {
+                return type_size(doc, component_id, None)
+                    .and_then(|s| s.checked_mul(count as usize));
+            },
+            Instruction::TypeMatrix {
+                result_id,
+                column_type_id,
+                column_count,
+            } if result_id == searched => {
+                let column_size = match matrix_stride {
+                    Some(stride) => stride as usize,
+                    None => type_size(doc, column_type_id, None)?,
+                };
+                return column_size.checked_mul(column_count as usize);
+            },
+            Instruction::TypeArray {
+                result_id,
+                type_id,
+                length_id,
+            } if result_id == searched => {
+                let stride = match decoration(doc, searched, spirv::DECORATION_ARRAY_STRIDE) {
+                    Some(stride) => stride as usize,
+                    None => type_size(doc, type_id, matrix_stride)?,
+                };
+                let len = constant_u64(doc, length_id)?;
+                if len > usize::MAX as u64 {
+                    return None;
+                }
+                return stride.checked_mul(len as usize);
+            },
+            Instruction::TypeStruct {
+                result_id,
+                ref member_types,
+            } if result_id == searched => {
+                let mut size = 0;
+                for (member, &member_ty) in member_types.iter().enumerate() {
+                    let member = member as u32;
+                    let offset = member_decoration(doc, searched, member, spirv::DECORATION_OFFSET)
+                        .unwrap_or(0) as usize;
+                    let stride =
+                        member_decoration(doc, searched, member, spirv::DECORATION_MATRIX_STRIDE);
+                    let end = offset.checked_add(type_size(doc, member_ty, stride)?)?;
+                    size = cmp::max(size, end);
+                }
+                return Some(size);
+            },
+            _ => (),
+        }
+    }
+
+    None
+}
+
+// Returns the type and the storage class of a variable.
+fn variable(doc: &Spirv, searched: u32) -> Option<(u32, u32)> {
+    doc.instructions
+        .iter()
+        .filter_map(|i| match *i {
+                        Instruction::Variable {
+                            result_type_id,
+                            result_id,
+                            storage_class,
+                        } if result_id == searched => Some((result_type_id, storage_class)),
+                        _ => None,
+                    })
+        .next()
+}
+
+// Returns the type pointed to by a pointer type.
+fn pointed_type(doc: &Spirv, pointer: u32) -> Option<u32> {
+    doc.instructions
+        .iter()
+        .filter_map(|i| match *i {
+                        Instruction::TypePointer { result_id, type_id, .. }
+                            if result_id == pointer => Some(type_id),
+                        _ => None,
+                    })
+        .next()
+}
+
+// Returns the value of an integer constant.
+fn constant_u64(doc: &Spirv, searched: u32) -> Option<u64> {
+    doc.instructions
+        .iter()
+        .filter_map(|i| match *i {
+                        Instruction::Constant { result_id, ref data, .. }
+                            if result_id == searched => {
+                            Some(data.iter().rev().fold(0u64, |a, &b| (a << 32) | b as u64))
+                        },
+                        _ => None,
+                    })
+        .next()
+}
+
+// Returns the name of an object, if it has a non-empty one.
+fn name(doc: &Spirv, searched: u32) -> Option<String> {
+    doc.instructions
+        .iter()
+        .filter_map(|i| match *i {
+                        Instruction::Name { target_id, ref name } if target_id == searched => {
+                            Some(name.clone())
+                        },
+                        _ => None,
+                    })
+        .next()
+        .and_then(|n| if !n.is_empty() { Some(n) } else { None })
+}
+
+// Returns the first parameter of a decoration of an object.
+fn decoration(doc: &Spirv, searched: u32, searched_decoration: u32) -> Option<u32> {
+    doc.instructions
+        .iter()
+        .filter_map(|i| match *i {
+                        Instruction::Decorate {
+                            target_id,
+                            decoration,
+                            ref params,
+                        } if target_id == searched && decoration == searched_decoration => {
+                            params.get(0).cloned()
+                        },
+                        _ => None,
+                    })
+        .next()
+}
+
+// Returns true if an object has a decoration, whether or not it has parameters.
+fn has_decoration(doc: &Spirv, searched: u32, searched_decoration: u32) -> bool {
+    doc.instructions.iter().any(|i| match *i {
+                                    Instruction::Decorate {
+                                        target_id,
+                                        decoration,
+                                        ..
+                                    } => {
+                                        target_id == searched &&
+                                            decoration == searched_decoration
+                                    },
+                                    _ => false,
+                                })
+}
+
+// Returns the first parameter of a decoration of a struct member, or `Some(0)` if the decoration
+// has no parameter.
+fn member_decoration(doc: &Spirv, searched: u32, searched_member: u32, searched_decoration: u32)
+                     -> Option<u32> {
+    doc.instructions
+        .iter()
+        .filter_map(|i| match *i {
+                        Instruction::MemberDecorate {
+                            target_id,
+                            member,
+                            decoration,
+                            ref params,
+                        } if target_id == searched && member == searched_member &&
+                                 decoration == searched_decoration => {
+                            Some(params.get(0).cloned().unwrap_or(0))
+                        },
+                        _ => None,
+                    })
+        .next()
+}
+
+// Returns true if the object is a built-in variable, or a block of built-in variables.
+fn is_builtin(doc: &Spirv, id: u32) -> bool {
+    for instruction in doc.instructions.iter() {
+        match *instruction {
+            Instruction::Decorate {
+                target_id,
+                decoration: spirv::DECORATION_BUILT_IN,
+                ..
+            } |
+            Instruction::MemberDecorate {
+                target_id,
+                decoration: spirv::DECORATION_BUILT_IN,
+                ..
+            } if target_id == id => {
+                return true;
+            },
+            _ => (),
+        }
+    }
+
+    for instruction in doc.instructions.iter() {
+        match *instruction {
+            Instruction::Variable {
+                result_type_id,
+                result_id,
+                ..
+            } if result_id == id => {
+                return is_builtin(doc, result_type_id);
+            },
+            Instruction::TypeArray { result_id, type_id, .. } if result_id == id => {
+                return is_builtin(doc, type_id);
+            },
+            Instruction::TypeRuntimeArray { result_id, type_id } if result_id == id => {
+                return is_builtin(doc, type_id);
+            },
+            Instruction::TypeStruct {
+                result_id,
+                ref member_types,
+            } if result_id == id => {
+                return member_types.iter().any(|&m| is_builtin(doc, m));
+            },
+            Instruction::TypePointer { result_id, type_id, .. } if result_id == id => {
+                return is_builtin(doc, type_id);
+            },
+            _ => (),
+        }
+    }
+
+    false
+}
+
+/// Error that can happen when reflecting a shader module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShaderReflectionError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The data doesn't start with a valid SPIR-V header.
+    InvalidHeader,
+    /// An instruction is truncated or has missing operands.
+    IncompleteInstruction,
+    /// A string is not valid UTF-8 or contains a nul character.
+    InvalidString,
+    /// An entry point has an execution model that vulkano doesn't support, such as `Kernel`.
+    UnsupportedExecutionModel,
+    /// A geometry shader doesn't specify the type of its input primitives.
+    MissingGeometryInputMode,
+    /// A variable of the interface of an entry point is missing a `Location` decoration.
+    MissingLocation {
+        /// Id of the variable.
+        id: u32,
+    },
+    /// A descriptor is missing a `Binding` decoration.
+    MissingBinding {
+        /// Id of the variable.
+        id: u32,
+    },
+    /// The set or the binding of a descriptor is larger than what the device supports.
+    DescriptorOutOfRange {
+        /// Id of the variable.
+        id: u32,
+    },
+    /// The locations used by a variable of the interface of an entry point overflow.
+    LocationOutOfRange {
+        /// Id of the variable.
+        id: u32,
+    },
+    /// A type used in an interface, a descriptor or push constants is not supported.
+    UnsupportedType {
+        /// Id of the type or of the variable.
+        id: u32,
+    },
+    /// The inputs or the outputs of an entry point are invalid.
+    InvalidInterface(RuntimeShaderInterfaceDefError),
+}
+
+impl error::Error for ShaderReflectionError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            ShaderReflectionError::OomError(_) => "not enough memory available",
+            ShaderReflectionError::InvalidHeader => {
+                "the data doesn't start with a valid SPIR-V header"
+            },
+            ShaderReflectionError::IncompleteInstruction => {
+                "an instruction is truncated or has missing operands"
+            },
+            ShaderReflectionError::InvalidString => {
+                "a string is not valid UTF-8 or contains a nul character"
+            },
+            ShaderReflectionError::UnsupportedExecutionModel => {
+                "an entry point has an execution model that isn't supported"
+            },
+            ShaderReflectionError::MissingGeometryInputMode => {
+                "a geometry shader doesn't specify the type of its input primitives"
+            },
+            ShaderReflectionError::MissingLocation { .. } => {
+                "a variable of the interface of an entry point is missing a location"
+            },
+            ShaderReflectionError::MissingBinding { .. } => {
+                "a descriptor is missing a binding"
+            },
+            ShaderReflectionError::DescriptorOutOfRange { .. } => {
+                "the set or the binding of a descriptor is larger than what the device supports"
+            },
+            ShaderReflectionError::LocationOutOfRange { .. } => {
+                "the locations used by a variable of the interface of an entry point overflow"
+            },
+            ShaderReflectionError::UnsupportedType { .. } => {
+                "a type used in an interface, a descriptor or push constants is not supported"
+            },
+            ShaderReflectionError::InvalidInterface(_) => {
+                "the inputs or the outputs of an entry point are invalid"
+            },
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ShaderReflectionError::OomError(ref err) => Some(err),
+            ShaderReflectionError::InvalidInterface(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ShaderReflectionError {
+    #[inline]
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
+    }
+}
+
+impl From<OomError> for ShaderReflectionError {
+    #[inline]
+    fn from(err: OomError) -> ShaderReflectionError {
+        ShaderReflectionError::OomError(err)
+    }
+}
+
+impl From<RuntimeShaderInterfaceDefError> for ShaderReflectionError {
+    #[inline]
+    fn from(err: RuntimeShaderInterfaceDefError) -> ShaderReflectionError {
+        ShaderReflectionError::InvalidInterface(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use descriptor::descriptor::DescriptorBufferDesc;
+    use descriptor::descriptor::DescriptorDescTy;
+    use descriptor::descriptor::DescriptorImageDesc;
+    use descriptor::descriptor::DescriptorImageDescArray;
+    use descriptor::descriptor::DescriptorImageDescDimensions;
+    use descriptor::descriptor::ShaderStages;
+    use descriptor::pipeline_layout::PipelineLayoutDesc;
+    use format::Format;
+    use pipeline::shader::GraphicsShaderType;
+    use pipeline::shader::ShaderInterfaceDef;
+    use pipeline::shader::ShaderModule;
+    use pipeline::shader::spirv;
+
+    use super::EntryPointType;
+    use super::ShaderReflectionError;
+    use super::reflect_entry_points;
+
+    // Limits passed to `reflect_entry_points`.
+    const MAX_SETS: u32 = 4;
+    const MAX_BINDINGS: u32 = 16;
+
+    // Appends an instruction to `words`.
+    fn op(words: &mut Vec<u32>, opcode: u32, operands: &[u32]) {
+        words.push(((operands.len() as u32 + 1) << 16) | opcode);
+        words.extend_from_slice(operands);
+    }
+
+    // Encodes a nul-terminated string as SPIR-V words.
+    fn string(s: &str) -> Vec<u32> {
+        let mut bytes = s.as_bytes().to_owned();
+        bytes.push(0);
+        while bytes.len() % 4 != 0 {
+            bytes.push(0);
+        }
+        bytes
+            .chunks(4)
+            .map(|c| {
+                     c[0] as u32 | ((c[1] as u32) << 8) | ((c[2] as u32) << 16) |
+                         ((c[3] as u32) << 24)
+                 })
+            .collect()
+    }
+
+    fn to_bytes(words: &[u32], big_endian: bool) -> Vec<u8> {
+        words
+            .iter()
+            .flat_map(|&w| {
+                let b = [w as u8, (w >> 8) as u8, (w >> 16) as u8, (w >> 24) as u8];
+                if big_endian {
+                    vec![b[3], b[2], b[1], b[0]]
+                } else {
+                    b.to_vec()
+                }
+            })
+            .collect()
+    }
+
+    // Assembles the equivalent of this vertex shader:
+    //
+    // ```glsl
+    // layout(location = 0) in vec2 position;
+    // layout(location = 1) in vec3 color;
+    // layout(location = 0) out vec3 v_color;
+    // layout(set = 0, binding = 0) uniform Data { mat4 world; };
+    // layout(set = 0, binding = 1) readonly buffer Values { float values[]; };
+    // layout(set = 1, binding = 2) uniform sampler2D tex[4];
+    // layout(push_constant) uniform PushConstants { vec4 tint; float scale; };
+    // void main() {}
+    // ```
+    fn vertex_shader(with_color_location: bool) -> Vec<u32> {
+        let mut w = vec![0x07230203, 0x00010000, 0, 100, 0];
+
+        op(&mut w, 17, &[1]); // OpCapability Shader
+        op(&mut w, 14, &[0, 1]); // OpMemoryModel Logical GLSL450
+        let mut entry_point = vec![spirv::EXECUTION_MODEL_VERTEX, 1];
+        entry_point.extend(string("main"));
+        entry_point.extend(&[10, 11, 12, 13]);
+        op(&mut w, 15, &entry_point);
+
+        for &(id, name) in &[(10, "position"), (11, "color"), (12, "v_color")] {
+            let mut operands = vec![id];
+            operands.extend(string(name));
+            op(&mut w, 5, &operands);
+        }
+
+        op(&mut w, 71, &[10, spirv::DECORATION_LOCATION, 0]);
+        if with_color_location {
+            op(&mut w, 71, &[11, spirv::DECORATION_LOCATION, 1]);
+        }
+        op(&mut w, 71, &[12, spirv::DECORATION_LOCATION, 0]);
+        op(&mut w, 72, &[30, 0, spirv::DECORATION_BUILT_IN, 0]);
+        op(&mut w, 71, &[30, spirv::DECORATION_BLOCK]);
+        op(&mut w, 71, &[40, spirv::DECORATION_BLOCK]);
+        op(&mut w, 72, &[40, 0, spirv::DECORATION_OFFSET, 0]);
+        op(&mut w, 72, &[40, 0, 5]); // ColMajor
+        op(&mut w, 72, &[40, 0, spirv::DECORATION_MATRIX_STRIDE, 16]);
+        op(&mut w, 71, &[41, spirv::DECORATION_DESCRIPTOR_SET, 0]);
+        op(&mut w, 71, &[41, spirv::DECORATION_BINDING, 0]);
+        op(&mut w, 71, &[51, spirv::DECORATION_ARRAY_STRIDE, 4]);
+        op(&mut w, 71, &[50, spirv::DECORATION_BUFFER_BLOCK]);
+        op(&mut w, 72, &[50, 0, spirv::DECORATION_OFFSET, 0]);
+        op(&mut w, 72, &[50, 0, spirv::DECORATION_NON_WRITABLE]);
+        op(&mut w, 71, &[52, spirv::DECORATION_DESCRIPTOR_SET, 0]);
+        op(&mut w, 71, &[52, spirv::DECORATION_BINDING, 1]);
+        op(&mut w, 71, &[63, spirv::DECORATION_DESCRIPTOR_SET, 1]);
+        op(&mut w, 71, &[63, spirv::DECORATION_BINDING, 2]);
+        op(&mut w, 71, &[70, spirv::DECORATION_BLOCK]);
+        op(&mut w, 72, &[70, 0, spirv::DECORATION_OFFSET, 0]);
+        op(&mut w, 72, &[70, 1, spirv::DECORATION_OFFSET, 16]);
+
+        op(&mut w, 19, &[2]); // OpTypeVoid
+        op(&mut w, 33, &[3, 2]); // OpTypeFunction
+        op(&mut w, 22, &[4, 32]); // float
+        op(&mut w, 23, &[5, 4, 2]); // vec2
+        op(&mut w, 23, &[6, 4, 3]); // vec3
+        op(&mut w, 23, &[7, 4, 4]); // vec4
+        op(&mut w, 24, &[8, 7, 4]); // mat4
+        op(&mut w, 21, &[9, 32, 0]); // uint
+        op(&mut w, 43, &[9, 20, 4]); // 4u
+
+        // Interface.
+        op(&mut w, 32, &[14, spirv::STORAGE_CLASS_INPUT, 5]);
+        op(&mut w, 32, &[15, spirv::STORAGE_CLASS_INPUT, 6]);
+        op(&mut w, 32, &[16, spirv::STORAGE_CLASS_OUTPUT, 6]);
+        op(&mut w, 30, &[30, 7]);
+        op(&mut w, 32, &[31, spirv::STORAGE_CLASS_OUTPUT, 30]);
+        op(&mut w, 59, &[14, 10, spirv::STORAGE_CLASS_INPUT]);
+        op(&mut w, 59, &[15, 11, spirv::STORAGE_CLASS_INPUT]);
+        op(&mut w, 59, &[16, 12, spirv::STORAGE_CLASS_OUTPUT]);
+        op(&mut w, 59, &[31, 13, spirv::STORAGE_CLASS_OUTPUT]);
+
+        // Uniform buffer.
+        op(&mut w, 30, &[40, 8]);
+        op(&mut w, 32, &[42, 2, 40]);
+        op(&mut w, 59, &[42, 41, 2]);
+
+        // Storage buffer.
+        op(&mut w, 29, &[51, 4]);
+        op(&mut w, 30, &[50, 51]);
+        op(&mut w, 32, &[53, 2, 50]);
+        op(&mut w, 59, &[53, 52, 2]);
+
+        // Array of combined image samplers.
+        op(&mut w, 25, &[60, 4, spirv::DIM_2D, 0, 0, 0, 1, 0]);
+        op(&mut w, 27, &[61, 60]);
+        op(&mut w, 28, &[62, 61, 20]);
+        op(&mut w, 32, &[64, 0, 62]);
+        op(&mut w, 59, &[64, 63, 0]);
+
+        // Push constants.
+        op(&mut w, 30, &[70, 7, 4]);
+        op(&mut w, 32, &[71, spirv::STORAGE_CLASS_PUSH_CONSTANT, 70]);
+        op(&mut w, 59, &[71, 72, spirv::STORAGE_CLASS_PUSH_CONSTANT]);
+
+        op(&mut w, 54, &[2, 1, 0, 3]); // OpFunction
+        op(&mut w, 248, &[80]); // OpLabel
+        op(&mut w, 253, &[]); // OpReturn
+        op(&mut w, 56, &[]); // OpFunctionEnd
+        w
+    }
+
+    #[test]
+    fn vertex_shader_interface() {
+        let doc = spirv::parse_spirv(&to_bytes(&vertex_shader(true), false)).unwrap();
+        let entry_points = reflect_entry_points(&doc, MAX_SETS, MAX_BINDINGS).unwrap();
+        assert_eq!(entry_points.len(), 1);
+
+        let entry_point = &entry_points[0];
+        assert_eq!(entry_point.name().to_str().unwrap(), "main");
+        assert_eq!(entry_point.ty(), EntryPointType::Graphics(GraphicsShaderType::Vertex));
+
+        let input = entry_point.input().elements().collect::<Vec<_>>();
+        assert_eq!(input.len(), 2);
+        assert_eq!(input[0].location, 0 .. 1);
+        assert_eq!(input[0].format, Format::R32G32Sfloat);
+        assert_eq!(input[0].name.as_ref().map(|n| &**n), Some("position"));
+        assert_eq!(input[1].location, 1 .. 2);
+        assert_eq!(input[1].format, Format::R32G32B32Sfloat);
+
+        // `gl_PerVertex` must be skipped.
+        let output = entry_point.output().elements().collect::<Vec<_>>();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].location, 0 .. 1);
+        assert_eq!(output[0].format, Format::R32G32B32Sfloat);
+        assert_eq!(output[0].name.as_ref().map(|n| &**n), Some("v_color"));
+    }
+
+    #[test]
+    fn vertex_shader_layout() {
+        let doc = spirv::parse_spirv(&to_bytes(&vertex_shader(true), false)).unwrap();
+        let entry_points = reflect_entry_points(&doc, MAX_SETS, MAX_BINDINGS).unwrap();
+        let layout = entry_points[0].layout();
+        let vertex = ShaderStages {
+            vertex: true,
+            ..ShaderStages::none()
+        };
+
+        assert_eq!(layout.num_sets(), 2);
+        assert_eq!(layout.num_bindings_in_set(0), Some(2));
+        assert_eq!(layout.num_bindings_in_set(1), Some(3));
+
+        let uniform = layout.descriptor(0, 0).unwrap();
+        assert_eq!(uniform.ty,
+                   DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                                dynamic: Some(false),
+                                                storage: false,
+                                            }));
+        assert_eq!(uniform.array_count, 1);
+        assert_eq!(uniform.stages, vertex);
+        assert!(uniform.readonly);
+
+        let storage = layout.descriptor(0, 1).unwrap();
+        assert_eq!(storage.ty,
+                   DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                                                dynamic: Some(false),
+                                                storage: true,
+                                            }));
+        assert!(storage.readonly);
+
+        assert!(layout.descriptor(1, 0).is_none());
+        assert!(layout.descriptor(1, 1).is_none());
+        let texture = layout.descriptor(1, 2).unwrap();
+        assert_eq!(texture.ty,
+                   DescriptorDescTy::CombinedImageSampler(DescriptorImageDesc {
+                       sampled: true,
+                       dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                       format: None,
+                       multisampled: false,
+                       array_layers: DescriptorImageDescArray::NonArrayed,
+                   }));
+        assert_eq!(texture.array_count, 4);
+
+        assert_eq!(layout.num_push_constants_ranges(), 1);
+        let push_constants = layout.push_constants_range(0).unwrap();
+        assert_eq!(push_constants.offset, 0);
+        assert_eq!(push_constants.size, 20);
+        assert_eq!(push_constants.stages, vertex);
+    }
+
+    #[test]
+    fn big_endian() {
+        let doc = spirv::parse_spirv(&to_bytes(&vertex_shader(true), true)).unwrap();
+        assert_eq!(doc.words, vertex_shader(true));
+        assert_eq!(reflect_entry_points(&doc, MAX_SETS, MAX_BINDINGS).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn invalid_header() {
+        match spirv::parse_spirv(&[0; 20]) {
+            Err(ShaderReflectionError::InvalidHeader) => (),
+            _ => panic!(),
+        }
+
+        match spirv::parse_spirv(&[0x03, 0x02, 0x23, 0x07]) {
+            Err(ShaderReflectionError::InvalidHeader) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn truncated_instruction() {
+        let mut words = vertex_shader(true);
+        words.push((3 << 16) | 5); // OpName with only one operand
+        words.push(10);
+
+        match spirv::parse_spirv(&to_bytes(&words, false)) {
+            Err(ShaderReflectionError::IncompleteInstruction) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn missing_location() {
+        let doc = spirv::parse_spirv(&to_bytes(&vertex_shader(false), false)).unwrap();
+        match reflect_entry_points(&doc, MAX_SETS, MAX_BINDINGS) {
+            Err(ShaderReflectionError::MissingLocation { id: 11 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn descriptor_out_of_range() {
+        let doc = spirv::parse_spirv(&to_bytes(&vertex_shader(true), false)).unwrap();
+
+        // The texture uses the set 1 and the binding 2.
+        match reflect_entry_points(&doc, 1, MAX_BINDINGS) {
+            Err(ShaderReflectionError::DescriptorOutOfRange { id: 63 }) => (),
+            _ => panic!(),
+        }
+
+        match reflect_entry_points(&doc, MAX_SETS, 2) {
+            Err(ShaderReflectionError::DescriptorOutOfRange { id: 63 }) => (),
+            _ => panic!(),
+        }
+
+        assert!(reflect_entry_points(&doc, 2, 3).is_ok());
+    }
+
+    #[test]
+    fn location_overflow() {
+        let mut words = vertex_shader(true);
+        let decoration = words
+            .windows(3)
+            .position(|w| w == [11, spirv::DECORATION_LOCATION, 1])
+            .unwrap();
+        words[decoration + 2] = 0xffffffff;
+
+        let doc = spirv::parse_spirv(&to_bytes(&words, false)).unwrap();
+        match reflect_entry_points(&doc, MAX_SETS, MAX_BINDINGS) {
+            Err(ShaderReflectionError::LocationOutOfRange { id: 11 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn reflect_module() {
+        let (_, device, _) = mock_dev_and_queue!();
+
+        let reflected = unsafe {
+            ShaderModule::reflect(device, &to_bytes(&vertex_shader(true), false)).unwrap()
+        };
+
+        assert_eq!(reflected.entry_points().len(), 1);
+        assert!(reflected.graphics_entry_point("main").is_some());
+        assert!(reflected.compute_entry_point("main").is_none());
+        assert!(reflected.graphics_entry_point("foo").is_none());
+    }
+}
@@ -0,0 +1,334 @@
+// Copyright (c) 2018 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Minimal SPIR-V parser.
+//!
+//! Only the instructions that are needed in order to reflect the interface of a shader module
+//! are decoded. This is a trimmed-down version of the parser of the `vulkano-shaders` crate.
+
+use pipeline::shader::ShaderReflectionError;
+
+// Values of the SPIR-V enumerations that we care about.
+pub const DECORATION_BLOCK: u32 = 2;
+pub const DECORATION_BUFFER_BLOCK: u32 = 3;
+pub const DECORATION_ARRAY_STRIDE: u32 = 6;
+pub const DECORATION_MATRIX_STRIDE: u32 = 7;
+pub const DECORATION_BUILT_IN: u32 = 11;
+pub const DECORATION_NON_WRITABLE: u32 = 24;
+pub const DECORATION_LOCATION: u32 = 30;
+pub const DECORATION_BINDING: u32 = 33;
+pub const DECORATION_DESCRIPTOR_SET: u32 = 34;
+pub const DECORATION_OFFSET: u32 = 35;
+
+pub const STORAGE_CLASS_INPUT: u32 = 1;
+pub const STORAGE_CLASS_OUTPUT: u32 = 3;
+pub const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+pub const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+pub const EXECUTION_MODEL_VERTEX: u32 = 0;
+pub const EXECUTION_MODEL_TESSELLATION_CONTROL: u32 = 1;
+pub const EXECUTION_MODEL_TESSELLATION_EVALUATION: u32 = 2;
+pub const EXECUTION_MODEL_GEOMETRY: u32 = 3;
+pub const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+pub const EXECUTION_MODEL_GL_COMPUTE: u32 = 5;
+
+pub const EXECUTION_MODE_INPUT_POINTS: u32 = 19;
+pub const EXECUTION_MODE_INPUT_LINES: u32 = 20;
+pub const EXECUTION_MODE_INPUT_LINES_ADJACENCY: u32 = 21;
+pub const EXECUTION_MODE_TRIANGLES: u32 = 22;
+pub const EXECUTION_MODE_INPUT_TRIANGLES_ADJACENCY: u32 = 23;
+
+pub const DIM_1D: u32 = 0;
+pub const DIM_2D: u32 = 1;
+pub const DIM_3D: u32 = 2;
+pub const DIM_CUBE: u32 = 3;
+pub const DIM_BUFFER: u32 = 5;
+pub const DIM_SUBPASS_DATA: u32 = 6;
+
+/// Magic number at the start of a SPIR-V module.
+const MAGIC: u32 = 0x07230203;
+
+/// A parsed SPIR-V module.
+#[derive(Debug, Clone)]
+pub struct Spirv {
+    /// The words of the module, in the native endianness.
+    pub words: Vec<u32>,
+    /// The decoded instructions.
+    pub instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    Unknown,
+    Name { target_id: u32, name: String },
+    EntryPoint {
+        execution: u32,
+        id: u32,
+        name: String,
+        interface: Vec<u32>,
+    },
+    ExecutionMode { target_id: u32, mode: u32 },
+    TypeInt {
+        result_id: u32,
+        width: u32,
+        signedness: bool,
+    },
+    TypeFloat { result_id: u32, width: u32 },
+    TypeVector {
+        result_id: u32,
+        component_id: u32,
+        count: u32,
+    },
+    TypeMatrix {
+        result_id: u32,
+        column_type_id: u32,
+        column_count: u32,
+    },
+    TypeImage {
+        result_id: u32,
+        dim: u32,
+        arrayed: bool,
+        ms: bool,
+        sampled: u32,
+    },
+    TypeSampler { result_id: u32 },
+    TypeSampledImage { result_id: u32, image_type_id: u32 },
+    TypeArray {
+        result_id: u32,
+        type_id: u32,
+        length_id: u32,
+    },
+    TypeRuntimeArray { result_id: u32, type_id: u32 },
+    TypeStruct {
+        result_id: u32,
+        member_types: Vec<u32>,
+    },
+    TypePointer {
+        result_id: u32,
+        storage_class: u32,
+        type_id: u32,
+    },
+    Constant {
+        result_type_id: u32,
+        result_id: u32,
+        data: Vec<u32>,
+    },
+    Variable {
+        result_type_id: u32,
+        result_id: u32,
+        storage_class: u32,
+    },
+    Decorate {
+        target_id: u32,
+        decoration: u32,
+        params: Vec<u32>,
+    },
+    MemberDecorate {
+        target_id: u32,
+        member: u32,
+        decoration: u32,
+        params: Vec<u32>,
+    },
+}
+
+/// Parses a SPIR-V module, in either endianness.
+pub fn parse_spirv(data: &[u8]) -> Result<Spirv, ShaderReflectionError> {
+    if data.len() < 20 || data.len() % 4 != 0 {
+        return Err(ShaderReflectionError::InvalidHeader);
+    }
+
+    let big_endian = |c: &[u8]| {
+        ((c[0] as u32) << 24) | ((c[1] as u32) << 16) | ((c[2] as u32) << 8) | c[3] as u32
+    };
+    let little_endian = |c: &[u8]| {
+        ((c[3] as u32) << 24) | ((c[2] as u32) << 16) | ((c[1] as u32) << 8) | c[0] as u32
+    };
+
+    // We need to determine whether the module is in big endian or little endian order depending
+    // on the magic number at the start of the data.
+    let words = if big_endian(&data[0 .. 4]) == MAGIC {
+        data.chunks(4).map(big_endian).collect::<Vec<_>>()
+    } else if little_endian(&data[0 .. 4]) == MAGIC {
+        data.chunks(4).map(little_endian).collect::<Vec<_>>()
+    } else {
+        return Err(ShaderReflectionError::InvalidHeader);
+    };
+
+    let instructions = {
+        let mut ret = Vec::new();
+        let mut i = &words[5 ..];
+        while !i.is_empty() {
+            let (instruction, rest) = parse_instruction(i)?;
+            ret.push(instruction);
+            i = rest;
+        }
+        ret
+    };
+
+    Ok(Spirv {
+           words: words,
+           instructions: instructions,
+       })
+}
+
+fn parse_instruction(i: &[u32]) -> Result<(Instruction, &[u32]), ShaderReflectionError> {
+    let word_count = (i[0] >> 16) as usize;
+    let opcode = (i[0] & 0xffff) as u16;
+
+    if word_count == 0 || i.len() < word_count {
+        return Err(ShaderReflectionError::IncompleteInstruction);
+    }
+
+    let instruction = decode_instruction(opcode, &i[1 .. word_count])?;
+    Ok((instruction, &i[word_count ..]))
+}
+
+fn decode_instruction(opcode: u16, operands: &[u32]) -> Result<Instruction, ShaderReflectionError> {
+    // Minimum number of operands of each instruction we decode.
+    let min_operands = match opcode {
+        5 => 1,
+        15 => 2,
+        16 => 2,
+        21 => 3,
+        22 => 2,
+        23 => 3,
+        24 => 3,
+        25 => 8,
+        26 => 1,
+        27 => 2,
+        28 => 3,
+        29 => 2,
+        30 => 1,
+        32 => 3,
+        43 => 2,
+        59 => 3,
+        71 => 2,
+        72 => 3,
+        _ => return Ok(Instruction::Unknown),
+    };
+
+    if operands.len() < min_operands {
+        return Err(ShaderReflectionError::IncompleteInstruction);
+    }
+
+    Ok(match opcode {
+           5 => Instruction::Name {
+               target_id: operands[0],
+               name: parse_string(&operands[1 ..])?.0,
+           },
+           15 => {
+               let (name, rest) = parse_string(&operands[2 ..])?;
+               Instruction::EntryPoint {
+                   execution: operands[0],
+                   id: operands[1],
+                   name: name,
+                   interface: rest.to_owned(),
+               }
+           },
+           16 => Instruction::ExecutionMode {
+               target_id: operands[0],
+               mode: operands[1],
+           },
+           21 => Instruction::TypeInt {
+               result_id: operands[0],
+               width: operands[1],
+               signedness: operands[2] != 0,
+           },
+           22 => Instruction::TypeFloat {
+               result_id: operands[0],
+               width: operands[1],
+           },
+           23 => Instruction::TypeVector {
+               result_id: operands[0],
+               component_id: operands[1],
+               count: operands[2],
+           },
+           24 => Instruction::TypeMatrix {
+               result_id: operands[0],
+               column_type_id: operands[1],
+               column_count: operands[2],
+           },
+           25 => Instruction::TypeImage {
+               result_id: operands[0],
+               dim: operands[2],
+               arrayed: operands[4] != 0,
+               ms: operands[5] != 0,
+               sampled: operands[6],
+           },
+           26 => Instruction::TypeSampler { result_id: operands[0] },
+           27 => Instruction::TypeSampledImage {
+               result_id: operands[0],
+               image_type_id: operands[1],
+           },
+           28 => Instruction::TypeArray {
+               result_id: operands[0],
+               type_id: operands[1],
+               length_id: operands[2],
+           },
+           29 => Instruction::TypeRuntimeArray {
+               result_id: operands[0],
+               type_id: operands[1],
+           },
+           30 => Instruction::TypeStruct {
+               result_id: operands[0],
+               member_types: operands[1 ..].to_owned(),
+           },
+           32 => Instruction::TypePointer {
+               result_id: operands[0],
+               storage_class: operands[1],
+               type_id: operands[2],
+           },
+           43 => Instruction::Constant {
+               result_type_id: operands[0],
+               result_id: operands[1],
+               data: operands[2 ..].to_owned(),
+           },
+           59 => Instruction::Variable {
+               result_type_id: operands[0],
+               result_id: operands[1],
+               storage_class: operands[2],
+           },
+           71 => Instruction::Decorate {
+               target_id: operands[0],
+               decoration: operands[1],
+               params: operands[2 ..].to_owned(),
+           },
+           72 => Instruction::MemberDecorate {
+               target_id: operands[0],
+               member: operands[1],
+               decoration: operands[2],
+               params: operands[3 ..].to_owned(),
+           },
+           _ => unreachable!(),
+       })
+}
+
+// Parses a nul-terminated UTF-8 string, and returns the string and the remaining operands.
+fn parse_string(data: &[u32]) -> Result<(String, &[u32]), ShaderReflectionError> {
+    let bytes = data.iter()
+        .flat_map(|&n| {
+                      let b1 = (n & 0xff) as u8;
+                      let b2 = ((n >> 8) & 0xff) as u8;
+                      let b3 = ((n >> 16) & 0xff) as u8;
+                      let b4 = ((n >> 24) & 0xff) as u8;
+                      vec![b1, b2, b3, b4].into_iter()
+                  })
+        .take_while(|&b| b != 0)
+        .collect::<Vec<u8>>();
+
+    let r = 1 + bytes.len() / 4;
+    if r > data.len() {
+        return Err(ShaderReflectionError::IncompleteInstruction);
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(s) => Ok((s, &data[r ..])),
+        Err(_) => Err(ShaderReflectionError::InvalidString),
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::mem;
+use std::sync::Arc;
+use std::vec::IntoIter as VecIntoIter;
+
+use buffer::BufferAccess;
+use pipeline::shader::ShaderInterfaceDef;
+use pipeline::vertex::AttributeInfo;
+use pipeline::vertex::IncompatibleVertexDefinitionError;
+use pipeline::vertex::InputRate;
+use pipeline::vertex::Vertex;
+use pipeline::vertex::VertexDefinition;
+use pipeline::vertex::VertexMemberInfo;
+use pipeline::vertex::VertexSource;
+
+/// A vertex definition made of any number of buffers, each of them containing one type of
+/// vertex and being read either once per vertex or once per instance.
+///
+/// Buffers are numbered in the order in which they are added, starting from 0. When drawing, the
+/// buffers must be passed in the same order, and there must be exactly one buffer per buffer of
+/// the definition or drawing panics.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate vulkano;
+/// # fn main() {
+/// use vulkano::pipeline::vertex::BuffersDefinition;
+///
+/// #[derive(Default, Copy, Clone)]
+/// struct Position { position: [f32; 3] }
+/// impl_vertex!(Position, position);
+///
+/// #[derive(Default, Copy, Clone)]
+/// struct Normal { normal: [f32; 3] }
+/// impl_vertex!(Normal, normal);
+///
+/// #[derive(Default, Copy, Clone)]
+/// struct Instance { offset: [f32; 2] }
+/// impl_vertex!(Instance, offset);
+///
+/// let definition = BuffersDefinition::new()
+///     .vertex::<Position>()
+///     .vertex::<Normal>()
+///     .instance::<Instance>();
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct BuffersDefinition {
+    buffers: Vec<BufferInfo>,
+}
+
+#[derive(Copy, Clone)]
+struct BufferInfo {
+    stride: usize,
+    input_rate: InputRate,
+    member: fn(&str) -> Option<VertexMemberInfo>,
+}
+
+impl BuffersDefinition {
+    /// Builds a new definition that doesn't contain any buffer.
+    #[inline]
+    pub fn new() -> BuffersDefinition {
+        BuffersDefinition { buffers: Vec::new() }
+    }
+
+    /// Adds a buffer whose elements of type `V` are read once per vertex.
+    #[inline]
+    pub fn vertex<V: Vertex>(self) -> Self {
+        self.buffer::<V>(InputRate::Vertex)
+    }
+
+    /// Adds a buffer whose elements of type `V` are read once per instance.
+    #[inline]
+    pub fn instance<V: Vertex>(self) -> Self {
+        self.buffer::<V>(InputRate::Instance)
+    }
+
+    #[inline]
+    fn buffer<V: Vertex>(mut self, input_rate: InputRate) -> Self {
+        self.buffers.push(BufferInfo {
+                              stride: mem::size_of::<V>(),
+                              input_rate: input_rate,
+                              member: <V as Vertex>::member,
+                          });
+        self
+    }
+}
+
+unsafe impl<I> VertexDefinition<I> for BuffersDefinition
+    where I: ShaderInterfaceDef
+{
+    type BuffersIter = VecIntoIter<(u32, usize, InputRate)>;
+    type AttribsIter = VecIntoIter<(u32, u32, AttributeInfo)>;
+
+    fn definition(
+        &self, interface: &I)
+        -> Result<(Self::BuffersIter, Self::AttribsIter), IncompatibleVertexDefinitionError> {
+        let mut attribs = Vec::with_capacity(interface.elements().len());
+        for e in interface.elements() {
+            let name = e.name.as_ref().unwrap();
+
+            let (infos, buffer_id) = match self.buffers
+                .iter()
+                .enumerate()
+                .filter_map(|(id, buffer)| (buffer.member)(name).map(|infos| (infos, id)))
+                .next() {
+                Some(found) => found,
+                None => {
+                    return Err(IncompatibleVertexDefinitionError::MissingAttribute {
+                                   attribute: name.clone().into_owned(),
+                               });
+                },
+            };
+
+            if !infos.ty.matches(infos.array_size,
+                                 e.format,
+                                 e.location.end - e.location.start)
+            {
+                return Err(IncompatibleVertexDefinitionError::FormatMismatch {
+                               attribute: name.clone().into_owned(),
+                               shader: (e.format, (e.location.end - e.location.start) as usize),
+                               definition: (infos.ty, infos.array_size),
+                           });
+            }
+
+            let mut offset = infos.offset;
+            for loc in e.location.clone() {
+                attribs.push((loc,
+                              buffer_id as u32,
+                              AttributeInfo {
+                                  offset: offset,
+                                  format: e.format,
+                              }));
+                offset += e.format.size().unwrap();
+            }
+        }
+
+        let buffers = self.buffers
+            .iter()
+            .enumerate()
+            .map(|(id, buffer)| (id as u32, buffer.stride, buffer.input_rate))
+            .collect::<Vec<_>>();
+
+        Ok((buffers.into_iter(), attribs.into_iter()))
+    }
+}
+
+unsafe impl VertexSource<Vec<Arc<BufferAccess + Send + Sync>>> for BuffersDefinition {
+    #[inline]
+    fn decode(&self, source: Vec<Arc<BufferAccess + Send + Sync>>)
+              -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
+        let strides = self.buffers.iter().map(|b| (b.stride, b.input_rate));
+        decode_buffers(strides, source)
+    }
+}
+
+/// Implementation of `VertexSource::decode` for a list of buffers whose stride and input rate
+/// are given by `strides`.
+///
+/// The number of vertices (or instances) is the minimum number of elements contained by the
+/// per-vertex (or per-instance) buffers. Buffers with a stride of 0 are ignored in this count.
+///
+/// # Panic
+///
+/// - Panics if the number of buffers in `source` is not the number of elements of `strides`.
+///
+pub(crate) fn decode_buffers<S>(strides: S, source: Vec<Arc<BufferAccess + Send + Sync>>)
+                                -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize)
+    where S: ExactSizeIterator<Item = (usize, InputRate)>
+{
+    assert_eq!(source.len(), strides.len());
+
+    let mut vertices = None;
+    let mut instances = None;
+
+    for ((stride, input_rate), buffer) in strides.zip(source.iter()) {
+        if stride == 0 {
+            continue;
+        }
+
+        let num = buffer.size() / stride;
+        let count = match input_rate {
+            InputRate::Vertex => &mut vertices,
+            InputRate::Instance => &mut instances,
+        };
+        *count = Some(count.map_or(num, |c: usize| c.min(num)));
+    }
+
+    let buffers = source
+        .into_iter()
+        .map(|buffer| Box::new(buffer) as Box<_>)
+        .collect();
+    (buffers, vertices.unwrap_or(0), instances.unwrap_or(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::sync::Arc;
+
+    use buffer::BufferAccess;
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use format::Format;
+    use pipeline::shader::RuntimeShaderInterfaceDef;
+    use pipeline::shader::ShaderInterfaceDefEntry;
+    use pipeline::vertex::BuffersDefinition;
+    use pipeline::vertex::IncompatibleVertexDefinitionError;
+    use pipeline::vertex::Vertex;
+    use pipeline::vertex::VertexDefinition;
+    use pipeline::vertex::VertexMemberInfo;
+    use pipeline::vertex::VertexMemberTy;
+    use pipeline::vertex::VertexSource;
+
+    #[derive(Debug, Copy, Clone)]
+    struct Position([f32; 3]);
+    unsafe impl Vertex for Position {
+        fn member(name: &str) -> Option<VertexMemberInfo> {
+            if name != "position" {
+                return None;
+            }
+
+            Some(VertexMemberInfo {
+                     offset: 0,
+                     ty: VertexMemberTy::F32,
+                     array_size: 3,
+                 })
+        }
+    }
+
+    #[derive(Debug, Copy, Clone)]
+    struct Offset([f32; 2]);
+    unsafe impl Vertex for Offset {
+        fn member(name: &str) -> Option<VertexMemberInfo> {
+            if name != "offset" {
+                return None;
+            }
+
+            Some(VertexMemberInfo {
+                     offset: 0,
+                     ty: VertexMemberTy::F32,
+                     array_size: 2,
+                 })
+        }
+    }
+
+    fn interface(entries: &[(u32, Format, &'static str)]) -> RuntimeShaderInterfaceDef {
+        RuntimeShaderInterfaceDef::new(entries.iter().map(|&(loc, format, name)| {
+            ShaderInterfaceDefEntry {
+                location: loc .. loc + 1,
+                format: format,
+                name: Some(Cow::Borrowed(name)),
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn definition() {
+        let definition = BuffersDefinition::new()
+            .vertex::<Position>()
+            .vertex::<()>()
+            .instance::<Offset>();
+        let interface = interface(&[(0, Format::R32G32B32Sfloat, "position"),
+                                    (1, Format::R32G32Sfloat, "offset")]);
+
+        let (buffers, attribs) = definition.definition(&interface).unwrap();
+        let buffers = buffers.map(|(id, stride, _)| (id, stride)).collect::<Vec<_>>();
+        assert_eq!(buffers, vec![(0, 12), (1, 0), (2, 8)]);
+        let attribs = attribs.map(|(loc, id, _)| (loc, id)).collect::<Vec<_>>();
+        assert_eq!(attribs, vec![(0, 0), (1, 2)]);
+    }
+
+    #[test]
+    fn missing_attribute() {
+        let definition = BuffersDefinition::new().vertex::<Position>();
+        let interface = interface(&[(0, Format::R32G32Sfloat, "offset")]);
+
+        match definition.definition(&interface) {
+            Err(IncompatibleVertexDefinitionError::MissingAttribute { .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn decode() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let definition = BuffersDefinition::new()
+            .vertex::<Position>()
+            .vertex::<Position>()
+            .instance::<Offset>();
+
+        let positions = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                       BufferUsage::all(),
+                                                       (0 .. 6).map(|_| Position([0.0; 3])))
+            .unwrap();
+        let normals = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                     BufferUsage::all(),
+                                                     (0 .. 4).map(|_| Position([0.0; 3])))
+            .unwrap();
+        let offsets = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                     BufferUsage::all(),
+                                                     (0 .. 3).map(|_| Offset([0.0; 2])))
+            .unwrap();
+
+        let source = vec![positions as Arc<BufferAccess + Send + Sync>,
+                          normals as Arc<_>,
+                          offsets as Arc<_>];
+        let (buffers, vertices, instances) = definition.decode(source);
+        assert_eq!(buffers.len(), 3);
+        assert_eq!(vertices, 4);
+        assert_eq!(instances, 3);
+    }
+}
@@ -59,6 +59,7 @@ pub enum InputRate {
 
 /// Information about a single attribute within a vertex.
 /// TODO: change that API
+#[derive(Debug, Copy, Clone)]
 pub struct AttributeInfo {
     /// Number of bytes between the start of a vertex and the location of attribute.
     pub offset: usize,
@@ -84,6 +85,26 @@ pub enum IncompatibleVertexDefinitionError {
         /// The format in the vertex definition.
         definition: (VertexMemberTy, usize),
     },
+
+    /// The format of an attribute of a runtime vertex definition does not match.
+    AttributeFormatMismatch {
+        /// Name of the attribute.
+        attribute: String,
+        /// The format in the vertex shader.
+        shader: Format,
+        /// The format in the vertex definition.
+        definition: Format,
+    },
+
+    /// An attribute of a runtime vertex definition doesn't fit in an element of its buffer.
+    AttributeOutOfBounds {
+        /// Name of the attribute.
+        attribute: String,
+        /// Offset of the end of the attribute within an element.
+        end: usize,
+        /// Stride of the buffer.
+        stride: usize,
+    },
 }
 
 impl error::Error for IncompatibleVertexDefinitionError {
@@ -91,9 +112,13 @@ impl error::Error for IncompatibleVertexDefinitionError {
     fn description(&self) -> &str {
         match *self {
             IncompatibleVertexDefinitionError::MissingAttribute { .. } => "an attribute is missing",
-            IncompatibleVertexDefinitionError::FormatMismatch { .. } => {
+            IncompatibleVertexDefinitionError::FormatMismatch { .. } |
+            IncompatibleVertexDefinitionError::AttributeFormatMismatch { .. } => {
                 "the format of an attribute does not match"
             },
+            IncompatibleVertexDefinitionError::AttributeOutOfBounds { .. } => {
+                "an attribute doesn't fit in an element of its buffer"
+            },
         }
     }
 }
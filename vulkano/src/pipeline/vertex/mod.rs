@@ -65,6 +65,7 @@
 
 pub use self::bufferless::BufferlessDefinition;
 pub use self::bufferless::BufferlessVertices;
+pub use self::buffers::BuffersDefinition;
 pub use self::definition::AttributeInfo;
 pub use self::definition::IncompatibleVertexDefinitionError;
 pub use self::definition::InputRate;
@@ -72,15 +73,18 @@ pub use self::definition::VertexDefinition;
 pub use self::definition::VertexSource;
 pub use self::impl_vertex::VertexMember;
 pub use self::one_one::OneVertexOneInstanceDefinition;
+pub use self::runtime::RuntimeVertexDefinition;
 pub use self::single::SingleBufferDefinition;
 pub use self::two::TwoBuffersDefinition;
 pub use self::vertex::Vertex;
 pub use self::vertex::VertexMemberInfo;
 pub use self::vertex::VertexMemberTy;
 
+mod buffers;
 mod definition;
 mod impl_vertex;
 mod one_one;
+mod runtime;
 mod single;
 mod two;
 mod vertex;
@@ -0,0 +1,286 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+use std::vec::IntoIter as VecIntoIter;
+
+use buffer::BufferAccess;
+use pipeline::shader::ShaderInterfaceDef;
+use pipeline::vertex::AttributeInfo;
+use pipeline::vertex::IncompatibleVertexDefinitionError;
+use pipeline::vertex::InputRate;
+use pipeline::vertex::VertexDefinition;
+use pipeline::vertex::VertexSource;
+use pipeline::vertex::buffers::decode_buffers;
+
+/// A vertex definition whose buffers and attributes are only known at runtime.
+///
+/// Each buffer is described by its stride and by the list of attributes it contains. An
+/// attribute is identified by the name of the vertex shader input it corresponds to, and its
+/// `AttributeInfo` gives its offset within an element of the buffer and its format. If the
+/// shader input covers multiple locations, the attribute is made of consecutive values of the
+/// same format.
+///
+/// Buffers are numbered in the order in which they are added, starting from 0. When drawing, the
+/// buffers must be passed in the same order, and there must be exactly one buffer per buffer of
+/// the definition or drawing panics.
+///
+/// Creating a pipeline returns an error if an attribute doesn't fit within the stride of its
+/// buffer. This isn't checked for buffers whose stride is 0, since all the vertices then read the
+/// same element.
+///
+/// # Example
+///
+/// ```
+/// use vulkano::format::Format;
+/// use vulkano::pipeline::vertex::AttributeInfo;
+/// use vulkano::pipeline::vertex::RuntimeVertexDefinition;
+///
+/// let definition = RuntimeVertexDefinition::new()
+///     .vertex(24, vec![
+///         ("position", AttributeInfo { offset: 0, format: Format::R32G32B32Sfloat }),
+///         ("normal", AttributeInfo { offset: 12, format: Format::R32G32B32Sfloat }),
+///     ])
+///     .instance(8, vec![
+///         ("offset", AttributeInfo { offset: 0, format: Format::R32G32Sfloat }),
+///     ]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeVertexDefinition {
+    buffers: Vec<RuntimeVertexBuffer>,
+}
+
+#[derive(Debug, Clone)]
+struct RuntimeVertexBuffer {
+    stride: usize,
+    input_rate: InputRate,
+    attributes: Vec<(String, AttributeInfo)>,
+}
+
+impl RuntimeVertexDefinition {
+    /// Builds a new definition that doesn't contain any buffer.
+    #[inline]
+    pub fn new() -> RuntimeVertexDefinition {
+        RuntimeVertexDefinition { buffers: Vec::new() }
+    }
+
+    /// Adds a buffer whose elements are read once per vertex. `stride` is the number of bytes
+    /// between two consecutive elements.
+    #[inline]
+    pub fn vertex<I, S>(self, stride: usize, attributes: I) -> Self
+        where I: IntoIterator<Item = (S, AttributeInfo)>,
+              S: Into<String>
+    {
+        self.buffer(stride, InputRate::Vertex, attributes)
+    }
+
+    /// Adds a buffer whose elements are read once per instance. `stride` is the number of bytes
+    /// between two consecutive elements.
+    #[inline]
+    pub fn instance<I, S>(self, stride: usize, attributes: I) -> Self
+        where I: IntoIterator<Item = (S, AttributeInfo)>,
+              S: Into<String>
+    {
+        self.buffer(stride, InputRate::Instance, attributes)
+    }
+
+    /// Adds a buffer with the given stride and input rate.
+    pub fn buffer<I, S>(mut self, stride: usize, input_rate: InputRate, attributes: I) -> Self
+        where I: IntoIterator<Item = (S, AttributeInfo)>,
+              S: Into<String>
+    {
+        self.buffers.push(RuntimeVertexBuffer {
+                              stride: stride,
+                              input_rate: input_rate,
+                              attributes: attributes
+                                  .into_iter()
+                                  .map(|(name, info)| (name.into(), info))
+                                  .collect(),
+                          });
+        self
+    }
+}
+
+unsafe impl<I> VertexDefinition<I> for RuntimeVertexDefinition
+    where I: ShaderInterfaceDef
+{
+    type BuffersIter = VecIntoIter<(u32, usize, InputRate)>;
+    type AttribsIter = VecIntoIter<(u32, u32, AttributeInfo)>;
+
+    fn definition(
+        &self, interface: &I)
+        -> Result<(Self::BuffersIter, Self::AttribsIter), IncompatibleVertexDefinitionError> {
+        let mut attribs = Vec::with_capacity(interface.elements().len());
+        for e in interface.elements() {
+            let name = e.name.as_ref().unwrap();
+
+            let found = self.buffers
+                .iter()
+                .enumerate()
+                .filter_map(|(id, buffer)| {
+                    buffer
+                        .attributes
+                        .iter()
+                        .find(|&&(ref n, _)| n == name)
+                        .map(|&(_, info)| (info, id, buffer.stride))
+                })
+                .next();
+
+            let (info, buffer_id, stride) = match found {
+                Some(found) => found,
+                None => {
+                    return Err(IncompatibleVertexDefinitionError::MissingAttribute {
+                                   attribute: name.clone().into_owned(),
+                               });
+                },
+            };
+
+            if info.format != e.format {
+                return Err(IncompatibleVertexDefinitionError::AttributeFormatMismatch {
+                               attribute: name.clone().into_owned(),
+                               shader: e.format,
+                               definition: info.format,
+                           });
+            }
+
+            let num_locations = (e.location.end - e.location.start) as usize;
+            let end = info.offset + e.format.size().unwrap() * num_locations;
+            if stride != 0 && end > stride {
+                return Err(IncompatibleVertexDefinitionError::AttributeOutOfBounds {
+                               attribute: name.clone().into_owned(),
+                               end: end,
+                               stride: stride,
+                           });
+            }
+
+            let mut offset = info.offset;
+            for loc in e.location.clone() {
+                attribs.push((loc,
+                              buffer_id as u32,
+                              AttributeInfo {
+                                  offset: offset,
+                                  format: e.format,
+                              }));
+                offset += e.format.size().unwrap();
+            }
+        }
+
+        let buffers = self.buffers
+            .iter()
+            .enumerate()
+            .map(|(id, buffer)| (id as u32, buffer.stride, buffer.input_rate))
+            .collect::<Vec<_>>();
+
+        Ok((buffers.into_iter(), attribs.into_iter()))
+    }
+}
+
+unsafe impl VertexSource<Vec<Arc<BufferAccess + Send + Sync>>> for RuntimeVertexDefinition {
+    #[inline]
+    fn decode(&self, source: Vec<Arc<BufferAccess + Send + Sync>>)
+              -> (Vec<Box<BufferAccess + Send + Sync>>, usize, usize) {
+        let strides = self.buffers.iter().map(|b| (b.stride, b.input_rate));
+        decode_buffers(strides, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use format::Format;
+    use pipeline::shader::RuntimeShaderInterfaceDef;
+    use pipeline::shader::ShaderInterfaceDefEntry;
+    use pipeline::vertex::AttributeInfo;
+    use pipeline::vertex::IncompatibleVertexDefinitionError;
+    use pipeline::vertex::RuntimeVertexDefinition;
+    use pipeline::vertex::VertexDefinition;
+
+    fn definition() -> RuntimeVertexDefinition {
+        RuntimeVertexDefinition::new()
+            .vertex(24,
+                    vec![("position",
+                          AttributeInfo {
+                              offset: 0,
+                              format: Format::R32G32B32Sfloat,
+                          }),
+                         ("normal",
+                          AttributeInfo {
+                              offset: 12,
+                              format: Format::R32G32B32Sfloat,
+                          })])
+            .instance(32,
+                      vec![("transform",
+                            AttributeInfo {
+                                offset: 0,
+                                format: Format::R32G32B32A32Sfloat,
+                            })])
+    }
+
+    #[test]
+    fn matrix_attribute() {
+        let interface = RuntimeShaderInterfaceDef::new(vec![
+            ShaderInterfaceDefEntry {
+                location: 0 .. 1,
+                format: Format::R32G32B32Sfloat,
+                name: Some(Cow::Borrowed("normal")),
+            },
+            ShaderInterfaceDefEntry {
+                location: 1 .. 3,
+                format: Format::R32G32B32A32Sfloat,
+                name: Some(Cow::Borrowed("transform")),
+            },
+        ]).unwrap();
+
+        let (buffers, attribs) = definition().definition(&interface).unwrap();
+        let buffers = buffers.map(|(id, stride, _)| (id, stride)).collect::<Vec<_>>();
+        assert_eq!(buffers, vec![(0, 24), (1, 32)]);
+        let attribs = attribs
+            .map(|(loc, id, info)| (loc, id, info.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(attribs, vec![(0, 0, 12), (1, 1, 0), (2, 1, 16)]);
+    }
+
+    #[test]
+    fn format_mismatch() {
+        let interface = RuntimeShaderInterfaceDef::new(vec![
+            ShaderInterfaceDefEntry {
+                location: 0 .. 1,
+                format: Format::R32G32B32A32Sfloat,
+                name: Some(Cow::Borrowed("position")),
+            },
+        ]).unwrap();
+
+        match definition().definition(&interface) {
+            Err(IncompatibleVertexDefinitionError::AttributeFormatMismatch { .. }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn attribute_out_of_bounds() {
+        // Three locations of 16 bytes don't fit in the stride of 32 bytes.
+        let interface = RuntimeShaderInterfaceDef::new(vec![
+            ShaderInterfaceDefEntry {
+                location: 0 .. 3,
+                format: Format::R32G32B32A32Sfloat,
+                name: Some(Cow::Borrowed("transform")),
+            },
+        ]).unwrap();
+
+        match definition().definition(&interface) {
+            Err(IncompatibleVertexDefinitionError::AttributeOutOfBounds {
+                    end: 48,
+                    stride: 32,
+                    ..
+                }) => (),
+            _ => panic!(),
+        }
+    }
+}
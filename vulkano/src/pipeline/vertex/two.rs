@@ -23,7 +23,8 @@ use pipeline::vertex::VertexDefinition;
 use pipeline::vertex::VertexSource;
 
 /// Unstable.
-// TODO: shouldn't be just `Two` but `Multi`
+///
+/// See `BuffersDefinition` for a definition made of any number of buffers.
 pub struct TwoBuffersDefinition<T, U>(pub PhantomData<(T, U)>);
 
 impl<T, U> TwoBuffersDefinition<T, U> {
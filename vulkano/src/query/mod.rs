@@ -12,6 +12,28 @@
 //! In Vulkan, queries are not created individually. Instead you manipulate **query pools**, which
 //! represent a collection of queries. Whenever you use a query, you have to specify both the query
 //! pool and the slot id within that query pool.
+//!
+//! Vulkano provides a typed query pool for each type of query: `OcclusionQueryPool`,
+//! `PipelineStatisticsQueryPool` and `TimestampQueryPool`. They can be used with the query
+//! commands of `AutoCommandBufferBuilder`, which keep track of the state of each query in order
+//! to prevent misuses. Once the command buffer has been executed, the results of the queries can
+//! be read with `get_results`.
+//!
+//! ```
+//! # use vulkano::device::Device;
+//! # use std::sync::Arc;
+//! # let device: Arc<Device> = return;
+//! use vulkano::query::QueryResultFlags;
+//! use vulkano::query::TimestampQueryPool;
+//!
+//! let pool = TimestampQueryPool::new(device.clone(), 2).unwrap();
+//!
+//! // ... record and execute a command buffer that writes the timestamps ...
+//!
+//! let mut timestamps = [0u64; 2];
+//! let flags = QueryResultFlags { wait: true, .. QueryResultFlags::none() };
+//! pool.get_results(0, 2, &mut timestamps, flags).unwrap();
+//! ```
 
 use std::error;
 use std::fmt;
@@ -25,12 +47,22 @@ use device::DeviceOwned;
 use Error;
 use OomError;
 use VulkanObject;
+use Success;
 use check_errors;
 use vk;
 
+pub use self::pools::OcclusionQueryPool;
+pub use self::pools::PipelineStatisticsQueryPool;
+pub use self::pools::QueryPoolAccess;
+pub use self::pools::QueryState;
+pub use self::pools::TimestampQueryPool;
+
+mod pools;
+
 pub struct UnsafeQueryPool {
     pool: vk::QueryPool,
     device: Arc<Device>,
+    ty: QueryType,
     num_slots: u32,
 }
 
@@ -72,10 +104,17 @@ impl UnsafeQueryPool {
         Ok(UnsafeQueryPool {
                pool: pool,
                device: device,
+               ty: ty,
                num_slots: num_slots,
            })
     }
 
+    /// Returns the type of the queries of that query pool.
+    #[inline]
+    pub fn ty(&self) -> QueryType {
+        self.ty
+    }
+
     /// Returns the number of slots of that query pool.
     #[inline]
     pub fn num_slots(&self) -> u32 {
@@ -91,6 +130,16 @@ impl UnsafeQueryPool {
         }
     }
 
+    /// Returns the number of values written by each query of that pool, not counting the
+    /// availability value.
+    #[inline]
+    pub fn num_values_per_query(&self) -> u32 {
+        match self.ty {
+            QueryType::PipelineStatistics(flags) => flags.count(),
+            QueryType::Occlusion | QueryType::Timestamp => 1,
+        }
+    }
+
     ///
     /// # Panic
     ///
@@ -99,7 +148,7 @@ impl UnsafeQueryPool {
     pub fn queries_range(&self, first_index: u32, count: u32) -> Option<UnsafeQueriesRange> {
         assert!(count >= 1);
 
-        if first_index + count < self.num_slots() {
+        if first_index.checked_add(count).map_or(false, |end| end <= self.num_slots()) {
             Some(UnsafeQueriesRange {
                      pool: self,
                      first: first_index,
@@ -167,16 +216,128 @@ impl<'a> UnsafeQueriesRange<'a> {
     pub fn count(&self) -> u32 {
         self.count
     }
+
+    /// Calls `vkGetQueryPoolResults` and writes the results of the queries to `destination`.
+    ///
+    /// For each query, `destination` receives the values of the query, followed by the
+    /// availability of the query if `flags.with_availability` is true. The number of values per
+    /// query is given by `UnsafeQueryPool::num_values_per_query`. `T` determines whether the
+    /// results are written as 32 bits or 64 bits integers.
+    ///
+    /// Returns `Ok(true)` if the results of all the queries were available, and `Ok(false)`
+    /// otherwise. In that situation the values of the queries that weren't available are only
+    /// written if `flags.partial` is true.
+    ///
+    /// # Safety
+    ///
+    /// - If `flags.wait` is true, every query of the range must eventually be ended or written by
+    ///   a command buffer that has been submitted, or this function will never return.
+    ///
+    pub unsafe fn get_results<T>(&self, destination: &mut [T], flags: QueryResultFlags)
+                                 -> Result<bool, GetResultsError>
+        where T: QueryResultElement
+    {
+        if flags.partial {
+            if let QueryType::Timestamp = self.pool.ty() {
+                return Err(GetResultsError::InvalidFlags);
+            }
+        }
+
+        let values_per_query = self.pool.num_values_per_query() as usize +
+            if flags.with_availability { 1 } else { 0 };
+        let required_len = values_per_query * self.count as usize;
+        if destination.len() < required_len {
+            return Err(GetResultsError::BufferTooSmall {
+                           required_len: required_len,
+                           actual_len: destination.len(),
+                       });
+        }
+
+        let stride = values_per_query * mem::size_of::<T>();
+
+        let vk = self.pool.device.pointers();
+        let result = check_errors(vk.GetQueryPoolResults(self.pool.device.internal_object(),
+                                                         self.pool.internal_object(),
+                                                         self.first,
+                                                         self.count,
+                                                         required_len * mem::size_of::<T>(),
+                                                         destination.as_mut_ptr() as *mut _,
+                                                         stride as vk::DeviceSize,
+                                                         flags.into_vulkan_bits::<T>()))?;
+
+        match result {
+            Success::Success => Ok(true),
+            Success::NotReady => Ok(false),
+            s => panic!("unexpected success value: {:?}", s),
+        }
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Flags that control how the results of queries are retrieved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QueryResultFlags {
+    /// Wait for the results of all the queries to be available before returning.
+    pub wait: bool,
+    /// Write the availability of each query (`0` or `1`) after its values.
+    pub with_availability: bool,
+    /// Also write the values of the queries whose results aren't available yet. These values are
+    /// between zero and the final result. Not allowed for timestamp queries.
+    pub partial: bool,
+}
+
+impl QueryResultFlags {
+    /// Builds a `QueryResultFlags` with all the flags set to false.
+    #[inline]
+    pub fn none() -> QueryResultFlags {
+        QueryResultFlags {
+            wait: false,
+            with_availability: false,
+            partial: false,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn into_vulkan_bits<T>(self) -> vk::QueryResultFlags
+        where T: QueryResultElement
+    {
+        let mut result = 0;
+        if mem::size_of::<T>() == 8 {
+            result |= vk::QUERY_RESULT_64_BIT;
+        }
+        if self.wait {
+            result |= vk::QUERY_RESULT_WAIT_BIT;
+        }
+        if self.with_availability {
+            result |= vk::QUERY_RESULT_WITH_AVAILABILITY_BIT;
+        }
+        if self.partial {
+            result |= vk::QUERY_RESULT_PARTIAL_BIT;
+        }
+        result
+    }
+}
+
+/// Integer type that the results of queries can be written as.
+///
+/// This trait is implemented on `u32` and `u64`. Results that don't fit in 32 bits are truncated
+/// when written as `u32`.
+pub unsafe trait QueryResultElement: Copy + Send + Sync + 'static {
+}
+
+unsafe impl QueryResultElement for u32 {
+}
+
+unsafe impl QueryResultElement for u64 {
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum QueryType {
     Occlusion,
     PipelineStatistics(QueryPipelineStatisticFlags),
     Timestamp,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct QueryPipelineStatisticFlags {
     pub input_assembly_vertices: bool,
     pub input_assembly_primitives: bool,
@@ -208,6 +369,14 @@ impl QueryPipelineStatisticFlags {
             compute_shader_invocations: false,
         }
     }
+
+    /// Returns the number of flags that are set, which is the number of values written by a
+    /// query that uses these flags.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        let vk: vk::QueryPipelineStatisticFlags = (*self).into();
+        vk.count_ones()
+    }
 }
 
 impl Into<vk::QueryPipelineStatisticFlags> for QueryPipelineStatisticFlags {
@@ -315,61 +484,142 @@ impl From<Error> for QueryPoolCreationError {
     }
 }
 
-pub struct OcclusionQueriesPool {
-    inner: UnsafeQueryPool,
+/// Error that can happen when reading the results of queries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GetResultsError {
+    /// Not enough memory.
+    OomError(OomError),
+    /// The connection to the device has been lost.
+    DeviceLost,
+    /// The range of queries is out of the range of the query pool.
+    OutOfRange,
+    /// The flags are not valid for this type of query.
+    InvalidFlags,
+    /// The destination is too small to hold the results.
+    BufferTooSmall {
+        /// Minimum number of elements of the destination.
+        required_len: usize,
+        /// Actual number of elements of the destination.
+        actual_len: usize,
+    },
+    /// Tried to wait for the result of a query that hasn't been ended or written by a submitted
+    /// command buffer since it was last reset. Waiting would never return.
+    QueryNotEnded {
+        /// Index of the query.
+        index: u32,
+    },
 }
 
-impl OcclusionQueriesPool {
-    /// See the docs of new().
-    pub fn raw(device: Arc<Device>, num_slots: u32) -> Result<OcclusionQueriesPool, OomError> {
-        Ok(OcclusionQueriesPool {
-               inner: match UnsafeQueryPool::new(device, QueryType::Occlusion, num_slots) {
-                   Ok(q) => q,
-                   Err(QueryPoolCreationError::OomError(err)) => return Err(err),
-                   Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled) => {
-                       unreachable!()
-                   },
-               },
-           })
+impl error::Error for GetResultsError {
+    #[inline]
+    fn description(&self) -> &str {
+        match *self {
+            GetResultsError::OomError(_) => "not enough memory available",
+            GetResultsError::DeviceLost => "the connection to the device has been lost",
+            GetResultsError::OutOfRange => {
+                "the range of queries is out of the range of the query pool"
+            },
+            GetResultsError::InvalidFlags => "the flags are not valid for this type of query",
+            GetResultsError::BufferTooSmall { .. } => {
+                "the destination is too small to hold the results"
+            },
+            GetResultsError::QueryNotEnded { .. } => {
+                "tried to wait for the result of a query that hasn't been ended or written by a \
+                 submitted command buffer since it was last reset"
+            },
+        }
     }
 
-    /// Builds a new query pool.
-    ///
-    /// # Panic
-    ///
-    /// - Panics if the device or host ran out of memory.
-    ///
     #[inline]
-    pub fn new(device: Arc<Device>, num_slots: u32) -> Arc<OcclusionQueriesPool> {
-        Arc::new(OcclusionQueriesPool::raw(device, num_slots).unwrap())
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            GetResultsError::OomError(ref err) => Some(err),
+            _ => None,
+        }
     }
+}
 
-    /// Returns the number of slots of that query pool.
+impl fmt::Display for GetResultsError {
     #[inline]
-    pub fn num_slots(&self) -> u32 {
-        self.inner.num_slots()
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", error::Error::description(self))
     }
 }
 
-unsafe impl DeviceOwned for OcclusionQueriesPool {
+impl From<OomError> for GetResultsError {
     #[inline]
-    fn device(&self) -> &Arc<Device> {
-        self.inner.device()
+    fn from(err: OomError) -> GetResultsError {
+        GetResultsError::OomError(err)
+    }
+}
+
+impl From<Error> for GetResultsError {
+    #[inline]
+    fn from(err: Error) -> GetResultsError {
+        match err {
+            err @ Error::OutOfHostMemory => GetResultsError::OomError(OomError::from(err)),
+            err @ Error::OutOfDeviceMemory => GetResultsError::OomError(OomError::from(err)),
+            Error::DeviceLost => GetResultsError::DeviceLost,
+            _ => panic!("unexpected error: {:?}", err),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use query::OcclusionQueriesPool;
+    use query::GetResultsError;
     use query::QueryPipelineStatisticFlags;
     use query::QueryPoolCreationError;
+    use query::QueryResultFlags;
     use query::QueryType;
     use query::UnsafeQueryPool;
 
     #[test]
-    fn occlusion_create() {
+    fn queries_range() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = UnsafeQueryPool::new(device, QueryType::Occlusion, 8).unwrap();
+
+        assert!(pool.queries_range(0, 8).is_some());
+        assert!(pool.queries_range(7, 1).is_some());
+        assert!(pool.queries_range(7, 2).is_none());
+        assert!(pool.queries_range(8, 1).is_none());
+    }
+
+    #[test]
+    fn get_results_buffer_too_small() {
         let (device, _) = gfx_dev_and_queue!();
-        let _ = OcclusionQueriesPool::new(device, 256);
+        let pool = UnsafeQueryPool::new(device, QueryType::Occlusion, 8).unwrap();
+        let range = pool.queries_range(0, 4).unwrap();
+
+        let flags = QueryResultFlags {
+            with_availability: true,
+            ..QueryResultFlags::none()
+        };
+        let mut results = [0u64; 4];
+        match unsafe { range.get_results(&mut results, flags) } {
+            Err(GetResultsError::BufferTooSmall {
+                    required_len: 8,
+                    actual_len: 4,
+                }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn timestamp_partial_forbidden() {
+        let (device, _) = gfx_dev_and_queue!();
+        let pool = UnsafeQueryPool::new(device, QueryType::Timestamp, 8).unwrap();
+        let range = pool.queries_range(0, 1).unwrap();
+
+        let flags = QueryResultFlags {
+            partial: true,
+            ..QueryResultFlags::none()
+        };
+        let mut results = [0u32; 1];
+        match unsafe { range.get_results(&mut results, flags) } {
+            Err(GetResultsError::InvalidFlags) => (),
+            _ => panic!(),
+        }
     }
 
     #[test]
@@ -0,0 +1,595 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use device::Device;
+use device::DeviceOwned;
+use query::GetResultsError;
+use query::QueryPipelineStatisticFlags;
+use query::QueryPoolCreationError;
+use query::QueryResultElement;
+use query::QueryResultFlags;
+use query::QueryType;
+use query::UnsafeQueryPool;
+
+use OomError;
+
+/// Trait for objects that contain a query pool whose queries can be used by the command buffer
+/// builders.
+///
+/// The safe command buffers use the methods of this trait to keep track of the state of each
+/// query. A command buffer tracks the state of the queries it uses while it is recorded, and
+/// applies these states to the pool when it is submitted.
+pub unsafe trait QueryPoolAccess: DeviceOwned + Send + Sync {
+    /// Returns the inner unsafe query pool.
+    fn inner(&self) -> &UnsafeQueryPool;
+
+    /// Returns the state of a query, as left by the command buffers that have been submitted.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `index` is out of range.
+    ///
+    fn query_state(&self, index: u32) -> QueryState;
+
+    /// Sets the state of a range of queries.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by command buffers when they are submitted, with the state in which
+    /// they leave the queries.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the range is out of range.
+    ///
+    unsafe fn set_query_state(&self, first_index: u32, count: u32, state: QueryState);
+}
+
+/// State of a query.
+///
+/// The `Active` and `Ended` states only exist within the command buffer that is being recorded.
+/// A query pool only ever contains queries that are `Uninitialized`, `Reset` or `Available`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QueryState {
+    /// The query has never been reset. It must be reset before it can be used.
+    Uninitialized,
+    /// A command that resets the query has been recorded.
+    Reset,
+    /// A command that begins the query has been recorded, but not the command that ends it.
+    Active,
+    /// A command that ends or writes the query has been recorded.
+    Ended,
+    /// The command buffer that ends or writes the query has been submitted. The result of the
+    /// query is available once that command has been executed.
+    Available,
+}
+
+// Query pool and the state of each of its queries. Shared by all the typed pools.
+struct TrackedQueryPool {
+    inner: UnsafeQueryPool,
+    states: Mutex<Vec<QueryState>>,
+}
+
+impl TrackedQueryPool {
+    #[inline]
+    fn new(device: Arc<Device>, ty: QueryType, num_slots: u32)
+           -> Result<TrackedQueryPool, QueryPoolCreationError> {
+        Ok(TrackedQueryPool {
+               inner: UnsafeQueryPool::new(device, ty, num_slots)?,
+               states: Mutex::new(vec![QueryState::Uninitialized; num_slots as usize]),
+           })
+    }
+
+    #[inline]
+    fn query_state(&self, index: u32) -> QueryState {
+        self.states.lock().unwrap()[index as usize]
+    }
+
+    #[inline]
+    fn set_query_state(&self, first_index: u32, count: u32, state: QueryState) {
+        let mut states = self.states.lock().unwrap();
+        let end = first_index
+            .checked_add(count)
+            .expect("the range of queries is out of range");
+        for s in &mut states[first_index as usize .. end as usize] {
+            *s = state;
+        }
+    }
+
+    fn get_results<T>(&self, first_index: u32, count: u32, destination: &mut [T],
+                      flags: QueryResultFlags)
+                      -> Result<bool, GetResultsError>
+        where T: QueryResultElement
+    {
+        let range = match self.inner.queries_range(first_index, count) {
+            Some(r) => r,
+            None => return Err(GetResultsError::OutOfRange),
+        };
+
+        let end = match first_index.checked_add(count) {
+            Some(end) => end,
+            None => return Err(GetResultsError::OutOfRange),
+        };
+
+        if flags.wait {
+            let states = self.states.lock().unwrap();
+            for index in first_index .. end {
+                if states[index as usize] != QueryState::Available {
+                    return Err(GetResultsError::QueryNotEnded { index: index });
+                }
+            }
+        }
+
+        unsafe { range.get_results(destination, flags) }
+    }
+}
+
+macro_rules! impl_query_pool_access {
+    ($name:ident) => (
+        unsafe impl QueryPoolAccess for $name {
+            #[inline]
+            fn inner(&self) -> &UnsafeQueryPool {
+                &self.inner.inner
+            }
+
+            #[inline]
+            fn query_state(&self, index: u32) -> QueryState {
+                self.inner.query_state(index)
+            }
+
+            #[inline]
+            unsafe fn set_query_state(&self, first_index: u32, count: u32, state: QueryState) {
+                self.inner.set_query_state(first_index, count, state)
+            }
+        }
+
+        unsafe impl DeviceOwned for $name {
+            #[inline]
+            fn device(&self) -> &Arc<Device> {
+                self.inner.inner.device()
+            }
+        }
+    );
+}
+
+/// Pool of occlusion queries.
+///
+/// An occlusion query counts the number of samples that pass the depth and stencil tests between
+/// the moment it is begun and the moment it is ended.
+pub struct OcclusionQueryPool {
+    inner: TrackedQueryPool,
+}
+
+impl OcclusionQueryPool {
+    /// Builds a new pool of occlusion queries.
+    pub fn new(device: Arc<Device>, num_slots: u32) -> Result<Arc<OcclusionQueryPool>, OomError> {
+        let inner = match TrackedQueryPool::new(device, QueryType::Occlusion, num_slots) {
+            Ok(q) => q,
+            Err(QueryPoolCreationError::OomError(err)) => return Err(err),
+            Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled) => {
+                unreachable!()
+            },
+        };
+
+        Ok(Arc::new(OcclusionQueryPool { inner: inner }))
+    }
+
+    /// Returns the number of slots of that query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.inner.inner.num_slots()
+    }
+
+    /// Reads the number of samples counted by the queries `first_index .. first_index + count`.
+    ///
+    /// See `UnsafeQueriesRange::get_results` for the layout of `destination` and the meaning of
+    /// the return value.
+    ///
+    /// Returns an error if `flags.wait` is true and one of the queries hasn't been ended by a
+    /// submitted command buffer since it was last reset.
+    #[inline]
+    pub fn get_results<T>(&self, first_index: u32, count: u32, destination: &mut [T],
+                          flags: QueryResultFlags)
+                          -> Result<bool, GetResultsError>
+        where T: QueryResultElement
+    {
+        self.inner.get_results(first_index, count, destination, flags)
+    }
+}
+
+impl_query_pool_access!(OcclusionQueryPool);
+
+/// Pool of pipeline statistics queries.
+///
+/// A pipeline statistics query counts various events, such as the number of shader invocations,
+/// between the moment it is begun and the moment it is ended. The `pipeline_statistics_query`
+/// feature must be enabled on the device.
+pub struct PipelineStatisticsQueryPool {
+    inner: TrackedQueryPool,
+    flags: QueryPipelineStatisticFlags,
+}
+
+impl PipelineStatisticsQueryPool {
+    /// Builds a new pool of pipeline statistics queries that count the events of `flags`.
+    pub fn new(device: Arc<Device>, num_slots: u32, flags: QueryPipelineStatisticFlags)
+               -> Result<Arc<PipelineStatisticsQueryPool>, QueryPoolCreationError> {
+        let ty = QueryType::PipelineStatistics(flags);
+        Ok(Arc::new(PipelineStatisticsQueryPool {
+                        inner: TrackedQueryPool::new(device, ty, num_slots)?,
+                        flags: flags,
+                    }))
+    }
+
+    /// Returns the number of slots of that query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.inner.inner.num_slots()
+    }
+
+    /// Returns the statistics counted by the queries of that pool.
+    #[inline]
+    pub fn flags(&self) -> QueryPipelineStatisticFlags {
+        self.flags
+    }
+
+    /// Reads the statistics of the queries `first_index .. first_index + count`.
+    ///
+    /// Each query writes one value per flag that is set, in the order in which the flags are
+    /// declared in `QueryPipelineStatisticFlags`. See `UnsafeQueriesRange::get_results` for the
+    /// layout of `destination` and the meaning of the return value.
+    ///
+    /// Returns an error if `flags.wait` is true and one of the queries hasn't been ended by a
+    /// submitted command buffer since it was last reset.
+    #[inline]
+    pub fn get_results<T>(&self, first_index: u32, count: u32, destination: &mut [T],
+                          flags: QueryResultFlags)
+                          -> Result<bool, GetResultsError>
+        where T: QueryResultElement
+    {
+        self.inner.get_results(first_index, count, destination, flags)
+    }
+}
+
+impl_query_pool_access!(PipelineStatisticsQueryPool);
+
+/// Pool of timestamp queries.
+///
+/// A timestamp query records the value of a GPU clock when all the previous commands have
+/// reached a certain pipeline stage. Timestamps are in units of `timestamp_period()`
+/// nanoseconds.
+pub struct TimestampQueryPool {
+    inner: TrackedQueryPool,
+}
+
+impl TimestampQueryPool {
+    /// Builds a new pool of timestamp queries.
+    pub fn new(device: Arc<Device>, num_slots: u32) -> Result<Arc<TimestampQueryPool>, OomError> {
+        let inner = match TrackedQueryPool::new(device, QueryType::Timestamp, num_slots) {
+            Ok(q) => q,
+            Err(QueryPoolCreationError::OomError(err)) => return Err(err),
+            Err(QueryPoolCreationError::PipelineStatisticsQueryFeatureNotEnabled) => {
+                unreachable!()
+            },
+        };
+
+        Ok(Arc::new(TimestampQueryPool { inner: inner }))
+    }
+
+    /// Returns the number of slots of that query pool.
+    #[inline]
+    pub fn num_slots(&self) -> u32 {
+        self.inner.inner.num_slots()
+    }
+
+    /// Returns the number of nanoseconds between two increments of the timestamps.
+    #[inline]
+    pub fn timestamp_period(&self) -> f32 {
+        self.inner.inner.device().physical_device().limits().timestamp_period()
+    }
+
+    /// Reads the timestamps of the queries `first_index .. first_index + count`.
+    ///
+    /// See `UnsafeQueriesRange::get_results` for the layout of `destination` and the meaning of
+    /// the return value. `flags.partial` is not allowed for timestamps.
+    ///
+    /// Returns an error if `flags.wait` is true and one of the queries hasn't been written by a
+    /// submitted command buffer since it was last reset.
+    #[inline]
+    pub fn get_results<T>(&self, first_index: u32, count: u32, destination: &mut [T],
+                          flags: QueryResultFlags)
+                          -> Result<bool, GetResultsError>
+        where T: QueryResultElement
+    {
+        self.inner.get_results(first_index, count, destination, flags)
+    }
+}
+
+impl_query_pool_access!(TimestampQueryPool);
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::AutoCommandBufferBuilderContextError;
+    use command_buffer::BeginQueryError;
+    use command_buffer::BuildError;
+    use command_buffer::CommandBuffer;
+    use query::GetResultsError;
+    use query::OcclusionQueryPool;
+    use query::QueryPoolAccess;
+    use query::QueryResultFlags;
+    use query::QueryState;
+    use query::TimestampQueryPool;
+    use sync::GpuFuture;
+    use sync::PipelineStages;
+
+    #[test]
+    fn occlusion_create() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = OcclusionQueryPool::new(device, 256).unwrap();
+        assert_eq!(pool.num_slots(), 256);
+        assert_eq!(pool.query_state(255), QueryState::Uninitialized);
+    }
+
+    #[test]
+    fn wait_on_unwritten_query() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device, 4).unwrap();
+
+        let flags = QueryResultFlags {
+            wait: true,
+            ..QueryResultFlags::none()
+        };
+        let mut results = [0u64; 2];
+        match pool.get_results(1, 2, &mut results, flags) {
+            Err(GetResultsError::QueryNotEnded { index: 1 }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn wait_on_unsubmitted_query() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let pool = OcclusionQueryPool::new(device.clone(), 1).unwrap();
+
+        let cb = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 1)
+            .unwrap()
+            .begin_query(pool.clone(), 0, false)
+            .unwrap()
+            .end_query(pool.clone(), 0)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(pool.query_state(0), QueryState::Uninitialized);
+
+        let flags = QueryResultFlags {
+            wait: true,
+            ..QueryResultFlags::none()
+        };
+        let mut results = [0u64; 1];
+        match pool.get_results(0, 1, &mut results, flags) {
+            Err(GetResultsError::QueryNotEnded { index: 0 }) => (),
+            _ => panic!(),
+        }
+
+        cb.execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(pool.query_state(0), QueryState::Available);
+        pool.get_results(0, 1, &mut results, flags).unwrap();
+    }
+
+    #[test]
+    fn states_applied_on_submit() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device.clone(), 2).unwrap();
+        let stages = PipelineStages {
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+
+        let write = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 2)
+            .unwrap()
+            .write_timestamp(pool.clone(), 0, stages)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        // The reset recorded in `write` isn't visible to other command buffers before `write` is
+        // submitted.
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+        assert!(builder.write_timestamp(pool.clone(), 1, stages).is_err());
+
+        write
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(pool.query_state(0), QueryState::Available);
+        assert_eq!(pool.query_state(1), QueryState::Reset);
+
+        // A reset that is recorded but never submitted leaves the pool untouched.
+        AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 1)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(pool.query_state(0), QueryState::Available);
+
+        AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .write_timestamp(pool.clone(), 1, stages)
+            .unwrap();
+    }
+
+    #[test]
+    fn query_ended_in_secondary() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device.clone(), 1).unwrap();
+        let stages = PipelineStages {
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+
+        // The secondary command buffer can only write the query once its reset is submitted.
+        AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 1)
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(pool.query_state(0), QueryState::Reset);
+
+        let secondary = AutoCommandBufferBuilder::secondary_compute(device.clone(),
+                                                                    queue.family())
+            .unwrap()
+            .write_timestamp(pool.clone(), 0, stages)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(pool.query_state(0), QueryState::Reset);
+
+        let primary =
+            AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+                .unwrap();
+        let primary = unsafe { primary.execute_commands(secondary).unwrap().build().unwrap() };
+        assert_eq!(pool.query_state(0), QueryState::Reset);
+
+        primary
+            .execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        assert_eq!(pool.query_state(0), QueryState::Available);
+    }
+
+    #[test]
+    fn get_results_out_of_range() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device, 4).unwrap();
+
+        let mut results = [0u64; 2];
+        match pool.get_results(3, 2, &mut results, QueryResultFlags::none()) {
+            Err(GetResultsError::OutOfRange) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let pool = TimestampQueryPool::new(device.clone(), 2).unwrap();
+        let top = PipelineStages {
+            top_of_pipe: true,
+            ..PipelineStages::none()
+        };
+        let bottom = PipelineStages {
+            bottom_of_pipe: true,
+            ..PipelineStages::none()
+        };
+
+        let cb = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 2)
+            .unwrap()
+            .write_timestamp(pool.clone(), 0, top)
+            .unwrap()
+            .write_timestamp(pool.clone(), 1, bottom)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(pool.query_state(1), QueryState::Uninitialized);
+
+        cb.execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let flags = QueryResultFlags {
+            wait: true,
+            with_availability: true,
+            ..QueryResultFlags::none()
+        };
+        let mut results = [0u64; 4];
+        assert!(pool.get_results(0, 2, &mut results, flags).unwrap());
+        assert_eq!(results[1], 1);
+        assert_eq!(results[3], 1);
+        assert!(results[2] > results[0]);
+    }
+
+    #[test]
+    fn occlusion_query_state() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let pool = OcclusionQueryPool::new(device.clone(), 2).unwrap();
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family()).unwrap();
+        match builder.begin_query(pool.clone(), 0, false) {
+            Err(BeginQueryError::CheckBeginQueryError(_)) => (),
+            _ => panic!(),
+        }
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 2)
+            .unwrap()
+            .begin_query(pool.clone(), 0, false)
+            .unwrap();
+        assert_eq!(pool.query_state(0), QueryState::Uninitialized);
+
+        let builder = match builder.begin_query(pool.clone(), 1, false) {
+            Err(BeginQueryError::AutoCommandBufferBuilderContextError(
+                    AutoCommandBufferBuilderContextError::QueryTypeAlreadyActive)) => {
+                AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+                    .unwrap()
+                    .reset_query_pool(pool.clone(), 0, 2)
+                    .unwrap()
+                    .begin_query(pool.clone(), 0, false)
+                    .unwrap()
+            },
+            _ => panic!(),
+        };
+
+        match builder.build() {
+            Err(BuildError::AutoCommandBufferBuilderContextError(
+                    AutoCommandBufferBuilderContextError::QueryIsActive)) => (),
+            _ => panic!(),
+        }
+
+        let builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family())
+            .unwrap()
+            .reset_query_pool(pool.clone(), 0, 2)
+            .unwrap();
+        match builder.end_query(pool.clone(), 0) {
+            Err(AutoCommandBufferBuilderContextError::QueryNotActive) => (),
+            _ => panic!(),
+        }
+    }
+}
@@ -164,9 +164,10 @@
 //! # use vulkano::sync::SharingMode;
 //! # use vulkano::format::Format;
 //! # use vulkano::swapchain::{Surface, Swapchain, SurfaceTransform, PresentMode, CompositeAlpha};
+//! # use vulkano::swapchain::ColorSpace;
 //! # fn create_swapchain(
 //! #     device: Arc<Device>, surface: Arc<Surface<()>>, present_queue: Arc<Queue>,
-//! #     buffers_count: u32, format: Format, dimensions: [u32; 2],
+//! #     buffers_count: u32, format: Format, color_space: ColorSpace, dimensions: [u32; 2],
 //! #     surface_transform: SurfaceTransform, composite_alpha: CompositeAlpha, present_mode: PresentMode
 //! # ) -> Result<(), Box<std::error::Error>> {
 //! // The created swapchain will be used as a color attachment for rendering.
@@ -187,6 +188,8 @@
 //!     buffers_count,
 //!     // The format of the images.
 //!     format,
+//!     // The color space of the images.
+//!     color_space,
 //!     // The size of each image.
 //!     dimensions,
 //!     // How many layers each image has.
@@ -306,6 +309,7 @@ pub use self::surface::Surface;
 pub use self::surface::SurfaceCreationError;
 pub use self::swapchain::AcquireError;
 pub use self::swapchain::AcquiredImage;
+pub use self::swapchain::HdrMetadata;
 pub use self::swapchain::PresentFuture;
 pub use self::swapchain::Swapchain;
 pub use self::swapchain::SwapchainAcquireFuture;
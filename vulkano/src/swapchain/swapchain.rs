@@ -203,6 +203,11 @@ impl <W> Swapchain<W> {
     /// supported by the implementation. All the parameters that you pass to `Swapchain::new`
     /// must be supported.
     ///
+    /// The `format` and `color_space` parameters must correspond to one of the elements of
+    /// `supported_formats` in the capabilities of the surface. Using any other color space than
+    /// `ColorSpace::SrgbNonLinear` requires the `VK_EXT_swapchain_colorspace` extension to be
+    /// enabled on the instance.
+    ///
     /// The `clipped` parameter indicates whether the implementation is allowed to discard
     /// rendering operations that affect regions of the surface which aren't visible. This is
     /// important to take into account if your fragment shader has side-effects or if you want to
@@ -218,12 +223,11 @@ impl <W> Swapchain<W> {
     /// - Panics if `usage` is empty.
     ///
     // TODO: remove `old_swapchain` parameter and add another function `with_old_swapchain`.
-    // TODO: add `ColorSpace` parameter
     // TODO: isn't it unsafe to take the surface through an Arc when it comes to vulkano-win?
     #[inline]
     pub fn new<F, S>(
         device: Arc<Device>, surface: Arc<Surface<W>>, num_images: u32, format: F,
-        dimensions: [u32; 2], layers: u32, usage: ImageUsage, sharing: S,
+        color_space: ColorSpace, dimensions: [u32; 2], layers: u32, usage: ImageUsage, sharing: S,
         transform: SurfaceTransform, alpha: CompositeAlpha, mode: PresentMode, clipped: bool,
         old_swapchain: Option<&Arc<Swapchain<W>>>)
         -> Result<(Arc<Swapchain<W>>, Vec<Arc<SwapchainImage<W>>>), SwapchainCreationError>
@@ -234,7 +238,7 @@ impl <W> Swapchain<W> {
                              surface,
                              num_images,
                              format.format(),
-                             ColorSpace::SrgbNonLinear,
+                             color_space,
                              dimensions,
                              layers,
                              usage,
@@ -274,6 +278,12 @@ impl <W> Swapchain<W> {
         assert_eq!(device.instance().internal_object(),
                    surface.instance().internal_object());
 
+        if color_space != ColorSpace::SrgbNonLinear &&
+            !surface.instance().loaded_extensions().ext_swapchain_colorspace
+        {
+            return Err(SwapchainCreationError::MissingExtensionSwapchainColorspace);
+        }
+
         // Checking that the requested parameters match the capabilities.
         let capabilities = surface.capabilities(device.physical_device())?;
         if num_images < capabilities.min_image_count {
@@ -433,13 +443,16 @@ impl <W> Swapchain<W> {
                     cubemap_compatible: false,
                 };
 
-                let img = UnsafeImage::from_raw(device.clone(),
-                                                image,
-                                                usage.to_usage_bits(),
-                                                format,
-                                                dims,
-                                                1,
-                                                1);
+                let mut img = UnsafeImage::from_raw(device.clone(),
+                                                    image,
+                                                    usage.to_usage_bits(),
+                                                    format,
+                                                    dims,
+                                                    1,
+                                                    1);
+                if let SharingMode::Concurrent(_) = sharing {
+                    img.set_exclusive_sharing(false);
+                }
 
                 ImageEntry {
                     image: img,
@@ -508,6 +521,14 @@ impl <W> Swapchain<W> {
         self.format
     }
 
+    /// Returns the color space of the images of the swapchain.
+    ///
+    /// See the documentation of `Swapchain::new`.
+    #[inline]
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
     /// Returns the dimensions of the images of the swapchain.
     ///
     /// See the documentation of `Swapchain::new`.
@@ -555,6 +576,40 @@ impl <W> Swapchain<W> {
     pub fn clipped(&self) -> bool {
         self.clipped
     }
+
+    /// Gives the presentation engine information about the HDR content of the images of the
+    /// swapchain, so that it can map it to the capabilities of the display.
+    ///
+    /// The metadata applies to the images that are presented after this call.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if the `VK_EXT_hdr_metadata` extension wasn't enabled on the device.
+    ///
+    pub fn set_hdr_metadata(&self, metadata: &HdrMetadata) {
+        assert!(self.device.loaded_extensions().ext_hdr_metadata,
+                "The VK_EXT_hdr_metadata extension must be enabled to set HDR metadata");
+
+        let xy = |c: [f32; 2]| vk::XYColorEXT { x: c[0], y: c[1] };
+
+        let infos = vk::HdrMetadataEXT {
+            sType: vk::STRUCTURE_TYPE_HDR_METADATA_EXT,
+            pNext: ptr::null(),
+            displayPrimaryRed: xy(metadata.display_primary_red),
+            displayPrimaryGreen: xy(metadata.display_primary_green),
+            displayPrimaryBlue: xy(metadata.display_primary_blue),
+            whitePoint: xy(metadata.white_point),
+            maxLuminance: metadata.max_luminance,
+            minLuminance: metadata.min_luminance,
+            maxContentLightLevel: metadata.max_content_light_level,
+            maxFrameAverageLightLevel: metadata.max_frame_average_light_level,
+        };
+
+        unsafe {
+            let vk = self.device.pointers();
+            vk.SetHdrMetadataEXT(self.device.internal_object(), 1, &self.swapchain, &infos);
+        }
+    }
 }
 
 unsafe impl<W> VulkanObject for Swapchain<W> {
@@ -592,6 +647,33 @@ impl<W> Drop for Swapchain<W> {
     }
 }
 
+/// Description of the HDR content that is presented to a swapchain, as defined by SMPTE 2086
+/// and CTA 861.3.
+///
+/// The chromaticity coordinates are expressed in the CIE 1931 xy color space, and the
+/// luminances and light levels are expressed in nits (candelas per square meter).
+///
+/// See `Swapchain::set_hdr_metadata`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HdrMetadata {
+    /// Chromaticity of the red primary of the mastering display.
+    pub display_primary_red: [f32; 2],
+    /// Chromaticity of the green primary of the mastering display.
+    pub display_primary_green: [f32; 2],
+    /// Chromaticity of the blue primary of the mastering display.
+    pub display_primary_blue: [f32; 2],
+    /// Chromaticity of the white point of the mastering display.
+    pub white_point: [f32; 2],
+    /// Maximum luminance of the mastering display.
+    pub max_luminance: f32,
+    /// Minimum luminance of the mastering display.
+    pub min_luminance: f32,
+    /// Maximum light level of any pixel of the content.
+    pub max_content_light_level: f32,
+    /// Maximum light level of the content, averaged over a frame.
+    pub max_frame_average_light_level: f32,
+}
+
 /// Error that can happen when creation a swapchain.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SwapchainCreationError {
@@ -607,6 +689,9 @@ pub enum SwapchainCreationError {
     NativeWindowInUse,
     /// The `VK_KHR_swapchain` extension was not enabled.
     MissingExtension,
+    /// A color space other than `SrgbNonLinear` was requested, but the
+    /// `VK_EXT_swapchain_colorspace` extension was not enabled on the instance.
+    MissingExtensionSwapchainColorspace,
     /// Surface mismatch between old and new swapchain.
     OldSwapchainSurfaceMismatch,
     /// The old swapchain has already been used to recreate another one.
@@ -653,6 +738,9 @@ impl error::Error for SwapchainCreationError {
             SwapchainCreationError::MissingExtension => {
                 "the `VK_KHR_swapchain` extension was not enabled"
             },
+            SwapchainCreationError::MissingExtensionSwapchainColorspace => {
+                "the `VK_EXT_swapchain_colorspace` extension was not enabled"
+            },
             SwapchainCreationError::OldSwapchainSurfaceMismatch => {
                 "surface mismatch between old and new swapchain"
             },
@@ -1038,12 +1126,16 @@ unsafe impl<P, W> GpuFuture for PresentFuture<P, W>
                                          self.present_region.as_ref());
                    SubmitAnyBuilder::QueuePresent(builder)
                },
-               SubmitAnyBuilder::QueuePresent(present) => {
-                   unimplemented!() // TODO:
-                /*present.submit();
-                let mut builder = SubmitPresentBuilder::new();
-                builder.add_swapchain(self.command_buffer.inner(), self.image_id);
-                SubmitAnyBuilder::CommandBuffer(builder)*/
+               SubmitAnyBuilder::QueuePresent(_) => {
+                   // Two presents can't be merged if they come from different futures. Submit
+                   // the previous one by flushing previous.
+                   self.previous.flush()?;
+
+                   let mut builder = SubmitPresentBuilder::new();
+                   builder.add_swapchain(&self.swapchain,
+                                         self.image_id as u32,
+                                         self.present_region.as_ref());
+                   SubmitAnyBuilder::QueuePresent(builder)
                },
            })
     }
@@ -1189,3 +1281,125 @@ pub unsafe fn acquire_next_image_raw<W>(swapchain: &Swapchain<W>, timeout: Optio
 
     Ok(AcquiredImage { id, suboptimal })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use device::Device;
+    use device::DeviceExtensions;
+    use device::Queue;
+    use features::Features;
+    use format::B8G8R8A8Unorm;
+    use format::R8G8B8A8Unorm;
+    use image::ImageUsage;
+    use instance::Instance;
+    use instance::InstanceExtensions;
+    use instance::PhysicalDevice;
+    use swapchain::ColorSpace;
+    use swapchain::CompositeAlpha;
+    use swapchain::PresentMode;
+    use swapchain::Surface;
+    use swapchain::SurfaceTransform;
+    use swapchain::Swapchain;
+    use swapchain::SwapchainCreationError;
+    use testing::MockLoader;
+
+    // Creates a mock surface and a device with a queue of the family that can present to it.
+    fn surface_and_queue(ext_swapchain_colorspace: bool)
+                         -> (MockLoader, Arc<Surface<()>>, Arc<Device>, Arc<Queue>) {
+        let loader = MockLoader::new();
+        let extensions = InstanceExtensions {
+            khr_surface: true,
+            ext_swapchain_colorspace: ext_swapchain_colorspace,
+            ..InstanceExtensions::none()
+        };
+        let instance = Instance::with_loader(loader.function_pointers(), None, &extensions, None)
+            .unwrap();
+        let surface = Arc::new(unsafe { Surface::from_raw_surface(instance.clone(), 0x5, ()) });
+
+        let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+        let family = physical.queue_family_by_id(0).unwrap();
+        let (device, mut queues) = Device::new(physical,
+                                               &Features::none(),
+                                               &DeviceExtensions::none(),
+                                               Some((family, 1.0)))
+            .unwrap();
+
+        (loader, surface, device, queues.next().unwrap())
+    }
+
+    #[test]
+    fn missing_extension_swapchain_colorspace() {
+        let (_, surface, device, queue) = surface_and_queue(false);
+
+        match Swapchain::new(device,
+                             surface,
+                             2,
+                             B8G8R8A8Unorm,
+                             ColorSpace::ExtendedSrgbLinear,
+                             [64, 64],
+                             1,
+                             ImageUsage {
+                                 color_attachment: true,
+                                 ..ImageUsage::none()
+                             },
+                             &queue,
+                             SurfaceTransform::Identity,
+                             CompositeAlpha::Opaque,
+                             PresentMode::Fifo,
+                             true,
+                             None) {
+            Err(SwapchainCreationError::MissingExtensionSwapchainColorspace) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let (_, surface, device, queue) = surface_and_queue(true);
+
+        // The mock surface only supports B8G8R8A8 formats in the sRGB non-linear color space.
+        match Swapchain::new(device.clone(),
+                             surface.clone(),
+                             2,
+                             R8G8B8A8Unorm,
+                             ColorSpace::SrgbNonLinear,
+                             [64, 64],
+                             1,
+                             ImageUsage {
+                                 color_attachment: true,
+                                 ..ImageUsage::none()
+                             },
+                             &queue,
+                             SurfaceTransform::Identity,
+                             CompositeAlpha::Opaque,
+                             PresentMode::Fifo,
+                             true,
+                             None) {
+            Err(SwapchainCreationError::UnsupportedFormat) => (),
+            _ => panic!(),
+        }
+
+        match Swapchain::new(device,
+                             surface,
+                             2,
+                             B8G8R8A8Unorm,
+                             ColorSpace::ExtendedSrgbLinear,
+                             [64, 64],
+                             1,
+                             ImageUsage {
+                                 color_attachment: true,
+                                 ..ImageUsage::none()
+                             },
+                             &queue,
+                             SurfaceTransform::Identity,
+                             CompositeAlpha::Opaque,
+                             PresentMode::Fifo,
+                             true,
+                             None) {
+            Err(SwapchainCreationError::UnsupportedFormat) => (),
+            _ => panic!(),
+        }
+    }
+}
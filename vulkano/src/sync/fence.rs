@@ -190,10 +190,38 @@ impl<D> Fence<D>
     /// # Panic
     ///
     /// Panics if not all fences belong to the same device.
+    #[inline]
     pub fn multi_wait<'a, I>(iter: I, timeout: Option<Duration>) -> Result<(), FenceWaitError>
         where I: IntoIterator<Item = &'a Fence<D>>,
               D: 'a
     {
+        Fence::multi_wait_impl(iter, true, timeout)
+    }
+
+    /// Waits until at least one of the fences is signaled, or at least until the timeout duration
+    /// has elapsed.
+    ///
+    /// Returns immediately if one of the fences is already known to be signaled, or if the list
+    /// is empty. Use `ready()` afterwards to know which fences are signaled.
+    ///
+    /// # Panic
+    ///
+    /// Panics if not all fences belong to the same device.
+    #[inline]
+    pub fn multi_wait_any<'a, I>(iter: I, timeout: Option<Duration>) -> Result<(), FenceWaitError>
+        where I: IntoIterator<Item = &'a Fence<D>>,
+              D: 'a
+    {
+        Fence::multi_wait_impl(iter, false, timeout)
+    }
+
+    // Shared implementation of `multi_wait` and `multi_wait_any`.
+    fn multi_wait_impl<'a, I>(iter: I, wait_all: bool, timeout: Option<Duration>)
+                              -> Result<(), FenceWaitError>
+        where I: IntoIterator<Item = &'a Fence<D>>,
+              D: 'a
+    {
+        let mut any_signaled = false;
         let mut device: Option<&Device> = None;
 
         let fences: SmallVec<[vk::Fence; 8]> = iter.into_iter()
@@ -207,6 +235,7 @@ impl<D> Fence<D>
                 };
 
                 if fence.signaled.load(Ordering::Relaxed) {
+                    any_signaled = true;
                     None
                 } else {
                     Some(fence.fence)
@@ -223,13 +252,17 @@ impl<D> Fence<D>
             u64::max_value()
         };
 
+        if fences.is_empty() || (!wait_all && any_signaled) {
+            return Ok(());
+        }
+
         let r = if let Some(device) = device {
             unsafe {
                 let vk = device.pointers();
                 check_errors(vk.WaitForFences(device.internal_object(),
                                               fences.len() as u32,
                                               fences.as_ptr(),
-                                              vk::TRUE,
+                                              if wait_all { vk::TRUE } else { vk::FALSE },
                                               timeout_ns))?
             }
         } else {
@@ -388,6 +421,7 @@ mod tests {
     use VulkanObject;
     use std::time::Duration;
     use sync::Fence;
+    use sync::FenceWaitError;
 
     #[test]
     fn fence_create() {
@@ -438,6 +472,22 @@ mod tests {
                              });
     }
 
+    #[test]
+    fn multiwait_any() {
+        let (device, _) = gfx_dev_and_queue!();
+
+        let fence1 = Fence::alloc(device.clone()).unwrap();
+        let fence2 = Fence::alloc(device.clone()).unwrap();
+        let fence3 = Fence::alloc_signaled(device.clone()).unwrap();
+
+        assert_eq!(Fence::multi_wait_any([&fence1, &fence2].iter().cloned(),
+                                         Some(Duration::new(0, 0))),
+                   Err(FenceWaitError::Timeout));
+        Fence::multi_wait_any([&fence1, &fence3].iter().cloned(), Some(Duration::new(0, 0)))
+            .unwrap();
+        assert!(!fence1.ready().unwrap());
+    }
+
     #[test]
     fn multireset_different_devices() {
         use std::iter::once;
@@ -0,0 +1,272 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::any::Any;
+use std::sync::Arc;
+use std::time::Duration;
+
+use OomError;
+use device::Device;
+use sync::FenceSignalFuture;
+use sync::FlushError;
+use sync::GpuFuture;
+use sync::now;
+
+/// Keeps track of the frames that are being processed by the GPU.
+///
+/// Rendering loops usually submit a new frame before the previous one has finished executing.
+/// This object holds a fixed number of slots, one per frame in flight, each with the fence
+/// signaled at the end of the frame and the transient resources used by that frame. When a slot
+/// is reused, `begin_frame` waits for its fence and only then destroys its resources.
+///
+/// # Example
+///
+/// ```no_run
+/// use vulkano::swapchain;
+/// use vulkano::sync::FlushError;
+/// use vulkano::sync::FramesInFlight;
+/// use vulkano::sync::GpuFuture;
+///
+/// # let device: std::sync::Arc<vulkano::device::Device> = return;
+/// # let swapchain: std::sync::Arc<vulkano::swapchain::Swapchain<()>> = return;
+/// # let queue: std::sync::Arc<vulkano::device::Queue> = return;
+/// let mut frames = FramesInFlight::new(device.clone(), 2);
+///
+/// loop {
+///     // Waits until the frame that used the same slot has finished.
+///     frames.begin_frame(None).unwrap();
+///
+///     let (image_num, acquire_future) = swapchain::acquire_next_image(swapchain.clone(), None)
+///         .unwrap();
+///
+///     let future = frames.previous_frame_end()
+///         .join(acquire_future)
+///         // .then_execute(...)
+///         .then_swapchain_present(queue.clone(), swapchain.clone(), image_num);
+///
+///     match frames.end_frame(future) {
+///         Ok(()) => (),
+///         Err(FlushError::OutOfDate) => {
+///             // recreate the swapchain
+///         },
+///         Err(e) => println!("{:?}", e),
+///     }
+/// }
+/// ```
+pub struct FramesInFlight {
+    device: Arc<Device>,
+    frames: Vec<FrameSlot>,
+    // Index within `frames` of the slot of the frame being built.
+    current: usize,
+    // End of the most recently submitted frame, if its submission succeeded.
+    last: Option<Arc<FenceSignalFuture<Box<GpuFuture>>>>,
+}
+
+// Storage for one frame in flight.
+struct FrameSlot {
+    // Signaled when the frame has finished executing. Must be declared before `resources` so that
+    // it is waited upon before the resources are dropped.
+    future: Option<Arc<FenceSignalFuture<Box<GpuFuture>>>>,
+    // Resources that must be kept alive until the frame has finished executing.
+    resources: Vec<Box<Any>>,
+}
+
+impl FramesInFlight {
+    /// Builds a new `FramesInFlight` that allows `num_frames` frames to be processed by the GPU
+    /// simultaneously.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `num_frames` is 0.
+    ///
+    pub fn new(device: Arc<Device>, num_frames: usize) -> FramesInFlight {
+        assert!(num_frames >= 1, "A FramesInFlight must have at least one frame");
+
+        FramesInFlight {
+            device: device,
+            frames: (0 .. num_frames)
+                .map(|_| {
+                         FrameSlot {
+                             future: None,
+                             resources: Vec::new(),
+                         }
+                     })
+                .collect(),
+            current: 0,
+            last: None,
+        }
+    }
+
+    /// Returns the number of frames that can be in flight at the same time.
+    #[inline]
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns the index of the slot of the frame currently being built, between 0 and
+    /// `num_frames()`.
+    ///
+    /// This can be used to index per-frame objects that are created ahead of time.
+    #[inline]
+    pub fn current_frame(&self) -> usize {
+        self.current
+    }
+
+    /// Returns true if the slot of the frame currently being built is free, in other words if
+    /// `begin_frame` won't block. Doesn't block.
+    pub fn is_ready(&self) -> Result<bool, OomError> {
+        match self.frames[self.current].future {
+            Some(ref future) => future.is_signaled(),
+            None => Ok(true),
+        }
+    }
+
+    /// Prepares the slot of the frame being built.
+    ///
+    /// If the slot is still used by a previous frame, blocks the current thread until that frame
+    /// has finished executing, then destroys the resources that were stored in it with
+    /// `keep_alive`. If `timeout` is `None` the wait is infinite, otherwise a
+    /// `FlushError::Timeout` is returned once `timeout` has elapsed. Passing a timeout of zero
+    /// allows polling.
+    ///
+    /// Also cleans up the resources of the other frames that have already finished.
+    pub fn begin_frame(&mut self, timeout: Option<Duration>) -> Result<(), FlushError> {
+        if let Some(ref mut last) = self.last {
+            last.cleanup_finished();
+        }
+
+        let frame = &mut self.frames[self.current];
+        if let Some(future) = frame.future.take() {
+            if let Err(err) = future.wait(timeout) {
+                frame.future = Some(future);
+                return Err(err);
+            }
+        }
+
+        frame.resources.clear();
+        Ok(())
+    }
+
+    /// Stores an object that will be kept alive until the frame being built has finished
+    /// executing on the GPU.
+    #[inline]
+    pub fn keep_alive<R>(&mut self, resource: R)
+        where R: Any
+    {
+        self.frames[self.current]
+            .resources
+            .push(Box::new(resource) as Box<_>);
+    }
+
+    /// Returns a future that corresponds to the end of the previous frame, to be used as the
+    /// starting point of the frame being built.
+    ///
+    /// If there is no previous frame, or if submitting the previous frame failed, returns a
+    /// future that is already finished.
+    pub fn previous_frame_end(&self) -> Box<GpuFuture> {
+        match self.last {
+            Some(ref last) => Box::new(last.clone()) as Box<_>,
+            None => Box::new(now(self.device.clone())) as Box<_>,
+        }
+    }
+
+    /// Signals a fence at the end of `future`, flushes it, and stores it in the slot of the frame
+    /// being built. The next frame then uses the next slot.
+    ///
+    /// If flushing fails, the error is returned and the next call to `previous_frame_end` will
+    /// return a future that is already finished. The next frame still uses the next slot, but no
+    /// future is stored in the slot of the failed frame, so the `begin_frame` that reuses it
+    /// doesn't block and destroys its resources immediately.
+    ///
+    /// # Panic
+    ///
+    /// - Panics if `future` doesn't correspond to an operation on a queue, for example if it was
+    ///   returned by `previous_frame_end` without chaining anything.
+    ///
+    pub fn end_frame<F>(&mut self, future: F) -> Result<(), FlushError>
+        where F: GpuFuture + 'static
+    {
+        let slot = self.current;
+        self.current = (self.current + 1) % self.frames.len();
+
+        match (Box::new(future) as Box<GpuFuture>).then_signal_fence_and_flush() {
+            Ok(future) => {
+                let future = Arc::new(future);
+                debug_assert!(self.frames[slot].future.is_none());
+                self.frames[slot].future = Some(future.clone());
+                self.last = Some(future);
+                Ok(())
+            },
+            Err(err) => {
+                self.last = None;
+                Err(err)
+            },
+        }
+    }
+}
+
+impl Drop for FramesInFlight {
+    fn drop(&mut self) {
+        // A frame holds the futures of the previous frames. We need to explicitly wait for every
+        // frame, otherwise a frame could be dropped while a later frame still references it and
+        // its resources would be destroyed before its fence is signaled.
+        self.last = None;
+        for frame in &mut self.frames {
+            if let Some(future) = frame.future.take() {
+                let _ = future.wait(None);
+            }
+            frame.resources.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::AutoCommandBufferBuilder;
+    use std::sync::Arc;
+    use sync::FramesInFlight;
+    use sync::GpuFuture;
+
+    #[test]
+    #[should_panic(expected = "A FramesInFlight must have at least one frame")]
+    fn zero_frames() {
+        let (_, device, _) = mock_dev_and_queue!();
+        let _ = FramesInFlight::new(device, 0);
+    }
+
+    #[test]
+    fn cycle_slots() {
+        let (_, device, queue) = mock_dev_and_queue!();
+        let mut frames = FramesInFlight::new(device.clone(), 2);
+        assert_eq!(frames.num_frames(), 2);
+
+        assert!(frames.is_ready().unwrap());
+        let resource = Arc::new(());
+
+        for n in 0 .. 4 {
+            assert_eq!(frames.current_frame(), n % 2);
+            frames.begin_frame(None).unwrap();
+            if n == 0 {
+                frames.keep_alive(resource.clone());
+            }
+            assert_eq!(Arc::strong_count(&resource), if n <= 1 { 2 } else { 1 });
+
+            let cb = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(),
+                                                                         queue.family())
+                .unwrap()
+                .build()
+                .unwrap();
+            let future = frames
+                .previous_frame_end()
+                .then_execute(queue.clone(), cb)
+                .unwrap();
+            frames.end_frame(future).unwrap();
+        }
+    }
+}
@@ -7,12 +7,21 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+#[cfg(feature = "async")]
+use std::future::Future;
 use std::mem;
+#[cfg(feature = "async")]
+use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
+#[cfg(feature = "async")]
+use std::task::Context;
+#[cfg(feature = "async")]
+use std::task::Poll;
 use std::time::Duration;
 
+use OomError;
 use buffer::BufferAccess;
 use command_buffer::submit::SubmitAnyBuilder;
 use command_buffer::submit::SubmitCommandBufferBuilder;
@@ -27,6 +36,8 @@ use sync::Fence;
 use sync::FlushError;
 use sync::GpuFuture;
 use sync::PipelineStages;
+#[cfg(feature = "async")]
+use sync::future::reaper;
 
 /// Builds a new fence signal future.
 #[inline]
@@ -37,7 +48,7 @@ pub fn then_signal_fence<F>(future: F, behavior: FenceSignalFutureBehavior) -> F
 
     assert!(future.queue().is_some()); // TODO: document
 
-    let fence = Fence::from_pool(device.clone()).unwrap();
+    let fence = Arc::new(Fence::from_pool(device.clone()).unwrap());
     FenceSignalFuture {
         device: device,
         state: Mutex::new(FenceSignalFutureState::Pending(future, fence)),
@@ -104,17 +115,17 @@ pub struct FenceSignalFuture<F>
 // been dropped).
 enum FenceSignalFutureState<F> {
     // Newly-created. Not submitted yet.
-    Pending(F, Fence),
+    Pending(F, Arc<Fence>),
 
     // Partially submitted to the queue. Only happens in situations where submitting requires two
     // steps, and when the first step succeeded while the second step failed.
     //
     // Note that if there's ever a submit operation that needs three steps we will need to rework
     // this code, as it was designed for two-step operations only.
-    PartiallyFlushed(F, Fence),
+    PartiallyFlushed(F, Arc<Fence>),
 
     // Submitted to the queue.
-    Flushed(F, Fence),
+    Flushed(F, Arc<Fence>),
 
     // The submission is finished. The previous future and the fence have been cleaned.
     Cleaned,
@@ -151,6 +162,163 @@ impl<F> FenceSignalFuture<F>
             _ => unreachable!(),
         }
     }
+
+    /// Returns true if the fence has been signaled by the GPU. Doesn't block.
+    ///
+    /// Returns false if the submission hasn't been flushed yet. Contrary to `wait`, this function
+    /// doesn't flush and doesn't clean the resources locked by previous submissions.
+    pub fn is_signaled(&self) -> Result<bool, OomError> {
+        let state = self.state.lock().unwrap();
+
+        match *state {
+            FenceSignalFutureState::Flushed(_, ref fence) => fence.ready(),
+            FenceSignalFutureState::Cleaned => Ok(true),
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns an object that implements `std::future::Future` and that resolves once the fence
+    /// is signaled by the GPU. Performs a flush if necessary.
+    ///
+    /// Instead of blocking the current thread like `wait` does, the fence is handed to a
+    /// background thread shared by all the futures of the program, which wakes the task once the
+    /// fence is signaled. Once resolved, the resources locked by previous submissions have been
+    /// cleaned up, as with `wait`.
+    ///
+    /// ```no_run
+    /// use std::future::Future;
+    /// use vulkano::sync::GpuFuture;
+    ///
+    /// # let future: Box<GpuFuture> = return;
+    /// fn spawn<T: Future>(_: T) { /* hand over the future to an executor */ }
+    ///
+    /// let future = future.then_signal_fence_and_flush().unwrap();
+    /// spawn(future.wait_async());
+    /// ```
+    ///
+    /// The returned object borrows `self`. See `wait_async_owned` for a version that can be
+    /// moved to another thread or stored for as long as needed.
+    ///
+    /// Only available if the `async` feature of vulkano is enabled.
+    #[cfg(feature = "async")]
+    #[inline]
+    pub fn wait_async(&self) -> FenceSignalFutureWait<F> {
+        FenceSignalFutureWait {
+            future: self,
+            token: reaper::token(),
+        }
+    }
+
+    /// Same as `wait_async`, but the returned object holds a reference-counted pointer to the
+    /// future instead of borrowing it. It is `'static` as long as `F` is.
+    ///
+    /// Only available if the `async` feature of vulkano is enabled.
+    #[cfg(feature = "async")]
+    #[inline]
+    pub fn wait_async_owned(self: Arc<Self>) -> FenceSignalFutureWaitOwned<F> {
+        FenceSignalFutureWaitOwned {
+            future: self,
+            token: reaper::token(),
+        }
+    }
+
+    // Implementation of `Future::poll` for `FenceSignalFutureWait` and
+    // `FenceSignalFutureWaitOwned`. `token` identifies the object being polled, so that polling
+    // it again replaces the waker registered by the previous poll.
+    #[cfg(feature = "async")]
+    fn poll_wait(&self, token: usize, cx: &mut Context) -> Poll<Result<(), FlushError>> {
+        let fence = {
+            let mut state = self.state.lock().unwrap();
+            if let Err(err) = self.flush_impl(&mut state) {
+                return Poll::Ready(Err(err));
+            }
+
+            match *state {
+                FenceSignalFutureState::Flushed(_, ref fence) => fence.clone(),
+                FenceSignalFutureState::Cleaned |
+                FenceSignalFutureState::Poisonned => return Poll::Ready(Ok(())),
+                FenceSignalFutureState::Pending(_, _) => unreachable!(),
+                FenceSignalFutureState::PartiallyFlushed(_, _) => unreachable!(),
+            }
+        };
+
+        match reaper::poll(&fence, token, cx.waker()) {
+            Poll::Ready(Ok(())) => {
+                // Doesn't block, but cleans up the previous submissions.
+                Poll::Ready(self.wait(Some(Duration::from_secs(0))))
+            },
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err.into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Future that resolves once a `FenceSignalFuture` is signaled.
+///
+/// Returned by `FenceSignalFuture::wait_async`.
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct FenceSignalFutureWait<'a, F>
+    where F: GpuFuture + 'a
+{
+    future: &'a FenceSignalFuture<F>,
+    token: usize,
+}
+
+#[cfg(feature = "async")]
+impl<'a, F> Future for FenceSignalFutureWait<'a, F>
+    where F: GpuFuture
+{
+    type Output = Result<(), FlushError>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), FlushError>> {
+        self.future.poll_wait(self.token, cx)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'a, F> Drop for FenceSignalFutureWait<'a, F>
+    where F: GpuFuture
+{
+    #[inline]
+    fn drop(&mut self) {
+        reaper::unregister(self.token);
+    }
+}
+
+/// Future that resolves once a `FenceSignalFuture` is signaled.
+///
+/// Returned by `FenceSignalFuture::wait_async_owned`.
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct FenceSignalFutureWaitOwned<F>
+    where F: GpuFuture
+{
+    future: Arc<FenceSignalFuture<F>>,
+    token: usize,
+}
+
+#[cfg(feature = "async")]
+impl<F> Future for FenceSignalFutureWaitOwned<F>
+    where F: GpuFuture
+{
+    type Output = Result<(), FlushError>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), FlushError>> {
+        self.future.poll_wait(self.token, cx)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F> Drop for FenceSignalFutureWaitOwned<F>
+    where F: GpuFuture
+{
+    #[inline]
+    fn drop(&mut self) {
+        reaper::unregister(self.token);
+    }
 }
 
 impl<F> FenceSignalFuture<F>
@@ -503,3 +671,80 @@ unsafe impl<F> GpuFuture for Arc<FenceSignalFuture<F>>
         (**self).check_image_access(image, layout, exclusive, queue)
     }
 }
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use command_buffer::AutoCommandBufferBuilder;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::Context;
+    use std::task::Poll;
+    use std::task::Wake;
+    use std::thread;
+    use sync::GpuFuture;
+    use sync::now;
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {
+        }
+    }
+
+    #[test]
+    fn wait_async() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let future = now(device.clone())
+            .then_execute(queue.clone(), cb)
+            .unwrap()
+            .then_signal_fence();
+
+        let waker = Arc::new(NoopWaker).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut wait = future.wait_async();
+        loop {
+            match Future::poll(Pin::new(&mut wait), &mut cx) {
+                Poll::Ready(result) => break result.unwrap(),
+                Poll::Pending => thread::yield_now(),
+            }
+        }
+
+        assert!(future.is_signaled().unwrap());
+    }
+
+    #[test]
+    fn wait_async_owned() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let cb = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())
+            .unwrap()
+            .build()
+            .unwrap();
+        let future = Arc::new(now(device.clone())
+                                  .then_execute(queue.clone(), cb)
+                                  .unwrap()
+                                  .then_signal_fence());
+
+        // The returned object doesn't borrow anything and can be polled from another thread.
+        let mut wait = future.clone().wait_async_owned();
+        thread::spawn(move || {
+            let waker = Arc::new(NoopWaker).into();
+            let mut cx = Context::from_waker(&waker);
+            loop {
+                match Future::poll(Pin::new(&mut wait), &mut cx) {
+                    Poll::Ready(result) => break result.unwrap(),
+                    Poll::Pending => thread::yield_now(),
+                }
+            }
+        }).join()
+            .unwrap();
+
+        assert!(future.is_signaled().unwrap());
+    }
+}
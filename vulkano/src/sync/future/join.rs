@@ -11,6 +11,8 @@ use std::sync::Arc;
 
 use buffer::BufferAccess;
 use command_buffer::submit::SubmitAnyBuilder;
+use command_buffer::submit::SubmitBindSparseBuilder;
+use command_buffer::submit::SubmitCommandBufferBuilder;
 use device::Device;
 use device::DeviceOwned;
 use device::Queue;
@@ -20,6 +22,7 @@ use sync::AccessCheckError;
 use sync::AccessFlagBits;
 use sync::FlushError;
 use sync::GpuFuture;
+use sync::LazySemaphore;
 use sync::PipelineStages;
 
 use VulkanObject;
@@ -38,9 +41,12 @@ pub fn join<F, S>(first: F, second: S) -> JoinFuture<F, S>
         assert!(first.queue().unwrap().is_same(&second.queue().unwrap()));
     }
 
+    let semaphore = LazySemaphore::new(first.device().clone());
+
     JoinFuture {
         first: first,
         second: second,
+        semaphore: semaphore,
     }
 }
 
@@ -49,6 +55,8 @@ pub fn join<F, S>(first: F, second: S) -> JoinFuture<F, S>
 pub struct JoinFuture<A, B> {
     first: A,
     second: B,
+    // Used to chain the submissions of the two futures when they can't be merged.
+    semaphore: LazySemaphore,
 }
 
 unsafe impl<A, B> DeviceOwned for JoinFuture<A, B>
@@ -83,13 +91,21 @@ unsafe impl<A, B> GpuFuture for JoinFuture<A, B>
         Ok(())
     }
 
-    #[inline]
     unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
-        // TODO: review this function
-
         let first = self.first.build_submission()?;
         let second = self.second.build_submission()?;
 
+        // Command buffer submissions and sparse bindings can only be merged if they are going to
+        // be executed on the same queue.
+        let same_queue = match (self.first.queue(), self.second.queue()) {
+            (Some(q1), Some(q2)) => q1.is_same(&q2),
+            _ => true,
+        };
+
+        // When two submissions can't be merged, the second one is submitted immediately and
+        // signals a semaphore that the first one waits upon. This only happens once, even if the
+        // submission is built again. Presents can't signal anything, so the futures that return
+        // them are flushed instead.
         Ok(match (first, second) {
                (SubmitAnyBuilder::Empty, b) => b,
                (a, SubmitAnyBuilder::Empty) => a,
@@ -98,66 +114,88 @@ unsafe impl<A, B> GpuFuture for JoinFuture<A, B>
                    SubmitAnyBuilder::SemaphoresWait(a)
                },
                (SubmitAnyBuilder::SemaphoresWait(a), SubmitAnyBuilder::CommandBuffer(b)) => {
-                   b.submit(&self.second.queue().clone().unwrap())?;
-                   SubmitAnyBuilder::SemaphoresWait(a)
+                   let a: SubmitCommandBufferBuilder = a.into();
+                   SubmitAnyBuilder::CommandBuffer(a.merge(b))
                },
                (SubmitAnyBuilder::CommandBuffer(a), SubmitAnyBuilder::SemaphoresWait(b)) => {
-                   a.submit(&self.first.queue().clone().unwrap())?;
-                   SubmitAnyBuilder::SemaphoresWait(b)
-               },
-               (SubmitAnyBuilder::SemaphoresWait(a), SubmitAnyBuilder::QueuePresent(b)) => {
-                   b.submit(&self.second.queue().clone().unwrap())?;
-                   SubmitAnyBuilder::SemaphoresWait(a)
-               },
-               (SubmitAnyBuilder::QueuePresent(a), SubmitAnyBuilder::SemaphoresWait(b)) => {
-                   a.submit(&self.first.queue().clone().unwrap())?;
-                   SubmitAnyBuilder::SemaphoresWait(b)
+                   SubmitAnyBuilder::CommandBuffer(a.merge(b.into()))
                },
                (SubmitAnyBuilder::SemaphoresWait(a), SubmitAnyBuilder::BindSparse(b)) => {
-                   b.submit(&self.second.queue().clone().unwrap())?;
-                   SubmitAnyBuilder::SemaphoresWait(a)
+                   let mut a: SubmitBindSparseBuilder = a.into();
+                   // Can't fail, since `a` doesn't have a fence.
+                   a.merge(b).unwrap();
+                   SubmitAnyBuilder::BindSparse(a)
                },
                (SubmitAnyBuilder::BindSparse(a), SubmitAnyBuilder::SemaphoresWait(b)) => {
-                   a.submit(&self.first.queue().clone().unwrap())?;
-                   SubmitAnyBuilder::SemaphoresWait(b)
+                   let mut b: SubmitBindSparseBuilder = b.into();
+                   // Can't fail, since `b` doesn't have a fence.
+                   b.merge(a).unwrap();
+                   SubmitAnyBuilder::BindSparse(b)
                },
-               (SubmitAnyBuilder::CommandBuffer(a), SubmitAnyBuilder::CommandBuffer(b)) => {
-                   // TODO: we may want to add debug asserts here
-                   let new = a.merge(b);
-                   SubmitAnyBuilder::CommandBuffer(new)
-               },
-               (SubmitAnyBuilder::QueuePresent(a), SubmitAnyBuilder::QueuePresent(b)) => {
-                   a.submit(&self.first.queue().clone().unwrap())?;
-                   b.submit(&self.second.queue().clone().unwrap())?;
+               (SubmitAnyBuilder::QueuePresent(_), SubmitAnyBuilder::QueuePresent(_)) => {
+                   self.first.flush()?;
+                   self.second.flush()?;
                    SubmitAnyBuilder::Empty
                },
-               (SubmitAnyBuilder::CommandBuffer(a), SubmitAnyBuilder::QueuePresent(b)) => {
-                   unimplemented!()
-               },
-               (SubmitAnyBuilder::QueuePresent(a), SubmitAnyBuilder::CommandBuffer(b)) => {
-                   unimplemented!()
+               (a, SubmitAnyBuilder::QueuePresent(_)) => {
+                   self.second.flush()?;
+                   a
                },
-               (SubmitAnyBuilder::BindSparse(a), SubmitAnyBuilder::QueuePresent(b)) => {
-                   unimplemented!()
+               (SubmitAnyBuilder::QueuePresent(_), b) => {
+                   self.first.flush()?;
+                   b
                },
-               (SubmitAnyBuilder::QueuePresent(a), SubmitAnyBuilder::BindSparse(b)) => {
-                   unimplemented!()
+               (SubmitAnyBuilder::CommandBuffer(mut a), SubmitAnyBuilder::CommandBuffer(mut b)) => {
+                   if same_queue && !(a.has_fence() && b.has_fence()) {
+                       SubmitAnyBuilder::CommandBuffer(a.merge(b))
+                   } else {
+                       let semaphore = self.semaphore.submit_signal(|semaphore| {
+                           b.add_signal_semaphore(semaphore);
+                           b.submit(&self.second.queue().unwrap()).map_err(FlushError::from)
+                       })?;
+                       a.add_wait_semaphore(semaphore, PipelineStages {
+                           all_commands: true,
+                           ..PipelineStages::none()
+                       });
+                       SubmitAnyBuilder::CommandBuffer(a)
+                   }
                },
-               (SubmitAnyBuilder::BindSparse(a), SubmitAnyBuilder::CommandBuffer(b)) => {
-                   unimplemented!()
+               (SubmitAnyBuilder::BindSparse(mut a), SubmitAnyBuilder::CommandBuffer(mut b)) => {
+                   let semaphore = self.semaphore.submit_signal(|semaphore| {
+                       b.add_signal_semaphore(semaphore);
+                       b.submit(&self.second.queue().unwrap()).map_err(FlushError::from)
+                   })?;
+                   a.add_wait_semaphore(semaphore);
+                   SubmitAnyBuilder::BindSparse(a)
                },
-               (SubmitAnyBuilder::CommandBuffer(a), SubmitAnyBuilder::BindSparse(b)) => {
-                   unimplemented!()
+               (SubmitAnyBuilder::CommandBuffer(mut a), SubmitAnyBuilder::BindSparse(mut b)) => {
+                   let semaphore = self.semaphore.submit_signal(|semaphore| {
+                       b.add_signal_semaphore(semaphore);
+                       b.submit(&self.second.queue().unwrap()).map_err(FlushError::from)
+                   })?;
+                   a.add_wait_semaphore(semaphore, PipelineStages {
+                       all_commands: true,
+                       ..PipelineStages::none()
+                   });
+                   SubmitAnyBuilder::CommandBuffer(a)
                },
                (SubmitAnyBuilder::BindSparse(mut a), SubmitAnyBuilder::BindSparse(b)) => {
-                   match a.merge(b) {
-                       Ok(()) => SubmitAnyBuilder::BindSparse(a),
-                       Err(_) => {
-                           // TODO: this happens if both bind sparse have been given a fence already
-                           //       annoying, but not impossible, to handle
-                           unimplemented!()
-                       },
-                   }
+                   // Merging fails if both builders have been given a fence already.
+                   let mut b = if same_queue {
+                       match a.merge(b) {
+                           Ok(()) => return Ok(SubmitAnyBuilder::BindSparse(a)),
+                           Err(b) => b,
+                       }
+                   } else {
+                       b
+                   };
+
+                   let semaphore = self.semaphore.submit_signal(|semaphore| {
+                       b.add_signal_semaphore(semaphore);
+                       b.submit(&self.second.queue().unwrap()).map_err(FlushError::from)
+                   })?;
+                   a.add_wait_semaphore(semaphore);
+                   SubmitAnyBuilder::BindSparse(a)
                },
            })
     }
@@ -243,3 +281,334 @@ unsafe impl<A, B> GpuFuture for JoinFuture<A, B>
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+
+    use buffer::BufferAccess;
+    use command_buffer::submit::SubmitAnyBuilder;
+    use command_buffer::submit::SubmitBindSparseBatchBuilder;
+    use command_buffer::submit::SubmitBindSparseBuilder;
+    use command_buffer::submit::SubmitCommandBufferBuilder;
+    use command_buffer::submit::SubmitPresentBuilder;
+    use command_buffer::submit::SubmitSemaphoresWaitBuilder;
+    use device::Device;
+    use device::DeviceOwned;
+    use device::Queue;
+    use image::ImageAccess;
+    use image::ImageLayout;
+    use sync::AccessCheckError;
+    use sync::AccessFlagBits;
+    use sync::Fence;
+    use sync::FlushError;
+    use sync::GpuFuture;
+    use sync::PipelineStages;
+    use sync::Semaphore;
+    use testing::Call;
+    use testing::MockLoader;
+
+    use SynchronizedVulkanObject;
+    use VulkanObject;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    enum Kind {
+        Empty,
+        SemaphoresWait,
+        CommandBuffer,
+        QueuePresent,
+        BindSparse,
+    }
+
+    const KINDS: [Kind; 5] = [
+        Kind::Empty,
+        Kind::SemaphoresWait,
+        Kind::CommandBuffer,
+        Kind::QueuePresent,
+        Kind::BindSparse,
+    ];
+
+    // Future whose `build_submission` returns a builder of the given kind, and that remembers
+    // whether it has been flushed.
+    struct DummyFuture {
+        kind: Kind,
+        queue: Arc<Queue>,
+        semaphore: Semaphore,
+        fence: Option<Fence>,
+        flushed: AtomicBool,
+    }
+
+    impl DummyFuture {
+        fn new(kind: Kind, queue: &Arc<Queue>, with_fence: bool) -> DummyFuture {
+            let device = queue.device().clone();
+            DummyFuture {
+                kind: kind,
+                queue: queue.clone(),
+                semaphore: Semaphore::alloc(device.clone()).unwrap(),
+                fence: if with_fence {
+                    Some(Fence::alloc(device).unwrap())
+                } else {
+                    None
+                },
+                flushed: AtomicBool::new(false),
+            }
+        }
+    }
+
+    unsafe impl DeviceOwned for DummyFuture {
+        fn device(&self) -> &Arc<Device> {
+            self.queue.device()
+        }
+    }
+
+    unsafe impl GpuFuture for DummyFuture {
+        fn cleanup_finished(&mut self) {
+        }
+
+        unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+            Ok(match self.kind {
+                   Kind::Empty => SubmitAnyBuilder::Empty,
+                   Kind::SemaphoresWait => {
+                       let mut builder = SubmitSemaphoresWaitBuilder::new();
+                       builder.add_wait_semaphore(&self.semaphore);
+                       SubmitAnyBuilder::SemaphoresWait(builder)
+                   },
+                   Kind::CommandBuffer => {
+                       let mut builder = SubmitCommandBufferBuilder::new();
+                       if let Some(ref fence) = self.fence {
+                           builder.set_fence_signal(fence);
+                       }
+                       SubmitAnyBuilder::CommandBuffer(builder)
+                   },
+                   // Never submitted, since the mock driver doesn't support swapchains.
+                   Kind::QueuePresent => {
+                       SubmitAnyBuilder::QueuePresent(SubmitPresentBuilder::new())
+                   },
+                   Kind::BindSparse => {
+                       let mut builder = SubmitBindSparseBuilder::new();
+                       builder.add(SubmitBindSparseBatchBuilder::new());
+                       if let Some(ref fence) = self.fence {
+                           builder.set_fence_signal(fence);
+                       }
+                       SubmitAnyBuilder::BindSparse(builder)
+                   },
+               })
+        }
+
+        fn flush(&self) -> Result<(), FlushError> {
+            self.flushed.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        unsafe fn signal_finished(&self) {
+        }
+
+        fn queue(&self) -> Option<Arc<Queue>> {
+            Some(self.queue.clone())
+        }
+
+        fn queue_change_allowed(&self) -> bool {
+            true
+        }
+
+        fn check_buffer_access(
+            &self, _: &BufferAccess, _: bool, _: &Queue)
+            -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+            Err(AccessCheckError::Unknown)
+        }
+
+        fn check_image_access(
+            &self, _: &ImageAccess, _: ImageLayout, _: bool, _: &Queue)
+            -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+            Err(AccessCheckError::Unknown)
+        }
+    }
+
+    // Builds a mock device with two queues.
+    fn mock_device() -> (MockLoader, Arc<Queue>, Arc<Queue>) {
+        let (loader, _, mut queues) = mock_dev_and_queue!(0, 0);
+        let q1 = queues.next().unwrap();
+        let q2 = queues.next().unwrap();
+        (loader, q1, q2)
+    }
+
+    fn kind_of(builder: &SubmitAnyBuilder) -> Kind {
+        match *builder {
+            SubmitAnyBuilder::Empty => Kind::Empty,
+            SubmitAnyBuilder::SemaphoresWait(_) => Kind::SemaphoresWait,
+            SubmitAnyBuilder::CommandBuffer(_) => Kind::CommandBuffer,
+            SubmitAnyBuilder::QueuePresent(_) => Kind::QueuePresent,
+            SubmitAnyBuilder::BindSparse(_) => Kind::BindSparse,
+        }
+    }
+
+    // Submits the builder returned by a join, except if it's a present.
+    fn submit(builder: SubmitAnyBuilder, queue: &Queue) {
+        match builder {
+            SubmitAnyBuilder::Empty |
+            SubmitAnyBuilder::QueuePresent(_) => (),
+            SubmitAnyBuilder::SemaphoresWait(builder) => {
+                let builder: SubmitCommandBufferBuilder = builder.into();
+                builder.submit(queue).unwrap();
+            },
+            SubmitAnyBuilder::CommandBuffer(builder) => builder.submit(queue).unwrap(),
+            SubmitAnyBuilder::BindSparse(builder) => builder.submit(queue).unwrap(),
+        }
+    }
+
+    // Returns the queue, the waited semaphores and the signaled semaphores of each submission.
+    fn submissions(calls: Vec<Call>) -> Vec<(usize, Vec<u64>, Vec<u64>)> {
+        calls
+            .into_iter()
+            .filter_map(|call| match call {
+                            Call::QueueSubmit { queue, batches, .. } => {
+                                let wait = batches
+                                    .iter()
+                                    .flat_map(|b| b.wait_semaphores.iter().map(|&(s, _)| s))
+                                    .collect();
+                                let signal = batches
+                                    .iter()
+                                    .flat_map(|b| b.signal_semaphores.iter().cloned())
+                                    .collect();
+                                Some((queue, wait, signal))
+                            },
+                            Call::QueueBindSparse {
+                                queue,
+                                wait_semaphores,
+                                signal_semaphores,
+                                ..
+                            } => Some((queue, wait_semaphores, signal_semaphores)),
+                            _ => None,
+                        })
+            .collect()
+    }
+
+    // Kind of the builder that joining `a` and `b` is expected to return.
+    fn expected_kind(a: Kind, b: Kind) -> Kind {
+        match (a, b) {
+            (Kind::Empty, b) => b,
+            (a, Kind::Empty) => a,
+            (Kind::QueuePresent, Kind::QueuePresent) => Kind::Empty,
+            (a, Kind::QueuePresent) => a,
+            (Kind::QueuePresent, b) => b,
+            (Kind::SemaphoresWait, b) => b,
+            (a, _) => a,
+        }
+    }
+
+    #[test]
+    fn all_pairs() {
+        let (loader, queue, _) = mock_device();
+
+        for &a in KINDS.iter() {
+            for &b in KINDS.iter() {
+                let join = DummyFuture::new(a, &queue, false)
+                    .join(DummyFuture::new(b, &queue, false));
+                loader.take_calls();
+
+                let builder = unsafe { join.build_submission().unwrap() };
+                assert_eq!(kind_of(&builder), expected_kind(a, b), "{:?} + {:?}", a, b);
+
+                // Presents are submitted by flushing the future that returned them, unless the
+                // other side is empty.
+                assert_eq!(join.first.flushed.load(Ordering::SeqCst),
+                           a == Kind::QueuePresent && b != Kind::Empty);
+                assert_eq!(join.second.flushed.load(Ordering::SeqCst),
+                           b == Kind::QueuePresent && a != Kind::Empty);
+
+                // A command buffer submission and a sparse binding can't be merged, in which
+                // case the second one is submitted immediately and signals a semaphore.
+                let chained = submissions(loader.take_calls());
+                let must_chain = match (a, b) {
+                    (Kind::CommandBuffer, Kind::BindSparse) |
+                    (Kind::BindSparse, Kind::CommandBuffer) => true,
+                    _ => false,
+                };
+                assert_eq!(chained.len(), must_chain as usize, "{:?} + {:?}", a, b);
+
+                submit(builder, &queue);
+                let submitted = submissions(loader.take_calls());
+                if let Some(&(_, _, ref signal)) = chained.first() {
+                    assert_eq!(signal.len(), 1);
+                    assert_eq!(submitted[0].1, *signal);
+                }
+
+                // The semaphores of the `SemaphoresWait` builders must still be waited upon.
+                for side in [&join.first, &join.second].iter() {
+                    if side.kind == Kind::SemaphoresWait && expected_kind(a, b) != Kind::Empty {
+                        assert!(submitted[0].1.contains(&side.semaphore.internal_object()));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn different_queues() {
+        let (loader, q1, q2) = mock_device();
+
+        for &(a, b) in &[
+            (Kind::CommandBuffer, Kind::CommandBuffer),
+            (Kind::BindSparse, Kind::BindSparse),
+        ]
+        {
+            let join = DummyFuture::new(a, &q1, false).join(DummyFuture::new(b, &q2, false));
+            loader.take_calls();
+
+            let builder = unsafe { join.build_submission().unwrap() };
+            assert_eq!(kind_of(&builder), a);
+
+            let chained = submissions(loader.take_calls());
+            assert_eq!(chained.len(), 1);
+            assert_eq!(chained[0].0, *q2.internal_object_guard());
+
+            submit(builder, &q1);
+            let submitted = submissions(loader.take_calls());
+            assert_eq!(submitted[0].0, *q1.internal_object_guard());
+            assert_eq!(submitted[0].1, chained[0].2);
+        }
+    }
+
+    #[test]
+    fn both_fences() {
+        let (loader, queue, _) = mock_device();
+
+        for &kind in &[Kind::CommandBuffer, Kind::BindSparse] {
+            let join = DummyFuture::new(kind, &queue, true)
+                .join(DummyFuture::new(kind, &queue, true));
+            loader.take_calls();
+
+            let builder = unsafe { join.build_submission().unwrap() };
+            assert_eq!(kind_of(&builder), kind);
+
+            let chained = submissions(loader.take_calls());
+            assert_eq!(chained.len(), 1);
+
+            submit(builder, &queue);
+            let submitted = submissions(loader.take_calls());
+            assert_eq!(submitted[0].1, chained[0].2);
+        }
+    }
+
+    #[test]
+    fn chained_submitted_once() {
+        let (loader, q1, q2) = mock_device();
+
+        let join = DummyFuture::new(Kind::CommandBuffer, &q1, false)
+            .join(DummyFuture::new(Kind::CommandBuffer, &q2, false));
+        loader.take_calls();
+
+        // Building the submission again, for example after a failed submit, must not submit the
+        // second side again.
+        let first = unsafe { join.build_submission().unwrap() };
+        let second = unsafe { join.build_submission().unwrap() };
+        assert_eq!(submissions(loader.take_calls()).len(), 1);
+
+        submit(first, &q1);
+        submit(second, &q1);
+        let submitted = submissions(loader.take_calls());
+        assert_eq!(submitted[0].1, submitted[1].1);
+    }
+}
@@ -33,6 +33,8 @@ use sync::FenceWaitError;
 use sync::PipelineStages;
 
 pub use self::fence_signal::{FenceSignalFuture, FenceSignalFutureBehavior};
+#[cfg(feature = "async")]
+pub use self::fence_signal::{FenceSignalFutureWait, FenceSignalFutureWaitOwned};
 pub use self::join::JoinFuture;
 pub use self::now::{NowFuture, now};
 pub use self::semaphore_signal::SemaphoreSignalFuture;
@@ -40,6 +42,8 @@ pub use self::semaphore_signal::SemaphoreSignalFuture;
 mod now;
 mod fence_signal;
 mod join;
+#[cfg(feature = "async")]
+mod reaper;
 mod semaphore_signal;
 
 /// Represents an event that will happen on the GPU in the future.
@@ -466,6 +470,13 @@ impl From<AccessError> for FlushError {
     }
 }
 
+impl From<OomError> for FlushError {
+    #[inline]
+    fn from(err: OomError) -> FlushError {
+        FlushError::OomError(err)
+    }
+}
+
 impl From<SubmitPresentError> for FlushError {
     #[inline]
     fn from(err: SubmitPresentError) -> FlushError {
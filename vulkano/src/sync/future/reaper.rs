@@ -0,0 +1,267 @@
+// Copyright (c) 2017 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Background thread that waits for fences on behalf of asynchronous tasks.
+//!
+//! The thread is started the first time a fence is registered, and sleeps on a condition variable
+//! as long as there is no fence to wait for. Otherwise it waits for any of the pending fences of
+//! a device with `Fence::multi_wait_any`, then wakes the tasks whose fence is signaled.
+//!
+//! A Vulkan wait can't be interrupted, so a fence that is registered while the thread is blocked
+//! is only taken into account once the current wait returns, either because one of the fences
+//! got signaled or at the latest after `WAIT_TIMEOUT_MS`.
+
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::task::Poll;
+use std::task::Waker;
+use std::thread;
+use std::time::Duration;
+
+use OomError;
+use device::DeviceOwned;
+use sync::Fence;
+use sync::FenceWaitError;
+
+// Maximum duration of a single wait of the reaper thread.
+const WAIT_TIMEOUT_MS: u64 = 100;
+
+lazy_static! {
+    static ref REAPER: Arc<Reaper> = {
+        let reaper = Arc::new(Reaper {
+            pending: Mutex::new(Vec::new()),
+            condvar: Condvar::new(),
+        });
+
+        let reaper2 = reaper.clone();
+        thread::Builder::new()
+            .name("vulkano fence reaper".to_owned())
+            .spawn(move || reaper2.run())
+            .expect("failed to start the fence reaper thread");
+
+        reaper
+    };
+}
+
+static NEXT_TOKEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a new value that identifies a task waiting for a fence. Passing the same token to
+/// `poll` multiple times replaces the waker of the previous call.
+#[inline]
+pub fn token() -> usize {
+    NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Checks whether `fence` is signaled. If it isn't, registers `waker` so that it is woken once the
+/// fence is signaled, or if checking the status of the fence fails.
+pub fn poll(fence: &Arc<Fence>, token: usize, waker: &Waker) -> Poll<Result<(), OomError>> {
+    match fence.ready() {
+        Ok(true) => Poll::Ready(Ok(())),
+        Ok(false) => {
+            // If the fence gets signaled between the check above and the registration, the reaper
+            // will notice it and wake the task immediately.
+            register(fence, token, waker);
+            Poll::Pending
+        },
+        Err(err) => Poll::Ready(Err(err)),
+    }
+}
+
+/// Removes the registration of the task identified by `token`, if any. Called when the task is
+/// dropped, so that its waker and its fence aren't kept alive until the fence is signaled.
+pub fn unregister(token: usize) {
+    let mut pending = REAPER.pending.lock().unwrap();
+    pending.retain(|e| e.token != token);
+}
+
+fn register(fence: &Arc<Fence>, token: usize, waker: &Waker) {
+    let mut pending = REAPER.pending.lock().unwrap();
+
+    if let Some(entry) = pending.iter_mut().find(|e| e.token == token) {
+        entry.fence = fence.clone();
+        if !entry.waker.will_wake(waker) {
+            entry.waker = waker.clone();
+        }
+        return;
+    }
+
+    pending.push(Registration {
+                     fence: fence.clone(),
+                     token: token,
+                     waker: waker.clone(),
+                 });
+    REAPER.condvar.notify_one();
+}
+
+struct Reaper {
+    // List of fences to wait upon, and the tasks to wake once they are signaled.
+    pending: Mutex<Vec<Registration>>,
+    // Notified whenever a fence is registered.
+    condvar: Condvar,
+}
+
+struct Registration {
+    fence: Arc<Fence>,
+    token: usize,
+    waker: Waker,
+}
+
+impl Reaper {
+    fn run(&self) {
+        loop {
+            let fences = {
+                let mut pending = self.pending.lock().unwrap();
+                while pending.is_empty() {
+                    pending = self.condvar.wait(pending).unwrap();
+                }
+                pending.iter().map(|e| e.fence.clone()).collect::<Vec<_>>()
+            };
+
+            // Only the fences of a single device can be waited upon at once. The fences of the
+            // other devices, if any, are polled below.
+            let device = fences[0].device().clone();
+            let result = Fence::multi_wait_any(fences
+                                                   .iter()
+                                                   .filter(|f| Arc::ptr_eq(f.device(), &device))
+                                                   .map(|f| &**f),
+                                               Some(Duration::from_millis(WAIT_TIMEOUT_MS)));
+
+            match result {
+                Ok(()) | Err(FenceWaitError::Timeout) => (),
+                // The wait returns immediately if the device is lost or out of memory, so we wait
+                // for the timeout duration before trying again in order not to spin.
+                Err(FenceWaitError::OomError(_)) | Err(FenceWaitError::DeviceLostError) => {
+                    thread::sleep(Duration::from_millis(WAIT_TIMEOUT_MS));
+                },
+            }
+
+            // Errors are reported to the task by waking it, as it will check the fence again.
+            let mut to_wake = Vec::new();
+            {
+                let mut pending = self.pending.lock().unwrap();
+                pending.retain(|e| match e.fence.ready() {
+                                   Ok(false) => true,
+                                   _ => {
+                                       to_wake.push(e.waker.clone());
+                                       false
+                                   },
+                               });
+            }
+
+            // Waking a task can run arbitrary code, including code that registers a fence, so it
+            // must only be done once the lock is released.
+            for waker in to_wake {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use command_buffer::submit::SubmitCommandBufferBuilder;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::sync::mpsc;
+    use std::task::Poll;
+    use std::task::Wake;
+    use std::task::Waker;
+    use std::time::Duration;
+    use sync::Fence;
+    use sync::future::reaper;
+
+    // Waker that sends a message every time it is woken.
+    struct ChannelWaker(Mutex<mpsc::Sender<()>>);
+
+    impl Wake for ChannelWaker {
+        fn wake(self: Arc<Self>) {
+            let _ = self.0.lock().unwrap().send(());
+        }
+    }
+
+    fn channel_waker() -> (Waker, mpsc::Receiver<()>) {
+        let (tx, rx) = mpsc::channel();
+        (Arc::new(ChannelWaker(Mutex::new(tx))).into(), rx)
+    }
+
+    #[test]
+    fn wakes_on_signal() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let fence = Arc::new(Fence::alloc(device.clone()).unwrap());
+        let token = reaper::token();
+        let (waker, woken) = channel_waker();
+        match reaper::poll(&fence, token, &waker) {
+            Poll::Pending => (),
+            _ => panic!(),
+        }
+
+        let mut builder = SubmitCommandBufferBuilder::new();
+        unsafe {
+            builder.set_fence_signal(&fence);
+        }
+        builder.submit(&queue).unwrap();
+
+        woken.recv_timeout(Duration::from_secs(5)).expect("the task was never woken");
+        match reaper::poll(&fence, token, &waker) {
+            Poll::Ready(Ok(())) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn repoll_replaces_waker() {
+        let (device, queue) = gfx_dev_and_queue!();
+
+        let fence = Arc::new(Fence::alloc(device.clone()).unwrap());
+        let token = reaper::token();
+
+        // Each poll uses a different waker, only the last one must be woken.
+        let (first, first_woken) = channel_waker();
+        let (second, second_woken) = channel_waker();
+        assert!(reaper::poll(&fence, token, &first).is_pending());
+        assert!(reaper::poll(&fence, token, &second).is_pending());
+
+        let mut builder = SubmitCommandBufferBuilder::new();
+        unsafe {
+            builder.set_fence_signal(&fence);
+        }
+        builder.submit(&queue).unwrap();
+
+        second_woken.recv_timeout(Duration::from_secs(5)).expect("the task was never woken");
+        assert!(first_woken.try_recv().is_err());
+    }
+
+    #[test]
+    fn unregister_removes_waker() {
+        let (_, device, _) = mock_dev_and_queue!();
+
+        // The fence is never submitted, so only `unregister` can remove the registration.
+        let fence = Arc::new(Fence::alloc(device.clone()).unwrap());
+        let token = reaper::token();
+        let (waker, _woken) = channel_waker();
+        assert!(reaper::poll(&fence, token, &waker).is_pending());
+        assert!(is_registered(token));
+
+        reaper::unregister(token);
+        assert!(!is_registered(token));
+    }
+
+    fn is_registered(token: usize) -> bool {
+        reaper::REAPER
+            .pending
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e.token == token)
+    }
+}
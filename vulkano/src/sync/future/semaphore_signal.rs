@@ -104,11 +104,9 @@ unsafe impl<F> GpuFuture for SemaphoreSignalFuture<F>
                     builder.add_signal_semaphore(&self.semaphore);
                     builder.submit(&queue)?;
                 },
-                SubmitAnyBuilder::BindSparse(_) => {
-                    unimplemented!() // TODO: how to do that?
-                    /*debug_assert_eq!(builder.num_signal_semaphores(), 0);
+                SubmitAnyBuilder::BindSparse(mut builder) => {
                     builder.add_signal_semaphore(&self.semaphore);
-                    try!(builder.submit(&queue));*/
+                    builder.submit(&queue)?;
                 },
                 SubmitAnyBuilder::QueuePresent(present) => {
                     present.submit(&queue)?;
@@ -112,7 +112,12 @@ pub use self::fence::FenceWaitError;
 pub use self::future::AccessCheckError;
 pub use self::future::AccessError;
 pub use self::future::FenceSignalFuture;
+#[cfg(feature = "async")]
+pub use self::future::FenceSignalFutureWait;
+#[cfg(feature = "async")]
+pub use self::future::FenceSignalFutureWaitOwned;
 pub use self::future::FlushError;
+pub use self::frames_in_flight::FramesInFlight;
 pub use self::future::GpuFuture;
 pub use self::future::JoinFuture;
 pub use self::future::NowFuture;
@@ -121,9 +126,11 @@ pub use self::future::now;
 pub use self::pipeline::AccessFlagBits;
 pub use self::pipeline::PipelineStages;
 pub use self::semaphore::Semaphore;
+pub(crate) use self::semaphore::LazySemaphore;
 
 mod event;
 mod fence;
+mod frames_in_flight;
 mod future;
 mod pipeline;
 mod semaphore;
@@ -10,6 +10,7 @@
 use std::mem;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use OomError;
 use SafeDeref;
@@ -127,6 +128,65 @@ impl<D> Drop for Semaphore<D>
     }
 }
 
+/// A semaphore from the pool that is only taken the first time it is needed.
+///
+/// Used by futures that need a semaphore in order to chain two submissions that can't be merged,
+/// which is rare enough that we don't want to pay for a semaphore every time.
+#[derive(Debug)]
+pub(crate) struct LazySemaphore {
+    device: Arc<Device>,
+    // Boxed so that the semaphore doesn't move. Never modified once it contains `Some`.
+    semaphore: Mutex<Option<Box<Semaphore>>>,
+    // True if a submission that signals the semaphore has been submitted.
+    signal_submitted: Mutex<bool>,
+}
+
+impl LazySemaphore {
+    /// Builds a new `LazySemaphore`. Doesn't allocate anything.
+    #[inline]
+    pub fn new(device: Arc<Device>) -> LazySemaphore {
+        LazySemaphore {
+            device: device,
+            semaphore: Mutex::new(None),
+            signal_submitted: Mutex::new(false),
+        }
+    }
+
+    /// Returns the semaphore, taking it from the pool if this is the first call.
+    pub fn get(&self) -> Result<&Semaphore, OomError> {
+        let mut semaphore = self.semaphore.lock().unwrap();
+
+        if semaphore.is_none() {
+            *semaphore = Some(Box::new(Semaphore::from_pool(self.device.clone())?));
+        }
+
+        // The box is never freed or replaced before `self` is destroyed, therefore the
+        // reference stays valid after the lock is released.
+        let ptr: *const Semaphore = &**semaphore.as_ref().unwrap();
+        unsafe { Ok(&*ptr) }
+    }
+
+    /// Returns the semaphore, after calling `submit` with it unless a previous call to this
+    /// function succeeded.
+    ///
+    /// `submit` is supposed to submit an operation that signals the semaphore. This can only
+    /// happen once, even if the submission that waits upon the semaphore is built again.
+    pub fn submit_signal<'s, F, E>(&'s self, submit: F) -> Result<&'s Semaphore, E>
+        where F: FnOnce(&'s Semaphore) -> Result<(), E>,
+              E: From<OomError>
+    {
+        let semaphore = self.get()?;
+
+        let mut signal_submitted = self.signal_submitted.lock().unwrap();
+        if !*signal_submitted {
+            submit(semaphore)?;
+            *signal_submitted = true;
+        }
+
+        Ok(semaphore)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use VulkanObject;
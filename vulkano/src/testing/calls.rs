@@ -0,0 +1,290 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+// Dispatchable handles (instances, physical devices, devices, queues and command buffers) are
+// stored as `usize`, while non-dispatchable handles are stored as `u64`. This matches what
+// `VulkanObject::internal_object()` returns for the corresponding vulkano objects.
+
+macro_rules! calls {
+    ($($name:ident { $($field:ident: $ty:ty),* },)+) => (
+        /// A call made to the mock Vulkan implementation.
+        ///
+        /// Each variant is named after the Vulkan function without its `vk` prefix, and holds the
+        /// parameters that were passed to it as well as the handles that were returned.
+        ///
+        /// Calls that retrieve function pointers (`vkGetInstanceProcAddr` and
+        /// `vkGetDeviceProcAddr`) are not recorded, and neither are the calls that are made
+        /// before an instance exists.
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum Call {
+            $(
+                $name { $($field: $ty),* },
+            )+
+        }
+
+        impl Call {
+            /// Returns the name of the Vulkan function, for example `"vkCmdDraw"`.
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    $(
+                        Call::$name { .. } => concat!("vk", stringify!($name)),
+                    )+
+                }
+            }
+        }
+    )
+}
+
+calls! {
+    CreateInstance { instance: usize, extensions: Vec<String>, layers: Vec<String> },
+    DestroyInstance { instance: usize },
+    EnumeratePhysicalDevices { instance: usize },
+    EnumerateDeviceExtensionProperties { physical_device: usize },
+    DestroySurfaceKHR { instance: usize, surface: u64 },
+    GetPhysicalDeviceSurfaceSupportKHR { physical_device: usize, queue_family: u32, surface: u64 },
+    GetPhysicalDeviceSurfaceCapabilitiesKHR { physical_device: usize, surface: u64 },
+    GetPhysicalDeviceSurfaceFormatsKHR { physical_device: usize, surface: u64 },
+    GetPhysicalDeviceSurfacePresentModesKHR { physical_device: usize, surface: u64 },
+    EnumerateDeviceLayerProperties { physical_device: usize },
+    GetPhysicalDeviceFeatures { physical_device: usize },
+    GetPhysicalDeviceFormatProperties { physical_device: usize, format: u32 },
+    GetPhysicalDeviceImageFormatProperties { physical_device: usize, format: u32 },
+    GetPhysicalDeviceProperties { physical_device: usize },
+    GetPhysicalDeviceQueueFamilyProperties { physical_device: usize },
+    GetPhysicalDeviceMemoryProperties { physical_device: usize },
+    GetPhysicalDeviceSparseImageFormatProperties { physical_device: usize, format: u32 },
+    CreateDevice { physical_device: usize, device: usize, extensions: Vec<String>,
+                   queues: Vec<(u32, u32)> },
+    DestroyDevice { device: usize },
+    GetDeviceQueue { device: usize, queue_family: u32, queue_index: u32, queue: usize },
+    QueueSubmit { queue: usize, batches: Vec<SubmitBatch>, fence: u64 },
+    QueueWaitIdle { queue: usize },
+    QueueBindSparse { queue: usize, wait_semaphores: Vec<u64>, signal_semaphores: Vec<u64>,
+                      fence: u64 },
+    DeviceWaitIdle { device: usize },
+    AllocateMemory { device: usize, memory: u64, size: u64, memory_type: u32 },
+    FreeMemory { device: usize, memory: u64 },
+    MapMemory { device: usize, memory: u64, offset: u64, size: u64 },
+    UnmapMemory { device: usize, memory: u64 },
+    FlushMappedMemoryRanges { device: usize, ranges: Vec<(u64, u64, u64)> },
+    InvalidateMappedMemoryRanges { device: usize, ranges: Vec<(u64, u64, u64)> },
+    GetDeviceMemoryCommitment { device: usize, memory: u64 },
+    BindBufferMemory { device: usize, buffer: u64, memory: u64, offset: u64 },
+    BindImageMemory { device: usize, image: u64, memory: u64, offset: u64 },
+    GetBufferMemoryRequirements { device: usize, buffer: u64 },
+    GetImageMemoryRequirements { device: usize, image: u64 },
+    GetImageSparseMemoryRequirements { device: usize, image: u64 },
+    CreateFence { device: usize, fence: u64, signaled: bool },
+    DestroyFence { device: usize, fence: u64 },
+    ResetFences { device: usize, fences: Vec<u64> },
+    GetFenceStatus { device: usize, fence: u64 },
+    WaitForFences { device: usize, fences: Vec<u64>, wait_all: bool, timeout: u64 },
+    CreateSemaphore { device: usize, semaphore: u64 },
+    DestroySemaphore { device: usize, semaphore: u64 },
+    CreateEvent { device: usize, event: u64 },
+    DestroyEvent { device: usize, event: u64 },
+    GetEventStatus { device: usize, event: u64 },
+    SetEvent { device: usize, event: u64 },
+    ResetEvent { device: usize, event: u64 },
+    CreateQueryPool { device: usize, query_pool: u64, ty: u32, query_count: u32,
+                      pipeline_statistics: u32 },
+    DestroyQueryPool { device: usize, query_pool: u64 },
+    GetQueryPoolResults { device: usize, query_pool: u64, first_query: u32, query_count: u32,
+                          stride: u64, flags: u32 },
+    CreateBuffer { device: usize, buffer: u64, size: u64, usage: u32, sharing_mode: u32,
+                   queue_families: Vec<u32> },
+    DestroyBuffer { device: usize, buffer: u64 },
+    CreateBufferView { device: usize, view: u64, buffer: u64, format: u32, offset: u64,
+                       range: u64 },
+    DestroyBufferView { device: usize, view: u64 },
+    CreateImage { device: usize, image: u64, format: u32, extent: [u32; 3], mip_levels: u32,
+                  array_layers: u32, samples: u32, usage: u32, sharing_mode: u32,
+                  queue_families: Vec<u32> },
+    DestroyImage { device: usize, image: u64 },
+    GetImageSubresourceLayout { device: usize, image: u64 },
+    CreateImageView { device: usize, view: u64, image: u64, format: u32 },
+    DestroyImageView { device: usize, view: u64 },
+    CreateShaderModule { device: usize, module: u64, code_size: usize },
+    DestroyShaderModule { device: usize, module: u64 },
+    CreatePipelineCache { device: usize, cache: u64, initial_data_size: usize },
+    DestroyPipelineCache { device: usize, cache: u64 },
+    GetPipelineCacheData { device: usize, cache: u64 },
+    MergePipelineCaches { device: usize, destination: u64, sources: Vec<u64> },
+    CreateGraphicsPipelines { device: usize, cache: u64, pipelines: Vec<u64>, flags: Vec<u32>,
+                              base_pipelines: Vec<(u64, i32)>,
+                              multisample: Vec<(u32, Option<u64>)> },
+    CreateComputePipelines { device: usize, cache: u64, pipelines: Vec<u64>, flags: Vec<u32>,
+                             base_pipelines: Vec<(u64, i32)> },
+    DestroyPipeline { device: usize, pipeline: u64 },
+    CreatePipelineLayout { device: usize, layout: u64, set_layouts: Vec<u64> },
+    DestroyPipelineLayout { device: usize, layout: u64 },
+    CreateSampler { device: usize, sampler: u64 },
+    DestroySampler { device: usize, sampler: u64 },
+    CreateDescriptorSetLayout { device: usize, layout: u64, bindings: Vec<(u32, u32, u32)> },
+    DestroyDescriptorSetLayout { device: usize, layout: u64 },
+    CreateDescriptorPool { device: usize, pool: u64, max_sets: u32, flags: u32 },
+    DestroyDescriptorPool { device: usize, pool: u64 },
+    ResetDescriptorPool { device: usize, pool: u64 },
+    AllocateDescriptorSets { device: usize, pool: u64, layouts: Vec<u64>, sets: Vec<u64> },
+    FreeDescriptorSets { device: usize, pool: u64, sets: Vec<u64> },
+    UpdateDescriptorSets { device: usize, writes: Vec<(u64, u32, u32, u32)>,
+                           copies: Vec<(u64, u32, u32, u64, u32, u32, u32)> },
+    CreateFramebuffer { device: usize, framebuffer: u64, render_pass: u64,
+                        attachments: Vec<u64>, dimensions: [u32; 3] },
+    DestroyFramebuffer { device: usize, framebuffer: u64 },
+    CreateRenderPass { device: usize, render_pass: u64, attachments: u32, subpasses: u32,
+                       dependencies: u32 },
+    DestroyRenderPass { device: usize, render_pass: u64 },
+    GetRenderAreaGranularity { device: usize, render_pass: u64 },
+    CreateCommandPool { device: usize, pool: u64, queue_family: u32, flags: u32 },
+    DestroyCommandPool { device: usize, pool: u64 },
+    ResetCommandPool { device: usize, pool: u64, flags: u32 },
+    TrimCommandPoolKHR { device: usize, pool: u64 },
+    AllocateCommandBuffers { device: usize, pool: u64, secondary: bool,
+                             command_buffers: Vec<usize> },
+    FreeCommandBuffers { device: usize, pool: u64, command_buffers: Vec<usize> },
+    BeginCommandBuffer { command_buffer: usize, flags: u32 },
+    EndCommandBuffer { command_buffer: usize },
+    ResetCommandBuffer { command_buffer: usize, flags: u32 },
+    CmdBindPipeline { command_buffer: usize, bind_point: u32, pipeline: u64 },
+    CmdSetViewport { command_buffer: usize, first_viewport: u32, viewports: u32 },
+    CmdSetScissor { command_buffer: usize, first_scissor: u32, scissors: u32 },
+    CmdSetLineWidth { command_buffer: usize, line_width: f32 },
+    CmdSetDepthBias { command_buffer: usize, constant_factor: f32, clamp: f32,
+                      slope_factor: f32 },
+    CmdSetBlendConstants { command_buffer: usize, constants: [f32; 4] },
+    CmdSetDepthBounds { command_buffer: usize, min: f32, max: f32 },
+    CmdSetStencilCompareMask { command_buffer: usize, face_mask: u32, compare_mask: u32 },
+    CmdSetStencilWriteMask { command_buffer: usize, face_mask: u32, write_mask: u32 },
+    CmdSetStencilReference { command_buffer: usize, face_mask: u32, reference: u32 },
+    CmdBindDescriptorSets { command_buffer: usize, bind_point: u32, layout: u64,
+                            first_set: u32, sets: Vec<u64>, dynamic_offsets: Vec<u32> },
+    CmdBindIndexBuffer { command_buffer: usize, buffer: u64, offset: u64, index_type: u32 },
+    CmdBindVertexBuffers { command_buffer: usize, first_binding: u32,
+                           buffers: Vec<(u64, u64)> },
+    CmdDraw { command_buffer: usize, vertex_count: u32, instance_count: u32, first_vertex: u32,
+              first_instance: u32 },
+    CmdDrawIndexed { command_buffer: usize, index_count: u32, instance_count: u32,
+                     first_index: u32, vertex_offset: i32, first_instance: u32 },
+    CmdDrawIndirect { command_buffer: usize, buffer: u64, offset: u64, draw_count: u32,
+                      stride: u32 },
+    CmdDrawIndexedIndirect { command_buffer: usize, buffer: u64, offset: u64, draw_count: u32,
+                             stride: u32 },
+    CmdDispatch { command_buffer: usize, dimensions: [u32; 3] },
+    CmdDispatchIndirect { command_buffer: usize, buffer: u64, offset: u64 },
+    CmdCopyBuffer { command_buffer: usize, source: u64, destination: u64,
+                    regions: Vec<(u64, u64, u64)> },
+    CmdCopyImage { command_buffer: usize, source: u64, source_layout: u32, destination: u64,
+                   destination_layout: u32,
+                   regions: Vec<(ImageSubresource, ImageSubresource)> },
+    CmdBlitImage { command_buffer: usize, source: u64, source_layout: u32, destination: u64,
+                   destination_layout: u32, regions: Vec<BlitRegion>, filter: u32 },
+    CmdCopyBufferToImage { command_buffer: usize, source: u64, destination: u64,
+                           destination_layout: u32, regions: Vec<(u64, ImageSubresource)> },
+    CmdCopyImageToBuffer { command_buffer: usize, source: u64, source_layout: u32,
+                           destination: u64, regions: Vec<(u64, ImageSubresource)> },
+    CmdUpdateBuffer { command_buffer: usize, destination: u64, offset: u64, data: Vec<u8> },
+    CmdFillBuffer { command_buffer: usize, destination: u64, offset: u64, size: u64,
+                    data: u32 },
+    CmdClearColorImage { command_buffer: usize, image: u64, layout: u32,
+                         ranges: Vec<ImageSubresource> },
+    CmdClearDepthStencilImage { command_buffer: usize, image: u64, layout: u32, depth: f32,
+                                stencil: u32, ranges: Vec<ImageSubresource> },
+    CmdClearAttachments { command_buffer: usize, attachments: Vec<(u32, u32)>,
+                          rects: Vec<([i32; 2], [u32; 2], u32, u32)> },
+    CmdResolveImage { command_buffer: usize, source: u64, source_layout: u32, destination: u64,
+                      destination_layout: u32,
+                      regions: Vec<(ImageSubresource, ImageSubresource)> },
+    CmdSetEvent { command_buffer: usize, event: u64, stages: u32 },
+    CmdResetEvent { command_buffer: usize, event: u64, stages: u32 },
+    CmdWaitEvents { command_buffer: usize, events: Vec<u64>, source_stages: u32,
+                    destination_stages: u32 },
+    CmdPipelineBarrier { command_buffer: usize, source_stages: u32, destination_stages: u32,
+                         by_region: bool, memory_barriers: u32,
+                         buffer_barriers: Vec<BufferBarrier>,
+                         image_barriers: Vec<ImageBarrier> },
+    CmdBeginQuery { command_buffer: usize, query_pool: u64, query: u32, flags: u32 },
+    CmdEndQuery { command_buffer: usize, query_pool: u64, query: u32 },
+    CmdResetQueryPool { command_buffer: usize, query_pool: u64, first_query: u32,
+                        query_count: u32 },
+    CmdWriteTimestamp { command_buffer: usize, stage: u32, query_pool: u64, query: u32 },
+    CmdCopyQueryPoolResults { command_buffer: usize, query_pool: u64, first_query: u32,
+                              query_count: u32, destination: u64, offset: u64, stride: u64,
+                              flags: u32 },
+    CmdPushConstants { command_buffer: usize, layout: u64, stages: u32, offset: u32,
+                       data: Vec<u8> },
+    CmdBeginRenderPass { command_buffer: usize, render_pass: u64, framebuffer: u64,
+                         render_area: ([i32; 2], [u32; 2]), clear_values: u32,
+                         secondary: bool },
+    CmdNextSubpass { command_buffer: usize, secondary: bool },
+    CmdEndRenderPass { command_buffer: usize },
+    CmdExecuteCommands { command_buffer: usize, command_buffers: Vec<usize> },
+    DebugMarkerSetObjectNameEXT { device: usize, object_type: u32, object: u64, name: String },
+    CmdDebugMarkerBeginEXT { command_buffer: usize, name: String, color: [f32; 4] },
+    CmdDebugMarkerEndEXT { command_buffer: usize },
+    CmdDebugMarkerInsertEXT { command_buffer: usize, name: String, color: [f32; 4] },
+}
+
+/// One `VkSubmitInfo` of a `vkQueueSubmit` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmitBatch {
+    /// Semaphores to wait upon, with the corresponding destination stages mask.
+    pub wait_semaphores: Vec<(u64, u32)>,
+    /// Command buffers to execute.
+    pub command_buffers: Vec<usize>,
+    /// Semaphores to signal.
+    pub signal_semaphores: Vec<u64>,
+}
+
+/// A `VkBufferMemoryBarrier` recorded in a `vkCmdPipelineBarrier` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferBarrier {
+    pub buffer: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub source_access: u32,
+    pub destination_access: u32,
+    pub source_queue_family: u32,
+    pub destination_queue_family: u32,
+}
+
+/// A `VkImageMemoryBarrier` recorded in a `vkCmdPipelineBarrier` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageBarrier {
+    pub image: u64,
+    pub old_layout: u32,
+    pub new_layout: u32,
+    pub source_access: u32,
+    pub destination_access: u32,
+    pub source_queue_family: u32,
+    pub destination_queue_family: u32,
+    pub subresource: ImageSubresource,
+}
+
+/// A `VkImageBlit` recorded in a `vkCmdBlitImage` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlitRegion {
+    pub source: ImageSubresource,
+    pub source_offsets: [[i32; 3]; 2],
+    pub destination: ImageSubresource,
+    pub destination_offsets: [[i32; 3]; 2],
+}
+
+/// Part of an image that a command operates on.
+///
+/// For commands that operate on a single mipmap level (copies, blits and resolves),
+/// `num_mipmap_levels` is always 1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ImageSubresource {
+    pub aspect_mask: u32,
+    pub first_mipmap_level: u32,
+    pub num_mipmap_levels: u32,
+    pub first_layer: u32,
+    pub num_layers: u32,
+}
@@ -0,0 +1,3367 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Implementation of the Vulkan entry points of the mock driver.
+//!
+//! Dispatchable handles are pointers to boxed state objects. Non-dispatchable handles are
+//! allocated from a global counter and looked up in the `Objects` of their device.
+
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use std::cmp;
+use std::ffi::CStr;
+use std::io;
+use std::io::Write;
+use std::mem;
+use std::os::raw::c_char;
+use std::os::raw::c_void;
+use std::process;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use features::Features;
+use testing::BlitRegion;
+use testing::BufferBarrier;
+use testing::Call;
+use testing::CallLog;
+use testing::ImageBarrier;
+use testing::ImageSubresource;
+use testing::SubmitBatch;
+use vk;
+
+/// Vendor ID reported by the mock physical device.
+pub const VENDOR_ID: u32 = 0xffff;
+/// Device ID reported by the mock physical device.
+pub const DEVICE_ID: u32 = 0x0001;
+/// Pipeline cache UUID reported by the mock physical device.
+pub const PIPELINE_CACHE_UUID: [u8; 16] = *b"vulkano-mock-drv";
+
+// Size of each of the two memory heaps.
+const HEAP_SIZE: u64 = 1024 * 1024 * 1024;
+
+// Instance extensions supported by the mock. The surfaces can't be created through the mock, but
+// `Surface::from_raw_surface` can wrap any handle.
+const INSTANCE_EXTENSIONS: &[&[u8]] = &[b"VK_KHR_surface", b"VK_EXT_swapchain_colorspace"];
+
+// Device extensions supported by the mock.
+const DEVICE_EXTENSIONS: &[&[u8]] = &[b"VK_KHR_maintenance1", b"VK_EXT_debug_marker"];
+
+// Source of all the non-dispatchable handles.
+static NEXT_HANDLE: AtomicUsize = AtomicUsize::new(1);
+
+#[inline]
+fn new_handle() -> u64 {
+    NEXT_HANDLE.fetch_add(1, Ordering::Relaxed) as u64
+}
+
+struct InstanceState {
+    log: Arc<CallLog>,
+    physical_device: Box<PhysicalDeviceState>,
+}
+
+struct PhysicalDeviceState {
+    log: Arc<CallLog>,
+}
+
+struct DeviceState {
+    log: Arc<CallLog>,
+    queues: Vec<Box<QueueState>>,
+    objects: Mutex<Objects>,
+}
+
+struct QueueState {
+    device: *const DeviceState,
+    family: u32,
+    index: u32,
+}
+
+struct CommandBufferState {
+    device: *const DeviceState,
+    ops: Mutex<Vec<Op>>,
+}
+
+// All the non-dispatchable objects of a device.
+#[derive(Default)]
+struct Objects {
+    memory: FnvHashMap<u64, MemoryState>,
+    buffers: FnvHashMap<u64, BufferState>,
+    fences: FnvHashMap<u64, bool>,
+    events: FnvHashMap<u64, bool>,
+    query_pools: FnvHashMap<u64, QueryPoolState>,
+    descriptor_pools: FnvHashMap<u64, DescriptorPoolState>,
+    pipeline_caches: FnvHashMap<u64, Vec<u8>>,
+    command_pools: FnvHashMap<u64, Vec<vk::CommandBuffer>>,
+    // Objects that don't have any state.
+    others: FnvHashSet<u64>,
+    // Amount of memory allocated in each heap.
+    heap_usage: [u64; 2],
+    // Value written by the next timestamp query.
+    timestamp: u64,
+}
+
+struct MemoryState {
+    heap: usize,
+    data: Vec<u8>,
+}
+
+struct BufferState {
+    size: u64,
+    binding: Option<(u64, u64)>,
+}
+
+struct QueryPoolState {
+    values_per_query: usize,
+    results: Vec<Option<u64>>,
+}
+
+struct DescriptorPoolState {
+    max_sets: u32,
+    sets: FnvHashSet<u64>,
+}
+
+// Operation that is performed when a command buffer is submitted.
+enum Op {
+    CopyBuffer(u64, u64, Vec<(u64, u64, u64)>),
+    FillBuffer(u64, u64, u64, u32),
+    UpdateBuffer(u64, u64, Vec<u8>),
+    SetEvent(u64, bool),
+    ResetQueries(u64, u32, u32),
+    EndQuery(u64, u32),
+    WriteTimestamp(u64, u32),
+    ExecuteCommands(Vec<vk::CommandBuffer>),
+}
+
+impl Objects {
+    fn remove(&mut self, handle: u64) {
+        if let Some(memory) = self.memory.remove(&handle) {
+            self.heap_usage[memory.heap] -= memory.data.len() as u64;
+        }
+        self.buffers.remove(&handle);
+        self.fences.remove(&handle);
+        self.events.remove(&handle);
+        self.query_pools.remove(&handle);
+        self.descriptor_pools.remove(&handle);
+        self.pipeline_caches.remove(&handle);
+        self.others.remove(&handle);
+    }
+
+    // Returns the memory bound to a buffer, and the offset of the buffer within that memory.
+    fn buffer_memory(&mut self, buffer: u64) -> (&mut [u8], usize) {
+        let (memory, offset) = object(&self.buffers, buffer, "buffer")
+            .binding
+            .unwrap_or_else(|| fail("buffer used without being bound to memory"));
+        (&mut object_mut(&mut self.memory, memory, "memory").data[..], offset as usize)
+    }
+
+    unsafe fn execute(&mut self, command_buffer: vk::CommandBuffer) {
+        let command_buffer = cb_state(command_buffer);
+        for op in command_buffer.ops.lock().unwrap().iter() {
+            match *op {
+                Op::CopyBuffer(src, dst, ref regions) => {
+                    for &(src_offset, dst_offset, size) in regions {
+                        let data = {
+                            let (memory, offset) = self.buffer_memory(src);
+                            let start = offset + src_offset as usize;
+                            memory[start .. start + size as usize].to_owned()
+                        };
+                        let (memory, offset) = self.buffer_memory(dst);
+                        let start = offset + dst_offset as usize;
+                        memory[start .. start + size as usize].copy_from_slice(&data);
+                    }
+                },
+                Op::FillBuffer(dst, dst_offset, size, data) => {
+                    let size = if size == vk::WHOLE_SIZE {
+                        (object(&self.buffers, dst, "buffer").size - dst_offset) & !3
+                    } else {
+                        size
+                    };
+                    let (memory, offset) = self.buffer_memory(dst);
+                    let start = offset + dst_offset as usize;
+                    for chunk in memory[start .. start + size as usize].chunks_mut(4) {
+                        write_u32(chunk, data);
+                    }
+                },
+                Op::UpdateBuffer(dst, dst_offset, ref data) => {
+                    let (memory, offset) = self.buffer_memory(dst);
+                    let start = offset + dst_offset as usize;
+                    memory[start .. start + data.len()].copy_from_slice(data);
+                },
+                Op::SetEvent(event, value) => {
+                    self.events.insert(event, value);
+                },
+                Op::ResetQueries(pool, first, count) => {
+                    let pool = object_mut(&mut self.query_pools, pool, "query pool");
+                    for result in &mut pool.results[first as usize .. (first + count) as usize] {
+                        *result = None;
+                    }
+                },
+                Op::EndQuery(pool, query) => {
+                    object_mut(&mut self.query_pools, pool, "query pool").results[query as usize] =
+                        Some(0);
+                },
+                Op::WriteTimestamp(pool, query) => {
+                    self.timestamp += 1000;
+                    let value = self.timestamp;
+                    object_mut(&mut self.query_pools, pool, "query pool").results[query as usize] =
+                        Some(value);
+                },
+                Op::ExecuteCommands(ref command_buffers) => {
+                    for &cb in command_buffers {
+                        self.execute(cb);
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[inline]
+unsafe fn instance_state<'a>(instance: vk::Instance) -> &'a InstanceState {
+    &*(instance as *const InstanceState)
+}
+
+#[inline]
+unsafe fn physical_device_state<'a>(physical_device: vk::PhysicalDevice)
+                                    -> &'a PhysicalDeviceState {
+    &*(physical_device as *const PhysicalDeviceState)
+}
+
+#[inline]
+unsafe fn device_state<'a>(device: vk::Device) -> &'a DeviceState {
+    &*(device as *const DeviceState)
+}
+
+#[inline]
+unsafe fn queue_state<'a>(queue: vk::Queue) -> &'a QueueState {
+    &*(queue as *const QueueState)
+}
+
+#[inline]
+unsafe fn cb_state<'a>(command_buffer: vk::CommandBuffer) -> &'a CommandBufferState {
+    &*(command_buffer as *const CommandBufferState)
+}
+
+// Records a call made with a command buffer.
+#[inline]
+unsafe fn cb_log(command_buffer: vk::CommandBuffer, call: Call) {
+    (*cb_state(command_buffer).device).log.push(call);
+}
+
+#[inline]
+unsafe fn cb_op(command_buffer: vk::CommandBuffer, op: Op) {
+    cb_state(command_buffer).ops.lock().unwrap().push(op);
+}
+
+#[inline]
+unsafe fn array<'a, T>(ptr: *const T, len: u32) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr, len as usize)
+    }
+}
+
+#[inline]
+unsafe fn strings(ptr: *const *const c_char, len: u32) -> Vec<String> {
+    array(ptr, len)
+        .iter()
+        .map(|&s| CStr::from_ptr(s).to_string_lossy().into_owned())
+        .collect()
+}
+
+// Implements the "call twice" idiom of the functions that enumerate things.
+unsafe fn write_array<T>(values: Vec<T>, count: *mut u32, out: *mut T) -> vk::Result {
+    if out.is_null() {
+        *count = values.len() as u32;
+        return vk::SUCCESS;
+    }
+
+    let total = values.len();
+    let written = cmp::min(*count as usize, total);
+    for (n, value) in values.into_iter().take(written).enumerate() {
+        ptr::write(out.offset(n as isize), value);
+    }
+    *count = written as u32;
+    if written < total {
+        vk::INCOMPLETE
+    } else {
+        vk::SUCCESS
+    }
+}
+
+// Returns the properties of the extensions of the given names.
+unsafe fn extension_properties(names: &[&[u8]]) -> Vec<vk::ExtensionProperties> {
+    names
+        .iter()
+        .map(|name| {
+                 let mut properties: vk::ExtensionProperties = mem::zeroed();
+                 for (dest, &byte) in properties.extensionName.iter_mut().zip(name.iter()) {
+                     *dest = byte as c_char;
+                 }
+                 properties.specVersion = 1;
+                 properties
+             })
+        .collect()
+}
+
+#[inline]
+fn subresource(layers: &vk::ImageSubresourceLayers) -> ImageSubresource {
+    ImageSubresource {
+        aspect_mask: layers.aspectMask,
+        first_mipmap_level: layers.mipLevel,
+        num_mipmap_levels: 1,
+        first_layer: layers.baseArrayLayer,
+        num_layers: layers.layerCount,
+    }
+}
+
+#[inline]
+fn offsets(offsets: &[vk::Offset3D; 2]) -> [[i32; 3]; 2] {
+    [
+        [offsets[0].x, offsets[0].y, offsets[0].z],
+        [offsets[1].x, offsets[1].y, offsets[1].z],
+    ]
+}
+
+#[inline]
+fn subresource_range(range: &vk::ImageSubresourceRange) -> ImageSubresource {
+    ImageSubresource {
+        aspect_mask: range.aspectMask,
+        first_mipmap_level: range.baseMipLevel,
+        num_mipmap_levels: range.levelCount,
+        first_layer: range.baseArrayLayer,
+        num_layers: range.layerCount,
+    }
+}
+
+/// Returns the log of an instance created by the mock `vkCreateInstance`.
+pub unsafe fn instance_log<'a>(instance: vk::Instance) -> &'a Arc<CallLog> {
+    &instance_state(instance).log
+}
+
+/// Returns the mock implementation of the Vulkan function of the given name.
+///
+/// Unknown names return a function that aborts the process when it is called.
+pub fn get_proc_addr(name: *const c_char) -> extern "system" fn() -> () {
+    macro_rules! entry_points {
+        ($($name:ident => $f:ident,)+) => (
+            match unsafe { CStr::from_ptr(name) }.to_bytes() {
+                $(
+                    n if n == concat!("vk", stringify!($name)).as_bytes() => {
+                        Some($f as *const ())
+                    },
+                )+
+                _ => None,
+            }
+        )
+    }
+
+    let f = entry_points! {
+        GetInstanceProcAddr => get_instance_proc_addr,
+        GetDeviceProcAddr => get_device_proc_addr,
+        CreateInstance => create_instance,
+        EnumerateInstanceExtensionProperties => enumerate_instance_extension_properties,
+        EnumerateInstanceLayerProperties => enumerate_instance_layer_properties,
+        DestroyInstance => destroy_instance,
+        EnumeratePhysicalDevices => enumerate_physical_devices,
+        EnumerateDeviceExtensionProperties => enumerate_device_extension_properties,
+        EnumerateDeviceLayerProperties => enumerate_device_layer_properties,
+        GetPhysicalDeviceFeatures => get_physical_device_features,
+        GetPhysicalDeviceFormatProperties => get_physical_device_format_properties,
+        GetPhysicalDeviceImageFormatProperties => get_physical_device_image_format_properties,
+        GetPhysicalDeviceProperties => get_physical_device_properties,
+        GetPhysicalDeviceQueueFamilyProperties => get_physical_device_queue_family_properties,
+        GetPhysicalDeviceMemoryProperties => get_physical_device_memory_properties,
+        DestroySurfaceKHR => destroy_surface,
+        GetPhysicalDeviceSurfaceSupportKHR => get_physical_device_surface_support,
+        GetPhysicalDeviceSurfaceCapabilitiesKHR => get_physical_device_surface_capabilities,
+        GetPhysicalDeviceSurfaceFormatsKHR => get_physical_device_surface_formats,
+        GetPhysicalDeviceSurfacePresentModesKHR => get_physical_device_surface_present_modes,
+        GetPhysicalDeviceSparseImageFormatProperties =>
+            get_physical_device_sparse_image_format_properties,
+        CreateDevice => create_device,
+        DestroyDevice => destroy_device,
+        GetDeviceQueue => get_device_queue,
+        QueueSubmit => queue_submit,
+        QueueWaitIdle => queue_wait_idle,
+        QueueBindSparse => queue_bind_sparse,
+        DeviceWaitIdle => device_wait_idle,
+        AllocateMemory => allocate_memory,
+        FreeMemory => free_memory,
+        MapMemory => map_memory,
+        UnmapMemory => unmap_memory,
+        FlushMappedMemoryRanges => flush_mapped_memory_ranges,
+        InvalidateMappedMemoryRanges => invalidate_mapped_memory_ranges,
+        GetDeviceMemoryCommitment => get_device_memory_commitment,
+        BindBufferMemory => bind_buffer_memory,
+        BindImageMemory => bind_image_memory,
+        GetBufferMemoryRequirements => get_buffer_memory_requirements,
+        GetImageMemoryRequirements => get_image_memory_requirements,
+        GetImageSparseMemoryRequirements => get_image_sparse_memory_requirements,
+        CreateFence => create_fence,
+        DestroyFence => destroy_fence,
+        ResetFences => reset_fences,
+        GetFenceStatus => get_fence_status,
+        WaitForFences => wait_for_fences,
+        CreateSemaphore => create_semaphore,
+        DestroySemaphore => destroy_semaphore,
+        CreateEvent => create_event,
+        DestroyEvent => destroy_event,
+        GetEventStatus => get_event_status,
+        SetEvent => set_event,
+        ResetEvent => reset_event,
+        CreateQueryPool => create_query_pool,
+        DestroyQueryPool => destroy_query_pool,
+        GetQueryPoolResults => get_query_pool_results,
+        CreateBuffer => create_buffer,
+        DestroyBuffer => destroy_buffer,
+        CreateBufferView => create_buffer_view,
+        DestroyBufferView => destroy_buffer_view,
+        CreateImage => create_image,
+        DestroyImage => destroy_image,
+        GetImageSubresourceLayout => get_image_subresource_layout,
+        CreateImageView => create_image_view,
+        DestroyImageView => destroy_image_view,
+        CreateShaderModule => create_shader_module,
+        DestroyShaderModule => destroy_shader_module,
+        CreatePipelineCache => create_pipeline_cache,
+        DestroyPipelineCache => destroy_pipeline_cache,
+        GetPipelineCacheData => get_pipeline_cache_data,
+        MergePipelineCaches => merge_pipeline_caches,
+        CreateGraphicsPipelines => create_graphics_pipelines,
+        CreateComputePipelines => create_compute_pipelines,
+        DestroyPipeline => destroy_pipeline,
+        CreatePipelineLayout => create_pipeline_layout,
+        DestroyPipelineLayout => destroy_pipeline_layout,
+        CreateSampler => create_sampler,
+        DestroySampler => destroy_sampler,
+        CreateDescriptorSetLayout => create_descriptor_set_layout,
+        DestroyDescriptorSetLayout => destroy_descriptor_set_layout,
+        CreateDescriptorPool => create_descriptor_pool,
+        DestroyDescriptorPool => destroy_descriptor_pool,
+        ResetDescriptorPool => reset_descriptor_pool,
+        AllocateDescriptorSets => allocate_descriptor_sets,
+        FreeDescriptorSets => free_descriptor_sets,
+        UpdateDescriptorSets => update_descriptor_sets,
+        CreateFramebuffer => create_framebuffer,
+        DestroyFramebuffer => destroy_framebuffer,
+        CreateRenderPass => create_render_pass,
+        DestroyRenderPass => destroy_render_pass,
+        GetRenderAreaGranularity => get_render_area_granularity,
+        CreateCommandPool => create_command_pool,
+        DestroyCommandPool => destroy_command_pool,
+        ResetCommandPool => reset_command_pool,
+        TrimCommandPoolKHR => trim_command_pool,
+        AllocateCommandBuffers => allocate_command_buffers,
+        FreeCommandBuffers => free_command_buffers,
+        BeginCommandBuffer => begin_command_buffer,
+        EndCommandBuffer => end_command_buffer,
+        ResetCommandBuffer => reset_command_buffer,
+        CmdBindPipeline => cmd_bind_pipeline,
+        CmdSetViewport => cmd_set_viewport,
+        CmdSetScissor => cmd_set_scissor,
+        CmdSetLineWidth => cmd_set_line_width,
+        CmdSetDepthBias => cmd_set_depth_bias,
+        CmdSetBlendConstants => cmd_set_blend_constants,
+        CmdSetDepthBounds => cmd_set_depth_bounds,
+        CmdSetStencilCompareMask => cmd_set_stencil_compare_mask,
+        CmdSetStencilWriteMask => cmd_set_stencil_write_mask,
+        CmdSetStencilReference => cmd_set_stencil_reference,
+        CmdBindDescriptorSets => cmd_bind_descriptor_sets,
+        CmdBindIndexBuffer => cmd_bind_index_buffer,
+        CmdBindVertexBuffers => cmd_bind_vertex_buffers,
+        CmdDraw => cmd_draw,
+        CmdDrawIndexed => cmd_draw_indexed,
+        CmdDrawIndirect => cmd_draw_indirect,
+        CmdDrawIndexedIndirect => cmd_draw_indexed_indirect,
+        CmdDispatch => cmd_dispatch,
+        CmdDispatchIndirect => cmd_dispatch_indirect,
+        CmdCopyBuffer => cmd_copy_buffer,
+        CmdCopyImage => cmd_copy_image,
+        CmdBlitImage => cmd_blit_image,
+        CmdCopyBufferToImage => cmd_copy_buffer_to_image,
+        CmdCopyImageToBuffer => cmd_copy_image_to_buffer,
+        CmdUpdateBuffer => cmd_update_buffer,
+        CmdFillBuffer => cmd_fill_buffer,
+        CmdClearColorImage => cmd_clear_color_image,
+        CmdClearDepthStencilImage => cmd_clear_depth_stencil_image,
+        CmdClearAttachments => cmd_clear_attachments,
+        CmdResolveImage => cmd_resolve_image,
+        CmdSetEvent => cmd_set_event,
+        CmdResetEvent => cmd_reset_event,
+        CmdWaitEvents => cmd_wait_events,
+        CmdPipelineBarrier => cmd_pipeline_barrier,
+        CmdBeginQuery => cmd_begin_query,
+        CmdEndQuery => cmd_end_query,
+        CmdResetQueryPool => cmd_reset_query_pool,
+        CmdWriteTimestamp => cmd_write_timestamp,
+        CmdCopyQueryPoolResults => cmd_copy_query_pool_results,
+        CmdPushConstants => cmd_push_constants,
+        CmdBeginRenderPass => cmd_begin_render_pass,
+        CmdNextSubpass => cmd_next_subpass,
+        CmdEndRenderPass => cmd_end_render_pass,
+        CmdExecuteCommands => cmd_execute_commands,
+        DebugMarkerSetObjectNameEXT => debug_marker_set_object_name,
+        CmdDebugMarkerBeginEXT => cmd_debug_marker_begin,
+        CmdDebugMarkerEndEXT => cmd_debug_marker_end,
+        CmdDebugMarkerInsertEXT => cmd_debug_marker_insert,
+    };
+
+    match f {
+        // Rust doesn't allow casting a data pointer to a function pointer with `as`, so the
+        // erased pointer has to be converted back explicitly.
+        Some(f) => unsafe { mem::transmute::<*const (), vk::PFN_vkVoidFunction>(f) },
+        None => {
+            let name = unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned();
+            let mut names = UNIMPLEMENTED_NAMES.lock().unwrap();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+            unimplemented_entry_point as vk::PFN_vkVoidFunction
+        },
+    }
+}
+
+lazy_static! {
+    // Names of the functions for which `get_proc_addr` returned `unimplemented_entry_point`.
+    static ref UNIMPLEMENTED_NAMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+}
+
+// The same function is returned for all the unimplemented names, so it can't know which one was
+// called and prints all of them.
+extern "system" fn unimplemented_entry_point() {
+    let names = match UNIMPLEMENTED_NAMES.lock() {
+        Ok(names) => names.join(", "),
+        Err(_) => String::new(),
+    };
+    fail(&format!("a Vulkan function that isn't implemented was called; it is one of: {}",
+                  names));
+}
+
+// Reports a misuse of the mock driver and aborts the process. Unwinding out of an
+// `extern "system"` function is undefined behavior, so the entry points never panic.
+fn fail(message: &str) -> ! {
+    let _ = writeln!(io::stderr(), "mock driver: {}", message);
+    process::abort();
+}
+
+// Aborts the process if it is dropped while the thread is panicking. Every entry point holds one,
+// so that a panic that wasn't foreseen, such as a failed bounds check, doesn't unwind out of it.
+// The panic hook has already printed the message of the panic at that point.
+struct AbortOnUnwind;
+
+impl Drop for AbortOnUnwind {
+    #[inline]
+    fn drop(&mut self) {
+        if thread::panicking() {
+            process::abort();
+        }
+    }
+}
+
+// Returns the state of the object `handle`, or aborts if the mock doesn't know it.
+#[inline]
+fn object<'a, T>(objects: &'a FnvHashMap<u64, T>, handle: u64, kind: &str) -> &'a T {
+    match objects.get(&handle) {
+        Some(object) => object,
+        None => fail(&format!("unknown {} {:#x}", kind, handle)),
+    }
+}
+
+// Returns the state of the object `handle`, or aborts if the mock doesn't know it.
+#[inline]
+fn object_mut<'a, T>(objects: &'a mut FnvHashMap<u64, T>, handle: u64, kind: &str)
+                     -> &'a mut T {
+    match objects.get_mut(&handle) {
+        Some(object) => object,
+        None => fail(&format!("unknown {} {:#x}", kind, handle)),
+    }
+}
+
+// Writes `value` at the start of `dest` in the byte order of the host, like the device would.
+fn write_u32(dest: &mut [u8], value: u32) {
+    assert!(dest.len() >= 4);
+    unsafe { ptr::write_unaligned(dest.as_mut_ptr() as *mut u32, value) }
+}
+
+// Writes `value` at the start of `dest` in the byte order of the host, like the device would.
+fn write_u64(dest: &mut [u8], value: u64) {
+    assert!(dest.len() >= 8);
+    unsafe { ptr::write_unaligned(dest.as_mut_ptr() as *mut u64, value) }
+}
+
+extern "system" fn get_instance_proc_addr(_: vk::Instance, name: *const c_char)
+                                          -> vk::PFN_vkVoidFunction {
+    let _abort = AbortOnUnwind;
+    get_proc_addr(name)
+}
+
+extern "system" fn get_device_proc_addr(_: vk::Device, name: *const c_char)
+                                        -> vk::PFN_vkVoidFunction {
+    let _abort = AbortOnUnwind;
+    get_proc_addr(name)
+}
+
+extern "system" fn create_instance(infos: *const vk::InstanceCreateInfo,
+                                   _: *const vk::AllocationCallbacks, out: *mut vk::Instance)
+                                   -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let infos = &*infos;
+        let extensions = strings(infos.ppEnabledExtensionNames, infos.enabledExtensionCount);
+        let layers = strings(infos.ppEnabledLayerNames, infos.enabledLayerCount);
+        if extensions
+            .iter()
+            .any(|ext| !INSTANCE_EXTENSIONS.contains(&ext.as_bytes()))
+        {
+            return vk::ERROR_EXTENSION_NOT_PRESENT;
+        }
+        if !layers.is_empty() {
+            return vk::ERROR_LAYER_NOT_PRESENT;
+        }
+
+        let log = CallLog::new();
+        let state = Box::new(InstanceState {
+                                 log: log.clone(),
+                                 physical_device: Box::new(PhysicalDeviceState {
+                                                               log: log.clone(),
+                                                           }),
+                             });
+        let instance = Box::into_raw(state) as vk::Instance;
+        log.push(Call::CreateInstance {
+                     instance,
+                     extensions,
+                     layers,
+                 });
+        *out = instance;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn enumerate_instance_extension_properties(
+    _: *const c_char, count: *mut u32, out: *mut vk::ExtensionProperties)
+    -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe { write_array(extension_properties(INSTANCE_EXTENSIONS), count, out) }
+}
+
+extern "system" fn enumerate_instance_layer_properties(count: *mut u32,
+                                                       out: *mut vk::LayerProperties)
+                                                       -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe { write_array(Vec::new(), count, out) }
+}
+
+extern "system" fn destroy_instance(instance: vk::Instance, _: *const vk::AllocationCallbacks) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = Box::from_raw(instance as *mut InstanceState);
+        state.log.push(Call::DestroyInstance { instance });
+    }
+}
+
+extern "system" fn enumerate_physical_devices(instance: vk::Instance, count: *mut u32,
+                                              out: *mut vk::PhysicalDevice)
+                                              -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = instance_state(instance);
+        state.log.push(Call::EnumeratePhysicalDevices { instance });
+        let physical_device = &*state.physical_device as *const PhysicalDeviceState as usize;
+        write_array(vec![physical_device], count, out)
+    }
+}
+
+extern "system" fn enumerate_device_extension_properties(
+    physical_device: vk::PhysicalDevice, _: *const c_char, count: *mut u32,
+    out: *mut vk::ExtensionProperties)
+    -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::EnumerateDeviceExtensionProperties { physical_device });
+        write_array(extension_properties(DEVICE_EXTENSIONS), count, out)
+    }
+}
+
+extern "system" fn destroy_surface(instance: vk::Instance, surface: vk::SurfaceKHR,
+                                   _: *const vk::AllocationCallbacks) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        instance_state(instance)
+            .log
+            .push(Call::DestroySurfaceKHR { instance, surface });
+    }
+}
+
+// Only the first queue family can present.
+extern "system" fn get_physical_device_surface_support(physical_device: vk::PhysicalDevice,
+                                                       queue_family: u32,
+                                                       surface: vk::SurfaceKHR,
+                                                       out: *mut vk::Bool32)
+                                                       -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceSurfaceSupportKHR {
+                      physical_device,
+                      queue_family,
+                      surface,
+                  });
+        *out = if queue_family == 0 { vk::TRUE } else { vk::FALSE };
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_physical_device_surface_capabilities(
+    physical_device: vk::PhysicalDevice, surface: vk::SurfaceKHR,
+    out: *mut vk::SurfaceCapabilitiesKHR)
+    -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceSurfaceCapabilitiesKHR {
+                      physical_device,
+                      surface,
+                  });
+        *out = vk::SurfaceCapabilitiesKHR {
+            minImageCount: 2,
+            maxImageCount: 8,
+            // The size of the swapchain determines the size of the surface.
+            currentExtent: vk::Extent2D {
+                width: 0xffffffff,
+                height: 0xffffffff,
+            },
+            minImageExtent: vk::Extent2D {
+                width: 1,
+                height: 1,
+            },
+            maxImageExtent: vk::Extent2D {
+                width: 4096,
+                height: 4096,
+            },
+            maxImageArrayLayers: 1,
+            supportedTransforms: vk::SURFACE_TRANSFORM_IDENTITY_BIT_KHR,
+            currentTransform: vk::SURFACE_TRANSFORM_IDENTITY_BIT_KHR,
+            supportedCompositeAlpha: vk::COMPOSITE_ALPHA_OPAQUE_BIT_KHR,
+            supportedUsageFlags: vk::IMAGE_USAGE_TRANSFER_DST_BIT | vk::IMAGE_USAGE_SAMPLED_BIT |
+                vk::IMAGE_USAGE_COLOR_ATTACHMENT_BIT,
+        };
+        vk::SUCCESS
+    }
+}
+
+// Only the sRGB non-linear color space is supported.
+extern "system" fn get_physical_device_surface_formats(physical_device: vk::PhysicalDevice,
+                                                       surface: vk::SurfaceKHR,
+                                                       count: *mut u32,
+                                                       out: *mut vk::SurfaceFormatKHR)
+                                                       -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceSurfaceFormatsKHR {
+                      physical_device,
+                      surface,
+                  });
+        let formats = [vk::FORMAT_B8G8R8A8_UNORM, vk::FORMAT_B8G8R8A8_SRGB]
+            .iter()
+            .map(|&format| {
+                     vk::SurfaceFormatKHR {
+                         format: format,
+                         colorSpace: vk::COLOR_SPACE_SRGB_NONLINEAR_KHR,
+                     }
+                 })
+            .collect();
+        write_array(formats, count, out)
+    }
+}
+
+extern "system" fn get_physical_device_surface_present_modes(physical_device: vk::PhysicalDevice,
+                                                             surface: vk::SurfaceKHR,
+                                                             count: *mut u32,
+                                                             out: *mut vk::PresentModeKHR)
+                                                             -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceSurfacePresentModesKHR {
+                      physical_device,
+                      surface,
+                  });
+        write_array(vec![vk::PRESENT_MODE_FIFO_KHR, vk::PRESENT_MODE_MAILBOX_KHR], count, out)
+    }
+}
+
+extern "system" fn enumerate_device_layer_properties(physical_device: vk::PhysicalDevice,
+                                                     count: *mut u32,
+                                                     out: *mut vk::LayerProperties)
+                                                     -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::EnumerateDeviceLayerProperties { physical_device });
+        write_array(Vec::new(), count, out)
+    }
+}
+
+extern "system" fn get_physical_device_features(physical_device: vk::PhysicalDevice,
+                                                out: *mut vk::PhysicalDeviceFeatures) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceFeatures { physical_device });
+
+        let features = Features {
+            texture_compression_etc2: false,
+            texture_compression_astc_ldr: false,
+            shader_resource_residency: false,
+            sparse_residency_buffer: false,
+            sparse_residency_image2d: false,
+            sparse_residency_image3d: false,
+            sparse_residency2_samples: false,
+            sparse_residency4_samples: false,
+            sparse_residency8_samples: false,
+            sparse_residency16_samples: false,
+            sparse_residency_aliased: false,
+            ..Features::all()
+        };
+        *out = features.into_vulkan_features();
+    }
+}
+
+// Every format supports everything, except for the 64 bits formats and the compressed formats.
+fn format_supported(format: vk::Format) -> bool {
+    if format == vk::FORMAT_UNDEFINED {
+        return false;
+    }
+    if format >= vk::FORMAT_R64_UINT && format <= vk::FORMAT_R64G64B64A64_SFLOAT {
+        return false;
+    }
+    if format >= vk::FORMAT_BC1_RGB_UNORM_BLOCK && format <= vk::FORMAT_ASTC_12x12_SRGB_BLOCK {
+        return false;
+    }
+    true
+}
+
+extern "system" fn get_physical_device_format_properties(physical_device: vk::PhysicalDevice,
+                                                         format: vk::Format,
+                                                         out: *mut vk::FormatProperties) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceFormatProperties { physical_device, format });
+
+        let features = if !format_supported(format) {
+            0
+        } else {
+            0x1fff | vk::FORMAT_FEATURE_TRANSFER_SRC_BIT_KHR |
+                vk::FORMAT_FEATURE_TRANSFER_DST_BIT_KHR
+        };
+
+        *out = vk::FormatProperties {
+            linearTilingFeatures: features,
+            optimalTilingFeatures: features,
+            bufferFeatures: features,
+        };
+    }
+}
+
+extern "system" fn get_physical_device_image_format_properties(
+    physical_device: vk::PhysicalDevice, format: vk::Format, _: vk::ImageType,
+    _: vk::ImageTiling, _: vk::ImageUsageFlags, _: vk::ImageCreateFlags,
+    out: *mut vk::ImageFormatProperties)
+    -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceImageFormatProperties { physical_device, format });
+
+        if !format_supported(format) {
+            return vk::ERROR_FORMAT_NOT_SUPPORTED;
+        }
+
+        *out = vk::ImageFormatProperties {
+            maxExtent: vk::Extent3D {
+                width: 16384,
+                height: 16384,
+                depth: 2048,
+            },
+            maxMipLevels: 15,
+            maxArrayLayers: 2048,
+            sampleCounts: 0x7f,
+            maxResourceSize: HEAP_SIZE,
+        };
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_physical_device_properties(physical_device: vk::PhysicalDevice,
+                                                  out: *mut vk::PhysicalDeviceProperties) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceProperties { physical_device });
+
+        let mut properties: vk::PhysicalDeviceProperties = mem::zeroed();
+        properties.apiVersion = 1 << 22;
+        properties.driverVersion = 1;
+        properties.vendorID = VENDOR_ID;
+        properties.deviceID = DEVICE_ID;
+        properties.deviceType = vk::PHYSICAL_DEVICE_TYPE_CPU;
+        for (dest, &byte) in properties
+            .deviceName
+            .iter_mut()
+            .zip(b"vulkano mock device".iter())
+        {
+            *dest = byte as c_char;
+        }
+        properties.pipelineCacheUUID = PIPELINE_CACHE_UUID;
+
+        // Limits are mostly the minimum values required by the specifications.
+        {
+            let limits = &mut properties.limits;
+            limits.maxImageDimension1D = 16384;
+            limits.maxImageDimension2D = 16384;
+            limits.maxImageDimension3D = 2048;
+            limits.maxImageDimensionCube = 16384;
+            limits.maxImageArrayLayers = 2048;
+            limits.maxTexelBufferElements = 65536;
+            limits.maxUniformBufferRange = 16384;
+            limits.maxStorageBufferRange = 1 << 27;
+            limits.maxPushConstantsSize = 128;
+            limits.maxMemoryAllocationCount = 4096;
+            limits.maxSamplerAllocationCount = 4000;
+            limits.bufferImageGranularity = 1024;
+            limits.sparseAddressSpaceSize = 1 << 31;
+            limits.maxBoundDescriptorSets = 4;
+            limits.maxPerStageDescriptorSamplers = 16;
+            limits.maxPerStageDescriptorUniformBuffers = 12;
+            limits.maxPerStageDescriptorStorageBuffers = 4;
+            limits.maxPerStageDescriptorSampledImages = 16;
+            limits.maxPerStageDescriptorStorageImages = 4;
+            limits.maxPerStageDescriptorInputAttachments = 4;
+            limits.maxPerStageResources = 128;
+            limits.maxDescriptorSetSamplers = 96;
+            limits.maxDescriptorSetUniformBuffers = 72;
+            limits.maxDescriptorSetUniformBuffersDynamic = 8;
+            limits.maxDescriptorSetStorageBuffers = 24;
+            limits.maxDescriptorSetStorageBuffersDynamic = 4;
+            limits.maxDescriptorSetSampledImages = 96;
+            limits.maxDescriptorSetStorageImages = 24;
+            limits.maxDescriptorSetInputAttachments = 4;
+            limits.maxVertexInputAttributes = 16;
+            limits.maxVertexInputBindings = 16;
+            limits.maxVertexInputAttributeOffset = 2047;
+            limits.maxVertexInputBindingStride = 2048;
+            limits.maxVertexOutputComponents = 64;
+            limits.maxTessellationGenerationLevel = 64;
+            limits.maxTessellationPatchSize = 32;
+            limits.maxTessellationControlPerVertexInputComponents = 64;
+            limits.maxTessellationControlPerVertexOutputComponents = 64;
+            limits.maxTessellationControlPerPatchOutputComponents = 120;
+            limits.maxTessellationControlTotalOutputComponents = 2048;
+            limits.maxTessellationEvaluationInputComponents = 64;
+            limits.maxTessellationEvaluationOutputComponents = 64;
+            limits.maxGeometryShaderInvocations = 32;
+            limits.maxGeometryInputComponents = 64;
+            limits.maxGeometryOutputComponents = 64;
+            limits.maxGeometryOutputVertices = 256;
+            limits.maxGeometryTotalOutputComponents = 1024;
+            limits.maxFragmentInputComponents = 64;
+            limits.maxFragmentOutputAttachments = 4;
+            limits.maxFragmentDualSrcAttachments = 1;
+            limits.maxFragmentCombinedOutputResources = 4;
+            limits.maxComputeSharedMemorySize = 16384;
+            limits.maxComputeWorkGroupCount = [65535, 65535, 65535];
+            limits.maxComputeWorkGroupInvocations = 128;
+            limits.maxComputeWorkGroupSize = [128, 128, 64];
+            limits.subPixelPrecisionBits = 4;
+            limits.subTexelPrecisionBits = 4;
+            limits.mipmapPrecisionBits = 4;
+            limits.maxDrawIndexedIndexValue = 0xffffffff;
+            limits.maxDrawIndirectCount = 0xffff;
+            limits.maxSamplerLodBias = 2.0;
+            limits.maxSamplerAnisotropy = 16.0;
+            limits.maxViewports = 16;
+            limits.maxViewportDimensions = [4096, 4096];
+            limits.viewportBoundsRange = [-8192.0, 8191.0];
+            limits.minMemoryMapAlignment = 64;
+            limits.minTexelBufferOffsetAlignment = 256;
+            limits.minUniformBufferOffsetAlignment = 256;
+            limits.minStorageBufferOffsetAlignment = 256;
+            limits.minTexelOffset = -8;
+            limits.maxTexelOffset = 7;
+            limits.minTexelGatherOffset = -8;
+            limits.maxTexelGatherOffset = 7;
+            limits.minInterpolationOffset = -0.5;
+            limits.maxInterpolationOffset = 0.4375;
+            limits.subPixelInterpolationOffsetBits = 4;
+            limits.maxFramebufferWidth = 4096;
+            limits.maxFramebufferHeight = 4096;
+            limits.maxFramebufferLayers = 256;
+            limits.framebufferColorSampleCounts = 0xf;
+            limits.framebufferDepthSampleCounts = 0xf;
+            limits.framebufferStencilSampleCounts = 0xf;
+            limits.framebufferNoAttachmentsSampleCounts = 0xf;
+            limits.maxColorAttachments = 4;
+            limits.sampledImageColorSampleCounts = 0xf;
+            limits.sampledImageIntegerSampleCounts = 0x1;
+            limits.sampledImageDepthSampleCounts = 0xf;
+            limits.sampledImageStencilSampleCounts = 0xf;
+            limits.storageImageSampleCounts = 0x1;
+            limits.maxSampleMaskWords = 1;
+            limits.timestampComputeAndGraphics = vk::TRUE;
+            limits.timestampPeriod = 1.0;
+            limits.maxClipDistances = 8;
+            limits.maxCullDistances = 8;
+            limits.maxCombinedClipAndCullDistances = 8;
+            limits.discreteQueuePriorities = 2;
+            limits.pointSizeRange = [1.0, 64.0];
+            limits.lineWidthRange = [1.0, 8.0];
+            limits.pointSizeGranularity = 1.0;
+            limits.lineWidthGranularity = 1.0;
+            limits.strictLines = vk::FALSE;
+            limits.standardSampleLocations = vk::TRUE;
+            limits.optimalBufferCopyOffsetAlignment = 1;
+            limits.optimalBufferCopyRowPitchAlignment = 1;
+            limits.nonCoherentAtomSize = 256;
+        }
+
+        *out = properties;
+    }
+}
+
+extern "system" fn get_physical_device_queue_family_properties(
+    physical_device: vk::PhysicalDevice, count: *mut u32,
+    out: *mut vk::QueueFamilyProperties) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceQueueFamilyProperties { physical_device });
+
+        let family = vk::QueueFamilyProperties {
+            queueFlags: vk::QUEUE_GRAPHICS_BIT | vk::QUEUE_COMPUTE_BIT |
+                vk::QUEUE_TRANSFER_BIT | vk::QUEUE_SPARSE_BINDING_BIT,
+            queueCount: 4,
+            timestampValidBits: 64,
+            minImageTransferGranularity: vk::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            },
+        };
+        let transfer_family = vk::QueueFamilyProperties {
+            queueFlags: vk::QUEUE_TRANSFER_BIT,
+            queueCount: 1,
+            timestampValidBits: 64,
+            minImageTransferGranularity: vk::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            },
+        };
+        write_array(vec![family, transfer_family], count, out);
+    }
+}
+
+extern "system" fn get_physical_device_memory_properties(
+    physical_device: vk::PhysicalDevice, out: *mut vk::PhysicalDeviceMemoryProperties) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceMemoryProperties { physical_device });
+
+        let mut properties: vk::PhysicalDeviceMemoryProperties = mem::zeroed();
+        properties.memoryTypeCount = 2;
+        properties.memoryTypes[0] = vk::MemoryType {
+            propertyFlags: vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+            heapIndex: 0,
+        };
+        properties.memoryTypes[1] = vk::MemoryType {
+            propertyFlags: vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT |
+                vk::MEMORY_PROPERTY_HOST_COHERENT_BIT,
+            heapIndex: 1,
+        };
+        properties.memoryHeapCount = 2;
+        properties.memoryHeaps[0] = vk::MemoryHeap {
+            size: HEAP_SIZE,
+            flags: vk::MEMORY_HEAP_DEVICE_LOCAL_BIT,
+        };
+        properties.memoryHeaps[1] = vk::MemoryHeap {
+            size: HEAP_SIZE,
+            flags: 0,
+        };
+        *out = properties;
+    }
+}
+
+extern "system" fn get_physical_device_sparse_image_format_properties(
+    physical_device: vk::PhysicalDevice, format: vk::Format, _: vk::ImageType,
+    _: vk::SampleCountFlagBits, _: vk::ImageUsageFlags, _: vk::ImageTiling, count: *mut u32,
+    out: *mut vk::SparseImageFormatProperties) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        physical_device_state(physical_device)
+            .log
+            .push(Call::GetPhysicalDeviceSparseImageFormatProperties { physical_device, format });
+        write_array(Vec::new(), count, out);
+    }
+}
+
+extern "system" fn create_device(physical_device: vk::PhysicalDevice,
+                                 infos: *const vk::DeviceCreateInfo,
+                                 _: *const vk::AllocationCallbacks, out: *mut vk::Device)
+                                 -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let infos = &*infos;
+        let log = physical_device_state(physical_device).log.clone();
+
+        let extensions = strings(infos.ppEnabledExtensionNames, infos.enabledExtensionCount);
+        if extensions
+            .iter()
+            .any(|ext| !DEVICE_EXTENSIONS.contains(&ext.as_bytes()))
+        {
+            return vk::ERROR_EXTENSION_NOT_PRESENT;
+        }
+
+        let queues = array(infos.pQueueCreateInfos, infos.queueCreateInfoCount)
+            .iter()
+            .map(|q| (q.queueFamilyIndex, q.queueCount))
+            .collect::<Vec<_>>();
+
+        let state = Box::into_raw(Box::new(DeviceState {
+                                               log: log.clone(),
+                                               queues: Vec::new(),
+                                               objects: Mutex::new(Default::default()),
+                                           }));
+        for &(family, count) in &queues {
+            for index in 0 .. count {
+                (*state).queues.push(Box::new(QueueState {
+                                                  device: state,
+                                                  family,
+                                                  index,
+                                              }));
+            }
+        }
+
+        let device = state as vk::Device;
+        log.push(Call::CreateDevice {
+                     physical_device,
+                     device,
+                     extensions,
+                     queues,
+                 });
+        *out = device;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn destroy_device(device: vk::Device, _: *const vk::AllocationCallbacks) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = Box::from_raw(device as *mut DeviceState);
+        state.log.push(Call::DestroyDevice { device });
+
+        // Command buffers that are still alive belong to the device.
+        let objects = state.objects.lock().unwrap();
+        for command_buffers in objects.command_pools.values() {
+            for &cb in command_buffers {
+                drop(Box::from_raw(cb as *mut CommandBufferState));
+            }
+        }
+    }
+}
+
+extern "system" fn get_device_queue(device: vk::Device, queue_family: u32, queue_index: u32,
+                                    out: *mut vk::Queue) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let queue = state
+            .queues
+            .iter()
+            .find(|q| q.family == queue_family && q.index == queue_index)
+            .map(|q| &**q as *const QueueState as vk::Queue)
+            .unwrap_or_else(|| fail("requested a queue that wasn't created with the device"));
+        state.log.push(Call::GetDeviceQueue {
+                           device,
+                           queue_family,
+                           queue_index,
+                           queue,
+                       });
+        *out = queue;
+    }
+}
+
+extern "system" fn queue_submit(queue: vk::Queue, count: u32, submits: *const vk::SubmitInfo,
+                                fence: vk::Fence)
+                                -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let device = &*queue_state(queue).device;
+
+        let batches = array(submits, count)
+            .iter()
+            .map(|submit| {
+                let wait = array(submit.pWaitSemaphores, submit.waitSemaphoreCount);
+                let stages = array(submit.pWaitDstStageMask, submit.waitSemaphoreCount);
+                SubmitBatch {
+                    wait_semaphores: wait.iter().cloned().zip(stages.iter().cloned()).collect(),
+                    command_buffers: array(submit.pCommandBuffers, submit.commandBufferCount)
+                        .to_owned(),
+                    signal_semaphores: array(submit.pSignalSemaphores,
+                                             submit.signalSemaphoreCount)
+                        .to_owned(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        {
+            let mut objects = device.objects.lock().unwrap();
+            for batch in &batches {
+                for &cb in &batch.command_buffers {
+                    objects.execute(cb);
+                }
+            }
+            if fence != 0 {
+                objects.fences.insert(fence, true);
+            }
+        }
+
+        device.log.push(Call::QueueSubmit {
+                            queue,
+                            batches,
+                            fence,
+                        });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn queue_wait_idle(queue: vk::Queue) -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        (*queue_state(queue).device)
+            .log
+            .push(Call::QueueWaitIdle { queue });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn queue_bind_sparse(queue: vk::Queue, count: u32,
+                                     infos: *const vk::BindSparseInfo, fence: vk::Fence)
+                                     -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let device = &*queue_state(queue).device;
+        let infos = array(infos, count);
+        let wait_semaphores = infos
+            .iter()
+            .flat_map(|i| array(i.pWaitSemaphores, i.waitSemaphoreCount).iter().cloned())
+            .collect();
+        let signal_semaphores = infos
+            .iter()
+            .flat_map(|i| array(i.pSignalSemaphores, i.signalSemaphoreCount).iter().cloned())
+            .collect();
+
+        if fence != 0 {
+            device.objects.lock().unwrap().fences.insert(fence, true);
+        }
+
+        device.log.push(Call::QueueBindSparse {
+                            queue,
+                            wait_semaphores,
+                            signal_semaphores,
+                            fence,
+                        });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn device_wait_idle(device: vk::Device) -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        device_state(device)
+            .log
+            .push(Call::DeviceWaitIdle { device });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn allocate_memory(device: vk::Device, infos: *const vk::MemoryAllocateInfo,
+                                   _: *const vk::AllocationCallbacks,
+                                   out: *mut vk::DeviceMemory)
+                                   -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let heap = infos.memoryTypeIndex as usize;
+        assert!(heap < 2, "mock driver: invalid memory type index");
+
+        let mut objects = state.objects.lock().unwrap();
+        if infos.allocationSize > HEAP_SIZE - objects.heap_usage[heap] {
+            return vk::ERROR_OUT_OF_DEVICE_MEMORY;
+        }
+        objects.heap_usage[heap] += infos.allocationSize;
+
+        let memory = new_handle();
+        objects.memory.insert(memory,
+                              MemoryState {
+                                  heap,
+                                  data: vec![0; infos.allocationSize as usize],
+                              });
+        state.log.push(Call::AllocateMemory {
+                           device,
+                           memory,
+                           size: infos.allocationSize,
+                           memory_type: infos.memoryTypeIndex,
+                       });
+        *out = memory;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn map_memory(device: vk::Device, memory: vk::DeviceMemory, offset: u64,
+                              size: u64, _: vk::MemoryMapFlags, out: *mut *mut c_void)
+                              -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let mut objects = state.objects.lock().unwrap();
+        let memory_state = object_mut(&mut objects.memory, memory, "memory");
+        if memory_state.heap != 1 {
+            return vk::ERROR_MEMORY_MAP_FAILED;
+        }
+        *out = memory_state.data.as_mut_ptr().offset(offset as isize) as *mut c_void;
+        state.log.push(Call::MapMemory {
+                           device,
+                           memory,
+                           offset,
+                           size,
+                       });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn unmap_memory(device: vk::Device, memory: vk::DeviceMemory) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        device_state(device)
+            .log
+            .push(Call::UnmapMemory { device, memory });
+    }
+}
+
+unsafe fn ranges(count: u32, ranges: *const vk::MappedMemoryRange) -> Vec<(u64, u64, u64)> {
+    array(ranges, count)
+        .iter()
+        .map(|r| (r.memory, r.offset, r.size))
+        .collect()
+}
+
+extern "system" fn flush_mapped_memory_ranges(device: vk::Device, count: u32,
+                                              list: *const vk::MappedMemoryRange)
+                                              -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        device_state(device)
+            .log
+            .push(Call::FlushMappedMemoryRanges {
+                      device,
+                      ranges: ranges(count, list),
+                  });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn invalidate_mapped_memory_ranges(device: vk::Device, count: u32,
+                                                   list: *const vk::MappedMemoryRange)
+                                                   -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        device_state(device)
+            .log
+            .push(Call::InvalidateMappedMemoryRanges {
+                      device,
+                      ranges: ranges(count, list),
+                  });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_device_memory_commitment(device: vk::Device, memory: vk::DeviceMemory,
+                                                out: *mut u64) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        *out = object(&state.objects.lock().unwrap().memory, memory, "memory").data.len() as u64;
+        state
+            .log
+            .push(Call::GetDeviceMemoryCommitment { device, memory });
+    }
+}
+
+extern "system" fn bind_buffer_memory(device: vk::Device, buffer: vk::Buffer,
+                                      memory: vk::DeviceMemory, offset: u64)
+                                      -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        state
+            .objects
+            .lock()
+            .unwrap()
+            .buffers
+            .get_mut(&buffer)
+            .unwrap()
+            .binding = Some((memory, offset));
+        state.log.push(Call::BindBufferMemory {
+                           device,
+                           buffer,
+                           memory,
+                           offset,
+                       });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn bind_image_memory(device: vk::Device, image: vk::Image,
+                                     memory: vk::DeviceMemory, offset: u64)
+                                     -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        device_state(device)
+            .log
+            .push(Call::BindImageMemory {
+                      device,
+                      image,
+                      memory,
+                      offset,
+                  });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_buffer_memory_requirements(device: vk::Device, buffer: vk::Buffer,
+                                                  out: *mut vk::MemoryRequirements) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let size = object(&state.objects.lock().unwrap().buffers, buffer, "buffer").size;
+        *out = vk::MemoryRequirements {
+            size: (size + 255) & !255,
+            alignment: 256,
+            memoryTypeBits: 0b11,
+        };
+        state
+            .log
+            .push(Call::GetBufferMemoryRequirements { device, buffer });
+    }
+}
+
+extern "system" fn get_image_memory_requirements(device: vk::Device, image: vk::Image,
+                                                 out: *mut vk::MemoryRequirements) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let size = object(&state.objects.lock().unwrap().buffers, image, "image").size;
+        *out = vk::MemoryRequirements {
+            size: (size + 4095) & !4095,
+            alignment: 4096,
+            memoryTypeBits: 0b11,
+        };
+        state
+            .log
+            .push(Call::GetImageMemoryRequirements { device, image });
+    }
+}
+
+extern "system" fn get_image_sparse_memory_requirements(
+    device: vk::Device, image: vk::Image, count: *mut u32,
+    out: *mut vk::SparseImageMemoryRequirements) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        device_state(device)
+            .log
+            .push(Call::GetImageSparseMemoryRequirements { device, image });
+        write_array(Vec::new(), count, out);
+    }
+}
+
+extern "system" fn create_fence(device: vk::Device, infos: *const vk::FenceCreateInfo,
+                                _: *const vk::AllocationCallbacks, out: *mut vk::Fence)
+                                -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let signaled = ((*infos).flags & vk::FENCE_CREATE_SIGNALED_BIT) != 0;
+        let fence = new_handle();
+        state.objects.lock().unwrap().fences.insert(fence, signaled);
+        state.log.push(Call::CreateFence {
+                           device,
+                           fence,
+                           signaled,
+                       });
+        *out = fence;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn reset_fences(device: vk::Device, count: u32, fences: *const vk::Fence)
+                                -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let fences = array(fences, count).to_owned();
+        {
+            let mut objects = state.objects.lock().unwrap();
+            for fence in &fences {
+                objects.fences.insert(*fence, false);
+            }
+        }
+        state.log.push(Call::ResetFences { device, fences });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_fence_status(device: vk::Device, fence: vk::Fence) -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let signaled = *object(&state.objects.lock().unwrap().fences, fence, "fence");
+        state.log.push(Call::GetFenceStatus { device, fence });
+        if signaled {
+            vk::SUCCESS
+        } else {
+            vk::NOT_READY
+        }
+    }
+}
+
+extern "system" fn wait_for_fences(device: vk::Device, count: u32, fences: *const vk::Fence,
+                                   wait_all: vk::Bool32, timeout: u64)
+                                   -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let fences = array(fences, count).to_owned();
+        let wait_all = wait_all != 0;
+
+        // Since submissions are executed immediately, a fence that isn't signaled yet will
+        // never be signaled. We report a timeout instead of blocking forever.
+        let done = {
+            let objects = state.objects.lock().unwrap();
+            if wait_all {
+                fences.iter().all(|f| objects.fences[f])
+            } else {
+                fences.iter().any(|f| objects.fences[f])
+            }
+        };
+
+        state.log.push(Call::WaitForFences {
+                           device,
+                           fences,
+                           wait_all,
+                           timeout,
+                       });
+        if done { vk::SUCCESS } else { vk::TIMEOUT }
+    }
+}
+
+extern "system" fn create_semaphore(device: vk::Device, _: *const vk::SemaphoreCreateInfo,
+                                    _: *const vk::AllocationCallbacks,
+                                    out: *mut vk::Semaphore)
+                                    -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let semaphore = new_handle();
+        state.objects.lock().unwrap().others.insert(semaphore);
+        state
+            .log
+            .push(Call::CreateSemaphore { device, semaphore });
+        *out = semaphore;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_event(device: vk::Device, _: *const vk::EventCreateInfo,
+                                _: *const vk::AllocationCallbacks, out: *mut vk::Event)
+                                -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let event = new_handle();
+        state.objects.lock().unwrap().events.insert(event, false);
+        state.log.push(Call::CreateEvent { device, event });
+        *out = event;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_event_status(device: vk::Device, event: vk::Event) -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let set = *object(&state.objects.lock().unwrap().events, event, "event");
+        state.log.push(Call::GetEventStatus { device, event });
+        if set { vk::EVENT_SET } else { vk::EVENT_RESET }
+    }
+}
+
+extern "system" fn set_event(device: vk::Device, event: vk::Event) -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        state.objects.lock().unwrap().events.insert(event, true);
+        state.log.push(Call::SetEvent { device, event });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn reset_event(device: vk::Device, event: vk::Event) -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        state.objects.lock().unwrap().events.insert(event, false);
+        state.log.push(Call::ResetEvent { device, event });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_query_pool(device: vk::Device, infos: *const vk::QueryPoolCreateInfo,
+                                     _: *const vk::AllocationCallbacks,
+                                     out: *mut vk::QueryPool)
+                                     -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let query_pool = new_handle();
+        let values_per_query = if infos.queryType == vk::QUERY_TYPE_PIPELINE_STATISTICS {
+            infos.pipelineStatistics.count_ones() as usize
+        } else {
+            1
+        };
+        state.objects.lock().unwrap().query_pools.insert(query_pool,
+                                                         QueryPoolState {
+                                                             values_per_query,
+                                                             results: vec![None; infos.queryCount
+                                                                                     as usize],
+                                                         });
+        state.log.push(Call::CreateQueryPool {
+                           device,
+                           query_pool,
+                           ty: infos.queryType,
+                           query_count: infos.queryCount,
+                           pipeline_statistics: infos.pipelineStatistics,
+                       });
+        *out = query_pool;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_query_pool_results(device: vk::Device, query_pool: vk::QueryPool,
+                                          first_query: u32, query_count: u32, data_size: usize,
+                                          data: *mut c_void, stride: u64,
+                                          flags: vk::QueryResultFlags)
+                                          -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        state.log.push(Call::GetQueryPoolResults {
+                           device,
+                           query_pool,
+                           first_query,
+                           query_count,
+                           stride,
+                           flags,
+                       });
+
+        let objects = state.objects.lock().unwrap();
+        let pool = object(&objects.query_pools, query_pool, "query pool");
+        let data = slice::from_raw_parts_mut(data as *mut u8, data_size);
+        let wide = (flags & vk::QUERY_RESULT_64_BIT) != 0;
+        let mut all_available = true;
+
+        for query in 0 .. query_count {
+            let result = pool.results[(first_query + query) as usize];
+            all_available &= result.is_some();
+
+            let mut values = Vec::new();
+            if result.is_some() || (flags & vk::QUERY_RESULT_PARTIAL_BIT) != 0 {
+                for _ in 0 .. pool.values_per_query {
+                    values.push(Some(result.unwrap_or(0)));
+                }
+            } else {
+                // Values of queries that aren't available aren't written.
+                for _ in 0 .. pool.values_per_query {
+                    values.push(None);
+                }
+            }
+            if (flags & vk::QUERY_RESULT_WITH_AVAILABILITY_BIT) != 0 {
+                values.push(Some(if result.is_some() { 1 } else { 0 }));
+            }
+
+            let base = (query as u64 * stride) as usize;
+            for (n, value) in values.into_iter().enumerate() {
+                let value = match value {
+                    Some(v) => v,
+                    None => continue,
+                };
+                if wide {
+                    write_u64(&mut data[base + n * 8 .. base + n * 8 + 8], value);
+                } else {
+                    write_u32(&mut data[base + n * 4 .. base + n * 4 + 4], value as u32);
+                }
+            }
+        }
+
+        if all_available {
+            vk::SUCCESS
+        } else {
+            vk::NOT_READY
+        }
+    }
+}
+
+extern "system" fn create_buffer(device: vk::Device, infos: *const vk::BufferCreateInfo,
+                                 _: *const vk::AllocationCallbacks, out: *mut vk::Buffer)
+                                 -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let buffer = new_handle();
+        state.objects.lock().unwrap().buffers.insert(buffer,
+                                                     BufferState {
+                                                         size: infos.size,
+                                                         binding: None,
+                                                     });
+        state.log.push(Call::CreateBuffer {
+                           device,
+                           buffer,
+                           size: infos.size,
+                           usage: infos.usage,
+                           sharing_mode: infos.sharingMode,
+                           queue_families: array(infos.pQueueFamilyIndices,
+                                                 infos.queueFamilyIndexCount)
+                               .to_owned(),
+                       });
+        *out = buffer;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_buffer_view(device: vk::Device,
+                                      infos: *const vk::BufferViewCreateInfo,
+                                      _: *const vk::AllocationCallbacks,
+                                      out: *mut vk::BufferView)
+                                      -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let view = new_handle();
+        state.objects.lock().unwrap().others.insert(view);
+        state.log.push(Call::CreateBufferView {
+                           device,
+                           view,
+                           buffer: infos.buffer,
+                           format: infos.format,
+                           offset: infos.offset,
+                           range: infos.range,
+                       });
+        *out = view;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_image(device: vk::Device, infos: *const vk::ImageCreateInfo,
+                                _: *const vk::AllocationCallbacks, out: *mut vk::Image)
+                                -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let image = new_handle();
+
+        // We don't know the size of each format, so we assume the largest possible texel size.
+        let size = infos.extent.width as u64 * infos.extent.height as u64 *
+            infos.extent.depth as u64 * infos.arrayLayers as u64 *
+            infos.samples as u64 * 16 * if infos.mipLevels > 1 { 2 } else { 1 };
+        // Images are stored in the same map as buffers, since we only care about their size.
+        state.objects.lock().unwrap().buffers.insert(image,
+                                                     BufferState {
+                                                         size,
+                                                         binding: None,
+                                                     });
+
+        state.log.push(Call::CreateImage {
+                           device,
+                           image,
+                           format: infos.format,
+                           extent: [infos.extent.width, infos.extent.height, infos.extent.depth],
+                           mip_levels: infos.mipLevels,
+                           array_layers: infos.arrayLayers,
+                           samples: infos.samples,
+                           usage: infos.usage,
+                           sharing_mode: infos.sharingMode,
+                           queue_families: array(infos.pQueueFamilyIndices,
+                                                 infos.queueFamilyIndexCount)
+                               .to_owned(),
+                       });
+        *out = image;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_image_subresource_layout(device: vk::Device, image: vk::Image,
+                                                _: *const vk::ImageSubresource,
+                                                out: *mut vk::SubresourceLayout) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let size = object(&state.objects.lock().unwrap().buffers, image, "image").size;
+        *out = vk::SubresourceLayout {
+            offset: 0,
+            size,
+            rowPitch: 0,
+            arrayPitch: 0,
+            depthPitch: 0,
+        };
+        state
+            .log
+            .push(Call::GetImageSubresourceLayout { device, image });
+    }
+}
+
+extern "system" fn create_image_view(device: vk::Device, infos: *const vk::ImageViewCreateInfo,
+                                     _: *const vk::AllocationCallbacks,
+                                     out: *mut vk::ImageView)
+                                     -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let view = new_handle();
+        state.objects.lock().unwrap().others.insert(view);
+        state.log.push(Call::CreateImageView {
+                           device,
+                           view,
+                           image: (*infos).image,
+                           format: (*infos).format,
+                       });
+        *out = view;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_shader_module(device: vk::Device,
+                                        infos: *const vk::ShaderModuleCreateInfo,
+                                        _: *const vk::AllocationCallbacks,
+                                        out: *mut vk::ShaderModule)
+                                        -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let module = new_handle();
+        state.objects.lock().unwrap().others.insert(module);
+        state.log.push(Call::CreateShaderModule {
+                           device,
+                           module,
+                           code_size: (*infos).codeSize,
+                       });
+        *out = module;
+        vk::SUCCESS
+    }
+}
+
+// Header written at the start of the pipeline cache data. Its fields are stored with the least
+// significant byte first, as required by the specification.
+fn pipeline_cache_header() -> Vec<u8> {
+    let mut header = Vec::with_capacity(32);
+    for &word in &[32, vk::PIPELINE_CACHE_HEADER_VERSION_ONE, VENDOR_ID, DEVICE_ID] {
+        header.extend_from_slice(&[word as u8, (word >> 8) as u8, (word >> 16) as u8,
+                                   (word >> 24) as u8]);
+    }
+    header.extend_from_slice(&PIPELINE_CACHE_UUID);
+    header
+}
+
+extern "system" fn create_pipeline_cache(device: vk::Device,
+                                         infos: *const vk::PipelineCacheCreateInfo,
+                                         _: *const vk::AllocationCallbacks,
+                                         out: *mut vk::PipelineCache)
+                                         -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let initial_data = if infos.initialDataSize == 0 {
+            &[][..]
+        } else {
+            slice::from_raw_parts(infos.pInitialData as *const u8, infos.initialDataSize)
+        };
+
+        // Just like real drivers, data that was produced by another device is ignored.
+        let header = pipeline_cache_header();
+        let data = if initial_data.starts_with(&header) {
+            initial_data[header.len() ..].to_owned()
+        } else {
+            Vec::new()
+        };
+
+        let cache = new_handle();
+        state.objects.lock().unwrap().pipeline_caches.insert(cache, data);
+        state.log.push(Call::CreatePipelineCache {
+                           device,
+                           cache,
+                           initial_data_size: infos.initialDataSize,
+                       });
+        *out = cache;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_pipeline_cache_data(device: vk::Device, cache: vk::PipelineCache,
+                                           size: *mut usize, out: *mut c_void)
+                                           -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        state
+            .log
+            .push(Call::GetPipelineCacheData { device, cache });
+
+        let mut data = pipeline_cache_header();
+        data.extend_from_slice(object(&state.objects.lock().unwrap().pipeline_caches,
+                                      cache,
+                                      "pipeline cache"));
+
+        if out.is_null() {
+            *size = data.len();
+            return vk::SUCCESS;
+        }
+
+        let written = cmp::min(*size, data.len());
+        ptr::copy_nonoverlapping(data.as_ptr(), out as *mut u8, written);
+        *size = written;
+        if written < data.len() {
+            vk::INCOMPLETE
+        } else {
+            vk::SUCCESS
+        }
+    }
+}
+
+extern "system" fn merge_pipeline_caches(device: vk::Device, destination: vk::PipelineCache,
+                                         count: u32, sources: *const vk::PipelineCache)
+                                         -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let sources = array(sources, count).to_owned();
+        {
+            let mut objects = state.objects.lock().unwrap();
+            for source in &sources {
+                let data = objects.pipeline_caches[source].clone();
+                objects
+                    .pipeline_caches
+                    .get_mut(&destination)
+                    .unwrap()
+                    .extend_from_slice(&data);
+            }
+        }
+        state.log.push(Call::MergePipelineCaches {
+                           device,
+                           destination,
+                           sources,
+                       });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_graphics_pipelines(device: vk::Device, cache: vk::PipelineCache,
+                                             count: u32,
+                                             infos: *const vk::GraphicsPipelineCreateInfo,
+                                             _: *const vk::AllocationCallbacks,
+                                             out: *mut vk::Pipeline)
+                                             -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = array(infos, count);
+        let pipelines = infos.iter().map(|_| new_handle()).collect::<Vec<_>>();
+        {
+            let mut objects = state.objects.lock().unwrap();
+            for (n, &pipeline) in pipelines.iter().enumerate() {
+                objects.others.insert(pipeline);
+                *out.offset(n as isize) = pipeline;
+            }
+        }
+        state.log.push(Call::CreateGraphicsPipelines {
+                           device,
+                           cache,
+                           pipelines,
+                           flags: infos.iter().map(|i| i.flags).collect(),
+                           base_pipelines: infos
+                               .iter()
+                               .map(|i| (i.basePipelineHandle, i.basePipelineIndex))
+                               .collect(),
+                           multisample: infos.iter().map(|i| multisample_state(i)).collect(),
+                       });
+        vk::SUCCESS
+    }
+}
+
+// Returns the number of samples and the sample mask of a graphics pipeline.
+unsafe fn multisample_state(infos: &vk::GraphicsPipelineCreateInfo) -> (u32, Option<u64>) {
+    let state = &*infos.pMultisampleState;
+    if state.pSampleMask.is_null() {
+        return (state.rasterizationSamples, None);
+    }
+
+    // The mask has one word per 32 samples.
+    let words = array(state.pSampleMask, (state.rasterizationSamples + 31) / 32);
+    let mask = words
+        .iter()
+        .enumerate()
+        .fold(0, |mask, (n, &word)| mask | ((word as u64) << (n * 32)));
+    (state.rasterizationSamples, Some(mask))
+}
+
+extern "system" fn create_compute_pipelines(device: vk::Device, cache: vk::PipelineCache,
+                                            count: u32,
+                                            infos: *const vk::ComputePipelineCreateInfo,
+                                            _: *const vk::AllocationCallbacks,
+                                            out: *mut vk::Pipeline)
+                                            -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = array(infos, count);
+        let pipelines = infos.iter().map(|_| new_handle()).collect::<Vec<_>>();
+        {
+            let mut objects = state.objects.lock().unwrap();
+            for (n, &pipeline) in pipelines.iter().enumerate() {
+                objects.others.insert(pipeline);
+                *out.offset(n as isize) = pipeline;
+            }
+        }
+        state.log.push(Call::CreateComputePipelines {
+                           device,
+                           cache,
+                           pipelines,
+                           flags: infos.iter().map(|i| i.flags).collect(),
+                           base_pipelines: infos
+                               .iter()
+                               .map(|i| (i.basePipelineHandle, i.basePipelineIndex))
+                               .collect(),
+                       });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_pipeline_layout(device: vk::Device,
+                                          infos: *const vk::PipelineLayoutCreateInfo,
+                                          _: *const vk::AllocationCallbacks,
+                                          out: *mut vk::PipelineLayout)
+                                          -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let layout = new_handle();
+        state.objects.lock().unwrap().others.insert(layout);
+        state.log.push(Call::CreatePipelineLayout {
+                           device,
+                           layout,
+                           set_layouts: array((*infos).pSetLayouts, (*infos).setLayoutCount)
+                               .to_owned(),
+                       });
+        *out = layout;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_sampler(device: vk::Device, _: *const vk::SamplerCreateInfo,
+                                  _: *const vk::AllocationCallbacks, out: *mut vk::Sampler)
+                                  -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let sampler = new_handle();
+        state.objects.lock().unwrap().others.insert(sampler);
+        state.log.push(Call::CreateSampler { device, sampler });
+        *out = sampler;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_descriptor_set_layout(device: vk::Device,
+                                                infos: *const vk::DescriptorSetLayoutCreateInfo,
+                                                _: *const vk::AllocationCallbacks,
+                                                out: *mut vk::DescriptorSetLayout)
+                                                -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let layout = new_handle();
+        state.objects.lock().unwrap().others.insert(layout);
+        state.log.push(Call::CreateDescriptorSetLayout {
+                           device,
+                           layout,
+                           bindings: array((*infos).pBindings, (*infos).bindingCount)
+                               .iter()
+                               .map(|b| (b.binding, b.descriptorType, b.descriptorCount))
+                               .collect(),
+                       });
+        *out = layout;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_descriptor_pool(device: vk::Device,
+                                          infos: *const vk::DescriptorPoolCreateInfo,
+                                          _: *const vk::AllocationCallbacks,
+                                          out: *mut vk::DescriptorPool)
+                                          -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let pool = new_handle();
+        state.objects.lock().unwrap().descriptor_pools.insert(pool,
+                                                              DescriptorPoolState {
+                                                                  max_sets: (*infos).maxSets,
+                                                                  sets: FnvHashSet::default(),
+                                                              });
+        state.log.push(Call::CreateDescriptorPool {
+                           device,
+                           pool,
+                           max_sets: (*infos).maxSets,
+                           flags: (*infos).flags,
+                       });
+        *out = pool;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn reset_descriptor_pool(device: vk::Device, pool: vk::DescriptorPool,
+                                         _: vk::DescriptorPoolResetFlags)
+                                         -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        state
+            .objects
+            .lock()
+            .unwrap()
+            .descriptor_pools
+            .get_mut(&pool)
+            .unwrap()
+            .sets
+            .clear();
+        state.log.push(Call::ResetDescriptorPool { device, pool });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn allocate_descriptor_sets(device: vk::Device,
+                                            infos: *const vk::DescriptorSetAllocateInfo,
+                                            out: *mut vk::DescriptorSet)
+                                            -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let pool = infos.descriptorPool;
+        let layouts = array(infos.pSetLayouts, infos.descriptorSetCount).to_owned();
+
+        let sets = {
+            let mut objects = state.objects.lock().unwrap();
+            let pool_state = object_mut(&mut objects.descriptor_pools, pool, "descriptor pool");
+            if pool_state.sets.len() + layouts.len() > pool_state.max_sets as usize {
+                return vk::ERROR_OUT_OF_POOL_MEMORY_KHR;
+            }
+            let sets = layouts.iter().map(|_| new_handle()).collect::<Vec<_>>();
+            for (n, &set) in sets.iter().enumerate() {
+                pool_state.sets.insert(set);
+                *out.offset(n as isize) = set;
+            }
+            sets
+        };
+
+        state.log.push(Call::AllocateDescriptorSets {
+                           device,
+                           pool,
+                           layouts,
+                           sets,
+                       });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn free_descriptor_sets(device: vk::Device, pool: vk::DescriptorPool,
+                                        count: u32, sets: *const vk::DescriptorSet)
+                                        -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let sets = array(sets, count).to_owned();
+        {
+            let mut objects = state.objects.lock().unwrap();
+            let pool_state = object_mut(&mut objects.descriptor_pools, pool, "descriptor pool");
+            for set in &sets {
+                pool_state.sets.remove(set);
+            }
+        }
+        state
+            .log
+            .push(Call::FreeDescriptorSets { device, pool, sets });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn update_descriptor_sets(device: vk::Device, write_count: u32,
+                                          writes: *const vk::WriteDescriptorSet,
+                                          copy_count: u32,
+                                          copies: *const vk::CopyDescriptorSet) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let writes = array(writes, write_count)
+            .iter()
+            .map(|w| (w.dstSet, w.dstBinding, w.dstArrayElement, w.descriptorCount))
+            .collect();
+        let copies = array(copies, copy_count)
+            .iter()
+            .map(|c| {
+                     (c.srcSet,
+                      c.srcBinding,
+                      c.srcArrayElement,
+                      c.dstSet,
+                      c.dstBinding,
+                      c.dstArrayElement,
+                      c.descriptorCount)
+                 })
+            .collect();
+        device_state(device)
+            .log
+            .push(Call::UpdateDescriptorSets {
+                      device,
+                      writes,
+                      copies,
+                  });
+    }
+}
+
+extern "system" fn create_framebuffer(device: vk::Device,
+                                      infos: *const vk::FramebufferCreateInfo,
+                                      _: *const vk::AllocationCallbacks,
+                                      out: *mut vk::Framebuffer)
+                                      -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let framebuffer = new_handle();
+        state.objects.lock().unwrap().others.insert(framebuffer);
+        state.log.push(Call::CreateFramebuffer {
+                           device,
+                           framebuffer,
+                           render_pass: infos.renderPass,
+                           attachments: array(infos.pAttachments, infos.attachmentCount)
+                               .to_owned(),
+                           dimensions: [infos.width, infos.height, infos.layers],
+                       });
+        *out = framebuffer;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn create_render_pass(device: vk::Device,
+                                      infos: *const vk::RenderPassCreateInfo,
+                                      _: *const vk::AllocationCallbacks,
+                                      out: *mut vk::RenderPass)
+                                      -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let render_pass = new_handle();
+        state.objects.lock().unwrap().others.insert(render_pass);
+        state.log.push(Call::CreateRenderPass {
+                           device,
+                           render_pass,
+                           attachments: infos.attachmentCount,
+                           subpasses: infos.subpassCount,
+                           dependencies: infos.dependencyCount,
+                       });
+        *out = render_pass;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn get_render_area_granularity(device: vk::Device,
+                                               render_pass: vk::RenderPass,
+                                               out: *mut vk::Extent2D) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        *out = vk::Extent2D {
+            width: 1,
+            height: 1,
+        };
+        device_state(device)
+            .log
+            .push(Call::GetRenderAreaGranularity { device, render_pass });
+    }
+}
+
+extern "system" fn create_command_pool(device: vk::Device,
+                                       infos: *const vk::CommandPoolCreateInfo,
+                                       _: *const vk::AllocationCallbacks,
+                                       out: *mut vk::CommandPool)
+                                       -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let pool = new_handle();
+        state
+            .objects
+            .lock()
+            .unwrap()
+            .command_pools
+            .insert(pool, Vec::new());
+        state.log.push(Call::CreateCommandPool {
+                           device,
+                           pool,
+                           queue_family: (*infos).queueFamilyIndex,
+                           flags: (*infos).flags,
+                       });
+        *out = pool;
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn destroy_command_pool(device: vk::Device, pool: vk::CommandPool,
+                                        _: *const vk::AllocationCallbacks) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        if let Some(command_buffers) = state.objects.lock().unwrap().command_pools.remove(&pool) {
+            for cb in command_buffers {
+                drop(Box::from_raw(cb as *mut CommandBufferState));
+            }
+        }
+        state
+            .log
+            .push(Call::DestroyCommandPool { device, pool });
+    }
+}
+
+extern "system" fn reset_command_pool(device: vk::Device, pool: vk::CommandPool,
+                                      flags: vk::CommandPoolResetFlags)
+                                      -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        for &cb in object(&state.objects.lock().unwrap().command_pools, pool, "command pool") {
+            cb_state(cb).ops.lock().unwrap().clear();
+        }
+        state.log.push(Call::ResetCommandPool {
+                           device,
+                           pool,
+                           flags,
+                       });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn trim_command_pool(device: vk::Device, pool: vk::CommandPool,
+                                     _: vk::CommandPoolTrimFlagsKHR) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        device_state(device)
+            .log
+            .push(Call::TrimCommandPoolKHR { device, pool });
+    }
+}
+
+extern "system" fn allocate_command_buffers(device: vk::Device,
+                                            infos: *const vk::CommandBufferAllocateInfo,
+                                            out: *mut vk::CommandBuffer)
+                                            -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let infos = &*infos;
+        let command_buffers = (0 .. infos.commandBufferCount)
+            .map(|_| {
+                     Box::into_raw(Box::new(CommandBufferState {
+                                                device: state,
+                                                ops: Mutex::new(Vec::new()),
+                                            })) as vk::CommandBuffer
+                 })
+            .collect::<Vec<_>>();
+
+        state
+            .objects
+            .lock()
+            .unwrap()
+            .command_pools
+            .get_mut(&infos.commandPool)
+            .unwrap()
+            .extend(command_buffers.iter().cloned());
+        for (n, &cb) in command_buffers.iter().enumerate() {
+            *out.offset(n as isize) = cb;
+        }
+
+        state.log.push(Call::AllocateCommandBuffers {
+                           device,
+                           pool: infos.commandPool,
+                           secondary: infos.level == vk::COMMAND_BUFFER_LEVEL_SECONDARY,
+                           command_buffers,
+                       });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn free_command_buffers(device: vk::Device, pool: vk::CommandPool, count: u32,
+                                        command_buffers: *const vk::CommandBuffer) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let state = device_state(device);
+        let command_buffers = array(command_buffers, count).to_owned();
+        {
+            let mut objects = state.objects.lock().unwrap();
+            let list = object_mut(&mut objects.command_pools, pool, "command pool");
+            for &cb in &command_buffers {
+                if let Some(pos) = list.iter().position(|&c| c == cb) {
+                    list.remove(pos);
+                    drop(Box::from_raw(cb as *mut CommandBufferState));
+                }
+            }
+        }
+        state.log.push(Call::FreeCommandBuffers {
+                           device,
+                           pool,
+                           command_buffers,
+                       });
+    }
+}
+
+extern "system" fn begin_command_buffer(command_buffer: vk::CommandBuffer,
+                                        infos: *const vk::CommandBufferBeginInfo)
+                                        -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_state(command_buffer).ops.lock().unwrap().clear();
+        cb_log(command_buffer,
+               Call::BeginCommandBuffer {
+                   command_buffer,
+                   flags: (*infos).flags,
+               });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn end_command_buffer(command_buffer: vk::CommandBuffer) -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer, Call::EndCommandBuffer { command_buffer });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn reset_command_buffer(command_buffer: vk::CommandBuffer,
+                                        flags: vk::CommandBufferResetFlags)
+                                        -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_state(command_buffer).ops.lock().unwrap().clear();
+        cb_log(command_buffer,
+               Call::ResetCommandBuffer {
+                   command_buffer,
+                   flags,
+               });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn cmd_bind_pipeline(command_buffer: vk::CommandBuffer,
+                                     bind_point: vk::PipelineBindPoint, pipeline: vk::Pipeline) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdBindPipeline {
+                   command_buffer,
+                   bind_point,
+                   pipeline,
+               });
+    }
+}
+
+extern "system" fn cmd_set_viewport(command_buffer: vk::CommandBuffer, first_viewport: u32,
+                                    viewports: u32, _: *const vk::Viewport) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdSetViewport {
+                   command_buffer,
+                   first_viewport,
+                   viewports,
+               });
+    }
+}
+
+extern "system" fn cmd_set_scissor(command_buffer: vk::CommandBuffer, first_scissor: u32,
+                                   scissors: u32, _: *const vk::Rect2D) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdSetScissor {
+                   command_buffer,
+                   first_scissor,
+                   scissors,
+               });
+    }
+}
+
+extern "system" fn cmd_set_line_width(command_buffer: vk::CommandBuffer, line_width: f32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdSetLineWidth {
+                   command_buffer,
+                   line_width,
+               });
+    }
+}
+
+extern "system" fn cmd_set_depth_bias(command_buffer: vk::CommandBuffer, constant_factor: f32,
+                                      clamp: f32, slope_factor: f32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdSetDepthBias {
+                   command_buffer,
+                   constant_factor,
+                   clamp,
+                   slope_factor,
+               });
+    }
+}
+
+extern "system" fn cmd_set_blend_constants(command_buffer: vk::CommandBuffer,
+                                           constants: *const f32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let mut values = [0.0; 4];
+        values.copy_from_slice(slice::from_raw_parts(constants, 4));
+        cb_log(command_buffer,
+               Call::CmdSetBlendConstants {
+                   command_buffer,
+                   constants: values,
+               });
+    }
+}
+
+extern "system" fn cmd_set_depth_bounds(command_buffer: vk::CommandBuffer, min: f32, max: f32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdSetDepthBounds {
+                   command_buffer,
+                   min,
+                   max,
+               });
+    }
+}
+
+extern "system" fn cmd_set_stencil_compare_mask(command_buffer: vk::CommandBuffer,
+                                                face_mask: vk::StencilFaceFlags,
+                                                compare_mask: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdSetStencilCompareMask {
+                   command_buffer,
+                   face_mask,
+                   compare_mask,
+               });
+    }
+}
+
+extern "system" fn cmd_set_stencil_write_mask(command_buffer: vk::CommandBuffer,
+                                              face_mask: vk::StencilFaceFlags,
+                                              write_mask: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdSetStencilWriteMask {
+                   command_buffer,
+                   face_mask,
+                   write_mask,
+               });
+    }
+}
+
+extern "system" fn cmd_set_stencil_reference(command_buffer: vk::CommandBuffer,
+                                             face_mask: vk::StencilFaceFlags, reference: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdSetStencilReference {
+                   command_buffer,
+                   face_mask,
+                   reference,
+               });
+    }
+}
+
+extern "system" fn cmd_bind_descriptor_sets(command_buffer: vk::CommandBuffer,
+                                            bind_point: vk::PipelineBindPoint,
+                                            layout: vk::PipelineLayout, first_set: u32,
+                                            set_count: u32, sets: *const vk::DescriptorSet,
+                                            dynamic_offset_count: u32,
+                                            dynamic_offsets: *const u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdBindDescriptorSets {
+                   command_buffer,
+                   bind_point,
+                   layout,
+                   first_set,
+                   sets: array(sets, set_count).to_owned(),
+                   dynamic_offsets: array(dynamic_offsets, dynamic_offset_count).to_owned(),
+               });
+    }
+}
+
+extern "system" fn cmd_bind_index_buffer(command_buffer: vk::CommandBuffer, buffer: vk::Buffer,
+                                         offset: u64, index_type: vk::IndexType) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdBindIndexBuffer {
+                   command_buffer,
+                   buffer,
+                   offset,
+                   index_type,
+               });
+    }
+}
+
+extern "system" fn cmd_bind_vertex_buffers(command_buffer: vk::CommandBuffer,
+                                           first_binding: u32, count: u32,
+                                           buffers: *const vk::Buffer, offsets: *const u64) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let buffers = array(buffers, count)
+            .iter()
+            .cloned()
+            .zip(array(offsets, count).iter().cloned())
+            .collect();
+        cb_log(command_buffer,
+               Call::CmdBindVertexBuffers {
+                   command_buffer,
+                   first_binding,
+                   buffers,
+               });
+    }
+}
+
+extern "system" fn cmd_draw(command_buffer: vk::CommandBuffer, vertex_count: u32,
+                            instance_count: u32, first_vertex: u32, first_instance: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdDraw {
+                   command_buffer,
+                   vertex_count,
+                   instance_count,
+                   first_vertex,
+                   first_instance,
+               });
+    }
+}
+
+extern "system" fn cmd_draw_indexed(command_buffer: vk::CommandBuffer, index_count: u32,
+                                    instance_count: u32, first_index: u32, vertex_offset: i32,
+                                    first_instance: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdDrawIndexed {
+                   command_buffer,
+                   index_count,
+                   instance_count,
+                   first_index,
+                   vertex_offset,
+                   first_instance,
+               });
+    }
+}
+
+extern "system" fn cmd_draw_indirect(command_buffer: vk::CommandBuffer, buffer: vk::Buffer,
+                                     offset: u64, draw_count: u32, stride: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdDrawIndirect {
+                   command_buffer,
+                   buffer,
+                   offset,
+                   draw_count,
+                   stride,
+               });
+    }
+}
+
+extern "system" fn cmd_draw_indexed_indirect(command_buffer: vk::CommandBuffer,
+                                             buffer: vk::Buffer, offset: u64, draw_count: u32,
+                                             stride: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdDrawIndexedIndirect {
+                   command_buffer,
+                   buffer,
+                   offset,
+                   draw_count,
+                   stride,
+               });
+    }
+}
+
+extern "system" fn cmd_dispatch(command_buffer: vk::CommandBuffer, x: u32, y: u32, z: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdDispatch {
+                   command_buffer,
+                   dimensions: [x, y, z],
+               });
+    }
+}
+
+extern "system" fn cmd_dispatch_indirect(command_buffer: vk::CommandBuffer,
+                                         buffer: vk::Buffer, offset: u64) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdDispatchIndirect {
+                   command_buffer,
+                   buffer,
+                   offset,
+               });
+    }
+}
+
+extern "system" fn cmd_copy_buffer(command_buffer: vk::CommandBuffer, source: vk::Buffer,
+                                   destination: vk::Buffer, count: u32,
+                                   regions: *const vk::BufferCopy) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let regions = array(regions, count)
+            .iter()
+            .map(|r| (r.srcOffset, r.dstOffset, r.size))
+            .collect::<Vec<_>>();
+        cb_op(command_buffer,
+              Op::CopyBuffer(source, destination, regions.clone()));
+        cb_log(command_buffer,
+               Call::CmdCopyBuffer {
+                   command_buffer,
+                   source,
+                   destination,
+                   regions,
+               });
+    }
+}
+
+extern "system" fn cmd_copy_image(command_buffer: vk::CommandBuffer, source: vk::Image,
+                                  source_layout: vk::ImageLayout, destination: vk::Image,
+                                  destination_layout: vk::ImageLayout, count: u32,
+                                  regions: *const vk::ImageCopy) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let regions = array(regions, count)
+            .iter()
+            .map(|r| (subresource(&r.srcSubresource), subresource(&r.dstSubresource)))
+            .collect();
+        cb_log(command_buffer,
+               Call::CmdCopyImage {
+                   command_buffer,
+                   source,
+                   source_layout,
+                   destination,
+                   destination_layout,
+                   regions,
+               });
+    }
+}
+
+extern "system" fn cmd_blit_image(command_buffer: vk::CommandBuffer, source: vk::Image,
+                                  source_layout: vk::ImageLayout, destination: vk::Image,
+                                  destination_layout: vk::ImageLayout, count: u32,
+                                  regions: *const vk::ImageBlit, filter: vk::Filter) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let regions = array(regions, count)
+            .iter()
+            .map(|r| {
+                     BlitRegion {
+                         source: subresource(&r.srcSubresource),
+                         source_offsets: offsets(&r.srcOffsets),
+                         destination: subresource(&r.dstSubresource),
+                         destination_offsets: offsets(&r.dstOffsets),
+                     }
+                 })
+            .collect();
+        cb_log(command_buffer,
+               Call::CmdBlitImage {
+                   command_buffer,
+                   source,
+                   source_layout,
+                   destination,
+                   destination_layout,
+                   regions,
+                   filter,
+               });
+    }
+}
+
+extern "system" fn cmd_copy_buffer_to_image(command_buffer: vk::CommandBuffer,
+                                            source: vk::Buffer, destination: vk::Image,
+                                            destination_layout: vk::ImageLayout, count: u32,
+                                            regions: *const vk::BufferImageCopy) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let regions = array(regions, count)
+            .iter()
+            .map(|r| (r.bufferOffset, subresource(&r.imageSubresource)))
+            .collect();
+        cb_log(command_buffer,
+               Call::CmdCopyBufferToImage {
+                   command_buffer,
+                   source,
+                   destination,
+                   destination_layout,
+                   regions,
+               });
+    }
+}
+
+extern "system" fn cmd_copy_image_to_buffer(command_buffer: vk::CommandBuffer,
+                                            source: vk::Image, source_layout: vk::ImageLayout,
+                                            destination: vk::Buffer, count: u32,
+                                            regions: *const vk::BufferImageCopy) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let regions = array(regions, count)
+            .iter()
+            .map(|r| (r.bufferOffset, subresource(&r.imageSubresource)))
+            .collect();
+        cb_log(command_buffer,
+               Call::CmdCopyImageToBuffer {
+                   command_buffer,
+                   source,
+                   source_layout,
+                   destination,
+                   regions,
+               });
+    }
+}
+
+extern "system" fn cmd_update_buffer(command_buffer: vk::CommandBuffer,
+                                     destination: vk::Buffer, offset: u64, size: u64,
+                                     data: *const u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let data = slice::from_raw_parts(data as *const u8, size as usize).to_owned();
+        cb_op(command_buffer,
+              Op::UpdateBuffer(destination, offset, data.clone()));
+        cb_log(command_buffer,
+               Call::CmdUpdateBuffer {
+                   command_buffer,
+                   destination,
+                   offset,
+                   data,
+               });
+    }
+}
+
+extern "system" fn cmd_fill_buffer(command_buffer: vk::CommandBuffer, destination: vk::Buffer,
+                                   offset: u64, size: u64, data: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_op(command_buffer, Op::FillBuffer(destination, offset, size, data));
+        cb_log(command_buffer,
+               Call::CmdFillBuffer {
+                   command_buffer,
+                   destination,
+                   offset,
+                   size,
+                   data,
+               });
+    }
+}
+
+extern "system" fn cmd_clear_color_image(command_buffer: vk::CommandBuffer, image: vk::Image,
+                                         layout: vk::ImageLayout,
+                                         _: *const vk::ClearColorValue, count: u32,
+                                         ranges: *const vk::ImageSubresourceRange) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdClearColorImage {
+                   command_buffer,
+                   image,
+                   layout,
+                   ranges: array(ranges, count).iter().map(subresource_range).collect(),
+               });
+    }
+}
+
+extern "system" fn cmd_clear_depth_stencil_image(command_buffer: vk::CommandBuffer,
+                                                 image: vk::Image, layout: vk::ImageLayout,
+                                                 value: *const vk::ClearDepthStencilValue,
+                                                 count: u32,
+                                                 ranges: *const vk::ImageSubresourceRange) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdClearDepthStencilImage {
+                   command_buffer,
+                   image,
+                   layout,
+                   depth: (*value).depth,
+                   stencil: (*value).stencil,
+                   ranges: array(ranges, count).iter().map(subresource_range).collect(),
+               });
+    }
+}
+
+extern "system" fn cmd_clear_attachments(command_buffer: vk::CommandBuffer,
+                                         attachment_count: u32,
+                                         attachments: *const vk::ClearAttachment,
+                                         rect_count: u32, rects: *const vk::ClearRect) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let attachments = array(attachments, attachment_count)
+            .iter()
+            .map(|a| (a.aspectMask, a.colorAttachment))
+            .collect();
+        let rects = array(rects, rect_count)
+            .iter()
+            .map(|r| {
+                     ([r.rect.offset.x, r.rect.offset.y],
+                      [r.rect.extent.width, r.rect.extent.height],
+                      r.baseArrayLayer,
+                      r.layerCount)
+                 })
+            .collect();
+        cb_log(command_buffer,
+               Call::CmdClearAttachments {
+                   command_buffer,
+                   attachments,
+                   rects,
+               });
+    }
+}
+
+extern "system" fn cmd_resolve_image(command_buffer: vk::CommandBuffer, source: vk::Image,
+                                     source_layout: vk::ImageLayout, destination: vk::Image,
+                                     destination_layout: vk::ImageLayout, count: u32,
+                                     regions: *const vk::ImageResolve) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let regions = array(regions, count)
+            .iter()
+            .map(|r| (subresource(&r.srcSubresource), subresource(&r.dstSubresource)))
+            .collect();
+        cb_log(command_buffer,
+               Call::CmdResolveImage {
+                   command_buffer,
+                   source,
+                   source_layout,
+                   destination,
+                   destination_layout,
+                   regions,
+               });
+    }
+}
+
+extern "system" fn cmd_set_event(command_buffer: vk::CommandBuffer, event: vk::Event,
+                                 stages: vk::PipelineStageFlags) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_op(command_buffer, Op::SetEvent(event, true));
+        cb_log(command_buffer,
+               Call::CmdSetEvent {
+                   command_buffer,
+                   event,
+                   stages,
+               });
+    }
+}
+
+extern "system" fn cmd_reset_event(command_buffer: vk::CommandBuffer, event: vk::Event,
+                                   stages: vk::PipelineStageFlags) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_op(command_buffer, Op::SetEvent(event, false));
+        cb_log(command_buffer,
+               Call::CmdResetEvent {
+                   command_buffer,
+                   event,
+                   stages,
+               });
+    }
+}
+
+extern "system" fn cmd_wait_events(command_buffer: vk::CommandBuffer, event_count: u32,
+                                   events: *const vk::Event,
+                                   source_stages: vk::PipelineStageFlags,
+                                   destination_stages: vk::PipelineStageFlags, _: u32,
+                                   _: *const vk::MemoryBarrier, _: u32,
+                                   _: *const vk::BufferMemoryBarrier, _: u32,
+                                   _: *const vk::ImageMemoryBarrier) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdWaitEvents {
+                   command_buffer,
+                   events: array(events, event_count).to_owned(),
+                   source_stages,
+                   destination_stages,
+               });
+    }
+}
+
+extern "system" fn cmd_pipeline_barrier(command_buffer: vk::CommandBuffer,
+                                        source_stages: vk::PipelineStageFlags,
+                                        destination_stages: vk::PipelineStageFlags,
+                                        dependency_flags: vk::DependencyFlags,
+                                        memory_barriers: u32, _: *const vk::MemoryBarrier,
+                                        buffer_barrier_count: u32,
+                                        buffer_barriers: *const vk::BufferMemoryBarrier,
+                                        image_barrier_count: u32,
+                                        image_barriers: *const vk::ImageMemoryBarrier) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let buffer_barriers = array(buffer_barriers, buffer_barrier_count)
+            .iter()
+            .map(|b| {
+                BufferBarrier {
+                    buffer: b.buffer,
+                    offset: b.offset,
+                    size: b.size,
+                    source_access: b.srcAccessMask,
+                    destination_access: b.dstAccessMask,
+                    source_queue_family: b.srcQueueFamilyIndex,
+                    destination_queue_family: b.dstQueueFamilyIndex,
+                }
+            })
+            .collect();
+        let image_barriers = array(image_barriers, image_barrier_count)
+            .iter()
+            .map(|b| {
+                ImageBarrier {
+                    image: b.image,
+                    old_layout: b.oldLayout,
+                    new_layout: b.newLayout,
+                    source_access: b.srcAccessMask,
+                    destination_access: b.dstAccessMask,
+                    source_queue_family: b.srcQueueFamilyIndex,
+                    destination_queue_family: b.dstQueueFamilyIndex,
+                    subresource: subresource_range(&b.subresourceRange),
+                }
+            })
+            .collect();
+        cb_log(command_buffer,
+               Call::CmdPipelineBarrier {
+                   command_buffer,
+                   source_stages,
+                   destination_stages,
+                   by_region: (dependency_flags & vk::DEPENDENCY_BY_REGION_BIT) != 0,
+                   memory_barriers,
+                   buffer_barriers,
+                   image_barriers,
+               });
+    }
+}
+
+extern "system" fn cmd_begin_query(command_buffer: vk::CommandBuffer,
+                                   query_pool: vk::QueryPool, query: u32,
+                                   flags: vk::QueryControlFlags) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdBeginQuery {
+                   command_buffer,
+                   query_pool,
+                   query,
+                   flags,
+               });
+    }
+}
+
+extern "system" fn cmd_end_query(command_buffer: vk::CommandBuffer, query_pool: vk::QueryPool,
+                                 query: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_op(command_buffer, Op::EndQuery(query_pool, query));
+        cb_log(command_buffer,
+               Call::CmdEndQuery {
+                   command_buffer,
+                   query_pool,
+                   query,
+               });
+    }
+}
+
+extern "system" fn cmd_reset_query_pool(command_buffer: vk::CommandBuffer,
+                                        query_pool: vk::QueryPool, first_query: u32,
+                                        query_count: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_op(command_buffer,
+              Op::ResetQueries(query_pool, first_query, query_count));
+        cb_log(command_buffer,
+               Call::CmdResetQueryPool {
+                   command_buffer,
+                   query_pool,
+                   first_query,
+                   query_count,
+               });
+    }
+}
+
+extern "system" fn cmd_write_timestamp(command_buffer: vk::CommandBuffer,
+                                       stage: vk::PipelineStageFlagBits,
+                                       query_pool: vk::QueryPool, query: u32) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_op(command_buffer, Op::WriteTimestamp(query_pool, query));
+        cb_log(command_buffer,
+               Call::CmdWriteTimestamp {
+                   command_buffer,
+                   stage,
+                   query_pool,
+                   query,
+               });
+    }
+}
+
+extern "system" fn cmd_copy_query_pool_results(command_buffer: vk::CommandBuffer,
+                                               query_pool: vk::QueryPool, first_query: u32,
+                                               query_count: u32, destination: vk::Buffer,
+                                               offset: u64, stride: u64,
+                                               flags: vk::QueryResultFlags) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdCopyQueryPoolResults {
+                   command_buffer,
+                   query_pool,
+                   first_query,
+                   query_count,
+                   destination,
+                   offset,
+                   stride,
+                   flags,
+               });
+    }
+}
+
+extern "system" fn cmd_push_constants(command_buffer: vk::CommandBuffer,
+                                      layout: vk::PipelineLayout,
+                                      stages: vk::ShaderStageFlags, offset: u32, size: u32,
+                                      data: *const c_void) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdPushConstants {
+                   command_buffer,
+                   layout,
+                   stages,
+                   offset,
+                   data: array(data as *const u8, size).to_owned(),
+               });
+    }
+}
+
+extern "system" fn cmd_begin_render_pass(command_buffer: vk::CommandBuffer,
+                                         infos: *const vk::RenderPassBeginInfo,
+                                         contents: vk::SubpassContents) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let infos = &*infos;
+        let area = &infos.renderArea;
+        cb_log(command_buffer,
+               Call::CmdBeginRenderPass {
+                   command_buffer,
+                   render_pass: infos.renderPass,
+                   framebuffer: infos.framebuffer,
+                   render_area: ([area.offset.x, area.offset.y],
+                                 [area.extent.width, area.extent.height]),
+                   clear_values: infos.clearValueCount,
+                   secondary: contents == vk::SUBPASS_CONTENTS_SECONDARY_COMMAND_BUFFERS,
+               });
+    }
+}
+
+extern "system" fn cmd_next_subpass(command_buffer: vk::CommandBuffer,
+                                    contents: vk::SubpassContents) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer,
+               Call::CmdNextSubpass {
+                   command_buffer,
+                   secondary: contents == vk::SUBPASS_CONTENTS_SECONDARY_COMMAND_BUFFERS,
+               });
+    }
+}
+
+extern "system" fn cmd_end_render_pass(command_buffer: vk::CommandBuffer) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer, Call::CmdEndRenderPass { command_buffer });
+    }
+}
+
+extern "system" fn cmd_execute_commands(command_buffer: vk::CommandBuffer, count: u32,
+                                        command_buffers: *const vk::CommandBuffer) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let command_buffers = array(command_buffers, count).to_owned();
+        cb_op(command_buffer, Op::ExecuteCommands(command_buffers.clone()));
+        cb_log(command_buffer,
+               Call::CmdExecuteCommands {
+                   command_buffer,
+                   command_buffers,
+               });
+    }
+}
+
+extern "system" fn debug_marker_set_object_name(device: vk::Device,
+                                                infos: *const vk::DebugMarkerObjectNameInfoEXT)
+                                                -> vk::Result {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let infos = &*infos;
+        device_state(device)
+            .log
+            .push(Call::DebugMarkerSetObjectNameEXT {
+                      device,
+                      object_type: infos.objectType,
+                      object: infos.object,
+                      name: CStr::from_ptr(infos.name).to_string_lossy().into_owned(),
+                  });
+        vk::SUCCESS
+    }
+}
+
+extern "system" fn cmd_debug_marker_begin(command_buffer: vk::CommandBuffer,
+                                          infos: *const vk::DebugMarkerMarkerInfoEXT) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let infos = &*infos;
+        cb_log(command_buffer,
+               Call::CmdDebugMarkerBeginEXT {
+                   command_buffer,
+                   name: CStr::from_ptr(infos.pMarkerName)
+                       .to_string_lossy()
+                       .into_owned(),
+                   color: infos.color,
+               });
+    }
+}
+
+extern "system" fn cmd_debug_marker_end(command_buffer: vk::CommandBuffer) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        cb_log(command_buffer, Call::CmdDebugMarkerEndEXT { command_buffer });
+    }
+}
+
+extern "system" fn cmd_debug_marker_insert(command_buffer: vk::CommandBuffer,
+                                           infos: *const vk::DebugMarkerMarkerInfoEXT) {
+    let _abort = AbortOnUnwind;
+    unsafe {
+        let infos = &*infos;
+        cb_log(command_buffer,
+               Call::CmdDebugMarkerInsertEXT {
+                   command_buffer,
+                   name: CStr::from_ptr(infos.pMarkerName)
+                       .to_string_lossy()
+                       .into_owned(),
+                   color: infos.color,
+               });
+    }
+}
+
+// Defines the functions that destroy objects that are kept in `Objects`.
+macro_rules! destroy_functions {
+    ($($f:ident => $variant:ident { $field:ident: $ty:ty },)+) => (
+        $(
+            extern "system" fn $f(device: vk::Device, $field: $ty,
+                                  _: *const vk::AllocationCallbacks) {
+                let _abort = AbortOnUnwind;
+                unsafe {
+                    let state = device_state(device);
+                    state.objects.lock().unwrap().remove($field);
+                    state.log.push(Call::$variant { device, $field });
+                }
+            }
+        )+
+    )
+}
+
+destroy_functions! {
+    free_memory => FreeMemory { memory: vk::DeviceMemory },
+    destroy_fence => DestroyFence { fence: vk::Fence },
+    destroy_semaphore => DestroySemaphore { semaphore: vk::Semaphore },
+    destroy_event => DestroyEvent { event: vk::Event },
+    destroy_query_pool => DestroyQueryPool { query_pool: vk::QueryPool },
+    destroy_buffer => DestroyBuffer { buffer: vk::Buffer },
+    destroy_buffer_view => DestroyBufferView { view: vk::BufferView },
+    destroy_image => DestroyImage { image: vk::Image },
+    destroy_image_view => DestroyImageView { view: vk::ImageView },
+    destroy_shader_module => DestroyShaderModule { module: vk::ShaderModule },
+    destroy_pipeline_cache => DestroyPipelineCache { cache: vk::PipelineCache },
+    destroy_pipeline => DestroyPipeline { pipeline: vk::Pipeline },
+    destroy_pipeline_layout => DestroyPipelineLayout { layout: vk::PipelineLayout },
+    destroy_sampler => DestroySampler { sampler: vk::Sampler },
+    destroy_descriptor_set_layout => DestroyDescriptorSetLayout {
+        layout: vk::DescriptorSetLayout
+    },
+    destroy_descriptor_pool => DestroyDescriptorPool { pool: vk::DescriptorPool },
+    destroy_framebuffer => DestroyFramebuffer { framebuffer: vk::Framebuffer },
+    destroy_render_pass => DestroyRenderPass { render_pass: vk::RenderPass },
+}
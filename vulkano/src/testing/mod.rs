@@ -0,0 +1,305 @@
+// Copyright (c) 2016 The vulkano developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! In-process mock of a Vulkan implementation, for testing without a GPU.
+//!
+//! The `MockLoader` struct implements the `Loader` trait. Instead of loading a Vulkan
+//! implementation from the system, it answers `vkGetInstanceProcAddr` with functions that are
+//! implemented inside of vulkano. These functions create fake handles, keep track of the objects
+//! that are alive, and record every call that is made to them.
+//!
+//! The mock exposes a single physical device with two queue families. The first one supports
+//! graphics, compute, transfer and sparse binding operations, and the second one only supports
+//! transfer operations. It advertises a host-visible and a
+//! device-local memory type, and supports most formats and features.
+//!
+//! This module is only available when the `testing` feature of vulkano is enabled.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(feature = "testing")] {
+//! use vulkano::instance::Instance;
+//! use vulkano::instance::InstanceExtensions;
+//! use vulkano::testing::Call;
+//! use vulkano::testing::MockLoader;
+//!
+//! let loader = MockLoader::new();
+//! let instance = Instance::with_loader(loader.function_pointers(), None,
+//!                                      &InstanceExtensions::none(), None).unwrap();
+//!
+//! match loader.calls()[0] {
+//!     Call::CreateInstance { .. } => (),
+//!     _ => panic!()
+//! }
+//! # }
+//! ```
+//!
+//! # Execution
+//!
+//! Command buffers are not executed on a GPU. When a command buffer is submitted, the mock
+//! immediately performs the buffer transfer operations (`vkCmdCopyBuffer`, `vkCmdFillBuffer` and
+//! `vkCmdUpdateBuffer`), sets and resets events, makes queries available, and then signals the
+//! fence of the submission. Draws, dispatches and image operations are only recorded.
+//!
+//! # Surfaces
+//!
+//! The mock supports the `VK_KHR_surface` and `VK_EXT_swapchain_colorspace` instance extensions,
+//! but can't create surfaces. Use `Surface::from_raw_surface` with any handle instead. Only the
+//! first queue family can present, and the surface supports the `B8G8R8A8Unorm` and
+//! `B8G8R8A8Srgb` formats in the sRGB non-linear color space.
+//!
+//! # Errors
+//!
+//! Calling an entry point that isn't implemented by the mock, or passing a handle that the mock
+//! doesn't know about, prints a message to stderr and aborts the process. Panics can't unwind
+//! across the `extern "system"` entry points.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use instance::PhysicalDevice;
+use instance::loader::FunctionPointers;
+use instance::loader::Loader;
+
+pub use self::calls::BlitRegion;
+pub use self::calls::BufferBarrier;
+pub use self::calls::Call;
+pub use self::calls::ImageBarrier;
+pub use self::calls::ImageSubresource;
+pub use self::calls::SubmitBatch;
+
+mod calls;
+mod driver;
+
+/// Implementation of `Loader` that answers with an in-process mock of Vulkan.
+///
+/// Cloning a `MockLoader` returns an object that shares the same list of recorded calls. This
+/// makes it possible to keep a clone around after passing the loader to `Instance::with_loader`.
+#[derive(Clone)]
+pub struct MockLoader {
+    logs: Arc<Mutex<Vec<Arc<CallLog>>>>,
+}
+
+impl MockLoader {
+    /// Builds a new mock loader that hasn't recorded any call yet.
+    #[inline]
+    pub fn new() -> MockLoader {
+        MockLoader { logs: Arc::new(Mutex::new(Vec::new())) }
+    }
+
+    /// Builds a `FunctionPointers` from a clone of this loader, ready to be passed to
+    /// `Instance::with_loader`.
+    #[inline]
+    pub fn function_pointers(&self) -> FunctionPointers<Box<Loader + Send + Sync>> {
+        FunctionPointers::new(Box::new(self.clone()))
+    }
+
+    /// Returns the list of all the calls that were made to the instances created from this
+    /// loader, in the order in which they were made.
+    pub fn calls(&self) -> Vec<Call> {
+        let logs = self.logs.lock().unwrap();
+        logs.iter()
+            .flat_map(|log| log.calls.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// Same as `calls()`, but also clears the list of recorded calls.
+    ///
+    /// This is useful to check the calls produced by one operation at a time.
+    pub fn take_calls(&self) -> Vec<Call> {
+        let logs = self.logs.lock().unwrap();
+        let mut out = Vec::new();
+        for log in logs.iter() {
+            out.extend(log.calls.lock().unwrap().drain(..));
+        }
+        out
+    }
+
+    /// Returns the names of the Vulkan functions that were called, in order.
+    ///
+    /// This is a shortcut for calling `name()` on each element returned by `calls()`.
+    #[inline]
+    pub fn call_names(&self) -> Vec<&'static str> {
+        self.calls().iter().map(|c| c.name()).collect()
+    }
+
+    // Registers the log of an instance that was created through this loader.
+    fn adopt(&self, log: &Arc<CallLog>) {
+        let mut logs = self.logs.lock().unwrap();
+        if !logs.iter().any(|l| Arc::ptr_eq(l, log)) {
+            logs.push(log.clone());
+        }
+    }
+}
+
+unsafe impl Loader for MockLoader {
+    fn get_instance_proc_addr(&self, instance: usize, name: *const ::std::os::raw::c_char)
+                              -> extern "system" fn() -> () {
+        if instance != 0 {
+            // The mock `vkCreateInstance` doesn't know which loader it belongs to, so each
+            // instance has its own log. Vulkano loads the instance function pointers right after
+            // creating it, which is when we link the log of the instance to this loader.
+            unsafe {
+                self.adopt(driver::instance_log(instance));
+            }
+        }
+
+        driver::get_proc_addr(name)
+    }
+}
+
+/// Returns true if the physical device is the one exposed by the mock driver.
+///
+/// Tests that depend on shaders actually running can use this to skip themselves.
+#[inline]
+pub fn is_mock_device(physical: &PhysicalDevice) -> bool {
+    physical.pci_vendor_id() == driver::VENDOR_ID && physical.pci_device_id() == driver::DEVICE_ID
+}
+
+// List of calls recorded by an instance and all the objects created from it.
+struct CallLog {
+    calls: Mutex<Vec<Call>>,
+}
+
+impl CallLog {
+    #[inline]
+    fn new() -> Arc<CallLog> {
+        Arc::new(CallLog { calls: Mutex::new(Vec::new()) })
+    }
+
+    #[inline]
+    fn push(&self, call: Call) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::BufferUsage;
+    use buffer::CpuAccessibleBuffer;
+    use command_buffer::AutoCommandBufferBuilder;
+    use command_buffer::CommandBuffer;
+    use format::Format;
+    use instance::Instance;
+    use instance::InstanceExtensions;
+    use instance::PhysicalDevice;
+    use std::sync::Arc;
+    use swapchain::ColorSpace;
+    use swapchain::Surface;
+    use sync::GpuFuture;
+    use testing::Call;
+    use testing::MockLoader;
+
+    #[test]
+    fn records_instance_creation() {
+        let loader = MockLoader::new();
+        let instance = Instance::with_loader(loader.function_pointers(),
+                                             None,
+                                             &InstanceExtensions::none(),
+                                             None)
+            .unwrap();
+        assert_eq!(PhysicalDevice::enumerate(&instance).count(), 1);
+
+        let names = loader.call_names();
+        assert_eq!(names[0], "vkCreateInstance");
+        assert_eq!(names[1], "vkEnumeratePhysicalDevices");
+
+        drop(instance);
+        assert_eq!(loader.call_names().last(), Some(&"vkDestroyInstance"));
+    }
+
+    #[test]
+    fn surface_queries() {
+        let loader = MockLoader::new();
+        let extensions = InstanceExtensions {
+            khr_surface: true,
+            ..InstanceExtensions::none()
+        };
+        let instance = Instance::with_loader(loader.function_pointers(), None, &extensions, None)
+            .unwrap();
+        let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+        let surface = Arc::new(unsafe { Surface::from_raw_surface(instance.clone(), 0x5, ()) });
+
+        assert!(surface
+                    .is_supported(physical.queue_family_by_id(0).unwrap())
+                    .unwrap());
+        assert!(!surface
+                     .is_supported(physical.queue_family_by_id(1).unwrap())
+                     .unwrap());
+
+        let caps = surface.capabilities(physical).unwrap();
+        assert_eq!(caps.min_image_count, 2);
+        assert_eq!(caps.max_image_count, Some(8));
+        assert_eq!(caps.current_extent, None);
+        assert!(caps.present_modes.fifo);
+        assert!(caps.supported_formats
+                    .contains(&(Format::B8G8R8A8Srgb, ColorSpace::SrgbNonLinear)));
+
+        drop(surface);
+        assert_eq!(loader.call_names().last(), Some(&"vkDestroySurfaceKHR"));
+    }
+
+    #[test]
+    fn unknown_instance_extension() {
+        let loader = MockLoader::new();
+        let extensions = InstanceExtensions {
+            khr_display: true,
+            ..InstanceExtensions::none()
+        };
+        assert!(Instance::with_loader(loader.function_pointers(), None, &extensions, None)
+                    .is_err());
+    }
+
+    #[test]
+    fn copy_buffer_executed() {
+        let (loader, device, queue) = mock_dev_and_queue!();
+
+        let source = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                    BufferUsage::all(),
+                                                    (0 .. 16u32).map(|n| n))
+            .unwrap();
+        let destination = CpuAccessibleBuffer::from_iter(device.clone(),
+                                                         BufferUsage::all(),
+                                                         (0 .. 16u32).map(|_| 0))
+            .unwrap();
+
+        loader.take_calls();
+
+        let cb = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+            .unwrap()
+            .copy_buffer(source.clone(), destination.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+        cb.execute(queue.clone())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let calls = loader.calls();
+        let copy = calls
+            .iter()
+            .filter_map(|call| match *call {
+                            Call::CmdCopyBuffer { source, destination, ref regions, .. } => {
+                                Some((source, destination, regions.clone()))
+                            },
+                            _ => None,
+                        })
+            .next()
+            .unwrap();
+        assert_eq!(copy.2, vec![(0, 0, 64)]);
+        assert!(calls.iter().any(|call| call.name() == "vkQueueSubmit"));
+
+        let content = destination.read().unwrap();
+        assert_eq!(&*content, &(0 .. 16).collect::<Vec<u32>>()[..]);
+    }
+}
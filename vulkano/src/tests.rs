@@ -66,6 +66,45 @@ macro_rules! gfx_dev_and_queue {
     });
 }
 
+/// Creates a device of the mock driver and a queue of its first queue family.
+///
+/// Returns the `MockLoader`, the device and the queue. If queue family indices are passed, one
+/// queue is created for each of them and an iterator to the queues is returned instead. The
+/// indices can be preceded with the `DeviceExtensions` to enable, followed by a semicolon.
+macro_rules! mock_dev_and_queue {
+    () => ({
+        let (loader, device, mut queues) = mock_dev_and_queue!(0);
+        (loader, device, queues.next().unwrap())
+    });
+
+    ($extensions:expr; $($family:expr),+) => ({
+        use instance;
+        use device::Device;
+        use features::Features;
+        use testing::MockLoader;
+
+        let loader = MockLoader::new();
+        let instance = instance::Instance::with_loader(loader.function_pointers(),
+                                                       None,
+                                                       &instance::InstanceExtensions::none(),
+                                                       None)
+            .unwrap();
+        let physical = instance::PhysicalDevice::enumerate(&instance).next().unwrap();
+        let families = vec![$(
+            (physical.queue_families().nth($family).unwrap(), 1.0),
+        )+];
+        let (device, queues) = Device::new(physical, &Features::none(), &$extensions, families)
+            .unwrap();
+
+        (loader, device, queues)
+    });
+
+    ($($family:expr),+) => ({
+        use device::DeviceExtensions;
+        mock_dev_and_queue!(DeviceExtensions::none(); $($family),+)
+    });
+}
+
 macro_rules! assert_should_panic {
     ($msg:expr, $code:block) => ({
         let res = ::std::panic::catch_unwind(|| {